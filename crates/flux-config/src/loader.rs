@@ -2,7 +2,7 @@ use anyhow::{anyhow, Result};
 use config::{Config, File, FileFormat};
 use std::path::{Path, PathBuf};
 
-use crate::{GlobalConfig, TimeShiftMergedConfig};
+use crate::{GlobalConfig, RecordingConfig, TimeShiftMergedConfig};
 use flux_storage::{DiskType, PoolConfig};
 
 /// 配置加载器
@@ -141,6 +141,29 @@ impl ConfigLoader {
         Ok(Some(pools))
     }
 
+    /// 加载录像配置（recording.toml 的 `[recording]` 段）；文件不存在时返回默认配置
+    pub fn load_recording(&self) -> Result<RecordingConfig> {
+        #[derive(serde::Deserialize)]
+        struct RecordingFile {
+            recording: RecordingConfig,
+        }
+
+        let config_path = self.config_dir.join("recording.toml");
+
+        if !config_path.exists() {
+            return Ok(RecordingConfig::default());
+        }
+
+        let config = Config::builder()
+            .add_source(File::new(
+                config_path.to_str().ok_or_else(|| anyhow!("Invalid config path"))?,
+                FileFormat::Toml,
+            ))
+            .build()?;
+
+        Ok(config.try_deserialize::<RecordingFile>()?.recording)
+    }
+
     /// 验证配置
     pub fn validate(&self) -> Result<()> {
         let global = self.load_global()?;
@@ -210,6 +233,23 @@ retention_days = 14
         assert_eq!(config.storage.retention_days, 14);
     }
 
+    #[test]
+    fn test_load_recording_config() {
+        let temp_dir = tempdir().unwrap();
+        let loader = ConfigLoader::new(temp_dir.path());
+        assert_eq!(loader.load_recording().unwrap().retention_days, 7);
+
+        fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../config/recording.toml"),
+            temp_dir.path().join("recording.toml"),
+        )
+        .unwrap();
+        let config = loader.load_recording().unwrap();
+        assert!(matches!(config.segment.strategy, crate::recording::SegmentStrategy::Adaptive));
+        assert!(matches!(config.index.engine, crate::recording::IndexEngine::Sqlite));
+        assert_eq!(config.storage.longterm_path, Some(PathBuf::from("/mnt/hdd/recordings/longterm")));
+    }
+
     #[test]
     fn test_validate_config() {
         let temp_dir = tempdir().unwrap();
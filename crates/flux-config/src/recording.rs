@@ -46,7 +46,7 @@ pub struct RecordingSegmentConfig {
 }
 
 /// 分片策略
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SegmentStrategy {
     /// 固定时长
@@ -79,7 +79,7 @@ pub struct RecordingCompressionConfig {
 }
 
 /// 压缩算法
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum CompressionAlgorithm {
     /// 不压缩
@@ -161,7 +161,7 @@ pub struct RecordingIndexConfig {
 }
 
 /// 索引引擎
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum IndexEngine {
     /// JSON 文件
@@ -1,6 +1,7 @@
 pub mod bus;
 pub mod entity;
 pub mod error;
+pub mod runtime;
 pub mod service;
 pub mod traits;
 
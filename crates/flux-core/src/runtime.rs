@@ -0,0 +1,54 @@
+use std::future::Future;
+use tokio::runtime::{Builder, Handle, RuntimeFlavor};
+
+/// 在同步回调（脚本函数、插件 Host 函数等）中等待异步操作
+///
+/// 多线程运行时内使用 `block_in_place`；单线程运行时或无运行时时在独立线程中创建临时运行时。
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    if let Ok(handle) = Handle::try_current() {
+        if handle.runtime_flavor() == RuntimeFlavor::MultiThread {
+            return tokio::task::block_in_place(|| handle.block_on(future));
+        }
+    }
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("Failed to build runtime for blocking call")
+                    .block_on(future)
+            })
+            .join()
+            .expect("Blocking call panicked")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn answer() -> u32 {
+        tokio::task::yield_now().await;
+        42
+    }
+
+    #[test]
+    fn test_block_on_without_runtime() {
+        assert_eq!(block_on(answer()), 42);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_current_thread() {
+        assert_eq!(block_on(answer()), 42);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_block_on_multi_thread() {
+        assert_eq!(block_on(answer()), 42);
+    }
+}
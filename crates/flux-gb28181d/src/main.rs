@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     routing::{get, post},
@@ -16,7 +16,7 @@ use flux_video::{
     VideoError,
 };
 use flux_media_core::{
-    snapshot::SnapshotOrchestrator,
+    snapshot::{SnapshotMode, SnapshotOrchestrator, SnapshotQuery, SoftwareDecoder},
     storage::{filesystem::FileSystemStorage, MediaStorage, StorageConfig},
     types::StreamId,
 };
@@ -246,6 +246,7 @@ impl GbStreamProcessor {
             mode: flux_media_core::snapshot::SnapshotMode::Keyframe,
             width: None,
             height: None,
+            quality: None,
         };
         self.orchestrator.get_snapshot(req).await.ok().map(|_| String::new())
     }
@@ -319,7 +320,10 @@ async fn main() -> anyhow::Result<()> {
         segment_duration_secs: 60,
     };
     let storage = Arc::new(RwLock::new(FileSystemStorage::new(storage_config)?));
-    let orchestrator = Arc::new(SnapshotOrchestrator::new(PathBuf::from(&args.keyframe_dir)));
+    let orchestrator = Arc::new(
+        SnapshotOrchestrator::new(PathBuf::from(&args.keyframe_dir))
+            .with_decoder(Arc::new(SoftwareDecoder::default())),
+    );
 
     let rtp_receiver = Arc::new(
         RtpReceiver::new(RtpReceiverConfig {
@@ -552,14 +556,10 @@ async fn list_device_channels(
 async fn snapshot(
     State(state): State<AppState>,
     Path(stream_id): Path<String>,
+    Query(query): Query<SnapshotQuery>,
 ) -> std::result::Result<Response, StatusCode> {
     let media_stream_id = StreamId::from_string(stream_id.clone());
-    let req = flux_media_core::snapshot::SnapshotRequest {
-        stream_id: media_stream_id,
-        mode: flux_media_core::snapshot::SnapshotMode::Auto,
-        width: None,
-        height: None,
-    };
+    let req = query.into_request(media_stream_id, SnapshotMode::Auto);
 
     let snapshot = state
         .orchestrator
@@ -567,10 +567,11 @@ async fn snapshot(
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
+    let content_type = snapshot.content_type();
     let mut resp: Response = snapshot.data.into_response();
     resp.headers_mut().insert(
         axum::http::header::CONTENT_TYPE,
-        HeaderValue::from_static("application/octet-stream"),
+        HeaderValue::from_static(content_type),
     );
     Ok(resp)
 }
@@ -75,6 +75,7 @@ async fn main() -> anyhow::Result<()> {
         mode: SnapshotMode::Keyframe,
         width: None,
         height: None,
+        quality: None,
     };
 
     let snapshot = orchestrator.get_snapshot(req).await?;
@@ -90,6 +91,7 @@ async fn main() -> anyhow::Result<()> {
         mode: SnapshotMode::Auto,
         width: Some(640),
         height: Some(480),
+        quality: Some(85),
     };
 
     let snapshot = orchestrator.get_snapshot(req).await?;
@@ -1,3 +1,5 @@
+use crate::playback::{DashMpdGenerator, DashRepresentation};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -95,44 +97,32 @@ impl MasterPlaylistGenerator {
         m3u8
     }
 
-    /// 生成 DASH MPD
+    /// 生成 DASH MPD（各变体无分片时间线，仅用于兼容旧接口）
+    #[deprecated(note = "use `playback::DashMpdGenerator` with per-variant segment timelines")]
     pub fn generate_dash_mpd(&self, base_url: &str) -> String {
-        let mut mpd = String::new();
-        
-        mpd.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
-        mpd.push_str("<MPD xmlns=\"urn:mpeg:dash:schema:mpd:2011\" ");
-        mpd.push_str("type=\"dynamic\" ");
-        mpd.push_str("minimumUpdatePeriod=\"PT2S\" ");
-        mpd.push_str("minBufferTime=\"PT4S\">\n");
-        
-        mpd.push_str("  <Period>\n");
-        mpd.push_str("    <AdaptationSet mimeType=\"video/mp4\" ");
-        mpd.push_str("codecs=\"avc1.64001f\">\n");
-
-        for variant in &self.variants {
-            mpd.push_str(&format!(
-                "      <Representation id=\"{}\" bandwidth=\"{}\" width=\"{}\" height=\"{}\">\n",
-                variant.name,
-                variant.bitrate * 1000,
-                variant.resolution.0,
-                variant.resolution.1
-            ));
-            mpd.push_str(&format!(
-                "        <BaseURL>{}/{}/</BaseURL>\n",
-                base_url, variant.name
-            ));
-            mpd.push_str("        <SegmentTemplate media=\"segment_$Number$.m4s\" ");
-            mpd.push_str("initialization=\"init.mp4\" ");
-            mpd.push_str("timescale=\"1000\" ");
-            mpd.push_str("duration=\"4000\"/>\n");
-            mpd.push_str("      </Representation>\n");
-        }
-
-        mpd.push_str("    </AdaptationSet>\n");
-        mpd.push_str("  </Period>\n");
-        mpd.push_str("</MPD>\n");
-
-        mpd
+        let representations: Vec<DashRepresentation> = self
+            .variants
+            .iter()
+            .map(|variant| DashRepresentation {
+                id: variant.name.clone(),
+                content_type: "video",
+                codecs: "avc1.64001f".to_string(),
+                bandwidth: variant.bitrate as u64 * 1000,
+                width: Some(variant.resolution.0),
+                height: Some(variant.resolution.1),
+                sample_rate: None,
+                channels: None,
+                timescale: 1000,
+                start_number: 0,
+                timeline: Vec::new(),
+            })
+            .collect();
+
+        let now = Utc::now();
+        let mut generator = DashMpdGenerator::new(now, 4, 5);
+        generator.initialization_template = format!("{}/$RepresentationID$/init.mp4", base_url);
+        generator.media_template = format!("{}/$RepresentationID$/segment_$Number$.m4s", base_url);
+        generator.generate(&representations, now)
     }
 }
 
@@ -220,6 +210,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_dash_mpd_generation() {
         let config = MultibitrateConfig::default();
         let generator = MasterPlaylistGenerator::new(config.variants);
@@ -230,5 +221,6 @@ mod tests {
         assert!(mpd.contains("<MPD"));
         assert!(mpd.contains("Representation"));
         assert!(mpd.contains("bandwidth=\"500000\""));
+        assert!(mpd.contains("media=\"http://example.com/stream/$RepresentationID$/segment_$Number$.m4s\""));
     }
 }
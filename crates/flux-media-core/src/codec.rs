@@ -0,0 +1,807 @@
+//! 编解码辅助：解码器配置记录解析、SPS 尺寸解析、RFC 6381 codecs 字符串、
+//! 长度前缀 NALU → Annex B 转换与 AAC ADTS 封装
+
+use crate::error::{MediaError, Result};
+use bytes::Bytes;
+
+/// Annex B 起始码
+pub const ANNEXB_START_CODE: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
+
+/// HEVC NALU 类型
+const HEVC_NAL_VPS: u8 = 32;
+const HEVC_NAL_SPS: u8 = 33;
+const HEVC_NAL_PPS: u8 = 34;
+
+/// 按 HEVC NALU 头解析类型（要求 forbidden 位与 nuh_layer_id 为 0、TemporalId 为 0），
+/// 不符合时返回 None；用于在未知编码的 Annex B 数据中识别 H.265
+pub fn hevc_nal_type(nalu: &[u8]) -> Option<u8> {
+    (nalu.len() >= 2 && nalu[0] & 0x81 == 0 && nalu[1] == 0x01).then(|| (nalu[0] >> 1) & 0x3F)
+}
+
+/// 是否为 HEVC IRAP 帧（BLA / IDR / CRA，NALU 类型 16-21）
+pub fn is_hevc_irap(nalu: &[u8]) -> bool {
+    hevc_nal_type(nalu).is_some_and(|nal_type| (16..=21).contains(&nal_type))
+}
+
+/// Annex B 数据的首个 NALU 为 HEVC VPS/SPS/PPS 或 IRAP 时视为 H.265
+pub fn is_hevc_annexb(data: &[u8]) -> bool {
+    split_annexb(data)
+        .first()
+        .and_then(|nalu| hevc_nal_type(nalu))
+        .is_some_and(|nal_type| matches!(nal_type, 16..=21 | 32..=34))
+}
+
+/// 解码器参数集（来自 AVC/HEVC DecoderConfigurationRecord）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParameterSets {
+    /// NALU 长度字段字节数（通常为 4）
+    pub nal_length_size: usize,
+    pub vps: Vec<Bytes>,
+    pub sps: Vec<Bytes>,
+    pub pps: Vec<Bytes>,
+}
+
+impl ParameterSets {
+    /// 以 Annex B 格式输出 VPS/SPS/PPS（关键帧前插入）
+    pub fn to_annexb(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for nalu in self.vps.iter().chain(&self.sps).chain(&self.pps) {
+            out.extend_from_slice(&ANNEXB_START_CODE);
+            out.extend_from_slice(nalu);
+        }
+        out
+    }
+}
+
+/// 解析 AVCDecoderConfigurationRecord（ISO/IEC 14496-15 5.2.4.1）
+pub fn parse_avc_decoder_config(data: &[u8]) -> Result<ParameterSets> {
+    if data.len() < 7 {
+        return Err(MediaError::Decode("AVC decoder config too short".to_string()));
+    }
+
+    let mut sets = ParameterSets {
+        nal_length_size: (data[4] & 0x03) as usize + 1,
+        ..Default::default()
+    };
+
+    let mut reader = ByteReader::new(&data[5..]);
+    let num_sps = reader.read_u8()? & 0x1F;
+    for _ in 0..num_sps {
+        sets.sps.push(reader.read_u16_prefixed()?);
+    }
+    let num_pps = reader.read_u8()?;
+    for _ in 0..num_pps {
+        sets.pps.push(reader.read_u16_prefixed()?);
+    }
+
+    Ok(sets)
+}
+
+/// 解析 HEVCDecoderConfigurationRecord（ISO/IEC 14496-15 8.3.3.1）
+pub fn parse_hevc_decoder_config(data: &[u8]) -> Result<ParameterSets> {
+    if data.len() < 23 {
+        return Err(MediaError::Decode("HEVC decoder config too short".to_string()));
+    }
+
+    let mut sets = ParameterSets {
+        nal_length_size: (data[21] & 0x03) as usize + 1,
+        ..Default::default()
+    };
+
+    let mut reader = ByteReader::new(&data[22..]);
+    let num_arrays = reader.read_u8()?;
+    for _ in 0..num_arrays {
+        let nal_type = reader.read_u8()? & 0x3F;
+        let num_nalus = reader.read_u16()?;
+        for _ in 0..num_nalus {
+            let nalu = reader.read_u16_prefixed()?;
+            match nal_type {
+                HEVC_NAL_VPS => sets.vps.push(nalu),
+                HEVC_NAL_SPS => sets.sps.push(nalu),
+                HEVC_NAL_PPS => sets.pps.push(nalu),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(sets)
+}
+
+/// 将长度前缀（AVCC/HVCC）格式的 NALU 序列转换为 Annex B
+pub fn length_prefixed_to_annexb(data: &[u8], nal_length_size: usize) -> Result<Vec<u8>> {
+    if !(1..=4).contains(&nal_length_size) {
+        return Err(MediaError::Decode(format!(
+            "Invalid NALU length size: {}",
+            nal_length_size
+        )));
+    }
+
+    let mut out = Vec::with_capacity(data.len() + 16);
+    let mut offset = 0;
+    while offset + nal_length_size <= data.len() {
+        let mut len = 0usize;
+        for byte in &data[offset..offset + nal_length_size] {
+            len = (len << 8) | *byte as usize;
+        }
+        offset += nal_length_size;
+
+        if offset + len > data.len() {
+            return Err(MediaError::Decode("NALU length exceeds payload".to_string()));
+        }
+        out.extend_from_slice(&ANNEXB_START_CODE);
+        out.extend_from_slice(&data[offset..offset + len]);
+        offset += len;
+    }
+
+    Ok(out)
+}
+
+/// 按起始码（3 或 4 字节）拆分 Annex B 字节流，返回不含起始码的 NALU
+pub fn split_annexb(data: &[u8]) -> Vec<&[u8]> {
+    let mut nalus = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut i = 0;
+
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            if let Some(begin) = start {
+                let mut end = i;
+                // 4 字节起始码的前导 0 不属于上一个 NALU
+                if end > begin && data[end - 1] == 0 {
+                    end -= 1;
+                }
+                if end > begin {
+                    nalus.push(&data[begin..end]);
+                }
+            }
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    if let Some(begin) = start {
+        if begin < data.len() {
+            nalus.push(&data[begin..]);
+        }
+    }
+
+    nalus
+}
+
+/// 将 Annex B 访问单元转换为 4 字节长度前缀格式（FLV / MP4 样本）
+pub fn annexb_to_length_prefixed(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 16);
+    for nalu in split_annexb(data) {
+        out.extend_from_slice(&(nalu.len() as u32).to_be_bytes());
+        out.extend_from_slice(nalu);
+    }
+    out
+}
+
+/// 由 SPS/PPS（含 NALU 头）构造 AVCDecoderConfigurationRecord（NALU 长度 4 字节）
+pub fn build_avc_decoder_config(sps: &[u8], pps: &[u8]) -> Result<Vec<u8>> {
+    if sps.len() < 4 || pps.is_empty() {
+        return Err(MediaError::Decode("Invalid SPS/PPS".to_string()));
+    }
+
+    let mut config = Vec::with_capacity(11 + sps.len() + pps.len());
+    config.push(1); // configurationVersion
+    config.extend_from_slice(&sps[1..4]); // profile / compatibility / level
+    config.push(0xFF); // lengthSizeMinusOne = 3
+    config.push(0xE1); // numOfSequenceParameterSets = 1
+    config.extend_from_slice(&(sps.len() as u16).to_be_bytes());
+    config.extend_from_slice(sps);
+    config.push(1); // numOfPictureParameterSets
+    config.extend_from_slice(&(pps.len() as u16).to_be_bytes());
+    config.extend_from_slice(pps);
+    Ok(config)
+}
+
+/// 由 VPS/SPS/PPS（含 NALU 头）构造 HEVCDecoderConfigurationRecord（NALU 长度 4 字节）
+///
+/// profile_tier_level 取自 SPS，其余字段使用 4:2:0 / 8 bit 默认值。
+pub fn build_hevc_decoder_config(vps: &[u8], sps: &[u8], pps: &[u8]) -> Result<Vec<u8>> {
+    let rbsp = remove_emulation_prevention(sps.get(2..).unwrap_or_default());
+    if vps.len() < 2 || pps.len() < 2 || rbsp.len() < 13 {
+        return Err(MediaError::Decode("Invalid VPS/SPS/PPS".to_string()));
+    }
+    let temporal_layers = ((rbsp[0] >> 1) & 0x07) + 1;
+    let temporal_id_nested = rbsp[0] & 0x01;
+
+    let mut config = Vec::with_capacity(23 + 15 + vps.len() + sps.len() + pps.len());
+    config.push(1); // configurationVersion
+    config.extend_from_slice(&rbsp[1..13]); // general profile / compatibility / constraints / level
+    config.extend_from_slice(&[0xF0, 0x00]); // min_spatial_segmentation_idc
+    config.push(0xFC); // parallelismType
+    config.push(0xFD); // chromaFormat = 4:2:0
+    config.push(0xF8); // bitDepthLumaMinus8
+    config.push(0xF8); // bitDepthChromaMinus8
+    config.extend_from_slice(&[0x00, 0x00]); // avgFrameRate
+    config.push((temporal_layers << 3) | (temporal_id_nested << 2) | 0x03); // lengthSizeMinusOne = 3
+    config.push(3); // numOfArrays
+    for (nal_type, nalu) in [(HEVC_NAL_VPS, vps), (HEVC_NAL_SPS, sps), (HEVC_NAL_PPS, pps)] {
+        config.push(0x80 | nal_type);
+        config.extend_from_slice(&1u16.to_be_bytes());
+        config.extend_from_slice(&(nalu.len() as u16).to_be_bytes());
+        config.extend_from_slice(nalu);
+    }
+    Ok(config)
+}
+
+/// 由 AVCDecoderConfigurationRecord 生成 RFC 6381 codecs 字符串（如 avc1.64001f）
+pub fn avc_codec_string(config: &[u8]) -> Result<String> {
+    if config.len() < 4 {
+        return Err(MediaError::Decode("AVC decoder config too short".to_string()));
+    }
+    Ok(format!("avc1.{:02x}{:02x}{:02x}", config[1], config[2], config[3]))
+}
+
+/// 由 HEVCDecoderConfigurationRecord 生成 codecs 字符串（ISO/IEC 14496-15 附录 E，如 hvc1.1.6.L93.B0）
+pub fn hevc_codec_string(config: &[u8]) -> Result<String> {
+    if config.len() < 13 {
+        return Err(MediaError::Decode("HEVC decoder config too short".to_string()));
+    }
+
+    let profile_space = match config[1] >> 6 {
+        0 => "",
+        1 => "A",
+        2 => "B",
+        _ => "C",
+    };
+    let tier = if config[1] & 0x20 != 0 { 'H' } else { 'L' };
+    let profile_idc = config[1] & 0x1F;
+    // 兼容标志按位逆序输出
+    let compatibility = u32::from_be_bytes([config[2], config[3], config[4], config[5]]).reverse_bits();
+
+    let mut codec = format!(
+        "hvc1.{}{}.{:X}.{}{}",
+        profile_space, profile_idc, compatibility, tier, config[12]
+    );
+    let constraints = &config[6..12];
+    let used = constraints.iter().rposition(|byte| *byte != 0).map_or(0, |i| i + 1);
+    for byte in &constraints[..used] {
+        codec.push_str(&format!(".{:X}", byte));
+    }
+    Ok(codec)
+}
+
+/// 解析 H.264 SPS（含 NALU 头）得到显示宽高
+pub fn parse_avc_sps_dimensions(sps: &[u8]) -> Result<(u32, u32)> {
+    let rbsp = remove_emulation_prevention(sps.get(1..).unwrap_or_default());
+    let mut reader = BitReader::new(&rbsp);
+
+    let profile_idc = reader.read_bits(8)?;
+    reader.skip_bits(16)?; // constraint flags + level_idc
+    reader.read_ue()?; // seq_parameter_set_id
+
+    let mut chroma_format_idc = 1;
+    if matches!(profile_idc, 100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135) {
+        chroma_format_idc = reader.read_ue()?;
+        if chroma_format_idc == 3 {
+            reader.skip_bits(1)?; // separate_colour_plane_flag
+        }
+        reader.read_ue()?; // bit_depth_luma_minus8
+        reader.read_ue()?; // bit_depth_chroma_minus8
+        reader.skip_bits(1)?; // qpprime_y_zero_transform_bypass_flag
+        if reader.read_bits(1)? == 1 {
+            let count = if chroma_format_idc == 3 { 12 } else { 8 };
+            for i in 0..count {
+                if reader.read_bits(1)? == 1 {
+                    skip_scaling_list(&mut reader, if i < 6 { 16 } else { 64 })?;
+                }
+            }
+        }
+    }
+
+    reader.read_ue()?; // log2_max_frame_num_minus4
+    match reader.read_ue()? {
+        0 => {
+            reader.read_ue()?; // log2_max_pic_order_cnt_lsb_minus4
+        }
+        1 => {
+            reader.skip_bits(1)?; // delta_pic_order_always_zero_flag
+            reader.read_se()?; // offset_for_non_ref_pic
+            reader.read_se()?; // offset_for_top_to_bottom_field
+            for _ in 0..reader.read_ue()? {
+                reader.read_se()?;
+            }
+        }
+        _ => {}
+    }
+    reader.read_ue()?; // max_num_ref_frames
+    reader.skip_bits(1)?; // gaps_in_frame_num_value_allowed_flag
+
+    let width_mbs = reader.read_ue()? + 1;
+    let height_map_units = reader.read_ue()? + 1;
+    let frame_mbs_only = reader.read_bits(1)?;
+    if frame_mbs_only == 0 {
+        reader.skip_bits(1)?; // mb_adaptive_frame_field_flag
+    }
+    reader.skip_bits(1)?; // direct_8x8_inference_flag
+
+    let mut width = width_mbs * 16;
+    let mut height = (2 - frame_mbs_only) * height_map_units * 16;
+    if reader.read_bits(1)? == 1 {
+        let (crop_x, crop_y) = match chroma_format_idc {
+            0 => (1, 2 - frame_mbs_only),
+            1 => (2, 2 * (2 - frame_mbs_only)),
+            2 => (2, 2 - frame_mbs_only),
+            _ => (1, 2 - frame_mbs_only),
+        };
+        let (left, right) = (reader.read_ue()?, reader.read_ue()?);
+        let (top, bottom) = (reader.read_ue()?, reader.read_ue()?);
+        width = width.saturating_sub(crop_x * (left + right));
+        height = height.saturating_sub(crop_y * (top + bottom));
+    }
+
+    Ok((width, height))
+}
+
+/// 解析 H.265 SPS（含 2 字节 NALU 头）得到显示宽高
+pub fn parse_hevc_sps_dimensions(sps: &[u8]) -> Result<(u32, u32)> {
+    let rbsp = remove_emulation_prevention(sps.get(2..).unwrap_or_default());
+    let mut reader = BitReader::new(&rbsp);
+
+    reader.skip_bits(4)?; // sps_video_parameter_set_id
+    let max_sub_layers_minus1 = reader.read_bits(3)?;
+    reader.skip_bits(1)?; // sps_temporal_id_nesting_flag
+
+    // profile_tier_level：general 部分 88 位 + general_level_idc 8 位
+    reader.skip_bits(96)?;
+    let mut sub_layer_flags = Vec::with_capacity(max_sub_layers_minus1 as usize);
+    for _ in 0..max_sub_layers_minus1 {
+        sub_layer_flags.push((reader.read_bits(1)?, reader.read_bits(1)?));
+    }
+    if max_sub_layers_minus1 > 0 {
+        reader.skip_bits(2 * (8 - max_sub_layers_minus1))?;
+    }
+    for (profile_present, level_present) in sub_layer_flags {
+        if profile_present == 1 {
+            reader.skip_bits(88)?;
+        }
+        if level_present == 1 {
+            reader.skip_bits(8)?;
+        }
+    }
+
+    reader.read_ue()?; // sps_seq_parameter_set_id
+    let chroma_format_idc = reader.read_ue()?;
+    if chroma_format_idc == 3 {
+        reader.skip_bits(1)?; // separate_colour_plane_flag
+    }
+    let mut width = reader.read_ue()?;
+    let mut height = reader.read_ue()?;
+
+    if reader.read_bits(1)? == 1 {
+        let (sub_width, sub_height) = match chroma_format_idc {
+            1 => (2, 2),
+            2 => (2, 1),
+            _ => (1, 1),
+        };
+        let (left, right) = (reader.read_ue()?, reader.read_ue()?);
+        let (top, bottom) = (reader.read_ue()?, reader.read_ue()?);
+        width = width.saturating_sub(sub_width * (left + right));
+        height = height.saturating_sub(sub_height * (top + bottom));
+    }
+
+    Ok((width, height))
+}
+
+fn skip_scaling_list(reader: &mut BitReader<'_>, size: usize) -> Result<()> {
+    let mut last_scale = 8i32;
+    let mut next_scale = 8i32;
+    for _ in 0..size {
+        if next_scale != 0 {
+            let delta = reader.read_se()?;
+            next_scale = (last_scale + delta + 256) % 256;
+        }
+        if next_scale != 0 {
+            last_scale = next_scale;
+        }
+    }
+    Ok(())
+}
+
+/// 去除 NALU 中的防竞争字节（00 00 03 → 00 00）
+fn remove_emulation_prevention(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+    for &byte in data {
+        if zeros >= 2 && byte == 0x03 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        out.push(byte);
+    }
+    out
+}
+
+/// AAC 采样率索引表（ISO/IEC 14496-3 1.6.3.4）
+const AAC_SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// AAC AudioSpecificConfig 中与 ADTS 相关的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AacConfig {
+    /// audioObjectType（2 = AAC-LC）
+    pub object_type: u8,
+    pub sampling_frequency_index: u8,
+    pub channel_configuration: u8,
+}
+
+impl AacConfig {
+    /// 解析 AudioSpecificConfig（RTMP AAC sequence header 的负载）
+    pub fn parse(asc: &[u8]) -> Result<Self> {
+        if asc.len() < 2 {
+            return Err(MediaError::Decode("AudioSpecificConfig too short".to_string()));
+        }
+
+        let object_type = asc[0] >> 3;
+        let sampling_frequency_index = ((asc[0] & 0x07) << 1) | (asc[1] >> 7);
+        let channel_configuration = (asc[1] >> 3) & 0x0F;
+
+        if object_type == 0 || object_type > 4 {
+            // ADTS 只能表达 Main/LC/SSR/LTP 四种 profile
+            return Err(MediaError::Decode(format!(
+                "AAC object type {} cannot be carried in ADTS",
+                object_type
+            )));
+        }
+        if sampling_frequency_index as usize >= AAC_SAMPLE_RATES.len() {
+            return Err(MediaError::Decode(format!(
+                "Unsupported AAC sampling frequency index {}",
+                sampling_frequency_index
+            )));
+        }
+
+        Ok(Self {
+            object_type,
+            sampling_frequency_index,
+            channel_configuration,
+        })
+    }
+
+    /// 解析 ADTS 头，返回配置、头长度与整帧长度
+    pub fn parse_adts(data: &[u8]) -> Result<(Self, usize, usize)> {
+        if data.len() < 7 || data[0] != 0xFF || data[1] & 0xF0 != 0xF0 {
+            return Err(MediaError::Decode("Invalid ADTS header".to_string()));
+        }
+        let header_len = if data[1] & 0x01 == 0 { 9 } else { 7 };
+        let frame_len =
+            (((data[3] & 0x03) as usize) << 11) | ((data[4] as usize) << 3) | ((data[5] >> 5) as usize);
+        if frame_len < header_len {
+            return Err(MediaError::Decode("Invalid ADTS frame length".to_string()));
+        }
+
+        let sampling_frequency_index = (data[2] >> 2) & 0x0F;
+        if sampling_frequency_index as usize >= AAC_SAMPLE_RATES.len() {
+            return Err(MediaError::Decode(format!(
+                "Unsupported AAC sampling frequency index {}",
+                sampling_frequency_index
+            )));
+        }
+        let config = Self {
+            object_type: (data[2] >> 6) + 1,
+            sampling_frequency_index,
+            channel_configuration: ((data[2] & 0x01) << 2) | (data[3] >> 6),
+        };
+        Ok((config, header_len, frame_len))
+    }
+
+    /// 由采样率与声道数构造 AAC-LC 配置（采样率不在索引表中时返回 None）
+    pub fn lc(sample_rate: u32, channels: u8) -> Option<Self> {
+        let index = AAC_SAMPLE_RATES.iter().position(|&rate| rate == sample_rate)?;
+        Some(Self {
+            object_type: 2,
+            sampling_frequency_index: index as u8,
+            channel_configuration: channels.min(7),
+        })
+    }
+
+    /// 序列化为 2 字节 AudioSpecificConfig（RTSP SDP 的 config 参数）
+    pub fn to_bytes(&self) -> [u8; 2] {
+        [
+            (self.object_type << 3) | (self.sampling_frequency_index >> 1),
+            ((self.sampling_frequency_index & 0x01) << 7) | (self.channel_configuration << 3),
+        ]
+    }
+
+    /// RFC 6381 codecs 字符串（如 mp4a.40.2）
+    pub fn codec_string(&self) -> String {
+        format!("mp4a.40.{}", self.object_type)
+    }
+
+    /// 声道数（channel_configuration 7 对应 7.1 声道）
+    pub fn channel_count(&self) -> u16 {
+        match self.channel_configuration {
+            7 => 8,
+            channels => channels as u16,
+        }
+    }
+
+    /// 采样率（Hz）
+    pub fn sample_rate(&self) -> u32 {
+        AAC_SAMPLE_RATES[self.sampling_frequency_index as usize]
+    }
+
+    /// 为一帧原始 AAC 数据生成 7 字节 ADTS 头（无 CRC）
+    pub fn adts_header(&self, payload_len: usize) -> [u8; 7] {
+        let frame_len = payload_len + 7;
+        let profile = self.object_type - 1;
+
+        [
+            0xFF,
+            0xF1, // MPEG-4, layer 0, no CRC
+            (profile << 6)
+                | (self.sampling_frequency_index << 2)
+                | ((self.channel_configuration >> 2) & 0x01),
+            ((self.channel_configuration & 0x03) << 6) | ((frame_len >> 11) as u8 & 0x03),
+            (frame_len >> 3) as u8,
+            (((frame_len & 0x07) as u8) << 5) | 0x1F,
+            0xFC, // buffer fullness 0x7FF, 1 raw data block
+        ]
+    }
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        let byte = *self
+            .data
+            .get(self.offset)
+            .ok_or_else(|| MediaError::Decode("Unexpected end of decoder config".to_string()))?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        Ok(((self.read_u8()? as u16) << 8) | self.read_u8()? as u16)
+    }
+
+    fn read_u16_prefixed(&mut self) -> Result<Bytes> {
+        let len = self.read_u16()? as usize;
+        if self.offset + len > self.data.len() {
+            return Err(MediaError::Decode("Parameter set exceeds decoder config".to_string()));
+        }
+        let bytes = Bytes::copy_from_slice(&self.data[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(bytes)
+    }
+}
+
+/// 按位读取器（支持 Exp-Golomb）
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn read_bits(&mut self, count: u32) -> Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..count {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or_else(|| MediaError::Decode("Unexpected end of SPS".to_string()))?;
+            let bit = (byte >> (7 - self.position % 8)) & 0x01;
+            value = (value << 1) | bit as u32;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn skip_bits(&mut self, count: u32) -> Result<()> {
+        if self.position + count as usize > self.data.len() * 8 {
+            return Err(MediaError::Decode("Unexpected end of SPS".to_string()));
+        }
+        self.position += count as usize;
+        Ok(())
+    }
+
+    fn read_ue(&mut self) -> Result<u32> {
+        let mut leading_zeros = 0;
+        while self.read_bits(1)? == 0 {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return Err(MediaError::Decode("Invalid Exp-Golomb code".to_string()));
+            }
+        }
+        Ok((1u32 << leading_zeros) - 1 + self.read_bits(leading_zeros)?)
+    }
+
+    fn read_se(&mut self) -> Result<i32> {
+        let value = self.read_ue()? as i64;
+        Ok(if value % 2 == 1 {
+            ((value + 1) / 2) as i32
+        } else {
+            -(value / 2) as i32
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_avc_decoder_config() {
+        let config = [
+            0x01, 0x64, 0x00, 0x1F, 0xFF, // version/profile/compat/level/length size
+            0xE1, 0x00, 0x03, 0x67, 0x64, 0x1F, // 1 SPS
+            0x01, 0x00, 0x02, 0x68, 0xEE, // 1 PPS
+        ];
+        let sets = parse_avc_decoder_config(&config).unwrap();
+        assert_eq!(sets.nal_length_size, 4);
+        assert_eq!(&sets.sps[0][..], &[0x67, 0x64, 0x1F]);
+        assert_eq!(&sets.pps[0][..], &[0x68, 0xEE]);
+        assert_eq!(
+            sets.to_annexb(),
+            vec![0, 0, 0, 1, 0x67, 0x64, 0x1F, 0, 0, 0, 1, 0x68, 0xEE]
+        );
+    }
+
+    #[test]
+    fn test_parse_hevc_decoder_config() {
+        let mut config = vec![0u8; 22];
+        config[0] = 0x01;
+        config[21] = 0x0F; // lengthSizeMinusOne = 3
+        config.push(3); // numOfArrays
+        for (nal_type, nalu) in [(32u8, [0x40, 0x01]), (33, [0x42, 0x01]), (34, [0x44, 0x01])] {
+            config.push(0x80 | nal_type);
+            config.extend_from_slice(&[0x00, 0x01, 0x00, 0x02]);
+            config.extend_from_slice(&nalu);
+        }
+
+        let sets = parse_hevc_decoder_config(&config).unwrap();
+        assert_eq!(sets.nal_length_size, 4);
+        assert_eq!(&sets.vps[0][..], &[0x40, 0x01]);
+        assert_eq!(&sets.sps[0][..], &[0x42, 0x01]);
+        assert_eq!(&sets.pps[0][..], &[0x44, 0x01]);
+    }
+
+    #[test]
+    fn test_aac_config_and_adts_header() {
+        // AAC-LC, 44.1kHz, stereo
+        let config = AacConfig::parse(&[0x12, 0x10]).unwrap();
+        assert_eq!(config.object_type, 2);
+        assert_eq!(config.sampling_frequency_index, 4);
+        assert_eq!(config.channel_configuration, 2);
+        assert_eq!(config.sample_rate(), 44100);
+        assert_eq!(AacConfig::lc(44100, 2), Some(config));
+        assert_eq!(config.to_bytes(), [0x12, 0x10]);
+        assert!(AacConfig::lc(44000, 2).is_none());
+
+        let header = config.adts_header(100);
+        assert_eq!(header, [0xFF, 0xF1, 0x50, 0x80, 0x0D, 0x7F, 0xFC]);
+
+        let mut frame = header.to_vec();
+        frame.extend_from_slice(&[0u8; 100]);
+        assert_eq!(AacConfig::parse_adts(&frame).unwrap(), (config, 7, 107));
+        assert!(AacConfig::parse_adts(&frame[1..]).is_err());
+
+        assert!(AacConfig::parse(&[0x12]).is_err());
+        // HE-AAC（object type 5）不能直接写入 ADTS
+        assert!(AacConfig::parse(&[0x2A, 0x10]).is_err());
+    }
+
+    #[test]
+    fn test_build_hevc_decoder_config() {
+        let vps = [0x40, 0x01, 0x0C];
+        let sps = [
+            0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xB0, 0x00, 0x00, 0x03, 0x00,
+            0x00, 0x03, 0x00, 0x5D, 0xA0, 0x03, 0xC0, 0x80, 0x11, 0x07, 0xCB, 0x96,
+        ];
+        let pps = [0x44, 0x01, 0xC1];
+        let config = build_hevc_decoder_config(&vps, &sps, &pps).unwrap();
+
+        assert_eq!(hevc_codec_string(&config).unwrap(), "hvc1.1.6.L93.B0");
+        let sets = parse_hevc_decoder_config(&config).unwrap();
+        assert_eq!(sets.nal_length_size, 4);
+        assert_eq!(&sets.vps[0][..], &vps);
+        assert_eq!(&sets.sps[0][..], &sps);
+        assert_eq!(&sets.pps[0][..], &pps);
+        assert!(build_hevc_decoder_config(&vps, &sps[..8], &pps).is_err());
+    }
+
+    #[test]
+    fn test_parse_avc_sps_dimensions() {
+        // High profile 1280x720
+        let sps = [0x67, 0x64, 0x00, 0x1F, 0xAC, 0xD9, 0x40, 0x50, 0x05, 0xB9];
+        assert_eq!(parse_avc_sps_dimensions(&sps).unwrap(), (1280, 720));
+
+        // 1920x1088 编码，底部裁剪 8 行
+        let sps = [0x67, 0x64, 0x00, 0x1F, 0xAC, 0xD9, 0x40, 0x78, 0x02, 0x27, 0xE5, 0x40];
+        assert_eq!(parse_avc_sps_dimensions(&sps).unwrap(), (1920, 1080));
+    }
+
+    #[test]
+    fn test_parse_hevc_sps_dimensions() {
+        // Main profile 1920x1088，底部裁剪 8 行，含防竞争字节
+        let sps = [
+            0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xB0, 0x00, 0x00, 0x03, 0x00,
+            0x00, 0x03, 0x00, 0x5D, 0xA0, 0x03, 0xC0, 0x80, 0x11, 0x07, 0xCB, 0x96,
+        ];
+        assert_eq!(parse_hevc_sps_dimensions(&sps).unwrap(), (1920, 1080));
+    }
+
+    #[test]
+    fn test_codec_strings() {
+        let avc = [0x01, 0x64, 0x00, 0x1F, 0xFF];
+        assert_eq!(avc_codec_string(&avc).unwrap(), "avc1.64001f");
+
+        // Main profile, Main tier, level 3.1
+        let mut hevc = vec![0u8; 23];
+        hevc[1] = 0x01;
+        hevc[2] = 0x60;
+        hevc[6] = 0xB0;
+        hevc[12] = 93;
+        assert_eq!(hevc_codec_string(&hevc).unwrap(), "hvc1.1.6.L93.B0");
+
+        let aac = AacConfig::parse(&[0x12, 0x10]).unwrap();
+        assert_eq!(aac.codec_string(), "mp4a.40.2");
+        assert_eq!(aac.channel_count(), 2);
+    }
+
+    #[test]
+    fn test_length_prefixed_to_annexb() {
+        let data = [0, 0, 0, 2, 0x65, 0x88, 0, 0, 0, 1, 0x06];
+        let annexb = length_prefixed_to_annexb(&data, 4).unwrap();
+        assert_eq!(annexb, vec![0, 0, 0, 1, 0x65, 0x88, 0, 0, 0, 1, 0x06]);
+
+        assert!(length_prefixed_to_annexb(&[0, 0, 0, 9, 0x65], 4).is_err());
+    }
+
+    #[test]
+    fn test_build_avc_decoder_config_roundtrip() {
+        let sps = [0x67, 0x42, 0xE0, 0x1F, 0xAB];
+        let pps = [0x68, 0xCE, 0x3C, 0x80];
+        let config = build_avc_decoder_config(&sps, &pps).unwrap();
+        assert_eq!(avc_codec_string(&config).unwrap(), "avc1.42e01f");
+
+        let sets = parse_avc_decoder_config(&config).unwrap();
+        assert_eq!(sets.nal_length_size, 4);
+        assert_eq!(sets.sps[0].as_ref(), &sps);
+        assert_eq!(sets.pps[0].as_ref(), &pps);
+
+        let access_unit = [0, 0, 0, 1, 0x65, 0x88, 0, 0, 1, 0x06, 0x01];
+        let prefixed = annexb_to_length_prefixed(&access_unit);
+        assert_eq!(prefixed, vec![0, 0, 0, 2, 0x65, 0x88, 0, 0, 0, 2, 0x06, 0x01]);
+        assert_eq!(length_prefixed_to_annexb(&prefixed, 4).unwrap(), vec![0, 0, 0, 1, 0x65, 0x88, 0, 0, 0, 1, 0x06, 0x01]);
+    }
+
+    #[test]
+    fn test_split_annexb() {
+        let data = [0, 0, 0, 1, 0x67, 0x42, 0, 0, 1, 0x68, 0xCE, 0, 0, 0, 1, 0x65, 0x88, 0x00];
+        let nalus = split_annexb(&data);
+        assert_eq!(nalus, vec![&[0x67, 0x42][..], &[0x68, 0xCE][..], &[0x65, 0x88, 0x00][..]]);
+
+        assert!(split_annexb(&[0x65, 0x88]).is_empty());
+    }
+
+    #[test]
+    fn test_hevc_nal_type() {
+        assert_eq!(hevc_nal_type(&[0x40, 0x01, 0x0C]), Some(HEVC_NAL_VPS));
+        assert!(is_hevc_irap(&[0x26, 0x01, 0xAF])); // IDR_W_RADL
+        assert!(is_hevc_irap(&[0x2A, 0x01, 0xAF])); // CRA
+        assert!(!is_hevc_irap(&[0x02, 0x01, 0xD0])); // TRAIL_R
+        // H.264 IDR / P 帧不被识别为 HEVC
+        assert_eq!(hevc_nal_type(&[0x65, 0x88, 0x84]), None);
+        assert_eq!(hevc_nal_type(&[0x21, 0x9A, 0x02]), None);
+    }
+}
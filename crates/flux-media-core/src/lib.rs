@@ -1,4 +1,5 @@
 pub mod abr;
+pub mod codec;
 pub mod error;
 pub mod playback;
 pub mod protocol;
@@ -0,0 +1,877 @@
+//! CMAF 打包：按关键帧切分 fMP4 分片，同时提供 MPEG-DASH MPD 与 fMP4 HLS 播放列表
+//!
+//! 音视频各自独立成轨（video/audio），文件命名：
+//! - `{track}_init.mp4`：init segment
+//! - `{track}_{sequence}.m4s`：media segment
+//! - `{track}_{sequence}.{part}.m4s`：LL-HLS 部分分片（moof + mdat）
+//! - `manifest.mpd`、`master.m3u8`、`{track}.m3u8`
+
+use super::dash::{DashMpdGenerator, DashRepresentation};
+use super::fmp4::{
+    segment_type_box, Fmp4Muxer, Fmp4Sample, Fmp4Track, Fmp4TrackKind, AAC_SAMPLES_PER_FRAME,
+};
+use super::hls::{LowLatencyPart, LowLatencyPlaylist, LowLatencySegment, RenditionReport};
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::VecDeque;
+
+/// 视频轨道 ID
+pub const CMAF_VIDEO_TRACK_ID: u32 = 1;
+/// 音频轨道 ID
+pub const CMAF_AUDIO_TRACK_ID: u32 = 2;
+
+/// 默认 LL-HLS 部分分片目标时长（毫秒）
+pub const DEFAULT_PART_TARGET_MS: u32 = 500;
+
+/// 音频时间戳与累计采样数偏差超过该值（毫秒）时重新对齐
+const AUDIO_RESYNC_THRESHOLD_MS: i64 = 100;
+
+/// 播放列表中保留部分分片的已完成分片数
+const SEGMENTS_WITH_PARTS: usize = 2;
+
+/// CMAF 轨道类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmafTrackType {
+    Video,
+    Audio,
+}
+
+impl CmafTrackType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CmafTrackType::Video => "video",
+            CmafTrackType::Audio => "audio",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "video" => Some(CmafTrackType::Video),
+            "audio" => Some(CmafTrackType::Audio),
+            _ => None,
+        }
+    }
+}
+
+/// 已完成的 fMP4 分片
+#[derive(Debug, Clone)]
+pub struct CmafSegment {
+    pub sequence: u64,
+    /// 首样本 DTS（轨道时间基）
+    pub decode_time: u64,
+    /// 时长（轨道时间基）
+    pub duration: u64,
+    pub data: Bytes,
+    /// 组成该分片的部分分片
+    pub parts: Vec<CmafPart>,
+}
+
+/// LL-HLS 部分分片
+#[derive(Debug, Clone)]
+pub struct CmafPart {
+    /// 分片内序号（从 0 开始）
+    pub index: u32,
+    /// 时长（轨道时间基）
+    pub duration: u64,
+    /// 是否以同步样本开头
+    pub independent: bool,
+    pub data: Bytes,
+}
+
+/// 阻塞式请求的分片/部分分片状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartStatus {
+    Available,
+    /// 尚未生成
+    Pending,
+    /// 请求的分片超出直播边缘两个以上分片
+    TooFarAhead,
+}
+
+/// 按文件名取出的 CMAF 资源
+#[derive(Debug, Clone)]
+pub struct CmafResource {
+    pub data: Bytes,
+    pub content_type: &'static str,
+}
+
+struct CmafTrack {
+    muxer: Fmp4Muxer,
+    init_segment: Bytes,
+    /// 当前部分分片的样本
+    samples: Vec<Fmp4Sample>,
+    /// 当前部分分片首样本 DTS
+    part_decode_time: Option<u64>,
+    /// 当前分片首样本 DTS
+    segment_decode_time: Option<u64>,
+    /// 当前分片已完成的部分分片
+    parts: Vec<CmafPart>,
+    /// 下一个音频样本 DTS（按采样数累加）
+    next_decode_time: Option<u64>,
+    segments: VecDeque<CmafSegment>,
+    next_sequence: u64,
+}
+
+impl CmafTrack {
+    fn new(track: Fmp4Track, next_sequence: u64) -> Self {
+        let muxer = Fmp4Muxer::new(track);
+        Self {
+            init_segment: muxer.init_segment(),
+            muxer,
+            samples: Vec::new(),
+            part_decode_time: None,
+            segment_decode_time: None,
+            parts: Vec::new(),
+            next_decode_time: None,
+            segments: VecDeque::new(),
+            next_sequence,
+        }
+    }
+
+    fn timescale(&self) -> u32 {
+        self.muxer.track().timescale
+    }
+
+    fn has_media(&self) -> bool {
+        !self.samples.is_empty() || !self.parts.is_empty()
+    }
+
+    /// 添加样本；超过部分分片目标时长时先结束当前部分分片，返回是否产生了新的部分分片
+    fn add_sample(&mut self, sample: Fmp4Sample, decode_time: u64, part_target: u64) -> bool {
+        let part_duration: u64 = self.samples.iter().map(|sample| sample.duration as u64).sum();
+        let completed = !self.samples.is_empty()
+            && part_duration + sample.duration as u64 > part_target
+            && self.flush_part();
+
+        if self.samples.is_empty() {
+            self.part_decode_time = Some(decode_time);
+        }
+        self.segment_decode_time.get_or_insert(decode_time);
+        self.samples.push(sample);
+        completed
+    }
+
+    /// 将当前样本封装为部分分片
+    fn flush_part(&mut self) -> bool {
+        let Some(decode_time) = self.part_decode_time.take() else {
+            return false;
+        };
+        if self.samples.is_empty() {
+            return false;
+        }
+
+        let duration = self.samples.iter().map(|sample| sample.duration as u64).sum();
+        let independent = self.samples[0].is_sync;
+        let data = self.muxer.fragment(decode_time, &self.samples);
+        self.samples.clear();
+
+        self.parts.push(CmafPart {
+            index: self.parts.len() as u32,
+            duration,
+            independent,
+            data,
+        });
+        true
+    }
+
+    /// 结束当前分片（由已完成的部分分片拼接而成），返回是否产生了新分片
+    fn flush(&mut self, window: usize) -> bool {
+        self.flush_part();
+        let Some(decode_time) = self.segment_decode_time.take() else {
+            return false;
+        };
+        if self.parts.is_empty() {
+            return false;
+        }
+
+        let parts = std::mem::take(&mut self.parts);
+        let duration = parts.iter().map(|part| part.duration).sum();
+        let mut data = BytesMut::from(&segment_type_box()[..]);
+        for part in &parts {
+            data.extend_from_slice(&part.data);
+        }
+
+        self.segments.push_back(CmafSegment {
+            sequence: self.next_sequence,
+            decode_time,
+            duration,
+            data: data.freeze(),
+            parts,
+        });
+        self.next_sequence += 1;
+
+        while self.segments.len() > window {
+            self.segments.pop_front();
+        }
+        true
+    }
+
+    /// 最新的部分分片：(分片序号, 部分分片序号)
+    fn latest_part(&self) -> Option<(u64, u32)> {
+        if let Some(part) = self.parts.last() {
+            return Some((self.next_sequence, part.index));
+        }
+        let segment = self.segments.back()?;
+        Some((segment.sequence, segment.parts.last()?.index))
+    }
+
+    /// 按分片大小估算峰值码率（bps）
+    fn bandwidth(&self) -> u64 {
+        let timescale = self.timescale() as u64;
+        self.segments
+            .iter()
+            .filter(|segment| segment.duration > 0)
+            .map(|segment| segment.data.len() as u64 * 8 * timescale / segment.duration)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn target_duration(&self) -> u64 {
+        let timescale = self.timescale() as u64;
+        self.segments
+            .iter()
+            .map(|segment| segment.duration.div_ceil(timescale))
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    }
+}
+
+/// CMAF 打包器（单路流）
+pub struct CmafPackager {
+    segment_duration: u32,
+    window: usize,
+    /// 部分分片目标时长（毫秒）
+    part_target_ms: u32,
+    video: Option<CmafTrack>,
+    audio: Option<CmafTrack>,
+    /// 等待下一帧以确定时长的视频样本：(DTS 毫秒, 样本)
+    pending_video: Option<(u32, Fmp4Sample)>,
+    /// 当前分片起始时间（毫秒）
+    segment_start_ms: Option<u32>,
+    /// 媒体时间 0 对应的墙钟时间
+    availability_start_time: Option<DateTime<Utc>>,
+}
+
+impl CmafPackager {
+    /// `segment_duration` 为目标分片时长（秒），`window` 为保留的分片数
+    pub fn new(segment_duration: u32, window: usize) -> Self {
+        Self {
+            segment_duration,
+            window,
+            part_target_ms: DEFAULT_PART_TARGET_MS,
+            video: None,
+            audio: None,
+            pending_video: None,
+            segment_start_ms: None,
+            availability_start_time: None,
+        }
+    }
+
+    /// 设置部分分片目标时长（毫秒）
+    pub fn with_part_target(mut self, part_target_ms: u32) -> Self {
+        self.part_target_ms = part_target_ms.max(1);
+        self
+    }
+
+    fn part_target_ticks(&self, timescale: u32) -> u64 {
+        self.part_target_ms as u64 * timescale as u64 / 1000
+    }
+
+    /// 设置视频轨道（序列头变化时重建，已有分片作废）
+    pub fn set_video_track(&mut self, track: Fmp4Track) {
+        if let Some(replaced) = Self::replace_track(&mut self.video, track) {
+            self.video = Some(replaced);
+            self.pending_video = None;
+        }
+    }
+
+    /// 设置音频轨道（序列头变化时重建，已有分片作废）
+    pub fn set_audio_track(&mut self, track: Fmp4Track) {
+        if let Some(replaced) = Self::replace_track(&mut self.audio, track) {
+            self.audio = Some(replaced);
+        }
+    }
+
+    /// 相同配置的重复序列头返回 None
+    fn replace_track(current: &mut Option<CmafTrack>, track: Fmp4Track) -> Option<CmafTrack> {
+        let next_sequence = current.as_ref().map_or(0, |current| current.next_sequence);
+        let replaced = CmafTrack::new(track, next_sequence);
+        match current {
+            Some(current) if current.init_segment == replaced.init_segment => None,
+            _ => Some(replaced),
+        }
+    }
+
+    /// 写入视频帧（长度前缀 NALU，时间戳为毫秒）；返回是否产生了新的部分分片或分片
+    pub fn push_video(&mut self, dts_ms: u32, composition_offset_ms: i32, data: Bytes, is_keyframe: bool) -> bool {
+        let Some(timescale) = self.video.as_ref().map(CmafTrack::timescale) else {
+            return false;
+        };
+        let part_target = self.part_target_ticks(timescale);
+        let timescale_per_ms = timescale / 1000;
+        let Some(video) = self.video.as_mut() else {
+            return false;
+        };
+        // 首个分片必须从关键帧开始
+        if video.segment_decode_time.is_none() && self.pending_video.is_none() && !is_keyframe {
+            return false;
+        }
+
+        let mut updated = false;
+        if let Some((prev_dts, mut sample)) = self.pending_video.take() {
+            sample.duration = dts_ms.saturating_sub(prev_dts) * timescale_per_ms;
+            updated |= video.add_sample(sample, prev_dts as u64 * timescale_per_ms as u64, part_target);
+        }
+
+        if is_keyframe && video.has_media() && self.segment_elapsed(dts_ms) >= self.segment_duration * 1000 {
+            updated |= self.cut();
+        }
+
+        self.mark_segment_start(dts_ms);
+        self.pending_video = Some((
+            dts_ms,
+            Fmp4Sample {
+                data,
+                duration: 0,
+                composition_offset: composition_offset_ms * timescale_per_ms as i32,
+                is_sync: is_keyframe,
+            },
+        ));
+        updated
+    }
+
+    /// 写入 AAC 原始帧（时间戳为毫秒）；返回是否产生了新的部分分片或分片
+    pub fn push_audio(&mut self, dts_ms: u32, data: Bytes) -> bool {
+        let Some(timescale) = self.audio.as_ref().map(CmafTrack::timescale) else {
+            return false;
+        };
+        let part_target = self.part_target_ticks(timescale);
+
+        // 纯音频流按音频时间切分片
+        let mut updated = false;
+        if self.video.is_none()
+            && self.audio.as_ref().is_some_and(CmafTrack::has_media)
+            && self.segment_elapsed(dts_ms) >= self.segment_duration * 1000
+        {
+            updated |= self.cut();
+        }
+
+        self.mark_segment_start(dts_ms);
+        let Some(audio) = self.audio.as_mut() else {
+            return updated;
+        };
+        let timescale = timescale as u64;
+        let anchored = dts_ms as u64 * timescale / 1000;
+        let decode_time = match audio.next_decode_time {
+            Some(next) if ((next * 1000 / timescale) as i64 - dts_ms as i64).abs() <= AUDIO_RESYNC_THRESHOLD_MS => next,
+            _ => anchored,
+        };
+
+        let sample = Fmp4Sample {
+            data,
+            duration: AAC_SAMPLES_PER_FRAME,
+            composition_offset: 0,
+            is_sync: true,
+        };
+        updated |= audio.add_sample(sample, decode_time, part_target);
+        audio.next_decode_time = Some(decode_time + AAC_SAMPLES_PER_FRAME as u64);
+        updated
+    }
+
+    fn mark_segment_start(&mut self, dts_ms: u32) {
+        if self.segment_start_ms.is_none() {
+            self.segment_start_ms = Some(dts_ms);
+        }
+        if self.availability_start_time.is_none() {
+            self.availability_start_time = Some(Utc::now() - Duration::milliseconds(dts_ms as i64));
+        }
+    }
+
+    fn segment_elapsed(&self, dts_ms: u32) -> u32 {
+        self.segment_start_ms
+            .map_or(0, |start| dts_ms.saturating_sub(start))
+    }
+
+    /// 切分片：视频与音频在同一时刻结束当前分片
+    fn cut(&mut self) -> bool {
+        let mut updated = false;
+        if let Some(video) = self.video.as_mut() {
+            updated |= video.flush(self.window);
+        }
+        if let Some(audio) = self.audio.as_mut() {
+            updated |= audio.flush(self.window);
+        }
+        self.segment_start_ms = None;
+        updated
+    }
+
+    fn track(&self, track_type: CmafTrackType) -> Option<&CmafTrack> {
+        match track_type {
+            CmafTrackType::Video => self.video.as_ref(),
+            CmafTrackType::Audio => self.audio.as_ref(),
+        }
+    }
+
+    /// 已有可播放分片的轨道
+    fn ready_tracks(&self) -> impl Iterator<Item = (CmafTrackType, &CmafTrack)> {
+        [CmafTrackType::Video, CmafTrackType::Audio]
+            .into_iter()
+            .filter_map(|track_type| self.track(track_type).map(|track| (track_type, track)))
+            .filter(|(_, track)| !track.segments.is_empty())
+    }
+
+    /// 获取 init segment
+    pub fn init_segment(&self, track_type: CmafTrackType) -> Option<Bytes> {
+        self.track(track_type).map(|track| track.init_segment.clone())
+    }
+
+    /// 获取 media segment
+    pub fn segment(&self, track_type: CmafTrackType, sequence: u64) -> Option<Bytes> {
+        self.track(track_type)?
+            .segments
+            .iter()
+            .find(|segment| segment.sequence == sequence)
+            .map(|segment| segment.data.clone())
+    }
+
+    /// 获取部分分片（当前分片或窗口内已完成分片）
+    pub fn part(&self, track_type: CmafTrackType, sequence: u64, index: u32) -> Option<Bytes> {
+        let track = self.track(track_type)?;
+        let parts = if sequence == track.next_sequence {
+            &track.parts
+        } else {
+            &track
+                .segments
+                .iter()
+                .find(|segment| segment.sequence == sequence)?
+                .parts
+        };
+        parts.get(index as usize).map(|part| part.data.clone())
+    }
+
+    /// 查询分片（`part` 为 None 时指整个分片）是否已生成，用于 LL-HLS 阻塞请求
+    pub fn part_status(&self, track_type: CmafTrackType, sequence: u64, part: Option<u32>) -> PartStatus {
+        let Some(track) = self.track(track_type) else {
+            return PartStatus::Pending;
+        };
+        let current = track.next_sequence;
+
+        let part_ready = sequence == current && part.is_some_and(|part| (part as usize) < track.parts.len());
+        if sequence > current + 1 {
+            PartStatus::TooFarAhead
+        } else if sequence < current || part_ready {
+            PartStatus::Available
+        } else {
+            PartStatus::Pending
+        }
+    }
+
+    /// 解析部分分片文件名 `{track}_{sequence}.{part}.m4s`
+    pub fn parse_part_name(name: &str) -> Option<(CmafTrackType, u64, u32)> {
+        let (track, rest) = name.split_once('_')?;
+        let (sequence, part) = rest.strip_suffix(".m4s")?.split_once('.')?;
+        Some((
+            CmafTrackType::parse(track)?,
+            sequence.parse().ok()?,
+            part.parse().ok()?,
+        ))
+    }
+
+    /// 获取指定轨道的分片列表
+    pub fn segments(&self, track_type: CmafTrackType) -> Vec<CmafSegment> {
+        self.track(track_type)
+            .map(|track| track.segments.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 生成 DASH MPD（尚无分片时返回 None）
+    pub fn dash_mpd(&self) -> Option<String> {
+        let availability_start_time = self.availability_start_time?;
+        let representations: Vec<DashRepresentation> = self
+            .ready_tracks()
+            .map(|(track_type, track)| {
+                let fmp4_track = track.muxer.track();
+                let mut representation = DashRepresentation {
+                    id: track_type.as_str().to_string(),
+                    content_type: track_type.as_str(),
+                    codecs: fmp4_track.codec_string.clone(),
+                    bandwidth: track.bandwidth(),
+                    width: None,
+                    height: None,
+                    sample_rate: None,
+                    channels: None,
+                    timescale: track.timescale(),
+                    start_number: track.segments.front().map_or(0, |segment| segment.sequence),
+                    timeline: track
+                        .segments
+                        .iter()
+                        .map(|segment| (segment.decode_time, segment.duration))
+                        .collect(),
+                };
+                match &fmp4_track.kind {
+                    Fmp4TrackKind::Video { width, height, .. } if *width > 0 => {
+                        representation.width = Some(*width);
+                        representation.height = Some(*height);
+                    }
+                    Fmp4TrackKind::Audio { config, .. } => {
+                        representation.sample_rate = Some(config.sample_rate());
+                        representation.channels = Some(config.channel_count());
+                    }
+                    _ => {}
+                }
+                representation
+            })
+            .collect();
+
+        if representations.is_empty() {
+            return None;
+        }
+
+        let generator = DashMpdGenerator::new(availability_start_time, self.segment_duration, self.window);
+        Some(generator.generate(&representations, Utc::now()))
+    }
+
+    /// 生成 fMP4 HLS 主播放列表（音频作为独立 rendition）
+    pub fn hls_master_playlist(&self) -> Option<String> {
+        let video = self.video.as_ref().filter(|track| !track.segments.is_empty());
+        let audio = self.audio.as_ref().filter(|track| !track.segments.is_empty());
+        if video.is_none() && audio.is_none() {
+            return None;
+        }
+
+        let mut m3u8 = String::new();
+        m3u8.push_str("#EXTM3U\n");
+        m3u8.push_str("#EXT-X-VERSION:7\n");
+        m3u8.push_str("#EXT-X-INDEPENDENT-SEGMENTS\n");
+
+        match (video, audio) {
+            (Some(video), audio) => {
+                let mut bandwidth = video.bandwidth();
+                let mut codecs = video.muxer.track().codec_string.clone();
+                let mut stream_inf = String::new();
+
+                if let Some(audio) = audio {
+                    m3u8.push_str("#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"audio\",NAME=\"default\",DEFAULT=YES,AUTOSELECT=YES,URI=\"audio.m3u8\"\n");
+                    bandwidth += audio.bandwidth();
+                    codecs.push(',');
+                    codecs.push_str(&audio.muxer.track().codec_string);
+                    stream_inf.push_str(",AUDIO=\"audio\"");
+                }
+                if let Fmp4TrackKind::Video { width, height, .. } = &video.muxer.track().kind {
+                    if *width > 0 {
+                        stream_inf.push_str(&format!(",RESOLUTION={}x{}", width, height));
+                    }
+                }
+
+                m3u8.push_str(&format!(
+                    "#EXT-X-STREAM-INF:BANDWIDTH={},CODECS=\"{}\"{}\n",
+                    bandwidth, codecs, stream_inf
+                ));
+                m3u8.push_str("video.m3u8\n");
+            }
+            (None, Some(audio)) => {
+                m3u8.push_str(&format!(
+                    "#EXT-X-STREAM-INF:BANDWIDTH={},CODECS=\"{}\"\n",
+                    audio.bandwidth(),
+                    audio.muxer.track().codec_string
+                ));
+                m3u8.push_str("audio.m3u8\n");
+            }
+            (None, None) => {}
+        }
+
+        Some(m3u8)
+    }
+
+    /// 生成 fMP4 HLS 媒体播放列表（LL-HLS：近期分片列出部分分片并附带预加载提示）
+    pub fn hls_media_playlist(&self, track_type: CmafTrackType) -> Option<String> {
+        let track = self.track(track_type)?;
+        let name = track_type.as_str();
+        let timescale = track.timescale() as f64;
+
+        let to_parts = |sequence: u64, parts: &[CmafPart]| -> Vec<LowLatencyPart> {
+            parts
+                .iter()
+                .map(|part| LowLatencyPart {
+                    uri: format!("{}_{}.{}.m4s", name, sequence, part.index),
+                    duration: part.duration as f64 / timescale,
+                    independent: part.independent,
+                })
+                .collect()
+        };
+
+        let first_with_parts = track.segments.len().saturating_sub(SEGMENTS_WITH_PARTS);
+        let mut segments: Vec<LowLatencySegment> = track
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| LowLatencySegment {
+                uri: Some(format!("{}_{}.m4s", name, segment.sequence)),
+                duration: segment.duration as f64 / timescale,
+                parts: if i >= first_with_parts {
+                    to_parts(segment.sequence, &segment.parts)
+                } else {
+                    Vec::new()
+                },
+            })
+            .collect();
+        if !track.parts.is_empty() {
+            segments.push(LowLatencySegment {
+                uri: None,
+                duration: track.parts.iter().map(|part| part.duration).sum::<u64>() as f64 / timescale,
+                parts: to_parts(track.next_sequence, &track.parts),
+            });
+        }
+
+        // 另一条 rendition 的最新进度
+        let other = match track_type {
+            CmafTrackType::Video => CmafTrackType::Audio,
+            CmafTrackType::Audio => CmafTrackType::Video,
+        };
+        let rendition_reports = self
+            .track(other)
+            .and_then(CmafTrack::latest_part)
+            .map(|(last_msn, last_part)| RenditionReport {
+                uri: format!("{}.m3u8", other.as_str()),
+                last_msn,
+                last_part,
+            })
+            .into_iter()
+            .collect();
+
+        let playlist = LowLatencyPlaylist {
+            target_duration: track.target_duration().max(self.segment_duration as u64),
+            part_target: self.part_target_ms as f64 / 1000.0,
+            media_sequence: track.segments.front().map_or(track.next_sequence, |segment| segment.sequence),
+            map_uri: format!("{}_init.mp4", name),
+            segments,
+            preload_hint: Some(format!("{}_{}.{}.m4s", name, track.next_sequence, track.parts.len())),
+            rendition_reports,
+        };
+        Some(playlist.render())
+    }
+
+    /// 按文件名获取资源（manifest.mpd、master.m3u8、{track}.m3u8、{track}_init.mp4、
+    /// {track}_{n}.m4s、{track}_{n}.{part}.m4s）
+    pub fn resource(&self, name: &str) -> Option<CmafResource> {
+        const MPEGURL: &str = "application/vnd.apple.mpegurl";
+
+        if name == "manifest.mpd" {
+            return self.dash_mpd().map(|mpd| CmafResource {
+                data: Bytes::from(mpd),
+                content_type: "application/dash+xml",
+            });
+        }
+        if name == "master.m3u8" {
+            return self.hls_master_playlist().map(|m3u8| CmafResource {
+                data: Bytes::from(m3u8),
+                content_type: MPEGURL,
+            });
+        }
+        if let Some(track) = name.strip_suffix(".m3u8").and_then(CmafTrackType::parse) {
+            return self.hls_media_playlist(track).map(|m3u8| CmafResource {
+                data: Bytes::from(m3u8),
+                content_type: MPEGURL,
+            });
+        }
+
+        let (track, rest) = name.split_once('_')?;
+        let track_type = CmafTrackType::parse(track)?;
+        let content_type = match track_type {
+            CmafTrackType::Video => "video/mp4",
+            CmafTrackType::Audio => "audio/mp4",
+        };
+
+        let data = if rest == "init.mp4" {
+            self.init_segment(track_type)?
+        } else if let Some((_, sequence, part)) = Self::parse_part_name(name) {
+            self.part(track_type, sequence, part)?
+        } else {
+            let sequence = rest.strip_suffix(".m4s")?.parse().ok()?;
+            self.segment(track_type, sequence)?
+        };
+        Some(CmafResource { data, content_type })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VideoCodec;
+
+    fn video_track() -> Fmp4Track {
+        let mut config = vec![0x01, 0x64, 0x00, 0x1F, 0xFF, 0xE1, 0x00, 0x0A];
+        config.extend_from_slice(&[0x67, 0x64, 0x00, 0x1F, 0xAC, 0xD9, 0x40, 0x50, 0x05, 0xB9]);
+        config.extend_from_slice(&[0x01, 0x00, 0x02, 0x68, 0xEE]);
+        Fmp4Track::video(CMAF_VIDEO_TRACK_ID, VideoCodec::H264, Bytes::from(config)).unwrap()
+    }
+
+    fn audio_track() -> Fmp4Track {
+        Fmp4Track::aac(CMAF_AUDIO_TRACK_ID, Bytes::from_static(&[0x12, 0x10])).unwrap()
+    }
+
+    fn frame() -> Bytes {
+        Bytes::from_static(&[0, 0, 0, 2, 0x65, 0x88])
+    }
+
+    #[test]
+    fn test_segment_cut_on_keyframe() {
+        let mut packager = CmafPackager::new(2, 5);
+        packager.set_video_track(video_track());
+        packager.set_audio_track(audio_track());
+
+        // 非关键帧开头被丢弃
+        packager.push_video(0, 0, frame(), false);
+        assert!(packager.pending_video.is_none());
+
+        // 25fps，每 2 秒一个关键帧，共 3 个 GOP
+        for i in 0..150u32 {
+            let ts = 1000 + i * 40;
+            packager.push_video(ts, 0, frame(), i % 50 == 0);
+            if i % 2 == 0 {
+                packager.push_audio(ts, Bytes::from_static(&[0x21, 0x00]));
+            }
+        }
+
+        let segments = packager.segments(CmafTrackType::Video);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].sequence, 0);
+        assert_eq!(segments[0].decode_time, 1000 * 90);
+        assert_eq!(segments[0].duration, 2000 * 90);
+        assert_eq!(segments[1].decode_time, 3000 * 90);
+
+        // 音频在同一时刻切分
+        let audio = packager.segments(CmafTrackType::Audio);
+        assert_eq!(audio.len(), 2);
+        assert_eq!(audio[0].decode_time, 44100);
+
+        // 重复序列头不重建轨道
+        packager.set_video_track(video_track());
+        assert_eq!(packager.segments(CmafTrackType::Video).len(), 2);
+    }
+
+    #[test]
+    fn test_manifests_and_resources() {
+        let mut packager = CmafPackager::new(1, 3);
+        assert!(packager.dash_mpd().is_none());
+
+        packager.set_video_track(video_track());
+        packager.set_audio_track(audio_track());
+        for i in 0..100u32 {
+            let ts = i * 40;
+            packager.push_video(ts, 40, frame(), i % 25 == 0);
+            packager.push_audio(ts, Bytes::from_static(&[0x21, 0x00]));
+        }
+
+        // 窗口内只保留 3 个分片
+        let segments = packager.segments(CmafTrackType::Video);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].sequence, 0);
+
+        let mpd = packager.dash_mpd().unwrap();
+        assert!(mpd.contains("<S t=\"0\" d=\"90000\" r=\"2\"/>"));
+        assert!(mpd.contains("codecs=\"avc1.64001f\""));
+        assert!(mpd.contains("codecs=\"mp4a.40.2\""));
+
+        let master = packager.hls_master_playlist().unwrap();
+        assert!(master.contains("CODECS=\"avc1.64001f,mp4a.40.2\""));
+        assert!(master.contains("RESOLUTION=1280x720"));
+        assert!(master.contains("AUDIO=\"audio\""));
+
+        let media = packager.hls_media_playlist(CmafTrackType::Video).unwrap();
+        assert!(media.contains("#EXT-X-MAP:URI=\"video_init.mp4\""));
+        assert!(media.contains("#EXTINF:1.000,\nvideo_0.m4s"));
+
+        let init = packager.resource("video_init.mp4").unwrap();
+        assert_eq!(init.content_type, "video/mp4");
+        assert_eq!(&init.data[4..8], b"ftyp");
+        let segment = packager.resource("audio_1.m4s").unwrap();
+        assert_eq!(&segment.data[4..8], b"styp");
+        assert!(packager.resource("video_99.m4s").is_none());
+        assert!(packager.resource("other_init.mp4").is_none());
+        assert_eq!(
+            packager.resource("manifest.mpd").unwrap().content_type,
+            "application/dash+xml"
+        );
+    }
+
+    #[test]
+    fn test_low_latency_parts() {
+        let mut packager = CmafPackager::new(2, 5).with_part_target(500);
+        packager.set_video_track(video_track());
+        packager.set_audio_track(audio_track());
+
+        let mut updates = 0;
+        for i in 0..60u32 {
+            let ts = i * 40;
+            updates += packager.push_video(ts, 0, frame(), i % 50 == 0) as u32;
+            updates += packager.push_audio(ts, Bytes::from_static(&[0x21, 0x00])) as u32;
+        }
+        assert!(updates > 0);
+
+        // 第一个分片（0~2s）：12 帧一个部分分片（480ms），最后一个 80ms
+        let segments = packager.segments(CmafTrackType::Video);
+        assert_eq!(segments.len(), 1);
+        let parts = &segments[0].parts;
+        assert_eq!(parts.len(), 5);
+        assert_eq!(parts[0].duration, 12 * 3600);
+        assert!(parts[0].independent);
+        assert!(!parts[1].independent);
+        assert_eq!(parts[4].duration, 2 * 3600);
+        assert_eq!(parts.iter().map(|part| part.duration).sum::<u64>(), segments[0].duration);
+
+        // 分片数据 = styp + 各部分分片
+        let total: usize = parts.iter().map(|part| part.data.len()).sum();
+        assert_eq!(segments[0].data.len(), total + segment_type_box().len());
+
+        // 当前分片（2s 起）已完成 0 个部分分片：帧 50~58 在缓存中，帧 59 待定时长
+        assert_eq!(packager.part_status(CmafTrackType::Video, 0, None), PartStatus::Available);
+        assert_eq!(packager.part_status(CmafTrackType::Video, 1, Some(0)), PartStatus::Pending);
+        assert_eq!(packager.part_status(CmafTrackType::Video, 2, None), PartStatus::Pending);
+        assert_eq!(packager.part_status(CmafTrackType::Video, 3, None), PartStatus::TooFarAhead);
+
+        let playlist = packager.hls_media_playlist(CmafTrackType::Video).unwrap();
+        assert!(playlist.contains("#EXT-X-PART-INF:PART-TARGET=0.500"));
+        assert!(playlist.contains("#EXT-X-PART:DURATION=0.480,URI=\"video_0.0.m4s\",INDEPENDENT=YES"));
+        assert!(playlist.contains("#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"video_1.0.m4s\""));
+        assert!(playlist.contains("#EXT-X-RENDITION-REPORT:URI=\"audio.m3u8\",LAST-MSN=0,LAST-PART=2"));
+
+        // 推进到第二个分片的第一个部分分片
+        for i in 60..64u32 {
+            packager.push_video(i * 40, 0, frame(), false);
+        }
+        assert_eq!(packager.part_status(CmafTrackType::Video, 1, Some(0)), PartStatus::Available);
+
+        let part = packager.resource("video_1.0.m4s").unwrap();
+        assert_eq!(&part.data[4..8], b"moof");
+        assert!(packager.resource("video_0.4.m4s").is_some());
+        assert!(packager.resource("video_0.5.m4s").is_none());
+        assert_eq!(
+            CmafPackager::parse_part_name("audio_12.3.m4s"),
+            Some((CmafTrackType::Audio, 12, 3))
+        );
+        assert_eq!(CmafPackager::parse_part_name("audio_12.m4s"), None);
+    }
+
+    #[test]
+    fn test_audio_only() {
+        let mut packager = CmafPackager::new(1, 5);
+        packager.set_audio_track(audio_track());
+        for i in 0..100u32 {
+            packager.push_audio(i * 23, Bytes::from_static(&[0x21, 0x00]));
+        }
+
+        let segments = packager.segments(CmafTrackType::Audio);
+        assert_eq!(segments.len(), 2);
+        // 连续分片的时间线首尾相接
+        assert_eq!(
+            segments[0].decode_time + segments[0].duration,
+            segments[1].decode_time
+        );
+
+        let master = packager.hls_master_playlist().unwrap();
+        assert!(master.contains("audio.m3u8"));
+        assert!(!master.contains("video.m3u8"));
+    }
+}
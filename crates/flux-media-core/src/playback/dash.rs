@@ -0,0 +1,221 @@
+//! MPEG-DASH 动态 MPD 生成（SegmentTemplate + SegmentTimeline）
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// DASH 表示（Representation）及其所在的 AdaptationSet
+#[derive(Debug, Clone)]
+pub struct DashRepresentation {
+    /// Representation ID（用于 $RepresentationID$ 模板）
+    pub id: String,
+    /// "video" 或 "audio"
+    pub content_type: &'static str,
+    pub codecs: String,
+    pub bandwidth: u64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub timescale: u32,
+    /// 时间线首个分片的序号（$Number$）
+    pub start_number: u64,
+    /// 分片时间线：(开始时间, 时长)，单位为 timescale
+    pub timeline: Vec<(u64, u64)>,
+}
+
+/// 动态（直播）MPD 生成器
+#[derive(Debug, Clone)]
+pub struct DashMpdGenerator {
+    /// 媒体时间 0 对应的墙钟时间
+    pub availability_start_time: DateTime<Utc>,
+    /// 分片目标时长（秒）
+    pub segment_duration: u32,
+    /// 可回看的时长（秒）
+    pub time_shift_buffer_depth: u32,
+    /// init/media 模板（相对 MPD 的路径）
+    pub initialization_template: String,
+    pub media_template: String,
+}
+
+impl DashMpdGenerator {
+    pub fn new(availability_start_time: DateTime<Utc>, segment_duration: u32, window: usize) -> Self {
+        Self {
+            availability_start_time,
+            segment_duration,
+            time_shift_buffer_depth: segment_duration * window as u32,
+            initialization_template: "$RepresentationID$_init.mp4".to_string(),
+            media_template: "$RepresentationID$_$Number$.m4s".to_string(),
+        }
+    }
+
+    /// 生成 MPD
+    pub fn generate(&self, representations: &[DashRepresentation], publish_time: DateTime<Utc>) -> String {
+        let mut mpd = String::new();
+
+        mpd.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        mpd.push_str("<MPD xmlns=\"urn:mpeg:dash:schema:mpd:2011\" ");
+        mpd.push_str("profiles=\"urn:mpeg:dash:profile:isoff-live:2011\" ");
+        mpd.push_str("type=\"dynamic\" ");
+        mpd.push_str(&format!(
+            "availabilityStartTime=\"{}\" ",
+            format_datetime(self.availability_start_time)
+        ));
+        mpd.push_str(&format!("publishTime=\"{}\" ", format_datetime(publish_time)));
+        mpd.push_str(&format!(
+            "minimumUpdatePeriod=\"PT{}S\" ",
+            self.segment_duration.max(1)
+        ));
+        mpd.push_str(&format!(
+            "minBufferTime=\"PT{}S\" ",
+            self.segment_duration.max(1)
+        ));
+        mpd.push_str(&format!(
+            "timeShiftBufferDepth=\"PT{}S\" ",
+            self.time_shift_buffer_depth
+        ));
+        mpd.push_str(&format!(
+            "suggestedPresentationDelay=\"PT{}S\">\n",
+            self.segment_duration * 2
+        ));
+
+        mpd.push_str("  <Period id=\"0\" start=\"PT0S\">\n");
+
+        for representation in representations {
+            let mime_type = format!("{}/mp4", representation.content_type);
+            mpd.push_str(&format!(
+                "    <AdaptationSet contentType=\"{}\" mimeType=\"{}\" segmentAlignment=\"true\" startWithSAP=\"1\">\n",
+                representation.content_type, mime_type
+            ));
+
+            mpd.push_str(&format!(
+                "      <SegmentTemplate timescale=\"{}\" startNumber=\"{}\" initialization=\"{}\" media=\"{}\">\n",
+                representation.timescale,
+                representation.start_number,
+                self.initialization_template,
+                self.media_template
+            ));
+            mpd.push_str("        <SegmentTimeline>\n");
+            for (start, duration, repeat) in compact_timeline(&representation.timeline) {
+                if repeat > 0 {
+                    mpd.push_str(&format!(
+                        "          <S t=\"{}\" d=\"{}\" r=\"{}\"/>\n",
+                        start, duration, repeat
+                    ));
+                } else {
+                    mpd.push_str(&format!("          <S t=\"{}\" d=\"{}\"/>\n", start, duration));
+                }
+            }
+            mpd.push_str("        </SegmentTimeline>\n");
+            mpd.push_str("      </SegmentTemplate>\n");
+
+            let mut attributes = format!(
+                "id=\"{}\" codecs=\"{}\" bandwidth=\"{}\"",
+                representation.id, representation.codecs, representation.bandwidth
+            );
+            if let (Some(width), Some(height)) = (representation.width, representation.height) {
+                attributes.push_str(&format!(" width=\"{}\" height=\"{}\"", width, height));
+            }
+            if let Some(sample_rate) = representation.sample_rate {
+                attributes.push_str(&format!(" audioSamplingRate=\"{}\"", sample_rate));
+            }
+
+            if let Some(channels) = representation.channels {
+                mpd.push_str(&format!("      <Representation {}>\n", attributes));
+                mpd.push_str(&format!(
+                    "        <AudioChannelConfiguration schemeIdUri=\"urn:mpeg:dash:23003:3:audio_channel_configuration:2011\" value=\"{}\"/>\n",
+                    channels
+                ));
+                mpd.push_str("      </Representation>\n");
+            } else {
+                mpd.push_str(&format!("      <Representation {}/>\n", attributes));
+            }
+
+            mpd.push_str("    </AdaptationSet>\n");
+        }
+
+        mpd.push_str("  </Period>\n");
+        mpd.push_str("</MPD>\n");
+
+        mpd
+    }
+}
+
+/// 合并连续等长分片为 `<S t d r>` 条目：(t, d, r)
+fn compact_timeline(timeline: &[(u64, u64)]) -> Vec<(u64, u64, u32)> {
+    let mut entries: Vec<(u64, u64, u32)> = Vec::new();
+    for &(start, duration) in timeline {
+        if let Some((first, last_duration, repeat)) = entries.last_mut() {
+            let expected = *first + *last_duration * (*repeat as u64 + 1);
+            if *last_duration == duration && expected == start {
+                *repeat += 1;
+                continue;
+            }
+        }
+        entries.push((start, duration, 0));
+    }
+    entries
+}
+
+fn format_datetime(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_timeline() {
+        let timeline = [(0, 100), (100, 100), (200, 100), (300, 80), (400, 100)];
+        assert_eq!(
+            compact_timeline(&timeline),
+            vec![(0, 100, 2), (300, 80, 0), (400, 100, 0)]
+        );
+    }
+
+    #[test]
+    fn test_generate_mpd() {
+        let start = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let generator = DashMpdGenerator::new(start, 4, 5);
+
+        let video = DashRepresentation {
+            id: "video".to_string(),
+            content_type: "video",
+            codecs: "avc1.64001f".to_string(),
+            bandwidth: 2_000_000,
+            width: Some(1280),
+            height: Some(720),
+            sample_rate: None,
+            channels: None,
+            timescale: 90000,
+            start_number: 7,
+            timeline: vec![(630000, 360000), (990000, 360000)],
+        };
+        let audio = DashRepresentation {
+            id: "audio".to_string(),
+            content_type: "audio",
+            codecs: "mp4a.40.2".to_string(),
+            bandwidth: 128_000,
+            width: None,
+            height: None,
+            sample_rate: Some(44100),
+            channels: Some(2),
+            timescale: 44100,
+            start_number: 7,
+            timeline: vec![(308700, 176128)],
+        };
+
+        let mpd = generator.generate(&[video, audio], start);
+        assert!(mpd.contains("type=\"dynamic\""));
+        assert!(mpd.contains("availabilityStartTime=\"2024-01-01T00:00:00.000Z\""));
+        assert!(mpd.contains("timeShiftBufferDepth=\"PT20S\""));
+        assert!(mpd.contains("startNumber=\"7\""));
+        assert!(mpd.contains("<S t=\"630000\" d=\"360000\" r=\"1\"/>"));
+        assert!(mpd.contains("<S t=\"308700\" d=\"176128\"/>"));
+        assert!(mpd.contains("codecs=\"avc1.64001f\""));
+        assert!(mpd.contains("width=\"1280\" height=\"720\""));
+        assert!(mpd.contains("audioSamplingRate=\"44100\""));
+        assert!(mpd.contains("media=\"$RepresentationID$_$Number$.m4s\""));
+    }
+}
@@ -1,4 +1,5 @@
-use crate::error::Result;
+use crate::error::{MediaError, Result};
+use crate::types::VideoCodec;
 use bytes::{BufMut, Bytes, BytesMut};
 
 /// FLV 封装器
@@ -22,6 +23,146 @@ pub struct FlvTag {
     pub data: Bytes,
 }
 
+/// FLV 视频包类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlvVideoPacketType {
+    /// 解码器配置（AVC/HEVC DecoderConfigurationRecord、AV1CodecConfigurationRecord）
+    SequenceHeader,
+    /// 编码帧
+    CodedFrames,
+    /// 序列结束
+    EndOfSequence,
+    /// 其他（元数据、命令帧等）
+    Other,
+}
+
+/// 解析后的 FLV 视频 tag body
+///
+/// 同时支持传统格式（CodecID 7 = AVC，12 = HEVC）和 Enhanced RTMP 的
+/// ExVideoTagHeader（FourCC `avc1` / `hvc1` / `av01`）。
+#[derive(Debug, Clone)]
+pub struct FlvVideoTag<'a> {
+    pub codec: VideoCodec,
+    pub is_keyframe: bool,
+    pub packet_type: FlvVideoPacketType,
+    /// 合成时间偏移（毫秒，PTS = DTS + composition_time）
+    pub composition_time: i32,
+    /// 去掉 tag 头后的数据（配置记录或长度前缀的 NALU/OBU）
+    pub payload: &'a [u8],
+    /// 是否为 Enhanced RTMP 扩展头
+    pub enhanced: bool,
+}
+
+impl<'a> FlvVideoTag<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let first = *data
+            .first()
+            .ok_or_else(|| MediaError::Decode("Empty video tag".to_string()))?;
+
+        if first & 0x80 != 0 {
+            Self::parse_enhanced(data)
+        } else {
+            Self::parse_legacy(data)
+        }
+    }
+
+    fn parse_legacy(data: &'a [u8]) -> Result<Self> {
+        let frame_type = data[0] >> 4;
+        let codec = match data[0] & 0x0F {
+            7 => VideoCodec::H264,  // AVC
+            12 => VideoCodec::H265, // HEVC（国内 CDN 扩展）
+            _ => VideoCodec::Unknown,
+        };
+
+        if codec == VideoCodec::Unknown || data.len() < 5 {
+            return Ok(Self {
+                codec,
+                is_keyframe: frame_type == 1,
+                packet_type: FlvVideoPacketType::Other,
+                composition_time: 0,
+                payload: data.get(1..).unwrap_or_default(),
+                enhanced: false,
+            });
+        }
+
+        let packet_type = match data[1] {
+            0 => FlvVideoPacketType::SequenceHeader,
+            1 => FlvVideoPacketType::CodedFrames,
+            2 => FlvVideoPacketType::EndOfSequence,
+            _ => FlvVideoPacketType::Other,
+        };
+
+        Ok(Self {
+            codec,
+            is_keyframe: frame_type == 1,
+            packet_type,
+            composition_time: read_si24(&data[2..5]),
+            payload: &data[5..],
+            enhanced: false,
+        })
+    }
+
+    fn parse_enhanced(data: &'a [u8]) -> Result<Self> {
+        if data.len() < 5 {
+            return Err(MediaError::Decode("Enhanced video tag too short".to_string()));
+        }
+
+        let frame_type = (data[0] >> 4) & 0x07;
+        let codec = match &data[1..5] {
+            b"avc1" => VideoCodec::H264,
+            b"hvc1" => VideoCodec::H265,
+            b"av01" => VideoCodec::AV1,
+            _ => VideoCodec::Unknown,
+        };
+
+        // 命令帧（frame type 5）不携带视频数据
+        if frame_type == 5 {
+            return Ok(Self {
+                codec,
+                is_keyframe: false,
+                packet_type: FlvVideoPacketType::Other,
+                composition_time: 0,
+                payload: &data[5..],
+                enhanced: true,
+            });
+        }
+
+        let (packet_type, composition_time, payload) = match data[0] & 0x0F {
+            0 => (FlvVideoPacketType::SequenceHeader, 0, &data[5..]),
+            // CodedFrames：AVC/HEVC 带 SI24 合成时间
+            1 if codec == VideoCodec::H264 || codec == VideoCodec::H265 => {
+                if data.len() < 8 {
+                    return Err(MediaError::Decode("Enhanced video tag too short".to_string()));
+                }
+                (FlvVideoPacketType::CodedFrames, read_si24(&data[5..8]), &data[8..])
+            }
+            1 | 3 => (FlvVideoPacketType::CodedFrames, 0, &data[5..]), // CodedFramesX 无合成时间
+            2 => (FlvVideoPacketType::EndOfSequence, 0, &data[5..]),
+            _ => (FlvVideoPacketType::Other, 0, &data[5..]),
+        };
+
+        Ok(Self {
+            codec,
+            is_keyframe: frame_type == 1,
+            packet_type,
+            composition_time,
+            payload,
+            enhanced: true,
+        })
+    }
+
+    /// 是否为解码器配置（序列头）
+    pub fn is_sequence_header(&self) -> bool {
+        self.packet_type == FlvVideoPacketType::SequenceHeader
+    }
+}
+
+/// 读取有符号 24 位大端整数
+fn read_si24(bytes: &[u8]) -> i32 {
+    let value = ((bytes[0] as i32) << 16) | ((bytes[1] as i32) << 8) | bytes[2] as i32;
+    (value << 8) >> 8
+}
+
 impl FlvMuxer {
     pub fn new() -> Self {
         Self {
@@ -173,6 +314,59 @@ mod tests {
         assert_eq!(data_size, 2);
     }
 
+    #[test]
+    fn test_parse_legacy_avc_tag() {
+        let data = [0x17, 0x01, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x01, 0x65];
+        let tag = FlvVideoTag::parse(&data).unwrap();
+        assert_eq!(tag.codec, VideoCodec::H264);
+        assert!(tag.is_keyframe);
+        assert_eq!(tag.packet_type, FlvVideoPacketType::CodedFrames);
+        assert_eq!(tag.composition_time, 40);
+        assert_eq!(tag.payload, &data[5..]);
+        assert!(!tag.enhanced);
+    }
+
+    #[test]
+    fn test_parse_enhanced_hevc_tags() {
+        // 序列头：IsExHeader | keyframe | SequenceStart + "hvc1"
+        let mut header = vec![0x90];
+        header.extend_from_slice(b"hvc1");
+        header.extend_from_slice(&[0x01, 0x02]);
+        let tag = FlvVideoTag::parse(&header).unwrap();
+        assert_eq!(tag.codec, VideoCodec::H265);
+        assert!(tag.is_sequence_header());
+        assert_eq!(tag.payload, &[0x01, 0x02]);
+
+        // CodedFrames：带负的合成时间
+        let mut frame = vec![0xA1];
+        frame.extend_from_slice(b"hvc1");
+        frame.extend_from_slice(&[0xFF, 0xFF, 0xD8, 0x00, 0x00, 0x00, 0x01, 0x02]);
+        let tag = FlvVideoTag::parse(&frame).unwrap();
+        assert!(!tag.is_keyframe);
+        assert_eq!(tag.packet_type, FlvVideoPacketType::CodedFrames);
+        assert_eq!(tag.composition_time, -40);
+        assert_eq!(tag.payload, &[0x00, 0x00, 0x00, 0x01, 0x02]);
+
+        // CodedFramesX：无合成时间
+        let mut frame_x = vec![0x93];
+        frame_x.extend_from_slice(b"hvc1");
+        frame_x.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x26]);
+        let tag = FlvVideoTag::parse(&frame_x).unwrap();
+        assert!(tag.is_keyframe);
+        assert_eq!(tag.composition_time, 0);
+        assert_eq!(tag.payload.len(), 5);
+    }
+
+    #[test]
+    fn test_parse_enhanced_av1_tag() {
+        let mut frame = vec![0x91];
+        frame.extend_from_slice(b"av01");
+        frame.extend_from_slice(&[0x12, 0x00]);
+        let tag = FlvVideoTag::parse(&frame).unwrap();
+        assert_eq!(tag.codec, VideoCodec::AV1);
+        assert_eq!(tag.payload, &[0x12, 0x00]);
+    }
+
     #[test]
     fn test_reset() {
         let mut muxer = FlvMuxer::new();
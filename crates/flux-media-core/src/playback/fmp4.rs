@@ -0,0 +1,588 @@
+//! ISO-BMFF 分片 MP4（fMP4/CMAF）封装：init segment（ftyp + moov）与 media segment（styp + moof + mdat）
+
+use crate::codec::{
+    avc_codec_string, hevc_codec_string, parse_avc_decoder_config, parse_avc_sps_dimensions,
+    parse_hevc_decoder_config, parse_hevc_sps_dimensions, AacConfig,
+};
+use crate::error::{MediaError, Result};
+use crate::types::VideoCodec;
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// 视频轨道时间基（90kHz，与 RTMP 毫秒时间戳整除）
+pub const VIDEO_TIMESCALE: u32 = 90000;
+
+/// 每个 AAC 帧的采样数
+pub const AAC_SAMPLES_PER_FRAME: u32 = 1024;
+
+const UNITY_MATRIX: [u32; 9] = [0x00010000, 0, 0, 0, 0x00010000, 0, 0, 0, 0x40000000];
+
+/// 样本标志：同步样本（不依赖其他帧）
+const SAMPLE_FLAGS_SYNC: u32 = 0x0200_0000;
+/// 样本标志：非同步样本（依赖其他帧）
+const SAMPLE_FLAGS_NON_SYNC: u32 = 0x0101_0000;
+
+/// fMP4 轨道描述
+#[derive(Debug, Clone)]
+pub struct Fmp4Track {
+    pub track_id: u32,
+    pub timescale: u32,
+    pub kind: Fmp4TrackKind,
+    /// RFC 6381 codecs 字符串
+    pub codec_string: String,
+}
+
+/// 轨道类型与解码配置
+#[derive(Debug, Clone)]
+pub enum Fmp4TrackKind {
+    Video {
+        codec: VideoCodec,
+        width: u32,
+        height: u32,
+        /// AVC/HEVC DecoderConfigurationRecord 原文（写入 avcC/hvcC）
+        decoder_config: Bytes,
+    },
+    Audio {
+        config: AacConfig,
+        /// AudioSpecificConfig 原文（写入 esds）
+        audio_specific_config: Bytes,
+    },
+}
+
+impl Fmp4Track {
+    /// 由 RTMP 视频序列头（DecoderConfigurationRecord）创建视频轨道
+    pub fn video(track_id: u32, codec: VideoCodec, decoder_config: Bytes) -> Result<Self> {
+        let (parameter_sets, codec_string) = match codec {
+            VideoCodec::H264 => (
+                parse_avc_decoder_config(&decoder_config)?,
+                avc_codec_string(&decoder_config)?,
+            ),
+            VideoCodec::H265 => (
+                parse_hevc_decoder_config(&decoder_config)?,
+                hevc_codec_string(&decoder_config)?,
+            ),
+            other => {
+                return Err(MediaError::InvalidConfig(format!(
+                    "Video codec {} is not supported in fMP4",
+                    other
+                )))
+            }
+        };
+
+        // 尺寸解析失败不影响封装，播放器以码流内 SPS 为准
+        let (width, height) = parameter_sets
+            .sps
+            .first()
+            .and_then(|sps| match codec {
+                VideoCodec::H264 => parse_avc_sps_dimensions(sps).ok(),
+                _ => parse_hevc_sps_dimensions(sps).ok(),
+            })
+            .unwrap_or((0, 0));
+
+        Ok(Self {
+            track_id,
+            timescale: VIDEO_TIMESCALE,
+            kind: Fmp4TrackKind::Video {
+                codec,
+                width,
+                height,
+                decoder_config,
+            },
+            codec_string,
+        })
+    }
+
+    /// 由 RTMP AAC 序列头（AudioSpecificConfig）创建音频轨道，时间基为采样率
+    pub fn aac(track_id: u32, audio_specific_config: Bytes) -> Result<Self> {
+        let config = AacConfig::parse(&audio_specific_config)?;
+        Ok(Self {
+            track_id,
+            timescale: config.sample_rate(),
+            codec_string: config.codec_string(),
+            kind: Fmp4TrackKind::Audio {
+                config,
+                audio_specific_config,
+            },
+        })
+    }
+
+    pub fn is_video(&self) -> bool {
+        matches!(self.kind, Fmp4TrackKind::Video { .. })
+    }
+}
+
+/// fMP4 样本（视频为长度前缀 NALU，音频为原始 AAC 帧）
+#[derive(Debug, Clone)]
+pub struct Fmp4Sample {
+    pub data: Bytes,
+    /// 时长（轨道时间基）
+    pub duration: u32,
+    /// PTS - DTS（轨道时间基）
+    pub composition_offset: i32,
+    pub is_sync: bool,
+}
+
+/// 单轨道 fMP4 封装器
+pub struct Fmp4Muxer {
+    track: Fmp4Track,
+    sequence_number: u32,
+}
+
+impl Fmp4Muxer {
+    pub fn new(track: Fmp4Track) -> Self {
+        Self {
+            track,
+            sequence_number: 1,
+        }
+    }
+
+    pub fn track(&self) -> &Fmp4Track {
+        &self.track
+    }
+
+    /// 生成 init segment（ftyp + moov）
+    pub fn init_segment(&self) -> Bytes {
+        let mut out = BytesMut::with_capacity(1024);
+        write_box(&mut out, b"ftyp", |out| {
+            out.put_slice(b"iso6");
+            out.put_u32(0);
+            for brand in [b"iso6", b"cmfc", b"mp41", b"dash"] {
+                out.put_slice(brand);
+            }
+        });
+        write_box(&mut out, b"moov", |out| {
+            write_mvhd(out, self.track.track_id + 1, 0);
+            write_trak(out, &self.track, TrackTiming::default(), write_empty_sample_tables);
+            write_box(out, b"mvex", |out| {
+                write_full_box(out, b"trex", 0, 0, |out| {
+                    out.put_u32(self.track.track_id);
+                    out.put_u32(1); // default_sample_description_index
+                    out.put_u32(0); // default_sample_duration
+                    out.put_u32(0); // default_sample_size
+                    out.put_u32(0); // default_sample_flags
+                });
+            });
+        });
+        out.freeze()
+    }
+
+    /// 生成 media segment（styp + moof + mdat）；`base_decode_time` 为首个样本的 DTS（轨道时间基）
+    pub fn media_segment(&mut self, base_decode_time: u64, samples: &[Fmp4Sample]) -> Bytes {
+        let mut out = BytesMut::from(&segment_type_box()[..]);
+        out.extend_from_slice(&self.fragment(base_decode_time, samples));
+        out.freeze()
+    }
+
+    /// 生成单个分片片段（moof + mdat），可作为 LL-HLS 部分分片
+    pub fn fragment(&mut self, base_decode_time: u64, samples: &[Fmp4Sample]) -> Bytes {
+        let is_video = self.track.is_video();
+        let mdat_size: usize = samples.iter().map(|sample| sample.data.len()).sum();
+        let mut out = BytesMut::with_capacity(mdat_size + 256 + samples.len() * 16);
+
+        let mut data_offset_pos = 0;
+        write_box(&mut out, b"moof", |out| {
+            write_full_box(out, b"mfhd", 0, 0, |out| out.put_u32(self.sequence_number));
+            write_box(out, b"traf", |out| {
+                // default-base-is-moof
+                write_full_box(out, b"tfhd", 0, 0x02_0000, |out| out.put_u32(self.track.track_id));
+                write_full_box(out, b"tfdt", 1, 0, |out| out.put_u64(base_decode_time));
+
+                // data-offset + duration + size + flags (+ composition offset)
+                let mut flags = 0x000001 | 0x000100 | 0x000200 | 0x000400;
+                if is_video {
+                    flags |= 0x000800;
+                }
+                write_full_box(out, b"trun", 1, flags, |out| {
+                    out.put_u32(samples.len() as u32);
+                    data_offset_pos = out.len();
+                    out.put_i32(0);
+                    for sample in samples {
+                        out.put_u32(sample.duration);
+                        out.put_u32(sample.data.len() as u32);
+                        out.put_u32(if sample.is_sync {
+                            SAMPLE_FLAGS_SYNC
+                        } else {
+                            SAMPLE_FLAGS_NON_SYNC
+                        });
+                        if is_video {
+                            out.put_i32(sample.composition_offset);
+                        }
+                    }
+                });
+            });
+        });
+
+        // data_offset 相对 moof 起始，指向 mdat 负载
+        let data_offset = (out.len() + 8) as i32;
+        out[data_offset_pos..data_offset_pos + 4].copy_from_slice(&data_offset.to_be_bytes());
+
+        out.put_u32((mdat_size + 8) as u32);
+        out.put_slice(b"mdat");
+        for sample in samples {
+            out.put_slice(&sample.data);
+        }
+
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+        out.freeze()
+    }
+}
+
+/// media segment 的 styp box
+pub fn segment_type_box() -> Bytes {
+    let mut out = BytesMut::with_capacity(24);
+    write_box(&mut out, b"styp", |out| {
+        out.put_slice(b"msdh");
+        out.put_u32(0);
+        out.put_slice(b"msdh");
+        out.put_slice(b"msix");
+    });
+    out.freeze()
+}
+
+/// 轨道时长（非分片 MP4 使用；fMP4 全为 0）
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TrackTiming {
+    /// 影片时间基（毫秒）下的时长
+    pub duration: u32,
+    /// 轨道时间基下的时长
+    pub media_duration: u32,
+    /// 相对影片起点的延迟（毫秒），非 0 时写入空编辑
+    pub start_delay: u32,
+}
+
+/// 影片时间基（mvhd / tkhd / elst 使用毫秒）
+pub(crate) const MOVIE_TIMESCALE: u32 = 1000;
+
+pub(crate) fn write_mvhd(out: &mut BytesMut, next_track_id: u32, duration: u32) {
+    write_full_box(out, b"mvhd", 0, 0, |out| {
+        out.put_u32(0); // creation_time
+        out.put_u32(0); // modification_time
+        out.put_u32(MOVIE_TIMESCALE); // timescale
+        out.put_u32(duration);
+        out.put_u32(0x00010000); // rate 1.0
+        out.put_u16(0x0100); // volume 1.0
+        out.put_bytes(0, 10);
+        for value in UNITY_MATRIX {
+            out.put_u32(value);
+        }
+        out.put_bytes(0, 24); // pre_defined
+        out.put_u32(next_track_id);
+    });
+}
+
+/// 写入 trak；`sample_tables` 在 stsd 之后写入 stts / stsc / stsz / stco 等样本表
+pub(crate) fn write_trak(
+    out: &mut BytesMut,
+    track: &Fmp4Track,
+    timing: TrackTiming,
+    sample_tables: impl FnOnce(&mut BytesMut),
+) {
+    let (width, height, is_audio) = match &track.kind {
+        Fmp4TrackKind::Video { width, height, .. } => (*width, *height, false),
+        Fmp4TrackKind::Audio { .. } => (0, 0, true),
+    };
+
+    write_box(out, b"trak", |out| {
+        // track_enabled | track_in_movie
+        write_full_box(out, b"tkhd", 0, 0x000003, |out| {
+            out.put_u32(0); // creation_time
+            out.put_u32(0); // modification_time
+            out.put_u32(track.track_id);
+            out.put_u32(0);
+            out.put_u32(timing.duration);
+            out.put_bytes(0, 8);
+            out.put_u16(0); // layer
+            out.put_u16(0); // alternate_group
+            out.put_u16(if is_audio { 0x0100 } else { 0 });
+            out.put_u16(0);
+            for value in UNITY_MATRIX {
+                out.put_u32(value);
+            }
+            out.put_u32(width << 16);
+            out.put_u32(height << 16);
+        });
+
+        if timing.start_delay > 0 {
+            write_box(out, b"edts", |out| {
+                write_full_box(out, b"elst", 0, 0, |out| {
+                    out.put_u32(2);
+                    // 空编辑：轨道晚于影片起点开始
+                    out.put_u32(timing.start_delay);
+                    out.put_i32(-1);
+                    out.put_u32(0x00010000);
+                    out.put_u32(timing.duration.saturating_sub(timing.start_delay));
+                    out.put_i32(0);
+                    out.put_u32(0x00010000);
+                });
+            });
+        }
+
+        write_box(out, b"mdia", |out| {
+            write_full_box(out, b"mdhd", 0, 0, |out| {
+                out.put_u32(0);
+                out.put_u32(0);
+                out.put_u32(track.timescale);
+                out.put_u32(timing.media_duration);
+                out.put_u16(0x55C4); // 'und'
+                out.put_u16(0);
+            });
+            write_full_box(out, b"hdlr", 0, 0, |out| {
+                out.put_u32(0);
+                out.put_slice(if is_audio { b"soun" } else { b"vide" });
+                out.put_bytes(0, 12);
+                out.put_slice(if is_audio { b"SoundHandler\0" } else { b"VideoHandler\0" });
+            });
+            write_box(out, b"minf", |out| {
+                if is_audio {
+                    write_full_box(out, b"smhd", 0, 0, |out| out.put_u32(0));
+                } else {
+                    write_full_box(out, b"vmhd", 0, 1, |out| out.put_bytes(0, 8));
+                }
+                write_box(out, b"dinf", |out| {
+                    write_full_box(out, b"dref", 0, 0, |out| {
+                        out.put_u32(1);
+                        // self-contained
+                        write_full_box(out, b"url ", 0, 1, |_| {});
+                    });
+                });
+                write_box(out, b"stbl", |out| {
+                    write_full_box(out, b"stsd", 0, 0, |out| {
+                        out.put_u32(1);
+                        write_sample_entry(out, track);
+                    });
+                    sample_tables(out);
+                });
+            });
+        });
+    });
+}
+
+/// fMP4 的 moov 中样本表为空，样本在 moof 中描述
+fn write_empty_sample_tables(out: &mut BytesMut) {
+    write_full_box(out, b"stts", 0, 0, |out| out.put_u32(0));
+    write_full_box(out, b"stsc", 0, 0, |out| out.put_u32(0));
+    write_full_box(out, b"stsz", 0, 0, |out| {
+        out.put_u32(0);
+        out.put_u32(0);
+    });
+    write_full_box(out, b"stco", 0, 0, |out| out.put_u32(0));
+}
+
+fn write_sample_entry(out: &mut BytesMut, track: &Fmp4Track) {
+    match &track.kind {
+        Fmp4TrackKind::Video {
+            codec,
+            width,
+            height,
+            decoder_config,
+        } => {
+            let (entry, config_box) = match codec {
+                VideoCodec::H265 => (b"hvc1", b"hvcC"),
+                _ => (b"avc1", b"avcC"),
+            };
+            write_box(out, entry, |out| {
+                out.put_bytes(0, 6);
+                out.put_u16(1); // data_reference_index
+                out.put_bytes(0, 16);
+                out.put_u16(*width as u16);
+                out.put_u16(*height as u16);
+                out.put_u32(0x00480000); // 72 dpi
+                out.put_u32(0x00480000);
+                out.put_u32(0);
+                out.put_u16(1); // frame_count
+                out.put_bytes(0, 32); // compressorname
+                out.put_u16(0x0018); // depth
+                out.put_i16(-1);
+                write_box(out, config_box, |out| out.put_slice(decoder_config));
+            });
+        }
+        Fmp4TrackKind::Audio {
+            config,
+            audio_specific_config,
+        } => {
+            write_box(out, b"mp4a", |out| {
+                out.put_bytes(0, 6);
+                out.put_u16(1); // data_reference_index
+                out.put_bytes(0, 8);
+                out.put_u16(config.channel_count());
+                out.put_u16(16); // samplesize
+                out.put_u32(0);
+                out.put_u32(config.sample_rate().min(0xFFFF) << 16);
+                write_full_box(out, b"esds", 0, 0, |out| {
+                    write_esds(out, track.track_id, audio_specific_config);
+                });
+            });
+        }
+    }
+}
+
+/// ES_Descriptor（ISO/IEC 14496-1 7.2.6.5）
+fn write_esds(out: &mut BytesMut, track_id: u32, audio_specific_config: &[u8]) {
+    let dsi_len = 2 + audio_specific_config.len();
+    let dcd_len = 13 + dsi_len;
+    let es_len = 3 + 2 + dcd_len + 3;
+
+    out.put_u8(0x03); // ES_DescrTag
+    out.put_u8(es_len as u8);
+    out.put_u16(track_id as u16);
+    out.put_u8(0);
+
+    out.put_u8(0x04); // DecoderConfigDescrTag
+    out.put_u8(dcd_len as u8);
+    out.put_u8(0x40); // Audio ISO/IEC 14496-3
+    out.put_u8(0x15); // AudioStream
+    out.put_bytes(0, 3); // bufferSizeDB
+    out.put_u32(0); // maxBitrate
+    out.put_u32(0); // avgBitrate
+
+    out.put_u8(0x05); // DecSpecificInfoTag
+    out.put_u8(audio_specific_config.len() as u8);
+    out.put_slice(audio_specific_config);
+
+    out.put_u8(0x06); // SLConfigDescrTag
+    out.put_u8(1);
+    out.put_u8(0x02);
+}
+
+pub(crate) fn write_box(out: &mut BytesMut, kind: &[u8; 4], body: impl FnOnce(&mut BytesMut)) {
+    let start = out.len();
+    out.put_u32(0);
+    out.put_slice(kind);
+    body(out);
+    let size = (out.len() - start) as u32;
+    out[start..start + 4].copy_from_slice(&size.to_be_bytes());
+}
+
+pub(crate) fn write_full_box(
+    out: &mut BytesMut,
+    kind: &[u8; 4],
+    version: u8,
+    flags: u32,
+    body: impl FnOnce(&mut BytesMut),
+) {
+    write_box(out, kind, |out| {
+        out.put_u32(((version as u32) << 24) | (flags & 0x00FF_FFFF));
+        body(out);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn avc_config() -> Bytes {
+        let mut config = vec![0x01, 0x64, 0x00, 0x1F, 0xFF, 0xE1, 0x00, 0x0A];
+        config.extend_from_slice(&[0x67, 0x64, 0x00, 0x1F, 0xAC, 0xD9, 0x40, 0x50, 0x05, 0xB9]);
+        config.extend_from_slice(&[0x01, 0x00, 0x02, 0x68, 0xEE]);
+        Bytes::from(config)
+    }
+
+    /// 查找顶层或嵌套 box，返回 box 内容
+    fn find_box<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+        let mut offset = 0;
+        while offset + 8 <= data.len() {
+            let size = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+            let kind = &data[offset + 4..offset + 8];
+            if kind == path[0] {
+                let body = &data[offset + 8..offset + size];
+                return if path.len() == 1 {
+                    Some(body)
+                } else {
+                    find_box(body, &path[1..])
+                };
+            }
+            offset += size;
+        }
+        None
+    }
+
+    #[test]
+    fn test_video_track() {
+        let track = Fmp4Track::video(1, VideoCodec::H264, avc_config()).unwrap();
+        assert_eq!(track.codec_string, "avc1.64001f");
+        assert!(matches!(
+            track.kind,
+            Fmp4TrackKind::Video { width: 1280, height: 720, .. }
+        ));
+        assert!(Fmp4Track::video(1, VideoCodec::AV1, avc_config()).is_err());
+    }
+
+    #[test]
+    fn test_init_segment() {
+        let track = Fmp4Track::video(1, VideoCodec::H264, avc_config()).unwrap();
+        let init = Fmp4Muxer::new(track).init_segment();
+
+        assert_eq!(&init[4..8], b"ftyp");
+        let tkhd = find_box(&init, &[b"moov", b"trak", b"tkhd"]).unwrap();
+        assert_eq!(u32::from_be_bytes(tkhd[12..16].try_into().unwrap()), 1);
+        assert_eq!(u32::from_be_bytes(tkhd[76..80].try_into().unwrap()), 1280 << 16);
+
+        let mdhd = find_box(&init, &[b"moov", b"trak", b"mdia", b"mdhd"]).unwrap();
+        assert_eq!(u32::from_be_bytes(mdhd[12..16].try_into().unwrap()), VIDEO_TIMESCALE);
+
+        let stsd = find_box(&init, &[b"moov", b"trak", b"mdia", b"minf", b"stbl", b"stsd"]).unwrap();
+        let avc1 = find_box(&stsd[8..], &[b"avc1"]).unwrap();
+        let avcc = find_box(&avc1[78..], &[b"avcC"]).unwrap();
+        assert_eq!(avcc, &avc_config()[..]);
+
+        assert!(find_box(&init, &[b"moov", b"mvex", b"trex"]).is_some());
+    }
+
+    #[test]
+    fn test_aac_init_segment() {
+        let track = Fmp4Track::aac(2, Bytes::from_static(&[0x12, 0x10])).unwrap();
+        assert_eq!(track.timescale, 44100);
+        assert_eq!(track.codec_string, "mp4a.40.2");
+
+        let init = Fmp4Muxer::new(track).init_segment();
+        let stsd = find_box(&init, &[b"moov", b"trak", b"mdia", b"minf", b"stbl", b"stsd"]).unwrap();
+        let mp4a = find_box(&stsd[8..], &[b"mp4a"]).unwrap();
+        assert_eq!(u16::from_be_bytes([mp4a[16], mp4a[17]]), 2);
+        let esds = find_box(&mp4a[28..], &[b"esds"]).unwrap();
+        // DecSpecificInfo 中为原始 AudioSpecificConfig
+        let asc_pos = esds.windows(2).position(|w| w == [0x05, 0x02]).unwrap();
+        assert_eq!(&esds[asc_pos + 2..asc_pos + 4], &[0x12, 0x10]);
+    }
+
+    #[test]
+    fn test_media_segment() {
+        let track = Fmp4Track::video(1, VideoCodec::H264, avc_config()).unwrap();
+        let mut muxer = Fmp4Muxer::new(track);
+
+        let samples = vec![
+            Fmp4Sample {
+                data: Bytes::from_static(&[0, 0, 0, 2, 0x65, 0x88]),
+                duration: 3600,
+                composition_offset: 3600,
+                is_sync: true,
+            },
+            Fmp4Sample {
+                data: Bytes::from_static(&[0, 0, 0, 1, 0x41]),
+                duration: 3600,
+                composition_offset: 0,
+                is_sync: false,
+            },
+        ];
+        let segment = muxer.media_segment(900000, &samples);
+
+        assert_eq!(&segment[4..8], b"styp");
+        let mfhd = find_box(&segment, &[b"moof", b"mfhd"]).unwrap();
+        assert_eq!(u32::from_be_bytes(mfhd[4..8].try_into().unwrap()), 1);
+
+        let tfdt = find_box(&segment, &[b"moof", b"traf", b"tfdt"]).unwrap();
+        assert_eq!(tfdt[0], 1);
+        assert_eq!(u64::from_be_bytes(tfdt[4..12].try_into().unwrap()), 900000);
+
+        let trun = find_box(&segment, &[b"moof", b"traf", b"trun"]).unwrap();
+        assert_eq!(u32::from_be_bytes(trun[4..8].try_into().unwrap()), 2);
+
+        // data_offset 指向 mdat 负载
+        let moof_start = u32::from_be_bytes(segment[0..4].try_into().unwrap()) as usize;
+        let data_offset = i32::from_be_bytes(trun[8..12].try_into().unwrap()) as usize;
+        assert_eq!(&segment[moof_start + data_offset..][..6], &[0, 0, 0, 2, 0x65, 0x88]);
+
+        // 序号递增
+        let segment = muxer.media_segment(907200, &samples[1..]);
+        let mfhd = find_box(&segment, &[b"moof", b"mfhd"]).unwrap();
+        assert_eq!(u32::from_be_bytes(mfhd[4..8].try_into().unwrap()), 2);
+    }
+}
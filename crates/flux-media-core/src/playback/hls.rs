@@ -33,6 +33,92 @@ pub struct HlsPlaylist {
     pub segments: Vec<HlsSegment>,
 }
 
+/// LL-HLS 媒体播放列表（fMP4 分片 + 部分分片）
+#[derive(Debug, Clone, Default)]
+pub struct LowLatencyPlaylist {
+    pub target_duration: u64,
+    /// 部分分片目标时长（秒）
+    pub part_target: f64,
+    pub media_sequence: u64,
+    /// EXT-X-MAP 的 init segment URI
+    pub map_uri: String,
+    pub segments: Vec<LowLatencySegment>,
+    /// 下一个部分分片的 URI（EXT-X-PRELOAD-HINT）
+    pub preload_hint: Option<String>,
+    pub rendition_reports: Vec<RenditionReport>,
+}
+
+/// LL-HLS 分片；`uri` 为 None 表示正在生成的分片（只列出部分分片）
+#[derive(Debug, Clone)]
+pub struct LowLatencySegment {
+    pub uri: Option<String>,
+    pub duration: f64,
+    pub parts: Vec<LowLatencyPart>,
+}
+
+/// LL-HLS 部分分片
+#[derive(Debug, Clone)]
+pub struct LowLatencyPart {
+    pub uri: String,
+    pub duration: f64,
+    pub independent: bool,
+}
+
+/// 其他 rendition 的最新进度（EXT-X-RENDITION-REPORT）
+#[derive(Debug, Clone)]
+pub struct RenditionReport {
+    pub uri: String,
+    pub last_msn: u64,
+    pub last_part: u32,
+}
+
+impl LowLatencyPlaylist {
+    /// 生成 M3U8
+    pub fn render(&self) -> String {
+        let mut m3u8 = String::new();
+
+        m3u8.push_str("#EXTM3U\n");
+        m3u8.push_str("#EXT-X-VERSION:7\n");
+        m3u8.push_str(&format!("#EXT-X-TARGETDURATION:{}\n", self.target_duration));
+        // 播放器距直播边缘至少保留 3 个部分分片
+        m3u8.push_str(&format!(
+            "#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK={:.3}\n",
+            self.part_target * 3.0
+        ));
+        m3u8.push_str(&format!("#EXT-X-PART-INF:PART-TARGET={:.3}\n", self.part_target));
+        m3u8.push_str(&format!("#EXT-X-MEDIA-SEQUENCE:{}\n", self.media_sequence));
+        m3u8.push_str("#EXT-X-INDEPENDENT-SEGMENTS\n");
+        m3u8.push_str(&format!("#EXT-X-MAP:URI=\"{}\"\n", self.map_uri));
+
+        for segment in &self.segments {
+            for part in &segment.parts {
+                m3u8.push_str(&format!(
+                    "#EXT-X-PART:DURATION={:.3},URI=\"{}\"{}\n",
+                    part.duration,
+                    part.uri,
+                    if part.independent { ",INDEPENDENT=YES" } else { "" }
+                ));
+            }
+            if let Some(uri) = &segment.uri {
+                m3u8.push_str(&format!("#EXTINF:{:.3},\n", segment.duration));
+                m3u8.push_str(&format!("{}\n", uri));
+            }
+        }
+
+        if let Some(uri) = &self.preload_hint {
+            m3u8.push_str(&format!("#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"{}\"\n", uri));
+        }
+        for report in &self.rendition_reports {
+            m3u8.push_str(&format!(
+                "#EXT-X-RENDITION-REPORT:URI=\"{}\",LAST-MSN={},LAST-PART={}\n",
+                report.uri, report.last_msn, report.last_part
+            ));
+        }
+
+        m3u8
+    }
+}
+
 impl HlsGenerator {
     pub fn new(stream_id: StreamId, target_duration: u32, playlist_length: usize) -> Self {
         Self {
@@ -170,6 +256,54 @@ mod tests {
         assert_eq!(info.segments[0].sequence, 2); // 从序号 2 开始
     }
 
+    #[test]
+    fn test_low_latency_playlist() {
+        let playlist = LowLatencyPlaylist {
+            target_duration: 2,
+            part_target: 0.5,
+            media_sequence: 10,
+            map_uri: "video_init.mp4".to_string(),
+            segments: vec![
+                LowLatencySegment {
+                    uri: Some("video_10.m4s".to_string()),
+                    duration: 2.0,
+                    parts: vec![LowLatencyPart {
+                        uri: "video_10.0.m4s".to_string(),
+                        duration: 0.48,
+                        independent: true,
+                    }],
+                },
+                LowLatencySegment {
+                    uri: None,
+                    duration: 0.48,
+                    parts: vec![LowLatencyPart {
+                        uri: "video_11.0.m4s".to_string(),
+                        duration: 0.48,
+                        independent: false,
+                    }],
+                },
+            ],
+            preload_hint: Some("video_11.1.m4s".to_string()),
+            rendition_reports: vec![RenditionReport {
+                uri: "audio.m3u8".to_string(),
+                last_msn: 11,
+                last_part: 0,
+            }],
+        };
+
+        let m3u8 = playlist.render();
+        assert!(m3u8.contains("#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK=1.500"));
+        assert!(m3u8.contains("#EXT-X-PART-INF:PART-TARGET=0.500"));
+        assert!(m3u8.contains(
+            "#EXT-X-PART:DURATION=0.480,URI=\"video_10.0.m4s\",INDEPENDENT=YES\n#EXTINF:2.000,\nvideo_10.m4s"
+        ));
+        assert!(m3u8.contains("#EXT-X-PART:DURATION=0.480,URI=\"video_11.0.m4s\"\n"));
+        assert!(m3u8.contains("#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"video_11.1.m4s\""));
+        assert!(m3u8.contains("#EXT-X-RENDITION-REPORT:URI=\"audio.m3u8\",LAST-MSN=11,LAST-PART=0"));
+        // 正在生成的分片不输出 EXTINF
+        assert!(!m3u8.contains("video_11.m4s"));
+    }
+
     #[tokio::test]
     async fn test_get_segment() {
         let stream_id = StreamId::new("rtmp", "live/test");
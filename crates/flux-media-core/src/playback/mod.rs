@@ -1,7 +1,18 @@
+pub mod cmaf;
+pub mod dash;
 pub mod hls;
 pub mod flv;
+pub mod fmp4;
+pub mod mp4;
 pub mod ts;
 
-pub use hls::{HlsGenerator, HlsPlaylist, HlsSegment};
-pub use flv::{FlvMuxer, FlvTag};
-pub use ts::TsMuxer;
+pub use cmaf::{CmafPackager, CmafPart, CmafResource, CmafSegment, CmafTrackType, PartStatus};
+pub use dash::{DashMpdGenerator, DashRepresentation};
+pub use hls::{
+    HlsGenerator, HlsPlaylist, HlsSegment, LowLatencyPart, LowLatencyPlaylist, LowLatencySegment,
+    RenditionReport,
+};
+pub use flv::{FlvMuxer, FlvTag, FlvVideoPacketType, FlvVideoTag};
+pub use fmp4::{Fmp4Muxer, Fmp4Sample, Fmp4Track, Fmp4TrackKind};
+pub use mp4::{Mp4Sample, Mp4Writer};
+pub use ts::{TsDemuxer, TsFrame, TsMuxer};
@@ -0,0 +1,380 @@
+//! 渐进式（非分片）MP4 封装：ftyp + mdat + moov，用于录像导出下载
+//!
+//! 样本按时间交错写入 mdat，每个样本一个 chunk；moov 在 mdat 之后写入，
+//! 偏移使用 co64，支持超过 4GB 的导出文件。
+
+use crate::error::{MediaError, Result};
+use bytes::{BufMut, Bytes, BytesMut};
+use std::cmp::Ordering;
+
+use super::fmp4::{
+    write_box, write_full_box, write_mvhd, write_trak, Fmp4Track, TrackTiming, AAC_SAMPLES_PER_FRAME,
+    MOVIE_TIMESCALE, VIDEO_TIMESCALE,
+};
+
+/// mdat 头长度（size = 1 + largesize）
+const MDAT_HEADER_LEN: usize = 16;
+
+/// MP4 样本（视频为长度前缀 NALU，音频为原始 AAC 帧）
+#[derive(Debug, Clone)]
+pub struct Mp4Sample {
+    pub data: Bytes,
+    /// 解码时间戳（轨道时间基，各轨道基于同一时间原点；最早的样本即影片起点）
+    pub dts: u64,
+    /// PTS - DTS（轨道时间基）
+    pub composition_offset: i32,
+    pub is_sync: bool,
+}
+
+struct TrackState {
+    track: Fmp4Track,
+    samples: Vec<Mp4Sample>,
+}
+
+impl TrackState {
+    fn first_dts(&self) -> u64 {
+        self.samples.first().map(|sample| sample.dts).unwrap_or(0)
+    }
+
+    /// 首个样本在影片时间基（毫秒）下的时间
+    fn first_dts_ms(&self) -> u64 {
+        self.first_dts() * MOVIE_TIMESCALE as u64 / self.track.timescale as u64
+    }
+
+    /// 各样本时长；最后一个样本沿用前一个时长
+    fn durations(&self) -> Vec<u32> {
+        let default = if self.track.is_video() {
+            VIDEO_TIMESCALE / 25
+        } else {
+            AAC_SAMPLES_PER_FRAME
+        };
+        let mut durations: Vec<u32> = self
+            .samples
+            .windows(2)
+            .map(|pair| pair[1].dts.saturating_sub(pair[0].dts) as u32)
+            .collect();
+        durations.push(durations.last().copied().unwrap_or(default));
+        durations
+    }
+}
+
+/// 多轨道渐进式 MP4 写入器
+pub struct Mp4Writer {
+    tracks: Vec<TrackState>,
+}
+
+impl Mp4Writer {
+    pub fn new(tracks: Vec<Fmp4Track>) -> Self {
+        Self {
+            tracks: tracks
+                .into_iter()
+                .map(|track| TrackState {
+                    track,
+                    samples: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// 追加样本，同一轨道的样本须按 DTS 递增
+    pub fn push(&mut self, track_id: u32, sample: Mp4Sample) -> Result<()> {
+        let state = self
+            .tracks
+            .iter_mut()
+            .find(|state| state.track.track_id == track_id)
+            .ok_or_else(|| MediaError::InvalidConfig(format!("Unknown MP4 track {}", track_id)))?;
+        if state.samples.last().is_some_and(|last| sample.dts < last.dts) {
+            return Err(MediaError::InvalidConfig(format!(
+                "Non-monotonic DTS {} on MP4 track {}",
+                sample.dts, track_id
+            )));
+        }
+        state.samples.push(sample);
+        Ok(())
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.tracks.iter().map(|state| state.samples.len()).sum()
+    }
+
+    /// 生成完整 MP4 文件；没有样本的轨道不写入
+    pub fn finish(self) -> Bytes {
+        let tracks: Vec<TrackState> = self
+            .tracks
+            .into_iter()
+            .filter(|state| !state.samples.is_empty())
+            .collect();
+
+        let mut out = BytesMut::with_capacity(
+            tracks
+                .iter()
+                .flat_map(|state| state.samples.iter())
+                .map(|sample| sample.data.len())
+                .sum::<usize>()
+                + 4096,
+        );
+        write_box(&mut out, b"ftyp", |out| {
+            out.put_slice(b"isom");
+            out.put_u32(0x200);
+            for brand in [b"isom", b"iso2", b"avc1", b"mp41"] {
+                out.put_slice(brand);
+            }
+        });
+
+        // 按解码时间交错写入样本，记录每个样本的文件偏移
+        let mdat_start = out.len();
+        out.put_u32(1);
+        out.put_slice(b"mdat");
+        out.put_u64(0);
+        let mut offsets: Vec<Vec<u64>> = tracks.iter().map(|state| Vec::with_capacity(state.samples.len())).collect();
+        let mut cursors = vec![0usize; tracks.len()];
+        while let Some(index) = next_track(&tracks, &cursors) {
+            let sample = &tracks[index].samples[cursors[index]];
+            offsets[index].push(out.len() as u64);
+            out.put_slice(&sample.data);
+            cursors[index] += 1;
+        }
+        let mdat_size = (out.len() - mdat_start) as u64;
+        out[mdat_start + 8..mdat_start + MDAT_HEADER_LEN].copy_from_slice(&mdat_size.to_be_bytes());
+
+        let movie_start = tracks.iter().map(TrackState::first_dts_ms).min().unwrap_or(0);
+        let timings: Vec<(TrackTiming, Vec<u32>)> = tracks
+            .iter()
+            .map(|state| {
+                let durations = state.durations();
+                let media_duration: u64 = durations.iter().map(|&d| d as u64).sum();
+                let start_delay = (state.first_dts_ms() - movie_start) as u32;
+                let timing = TrackTiming {
+                    duration: start_delay
+                        + (media_duration * MOVIE_TIMESCALE as u64 / state.track.timescale as u64) as u32,
+                    media_duration: media_duration as u32,
+                    start_delay,
+                };
+                (timing, durations)
+            })
+            .collect();
+        let movie_duration = timings.iter().map(|(timing, _)| timing.duration).max().unwrap_or(0);
+        let next_track_id = tracks.iter().map(|state| state.track.track_id).max().unwrap_or(0) + 1;
+
+        write_box(&mut out, b"moov", |out| {
+            write_mvhd(out, next_track_id, movie_duration);
+            for ((state, (timing, durations)), offsets) in tracks.iter().zip(&timings).zip(&offsets) {
+                write_trak(out, &state.track, *timing, |out| {
+                    write_sample_tables(out, state, durations, offsets)
+                });
+            }
+        });
+        out.freeze()
+    }
+}
+
+/// 选出下一个待写入样本解码时间最早的轨道（各轨道 DTS 须基于同一时间原点）
+fn next_track(tracks: &[TrackState], cursors: &[usize]) -> Option<usize> {
+    let mut best: Option<(usize, u64, u64)> = None;
+    for (index, state) in tracks.iter().enumerate() {
+        let Some(sample) = state.samples.get(cursors[index]) else {
+            continue;
+        };
+        let dts = sample.dts;
+        let timescale = state.track.timescale as u64;
+        // 交叉相乘比较不同时间基下的时间
+        let earlier = match best {
+            None => true,
+            Some((_, best_dts, best_timescale)) => {
+                (dts as u128 * best_timescale as u128).cmp(&(best_dts as u128 * timescale as u128)) == Ordering::Less
+            }
+        };
+        if earlier {
+            best = Some((index, dts, timescale));
+        }
+    }
+    best.map(|(index, _, _)| index)
+}
+
+fn write_sample_tables(out: &mut BytesMut, state: &TrackState, durations: &[u32], offsets: &[u64]) {
+    // stts：相同时长合并为一项
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for &duration in durations {
+        match runs.last_mut() {
+            Some((count, last)) if *last == duration => *count += 1,
+            _ => runs.push((1, duration)),
+        }
+    }
+    write_full_box(out, b"stts", 0, 0, |out| {
+        out.put_u32(runs.len() as u32);
+        for (count, duration) in &runs {
+            out.put_u32(*count);
+            out.put_u32(*duration);
+        }
+    });
+
+    if state.samples.iter().any(|sample| sample.composition_offset != 0) {
+        let mut runs: Vec<(u32, i32)> = Vec::new();
+        for sample in &state.samples {
+            match runs.last_mut() {
+                Some((count, last)) if *last == sample.composition_offset => *count += 1,
+                _ => runs.push((1, sample.composition_offset)),
+            }
+        }
+        write_full_box(out, b"ctts", 1, 0, |out| {
+            out.put_u32(runs.len() as u32);
+            for (count, offset) in &runs {
+                out.put_u32(*count);
+                out.put_i32(*offset);
+            }
+        });
+    }
+
+    // 全部为同步样本时省略 stss
+    if state.track.is_video() && state.samples.iter().any(|sample| !sample.is_sync) {
+        let sync: Vec<u32> = state
+            .samples
+            .iter()
+            .enumerate()
+            .filter(|(_, sample)| sample.is_sync)
+            .map(|(index, _)| index as u32 + 1)
+            .collect();
+        write_full_box(out, b"stss", 0, 0, |out| {
+            out.put_u32(sync.len() as u32);
+            for number in &sync {
+                out.put_u32(*number);
+            }
+        });
+    }
+
+    write_full_box(out, b"stsc", 0, 0, |out| {
+        out.put_u32(1);
+        out.put_u32(1); // first_chunk
+        out.put_u32(1); // samples_per_chunk
+        out.put_u32(1); // sample_description_index
+    });
+    write_full_box(out, b"stsz", 0, 0, |out| {
+        out.put_u32(0);
+        out.put_u32(state.samples.len() as u32);
+        for sample in &state.samples {
+            out.put_u32(sample.data.len() as u32);
+        }
+    });
+    write_full_box(out, b"co64", 0, 0, |out| {
+        out.put_u32(offsets.len() as u32);
+        for offset in offsets {
+            out.put_u64(*offset);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VideoCodec;
+
+    fn avc_config() -> Bytes {
+        let mut config = vec![0x01, 0x64, 0x00, 0x1F, 0xFF, 0xE1, 0x00, 0x0A];
+        config.extend_from_slice(&[0x67, 0x64, 0x00, 0x1F, 0xAC, 0xD9, 0x40, 0x50, 0x05, 0xB9]);
+        config.extend_from_slice(&[0x01, 0x00, 0x02, 0x68, 0xEE]);
+        Bytes::from(config)
+    }
+
+    /// 查找嵌套 box，返回 box 内容（不处理 largesize）
+    fn find_box<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+        let mut offset = 0;
+        while offset + 8 <= data.len() {
+            let size = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+            let size = if size == 1 {
+                u64::from_be_bytes(data[offset + 8..offset + 16].try_into().unwrap()) as usize
+            } else {
+                size
+            };
+            if &data[offset + 4..offset + 8] == path[0] {
+                let body = &data[offset + 8..offset + size];
+                return if path.len() == 1 {
+                    Some(body)
+                } else {
+                    find_box(body, &path[1..])
+                };
+            }
+            offset += size;
+        }
+        None
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_progressive_mp4() {
+        let video = Fmp4Track::video(1, VideoCodec::H264, avc_config()).unwrap();
+        let audio = Fmp4Track::aac(2, Bytes::from_static(&[0x12, 0x10])).unwrap();
+        let mut writer = Mp4Writer::new(vec![video, audio]);
+
+        // 视频 25fps 两秒（自 10s 起），每秒一个关键帧；音频晚 100ms 开始
+        for frame in 0..50u64 {
+            writer
+                .push(
+                    1,
+                    Mp4Sample {
+                        data: Bytes::from(vec![0, 0, 0, 1, if frame % 25 == 0 { 0x65 } else { 0x41 }]),
+                        dts: 900_000 + frame * 3600,
+                        composition_offset: 0,
+                        is_sync: frame % 25 == 0,
+                    },
+                )
+                .unwrap();
+        }
+        for frame in 0..80u64 {
+            writer
+                .push(
+                    2,
+                    Mp4Sample {
+                        data: Bytes::from_static(&[0x21, 0x10]),
+                        dts: 441_000 + 4410 + frame * 1024,
+                        composition_offset: 0,
+                        is_sync: true,
+                    },
+                )
+                .unwrap();
+        }
+        let unknown = Mp4Sample {
+            data: Bytes::new(),
+            dts: 0,
+            composition_offset: 0,
+            is_sync: true,
+        };
+        assert!(writer.push(3, unknown).is_err());
+        assert_eq!(writer.sample_count(), 130);
+
+        let file = writer.finish();
+        assert_eq!(&file[4..8], b"ftyp");
+        let moov = find_box(&file, &[b"moov"]).unwrap();
+        let mvhd = find_box(moov, &[b"mvhd"]).unwrap();
+        assert_eq!(u32_at(mvhd, 12), MOVIE_TIMESCALE);
+        assert_eq!(u32_at(mvhd, 16), 2000);
+
+        // 视频轨道：stss 指向两个关键帧，co64 指向样本数据
+        let video_trak = find_box(moov, &[b"trak"]).unwrap();
+        let stbl = find_box(video_trak, &[b"mdia", b"minf", b"stbl"]).unwrap();
+        let stss = find_box(stbl, &[b"stss"]).unwrap();
+        assert_eq!((u32_at(stss, 4), u32_at(stss, 8), u32_at(stss, 12)), (2, 1, 26));
+        let stts = find_box(stbl, &[b"stts"]).unwrap();
+        assert_eq!((u32_at(stts, 4), u32_at(stts, 8), u32_at(stts, 12)), (1, 50, 3600));
+        let co64 = find_box(stbl, &[b"co64"]).unwrap();
+        assert_eq!(u32_at(co64, 4), 50);
+        let first = u64::from_be_bytes(co64[8..16].try_into().unwrap()) as usize;
+        assert_eq!(&file[first..first + 5], &[0, 0, 0, 1, 0x65]);
+        assert!(find_box(video_trak, &[b"edts"]).is_none());
+
+        // 音频轨道：空编辑延迟 100ms
+        let audio_offset = moov.windows(4).rposition(|w| w == b"trak").unwrap() - 4;
+        let audio_trak = find_box(&moov[audio_offset..], &[b"trak"]).unwrap();
+        let elst = find_box(audio_trak, &[b"edts", b"elst"]).unwrap();
+        assert_eq!((u32_at(elst, 4), u32_at(elst, 8)), (2, 100));
+        let stbl = find_box(audio_trak, &[b"mdia", b"minf", b"stbl"]).unwrap();
+        assert!(find_box(stbl, &[b"stss"]).is_none());
+        let co64 = find_box(stbl, &[b"co64"]).unwrap();
+        // 交错写入：首个音频样本位于若干视频帧之后
+        let first_audio = u64::from_be_bytes(co64[8..16].try_into().unwrap()) as usize;
+        assert!(first_audio > first + 5);
+        assert_eq!(&file[first_audio..first_audio + 2], &[0x21, 0x10]);
+    }
+}
@@ -1,5 +1,22 @@
-use crate::error::Result;
-use bytes::{BufMut, Bytes, BytesMut};
+use crate::error::{MediaError, Result};
+use crate::types::{AudioCodec, VideoCodec};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::HashMap;
+
+/// TS 包长度
+const TS_PACKET_SIZE: usize = 188;
+/// TS 包头长度
+const TS_HEADER_SIZE: usize = 4;
+/// PMT PID
+const PMT_PID: u16 = 0x1000;
+
+/// PMT 中的 stream_type
+pub const STREAM_TYPE_H264: u8 = 0x1B;
+pub const STREAM_TYPE_H265: u8 = 0x24;
+pub const STREAM_TYPE_AAC: u8 = 0x0F;
+/// G.711 私有 stream_type（GB28181/NVR 约定，HLS 播放器不支持）
+pub const STREAM_TYPE_G711A: u8 = 0x90;
+pub const STREAM_TYPE_G711U: u8 = 0x91;
 
 /// MPEG-TS 封装器
 pub struct TsMuxer {
@@ -7,6 +24,11 @@ pub struct TsMuxer {
     video_pid: u16,
     audio_pid: u16,
     pcr_pid: u16,
+    video_stream_type: u8,
+    /// 是否在 PMT 中声明视频流（纯音频流时为 false，PCR 改由音频 PID 承载）
+    video_enabled: bool,
+    /// 音频 stream_type；None 表示 PMT 中不声明音频
+    audio_stream_type: Option<u8>,
     continuity_counter_pat: u8,
     continuity_counter_pmt: u8,
     continuity_counter_video: u8,
@@ -20,6 +42,9 @@ impl TsMuxer {
             video_pid: 0x100,
             audio_pid: 0x101,
             pcr_pid: 0x100,
+            video_stream_type: STREAM_TYPE_H264,
+            video_enabled: true,
+            audio_stream_type: None,
             continuity_counter_pat: 0,
             continuity_counter_pmt: 0,
             continuity_counter_video: 0,
@@ -27,91 +52,133 @@ impl TsMuxer {
         }
     }
 
-    /// 生成 PAT (Program Association Table)
-    fn generate_pat(&mut self) -> Bytes {
-        let mut packet = BytesMut::with_capacity(188);
-        
-        // TS Header
-        packet.put_u8(0x47); // Sync byte
-        packet.put_u8(0x40); // Payload unit start indicator
-        packet.put_u8(0x00); // PID = 0 (PAT)
-        packet.put_u8(0x10 | (self.continuity_counter_pat & 0x0F));
-        self.continuity_counter_pat = (self.continuity_counter_pat + 1) & 0x0F;
-
-        // Pointer field
-        packet.put_u8(0x00);
-
-        // PAT
-        packet.put_u8(0x00); // Table ID
-        packet.put_u8(0xB0); // Section syntax indicator
-        packet.put_u8(0x0D); // Section length
-        packet.put_u16(0x0001); // Transport stream ID
-        packet.put_u8(0xC1); // Version 0, current
-        packet.put_u8(0x00); // Section number
-        packet.put_u8(0x00); // Last section number
-        packet.put_u16(0x0001); // Program number
-        packet.put_u16(0xE000 | 0x1000); // PMT PID = 0x1000
+    /// 设置视频编码（决定 PMT 中的 stream_type），变更后重新输出 PAT/PMT
+    pub fn set_video_codec(&mut self, codec: VideoCodec) -> Result<()> {
+        let stream_type = match codec {
+            VideoCodec::H264 => STREAM_TYPE_H264,
+            VideoCodec::H265 => STREAM_TYPE_H265,
+            other => {
+                return Err(MediaError::InvalidConfig(format!(
+                    "Video codec {} is not supported in MPEG-TS",
+                    other
+                )))
+            }
+        };
 
-        // CRC32 (simplified - should calculate actual CRC)
-        packet.put_u32(0x00000000);
+        if stream_type != self.video_stream_type {
+            self.video_stream_type = stream_type;
+            self.pat_pmt_sent = false;
+        }
+        self.set_video_enabled(true);
+        Ok(())
+    }
 
-        // Padding
-        while packet.len() < 188 {
-            packet.put_u8(0xFF);
+    /// 启用/禁用视频流；禁用后 PMT 只声明音频，PCR 随音频 PID 输出
+    pub fn set_video_enabled(&mut self, enabled: bool) {
+        if enabled != self.video_enabled {
+            self.video_enabled = enabled;
+            self.pcr_pid = if enabled { self.video_pid } else { self.audio_pid };
+            self.pat_pmt_sent = false;
         }
+    }
 
-        packet.freeze()
+    /// 是否声明视频流
+    pub fn video_enabled(&self) -> bool {
+        self.video_enabled
     }
 
-    /// 生成 PMT (Program Map Table)
-    fn generate_pmt(&mut self) -> Bytes {
-        let mut packet = BytesMut::with_capacity(188);
-        
-        // TS Header
-        packet.put_u8(0x47); // Sync byte
-        packet.put_u8(0x50); // Payload unit start indicator
-        packet.put_u8(0x00); // PID = 0x1000 (PMT)
-        packet.put_u8(0x10 | (self.continuity_counter_pmt & 0x0F));
-        self.continuity_counter_pmt = (self.continuity_counter_pmt + 1) & 0x0F;
+    /// 当前视频 stream_type
+    pub fn video_stream_type(&self) -> u8 {
+        self.video_stream_type
+    }
 
-        // Pointer field
-        packet.put_u8(0x00);
+    /// 设置音频编码（在 PMT 中声明音频流），变更后重新输出 PAT/PMT
+    pub fn set_audio_codec(&mut self, codec: AudioCodec) -> Result<()> {
+        let stream_type = match codec {
+            AudioCodec::AAC => STREAM_TYPE_AAC,
+            AudioCodec::G711A => STREAM_TYPE_G711A,
+            AudioCodec::G711U => STREAM_TYPE_G711U,
+            other => {
+                return Err(MediaError::InvalidConfig(format!(
+                    "Audio codec {:?} is not supported in MPEG-TS",
+                    other
+                )))
+            }
+        };
 
-        // PMT
-        packet.put_u8(0x02); // Table ID
-        packet.put_u8(0xB0); // Section syntax indicator
-        packet.put_u8(0x17); // Section length
-        packet.put_u16(0x0001); // Program number
-        packet.put_u8(0xC1); // Version 0, current
-        packet.put_u8(0x00); // Section number
-        packet.put_u8(0x00); // Last section number
-        packet.put_u16(0xE000 | self.pcr_pid); // PCR PID
+        if self.audio_stream_type != Some(stream_type) {
+            self.audio_stream_type = Some(stream_type);
+            self.pat_pmt_sent = false;
+        }
+        Ok(())
+    }
 
-        // Program info length
-        packet.put_u16(0xF000);
+    /// 当前音频 stream_type
+    pub fn audio_stream_type(&self) -> Option<u8> {
+        self.audio_stream_type
+    }
 
-        // Video stream (H.264)
-        packet.put_u8(0x1B); // Stream type (H.264)
-        packet.put_u16(0xE000 | self.video_pid);
-        packet.put_u16(0xF000); // ES info length
+    /// 在下一个 PES 前重新输出 PAT/PMT（每个 HLS 分片开头都需要）
+    pub fn force_pat_pmt(&mut self) {
+        self.pat_pmt_sent = false;
+    }
 
-        // Audio stream (AAC)
-        packet.put_u8(0x0F); // Stream type (AAC)
-        packet.put_u16(0xE000 | self.audio_pid);
-        packet.put_u16(0xF000); // ES info length
+    /// 生成 PAT (Program Association Table)
+    fn generate_pat(&mut self) -> Bytes {
+        let mut section = BytesMut::with_capacity(16);
+        section.put_u8(0x00); // Table ID
+        section.put_u16(0xB000 | 13); // Section syntax indicator + section length
+        section.put_u16(0x0001); // Transport stream ID
+        section.put_u8(0xC1); // Version 0, current
+        section.put_u8(0x00); // Section number
+        section.put_u8(0x00); // Last section number
+        section.put_u16(0x0001); // Program number
+        section.put_u16(0xE000 | PMT_PID); // PMT PID
+
+        let crc = crc32_mpeg2(&section);
+        section.put_u32(crc);
+
+        let packet = psi_packet(0x0000, self.continuity_counter_pat, &section);
+        self.continuity_counter_pat = (self.continuity_counter_pat + 1) & 0x0F;
+        packet
+    }
 
-        // CRC32
-        packet.put_u32(0x00000000);
+    /// 生成 PMT (Program Map Table)
+    fn generate_pmt(&mut self) -> Bytes {
+        let mut streams = Vec::with_capacity(2);
+        if self.video_enabled {
+            streams.push((self.video_stream_type, self.video_pid));
+        }
+        if let Some(audio_stream_type) = self.audio_stream_type {
+            streams.push((audio_stream_type, self.audio_pid));
+        }
 
-        // Padding
-        while packet.len() < 188 {
-            packet.put_u8(0xFF);
+        let section_length = 9 + 5 * streams.len() + 4;
+        let mut section = BytesMut::with_capacity(3 + section_length);
+        section.put_u8(0x02); // Table ID
+        section.put_u16(0xB000 | section_length as u16); // Section syntax indicator + section length
+        section.put_u16(0x0001); // Program number
+        section.put_u8(0xC1); // Version 0, current
+        section.put_u8(0x00); // Section number
+        section.put_u8(0x00); // Last section number
+        section.put_u16(0xE000 | self.pcr_pid); // PCR PID
+        section.put_u16(0xF000); // Program info length
+
+        for (stream_type, pid) in streams {
+            section.put_u8(stream_type);
+            section.put_u16(0xE000 | pid);
+            section.put_u16(0xF000); // ES info length
         }
 
-        packet.freeze()
+        let crc = crc32_mpeg2(&section);
+        section.put_u32(crc);
+
+        let packet = psi_packet(PMT_PID, self.continuity_counter_pmt, &section);
+        self.continuity_counter_pmt = (self.continuity_counter_pmt + 1) & 0x0F;
+        packet
     }
 
-    /// 封装 PES 包
+    /// 封装视频 PES 包（data 为 Annex B 格式的访问单元，时间戳为 90kHz）
     pub fn mux_video_pes(&mut self, data: &[u8], pts: u64, dts: u64, is_keyframe: bool) -> Result<Vec<Bytes>> {
         let mut packets = Vec::new();
 
@@ -122,78 +189,53 @@ impl TsMuxer {
             self.pat_pmt_sent = true;
         }
 
-        // 构造 PES header
-        let mut pes = BytesMut::new();
-        pes.put_slice(&[0x00, 0x00, 0x01]); // Packet start code
-        pes.put_u8(0xE0); // Stream ID (video)
-        
-        // PES packet length (0 = unbounded)
-        pes.put_u16(0);
-
-        // PES header flags
-        pes.put_u8(0x80); // Marker bits
-        pes.put_u8(0xC0); // PTS + DTS flags
-        pes.put_u8(10); // PES header length
+        // 构造 PES（视频 PES 长度可超过 65535，使用 0 表示不限长）
+        let mut pes = BytesMut::with_capacity(data.len() + 19);
+        write_pes_header(&mut pes, 0xE0, pts, dts, None);
+        pes.put_slice(data);
 
-        // PTS
-        pes.put_u8(0x31 | (((pts >> 30) & 0x07) << 1) as u8);
-        pes.put_u16((((pts >> 15) & 0x7FFF) << 1) as u16);
-        pes.put_u16((((pts) & 0x7FFF) << 1) as u16);
+        // 视频 PID 同时承载 PCR
+        let pcr = (self.pcr_pid == self.video_pid).then_some(dts);
+        packets.extend(packetize(
+            self.video_pid,
+            &mut self.continuity_counter_video,
+            &pes,
+            pcr,
+            is_keyframe,
+        ));
 
-        // DTS
-        pes.put_u8(0x11 | (((dts >> 30) & 0x07) << 1) as u8);
-        pes.put_u16((((dts >> 15) & 0x7FFF) << 1) as u16);
-        pes.put_u16((((dts) & 0x7FFF) << 1) as u16);
+        Ok(packets)
+    }
 
-        // PES data
-        pes.put_slice(data);
+    /// 封装音频 PES 包（AAC 为带 ADTS 头的帧，G.711 为原始样本；PTS 为 90kHz）
+    pub fn mux_audio_pes(&mut self, data: &[u8], pts: u64) -> Result<Vec<Bytes>> {
+        if self.audio_stream_type.is_none() {
+            return Err(MediaError::InvalidConfig(
+                "Audio codec not configured".to_string(),
+            ));
+        }
 
-        // 分割成 TS 包
-        let pes_data = pes.freeze();
-        let mut offset = 0;
-        let mut first_packet = true;
-
-        while offset < pes_data.len() {
-            let mut packet = BytesMut::with_capacity(188);
-            
-            // TS Header
-            packet.put_u8(0x47); // Sync byte
-            
-            let mut flags = 0x00;
-            if first_packet {
-                flags |= 0x40; // Payload unit start indicator
-                if is_keyframe {
-                    flags |= 0x20; // Adaptation field present
-                }
-            }
-            packet.put_u8(flags);
-            packet.put_u8((self.video_pid >> 8) as u8);
-            packet.put_u8((self.video_pid & 0xFF) as u8 | 0x10 | (self.continuity_counter_video & 0x0F));
-            self.continuity_counter_video = (self.continuity_counter_video + 1) & 0x0F;
-
-            // Adaptation field (for keyframe)
-            if first_packet && is_keyframe {
-                packet.put_u8(7); // Adaptation field length
-                packet.put_u8(0x50); // Random access indicator
-                for _ in 0..6 {
-                    packet.put_u8(0xFF); // Stuffing
-                }
-            }
+        let mut packets = Vec::new();
 
-            // Payload
-            let payload_size = 188 - packet.len();
-            let chunk_size = std::cmp::min(payload_size, pes_data.len() - offset);
-            packet.put_slice(&pes_data[offset..offset + chunk_size]);
-            offset += chunk_size;
+        if !self.pat_pmt_sent {
+            packets.push(self.generate_pat());
+            packets.push(self.generate_pmt());
+            self.pat_pmt_sent = true;
+        }
 
-            // Padding
-            while packet.len() < 188 {
-                packet.put_u8(0xFF);
-            }
+        let mut pes = BytesMut::with_capacity(data.len() + 14);
+        write_pes_header(&mut pes, 0xC0, pts, pts, Some(data.len()));
+        pes.put_slice(data);
 
-            packets.push(packet.freeze());
-            first_packet = false;
-        }
+        // 纯音频流由音频 PID 承载 PCR
+        let pcr = (self.pcr_pid == self.audio_pid).then_some(pts);
+        packets.extend(packetize(
+            self.audio_pid,
+            &mut self.continuity_counter_audio,
+            &pes,
+            pcr,
+            pcr.is_some(),
+        ));
 
         Ok(packets)
     }
@@ -214,6 +256,381 @@ impl Default for TsMuxer {
     }
 }
 
+/// 解封装得到的 PES 帧（时间戳为 90kHz）
+#[derive(Debug, Clone)]
+pub struct TsFrame {
+    pub pid: u16,
+    /// PMT 中声明的 stream_type
+    pub stream_type: u8,
+    pub pts: u64,
+    pub dts: u64,
+    /// PES 负载（视频为 Annex B，AAC 带 ADTS 头）
+    pub data: Bytes,
+    /// 首个 TS 包自适应字段中的随机访问标志
+    pub random_access: bool,
+}
+
+/// 单个 PID 的 PES 重组状态
+struct PesAssembler {
+    stream_type: u8,
+    data: BytesMut,
+    random_access: bool,
+}
+
+impl PesAssembler {
+    /// PES 带长度且已收齐时返回 true
+    fn is_complete(&self) -> bool {
+        if self.data.len() < 6 {
+            return false;
+        }
+        let packet_len = ((self.data[4] as usize) << 8) | self.data[5] as usize;
+        packet_len != 0 && self.data.len() >= 6 + packet_len
+    }
+
+    /// 取出并解析当前 PES
+    fn take_frame(&mut self, pid: u16) -> Option<TsFrame> {
+        let pes = self.data.split().freeze();
+        if pes.len() < 9 || pes[..3] != [0x00, 0x00, 0x01] {
+            return None;
+        }
+
+        let flags = pes[7] >> 6;
+        let header_end = 9 + pes[8] as usize;
+        if header_end > pes.len() || (flags & 0x02 != 0 && pes.len() < 14) || (flags == 0x03 && pes.len() < 19) {
+            return None;
+        }
+        let pts = if flags & 0x02 != 0 { read_timestamp(&pes[9..14]) } else { 0 };
+        let dts = if flags == 0x03 { read_timestamp(&pes[14..19]) } else { pts };
+
+        let packet_len = ((pes[4] as usize) << 8) | pes[5] as usize;
+        let end = if packet_len == 0 { pes.len() } else { (6 + packet_len).min(pes.len()) };
+
+        Some(TsFrame {
+            pid,
+            stream_type: self.stream_type,
+            pts,
+            dts,
+            data: pes.slice(header_end..end),
+            random_access: self.random_access,
+        })
+    }
+}
+
+/// MPEG-TS 解封装器：解析 PAT/PMT，按 PID 重组 PES
+///
+/// 视频 PES 通常不带长度，在同一 PID 的下一个 PES 开始时输出；
+/// 带长度的 PES（音频）收齐即输出。
+#[derive(Default)]
+pub struct TsDemuxer {
+    pending: BytesMut,
+    pmt_pid: Option<u16>,
+    streams: HashMap<u16, PesAssembler>,
+}
+
+impl TsDemuxer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 输入任意切分的 TS 数据，返回已完整的帧
+    pub fn push(&mut self, data: &[u8]) -> Vec<TsFrame> {
+        self.pending.extend_from_slice(data);
+        let mut frames = Vec::new();
+
+        loop {
+            // 重新同步到 0x47
+            match self.pending.iter().position(|&byte| byte == 0x47) {
+                Some(0) => {}
+                Some(offset) => self.pending.advance(offset),
+                None => {
+                    self.pending.clear();
+                    break;
+                }
+            }
+            if self.pending.len() < TS_PACKET_SIZE {
+                break;
+            }
+
+            let packet = self.pending.split_to(TS_PACKET_SIZE);
+            self.parse_packet(&packet, &mut frames);
+        }
+
+        frames
+    }
+
+    /// 输出各 PID 中未结束的 PES（流结束时调用）
+    pub fn flush(&mut self) -> Vec<TsFrame> {
+        let mut frames: Vec<TsFrame> = self
+            .streams
+            .iter_mut()
+            .filter(|(_, stream)| !stream.data.is_empty())
+            .filter_map(|(pid, stream)| stream.take_frame(*pid))
+            .collect();
+        frames.sort_by_key(|frame| frame.dts);
+        frames
+    }
+
+    /// PMT 中声明的 (PID, stream_type)
+    pub fn streams(&self) -> Vec<(u16, u8)> {
+        let mut streams: Vec<(u16, u8)> = self
+            .streams
+            .iter()
+            .map(|(pid, stream)| (*pid, stream.stream_type))
+            .collect();
+        streams.sort();
+        streams
+    }
+
+    fn parse_packet(&mut self, packet: &[u8], frames: &mut Vec<TsFrame>) {
+        let payload_unit_start = packet[1] & 0x40 != 0;
+        let pid = ((packet[1] as u16 & 0x1F) << 8) | packet[2] as u16;
+        let adaptation_control = (packet[3] >> 4) & 0x03;
+
+        let mut offset = TS_HEADER_SIZE;
+        let mut random_access = false;
+        if adaptation_control & 0x02 != 0 {
+            let adaptation_len = packet[4] as usize;
+            random_access = adaptation_len > 0 && packet[5] & 0x40 != 0;
+            offset += 1 + adaptation_len;
+        }
+        if adaptation_control & 0x01 == 0 || offset >= TS_PACKET_SIZE {
+            return;
+        }
+        let payload = &packet[offset..];
+
+        if pid == 0x0000 {
+            if payload_unit_start {
+                self.parse_pat(payload);
+            }
+            return;
+        }
+        if Some(pid) == self.pmt_pid {
+            if payload_unit_start {
+                self.parse_pmt(payload);
+            }
+            return;
+        }
+
+        let Some(stream) = self.streams.get_mut(&pid) else {
+            return;
+        };
+        if payload_unit_start {
+            if !stream.data.is_empty() {
+                frames.extend(stream.take_frame(pid));
+            }
+            stream.random_access = random_access;
+        } else if stream.data.is_empty() {
+            // 丢失 PES 开头，等待下一个 PES
+            return;
+        }
+
+        stream.data.extend_from_slice(payload);
+        if stream.is_complete() {
+            frames.extend(stream.take_frame(pid));
+        }
+    }
+
+    /// 取出 PSI section（跳过 pointer field），长度不足时返回 None
+    fn psi_section(payload: &[u8], table_id: u8) -> Option<&[u8]> {
+        let start = 1 + *payload.first()? as usize;
+        let section = payload.get(start..)?;
+        if section.len() < 3 || section[0] != table_id {
+            return None;
+        }
+        let section_len = (((section[1] as usize) & 0x0F) << 8) | section[2] as usize;
+        // 去掉 CRC32
+        section.get(..3 + section_len)?.get(..(3 + section_len).checked_sub(4)?)
+    }
+
+    fn parse_pat(&mut self, payload: &[u8]) {
+        let Some(section) = Self::psi_section(payload, 0x00) else {
+            return;
+        };
+        for program in section.get(8..).unwrap_or_default().chunks_exact(4) {
+            let program_number = ((program[0] as u16) << 8) | program[1] as u16;
+            if program_number != 0 {
+                self.pmt_pid = Some(((program[2] as u16 & 0x1F) << 8) | program[3] as u16);
+                return;
+            }
+        }
+    }
+
+    fn parse_pmt(&mut self, payload: &[u8]) {
+        let Some(section) = Self::psi_section(payload, 0x02) else {
+            return;
+        };
+        if section.len() < 12 {
+            return;
+        }
+
+        let program_info_len = (((section[10] as usize) & 0x0F) << 8) | section[11] as usize;
+        let mut offset = 12 + program_info_len;
+        while offset + 5 <= section.len() {
+            let stream_type = section[offset];
+            let pid = ((section[offset + 1] as u16 & 0x1F) << 8) | section[offset + 2] as u16;
+            let es_info_len = (((section[offset + 3] as usize) & 0x0F) << 8) | section[offset + 4] as usize;
+
+            let stream = self.streams.entry(pid).or_insert_with(|| PesAssembler {
+                stream_type,
+                data: BytesMut::new(),
+                random_access: false,
+            });
+            if stream.stream_type != stream_type {
+                stream.stream_type = stream_type;
+                stream.data.clear();
+            }
+
+            offset += 5 + es_info_len;
+        }
+    }
+}
+
+/// 读取 33 位 PES 时间戳
+fn read_timestamp(data: &[u8]) -> u64 {
+    (((data[0] as u64 >> 1) & 0x07) << 30)
+        | ((((data[1] as u64) << 8 | data[2] as u64) >> 1) << 15)
+        | (((data[3] as u64) << 8 | data[4] as u64) >> 1)
+}
+
+/// 写入 PES 头；`payload_len` 为 None 时 PES_packet_length 置 0（仅视频允许）
+fn write_pes_header(buf: &mut BytesMut, stream_id: u8, pts: u64, dts: u64, payload_len: Option<usize>) {
+    let with_dts = pts != dts;
+    let header_data_len: usize = if with_dts { 10 } else { 5 };
+
+    buf.put_slice(&[0x00, 0x00, 0x01]); // Packet start code
+    buf.put_u8(stream_id);
+
+    let packet_len = payload_len
+        .map(|len| 3 + header_data_len + len)
+        .filter(|len| *len <= u16::MAX as usize)
+        .unwrap_or(0);
+    buf.put_u16(packet_len as u16);
+
+    buf.put_u8(0x80); // Marker bits
+    buf.put_u8(if with_dts { 0xC0 } else { 0x80 }); // PTS (+ DTS) flags
+    buf.put_u8(header_data_len as u8);
+
+    if with_dts {
+        write_timestamp(buf, 0x3, pts);
+        write_timestamp(buf, 0x1, dts);
+    } else {
+        write_timestamp(buf, 0x2, pts);
+    }
+}
+
+/// 写入 33 位 PES 时间戳（带 marker bit）
+fn write_timestamp(buf: &mut BytesMut, prefix: u8, ts: u64) {
+    buf.put_u8((prefix << 4) | ((((ts >> 30) & 0x07) as u8) << 1) | 0x01);
+    buf.put_u16(((((ts >> 15) & 0x7FFF) as u16) << 1) | 0x01);
+    buf.put_u16((((ts & 0x7FFF) as u16) << 1) | 0x01);
+}
+
+/// 将 PES 切分为 TS 包；首包可携带 PCR 和随机访问标志，末包用自适应字段填充
+fn packetize(pid: u16, cc: &mut u8, pes: &[u8], pcr: Option<u64>, random_access: bool) -> Vec<Bytes> {
+    let mut packets = Vec::with_capacity(pes.len() / 184 + 1);
+    let mut offset = 0;
+    let mut first_packet = true;
+
+    while offset < pes.len() {
+        // 自适应字段内容（不含长度字节）
+        let mut adaptation: Option<Vec<u8>> = None;
+        if first_packet && (pcr.is_some() || random_access) {
+            let mut field = Vec::with_capacity(7);
+            let mut flags = 0u8;
+            if random_access {
+                flags |= 0x40; // Random access indicator
+            }
+            if pcr.is_some() {
+                flags |= 0x10; // PCR flag
+            }
+            field.push(flags);
+            if let Some(pcr) = pcr {
+                let base = pcr & 0x1_FFFF_FFFF;
+                field.push((base >> 25) as u8);
+                field.push((base >> 17) as u8);
+                field.push((base >> 9) as u8);
+                field.push((base >> 1) as u8);
+                field.push((((base & 0x01) as u8) << 7) | 0x7E);
+                field.push(0x00);
+            }
+            adaptation = Some(field);
+        }
+
+        let remaining = pes.len() - offset;
+        let adaptation_len = adaptation.as_ref().map_or(0, |field| field.len() + 1);
+        let available = TS_PACKET_SIZE - TS_HEADER_SIZE - adaptation_len;
+        if remaining < available {
+            let stuffing = available - remaining;
+            match adaptation.as_mut() {
+                Some(field) => field.extend(std::iter::repeat_n(0xFF, stuffing)),
+                None if stuffing == 1 => adaptation = Some(Vec::new()),
+                None => {
+                    let mut field = vec![0x00];
+                    field.extend(std::iter::repeat_n(0xFF, stuffing - 2));
+                    adaptation = Some(field);
+                }
+            }
+        }
+
+        let mut packet = BytesMut::with_capacity(TS_PACKET_SIZE);
+        packet.put_u8(0x47); // Sync byte
+        let pusi = if first_packet { 0x40 } else { 0x00 };
+        packet.put_u8(pusi | ((pid >> 8) as u8 & 0x1F));
+        packet.put_u8((pid & 0xFF) as u8);
+        let adaptation_control = if adaptation.is_some() { 0x30 } else { 0x10 };
+        packet.put_u8(adaptation_control | (*cc & 0x0F));
+        *cc = (*cc + 1) & 0x0F;
+
+        if let Some(field) = &adaptation {
+            packet.put_u8(field.len() as u8);
+            packet.put_slice(field);
+        }
+
+        let chunk_size = std::cmp::min(TS_PACKET_SIZE - packet.len(), remaining);
+        packet.put_slice(&pes[offset..offset + chunk_size]);
+        offset += chunk_size;
+
+        packets.push(packet.freeze());
+        first_packet = false;
+    }
+
+    packets
+}
+
+/// 生成承载 PSI 表（PAT/PMT）的 TS 包
+fn psi_packet(pid: u16, cc: u8, section: &[u8]) -> Bytes {
+    let mut packet = BytesMut::with_capacity(TS_PACKET_SIZE);
+    packet.put_u8(0x47); // Sync byte
+    packet.put_u8(0x40 | ((pid >> 8) as u8 & 0x1F)); // Payload unit start indicator
+    packet.put_u8((pid & 0xFF) as u8);
+    packet.put_u8(0x10 | (cc & 0x0F));
+    packet.put_u8(0x00); // Pointer field
+    packet.put_slice(section);
+
+    // Padding
+    while packet.len() < TS_PACKET_SIZE {
+        packet.put_u8(0xFF);
+    }
+
+    packet.freeze()
+}
+
+/// MPEG-2 CRC32（多项式 0x04C11DB7，无反转）
+fn crc32_mpeg2(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= (*byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,6 +672,163 @@ mod tests {
         assert!(muxer.pat_pmt_sent);
     }
 
+    #[test]
+    fn test_ts_packet_headers() {
+        let mut muxer = TsMuxer::new();
+        let data = vec![0xAB; 1000];
+
+        let packets = muxer.mux_video_pes(&data, 90000, 87000, true).unwrap();
+        for packet in &packets {
+            assert_eq!(packet.len(), 188);
+            assert_eq!(packet[0], 0x47);
+        }
+
+        // PAT / PMT PID
+        assert_eq!(((packets[0][1] as u16 & 0x1F) << 8) | packets[0][2] as u16, 0);
+        assert_eq!(((packets[1][1] as u16 & 0x1F) << 8) | packets[1][2] as u16, 0x1000);
+
+        // 首个视频包：PUSI + PID 0x100 + 自适应字段（RAI + PCR）
+        let first = &packets[2];
+        assert_eq!(first[1], 0x41);
+        assert_eq!(first[2], 0x00);
+        assert_eq!(first[3] & 0x30, 0x30);
+        assert_eq!(first[5] & 0x50, 0x50);
+
+        // 负载总量等于 PES 长度（19 字节头 + 数据）
+        let payload: usize = packets[2..]
+            .iter()
+            .map(|p| {
+                let af_len = if p[3] & 0x20 != 0 { p[4] as usize + 1 } else { 0 };
+                188 - 4 - af_len
+            })
+            .sum();
+        assert_eq!(payload, 19 + data.len());
+    }
+
+    #[test]
+    fn test_psi_crc() {
+        // 对包含 CRC 的完整 section 再次计算 CRC 应得 0
+        let mut muxer = TsMuxer::new();
+        let pat = muxer.generate_pat();
+        assert_eq!(crc32_mpeg2(&pat[5..5 + 16]), 0);
+
+        let pmt = muxer.generate_pmt();
+        let section_len = (((pmt[6] as usize) & 0x0F) << 8) | pmt[7] as usize;
+        assert_eq!(crc32_mpeg2(&pmt[5..5 + 3 + section_len]), 0);
+    }
+
+    #[test]
+    fn test_h265_stream_type() {
+        let mut muxer = TsMuxer::new();
+        muxer.mux_video_pes(&[0, 0, 0, 1, 0x26], 0, 0, true).unwrap();
+
+        muxer.set_video_codec(VideoCodec::H265).unwrap();
+        assert_eq!(muxer.video_stream_type(), STREAM_TYPE_H265);
+        assert!(!muxer.pat_pmt_sent);
+
+        let packets = muxer.mux_video_pes(&[0, 0, 0, 1, 0x26], 0, 0, true).unwrap();
+        // PMT 第一个 ES 条目的 stream_type
+        assert_eq!(packets[1][17], STREAM_TYPE_H265);
+
+        assert!(muxer.set_video_codec(VideoCodec::AV1).is_err());
+    }
+
+    #[test]
+    fn test_mux_audio_pes() {
+        let mut muxer = TsMuxer::new();
+        assert!(muxer.mux_audio_pes(&[0xFF, 0xF1], 0).is_err());
+
+        muxer.set_audio_codec(AudioCodec::AAC).unwrap();
+        let frame = vec![0x11; 300];
+        let packets = muxer.mux_audio_pes(&frame, 180000).unwrap();
+
+        // PMT 声明视频 + AAC 音频
+        assert_eq!(packets[1][17], STREAM_TYPE_H264);
+        assert_eq!(packets[1][22], STREAM_TYPE_AAC);
+
+        // 音频 PES：PID 0x101，stream id 0xC0，PTS only，带长度
+        let first = &packets[2];
+        assert_eq!(first[1], 0x41);
+        assert_eq!(first[2], 0x01);
+        let pes = &first[4..];
+        assert_eq!(&pes[..4], &[0x00, 0x00, 0x01, 0xC0]);
+        assert_eq!(((pes[4] as usize) << 8) | pes[5] as usize, 3 + 5 + frame.len());
+        assert_eq!(pes[7], 0x80);
+
+        // PTS 解码
+        let pts = (((pes[9] as u64 >> 1) & 0x07) << 30)
+            | ((((pes[10] as u64) << 8 | pes[11] as u64) >> 1) << 15)
+            | (((pes[12] as u64) << 8 | pes[13] as u64) >> 1);
+        assert_eq!(pts, 180000);
+
+        assert!(muxer.set_audio_codec(AudioCodec::Opus).is_err());
+    }
+
+    #[test]
+    fn test_audio_only() {
+        let mut muxer = TsMuxer::new();
+        muxer.set_audio_codec(AudioCodec::G711A).unwrap();
+        muxer.set_video_enabled(false);
+
+        let packets = muxer.mux_audio_pes(&[0xD5; 160], 900).unwrap();
+        let pmt = &packets[1];
+        // PCR PID 指向音频，且只声明一个 G.711 流
+        assert_eq!(((pmt[13] as u16 & 0x1F) << 8) | pmt[14] as u16, 0x101);
+        assert_eq!(pmt[17], STREAM_TYPE_G711A);
+        assert_eq!(pmt[7], 9 + 5 + 4);
+
+        // 音频首包携带 PCR
+        let audio = &packets[2];
+        assert_eq!(audio[3] & 0x30, 0x30);
+        assert_eq!(audio[5] & 0x10, 0x10);
+
+        // 重新设置视频编码后恢复视频
+        muxer.set_video_codec(VideoCodec::H264).unwrap();
+        assert!(muxer.video_enabled());
+    }
+
+    #[test]
+    fn test_demux_roundtrip() {
+        let mut muxer = TsMuxer::new();
+        muxer.set_audio_codec(AudioCodec::AAC).unwrap();
+
+        let keyframe = [&[0u8, 0, 0, 1, 0x67, 0x42][..], &[0, 0, 0, 1, 0x65], &[0x88; 500]].concat();
+        let mut ts = Vec::new();
+        for packet in muxer.mux_video_pes(&keyframe, 93600, 90000, true).unwrap() {
+            ts.extend_from_slice(&packet);
+        }
+        for packet in muxer.mux_audio_pes(&[0xFF, 0xF1, 0x50, 0x80, 0x02, 0x1F, 0xFC, 0x21], 91000).unwrap() {
+            ts.extend_from_slice(&packet);
+        }
+        for packet in muxer.mux_video_pes(&[0, 0, 0, 1, 0x41, 0x9A], 97200, 93600, false).unwrap() {
+            ts.extend_from_slice(&packet);
+        }
+
+        let mut demuxer = TsDemuxer::new();
+        // 任意切分（以 SRT 负载 1316 字节为例）且前面带有垃圾数据
+        let mut frames = demuxer.push(&[0x00, 0x12]);
+        for chunk in ts.chunks(1316) {
+            frames.extend(demuxer.push(chunk));
+        }
+        assert_eq!(demuxer.streams(), vec![(0x100, STREAM_TYPE_H264), (0x101, STREAM_TYPE_AAC)]);
+
+        // 音频带长度立即输出；第一个视频帧在下一个视频 PES 开始时输出
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].stream_type, STREAM_TYPE_AAC);
+        assert_eq!(frames[0].pts, 91000);
+        assert_eq!(frames[0].data.len(), 8);
+
+        assert_eq!(frames[1].stream_type, STREAM_TYPE_H264);
+        assert_eq!((frames[1].pts, frames[1].dts), (93600, 90000));
+        assert!(frames[1].random_access);
+        assert_eq!(frames[1].data.as_ref(), keyframe.as_slice());
+
+        let rest = demuxer.flush();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].data.as_ref(), &[0, 0, 0, 1, 0x41, 0x9A]);
+        assert!(!rest[0].random_access);
+    }
+
     #[test]
     fn test_reset() {
         let mut muxer = TsMuxer::new();
@@ -0,0 +1,557 @@
+use crate::codec::{is_hevc_annexb, is_hevc_irap, split_annexb};
+use crate::error::{MediaError, Result};
+use crate::types::{KeyframeInfo, StreamId};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[cfg(feature = "software-decoder")]
+pub mod software;
+
+#[cfg(feature = "software-decoder")]
+pub use software::{SoftwareDecoder, SoftwareDecoderConfig};
+
+/// 每个流缓存的解码快照规格数（尺寸 / 质量组合）
+const MAX_DECODED_VARIANTS: usize = 8;
+
+/// Snapshot 模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotMode {
+    /// 自动选择（配置了解码器时优先 decode，失败降级到 keyframe；否则仅 keyframe）
+    Auto,
+    /// 仅 Keyframe 快照（低延迟、低成本）
+    Keyframe,
+    /// Decode 快照（高质量、可缩放/水印）
+    Decode,
+}
+
+impl Default for SnapshotMode {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+/// 缩略图规格（保持宽高比，缩放到不超过该尺寸）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailSize {
+    Small,
+    Medium,
+    Large,
+    /// 原始分辨率
+    Original,
+}
+
+impl ThumbnailSize {
+    /// 最大宽高（Original 为 None）
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match self {
+            Self::Small => Some((320, 180)),
+            Self::Medium => Some((640, 360)),
+            Self::Large => Some((1280, 720)),
+            Self::Original => None,
+        }
+    }
+}
+
+/// Snapshot 请求
+#[derive(Debug, Clone)]
+pub struct SnapshotRequest {
+    pub stream_id: StreamId,
+    pub mode: SnapshotMode,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// JPEG 质量（1-100），None 使用解码器默认值
+    pub quality: Option<u8>,
+}
+
+/// HTTP 快照查询参数：`?size=small|medium|large|original&width=&height=&quality=`
+///
+/// 显式的 width / height 优先于 size。
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SnapshotQuery {
+    pub size: Option<ThumbnailSize>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub quality: Option<u8>,
+}
+
+impl SnapshotQuery {
+    pub fn into_request(self, stream_id: StreamId, mode: SnapshotMode) -> SnapshotRequest {
+        let (width, height) = match (self.width, self.height) {
+            (None, None) => self
+                .size
+                .and_then(|size| size.dimensions())
+                .map(|(width, height)| (Some(width), Some(height)))
+                .unwrap_or((None, None)),
+            dimensions => dimensions,
+        };
+        SnapshotRequest {
+            stream_id,
+            mode,
+            width,
+            height,
+            quality: self.quality,
+        }
+    }
+}
+
+/// Snapshot 结果
+#[derive(Debug, Clone)]
+pub struct SnapshotResult {
+    pub data: Bytes,
+    pub mode_used: SnapshotMode,
+    pub timestamp: DateTime<Utc>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl SnapshotResult {
+    /// HTTP Content-Type：decode 快照为 JPEG，keyframe 快照为原始码流
+    pub fn content_type(&self) -> &'static str {
+        match self.mode_used {
+            SnapshotMode::Decode => "image/jpeg",
+            _ => "application/octet-stream",
+        }
+    }
+}
+
+/// Snapshot 编排器（协议无关）
+/// 
+/// 职责：
+/// 1. 管理 keyframe cache
+/// 2. 提供 keyframe/decode 双模式 snapshot，decode 结果按关键帧时间戳缓存
+/// 3. 实现 auto 模式的降级策略
+pub struct SnapshotOrchestrator {
+    keyframe_dir: PathBuf,
+    cache: Arc<RwLock<SnapshotCache>>,
+    decoder: Option<Arc<dyn SnapshotDecoder>>,
+}
+
+impl SnapshotOrchestrator {
+    pub fn new(keyframe_dir: PathBuf) -> Self {
+        Self {
+            keyframe_dir,
+            cache: Arc::new(RwLock::new(SnapshotCache::new())),
+            decoder: None,
+        }
+    }
+
+    pub fn with_decoder(mut self, decoder: Arc<dyn SnapshotDecoder>) -> Self {
+        self.decoder = Some(decoder);
+        self
+    }
+
+    /// 处理视频样本并提取关键帧
+    pub async fn process_keyframe(
+        &self,
+        stream_id: &StreamId,
+        data: &[u8],
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<KeyframeInfo>> {
+        if !Self::is_keyframe(data) {
+            return Ok(None);
+        }
+
+        let keyframe_path = self.keyframe_path(stream_id, timestamp, is_hevc_annexb(data));
+        if let Some(parent) = keyframe_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::write(&keyframe_path, data).await?;
+
+        let info = KeyframeInfo {
+            stream_id: stream_id.clone(),
+            file_path: keyframe_path.to_string_lossy().to_string(),
+            timestamp,
+            size: data.len(),
+        };
+
+        let mut cache = self.cache.write().await;
+        cache.update(stream_id.clone(), info.clone());
+
+        Ok(Some(info))
+    }
+
+    /// 获取 snapshot
+    pub async fn get_snapshot(&self, req: SnapshotRequest) -> Result<SnapshotResult> {
+        match req.mode {
+            SnapshotMode::Keyframe => self.get_keyframe_snapshot(&req.stream_id).await,
+            SnapshotMode::Decode => self.get_decode_snapshot(&req).await,
+            SnapshotMode::Auto if self.decoder.is_some() => {
+                // 优先输出可直接显示的 JPEG，解码失败则降级到 keyframe
+                match self.get_decode_snapshot(&req).await {
+                    Ok(result) => Ok(result),
+                    Err(e) => {
+                        tracing::debug!("Decode snapshot failed, falling back to keyframe: {}", e);
+                        self.get_keyframe_snapshot(&req.stream_id).await
+                    }
+                }
+            }
+            SnapshotMode::Auto => self.get_keyframe_snapshot(&req.stream_id).await,
+        }
+    }
+
+    async fn get_keyframe_snapshot(&self, stream_id: &StreamId) -> Result<SnapshotResult> {
+        let cache = self.cache.read().await;
+        let info = cache
+            .get(stream_id)
+            .ok_or_else(|| MediaError::SnapshotNotAvailable(stream_id.to_string()))?;
+
+        let data = tokio::fs::read(&info.file_path).await.map_err(|e| {
+            MediaError::SnapshotNotAvailable(format!("Failed to read keyframe: {}", e))
+        })?;
+
+        Ok(SnapshotResult {
+            data: Bytes::from(data),
+            mode_used: SnapshotMode::Keyframe,
+            timestamp: info.timestamp,
+            width: None,
+            height: None,
+        })
+    }
+
+    async fn get_decode_snapshot(&self, req: &SnapshotRequest) -> Result<SnapshotResult> {
+        let decoder = self.decoder.as_ref().ok_or_else(|| {
+            MediaError::SnapshotNotAvailable("Decoder not configured".to_string())
+        })?;
+
+        // 先获取 keyframe 作为解码源
+        let (file_path, timestamp) = {
+            let cache = self.cache.read().await;
+            let info = cache
+                .get(&req.stream_id)
+                .ok_or_else(|| MediaError::SnapshotNotAvailable(req.stream_id.to_string()))?;
+            (info.file_path.clone(), info.timestamp)
+        };
+
+        // 同一关键帧、同一规格只解码一次
+        let key = DecodeKey {
+            width: req.width,
+            height: req.height,
+            quality: req.quality,
+        };
+        if let Some(data) = self.cache.read().await.get_decoded(&req.stream_id, timestamp, &key) {
+            return Ok(SnapshotResult {
+                data,
+                mode_used: SnapshotMode::Decode,
+                timestamp,
+                width: req.width,
+                height: req.height,
+            });
+        }
+
+        let keyframe_data = tokio::fs::read(&file_path).await.map_err(|e| {
+            MediaError::SnapshotNotAvailable(format!("Failed to read keyframe: {}", e))
+        })?;
+
+        // 解码并生成 JPEG
+        let decoded = decoder
+            .decode(&keyframe_data, req.width, req.height, req.quality)
+            .await?;
+        self.cache
+            .write()
+            .await
+            .insert_decoded(&req.stream_id, timestamp, key, decoded.clone());
+
+        Ok(SnapshotResult {
+            data: decoded,
+            mode_used: SnapshotMode::Decode,
+            timestamp,
+            width: req.width,
+            height: req.height,
+        })
+    }
+
+    /// 关键帧文件路径，扩展名随编码（`.h264` / `.h265`）
+    fn keyframe_path(&self, stream_id: &StreamId, timestamp: DateTime<Utc>, hevc: bool) -> PathBuf {
+        let ts_millis = timestamp.timestamp_millis();
+        let extension = if hevc { "h265" } else { "h264" };
+        self.keyframe_dir
+            .join(stream_id.as_str())
+            .join(format!("{}.{}", ts_millis, extension))
+    }
+
+    /// H.264 IDR（NALU 类型 5）或 H.265 IRAP（BLA / IDR / CRA，NALU 类型 16-21）
+    fn is_keyframe(data: &[u8]) -> bool {
+        split_annexb(data)
+            .into_iter()
+            .any(|nalu| nalu[0] & 0x1F == 5 || is_hevc_irap(nalu))
+    }
+}
+
+/// 解码快照规格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DecodeKey {
+    width: Option<u32>,
+    height: Option<u32>,
+    quality: Option<u8>,
+}
+
+/// 某个关键帧的解码结果
+struct DecodedEntry {
+    timestamp: DateTime<Utc>,
+    variants: Vec<(DecodeKey, Bytes)>,
+}
+
+/// Snapshot 缓存
+struct SnapshotCache {
+    latest: HashMap<StreamId, KeyframeInfo>,
+    /// 最新关键帧的解码结果，关键帧更新后失效
+    decoded: HashMap<StreamId, DecodedEntry>,
+}
+
+impl SnapshotCache {
+    fn new() -> Self {
+        Self {
+            latest: HashMap::new(),
+            decoded: HashMap::new(),
+        }
+    }
+
+    fn update(&mut self, stream_id: StreamId, info: KeyframeInfo) {
+        self.decoded.remove(&stream_id);
+        self.latest.insert(stream_id, info);
+    }
+
+    fn get(&self, stream_id: &StreamId) -> Option<&KeyframeInfo> {
+        self.latest.get(stream_id)
+    }
+
+    fn get_decoded(&self, stream_id: &StreamId, timestamp: DateTime<Utc>, key: &DecodeKey) -> Option<Bytes> {
+        let entry = self.decoded.get(stream_id).filter(|entry| entry.timestamp == timestamp)?;
+        entry
+            .variants
+            .iter()
+            .find(|(variant, _)| variant == key)
+            .map(|(_, data)| data.clone())
+    }
+
+    fn insert_decoded(&mut self, stream_id: &StreamId, timestamp: DateTime<Utc>, key: DecodeKey, data: Bytes) {
+        let entry = self.decoded.entry(stream_id.clone()).or_insert_with(|| DecodedEntry {
+            timestamp,
+            variants: Vec::new(),
+        });
+        if entry.timestamp != timestamp {
+            // 解码期间关键帧已更新，只保留较新的结果
+            if entry.timestamp > timestamp {
+                return;
+            }
+            entry.timestamp = timestamp;
+            entry.variants.clear();
+        }
+        if entry.variants.len() >= MAX_DECODED_VARIANTS {
+            entry.variants.remove(0);
+        }
+        entry.variants.push((key, data));
+    }
+}
+
+/// Snapshot 解码器接口（可扩展）
+#[async_trait::async_trait]
+pub trait SnapshotDecoder: Send + Sync {
+    /// 解码关键帧（Annex B）并编码为图片；宽高为缩放上限，`quality` 为 JPEG 质量
+    async fn decode(
+        &self,
+        h264_data: &[u8],
+        width: Option<u32>,
+        height: Option<u32>,
+        quality: Option<u8>,
+    ) -> Result<Bytes>;
+}
+
+/// Stub 解码器（用于测试和占位）
+pub struct StubDecoder;
+
+#[async_trait::async_trait]
+impl SnapshotDecoder for StubDecoder {
+    async fn decode(
+        &self,
+        h264_data: &[u8],
+        _width: Option<u32>,
+        _height: Option<u32>,
+        _quality: Option<u8>,
+    ) -> Result<Bytes> {
+        // Stub: 直接返回原始数据
+        // 需要 JPEG 时启用 software-decoder 特性并使用 SoftwareDecoder
+        Ok(Bytes::copy_from_slice(h264_data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_snapshot_orchestrator_keyframe() {
+        let temp_dir = tempdir().unwrap();
+        let orchestrator = SnapshotOrchestrator::new(temp_dir.path().to_path_buf());
+
+        let stream_id = StreamId::new("test", "stream1");
+        let timestamp = Utc::now();
+
+        // 构造包含 IDR NALU 的数据
+        let mut h264_data = Vec::new();
+        h264_data.extend_from_slice(&[0, 0, 0, 1, 0x67]); // SPS
+        h264_data.extend_from_slice(&[0, 0, 0, 1, 0x68]); // PPS
+        h264_data.extend_from_slice(&[0, 0, 0, 1, 0x65]); // IDR (type 5)
+        h264_data.extend_from_slice(&[0xAA; 100]);
+
+        let result = orchestrator
+            .process_keyframe(&stream_id, &h264_data, timestamp)
+            .await
+            .unwrap();
+
+        assert!(result.unwrap().file_path.ends_with(".h264"));
+
+        // H.265（VPS + IDR_W_RADL）使用 .h265 扩展名
+        let hevc_data = [0, 0, 0, 1, 0x40, 0x01, 0x0C, 0, 0, 0, 1, 0x26, 0x01, 0xAF];
+        let hevc = orchestrator
+            .process_keyframe(&StreamId::new("test", "stream2"), &hevc_data, timestamp)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(hevc.file_path.ends_with(".h265"));
+
+        let req = SnapshotRequest {
+            stream_id: stream_id.clone(),
+            mode: SnapshotMode::Keyframe,
+            width: None,
+            height: None,
+            quality: None,
+        };
+
+        let snapshot = orchestrator.get_snapshot(req).await.unwrap();
+        assert_eq!(snapshot.mode_used, SnapshotMode::Keyframe);
+        assert!(!snapshot.data.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_orchestrator_auto_fallback() {
+        let temp_dir = tempdir().unwrap();
+        let orchestrator = SnapshotOrchestrator::new(temp_dir.path().to_path_buf())
+            .with_decoder(Arc::new(StubDecoder));
+
+        let stream_id = StreamId::new("test", "stream1");
+        let timestamp = Utc::now();
+
+        let mut h264_data = Vec::new();
+        h264_data.extend_from_slice(&[0, 0, 0, 1, 0x65]); // IDR
+        h264_data.extend_from_slice(&[0xBB; 50]);
+
+        orchestrator
+            .process_keyframe(&stream_id, &h264_data, timestamp)
+            .await
+            .unwrap();
+
+        let req = SnapshotRequest {
+            stream_id: stream_id.clone(),
+            mode: SnapshotMode::Auto,
+            width: Some(640),
+            height: Some(480),
+            quality: None,
+        };
+
+        let snapshot = orchestrator.get_snapshot(req).await.unwrap();
+        assert!(matches!(
+            snapshot.mode_used,
+            SnapshotMode::Keyframe | SnapshotMode::Decode
+        ));
+    }
+
+    /// 统计解码次数的解码器
+    struct CountingDecoder(std::sync::atomic::AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl SnapshotDecoder for CountingDecoder {
+        async fn decode(
+            &self,
+            _h264_data: &[u8],
+            width: Option<u32>,
+            _height: Option<u32>,
+            _quality: Option<u8>,
+        ) -> Result<Bytes> {
+            let count = self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            Ok(Bytes::from(format!("{}:{:?}", count, width)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_snapshot_cached_by_keyframe() {
+        let temp_dir = tempdir().unwrap();
+        let decoder = Arc::new(CountingDecoder(std::sync::atomic::AtomicUsize::new(0)));
+        let orchestrator = SnapshotOrchestrator::new(temp_dir.path().to_path_buf()).with_decoder(decoder.clone());
+        let stream_id = StreamId::new("test", "stream1");
+        let request = |width: Option<u32>| SnapshotRequest {
+            stream_id: stream_id.clone(),
+            mode: SnapshotMode::Auto,
+            width,
+            height: None,
+            quality: None,
+        };
+
+        let timestamp = Utc::now();
+        orchestrator
+            .process_keyframe(&stream_id, &[0, 0, 0, 1, 0x65, 0x88], timestamp)
+            .await
+            .unwrap();
+
+        let first = orchestrator.get_snapshot(request(Some(320))).await.unwrap();
+        assert_eq!(first.mode_used, SnapshotMode::Decode);
+        assert_eq!(first.content_type(), "image/jpeg");
+        let again = orchestrator.get_snapshot(request(Some(320))).await.unwrap();
+        assert_eq!(again.data, first.data);
+        orchestrator.get_snapshot(request(Some(640))).await.unwrap();
+        assert_eq!(decoder.0.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // 新关键帧使缓存失效
+        orchestrator
+            .process_keyframe(&stream_id, &[0, 0, 0, 1, 0x65, 0x99], timestamp + chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        let fresh = orchestrator.get_snapshot(request(Some(320))).await.unwrap();
+        assert_ne!(fresh.data, first.data);
+        assert_eq!(decoder.0.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_thumbnail_size() {
+        assert_eq!(ThumbnailSize::Small.dimensions(), Some((320, 180)));
+        assert_eq!(ThumbnailSize::Original.dimensions(), None);
+        let size: ThumbnailSize = serde_json::from_str("\"medium\"").unwrap();
+        assert_eq!(size, ThumbnailSize::Medium);
+
+        let query: SnapshotQuery = serde_json::from_str(r#"{"size":"small","quality":60}"#).unwrap();
+        let request = query.into_request(StreamId::new("test", "stream1"), SnapshotMode::Auto);
+        assert_eq!((request.width, request.height, request.quality), (Some(320), Some(180), Some(60)));
+        let query: SnapshotQuery = serde_json::from_str(r#"{"size":"small","width":100}"#).unwrap();
+        let request = query.into_request(StreamId::new("test", "stream1"), SnapshotMode::Auto);
+        assert_eq!((request.width, request.height), (Some(100), None));
+    }
+
+    #[test]
+    fn test_is_keyframe_detection() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0, 0, 0, 1, 0x65]); // IDR
+        assert!(SnapshotOrchestrator::is_keyframe(&data));
+
+        let mut data2 = Vec::new();
+        data2.extend_from_slice(&[0, 0, 1, 0x65]); // IDR (3-byte start code)
+        assert!(SnapshotOrchestrator::is_keyframe(&data2));
+
+        let mut data3 = Vec::new();
+        data3.extend_from_slice(&[0, 0, 0, 1, 0x61]); // Non-IDR
+        assert!(!SnapshotOrchestrator::is_keyframe(&data3));
+
+        // H.265 VPS + IDR_W_RADL / CRA / TRAIL_R
+        assert!(SnapshotOrchestrator::is_keyframe(&[0, 0, 0, 1, 0x40, 0x01, 0x0C, 0, 0, 0, 1, 0x26, 0x01, 0xAF]));
+        assert!(SnapshotOrchestrator::is_keyframe(&[0, 0, 1, 0x2A, 0x01, 0xAF]));
+        assert!(!SnapshotOrchestrator::is_keyframe(&[0, 0, 0, 1, 0x02, 0x01, 0xD0]));
+    }
+}
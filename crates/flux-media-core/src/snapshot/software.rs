@@ -0,0 +1,375 @@
+//! 纯软件快照解码器：关键帧（Annex B）经 OpenH264 解码为 RGB，缩放后编码为 JPEG
+//!
+//! 全程 CPU 计算，不依赖 GPU 或外部进程；每次解码使用独立的解码器实例，
+//! 在阻塞线程池中执行，避免占用异步运行时。
+//!
+//! H.265 关键帧由 libde265 解码，需启用 `software-decoder-hevc` 特性（链接系统 libde265）；
+//! 未启用时 H.265 关键帧返回解码错误，Auto 模式降级为 keyframe 快照。
+
+use super::SnapshotDecoder;
+use crate::codec::is_hevc_annexb;
+use crate::error::{MediaError, Result};
+use bytes::Bytes;
+use openh264::decoder::{DecodedYUV, Decoder};
+use openh264::formats::YUVSource;
+
+/// 软件解码器配置
+#[derive(Debug, Clone)]
+pub struct SoftwareDecoderConfig {
+    /// 默认 JPEG 质量（1-100）
+    pub default_quality: u8,
+    /// 未指定尺寸时输出的最大边长，超过则等比缩小
+    pub max_dimension: u32,
+}
+
+impl Default for SoftwareDecoderConfig {
+    fn default() -> Self {
+        Self {
+            default_quality: 80,
+            max_dimension: 1920,
+        }
+    }
+}
+
+/// RGB24 图像
+#[derive(Debug, Clone)]
+pub struct RgbImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// 纯软件快照解码器（H.264，启用 `software-decoder-hevc` 时支持 H.265）
+#[derive(Debug, Clone, Default)]
+pub struct SoftwareDecoder {
+    config: SoftwareDecoderConfig,
+}
+
+impl SoftwareDecoder {
+    pub fn new(config: SoftwareDecoderConfig) -> Self {
+        Self { config }
+    }
+
+    /// 解码关键帧为 RGB 图像
+    pub fn decode_rgb(data: &[u8]) -> Result<RgbImage> {
+        if is_hevc_annexb(data) {
+            return decode_hevc_rgb(data);
+        }
+
+        let mut decoder = Decoder::new().map_err(|e| MediaError::Decode(e.to_string()))?;
+        let image = match decoder.decode(data).map_err(|e| MediaError::Decode(e.to_string()))? {
+            Some(yuv) => to_rgb(&yuv),
+            None => {
+                // 单个访问单元可能留在解码缓冲中，需要显式冲刷
+                let frames = decoder
+                    .flush_remaining()
+                    .map_err(|e| MediaError::Decode(e.to_string()))?;
+                frames
+                    .last()
+                    .map(to_rgb)
+                    .ok_or_else(|| MediaError::Decode("No picture decoded from keyframe".to_string()))?
+            }
+        };
+        Ok(image)
+    }
+
+    /// 解码、缩放并编码为 JPEG
+    pub fn render_jpeg(&self, data: &[u8], width: Option<u32>, height: Option<u32>, quality: Option<u8>) -> Result<Bytes> {
+        let image = Self::decode_rgb(data)?;
+        let (target_width, target_height) =
+            target_dimensions(image.width, image.height, width, height, self.config.max_dimension);
+        let image = scale(&image, target_width, target_height);
+        encode_jpeg(&image, quality.unwrap_or(self.config.default_quality))
+    }
+}
+
+#[async_trait::async_trait]
+impl SnapshotDecoder for SoftwareDecoder {
+    async fn decode(
+        &self,
+        h264_data: &[u8],
+        width: Option<u32>,
+        height: Option<u32>,
+        quality: Option<u8>,
+    ) -> Result<Bytes> {
+        let decoder = self.clone();
+        let data = h264_data.to_vec();
+        tokio::task::spawn_blocking(move || decoder.render_jpeg(&data, width, height, quality))
+            .await
+            .map_err(|e| MediaError::Decode(format!("Snapshot decode task failed: {}", e)))?
+    }
+}
+
+fn to_rgb(yuv: &DecodedYUV<'_>) -> RgbImage {
+    let (width, height) = yuv.dimensions();
+    let mut data = vec![0; width * height * 3];
+    yuv.write_rgb8(&mut data);
+    RgbImage {
+        width: width as u32,
+        height: height as u32,
+        data,
+    }
+}
+
+#[cfg(not(feature = "software-decoder-hevc"))]
+fn decode_hevc_rgb(_data: &[u8]) -> Result<RgbImage> {
+    Err(MediaError::Decode(
+        "H.265 keyframes require the software-decoder-hevc feature".to_string(),
+    ))
+}
+
+/// 经 libde265 解码 H.265 关键帧（VPS/SPS/PPS + IRAP）
+#[cfg(feature = "software-decoder-hevc")]
+fn decode_hevc_rgb(data: &[u8]) -> Result<RgbImage> {
+    use libde265_rs::{new_decoder, DeError, DecodeResult};
+
+    let decode_error = |e: DeError| MediaError::Decode(format!("H.265 decode failed: {}", e));
+    let (mut input, mut output) = new_decoder().map_err(decode_error)?;
+    input.push_data(data, 0, 0).map_err(decode_error)?;
+    input.flush_data().map_err(decode_error)?;
+
+    loop {
+        let result = input.decode();
+        if let Some(picture) = output.next_picture() {
+            return hevc_picture_to_rgb(&picture);
+        }
+        match result {
+            Ok(DecodeResult::CallAgain) | Err(DeError::ErrorImageBufferFull) => {}
+            Ok(DecodeResult::Done) | Err(DeError::ErrorWaitingForInputData) => {
+                return Err(MediaError::Decode("No picture decoded from keyframe".to_string()));
+            }
+            Err(e) => return Err(decode_error(e)),
+        }
+    }
+}
+
+/// 8 bit 4:2:0 YCbCr（BT.601）转 RGB24
+#[cfg(feature = "software-decoder-hevc")]
+fn hevc_picture_to_rgb(picture: &libde265_rs::Image<'_>) -> Result<RgbImage> {
+    use libde265_rs::{Channel, ChromaFormat};
+
+    if picture.chroma_format() != ChromaFormat::C420 || picture.bits_per_pixel(Channel::Y) != 8 {
+        return Err(MediaError::Decode(format!(
+            "Unsupported H.265 picture format: {:?}, {} bit",
+            picture.chroma_format(),
+            picture.bits_per_pixel(Channel::Y)
+        )));
+    }
+
+    let (width, height) = (picture.width(Channel::Y) as usize, picture.height(Channel::Y) as usize);
+    let (y_plane, y_stride) = picture.plane(Channel::Y);
+    let (u_plane, u_stride) = picture.plane(Channel::Cb);
+    let (v_plane, v_stride) = picture.plane(Channel::Cr);
+    let (luma_offset, luma_scale, chroma_scale) = if picture.full_range() {
+        (0.0, 1.0, 255.0 / 254.0)
+    } else {
+        (16.0, 255.0 / 219.0, 255.0 / 224.0)
+    };
+
+    let mut data = vec![0u8; width * height * 3];
+    for row in 0..height {
+        for col in 0..width {
+            let luma = (y_plane[row * y_stride + col] as f32 - luma_offset) * luma_scale;
+            let cb = (u_plane[row / 2 * u_stride + col / 2] as f32 - 128.0) * chroma_scale;
+            let cr = (v_plane[row / 2 * v_stride + col / 2] as f32 - 128.0) * chroma_scale;
+            let offset = (row * width + col) * 3;
+            data[offset] = (luma + 1.402 * cr).round().clamp(0.0, 255.0) as u8;
+            data[offset + 1] = (luma - 0.344_136 * cb - 0.714_136 * cr).round().clamp(0.0, 255.0) as u8;
+            data[offset + 2] = (luma + 1.772 * cb).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    Ok(RgbImage {
+        width: width as u32,
+        height: height as u32,
+        data,
+    })
+}
+
+/// 计算输出尺寸：保持宽高比，缩放到不超过指定宽高；都未指定时以 `max_dimension` 为上限
+pub fn target_dimensions(
+    source_width: u32,
+    source_height: u32,
+    width: Option<u32>,
+    height: Option<u32>,
+    max_dimension: u32,
+) -> (u32, u32) {
+    let (max_width, max_height) = match (width, height) {
+        (Some(width), Some(height)) => (width, height),
+        (Some(width), None) => (width, u32::MAX),
+        (None, Some(height)) => (u32::MAX, height),
+        (None, None) => (max_dimension, max_dimension),
+    };
+
+    let scale = (max_width as f64 / source_width as f64).min(max_height as f64 / source_height as f64);
+    // 只指定了尺寸上限时不放大
+    let scale = if width.is_none() && height.is_none() { scale.min(1.0) } else { scale };
+    (
+        ((source_width as f64 * scale).round() as u32).max(1),
+        ((source_height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+/// 区域平均缩放（缩小时对源区域取均值，放大时退化为最近邻）
+pub fn scale(image: &RgbImage, width: u32, height: u32) -> RgbImage {
+    if (width, height) == (image.width, image.height) {
+        return image.clone();
+    }
+
+    let (source_width, source_height) = (image.width as usize, image.height as usize);
+    let (width, height) = (width as usize, height as usize);
+    let mut data = vec![0u8; width * height * 3];
+    for y in 0..height {
+        let y0 = y * source_height / height;
+        let y1 = ((y + 1) * source_height / height).max(y0 + 1);
+        for x in 0..width {
+            let x0 = x * source_width / width;
+            let x1 = ((x + 1) * source_width / width).max(x0 + 1);
+            let mut sum = [0u32; 3];
+            for sy in y0..y1 {
+                let row = &image.data[(sy * source_width + x0) * 3..(sy * source_width + x1) * 3];
+                for pixel in row.chunks_exact(3) {
+                    sum[0] += pixel[0] as u32;
+                    sum[1] += pixel[1] as u32;
+                    sum[2] += pixel[2] as u32;
+                }
+            }
+            let count = ((y1 - y0) * (x1 - x0)) as u32;
+            let offset = (y * width + x) * 3;
+            for channel in 0..3 {
+                data[offset + channel] = ((sum[channel] + count / 2) / count) as u8;
+            }
+        }
+    }
+
+    RgbImage {
+        width: width as u32,
+        height: height as u32,
+        data,
+    }
+}
+
+/// 编码为 baseline JPEG
+pub fn encode_jpeg(image: &RgbImage, quality: u8) -> Result<Bytes> {
+    if image.width > u16::MAX as u32 || image.height > u16::MAX as u32 {
+        return Err(MediaError::Decode(format!(
+            "Image too large for JPEG: {}x{}",
+            image.width, image.height
+        )));
+    }
+
+    let mut out = Vec::with_capacity(image.data.len() / 8);
+    jpeg_encoder::Encoder::new(&mut out, quality.clamp(1, 100))
+        .encode(
+            &image.data,
+            image.width as u16,
+            image.height as u16,
+            jpeg_encoder::ColorType::Rgb,
+        )
+        .map_err(|e| MediaError::Decode(format!("JPEG encode failed: {}", e)))?;
+    Ok(Bytes::from(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openh264::encoder::Encoder;
+    use openh264::formats::YUVBuffer;
+
+    /// 用 OpenH264 编码一帧 64x48 的左右两色画面（IDR，含 SPS/PPS）
+    fn encode_keyframe() -> Vec<u8> {
+        let (width, height) = (64usize, 48usize);
+        let mut rgb = vec![0u8; width * height * 3];
+        for y in 0..height {
+            for x in 0..width {
+                let color = if x < width / 2 { [220, 30, 30] } else { [30, 30, 220] };
+                rgb[(y * width + x) * 3..][..3].copy_from_slice(&color);
+            }
+        }
+        let yuv = YUVBuffer::from_rgb8_source(openh264::formats::RgbSliceU8::new(&rgb, (width, height)));
+        let mut encoder = Encoder::new().unwrap();
+        encoder.encode(&yuv).unwrap().to_vec()
+    }
+
+    /// 解析 JPEG SOF0 中的宽高
+    fn jpeg_dimensions(jpeg: &[u8]) -> (u16, u16) {
+        let sof = jpeg.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+        (
+            u16::from_be_bytes([jpeg[sof + 7], jpeg[sof + 8]]),
+            u16::from_be_bytes([jpeg[sof + 5], jpeg[sof + 6]]),
+        )
+    }
+
+    #[test]
+    fn test_decode_keyframe_to_rgb() {
+        let image = SoftwareDecoder::decode_rgb(&encode_keyframe()).unwrap();
+        assert_eq!((image.width, image.height), (64, 48));
+
+        // 左红右蓝（允许有损误差）
+        let left = &image.data[(24 * 64 + 8) * 3..][..3];
+        let right = &image.data[(24 * 64 + 56) * 3..][..3];
+        assert!(left[0] > 150 && left[2] < 100, "left {:?}", left);
+        assert!(right[2] > 150 && right[0] < 100, "right {:?}", right);
+    }
+
+    #[tokio::test]
+    async fn test_render_jpeg_thumbnail() {
+        let decoder = SoftwareDecoder::default();
+        let keyframe = encode_keyframe();
+
+        let jpeg = decoder.decode(&keyframe, None, None, None).await.unwrap();
+        assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+        assert_eq!(jpeg_dimensions(&jpeg), (64, 48));
+
+        let thumbnail = decoder.decode(&keyframe, Some(32), Some(32), Some(50)).await.unwrap();
+        assert_eq!(jpeg_dimensions(&thumbnail), (32, 24));
+
+        let low = decoder.decode(&keyframe, None, None, Some(5)).await.unwrap();
+        let high = decoder.decode(&keyframe, None, None, Some(95)).await.unwrap();
+        assert!(low.len() < high.len());
+    }
+
+    #[test]
+    fn test_rejects_invalid_input() {
+        // HEVC VPS
+        assert!(SoftwareDecoder::decode_rgb(&[0, 0, 0, 1, 0x40, 0x01, 0x0C, 0x01]).is_err());
+        assert!(SoftwareDecoder::decode_rgb(&[0, 0, 0, 1, 0x65, 0x88]).is_err());
+    }
+
+    #[cfg(feature = "software-decoder-hevc")]
+    #[tokio::test]
+    async fn test_decode_hevc_keyframe() {
+        let keyframe = include_bytes!("../../testdata/hevc_idr_316x240.h265");
+        let image = SoftwareDecoder::decode_rgb(keyframe).unwrap();
+        assert_eq!((image.width, image.height), (316, 240));
+        assert_eq!(image.data.len(), 316 * 240 * 3);
+        assert!(image.data.iter().any(|&value| value > 16));
+
+        let thumbnail = SoftwareDecoder::default()
+            .decode(keyframe, Some(158), None, None)
+            .await
+            .unwrap();
+        assert_eq!(jpeg_dimensions(&thumbnail), (158, 120));
+    }
+
+    #[test]
+    fn test_target_dimensions() {
+        assert_eq!(target_dimensions(1920, 1080, Some(320), Some(180), 1920), (320, 180));
+        assert_eq!(target_dimensions(1920, 1080, Some(320), Some(320), 1920), (320, 180));
+        assert_eq!(target_dimensions(1920, 1080, Some(640), None, 1920), (640, 360));
+        assert_eq!(target_dimensions(1920, 1080, None, None, 1280), (1280, 720));
+        assert_eq!(target_dimensions(640, 480, None, None, 1920), (640, 480));
+    }
+
+    #[test]
+    fn test_scale_averages_area() {
+        let image = RgbImage {
+            width: 2,
+            height: 2,
+            data: vec![0, 0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 0],
+        };
+        let scaled = scale(&image, 1, 1);
+        assert_eq!(scaled.data, vec![128, 128, 128]);
+        assert_eq!(scale(&image, 4, 4).data.len(), 48);
+    }
+}
@@ -62,13 +62,33 @@ pub struct VideoSample {
     pub codec: VideoCodec,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum VideoCodec {
     H264,
     H265,
+    AV1,
     Unknown,
 }
 
+impl VideoCodec {
+    /// 编码名称（用于 API 输出）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "h264",
+            VideoCodec::H265 => "h265",
+            VideoCodec::AV1 => "av1",
+            VideoCodec::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 /// 音频样本（协议无关）
 #[derive(Debug, Clone)]
 pub struct AudioSample {
@@ -86,6 +106,10 @@ pub enum AudioCodec {
     MP3,
     Opus,
     PCM,
+    /// G.711 A-law
+    G711A,
+    /// G.711 μ-law
+    G711U,
     Unknown,
 }
 
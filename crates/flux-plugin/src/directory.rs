@@ -0,0 +1,406 @@
+//! 插件目录：扫描、安装与文件监听
+//!
+//! 支持两种插件包格式：
+//! - 目录包：`<dir>/<id>/plugin.toml` + Wasm 文件（清单 `wasm` 字段，默认 `plugin.wasm`）
+//! - 单文件：`<dir>/<id>.wasm`，可选同名 `<id>.toml` 清单
+//!
+//! 没有 `plugin.toml` 的子目录（如插件源码）会被忽略。同一 ID 同时存在两种格式时以目录包为准。
+
+use crate::manager::PluginManager;
+use crate::manifest::{is_valid_id, PluginManifest};
+use anyhow::{anyhow, bail, Context, Result};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 目录包的清单文件名
+pub const MANIFEST_FILE: &str = "plugin.toml";
+/// 目录包默认的 Wasm 文件名
+pub const DEFAULT_WASM_FILE: &str = "plugin.wasm";
+/// 文件变化后等待静默的时间，避免写入过程中加载半个文件
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 磁盘上的插件包
+#[derive(Debug, Clone)]
+pub struct PluginPackage {
+    pub manifest: PluginManifest,
+    pub wasm: Vec<u8>,
+    /// 包所在路径（目录包为目录，单文件为 `.wasm` 文件）
+    pub path: PathBuf,
+}
+
+impl PluginPackage {
+    /// 读取目录包
+    pub fn read_dir(dir: &Path) -> Result<Self> {
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let content = std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Failed to read {:?}", manifest_path))?;
+        let mut manifest = PluginManifest::from_toml(&content)?;
+        if manifest.id.is_empty() {
+            manifest.id = file_name(dir)?;
+        }
+        manifest.validate()?;
+        let wasm_path = dir.join(manifest.wasm.as_deref().unwrap_or(DEFAULT_WASM_FILE));
+        let wasm = std::fs::read(&wasm_path).with_context(|| format!("Failed to read {:?}", wasm_path))?;
+        Ok(Self {
+            manifest,
+            wasm,
+            path: dir.to_path_buf(),
+        })
+    }
+
+    /// 读取单文件插件及同名清单
+    pub fn read_file(wasm_path: &Path) -> Result<Self> {
+        let stem = wasm_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .ok_or_else(|| anyhow!("Invalid plugin filename: {:?}", wasm_path))?;
+        let manifest_path = wasm_path.with_extension("toml");
+        let mut manifest = match std::fs::read_to_string(&manifest_path) {
+            Ok(content) => PluginManifest::from_toml(&content)?,
+            Err(_) => PluginManifest::default(),
+        };
+        if manifest.id.is_empty() {
+            manifest.id = stem;
+        }
+        manifest.validate()?;
+        let wasm = std::fs::read(wasm_path).with_context(|| format!("Failed to read {:?}", wasm_path))?;
+        Ok(Self {
+            manifest,
+            wasm,
+            path: wasm_path.to_path_buf(),
+        })
+    }
+
+    /// 内容指纹，用于判断文件变化后是否需要重新加载
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.wasm.hash(&mut hasher);
+        self.manifest.to_toml().unwrap_or_default().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// 目录中已加载的插件
+struct LoadedPackage {
+    path: PathBuf,
+    fingerprint: u64,
+}
+
+/// 插件目录管理
+pub struct PluginDirectory {
+    dir: PathBuf,
+    manager: Arc<PluginManager>,
+    loaded: Mutex<HashMap<String, LoadedPackage>>,
+}
+
+impl PluginDirectory {
+    pub fn new(dir: impl Into<PathBuf>, manager: Arc<PluginManager>) -> Self {
+        Self {
+            dir: dir.into(),
+            manager,
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn manager(&self) -> &Arc<PluginManager> {
+        &self.manager
+    }
+
+    /// 扫描目录中的插件包（无效的包记录日志后跳过）
+    pub fn scan(&self) -> Result<Vec<PluginPackage>> {
+        let entries = std::fs::read_dir(&self.dir).with_context(|| format!("Plugin directory not found: {:?}", self.dir))?;
+        let mut packages: HashMap<String, PluginPackage> = HashMap::new();
+        let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|e| e.path())).collect();
+        // 单文件在前，目录包在后覆盖同名插件
+        paths.sort_by_key(|path| (path.is_dir(), path.clone()));
+
+        for path in paths {
+            let package = if path.is_dir() {
+                if !path.join(MANIFEST_FILE).is_file() {
+                    continue;
+                }
+                PluginPackage::read_dir(&path)
+            } else if path.extension().is_some_and(|ext| ext == "wasm") {
+                PluginPackage::read_file(&path)
+            } else {
+                continue;
+            };
+            match package {
+                Ok(package) => {
+                    packages.insert(package.manifest.id.clone(), package);
+                }
+                Err(e) => tracing::error!("Invalid plugin package {:?}: {:?}", path, e),
+            }
+        }
+
+        let mut packages: Vec<_> = packages.into_values().collect();
+        packages.sort_by(|a, b| a.manifest.id.cmp(&b.manifest.id));
+        Ok(packages)
+    }
+
+    /// 同步目录与已加载插件：加载新增或变化的包，卸载已删除的包，返回加载的插件 ID
+    pub fn sync(&self) -> Result<Vec<String>> {
+        let packages = self.scan()?;
+        let mut loaded = self.loaded.lock().map_err(|e| anyhow!("Failed to acquire lock: {}", e))?;
+        let mut changed = Vec::new();
+
+        for package in &packages {
+            let id = &package.manifest.id;
+            let fingerprint = package.fingerprint();
+            if loaded.get(id).is_some_and(|current| current.fingerprint == fingerprint) {
+                continue;
+            }
+            match self
+                .manager
+                .load_plugin_with_manifest(id, &package.wasm, package.manifest.clone())
+            {
+                Ok(()) => {
+                    tracing::info!("Loaded plugin '{}' v{} from {:?}", id, package.manifest.version, package.path);
+                    loaded.insert(
+                        id.clone(),
+                        LoadedPackage {
+                            path: package.path.clone(),
+                            fingerprint,
+                        },
+                    );
+                    changed.push(id.clone());
+                }
+                Err(e) => tracing::error!("Failed to load plugin '{}' from {:?}: {:?}", id, package.path, e),
+            }
+        }
+
+        let removed: Vec<String> = loaded
+            .keys()
+            .filter(|id| !packages.iter().any(|package| &package.manifest.id == *id))
+            .cloned()
+            .collect();
+        for id in removed {
+            loaded.remove(&id);
+            if self.manager.unload_plugin(&id)? {
+                tracing::info!("Unloaded plugin '{}' (package removed)", id);
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// 安装插件包：先加载校验，成功后写入目录（`<dir>/<id>/`）
+    pub fn install(&self, mut manifest: PluginManifest, wasm: &[u8]) -> Result<()> {
+        manifest.validate()?;
+        let id = manifest.id.clone();
+        // 目录包中的 Wasm 文件名固定，避免写出清单以外的文件
+        manifest.wasm = None;
+
+        let mut loaded = self.loaded.lock().map_err(|e| anyhow!("Failed to acquire lock: {}", e))?;
+        self.manager.load_plugin_with_manifest(&id, wasm, manifest.clone())?;
+
+        let package_dir = self.dir.join(&id);
+        std::fs::create_dir_all(&package_dir)?;
+        // 先写临时文件再改名，监听线程不会读到写了一半的文件
+        write_atomic(&package_dir.join(DEFAULT_WASM_FILE), wasm)?;
+        write_atomic(&package_dir.join(MANIFEST_FILE), manifest.to_toml()?.as_bytes())?;
+        // 同名单文件插件由目录包取代
+        remove_if_exists(&self.dir.join(format!("{}.wasm", id)))?;
+        remove_if_exists(&self.dir.join(format!("{}.toml", id)))?;
+
+        let package = PluginPackage::read_dir(&package_dir)?;
+        loaded.insert(
+            id,
+            LoadedPackage {
+                path: package_dir,
+                fingerprint: package.fingerprint(),
+            },
+        );
+        Ok(())
+    }
+
+    /// 卸载插件并删除其插件包
+    pub fn remove(&self, plugin_id: &str) -> Result<bool> {
+        if !is_valid_id(plugin_id) {
+            bail!("Invalid plugin id '{}'", plugin_id);
+        }
+        let mut loaded = self.loaded.lock().map_err(|e| anyhow!("Failed to acquire lock: {}", e))?;
+        let unloaded = self.manager.unload_plugin(plugin_id)?;
+        if let Some(package) = loaded.remove(plugin_id) {
+            if package.path.is_dir() {
+                std::fs::remove_dir_all(&package.path)?;
+            } else {
+                remove_if_exists(&package.path)?;
+                remove_if_exists(&package.path.with_extension("toml"))?;
+            }
+        }
+        Ok(unloaded)
+    }
+
+    /// 监听目录变化并自动同步，返回的句柄被丢弃时停止监听
+    pub fn watch(self: &Arc<Self>) -> Result<PluginWatcher> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = RecommendedWatcher::new(
+            move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    if !event.kind.is_access() {
+                        let _ = tx.send(());
+                    }
+                }
+            },
+            notify::Config::default(),
+        )?;
+        watcher.watch(&self.dir, RecursiveMode::Recursive)?;
+
+        let directory = Arc::downgrade(self);
+        std::thread::Builder::new()
+            .name("plugin-watcher".to_string())
+            .spawn(move || {
+                // 发送端随 watcher 一起释放时退出
+                while rx.recv().is_ok() {
+                    while rx.recv_timeout(WATCH_DEBOUNCE).is_ok() {}
+                    let Some(directory) = directory.upgrade() else {
+                        break;
+                    };
+                    if let Err(e) = directory.sync() {
+                        tracing::error!("Failed to sync plugin directory: {:?}", e);
+                    }
+                }
+            })?;
+
+        tracing::info!("Watching plugin directory {:?}", self.dir);
+        Ok(PluginWatcher { _watcher: watcher })
+    }
+}
+
+/// 目录监听句柄
+pub struct PluginWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+fn file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| anyhow!("Invalid plugin path: {:?}", path))
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 返回固定值的旧 ABI 测试插件
+    fn module(value: i32) -> String {
+        format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) i32.const 1024)
+                (func (export "dealloc") (param i32 i32))
+                (func (export "on_msg") (param i32 i32) (result i32) i32.const {}))"#,
+            value
+        )
+    }
+
+    fn setup() -> (tempfile::TempDir, Arc<PluginManager>, Arc<PluginDirectory>) {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = Arc::new(PluginManager::new().unwrap());
+        let directory = Arc::new(PluginDirectory::new(dir.path(), manager.clone()));
+        (dir, manager, directory)
+    }
+
+    #[test]
+    fn test_sync_loads_reloads_and_unloads() {
+        let (dir, manager, directory) = setup();
+        std::fs::write(dir.path().join("flat.wasm"), module(1)).unwrap();
+        std::fs::write(dir.path().join("flat.toml"), "version = \"0.1.0\"").unwrap();
+        let package = dir.path().join("pkg");
+        std::fs::create_dir(&package).unwrap();
+        std::fs::write(package.join("plugin.wasm"), module(2)).unwrap();
+        std::fs::write(package.join("plugin.toml"), "version = \"1.0.0\"\nentry_points = [\"on_msg\"]").unwrap();
+        // 没有清单的子目录（插件源码）被忽略
+        std::fs::create_dir(dir.path().join("source")).unwrap();
+
+        assert_eq!(directory.sync().unwrap(), vec!["flat", "pkg"]);
+        assert_eq!(manager.call_plugin("flat", "on_msg", "").unwrap(), 1);
+        assert_eq!(manager.plugin_info("pkg").unwrap().version, "1.0.0");
+
+        // 未变化的包不重新加载
+        assert!(directory.sync().unwrap().is_empty());
+
+        std::fs::write(package.join("plugin.wasm"), module(3)).unwrap();
+        assert_eq!(directory.sync().unwrap(), vec!["pkg"]);
+        assert_eq!(manager.call_plugin("pkg", "on_msg", "").unwrap(), 3);
+
+        // 无效的新版本不影响已加载的版本
+        std::fs::write(package.join("plugin.wasm"), "not wasm").unwrap();
+        assert!(directory.sync().unwrap().is_empty());
+        assert_eq!(manager.call_plugin("pkg", "on_msg", "").unwrap(), 3);
+
+        std::fs::remove_file(dir.path().join("flat.wasm")).unwrap();
+        directory.sync().unwrap();
+        assert!(manager.plugin_info("flat").is_err());
+        assert!(manager.plugin_info("pkg").is_ok());
+    }
+
+    #[test]
+    fn test_install_and_remove() {
+        let (dir, manager, directory) = setup();
+        let manifest = PluginManifest {
+            id: "uploaded".to_string(),
+            version: "2.1.0".to_string(),
+            ..Default::default()
+        };
+        directory.install(manifest.clone(), module(7).as_bytes()).unwrap();
+        assert_eq!(manager.call_plugin("uploaded", "on_msg", "").unwrap(), 7);
+        assert!(dir.path().join("uploaded").join(MANIFEST_FILE).is_file());
+        // 安装的包与磁盘一致，同步时不会重复加载
+        assert!(directory.sync().unwrap().is_empty());
+
+        // 无效模块不写入磁盘
+        let invalid = PluginManifest {
+            id: "broken".to_string(),
+            ..Default::default()
+        };
+        assert!(directory.install(invalid, b"not wasm").is_err());
+        assert!(!dir.path().join("broken").exists());
+
+        assert!(directory.remove("uploaded").unwrap());
+        assert!(!dir.path().join("uploaded").exists());
+        assert!(manager.plugin_info("uploaded").is_err());
+        assert!(directory.remove("../escape").is_err());
+    }
+
+    #[test]
+    fn test_watch_reloads_changed_packages() {
+        let (dir, manager, directory) = setup();
+        std::fs::write(dir.path().join("watched.wasm"), module(1)).unwrap();
+        directory.sync().unwrap();
+        let _watcher = directory.watch().unwrap();
+
+        std::fs::write(dir.path().join("watched.wasm"), module(2)).unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while manager.call_plugin("watched", "on_msg", "").unwrap() != 2 {
+            assert!(std::time::Instant::now() < deadline, "plugin was not reloaded");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! 插件 Host 函数（能力受清单控制，默认拒绝）
+//!
+//! 函数签名与返回码约定见 `flux_types::plugin`。
+
+use crate::manifest::{Capability, PluginManifest};
+use crate::wasm_host::PluginState;
+use anyhow::Result;
+use flux_core::bus::SharedEventBus;
+use flux_core::runtime::block_on;
+use flux_device::DeviceRegistry;
+use flux_types::message::Message;
+use flux_types::plugin::{
+    pack_ptr_len, HttpRequest, HttpResponse, HOST_ERR_DENIED, HOST_ERR_FAILED, HOST_ERR_INVALID,
+    HOST_ERR_LIMIT, HOST_OK,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wasmtime::{Caller, Extern, Linker};
+
+/// 单个插件的 KV 键数量上限
+pub const MAX_KV_ENTRIES: usize = 1024;
+/// KV 键长度上限（字节）
+pub const MAX_KV_KEY_BYTES: usize = 256;
+/// KV 值大小上限（字节）
+pub const MAX_KV_VALUE_BYTES: usize = 64 * 1024;
+/// 发布消息的主题与负载大小上限（字节）
+pub const MAX_PUBLISH_BYTES: usize = 1024 * 1024;
+/// HTTP 请求与响应体大小上限（字节）
+pub const MAX_HTTP_BODY_BYTES: usize = 1024 * 1024;
+/// HTTP 请求超时
+pub const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 设备元数据查询
+pub trait DeviceLookup: Send + Sync {
+    /// 按 ID 查询设备，返回 JSON（不含密钥）
+    fn device(&self, device_id: &str) -> Result<Option<serde_json::Value>>;
+}
+
+impl DeviceLookup for DeviceRegistry {
+    fn device(&self, device_id: &str) -> Result<Option<serde_json::Value>> {
+        let device = block_on(self.get(device_id))?;
+        Ok(device
+            .map(|mut device| {
+                device.secret = None;
+                serde_json::to_value(device)
+            })
+            .transpose()?)
+    }
+}
+
+/// Host 函数依赖的外部服务（所有插件共用）
+#[derive(Clone)]
+pub struct HostServices {
+    pub event_bus: Option<SharedEventBus>,
+    pub devices: Option<Arc<dyn DeviceLookup>>,
+    http: reqwest::Client,
+}
+
+impl Default for HostServices {
+    fn default() -> Self {
+        // 调用可能跨越不同的运行时（见 block_on），不复用连接
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .pool_max_idle_per_host(0)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+        Self {
+            event_bus: None,
+            devices: None,
+            http,
+        }
+    }
+}
+
+/// 单个插件的 Host 上下文（同一插件的所有实例共享）
+pub struct PluginHostContext {
+    pub plugin_id: String,
+    pub manifest: PluginManifest,
+    services: HostServices,
+    kv: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl PluginHostContext {
+    pub fn new(plugin_id: &str, manifest: PluginManifest, services: HostServices) -> Self {
+        Self {
+            plugin_id: plugin_id.to_string(),
+            manifest,
+            services,
+            kv: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 沿用旧版本的 KV 状态（热更新时使用）
+    pub(crate) fn inherit_state(mut self, previous: &PluginHostContext) -> Self {
+        self.kv = previous.kv.clone();
+        self
+    }
+
+    pub fn kv_get(&self, key: &str) -> Option<Vec<u8>> {
+        self.kv.lock().ok()?.get(key).cloned()
+    }
+
+    fn kv_set(&self, key: String, value: Vec<u8>) -> i32 {
+        let Ok(mut kv) = self.kv.lock() else {
+            return HOST_ERR_FAILED;
+        };
+        if !kv.contains_key(&key) && kv.len() >= MAX_KV_ENTRIES {
+            return HOST_ERR_LIMIT;
+        }
+        kv.insert(key, value);
+        HOST_OK
+    }
+
+    fn kv_delete(&self, key: &str) -> i32 {
+        match self.kv.lock() {
+            Ok(mut kv) => {
+                kv.remove(key);
+                HOST_OK
+            }
+            Err(_) => HOST_ERR_FAILED,
+        }
+    }
+
+    fn publish(&self, topic: String, payload: serde_json::Value) -> i32 {
+        let Some(bus) = &self.services.event_bus else {
+            return HOST_ERR_FAILED;
+        };
+        // 无订阅者不算失败
+        let _ = bus.publish(Message::new(topic, payload));
+        HOST_OK
+    }
+
+    fn device(&self, device_id: &str) -> Result<Option<serde_json::Value>, i32> {
+        let Some(devices) = &self.services.devices else {
+            return Err(HOST_ERR_FAILED);
+        };
+        devices.device(device_id).map_err(|e| {
+            tracing::warn!("Plugin '{}' device lookup failed: {}", self.plugin_id, e);
+            HOST_ERR_FAILED
+        })
+    }
+
+    fn http_request(&self, request: HttpRequest) -> Result<HttpResponse, i32> {
+        if !self.manifest.http_allowed(&request.url) {
+            tracing::warn!("Plugin '{}' HTTP request to {} not allowed", self.plugin_id, request.url);
+            return Err(HOST_ERR_DENIED);
+        }
+        let method = reqwest::Method::from_bytes(request.method.to_uppercase().as_bytes()).map_err(|_| HOST_ERR_INVALID)?;
+        let mut builder = self.services.http.request(method, &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body {
+            if body.len() > MAX_HTTP_BODY_BYTES {
+                return Err(HOST_ERR_LIMIT);
+            }
+            builder = builder.body(body);
+        }
+
+        let plugin_id = self.plugin_id.clone();
+        block_on(async move {
+            let mut response = builder.send().await?;
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .collect();
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await? {
+                if body.len() + chunk.len() > MAX_HTTP_BODY_BYTES {
+                    return Ok(Err(HOST_ERR_LIMIT));
+                }
+                body.extend_from_slice(&chunk);
+            }
+            Ok::<_, reqwest::Error>(Ok(HttpResponse {
+                status,
+                headers,
+                body: String::from_utf8_lossy(&body).into_owned(),
+            }))
+        })
+        .unwrap_or_else(|e| {
+            tracing::warn!("Plugin '{}' HTTP request failed: {}", plugin_id, e);
+            Err(HOST_ERR_FAILED)
+        })
+    }
+}
+
+/// 注册能力相关的 Host 函数
+pub(crate) fn register(linker: &mut Linker<PluginState>) -> Result<()> {
+    linker.func_wrap("env", "flux_time_now_ms", |caller: Caller<'_, PluginState>| -> i64 {
+        if !allowed(&caller, Capability::Time) {
+            return HOST_ERR_DENIED as i64;
+        }
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0)
+    })?;
+
+    linker.func_wrap(
+        "env",
+        "flux_kv_get",
+        |mut caller: Caller<'_, PluginState>, key_ptr: i32, key_len: i32| -> i64 {
+            if !allowed(&caller, Capability::Kv) {
+                return HOST_ERR_DENIED as i64;
+            }
+            let key = match read_string(&mut caller, key_ptr, key_len, MAX_KV_KEY_BYTES) {
+                Ok(key) => key,
+                Err(code) => return code as i64,
+            };
+            match caller.data().host.kv_get(&key) {
+                Some(value) => write_guest(&mut caller, &value),
+                None => 0,
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "flux_kv_set",
+        |mut caller: Caller<'_, PluginState>, key_ptr: i32, key_len: i32, value_ptr: i32, value_len: i32| -> i32 {
+            if !allowed(&caller, Capability::Kv) {
+                return HOST_ERR_DENIED;
+            }
+            let key = match read_string(&mut caller, key_ptr, key_len, MAX_KV_KEY_BYTES) {
+                Ok(key) => key,
+                Err(code) => return code,
+            };
+            match read_bytes(&mut caller, value_ptr, value_len, MAX_KV_VALUE_BYTES) {
+                Ok(value) => caller.data().host.kv_set(key, value),
+                Err(code) => code,
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "flux_kv_delete",
+        |mut caller: Caller<'_, PluginState>, key_ptr: i32, key_len: i32| -> i32 {
+            if !allowed(&caller, Capability::Kv) {
+                return HOST_ERR_DENIED;
+            }
+            match read_string(&mut caller, key_ptr, key_len, MAX_KV_KEY_BYTES) {
+                Ok(key) => caller.data().host.kv_delete(&key),
+                Err(code) => code,
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "flux_publish",
+        |mut caller: Caller<'_, PluginState>, topic_ptr: i32, topic_len: i32, payload_ptr: i32, payload_len: i32| -> i32 {
+            if !allowed(&caller, Capability::Publish) {
+                return HOST_ERR_DENIED;
+            }
+            let topic = match read_string(&mut caller, topic_ptr, topic_len, MAX_PUBLISH_BYTES) {
+                Ok(topic) if !topic.is_empty() => topic,
+                Ok(_) => return HOST_ERR_INVALID,
+                Err(code) => return code,
+            };
+            let payload = match read_bytes(&mut caller, payload_ptr, payload_len, MAX_PUBLISH_BYTES) {
+                Ok(bytes) => match serde_json::from_slice(&bytes) {
+                    Ok(payload) => payload,
+                    Err(_) => return HOST_ERR_INVALID,
+                },
+                Err(code) => return code,
+            };
+            caller.data().host.publish(topic, payload)
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "flux_device_get",
+        |mut caller: Caller<'_, PluginState>, id_ptr: i32, id_len: i32| -> i64 {
+            if !allowed(&caller, Capability::DeviceRead) {
+                return HOST_ERR_DENIED as i64;
+            }
+            let device_id = match read_string(&mut caller, id_ptr, id_len, MAX_KV_KEY_BYTES) {
+                Ok(id) => id,
+                Err(code) => return code as i64,
+            };
+            let host = caller.data().host.clone();
+            match host.device(&device_id) {
+                Ok(Some(device)) => write_guest(&mut caller, device.to_string().as_bytes()),
+                Ok(None) => 0,
+                Err(code) => code as i64,
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "flux_http_request",
+        |mut caller: Caller<'_, PluginState>, req_ptr: i32, req_len: i32| -> i64 {
+            if !allowed(&caller, Capability::Http) {
+                return HOST_ERR_DENIED as i64;
+            }
+            let request: HttpRequest = match read_bytes(&mut caller, req_ptr, req_len, MAX_HTTP_BODY_BYTES * 2) {
+                Ok(bytes) => match serde_json::from_slice(&bytes) {
+                    Ok(request) => request,
+                    Err(_) => return HOST_ERR_INVALID as i64,
+                },
+                Err(code) => return code as i64,
+            };
+            let host = caller.data().host.clone();
+            match host.http_request(request) {
+                Ok(response) => match serde_json::to_vec(&response) {
+                    Ok(bytes) => write_guest(&mut caller, &bytes),
+                    Err(_) => HOST_ERR_FAILED as i64,
+                },
+                Err(code) => code as i64,
+            }
+        },
+    )?;
+
+    Ok(())
+}
+
+fn allowed(caller: &Caller<'_, PluginState>, capability: Capability) -> bool {
+    let host = &caller.data().host;
+    let allowed = host.manifest.allows(capability);
+    if !allowed {
+        tracing::debug!("Plugin '{}' denied capability {:?}", host.plugin_id, capability);
+    }
+    allowed
+}
+
+/// 读取插件内存
+fn read_bytes(caller: &mut Caller<'_, PluginState>, ptr: i32, len: i32, max: usize) -> Result<Vec<u8>, i32> {
+    if ptr < 0 || len < 0 {
+        return Err(HOST_ERR_INVALID);
+    }
+    if len as usize > max {
+        return Err(HOST_ERR_LIMIT);
+    }
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        return Err(HOST_ERR_FAILED);
+    };
+    let mut buf = vec![0u8; len as usize];
+    memory
+        .read(&mut *caller, ptr as usize, &mut buf)
+        .map_err(|_| HOST_ERR_INVALID)?;
+    Ok(buf)
+}
+
+fn read_string(caller: &mut Caller<'_, PluginState>, ptr: i32, len: i32, max: usize) -> Result<String, i32> {
+    String::from_utf8(read_bytes(caller, ptr, len, max)?).map_err(|_| HOST_ERR_INVALID)
+}
+
+/// 通过插件的 `alloc` 分配缓冲区并写入数据，返回打包的指针与长度
+fn write_guest(caller: &mut Caller<'_, PluginState>, data: &[u8]) -> i64 {
+    if data.is_empty() {
+        return 0;
+    }
+    let (Some(Extern::Func(alloc)), Some(Extern::Memory(memory))) =
+        (caller.get_export("alloc"), caller.get_export("memory"))
+    else {
+        return HOST_ERR_FAILED as i64;
+    };
+    let Ok(alloc) = alloc.typed::<i32, i32>(&*caller) else {
+        return HOST_ERR_FAILED as i64;
+    };
+    let ptr = match alloc.call(&mut *caller, data.len() as i32) {
+        Ok(ptr) if ptr > 0 => ptr,
+        _ => return HOST_ERR_FAILED as i64,
+    };
+    if memory.write(&mut *caller, ptr as usize, data).is_err() {
+        return HOST_ERR_FAILED as i64;
+    }
+    pack_ptr_len(ptr as u32, data.len() as u32)
+}
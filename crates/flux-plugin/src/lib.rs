@@ -1,5 +1,14 @@
+pub mod directory;
+pub mod host;
 pub mod manager;
+pub mod manifest;
 pub mod memory;
+pub mod stats;
 pub mod wasm_host;
 
-pub use manager::PluginManager;
+pub use directory::{PluginDirectory, PluginPackage, PluginWatcher};
+pub use host::{DeviceLookup, HostServices, PluginHostContext};
+pub use manager::{PluginInfo, PluginManager, QuarantinePolicy};
+pub use manifest::{Capability, PluginManifest};
+pub use stats::TrapKind;
+pub use wasm_host::WasmResourceLimits;
@@ -1,17 +1,52 @@
-use crate::wasm_host::{WasmHost, WasmResourceLimiter};
-use anyhow::{anyhow, Context, Result};
+use crate::host::{DeviceLookup, HostServices, PluginHostContext};
+use crate::manifest::{Capability, PluginManifest};
+use crate::stats::{self, PluginStats, TrapKind};
+use crate::wasm_host::{PluginState, WasmHost, WasmResourceLimits};
+use anyhow::{anyhow, bail, Context, Result};
+use flux_core::bus::SharedEventBus;
+use flux_types::plugin::{unpack_ptr_len, PluginResponse, ABI_VERSION_EXPORT, PLUGIN_ABI_VERSION};
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use wasmtime::{Module, Store};
 
 /// 插件实例池配置
-const DEFAULT_POOL_SIZE: usize = 4; // 每个插件默认保持4个实例
+pub const DEFAULT_POOL_SIZE: usize = 4; // 每个插件默认保持4个实例
+
+/// 插件返回数据的大小上限
+const MAX_RESPONSE_BYTES: u32 = 4 * 1024 * 1024;
 
 pub struct PluginManager {
     host: WasmHost,
     // 存储每个插件的模块和实例池
     plugins: RwLock<HashMap<String, PluginPool>>,
     pool_size: usize,
+    // Host 函数使用的外部服务
+    services: HostServices,
+    // 实例池代数（热更新后旧实例不再归还）
+    next_generation: AtomicU64,
+    quarantine: QuarantinePolicy,
+}
+
+/// 隔离策略：连续 trap 达到阈值后，在隔离期内拒绝调用
+///
+/// 隔离期满后放行一次试探调用，成功则恢复，再次 trap 则重新隔离。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuarantinePolicy {
+    /// 连续 trap 次数阈值（0 表示不隔离）
+    pub max_consecutive_traps: u32,
+    pub duration: Duration,
+}
+
+impl Default for QuarantinePolicy {
+    fn default() -> Self {
+        Self {
+            max_consecutive_traps: 5,
+            duration: Duration::from_secs(60),
+        }
+    }
 }
 
 /// 单个插件的实例池
@@ -21,11 +56,50 @@ struct PluginPool {
     available: Vec<PluginInstance>,
     // 实例池大小限制
     max_size: usize,
+    // 插件声明的 ABI 版本（未声明为 0）
+    abi_version: i32,
+    // Host 上下文（能力清单与 KV 状态，所有实例共享）
+    host: Arc<PluginHostContext>,
+    generation: u64,
+    enabled: bool,
+    loaded_at: i64,
+    // 调用统计（热更新时沿用）
+    stats: Arc<PluginStats>,
+    consecutive_traps: u32,
+    quarantined_until: Option<Instant>,
 }
 
 struct PluginInstance {
-    store: Store<WasmResourceLimiter>,
+    store: Store<PluginState>,
     instance: wasmtime::Instance,
+    generation: u64,
+    stats: Arc<PluginStats>,
+}
+
+/// 已加载插件的信息
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    pub id: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub abi_version: i32,
+    pub entry_points: Vec<String>,
+    pub capabilities: Vec<Capability>,
+    pub config_schema: Option<serde_json::Value>,
+    pub enabled: bool,
+    /// 加载时间（Unix 毫秒）
+    pub loaded_at: i64,
+    pub pool_available: usize,
+    pub pool_max_size: usize,
+    pub calls: u64,
+    pub errors: u64,
+    pub traps: u64,
+    pub pool_hits: u64,
+    pub pool_misses: u64,
+    /// 最近调用的 p99 延迟（毫秒）
+    pub latency_p99_ms: Option<f64>,
+    pub quarantined: bool,
+    pub consecutive_traps: u32,
 }
 
 impl PluginManager {
@@ -34,45 +108,203 @@ impl PluginManager {
     }
 
     pub fn with_pool_size(pool_size: usize) -> Result<Self> {
+        Self::with_limits(pool_size, WasmResourceLimits::default())
+    }
+
+    /// 指定实例池大小与资源限制（内存、单次调用 fuel 与超时）
+    pub fn with_limits(pool_size: usize, limits: WasmResourceLimits) -> Result<Self> {
         Ok(Self {
-            host: WasmHost::new()?,
+            host: WasmHost::with_limits(limits)?,
             plugins: RwLock::new(HashMap::new()),
             pool_size,
+            services: HostServices::default(),
+            next_generation: AtomicU64::new(1),
+            quarantine: QuarantinePolicy::default(),
         })
     }
 
+    /// 反复 trap 的插件自动隔离
+    pub fn with_quarantine(mut self, policy: QuarantinePolicy) -> Self {
+        self.quarantine = policy;
+        self
+    }
+
+    /// 插件可通过 `flux_publish` 向事件总线发布消息
+    pub fn with_event_bus(mut self, event_bus: SharedEventBus) -> Self {
+        self.services.event_bus = Some(event_bus);
+        self
+    }
+
+    /// 插件可通过 `flux_device_get` 读取设备元数据
+    pub fn with_device_lookup(mut self, devices: Arc<dyn DeviceLookup>) -> Self {
+        self.services.devices = Some(devices);
+        self
+    }
+
+    /// 加载插件（不授予任何 Host 能力）
     pub fn load_plugin(&self, plugin_id: &str, wasm_bytes: &[u8]) -> Result<()> {
+        self.load_plugin_with_manifest(plugin_id, wasm_bytes, PluginManifest::default())
+    }
+
+    /// 按清单加载插件，只授予清单中声明的 Host 能力
+    ///
+    /// 插件已加载时原子替换其实例池：新版本编译、校验通过后才替换，失败时旧版本保持不变；
+    /// 进行中的调用在旧实例上完成，之后旧实例被丢弃。启用状态与 KV 状态沿用旧版本。
+    pub fn load_plugin_with_manifest(
+        &self,
+        plugin_id: &str,
+        wasm_bytes: &[u8],
+        mut manifest: PluginManifest,
+    ) -> Result<()> {
+        if manifest.id.is_empty() {
+            manifest.id = plugin_id.to_string();
+        } else if manifest.id != plugin_id {
+            bail!("Manifest id '{}' does not match plugin id '{}'", manifest.id, plugin_id);
+        }
+        manifest.validate()?;
+
         let module = Arc::new(self.host.load_module(wasm_bytes)?);
+        let mut host = PluginHostContext::new(plugin_id, manifest, self.services.clone());
+        let mut enabled = true;
+        let mut plugin_stats = Arc::new(PluginStats::default());
+        {
+            let plugins = self
+                .plugins
+                .read()
+                .map_err(|e| anyhow!("Failed to acquire read lock: {}", e))?;
+            if let Some(previous) = plugins.get(plugin_id) {
+                host = host.inherit_state(&previous.host);
+                enabled = previous.enabled;
+                plugin_stats = previous.stats.clone();
+            }
+        }
+        let host = Arc::new(host);
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
 
         // 预创建初始实例（懒加载策略：先创建1个，按需增长）
         let linker = self.host.create_linker();
-        let mut store = self.host.create_store();
+        let mut store = self.host.create_store(host.clone());
         let instance = linker
             .instantiate(&mut store, &module)
             .context("Failed to instantiate plugin")?;
 
-        let plugin_instance = PluginInstance { store, instance };
+        let abi_version = match instance.get_typed_func::<(), i32>(&mut store, ABI_VERSION_EXPORT) {
+            Ok(version_fn) => version_fn.call(&mut store, ())?,
+            Err(_) => 0,
+        };
+        if abi_version > PLUGIN_ABI_VERSION {
+            bail!(
+                "Plugin '{}' requires ABI version {}, host supports up to {}",
+                plugin_id,
+                abi_version,
+                PLUGIN_ABI_VERSION
+            );
+        }
+        if let Some(declared) = host.manifest.abi_version {
+            if declared != abi_version {
+                bail!(
+                    "Plugin '{}' manifest declares ABI version {} but module exports {}",
+                    plugin_id,
+                    declared,
+                    abi_version
+                );
+            }
+        }
+        for entry in &host.manifest.entry_points {
+            if instance.get_func(&mut store, entry).is_none() {
+                bail!("Plugin '{}' does not export entry point '{}'", plugin_id, entry);
+            }
+        }
+
+        let plugin_instance = PluginInstance {
+            store,
+            instance,
+            generation,
+            stats: plugin_stats.clone(),
+        };
 
         let pool = PluginPool {
             module,
             available: vec![plugin_instance],
             max_size: self.pool_size,
+            abi_version,
+            host,
+            generation,
+            enabled,
+            loaded_at: now_ms(),
+            stats: plugin_stats,
+            consecutive_traps: 0,
+            quarantined_until: None,
         };
 
         let mut plugins = self
             .plugins
             .write()
             .map_err(|e| anyhow!("Failed to acquire write lock: {}", e))?;
-        plugins.insert(plugin_id.to_string(), pool);
+        let replaced = plugins.insert(plugin_id.to_string(), pool).is_some();
+        stats::set_loaded_plugins(plugins.len());
+        stats::set_pool_available(plugin_id, 1);
+        stats::set_quarantined(plugin_id, false);
 
         tracing::debug!(
-            "Loaded plugin '{}' with pool size {}",
+            "{} plugin '{}' with pool size {} (ABI v{})",
+            if replaced { "Reloaded" } else { "Loaded" },
             plugin_id,
-            self.pool_size
+            self.pool_size,
+            abi_version
         );
         Ok(())
     }
 
+    /// 卸载插件，进行中的调用完成后实例随之释放
+    pub fn unload_plugin(&self, plugin_id: &str) -> Result<bool> {
+        let mut plugins = self
+            .plugins
+            .write()
+            .map_err(|e| anyhow!("Failed to acquire write lock: {}", e))?;
+        let removed = plugins.remove(plugin_id).is_some();
+        stats::set_loaded_plugins(plugins.len());
+        if removed {
+            tracing::debug!("Unloaded plugin '{}'", plugin_id);
+        }
+        Ok(removed)
+    }
+
+    /// 启用或停用插件，停用后调用直接返回错误
+    pub fn set_enabled(&self, plugin_id: &str, enabled: bool) -> Result<()> {
+        let mut plugins = self
+            .plugins
+            .write()
+            .map_err(|e| anyhow!("Failed to acquire write lock: {}", e))?;
+        let pool = plugins
+            .get_mut(plugin_id)
+            .ok_or_else(|| anyhow!("Plugin not found: {}", plugin_id))?;
+        pool.enabled = enabled;
+        Ok(())
+    }
+
+    pub fn plugin_info(&self, plugin_id: &str) -> Result<PluginInfo> {
+        let plugins = self
+            .plugins
+            .read()
+            .map_err(|e| anyhow!("Failed to acquire read lock: {}", e))?;
+        plugins
+            .get(plugin_id)
+            .map(|pool| pool.info(plugin_id))
+            .ok_or_else(|| anyhow!("Plugin not found: {}", plugin_id))
+    }
+
+    /// 已加载插件列表（按 ID 排序）
+    pub fn list_plugins(&self) -> Result<Vec<PluginInfo>> {
+        let plugins = self
+            .plugins
+            .read()
+            .map_err(|e| anyhow!("Failed to acquire read lock: {}", e))?;
+        let mut infos: Vec<_> = plugins.iter().map(|(id, pool)| pool.info(id)).collect();
+        infos.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(infos)
+    }
+
     /// Call a function in the plugin.
     /// Example: "on_msg(ptr, len) -> int"
     /// 使用实例池策略：从池中获取实例，使用后归还
@@ -82,16 +314,72 @@ impl PluginManager {
         function_name: &str,
         input_data: &str,
     ) -> Result<i32> {
-        // 1. 从池中获取或创建实例
-        let mut instance = self.acquire_instance(plugin_id)?;
+        self.with_instance(plugin_id, |instance| {
+            self.execute_plugin_call(instance, function_name, input_data)
+        })
+    }
 
-        // 2. 执行插件调用
-        let result = self.execute_plugin_call(&mut instance, function_name, input_data);
+    /// 调用插件并取回数据
+    ///
+    /// 入口函数返回 `i64` 时按 ABI v1 读取插件返回的 [`PluginResponse`]，
+    /// `data` 缺省时返回 `None`；返回 `i32` 的旧 ABI 插件只有状态码，负数视为失败，成功时返回 `None`。
+    pub fn call_plugin_json(
+        &self,
+        plugin_id: &str,
+        function_name: &str,
+        input_data: &str,
+    ) -> Result<Option<serde_json::Value>> {
+        self.with_instance(plugin_id, |instance| {
+            match returns_i64(instance, function_name)? {
+                true => self.execute_data_call(instance, function_name, input_data),
+                false => self
+                    .execute_plugin_call(instance, function_name, input_data)
+                    .and_then(|code| match code {
+                        code if code < 0 => Err(anyhow!("Plugin returned error code {}", code)),
+                        _ => Ok(None),
+                    }),
+            }
+        })
+    }
 
-        // 3. 归还实例到池中（无论成功失败）
-        self.release_instance(plugin_id, instance)?;
+    /// 取出实例执行一次调用：重置 fuel 与超时，记录统计，归还或丢弃实例
+    fn with_instance<T>(
+        &self,
+        plugin_id: &str,
+        call: impl FnOnce(&mut PluginInstance) -> Result<T>,
+    ) -> Result<T> {
+        let mut instance = self.acquire_instance(plugin_id)?;
+        self.host.prepare_call(&mut instance.store)?;
+
+        let started = Instant::now();
+        let result = call(&mut instance);
+        let elapsed = started.elapsed();
+
+        let trap = result.as_ref().err().and_then(TrapKind::classify);
+        instance.stats.record_call(plugin_id, elapsed, result.is_err(), trap);
+        self.finish_call(plugin_id, instance, trap)?;
+
+        result.map_err(|e| match trap {
+            Some(TrapKind::Fuel) => e.context(format!("Plugin '{}' exceeded its CPU budget", plugin_id)),
+            Some(TrapKind::Timeout) => e.context(format!(
+                "Plugin '{}' timed out after {:?}",
+                plugin_id,
+                self.host.resource_limits().call_timeout
+            )),
+            _ => e,
+        })
+    }
 
-        result
+    /// 插件声明的 ABI 版本（未声明为 0）
+    pub fn abi_version(&self, plugin_id: &str) -> Result<i32> {
+        let plugins = self
+            .plugins
+            .read()
+            .map_err(|e| anyhow!("Failed to acquire read lock: {}", e))?;
+        plugins
+            .get(plugin_id)
+            .map(|pool| pool.abi_version)
+            .ok_or_else(|| anyhow!("Plugin not found: {}", plugin_id))
     }
 
     /// 从实例池获取一个可用实例
@@ -104,6 +392,24 @@ impl PluginManager {
         let pool = plugins
             .get_mut(plugin_id)
             .ok_or_else(|| anyhow!("Plugin not found: {}", plugin_id))?;
+        if !pool.enabled {
+            bail!("Plugin disabled: {}", plugin_id);
+        }
+        if let Some(until) = pool.quarantined_until {
+            let now = Instant::now();
+            if now < until {
+                bail!(
+                    "Plugin quarantined: {} ({}s remaining)",
+                    plugin_id,
+                    (until - now).as_secs() + 1
+                );
+            }
+            // 隔离期满，放行试探调用，再次 trap 立即重新隔离
+            tracing::info!("Plugin '{}' quarantine expired, allowing a trial call", plugin_id);
+            pool.quarantined_until = None;
+            pool.consecutive_traps = self.quarantine.max_consecutive_traps.saturating_sub(1);
+            stats::set_quarantined(plugin_id, false);
+        }
 
         // 尝试从池中获取实例
         if let Some(instance) = pool.available.pop() {
@@ -111,7 +417,8 @@ impl PluginManager {
                 "Reusing instance from pool for plugin '{}' (pool hit)",
                 plugin_id
             );
-            // Metrics: 池命中
+            pool.stats.record_pool(plugin_id, true);
+            stats::set_pool_available(plugin_id, pool.available.len());
             return Ok(instance);
         }
 
@@ -120,16 +427,21 @@ impl PluginManager {
             "Creating new instance for plugin '{}' (pool miss)",
             plugin_id
         );
-        // Metrics: 池未命中
+        pool.stats.record_pool(plugin_id, false);
 
         if pool.available.len() < pool.max_size {
             let linker = self.host.create_linker();
-            let mut store = self.host.create_store();
+            let mut store = self.host.create_store(pool.host.clone());
             let instance = linker
                 .instantiate(&mut store, &pool.module)
                 .context("Failed to instantiate plugin from pool")?;
 
-            Ok(PluginInstance { store, instance })
+            Ok(PluginInstance {
+                store,
+                instance,
+                generation: pool.generation,
+                stats: pool.stats.clone(),
+            })
         } else {
             // 池已满且无可用实例，创建临时实例
             tracing::warn!(
@@ -137,31 +449,63 @@ impl PluginManager {
                 plugin_id
             );
             let linker = self.host.create_linker();
-            let mut store = self.host.create_store();
+            let mut store = self.host.create_store(pool.host.clone());
             let instance = linker
                 .instantiate(&mut store, &pool.module)
                 .context("Failed to instantiate temporary plugin instance")?;
 
-            Ok(PluginInstance { store, instance })
+            Ok(PluginInstance {
+                store,
+                instance,
+                generation: pool.generation,
+                stats: pool.stats.clone(),
+            })
         }
     }
 
-    /// 归还实例到池中
-    fn release_instance(&self, plugin_id: &str, instance: PluginInstance) -> Result<()> {
+    /// 调用结束后归还实例：更新连续 trap 计数与隔离状态，trap 后的实例状态不可信，直接丢弃
+    fn finish_call(&self, plugin_id: &str, instance: PluginInstance, trap: Option<TrapKind>) -> Result<()> {
         let mut plugins = self
             .plugins
             .write()
             .map_err(|e| anyhow!("Failed to acquire write lock: {}", e))?;
 
         if let Some(pool) = plugins.get_mut(plugin_id) {
-            // 只有池未满时才归还
-            if pool.available.len() < pool.max_size {
+            let current = pool.generation == instance.generation;
+            // 热更新前取出的实例属于旧版本，直接丢弃
+            if !current {
+                tracing::trace!("Discarding stale instance for plugin '{}'", plugin_id);
+            } else if let Some(kind) = trap {
+                pool.consecutive_traps += 1;
+                tracing::warn!(
+                    "Plugin '{}' trapped ({}), {} consecutive, discarding instance",
+                    plugin_id,
+                    kind.as_str(),
+                    pool.consecutive_traps
+                );
+                let policy = self.quarantine;
+                if policy.max_consecutive_traps > 0 && pool.consecutive_traps >= policy.max_consecutive_traps {
+                    tracing::error!(
+                        "Plugin '{}' quarantined for {:?} after {} consecutive traps",
+                        plugin_id,
+                        policy.duration,
+                        pool.consecutive_traps
+                    );
+                    pool.quarantined_until = Some(Instant::now() + policy.duration);
+                    stats::set_quarantined(plugin_id, true);
+                }
+            } else if pool.available.len() < pool.max_size {
+                // 只有池未满时才归还
                 pool.available.push(instance);
                 tracing::trace!("Returned instance to pool for plugin '{}'", plugin_id);
             } else {
                 // 池已满，丢弃实例（自动清理）
                 tracing::trace!("Pool full, discarding instance for plugin '{}'", plugin_id);
             }
+            if current && trap.is_none() {
+                pool.consecutive_traps = 0;
+            }
+            stats::set_pool_available(plugin_id, pool.available.len());
         }
 
         Ok(())
@@ -206,27 +550,74 @@ impl PluginManager {
         }
 
         // 3. Call the function
-        let result = target_fn.call(&mut *store, (ptr, len));
+        // 调用 trap 后实例会被丢弃，不再释放内存（fuel 耗尽时 dealloc 也会 trap）
+        let result = target_fn.call(&mut *store, (ptr, len))?;
 
-        // 4. 释放 Wasm 内存（无论函数调用成功与否）
-        let dealloc_result = dealloc_fn.call(&mut *store, (ptr, len));
+        // 4. 释放 Wasm 内存
+        if let Err(e) = dealloc_fn.call(&mut *store, (ptr, len)) {
+            tracing::error!("Failed to deallocate Wasm memory: {}", e);
+            return Err(e.context("Memory deallocation failed"));
+        }
+        Ok(result)
+    }
 
-        // 优先返回业务逻辑错误，但记录 dealloc 失败
-        match (result, dealloc_result) {
-            (Ok(r), Ok(())) => Ok(r),
-            (Ok(_), Err(e)) => {
-                tracing::error!("Failed to deallocate Wasm memory: {}", e);
-                Err(anyhow!("Memory deallocation failed: {}", e))
-            }
-            (Err(e), Ok(())) => Err(e),
-            (Err(e1), Err(e2)) => {
-                tracing::error!("Failed to deallocate Wasm memory: {}", e2);
-                Err(anyhow!(
-                    "Plugin call failed: {}, dealloc also failed: {}",
-                    e1,
-                    e2
-                ))
-            }
+    /// ABI v1 调用：写入输入，读取并释放插件分配的结果缓冲区
+    fn execute_data_call(
+        &self,
+        plugin: &mut PluginInstance,
+        function_name: &str,
+        input_data: &str,
+    ) -> Result<Option<serde_json::Value>> {
+        let instance = plugin.instance;
+        let store = &mut plugin.store;
+
+        let alloc_fn = instance
+            .get_typed_func::<i32, i32>(&mut *store, "alloc")
+            .context("Plugin must export 'alloc' function")?;
+        let dealloc_fn = instance
+            .get_typed_func::<(i32, i32), ()>(&mut *store, "dealloc")
+            .context("Plugin must export 'dealloc' function")?;
+        let target_fn = instance
+            .get_typed_func::<(i32, i32), i64>(&mut *store, function_name)
+            .context(format!("Plugin must export '{}' function", function_name))?;
+        let memory = instance
+            .get_memory(&mut *store, "memory")
+            .context("Plugin must export 'memory'")?;
+
+        // 1. 写入输入
+        let bytes = input_data.as_bytes();
+        let len = bytes.len() as i32;
+        let ptr = alloc_fn.call(&mut *store, len)?;
+        if let Err(e) = memory.write(&mut *store, ptr as usize, bytes) {
+            let _ = dealloc_fn.call(&mut *store, (ptr, len));
+            return Err(e.into());
+        }
+
+        // 2. 调用并释放输入（trap 后实例会被丢弃，不再释放）
+        let result = target_fn.call(&mut *store, (ptr, len))?;
+        dealloc_fn
+            .call(&mut *store, (ptr, len))
+            .context("Memory deallocation failed")?;
+        let (out_ptr, out_len) = unpack_ptr_len(result);
+        if out_len == 0 {
+            return Ok(None);
+        }
+
+        // 3. 读取并释放结果缓冲区
+        if out_len > MAX_RESPONSE_BYTES {
+            let _ = dealloc_fn.call(&mut *store, (out_ptr as i32, out_len as i32));
+            bail!("Plugin response too large: {} bytes", out_len);
+        }
+        let mut output = vec![0u8; out_len as usize];
+        let read = memory.read(&mut *store, out_ptr as usize, &mut output);
+        dealloc_fn
+            .call(&mut *store, (out_ptr as i32, out_len as i32))
+            .context("Memory deallocation failed")?;
+        read.context("Plugin response out of bounds")?;
+
+        match serde_json::from_slice(&output).context("Invalid plugin response")? {
+            PluginResponse::Ok { data } => Ok(data),
+            PluginResponse::Error { message } => Err(anyhow!("Plugin error: {}", message)),
         }
     }
 
@@ -248,6 +639,51 @@ impl PluginManager {
     }
 }
 
+impl PluginPool {
+    fn info(&self, plugin_id: &str) -> PluginInfo {
+        let manifest = &self.host.manifest;
+        PluginInfo {
+            id: plugin_id.to_string(),
+            version: manifest.version.clone(),
+            description: manifest.description.clone(),
+            abi_version: self.abi_version,
+            entry_points: manifest.entry_points.clone(),
+            capabilities: manifest.capabilities.clone(),
+            config_schema: manifest.config_schema.clone(),
+            enabled: self.enabled,
+            loaded_at: self.loaded_at,
+            pool_available: self.available.len(),
+            pool_max_size: self.max_size,
+            calls: self.stats.calls(),
+            errors: self.stats.errors(),
+            traps: self.stats.traps(),
+            pool_hits: self.stats.pool_hits(),
+            pool_misses: self.stats.pool_misses(),
+            latency_p99_ms: self.stats.latency_p99_ms(),
+            quarantined: self.quarantined_until.is_some_and(|until| until > Instant::now()),
+            consecutive_traps: self.consecutive_traps,
+        }
+    }
+}
+
+fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// 入口函数是否按 ABI v1 返回 `i64`
+fn returns_i64(plugin: &mut PluginInstance, function_name: &str) -> Result<bool> {
+    let func = plugin
+        .instance
+        .get_func(&mut plugin.store, function_name)
+        .ok_or_else(|| anyhow!("Plugin must export '{}' function", function_name))?;
+    let ty = func.ty(&plugin.store);
+    let returns_i64 = matches!(ty.results().next(), Some(wasmtime::ValType::I64));
+    Ok(returns_i64)
+}
+
 /// 插件池统计信息
 #[derive(Debug, Clone)]
 pub struct PoolStats {
@@ -430,4 +866,342 @@ mod tests {
         // 插件应该仍然可以调用
         assert!(manager.call_plugin("reload_test", "on_msg", "test").is_ok());
     }
+
+    /// 用 WAT 构造 ABI v1 测试插件（简单的递增分配器，结果放在数据段）
+    fn abi_v1_module(version: i32) -> String {
+        let ok = r#"{"status":"ok","data":{"topic":"out"}}"#;
+        let drop = r#"{"status":"ok","data":null}"#;
+        let error = r#"{"status":"error","message":"boom"}"#;
+        let result = |offset: usize, body: &str| {
+            format!(
+                "i64.const {} i64.const 32 i64.shl i64.const {} i64.or",
+                offset,
+                body.len()
+            )
+        };
+        format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (global $heap (mut i32) (i32.const 8192))
+                (func (export "alloc") (param $len i32) (result i32)
+                    (local $ptr i32)
+                    global.get $heap
+                    local.set $ptr
+                    global.get $heap
+                    local.get $len
+                    i32.add
+                    global.set $heap
+                    local.get $ptr)
+                (func (export "dealloc") (param i32 i32))
+                (func (export "flux_abi_version") (result i32) i32.const {version})
+                (data (i32.const 1024) "{ok}")
+                (data (i32.const 2048) "{drop}")
+                (data (i32.const 3072) "{error}")
+                (func (export "transform") (param i32 i32) (result i64) {transform})
+                (func (export "filter") (param i32 i32) (result i64) {filter})
+                (func (export "fail") (param i32 i32) (result i64) {fail})
+                (func (export "pass") (param i32 i32) (result i64) i64.const 0)
+                (func (export "legacy") (param i32 i32) (result i32) local.get 1)
+                (func (export "legacy_fail") (param i32 i32) (result i32) i32.const -1))"#,
+            version = version,
+            ok = ok.replace('"', "\\\""),
+            drop = drop.replace('"', "\\\""),
+            error = error.replace('"', "\\\""),
+            transform = result(1024, ok),
+            filter = result(2048, drop),
+            fail = result(3072, error),
+        )
+    }
+
+    #[test]
+    fn test_call_plugin_json_abi_v1() {
+        let manager = PluginManager::new().unwrap();
+        manager.load_plugin("abi", abi_v1_module(1).as_bytes()).unwrap();
+        assert_eq!(manager.abi_version("abi").unwrap(), 1);
+
+        let input = r#"{"topic":"in","payload":{}}"#;
+        let data = manager.call_plugin_json("abi", "transform", input).unwrap();
+        assert_eq!(data, Some(serde_json::json!({"topic": "out"})));
+        assert_eq!(
+            manager.call_plugin_json("abi", "filter", input).unwrap(),
+            Some(serde_json::Value::Null)
+        );
+        assert_eq!(manager.call_plugin_json("abi", "pass", input).unwrap(), None);
+
+        let err = manager.call_plugin_json("abi", "fail", input).unwrap_err();
+        assert!(err.to_string().contains("boom"));
+    }
+
+    #[test]
+    fn test_call_plugin_json_legacy_abi() {
+        let manager = PluginManager::new().unwrap();
+        manager.load_plugin("abi", abi_v1_module(1).as_bytes()).unwrap();
+
+        // 返回 i32 的入口函数按旧 ABI 处理
+        assert_eq!(manager.call_plugin_json("abi", "legacy", "abc").unwrap(), None);
+        assert!(manager.call_plugin_json("abi", "legacy_fail", "abc").is_err());
+        assert_eq!(manager.call_plugin("abi", "legacy", "abc").unwrap(), 3);
+        assert!(manager.call_plugin_json("abi", "missing", "abc").is_err());
+    }
+
+    #[test]
+    fn test_rejects_newer_abi_version() {
+        let manager = PluginManager::new().unwrap();
+        let err = manager.load_plugin("future", abi_v1_module(99).as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("ABI version 99"));
+    }
+    /// 导入 Host 函数的测试插件
+    fn host_module() -> &'static str {
+        r#"(module
+            (import "env" "flux_time_now_ms" (func $now (result i64)))
+            (import "env" "flux_kv_get" (func $kv_get (param i32 i32) (result i64)))
+            (import "env" "flux_kv_set" (func $kv_set (param i32 i32 i32 i32) (result i32)))
+            (import "env" "flux_publish" (func $publish (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (global $heap (mut i32) (i32.const 8192))
+            (func (export "alloc") (param $len i32) (result i32)
+                (local $ptr i32)
+                global.get $heap
+                local.set $ptr
+                global.get $heap
+                local.get $len
+                i32.add
+                global.set $heap
+                local.get $ptr)
+            (func (export "dealloc") (param i32 i32))
+            (data (i32.const 0) "k")
+            (data (i32.const 16) "alerts")
+            (func (export "now") (param i32 i32) (result i32)
+                call $now
+                i64.const 0
+                i64.lt_s
+                if (result i32) i32.const -1 else i32.const 1 end)
+            (func (export "remember") (param i32 i32) (result i32)
+                (call $kv_set (i32.const 0) (i32.const 1) (local.get 0) (local.get 1)))
+            (func (export "recall") (param i32 i32) (result i64)
+                (call $kv_get (i32.const 0) (i32.const 1)))
+            (func (export "emit") (param i32 i32) (result i32)
+                (call $publish (i32.const 16) (i32.const 6) (local.get 0) (local.get 1))))"#
+    }
+
+    #[test]
+    fn test_host_capabilities_denied_by_default() {
+        let manager = PluginManager::new().unwrap();
+        manager.load_plugin("sandboxed", host_module().as_bytes()).unwrap();
+
+        let denied = flux_types::plugin::HOST_ERR_DENIED;
+        assert_eq!(manager.call_plugin("sandboxed", "now", "").unwrap(), denied);
+        assert_eq!(manager.call_plugin("sandboxed", "remember", "x").unwrap(), denied);
+        assert_eq!(manager.call_plugin("sandboxed", "emit", "{}").unwrap(), denied);
+    }
+
+    #[test]
+    fn test_host_capabilities_granted_by_manifest() {
+        let bus = Arc::new(flux_core::bus::EventBus::new(16));
+        let mut events = bus.subscribe();
+        let manager = PluginManager::new().unwrap().with_event_bus(bus);
+        let manifest = PluginManifest::from_toml(r#"capabilities = ["time", "kv", "publish"]"#).unwrap();
+        manager
+            .load_plugin_with_manifest("trusted", host_module().as_bytes(), manifest)
+            .unwrap();
+
+        assert_eq!(manager.call_plugin("trusted", "now", "").unwrap(), 1);
+
+        // KV 中保存一个完整响应，recall 原样返回给 Host
+        assert_eq!(manager.call_plugin_json("trusted", "recall", "").unwrap(), None);
+        let stored = r#"{"status":"ok","data":42}"#;
+        assert_eq!(manager.call_plugin("trusted", "remember", stored).unwrap(), 0);
+        assert_eq!(
+            manager.call_plugin_json("trusted", "recall", "").unwrap(),
+            Some(serde_json::json!(42))
+        );
+
+        assert_eq!(manager.call_plugin("trusted", "emit", r#"{"level":"high"}"#).unwrap(), 0);
+        let message = events.try_recv().unwrap();
+        assert_eq!(message.topic, "alerts");
+        assert_eq!(message.payload, serde_json::json!({"level": "high"}));
+        assert_eq!(
+            manager.call_plugin("trusted", "emit", "not json").unwrap(),
+            flux_types::plugin::HOST_ERR_INVALID
+        );
+    }
+    #[test]
+    fn test_hot_swap_keeps_in_flight_calls() {
+        let manager = PluginManager::new().unwrap();
+        manager.load_plugin("swap", abi_v1_module(1).as_bytes()).unwrap();
+
+        // 模拟进行中的调用：替换前取出的实例仍可完成调用
+        let mut in_flight = manager.acquire_instance("swap").unwrap();
+        manager.load_plugin("swap", host_module().as_bytes()).unwrap();
+        let data = manager.execute_data_call(&mut in_flight, "transform", "{}").unwrap();
+        assert_eq!(data, Some(serde_json::json!({"topic": "out"})));
+        manager.finish_call("swap", in_flight, None).unwrap();
+
+        // 旧实例不会回到新池中，新调用使用新版本
+        assert_eq!(manager.get_pool_stats("swap").unwrap().available, 1);
+        assert!(manager.call_plugin_json("swap", "transform", "{}").is_err());
+        assert_eq!(manager.abi_version("swap").unwrap(), 0);
+
+        assert!(manager.unload_plugin("swap").unwrap());
+        assert!(!manager.unload_plugin("swap").unwrap());
+        assert!(manager.call_plugin("swap", "now", "").is_err());
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_previous_version() {
+        let manager = PluginManager::new().unwrap();
+        let manifest = PluginManifest {
+            version: "1.0.0".to_string(),
+            abi_version: Some(1),
+            entry_points: vec!["transform".to_string()],
+            ..Default::default()
+        };
+        manager
+            .load_plugin_with_manifest("versioned", abi_v1_module(1).as_bytes(), manifest.clone())
+            .unwrap();
+
+        let missing_entry = PluginManifest {
+            version: "2.0.0".to_string(),
+            entry_points: vec!["missing".to_string()],
+            ..Default::default()
+        };
+        let err = manager
+            .load_plugin_with_manifest("versioned", abi_v1_module(1).as_bytes(), missing_entry)
+            .unwrap_err();
+        assert!(err.to_string().contains("entry point 'missing'"));
+
+        // 清单声明的 ABI 版本与模块不一致
+        let err = manager
+            .load_plugin_with_manifest("versioned", host_module().as_bytes(), manifest)
+            .unwrap_err();
+        assert!(err.to_string().contains("declares ABI version 1"));
+
+        let mismatched = PluginManifest {
+            id: "other".to_string(),
+            ..Default::default()
+        };
+        assert!(manager
+            .load_plugin_with_manifest("versioned", abi_v1_module(1).as_bytes(), mismatched)
+            .is_err());
+
+        let info = manager.plugin_info("versioned").unwrap();
+        assert_eq!(info.version, "1.0.0");
+        assert_eq!(info.entry_points, vec!["transform".to_string()]);
+        assert!(manager.call_plugin_json("versioned", "transform", "{}").is_ok());
+    }
+
+    #[test]
+    fn test_enable_disable_survives_reload() {
+        let manager = PluginManager::new().unwrap();
+        let manifest = PluginManifest::from_toml(r#"capabilities = ["kv"]"#).unwrap();
+        manager
+            .load_plugin_with_manifest("toggle", host_module().as_bytes(), manifest.clone())
+            .unwrap();
+        let stored = r#"{"status":"ok","data":"kept"}"#;
+        assert_eq!(manager.call_plugin("toggle", "remember", stored).unwrap(), 0);
+
+        manager.set_enabled("toggle", false).unwrap();
+        let err = manager.call_plugin("toggle", "remember", stored).unwrap_err();
+        assert!(err.to_string().contains("disabled"));
+
+        // 重新加载沿用停用状态与 KV 状态
+        manager
+            .load_plugin_with_manifest("toggle", host_module().as_bytes(), manifest)
+            .unwrap();
+        assert!(!manager.plugin_info("toggle").unwrap().enabled);
+        manager.set_enabled("toggle", true).unwrap();
+        assert_eq!(
+            manager.call_plugin_json("toggle", "recall", "").unwrap(),
+            Some(serde_json::json!("kept"))
+        );
+
+        manager.load_plugin("another", abi_v1_module(1).as_bytes()).unwrap();
+        let ids: Vec<_> = manager.list_plugins().unwrap().into_iter().map(|info| info.id).collect();
+        assert_eq!(ids, vec!["another", "toggle"]);
+        assert!(manager.set_enabled("missing", true).is_err());
+    }
+
+    /// 死循环测试插件
+    fn spin_module() -> &'static str {
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "dealloc") (param i32 i32))
+            (func (export "spin") (param i32 i32) (result i32)
+                (loop $forever br $forever)
+                i32.const 0)
+            (func (export "ok") (param i32 i32) (result i32) i32.const 7))"#
+    }
+
+    fn limited_manager(fuel_per_call: u64, call_timeout: Duration) -> PluginManager {
+        let limits = WasmResourceLimits {
+            fuel_per_call,
+            call_timeout,
+            ..Default::default()
+        };
+        PluginManager::with_limits(1, limits).unwrap()
+    }
+
+    #[test]
+    fn test_fuel_limit_stops_infinite_loop() {
+        let manager = limited_manager(100_000, Duration::from_secs(60));
+        manager.load_plugin("spin", spin_module().as_bytes()).unwrap();
+
+        let err = manager.call_plugin("spin", "spin", "").unwrap_err();
+        assert_eq!(TrapKind::classify(&err), Some(TrapKind::Fuel));
+        assert!(err.to_string().contains("CPU budget"));
+
+        // 每次调用重新计算预算，trap 的实例被丢弃
+        assert_eq!(manager.call_plugin("spin", "ok", "").unwrap(), 7);
+        assert_eq!(manager.call_plugin("spin", "ok", "").unwrap(), 7);
+
+        let info = manager.plugin_info("spin").unwrap();
+        assert_eq!((info.calls, info.errors, info.traps), (3, 1, 1));
+        assert_eq!((info.pool_hits, info.pool_misses), (2, 1));
+        assert_eq!(info.consecutive_traps, 0);
+        assert!(info.latency_p99_ms.is_some());
+    }
+
+    #[test]
+    fn test_call_timeout_stops_infinite_loop() {
+        let manager = limited_manager(u64::MAX / 4, Duration::from_millis(100));
+        manager.load_plugin("spin", spin_module().as_bytes()).unwrap();
+
+        let started = Instant::now();
+        let err = manager.call_plugin_json("spin", "spin", "").unwrap_err();
+        assert_eq!(TrapKind::classify(&err), Some(TrapKind::Timeout));
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(manager.call_plugin("spin", "ok", "").unwrap(), 7);
+    }
+
+    #[test]
+    fn test_quarantine_after_repeated_traps() {
+        let policy = QuarantinePolicy {
+            max_consecutive_traps: 2,
+            duration: Duration::from_millis(200),
+        };
+        let manager = limited_manager(100_000, Duration::from_secs(60)).with_quarantine(policy);
+        manager.load_plugin("spin", spin_module().as_bytes()).unwrap();
+
+        assert!(manager.call_plugin("spin", "spin", "").is_err());
+        assert!(!manager.plugin_info("spin").unwrap().quarantined);
+        assert!(manager.call_plugin("spin", "spin", "").is_err());
+        let err = manager.call_plugin("spin", "ok", "").unwrap_err();
+        assert!(err.to_string().contains("quarantined"));
+        assert!(manager.plugin_info("spin").unwrap().quarantined);
+
+        // 隔离期满后的试探调用再次 trap，立即重新隔离
+        std::thread::sleep(Duration::from_millis(250));
+        assert!(manager.call_plugin("spin", "spin", "").is_err());
+        assert!(manager.call_plugin("spin", "ok", "").unwrap_err().to_string().contains("quarantined"));
+
+        // 试探调用成功后恢复
+        std::thread::sleep(Duration::from_millis(250));
+        assert_eq!(manager.call_plugin("spin", "ok", "").unwrap(), 7);
+        let info = manager.plugin_info("spin").unwrap();
+        assert!(!info.quarantined);
+        assert_eq!(info.consecutive_traps, 0);
+        assert_eq!(info.traps, 3);
+    }
 }
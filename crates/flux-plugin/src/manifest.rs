@@ -0,0 +1,216 @@
+//! 插件清单：插件元数据与所需的 Host 能力，未声明的能力一律拒绝
+//!
+//! ```toml
+//! id = "enricher"
+//! version = "1.2.0"
+//! abi_version = 1
+//! entry_points = ["enrich"]
+//! capabilities = ["kv", "publish", "http"]
+//! http_allow = ["https://api.example.com/v1/"]
+//!
+//! [config_schema]
+//! type = "object"
+//! ```
+
+use anyhow::{bail, Context, Result};
+use flux_types::plugin::PLUGIN_ABI_VERSION;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// Host 能力
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// 插件私有的键值状态
+    Kv,
+    /// 向事件总线发布消息
+    Publish,
+    /// 读取设备元数据
+    DeviceRead,
+    /// 读取当前时间
+    Time,
+    /// 访问允许列表内的 HTTP 地址
+    Http,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginManifest {
+    /// 插件 ID（缺省取文件名或目录名）
+    pub id: String,
+    pub version: String,
+    pub description: Option<String>,
+    /// 要求的 ABI 版本（声明时须与模块导出的版本一致）
+    pub abi_version: Option<i32>,
+    /// 目录包内的 Wasm 文件名（默认 `plugin.wasm`）
+    pub wasm: Option<String>,
+    /// 入口函数（加载时校验模块已导出）
+    pub entry_points: Vec<String>,
+    pub capabilities: Vec<Capability>,
+    /// HTTP 允许列表（URL 前缀，协议、主机、端口须一致，路径按段前缀匹配）
+    pub http_allow: Vec<String>,
+    /// 插件配置的 JSON Schema
+    pub config_schema: Option<serde_json::Value>,
+}
+
+impl Default for PluginManifest {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            version: "0.0.0".to_string(),
+            description: None,
+            abi_version: None,
+            wasm: None,
+            entry_points: Vec::new(),
+            capabilities: Vec::new(),
+            http_allow: Vec::new(),
+            config_schema: None,
+        }
+    }
+}
+
+impl PluginManifest {
+    pub fn from_toml(content: &str) -> Result<Self> {
+        toml::from_str(content).context("Invalid plugin manifest")
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).context("Failed to serialize plugin manifest")
+    }
+
+    /// 校验元数据（ID 会用作目录名，只允许字母、数字、`-` 和 `_`）
+    pub fn validate(&self) -> Result<()> {
+        if !is_valid_id(&self.id) {
+            bail!("Invalid plugin id '{}'", self.id);
+        }
+        if self.version.trim().is_empty() {
+            bail!("Plugin '{}' has an empty version", self.id);
+        }
+        if let Some(version) = self.abi_version {
+            if !(0..=PLUGIN_ABI_VERSION).contains(&version) {
+                bail!(
+                    "Plugin '{}' requires ABI version {}, host supports up to {}",
+                    self.id,
+                    version,
+                    PLUGIN_ABI_VERSION
+                );
+            }
+        }
+        if let Some(wasm) = &self.wasm {
+            if wasm.is_empty() || wasm.contains(['/', '\\']) || wasm.starts_with('.') {
+                bail!("Plugin '{}' has an invalid wasm file name '{}'", self.id, wasm);
+            }
+        }
+        if self.config_schema.as_ref().is_some_and(|schema| !schema.is_object()) {
+            bail!("Plugin '{}' config_schema must be an object", self.id);
+        }
+        Ok(())
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// URL 是否在 HTTP 允许列表内
+    pub fn http_allowed(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+        self.http_allow
+            .iter()
+            .filter_map(|prefix| Url::parse(prefix).ok())
+            .any(|prefix| {
+                prefix.scheme() == url.scheme()
+                    && prefix.host_str() == url.host_str()
+                    && prefix.port_or_known_default() == url.port_or_known_default()
+                    && path_has_prefix(url.path(), prefix.path())
+            })
+    }
+}
+
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn path_has_prefix(path: &str, prefix: &str) -> bool {
+    if prefix.ends_with('/') {
+        return path.starts_with(prefix);
+    }
+    path == prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_denies_by_default() {
+        let manifest = PluginManifest::default();
+        assert!(!manifest.allows(Capability::Kv));
+        assert!(!manifest.http_allowed("https://example.com/"));
+
+        let manifest = PluginManifest::from_toml(
+            r#"
+capabilities = ["kv", "time"]
+"#,
+        )
+        .unwrap();
+        assert!(manifest.allows(Capability::Kv));
+        assert!(manifest.allows(Capability::Time));
+        assert!(!manifest.allows(Capability::Publish));
+        assert!(PluginManifest::from_toml(r#"capabilities = ["root"]"#).is_err());
+    }
+
+    #[test]
+    fn test_http_allow_list() {
+        let manifest = PluginManifest {
+            capabilities: vec![Capability::Http],
+            http_allow: vec!["https://api.example.com/v1".to_string(), "http://10.0.0.5:8080/".to_string()],
+            ..Default::default()
+        };
+        assert!(manifest.http_allowed("https://api.example.com/v1"));
+        assert!(manifest.http_allowed("https://api.example.com/v1/devices?id=1"));
+        assert!(manifest.http_allowed("http://10.0.0.5:8080/anything"));
+
+        assert!(!manifest.http_allowed("https://api.example.com/v10"));
+        assert!(!manifest.http_allowed("http://api.example.com/v1"));
+        assert!(!manifest.http_allowed("https://api.example.com.evil.com/v1"));
+        assert!(!manifest.http_allowed("https://api.example.com:8443/v1"));
+        assert!(!manifest.http_allowed("http://10.0.0.5/anything"));
+        assert!(!manifest.http_allowed("not a url"));
+    }
+
+    #[test]
+    fn test_manifest_metadata() {
+        let manifest = PluginManifest::from_toml(
+            r#"
+id = "enricher"
+version = "1.2.0"
+abi_version = 1
+entry_points = ["enrich"]
+
+[config_schema]
+type = "object"
+required = ["site"]
+"#,
+        )
+        .unwrap();
+        assert!(manifest.validate().is_ok());
+        assert_eq!(manifest.config_schema.as_ref().unwrap()["required"][0], "site");
+        assert_eq!(PluginManifest::from_toml(&manifest.to_toml().unwrap()).unwrap(), manifest);
+
+        let invalid = |f: fn(&mut PluginManifest)| {
+            let mut manifest = manifest.clone();
+            f(&mut manifest);
+            manifest.validate().is_err()
+        };
+        assert!(invalid(|m| m.id = "../etc".to_string()));
+        assert!(invalid(|m| m.id.clear()));
+        assert!(invalid(|m| m.version.clear()));
+        assert!(invalid(|m| m.abi_version = Some(99)));
+        assert!(invalid(|m| m.wasm = Some("../x.wasm".to_string())));
+        assert!(invalid(|m| m.config_schema = Some(serde_json::json!("string"))));
+    }
+}
@@ -0,0 +1,177 @@
+//! 插件调用统计与 Prometheus 指标（均带 `plugin` 标签）
+//!
+//! - `flux_plugin_calls_total`、`flux_plugin_failures_total`
+//! - `flux_plugin_traps_total`（`kind`：`fuel`、`timeout`、`trap`）
+//! - `flux_plugin_duration_seconds`
+//! - `flux_plugin_pool_hits_total`、`flux_plugin_pool_misses_total`、`flux_plugin_pool_available`
+//! - `flux_plugin_quarantined`
+//! - `flux_plugins_loaded`（无标签）
+
+use metrics::{counter, gauge, histogram};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use wasmtime::Trap;
+
+/// 计算延迟分位数的采样窗口大小
+const LATENCY_WINDOW: usize = 1024;
+
+/// Trap 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapKind {
+    /// fuel 耗尽（CPU 预算）
+    Fuel,
+    /// 超过调用超时（epoch 中断）
+    Timeout,
+    /// 其他 trap（unreachable、越界访问等）
+    Trap,
+}
+
+impl TrapKind {
+    /// 从调用错误中识别 trap，普通错误返回 `None`
+    pub fn classify(error: &anyhow::Error) -> Option<Self> {
+        error.chain().find_map(|cause| cause.downcast_ref::<Trap>()).map(|trap| match trap {
+            Trap::OutOfFuel => TrapKind::Fuel,
+            Trap::Interrupt => TrapKind::Timeout,
+            _ => TrapKind::Trap,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrapKind::Fuel => "fuel",
+            TrapKind::Timeout => "timeout",
+            TrapKind::Trap => "trap",
+        }
+    }
+}
+
+/// 单个插件的累计统计（热更新时沿用）
+#[derive(Debug, Default)]
+pub(crate) struct PluginStats {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    traps: AtomicU64,
+    pool_hits: AtomicU64,
+    pool_misses: AtomicU64,
+    latencies: Mutex<LatencyWindow>,
+}
+
+#[derive(Debug, Default)]
+struct LatencyWindow {
+    samples: Vec<Duration>,
+    next: usize,
+}
+
+impl PluginStats {
+    pub(crate) fn record_pool(&self, plugin_id: &str, hit: bool) {
+        if hit {
+            self.pool_hits.fetch_add(1, Ordering::Relaxed);
+            counter!("flux_plugin_pool_hits_total", 1, "plugin" => plugin_id.to_string());
+        } else {
+            self.pool_misses.fetch_add(1, Ordering::Relaxed);
+            counter!("flux_plugin_pool_misses_total", 1, "plugin" => plugin_id.to_string());
+        }
+    }
+
+    pub(crate) fn record_call(&self, plugin_id: &str, elapsed: Duration, failed: bool, trap: Option<TrapKind>) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        counter!("flux_plugin_calls_total", 1, "plugin" => plugin_id.to_string());
+        histogram!("flux_plugin_duration_seconds", elapsed.as_secs_f64(), "plugin" => plugin_id.to_string());
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            counter!("flux_plugin_failures_total", 1, "plugin" => plugin_id.to_string());
+        }
+        if let Some(kind) = trap {
+            self.traps.fetch_add(1, Ordering::Relaxed);
+            counter!(
+                "flux_plugin_traps_total",
+                1,
+                "plugin" => plugin_id.to_string(),
+                "kind" => kind.as_str()
+            );
+        }
+
+        if let Ok(mut window) = self.latencies.lock() {
+            if window.samples.len() < LATENCY_WINDOW {
+                window.samples.push(elapsed);
+            } else {
+                let next = window.next;
+                window.samples[next] = elapsed;
+            }
+            window.next = (window.next + 1) % LATENCY_WINDOW;
+        }
+    }
+
+    pub(crate) fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn traps(&self) -> u64 {
+        self.traps.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn pool_hits(&self) -> u64 {
+        self.pool_hits.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn pool_misses(&self) -> u64 {
+        self.pool_misses.load(Ordering::Relaxed)
+    }
+
+    /// 最近调用的 p99 延迟（毫秒），无调用时为 `None`
+    pub(crate) fn latency_p99_ms(&self) -> Option<f64> {
+        let mut samples = self.latencies.lock().ok()?.samples.clone();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+        let rank = (samples.len() * 99).div_ceil(100).max(1);
+        Some(samples[rank - 1].as_secs_f64() * 1000.0)
+    }
+}
+
+pub(crate) fn set_pool_available(plugin_id: &str, available: usize) {
+    gauge!("flux_plugin_pool_available", available as f64, "plugin" => plugin_id.to_string());
+}
+
+pub(crate) fn set_quarantined(plugin_id: &str, quarantined: bool) {
+    gauge!(
+        "flux_plugin_quarantined",
+        if quarantined { 1.0 } else { 0.0 },
+        "plugin" => plugin_id.to_string()
+    );
+}
+
+pub(crate) fn set_loaded_plugins(count: usize) {
+    gauge!("flux_plugins_loaded", count as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_p99() {
+        let stats = PluginStats::default();
+        assert_eq!(stats.latency_p99_ms(), None);
+
+        for ms in 1..=100 {
+            stats.record_call("p", Duration::from_millis(ms), false, None);
+        }
+        assert_eq!(stats.latency_p99_ms(), Some(99.0));
+
+        // 窗口满后覆盖最早的样本
+        for _ in 0..LATENCY_WINDOW {
+            stats.record_call("p", Duration::from_millis(1), true, Some(TrapKind::Fuel));
+        }
+        assert_eq!(stats.latency_p99_ms(), Some(1.0));
+        assert_eq!(stats.calls(), 100 + LATENCY_WINDOW as u64);
+        assert_eq!(stats.errors(), LATENCY_WINDOW as u64);
+        assert_eq!(stats.traps(), LATENCY_WINDOW as u64);
+    }
+}
@@ -1,4 +1,8 @@
+use crate::host::{self, PluginHostContext};
 use flux_core::error::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use wasmtime::{Caller, Config, Engine, Linker, Module, ResourceLimiter, Store};
 
 /// Wasm 资源限制配置
@@ -14,6 +18,10 @@ pub struct WasmResourceLimits {
     pub max_tables: usize,
     /// 最大内存数量
     pub max_memories: usize,
+    /// 单次调用的 fuel 上限（约等于执行的 Wasm 指令数）
+    pub fuel_per_call: u64,
+    /// 单次调用的超时时间（epoch 中断，包含 Host 函数耗时）
+    pub call_timeout: Duration,
 }
 
 impl Default for WasmResourceLimits {
@@ -24,10 +32,15 @@ impl Default for WasmResourceLimits {
             max_instances: 10,
             max_tables: 1,
             max_memories: 1,
+            fuel_per_call: 1_000_000_000,
+            call_timeout: Duration::from_secs(5),
         }
     }
 }
 
+/// epoch 计时间隔
+pub const EPOCH_TICK: Duration = Duration::from_millis(10);
+
 /// 资源限制器实现
 pub struct WasmResourceLimiter {
     max_memory_bytes: usize,
@@ -81,9 +94,23 @@ impl ResourceLimiter for WasmResourceLimiter {
     }
 }
 
+/// Store 关联数据：资源限制器与插件的 Host 上下文
+pub struct PluginState {
+    limiter: WasmResourceLimiter,
+    pub host: Arc<PluginHostContext>,
+}
+
 pub struct WasmHost {
     engine: Engine,
     resource_limits: WasmResourceLimits,
+    // epoch 计时线程的停止标志
+    ticker_stop: Arc<AtomicBool>,
+}
+
+impl Drop for WasmHost {
+    fn drop(&mut self) {
+        self.ticker_stop.store(true, Ordering::Relaxed);
+    }
 }
 
 impl WasmHost {
@@ -104,50 +131,93 @@ impl WasmHost {
         config.cranelift_opt_level(wasmtime::OptLevel::Speed);
 
         let engine = Engine::new(&config)?;
+
+        // 定时推进 epoch，调用超过期限时在 Wasm 代码中触发中断
+        let ticker_stop = Arc::new(AtomicBool::new(false));
+        let ticker_engine = engine.clone();
+        let stop = ticker_stop.clone();
+        std::thread::Builder::new()
+            .name("wasm-epoch".to_string())
+            .spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(EPOCH_TICK);
+                    ticker_engine.increment_epoch();
+                }
+            })?;
+
         Ok(Self {
             engine,
             resource_limits,
+            ticker_stop,
         })
     }
 
+    pub fn resource_limits(&self) -> &WasmResourceLimits {
+        &self.resource_limits
+    }
+
+    /// 每次调用前重置 fuel 与 epoch 期限
+    pub fn prepare_call(&self, store: &mut Store<PluginState>) -> anyhow::Result<()> {
+        let budget = self.resource_limits.fuel_per_call;
+        let remaining = store.fuel_remaining().unwrap_or(0);
+        if remaining < budget {
+            store.add_fuel(budget - remaining)?;
+        } else if remaining > budget {
+            store.consume_fuel(remaining - budget)?;
+        }
+
+        // 向上取整并多留一个间隔，当前间隔已经过去的部分不计入
+        let timeout = self.resource_limits.call_timeout.as_millis();
+        let ticks = timeout.div_ceil(EPOCH_TICK.as_millis()).min(u32::MAX as u128) as u64 + 1;
+        store.set_epoch_deadline(ticks);
+        Ok(())
+    }
+
     pub fn load_module(&self, wasm_bytes: &[u8]) -> Result<Module> {
         Ok(Module::new(&self.engine, wasm_bytes)?)
     }
 
     /// 创建带资源限制的 Store
-    pub fn create_store(&self) -> Store<WasmResourceLimiter> {
+    pub fn create_store(&self, host: Arc<PluginHostContext>) -> Store<PluginState> {
         let limiter = WasmResourceLimiter::new(&self.resource_limits);
-        let mut store = Store::new(&self.engine, limiter);
+        let mut store = Store::new(&self.engine, PluginState { limiter, host });
 
         // 设置资源限制器
-        store.limiter(|limiter| limiter);
+        store.limiter(|state| &mut state.limiter);
 
-        // 注意：fuel 功能需要在编译时启用特定特性
-        // 这里我们主要依赖 ResourceLimiter 来限制内存和表大小
-        // CPU 时间限制可以通过 epoch interruption 实现
+        // 引擎开启了 fuel 计量和 epoch 中断，Store 初始 fuel 与 epoch 期限均为 0，
+        // 不设置时任何调用都会立即 trap；实例化（含 start 函数）同样受单次调用限制
+        if let Err(e) = self.prepare_call(&mut store) {
+            tracing::error!("Failed to set Wasm store limits: {}", e);
+        }
 
         store
     }
 
-    /// Linker with default imports (logging, etc)
-    pub fn create_linker(&self) -> Linker<WasmResourceLimiter> {
+    /// Linker with default imports (logging, capability-gated host functions)
+    pub fn create_linker(&self) -> Linker<PluginState> {
         let mut linker = Linker::new(&self.engine);
 
         // 注册多级别日志函数
         Self::register_log_functions(&mut linker);
 
+        // 注册能力相关的 Host 函数（调用时按插件清单检查）
+        if let Err(e) = host::register(&mut linker) {
+            tracing::error!("Failed to register host functions: {}", e);
+        }
+
         linker
     }
 
     /// 注册所有日志级别的导入函数
-    fn register_log_functions(linker: &mut Linker<WasmResourceLimiter>) {
+    fn register_log_functions(linker: &mut Linker<PluginState>) {
         // 使用宏减少重复代码
         macro_rules! register_log {
             ($name:literal, $level:expr) => {
                 if let Err(e) = linker.func_wrap(
                     "env",
                     $name,
-                    move |mut caller: Caller<'_, WasmResourceLimiter>, ptr: i32, len: i32| {
+                    move |mut caller: Caller<'_, PluginState>, ptr: i32, len: i32| {
                         Self::handle_log(&mut caller, ptr, len, $level);
                     },
                 ) {
@@ -165,7 +235,7 @@ impl WasmHost {
 
     /// 处理来自 Wasm 插件的日志调用
     fn handle_log(
-        caller: &mut Caller<'_, WasmResourceLimiter>,
+        caller: &mut Caller<'_, PluginState>,
         ptr: i32,
         len: i32,
         level: tracing::Level,
@@ -0,0 +1,376 @@
+//! 录像 HTTP 接口
+//!
+//! - `GET /api/v1/recordings/jobs`：录像任务列表
+//! - `POST /api/v1/recordings/jobs`：开始录像 `{"stream_id": "...", "schedule": {...}}`
+//! - `DELETE /api/v1/recordings/jobs/*stream_id`：停止录像
+//! - `POST /api/v1/recordings/events/*stream_id`：触发事件录像 `{"event": "..."}`
+//! - `GET /api/v1/recordings/segments/*stream_id?start=&end=`：按时间段查询分片（RFC 3339）
+//! - `POST /api/v1/recordings/maintenance`：立即执行保留期清理与分级压缩
+//! - `GET /api/v1/recordings/vod/*stream_id?start=&end=[&at=]`：VOD HLS 播放列表，`at` 指定起播时间
+//! - `GET /api/v1/recordings/segment/*stream_id?start_ms=`：播放列表中的 TS 分片（已解压）
+//! - `GET /api/v1/recordings/seek/*stream_id?start=&end=&at=`：定位到指定时间所在分片与偏移
+//! - `GET /api/v1/recordings/export/*stream_id?start=&end=`：导出为单个 MP4 下载
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use flux_media_core::types::StreamId;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::playback::validate_export_range;
+use crate::recorder::Recorder;
+use crate::schedule::RecordingSchedule;
+
+pub fn router<S>(recorder: Arc<Recorder>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    read_router(recorder.clone()).merge(write_router(recorder))
+}
+
+/// 只读接口（任务列表、分片查询、回放与导出），便于调用方单独设置权限
+pub fn read_router<S>(recorder: Arc<Recorder>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/api/v1/recordings/jobs", get(list_jobs))
+        .route("/api/v1/recordings/segments/*stream_id", get(list_segments))
+        .route("/api/v1/recordings/vod/*stream_id", get(vod_playlist))
+        .route("/api/v1/recordings/segment/*stream_id", get(get_segment))
+        .route("/api/v1/recordings/seek/*stream_id", get(seek))
+        .route("/api/v1/recordings/export/*stream_id", get(export_mp4))
+        .with_state(recorder)
+}
+
+/// 修改接口（启停录像、触发事件录像、执行维护）
+pub fn write_router<S>(recorder: Arc<Recorder>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/api/v1/recordings/jobs", post(start_job))
+        .route("/api/v1/recordings/jobs/*stream_id", delete(stop_job))
+        .route("/api/v1/recordings/events/*stream_id", post(trigger_event))
+        .route("/api/v1/recordings/maintenance", post(run_maintenance))
+        .with_state(recorder)
+}
+
+#[derive(Debug, Deserialize)]
+struct StartRequest {
+    stream_id: String,
+    #[serde(default = "continuous")]
+    schedule: RecordingSchedule,
+}
+
+fn continuous() -> RecordingSchedule {
+    RecordingSchedule::Continuous
+}
+
+#[derive(Debug, Deserialize)]
+struct TriggerRequest {
+    #[serde(default = "default_event")]
+    event: String,
+}
+
+fn default_event() -> String {
+    "manual".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct SegmentQuery {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaybackQuery {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SegmentFileQuery {
+    start_ms: i64,
+}
+
+async fn list_jobs(State(recorder): State<Arc<Recorder>>) -> Response {
+    Json(recorder.jobs().await).into_response()
+}
+
+async fn start_job(State(recorder): State<Arc<Recorder>>, Json(request): Json<StartRequest>) -> Response {
+    let stream_id = StreamId::from(request.stream_id);
+    if let Err(e) = recorder.start(stream_id.clone(), request.schedule).await {
+        return (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response();
+    }
+    match recorder.job(stream_id.as_str()).await {
+        Some(status) => (StatusCode::CREATED, Json(status)).into_response(),
+        None => StatusCode::CREATED.into_response(),
+    }
+}
+
+async fn stop_job(State(recorder): State<Arc<Recorder>>, Path(stream_id): Path<String>) -> Response {
+    match recorder.stop(&StreamId::from(stream_id)).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+}
+
+async fn trigger_event(
+    State(recorder): State<Arc<Recorder>>,
+    Path(stream_id): Path<String>,
+    request: Option<Json<TriggerRequest>>,
+) -> Response {
+    if !recorder.has_job(&stream_id).await {
+        return (StatusCode::NOT_FOUND, format!("Recording job not found: {}", stream_id)).into_response();
+    }
+    let event = request.map(|Json(request)| request.event).unwrap_or_else(default_event);
+    match recorder.trigger(&StreamId::from(stream_id), &event).await {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => (StatusCode::CONFLICT, e.to_string()).into_response(),
+    }
+}
+
+async fn list_segments(
+    State(recorder): State<Arc<Recorder>>,
+    Path(stream_id): Path<String>,
+    Query(query): Query<SegmentQuery>,
+) -> Response {
+    if query.end <= query.start {
+        return (StatusCode::BAD_REQUEST, "end must be after start").into_response();
+    }
+    match recorder.segments(&StreamId::from(stream_id), query.start, query.end).await {
+        Ok(segments) => Json(segments).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+async fn run_maintenance(State(recorder): State<Arc<Recorder>>) -> Response {
+    match recorder.run_maintenance().await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+async fn vod_playlist(
+    State(recorder): State<Arc<Recorder>>,
+    Path(stream_id): Path<String>,
+    Query(query): Query<PlaybackQuery>,
+) -> Response {
+    if query.end <= query.start {
+        return (StatusCode::BAD_REQUEST, "end must be after start").into_response();
+    }
+    let timeline = match recorder.timeline(&StreamId::from(stream_id.clone()), query.start, query.end).await {
+        Ok(timeline) => timeline,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    if timeline.is_empty() {
+        return (StatusCode::NOT_FOUND, format!("No recordings for {} in range", stream_id)).into_response();
+    }
+
+    let m3u8 = timeline.vod_playlist(
+        |segment| {
+            format!(
+                "/api/v1/recordings/segment/{}?start_ms={}",
+                stream_id,
+                segment.start.timestamp_millis()
+            )
+        },
+        query.at,
+    );
+    ([(header::CONTENT_TYPE, "application/vnd.apple.mpegurl")], m3u8).into_response()
+}
+
+async fn get_segment(
+    State(recorder): State<Arc<Recorder>>,
+    Path(stream_id): Path<String>,
+    Query(query): Query<SegmentFileQuery>,
+) -> Response {
+    let Some(start) = DateTime::from_timestamp_millis(query.start_ms) else {
+        return (StatusCode::BAD_REQUEST, "invalid start_ms").into_response();
+    };
+    let record = match recorder.segment_at(&StreamId::from(stream_id), start).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    match recorder.read_segment(&record).await {
+        Ok(data) => ([(header::CONTENT_TYPE, "video/mp2t")], data).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+async fn seek(
+    State(recorder): State<Arc<Recorder>>,
+    Path(stream_id): Path<String>,
+    Query(query): Query<PlaybackQuery>,
+) -> Response {
+    let Some(at) = query.at else {
+        return (StatusCode::BAD_REQUEST, "at is required").into_response();
+    };
+    match recorder.timeline(&StreamId::from(stream_id), query.start, query.end).await {
+        Ok(timeline) => match timeline.seek(at) {
+            Some(position) => Json(position).into_response(),
+            None => (StatusCode::NOT_FOUND, "No recording at or after the requested time").into_response(),
+        },
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+async fn export_mp4(
+    State(recorder): State<Arc<Recorder>>,
+    Path(stream_id): Path<String>,
+    Query(query): Query<PlaybackQuery>,
+) -> Response {
+    if let Err(e) = validate_export_range(query.start, query.end) {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+    let stream_id = StreamId::from(stream_id);
+    match recorder.export_mp4(&stream_id, query.start, query.end).await {
+        Ok(mp4) => {
+            let filename = format!(
+                "{}_{}_{}.mp4",
+                stream_id.as_str().replace('/', "_"),
+                query.start.format("%Y%m%dT%H%M%SZ"),
+                query.end.format("%Y%m%dT%H%M%SZ")
+            );
+            (
+                [
+                    (header::CONTENT_TYPE, "video/mp4".to_string()),
+                    (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+                ],
+                mp4,
+            )
+                .into_response()
+        }
+        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use flux_config::recording::{IndexEngine, RecordingConfig};
+    use flux_config::StreamingConfig;
+    use flux_storage::StorageManager;
+    use flux_stream::StreamManager;
+    use tower::ServiceExt;
+
+    fn json_request(method: &str, uri: &str, body: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_recording_router() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = RecordingConfig::default();
+        config.index.engine = IndexEngine::Json;
+        config.index.db_path = dir.path().to_path_buf();
+        let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+        let recorder = Arc::new(
+            Recorder::new(config, stream_manager, Arc::new(StorageManager::new()))
+                .await
+                .unwrap(),
+        );
+        let app: Router = router(recorder.clone());
+
+        let response = app
+            .clone()
+            .oneshot(json_request(
+                "POST",
+                "/api/v1/recordings/jobs",
+                r#"{"stream_id":"rtmp/live/cam1","schedule":{"type":"event","pre_roll_secs":5,"post_roll_secs":10}}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert!(recorder.has_job("rtmp/live/cam1").await);
+
+        let response = app
+            .clone()
+            .oneshot(json_request("POST", "/api/v1/recordings/events/rtmp/live/cam1", r#"{"event":"motion"}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let response = app
+            .clone()
+            .oneshot(json_request("POST", "/api/v1/recordings/events/rtmp/live/cam2", "{}"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/recordings/segments/rtmp/live/cam1?start=2024-01-01T00:00:00Z&end=2024-01-02T00:00:00Z")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // 无录像时回放 404，导出时间段非法 400
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/recordings/vod/rtmp/live/cam1?start=2024-01-01T00:00:00Z&end=2024-01-02T00:00:00Z")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/recordings/export/rtmp/live/cam1?start=2024-01-01T00:00:00Z&end=2024-01-02T00:00:00Z")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/recordings/segment/rtmp/live/cam1?start_ms=1704067200000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app
+            .clone()
+            .oneshot(Request::delete("/api/v1/recordings/jobs/rtmp/live/cam1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(!recorder.has_job("rtmp/live/cam1").await);
+
+        let response = app
+            .oneshot(Request::delete("/api/v1/recordings/jobs/rtmp/live/cam1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
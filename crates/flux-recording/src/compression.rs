@@ -0,0 +1,101 @@
+//! 录像分片压缩（实时 / 归档 / 长期三档，算法由 RecordingCompressionConfig 指定）
+
+use anyhow::{anyhow, Result};
+use flux_config::recording::CompressionAlgorithm;
+use std::io::{Read, Write};
+
+/// 压缩后文件名后缀
+pub fn extension(algorithm: CompressionAlgorithm) -> &'static str {
+    match algorithm {
+        CompressionAlgorithm::None => "",
+        CompressionAlgorithm::Lz4 => ".lz4",
+        CompressionAlgorithm::Zstd => ".zst",
+        CompressionAlgorithm::Brotli => ".br",
+        CompressionAlgorithm::Lzma => ".xz",
+    }
+}
+
+/// 配置文件中的算法名（与 serde 小写形式一致）
+pub fn name(algorithm: CompressionAlgorithm) -> &'static str {
+    match algorithm {
+        CompressionAlgorithm::None => "none",
+        CompressionAlgorithm::Lz4 => "lz4",
+        CompressionAlgorithm::Zstd => "zstd",
+        CompressionAlgorithm::Brotli => "brotli",
+        CompressionAlgorithm::Lzma => "lzma",
+    }
+}
+
+pub fn from_name(name: &str) -> Result<CompressionAlgorithm> {
+    match name {
+        "none" => Ok(CompressionAlgorithm::None),
+        "lz4" => Ok(CompressionAlgorithm::Lz4),
+        "zstd" => Ok(CompressionAlgorithm::Zstd),
+        "brotli" => Ok(CompressionAlgorithm::Brotli),
+        "lzma" => Ok(CompressionAlgorithm::Lzma),
+        other => Err(anyhow!("Unknown compression algorithm: {}", other)),
+    }
+}
+
+pub fn compress(algorithm: CompressionAlgorithm, data: &[u8]) -> Result<Vec<u8>> {
+    match algorithm {
+        CompressionAlgorithm::None => Ok(data.to_vec()),
+        CompressionAlgorithm::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+        CompressionAlgorithm::Zstd => Ok(zstd::encode_all(data, 3)?),
+        CompressionAlgorithm::Brotli => {
+            let mut output = Vec::new();
+            {
+                let mut writer = brotli::CompressorWriter::new(&mut output, 4096, 9, 22);
+                writer.write_all(data)?;
+            }
+            Ok(output)
+        }
+        CompressionAlgorithm::Lzma => {
+            let mut output = Vec::new();
+            lzma_rs::xz_compress(&mut &data[..], &mut output)?;
+            Ok(output)
+        }
+    }
+}
+
+pub fn decompress(algorithm: CompressionAlgorithm, data: &[u8]) -> Result<Vec<u8>> {
+    match algorithm {
+        CompressionAlgorithm::None => Ok(data.to_vec()),
+        CompressionAlgorithm::Lz4 => {
+            lz4_flex::decompress_size_prepended(data).map_err(|e| anyhow!("LZ4 decompress failed: {}", e))
+        }
+        CompressionAlgorithm::Zstd => Ok(zstd::decode_all(data)?),
+        CompressionAlgorithm::Brotli => {
+            let mut output = Vec::new();
+            brotli::Decompressor::new(data, 4096).read_to_end(&mut output)?;
+            Ok(output)
+        }
+        CompressionAlgorithm::Lzma => {
+            let mut output = Vec::new();
+            lzma_rs::xz_decompress(&mut &data[..], &mut output).map_err(|e| anyhow!("LZMA decompress failed: {:?}", e))?;
+            Ok(output)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 188) as u8).collect();
+        for algorithm in [
+            CompressionAlgorithm::None,
+            CompressionAlgorithm::Lz4,
+            CompressionAlgorithm::Zstd,
+            CompressionAlgorithm::Brotli,
+            CompressionAlgorithm::Lzma,
+        ] {
+            let compressed = compress(algorithm, &data).unwrap();
+            assert_eq!(decompress(algorithm, &compressed).unwrap(), data, "{}", name(algorithm));
+            assert_eq!(from_name(name(algorithm)).unwrap(), algorithm);
+        }
+        assert!(from_name("gzip").is_err());
+    }
+}
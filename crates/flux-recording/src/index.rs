@@ -0,0 +1,429 @@
+//! 录像时间索引：按流记录分片的起止时间、存储位置与压缩档位
+//!
+//! 引擎由 RecordingIndexConfig 决定：Json 每个流一个文件（db_path 为目录），
+//! Sqlite 单库单表（db_path 为数据库文件）。
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use flux_config::recording::{CompressionAlgorithm, IndexEngine, RecordingIndexConfig};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::compression;
+
+/// 存储档位，对应同名存储池
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageTier {
+    Realtime,
+    Archive,
+    Longterm,
+}
+
+impl StorageTier {
+    /// 存储池名称
+    pub fn pool_name(self) -> &'static str {
+        match self {
+            Self::Realtime => "realtime",
+            Self::Archive => "archive",
+            Self::Longterm => "longterm",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "realtime" => Ok(Self::Realtime),
+            "archive" => Ok(Self::Archive),
+            "longterm" => Ok(Self::Longterm),
+            other => Err(anyhow!("Unknown storage tier: {}", other)),
+        }
+    }
+}
+
+/// 一个录像分片（MPEG-TS，可能已压缩）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentRecord {
+    pub stream_id: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// 存储池内的相对路径
+    pub path: String,
+    pub pool: String,
+    pub size: u64,
+    pub compression: CompressionAlgorithm,
+    pub tier: StorageTier,
+    /// 事件录像的事件名
+    pub event: Option<String>,
+}
+
+impl SegmentRecord {
+    pub fn duration_ms(&self) -> i64 {
+        (self.end - self.start).num_milliseconds()
+    }
+
+    fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.start < end && self.end > start
+    }
+}
+
+/// 录像索引
+#[async_trait]
+pub trait RecordingIndex: Send + Sync {
+    async fn insert(&self, record: &SegmentRecord) -> Result<()>;
+
+    /// 分片迁移档位后替换记录
+    async fn replace(&self, old_path: &str, record: &SegmentRecord) -> Result<()>;
+
+    async fn remove(&self, stream_id: &str, path: &str) -> Result<()>;
+
+    /// 与 `[start, end)` 相交的分片，按开始时间排序
+    async fn query(&self, stream_id: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<SegmentRecord>>;
+
+    /// 所有流中结束时间早于 `before` 的分片
+    async fn ended_before(&self, before: DateTime<Utc>) -> Result<Vec<SegmentRecord>>;
+
+    /// 有录像的流
+    async fn streams(&self) -> Result<Vec<String>>;
+}
+
+/// 按配置打开索引
+pub async fn open_index(config: &RecordingIndexConfig) -> Result<Arc<dyn RecordingIndex>> {
+    match config.engine {
+        IndexEngine::Json => Ok(Arc::new(JsonIndex::open(&config.db_path).await?)),
+        IndexEngine::Sqlite => Ok(Arc::new(SqliteIndex::open(&config.db_path).await?)),
+        IndexEngine::Binary => Err(anyhow!("Binary recording index is not supported, use json or sqlite")),
+    }
+}
+
+/// JSON 索引：`{dir}/{stream}.json`，内存中保留全部记录
+pub struct JsonIndex {
+    dir: PathBuf,
+    streams: RwLock<HashMap<String, Vec<SegmentRecord>>>,
+}
+
+impl JsonIndex {
+    pub async fn open(dir: &Path) -> Result<Self> {
+        tokio::fs::create_dir_all(dir).await?;
+
+        let mut streams = HashMap::new();
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let records: Vec<SegmentRecord> = serde_json::from_slice(&tokio::fs::read(&path).await?)?;
+            if let Some(first) = records.first() {
+                streams.insert(first.stream_id.clone(), records);
+            }
+        }
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            streams: RwLock::new(streams),
+        })
+    }
+
+    fn file_path(&self, stream_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", stream_id.replace('/', "__")))
+    }
+
+    /// 先写临时文件再重命名，避免中途崩溃留下半个文件
+    async fn persist(&self, stream_id: &str, records: &[SegmentRecord]) -> Result<()> {
+        let path = self.file_path(stream_id);
+        if records.is_empty() {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Ok(());
+        }
+        let temp = path.with_extension("json.tmp");
+        tokio::fs::write(&temp, serde_json::to_vec(records)?).await?;
+        tokio::fs::rename(&temp, &path).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RecordingIndex for JsonIndex {
+    async fn insert(&self, record: &SegmentRecord) -> Result<()> {
+        let mut streams = self.streams.write().await;
+        let records = streams.entry(record.stream_id.clone()).or_default();
+        let position = records.partition_point(|existing| existing.start <= record.start);
+        records.insert(position, record.clone());
+        self.persist(&record.stream_id, records).await
+    }
+
+    async fn replace(&self, old_path: &str, record: &SegmentRecord) -> Result<()> {
+        let mut streams = self.streams.write().await;
+        let records = streams
+            .get_mut(&record.stream_id)
+            .ok_or_else(|| anyhow!("Recording not found: {}", old_path))?;
+        let existing = records
+            .iter_mut()
+            .find(|existing| existing.path == old_path)
+            .ok_or_else(|| anyhow!("Recording not found: {}", old_path))?;
+        *existing = record.clone();
+        self.persist(&record.stream_id, records).await
+    }
+
+    async fn remove(&self, stream_id: &str, path: &str) -> Result<()> {
+        let mut streams = self.streams.write().await;
+        let Some(records) = streams.get_mut(stream_id) else {
+            return Ok(());
+        };
+        records.retain(|record| record.path != path);
+        self.persist(stream_id, records).await?;
+        if records.is_empty() {
+            streams.remove(stream_id);
+        }
+        Ok(())
+    }
+
+    async fn query(&self, stream_id: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<SegmentRecord>> {
+        let streams = self.streams.read().await;
+        Ok(streams
+            .get(stream_id)
+            .map(|records| records.iter().filter(|record| record.overlaps(start, end)).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn ended_before(&self, before: DateTime<Utc>) -> Result<Vec<SegmentRecord>> {
+        let streams = self.streams.read().await;
+        Ok(streams
+            .values()
+            .flatten()
+            .filter(|record| record.end < before)
+            .cloned()
+            .collect())
+    }
+
+    async fn streams(&self) -> Result<Vec<String>> {
+        let mut streams: Vec<String> = self.streams.read().await.keys().cloned().collect();
+        streams.sort();
+        Ok(streams)
+    }
+}
+
+/// SQLite 索引
+pub struct SqliteIndex {
+    pool: SqlitePool,
+}
+
+impl SqliteIndex {
+    pub async fn open(db_path: &Path) -> Result<Self> {
+        if let Some(parent) = db_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let options = SqliteConnectOptions::new().filename(db_path).create_if_missing(true);
+        let pool = SqlitePoolOptions::new().max_connections(4).connect_with(options).await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS recording_segments (
+                path TEXT PRIMARY KEY,
+                stream_id TEXT NOT NULL,
+                start_ms INTEGER NOT NULL,
+                end_ms INTEGER NOT NULL,
+                pool TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                compression TEXT NOT NULL,
+                tier TEXT NOT NULL,
+                event TEXT
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_recording_segments_time ON recording_segments (stream_id, start_ms)")
+            .execute(&pool)
+            .await?;
+
+        Ok(Self { pool })
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<SegmentRecord> {
+        let timestamp = |column: &str| -> Result<DateTime<Utc>> {
+            let ms: i64 = row.try_get(column)?;
+            Utc.timestamp_millis_opt(ms)
+                .single()
+                .ok_or_else(|| anyhow!("Invalid timestamp in recording index: {}", ms))
+        };
+        Ok(SegmentRecord {
+            stream_id: row.try_get("stream_id")?,
+            start: timestamp("start_ms")?,
+            end: timestamp("end_ms")?,
+            path: row.try_get("path")?,
+            pool: row.try_get("pool")?,
+            size: row.try_get::<i64, _>("size")? as u64,
+            compression: compression::from_name(row.try_get("compression")?)?,
+            tier: StorageTier::from_name(row.try_get("tier")?)?,
+            event: row.try_get("event")?,
+        })
+    }
+
+    async fn write(&self, sql: &str, record: &SegmentRecord, key: Option<&str>) -> Result<()> {
+        let mut query = sqlx::query(sql)
+            .bind(&record.path)
+            .bind(&record.stream_id)
+            .bind(record.start.timestamp_millis())
+            .bind(record.end.timestamp_millis())
+            .bind(&record.pool)
+            .bind(record.size as i64)
+            .bind(compression::name(record.compression))
+            .bind(record.tier.pool_name())
+            .bind(&record.event);
+        if let Some(key) = key {
+            query = query.bind(key);
+        }
+        query.execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RecordingIndex for SqliteIndex {
+    async fn insert(&self, record: &SegmentRecord) -> Result<()> {
+        self.write(
+            "INSERT OR REPLACE INTO recording_segments
+                (path, stream_id, start_ms, end_ms, pool, size, compression, tier, event)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            record,
+            None,
+        )
+        .await
+    }
+
+    async fn replace(&self, old_path: &str, record: &SegmentRecord) -> Result<()> {
+        self.write(
+            "UPDATE recording_segments
+             SET path = ?, stream_id = ?, start_ms = ?, end_ms = ?, pool = ?, size = ?, compression = ?, tier = ?, event = ?
+             WHERE path = ?",
+            record,
+            Some(old_path),
+        )
+        .await
+    }
+
+    async fn remove(&self, stream_id: &str, path: &str) -> Result<()> {
+        sqlx::query("DELETE FROM recording_segments WHERE stream_id = ? AND path = ?")
+            .bind(stream_id)
+            .bind(path)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn query(&self, stream_id: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<SegmentRecord>> {
+        let rows = sqlx::query(
+            "SELECT * FROM recording_segments
+             WHERE stream_id = ? AND start_ms < ? AND end_ms > ?
+             ORDER BY start_ms",
+        )
+        .bind(stream_id)
+        .bind(end.timestamp_millis())
+        .bind(start.timestamp_millis())
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn ended_before(&self, before: DateTime<Utc>) -> Result<Vec<SegmentRecord>> {
+        let rows = sqlx::query("SELECT * FROM recording_segments WHERE end_ms < ? ORDER BY start_ms")
+            .bind(before.timestamp_millis())
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn streams(&self) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT DISTINCT stream_id FROM recording_segments ORDER BY stream_id")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(|row| Ok(row.try_get("stream_id")?)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn record(stream_id: &str, start_secs: i64, end_secs: i64) -> SegmentRecord {
+        SegmentRecord {
+            stream_id: stream_id.to_string(),
+            start: Utc.timestamp_opt(1_700_000_000 + start_secs, 0).unwrap(),
+            end: Utc.timestamp_opt(1_700_000_000 + end_secs, 0).unwrap(),
+            path: format!("recordings/{}/{}.ts", stream_id, start_secs),
+            pool: "realtime".to_string(),
+            size: 1024,
+            compression: CompressionAlgorithm::None,
+            tier: StorageTier::Realtime,
+            event: None,
+        }
+    }
+
+    async fn exercise(index: &dyn RecordingIndex) {
+        let base = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        index.insert(&record("rtmp/live/cam1", 60, 120)).await.unwrap();
+        index.insert(&record("rtmp/live/cam1", 0, 60)).await.unwrap();
+        index.insert(&record("rtmp/live/cam1", 180, 240)).await.unwrap();
+        index.insert(&record("rtmp/live/cam2", 0, 60)).await.unwrap();
+
+        let found = index
+            .query("rtmp/live/cam1", base + Duration::seconds(30), base + Duration::seconds(200))
+            .await
+            .unwrap();
+        let starts: Vec<i64> = found.iter().map(|record| (record.start - base).num_seconds()).collect();
+        assert_eq!(starts, vec![0, 60, 180]);
+        assert_eq!(found[0].duration_ms(), 60_000);
+
+        let mut archived = record("rtmp/live/cam1", 0, 60);
+        archived.path.push_str(".zst");
+        archived.tier = StorageTier::Archive;
+        archived.compression = CompressionAlgorithm::Zstd;
+        index.replace("recordings/rtmp/live/cam1/0.ts", &archived).await.unwrap();
+
+        let expired = index.ended_before(base + Duration::seconds(61)).await.unwrap();
+        assert_eq!(expired.len(), 2);
+        assert!(expired.contains(&archived));
+
+        index.remove("rtmp/live/cam2", "recordings/rtmp/live/cam2/0.ts").await.unwrap();
+        assert_eq!(index.streams().await.unwrap(), vec!["rtmp/live/cam1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_json_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = JsonIndex::open(dir.path()).await.unwrap();
+        exercise(&index).await;
+
+        // 重新打开后记录仍在
+        let reopened = JsonIndex::open(dir.path()).await.unwrap();
+        let all = reopened.ended_before(Utc::now()).await.unwrap();
+        assert_eq!(all.len(), 3);
+        assert!(all.iter().any(|record| record.tier == StorageTier::Archive));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("recordings.db");
+        let index = SqliteIndex::open(&db_path).await.unwrap();
+        exercise(&index).await;
+
+        let reopened = SqliteIndex::open(&db_path).await.unwrap();
+        assert_eq!(reopened.ended_before(Utc::now()).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_binary_engine_rejected() {
+        let config = RecordingIndexConfig {
+            engine: IndexEngine::Binary,
+            db_path: PathBuf::from("unused"),
+        };
+        assert!(open_index(&config).await.is_err());
+    }
+}
@@ -0,0 +1,15 @@
+//! 录像子系统：按 RecordingConfig 订阅 flux-stream 流，在关键帧处切分 MPEG-TS 分片，
+//! 写入 flux-storage 存储池，维护按流的时间索引，执行保留期清理与分级压缩，
+//! 并提供 VOD HLS 回放与 MP4 导出
+pub mod api;
+pub mod compression;
+pub mod index;
+pub mod playback;
+pub mod recorder;
+pub mod schedule;
+pub mod segmenter;
+
+pub use index::{open_index, RecordingIndex, SegmentRecord, StorageTier};
+pub use playback::{Mp4Exporter, SeekPosition, Timeline};
+pub use recorder::{pool_configs, JobStatus, MaintenanceReport, Recorder};
+pub use schedule::{RecordingSchedule, TimeWindow};
@@ -0,0 +1,480 @@
+//! 录像回放：按时间段把索引中的分片组织为时间线，生成 VOD HLS 播放列表、
+//! 支持定位拖动，并将分片重封装（不转码）为单个 MP4 供取证下载
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use flux_media_core::codec::{build_avc_decoder_config, build_hevc_decoder_config, split_annexb, AacConfig};
+use flux_media_core::playback::fmp4::AAC_SAMPLES_PER_FRAME;
+use flux_media_core::playback::ts::{STREAM_TYPE_AAC, STREAM_TYPE_H264, STREAM_TYPE_H265};
+use flux_media_core::playback::{Fmp4Track, Mp4Sample, Mp4Writer, TsDemuxer};
+use flux_media_core::types::VideoCodec;
+use serde::Serialize;
+
+use crate::index::SegmentRecord;
+
+/// 相邻分片间隔超过该值视为录像中断（EXT-X-DISCONTINUITY）
+pub const GAP_TOLERANCE_MS: i64 = 1000;
+
+/// 单次导出允许的最大时间跨度
+pub const MAX_EXPORT_SPAN_HOURS: i64 = 6;
+
+const VIDEO_TRACK_ID: u32 = 1;
+const AUDIO_TRACK_ID: u32 = 2;
+
+/// PTS/DTS 为 33 位计数
+const TS_CLOCK_MASK: u64 = (1 << 33) - 1;
+
+/// 一段时间内按起始时间排序的录像分片
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    segments: Vec<SegmentRecord>,
+}
+
+/// 定位结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SeekPosition {
+    /// 分片在时间线中的序号（播放列表中的第几个分片）
+    pub index: usize,
+    pub segment: SegmentRecord,
+    /// 分片内偏移（毫秒）
+    pub offset_ms: i64,
+    /// 播放列表媒体时间上的偏移（秒，不含中断间隔），用于 EXT-X-START
+    pub playlist_offset: f64,
+    /// 实际定位到的时间；落在中断间隔内时为下一个分片的开始
+    pub time: DateTime<Utc>,
+}
+
+impl Timeline {
+    pub fn new(mut segments: Vec<SegmentRecord>) -> Self {
+        segments.sort_by_key(|segment| segment.start);
+        Self { segments }
+    }
+
+    pub fn segments(&self) -> &[SegmentRecord] {
+        &self.segments
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// 二分查找指定时间的分片索引（最后一个开始时间早于等于目标的分片）
+    pub fn binary_search_by_time(&self, target: DateTime<Utc>) -> usize {
+        self.segments
+            .partition_point(|segment| segment.start <= target)
+            .saturating_sub(1)
+    }
+
+    /// 第 `index` 个分片之前是否存在录像中断
+    pub fn is_discontinuous(&self, index: usize) -> bool {
+        index > 0
+            && (self.segments[index].start - self.segments[index - 1].end).num_milliseconds().abs()
+                > GAP_TOLERANCE_MS
+    }
+
+    /// 定位到指定时间；时间线为空或目标晚于最后一个分片结束时返回 None
+    pub fn seek(&self, at: DateTime<Utc>) -> Option<SeekPosition> {
+        let last = self.segments.last()?;
+        if at >= last.end {
+            return None;
+        }
+
+        let mut index = self.binary_search_by_time(at);
+        let mut time = at.max(self.segments[index].start);
+        if time >= self.segments[index].end {
+            // 落在中断间隔内，跳到下一个分片开始
+            index += 1;
+            time = self.segments[index].start;
+        }
+
+        let segment = self.segments[index].clone();
+        let offset_ms = (time - segment.start).num_milliseconds();
+        let before: i64 = self.segments[..index].iter().map(SegmentRecord::duration_ms).sum();
+        Some(SeekPosition {
+            index,
+            playlist_offset: (before + offset_ms) as f64 / 1000.0,
+            offset_ms,
+            segment,
+            time,
+        })
+    }
+
+    /// 生成 VOD 播放列表；`uri` 生成分片地址，`at` 指定起播时间
+    pub fn vod_playlist(&self, uri: impl Fn(&SegmentRecord) -> String, at: Option<DateTime<Utc>>) -> String {
+        let target_duration = self
+            .segments
+            .iter()
+            .map(|segment| (segment.duration_ms() as f64 / 1000.0).ceil() as u64)
+            .max()
+            .unwrap_or(1)
+            .max(1);
+
+        let mut m3u8 = String::new();
+        m3u8.push_str("#EXTM3U\n");
+        m3u8.push_str("#EXT-X-VERSION:3\n");
+        m3u8.push_str(&format!("#EXT-X-TARGETDURATION:{}\n", target_duration));
+        m3u8.push_str("#EXT-X-MEDIA-SEQUENCE:0\n");
+        m3u8.push_str("#EXT-X-PLAYLIST-TYPE:VOD\n");
+        if let Some(position) = at.and_then(|at| self.seek(at)) {
+            m3u8.push_str(&format!("#EXT-X-START:TIME-OFFSET={:.3},PRECISE=YES\n", position.playlist_offset));
+        }
+
+        for (index, segment) in self.segments.iter().enumerate() {
+            if self.is_discontinuous(index) {
+                m3u8.push_str("#EXT-X-DISCONTINUITY\n");
+            }
+            // 首个分片和每次中断后标注挂钟时间
+            if index == 0 || self.is_discontinuous(index) {
+                m3u8.push_str(&format!(
+                    "#EXT-X-PROGRAM-DATE-TIME:{}\n",
+                    segment.start.to_rfc3339_opts(SecondsFormat::Millis, true)
+                ));
+            }
+            m3u8.push_str(&format!("#EXTINF:{:.3},\n", segment.duration_ms() as f64 / 1000.0));
+            m3u8.push_str(&uri(segment));
+            m3u8.push('\n');
+        }
+
+        m3u8.push_str("#EXT-X-ENDLIST\n");
+        m3u8
+    }
+}
+
+/// 已解复用的视频访问单元（时间为相对导出原点的 90kHz）
+struct VideoFrame {
+    dts: i64,
+    composition_offset: i32,
+    /// 长度前缀 NALU，不含 AUD 与参数集
+    data: Vec<u8>,
+    is_sync: bool,
+}
+
+/// 已拆分的 AAC 帧（时间为相对导出原点的 90kHz）
+struct AudioFrame {
+    dts: i64,
+    data: Bytes,
+}
+
+#[derive(Default)]
+struct ParameterSets {
+    vps: Option<Vec<u8>>,
+    sps: Option<Vec<u8>>,
+    pps: Option<Vec<u8>>,
+}
+
+/// 将录像分片（MPEG-TS）重封装为单个 MP4
+///
+/// 分片时间以索引中的挂钟时间为准，分片内按 DTS 差值推算，跨分片的时间戳不要求连续；
+/// 视频从 `start` 之前最近的关键帧开始，确保导出文件首帧可解码。
+pub struct Mp4Exporter {
+    origin: DateTime<Utc>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    video_codec: Option<VideoCodec>,
+    parameter_sets: ParameterSets,
+    video: Vec<VideoFrame>,
+    audio_config: Option<AacConfig>,
+    audio: Vec<AudioFrame>,
+}
+
+impl Mp4Exporter {
+    /// `origin` 为时间计算原点，须不晚于第一个分片的开始
+    pub fn new(origin: DateTime<Utc>, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            origin,
+            start,
+            end,
+            video_codec: None,
+            parameter_sets: ParameterSets::default(),
+            video: Vec::new(),
+            audio_config: None,
+            audio: Vec::new(),
+        }
+    }
+
+    /// 追加一个分片（已解压的 TS）
+    pub fn push_segment(&mut self, record: &SegmentRecord, ts: &[u8]) {
+        let mut demuxer = TsDemuxer::new();
+        let mut frames = demuxer.push(ts);
+        frames.extend(demuxer.flush());
+        let Some(base_dts) = frames.first().map(|frame| frame.dts) else {
+            return;
+        };
+        let segment_offset = (record.start - self.origin).num_milliseconds() * 90;
+
+        for frame in frames {
+            let dts = segment_offset + ts_delta(frame.dts, base_dts);
+            match frame.stream_type {
+                STREAM_TYPE_H264 => self.push_video(VideoCodec::H264, dts, ts_delta(frame.pts, frame.dts), &frame.data),
+                STREAM_TYPE_H265 => self.push_video(VideoCodec::H265, dts, ts_delta(frame.pts, frame.dts), &frame.data),
+                STREAM_TYPE_AAC => self.push_audio(dts, &frame.data),
+                // G.711 等无法放入 MP4 的音频直接丢弃
+                _ => {}
+            }
+        }
+    }
+
+    fn push_video(&mut self, codec: VideoCodec, dts: i64, composition_offset: i64, data: &[u8]) {
+        if *self.video_codec.get_or_insert(codec) != codec {
+            return;
+        }
+        if self.video.last().is_some_and(|last| dts <= last.dts) {
+            return;
+        }
+
+        let mut sample = Vec::with_capacity(data.len() + 16);
+        let mut is_sync = false;
+        for nalu in split_annexb(data) {
+            let Some(&header) = nalu.first() else {
+                continue;
+            };
+            let slot = match codec {
+                VideoCodec::H264 => match header & 0x1F {
+                    5 => {
+                        is_sync = true;
+                        None
+                    }
+                    7 => Some(&mut self.parameter_sets.sps),
+                    8 => Some(&mut self.parameter_sets.pps),
+                    // AUD
+                    9 => continue,
+                    _ => None,
+                },
+                _ => match (header >> 1) & 0x3F {
+                    16..=21 => {
+                        is_sync = true;
+                        None
+                    }
+                    32 => Some(&mut self.parameter_sets.vps),
+                    33 => Some(&mut self.parameter_sets.sps),
+                    34 => Some(&mut self.parameter_sets.pps),
+                    35 => continue,
+                    _ => None,
+                },
+            };
+            // 参数集写入 avcC/hvcC，不留在样本中
+            if let Some(slot) = slot {
+                slot.get_or_insert_with(|| nalu.to_vec());
+                continue;
+            }
+            sample.extend_from_slice(&(nalu.len() as u32).to_be_bytes());
+            sample.extend_from_slice(nalu);
+        }
+        if sample.is_empty() {
+            return;
+        }
+
+        self.video.push(VideoFrame {
+            dts,
+            composition_offset: composition_offset as i32,
+            data: sample,
+            is_sync,
+        });
+    }
+
+    fn push_audio(&mut self, dts: i64, data: &[u8]) {
+        let mut offset = 0;
+        let mut index = 0;
+        while offset < data.len() {
+            let Ok((config, header_len, frame_len)) = AacConfig::parse_adts(&data[offset..]) else {
+                break;
+            };
+            if offset + frame_len > data.len() {
+                break;
+            }
+            let config = *self.audio_config.get_or_insert(config);
+            // 同一 PES 中的后续帧按每帧 1024 采样顺延
+            let dts = dts + index * AAC_SAMPLES_PER_FRAME as i64 * 90_000 / config.sample_rate() as i64;
+            if self.audio.last().is_none_or(|last| dts > last.dts) {
+                self.audio.push(AudioFrame {
+                    dts,
+                    data: Bytes::copy_from_slice(&data[offset + header_len..offset + frame_len]),
+                });
+            }
+            offset += frame_len;
+            index += 1;
+        }
+    }
+
+    /// 裁剪到 `[start, end]` 并生成 MP4
+    pub fn finish(self) -> Result<Bytes> {
+        let start = (self.start - self.origin).num_milliseconds() * 90;
+        let end = (self.end - self.origin).num_milliseconds() * 90;
+
+        // 视频从 start 之前（含）最近的关键帧开始
+        let first_video = self
+            .video
+            .iter()
+            .rposition(|frame| frame.is_sync && frame.dts <= start)
+            .or_else(|| self.video.iter().position(|frame| frame.is_sync));
+        let video: Vec<&VideoFrame> = match first_video {
+            Some(first) => self.video[first..].iter().filter(|frame| frame.dts <= end).collect(),
+            None => Vec::new(),
+        };
+        let audio_start = video.first().map_or(start, |frame| frame.dts.min(start));
+        let audio: Vec<&AudioFrame> = self
+            .audio
+            .iter()
+            .filter(|frame| frame.dts >= audio_start && frame.dts <= end)
+            .collect();
+
+        let mut tracks = Vec::new();
+        if !video.is_empty() {
+            let codec = self.video_codec.unwrap_or(VideoCodec::H264);
+            let parameter_sets = &self.parameter_sets;
+            let (sps, pps) = parameter_sets
+                .sps
+                .as_deref()
+                .zip(parameter_sets.pps.as_deref())
+                .ok_or_else(|| anyhow!("Missing SPS/PPS in recording"))?;
+            let decoder_config = match codec {
+                VideoCodec::H264 => build_avc_decoder_config(sps, pps)?,
+                _ => {
+                    let vps = parameter_sets
+                        .vps
+                        .as_deref()
+                        .ok_or_else(|| anyhow!("Missing VPS in recording"))?;
+                    build_hevc_decoder_config(vps, sps, pps)?
+                }
+            };
+            tracks.push(Fmp4Track::video(VIDEO_TRACK_ID, codec, Bytes::from(decoder_config))?);
+        }
+        let audio_config = self.audio_config.filter(|_| !audio.is_empty());
+        if let Some(config) = audio_config {
+            tracks.push(Fmp4Track::aac(AUDIO_TRACK_ID, Bytes::copy_from_slice(&config.to_bytes()))?);
+        }
+        if tracks.is_empty() {
+            return Err(anyhow!("No exportable media in the requested range"));
+        }
+
+        // 导出原点不晚于任何样本，时间戳均非负
+        let mut writer = Mp4Writer::new(tracks);
+        for frame in video {
+            writer.push(
+                VIDEO_TRACK_ID,
+                Mp4Sample {
+                    data: Bytes::from(frame.data.clone()),
+                    dts: frame.dts.max(0) as u64,
+                    composition_offset: frame.composition_offset,
+                    is_sync: frame.is_sync,
+                },
+            )?;
+        }
+        if let Some(config) = audio_config {
+            let sample_rate = config.sample_rate() as u64;
+            for frame in audio {
+                writer.push(
+                    AUDIO_TRACK_ID,
+                    Mp4Sample {
+                        data: frame.data.clone(),
+                        dts: frame.dts.max(0) as u64 * sample_rate / 90_000,
+                        composition_offset: 0,
+                        is_sync: true,
+                    },
+                )?;
+            }
+        }
+        Ok(writer.finish())
+    }
+}
+
+/// 33 位时间戳差值（90kHz），处理回绕，允许为负
+fn ts_delta(value: u64, base: u64) -> i64 {
+    let delta = value.wrapping_sub(base) & TS_CLOCK_MASK;
+    if delta > TS_CLOCK_MASK / 2 {
+        delta as i64 - (TS_CLOCK_MASK as i64 + 1)
+    } else {
+        delta as i64
+    }
+}
+
+/// 导出时间跨度是否合法
+pub fn validate_export_range(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<()> {
+    if end <= start {
+        return Err(anyhow!("end must be after start"));
+    }
+    if end - start > Duration::hours(MAX_EXPORT_SPAN_HOURS) {
+        return Err(anyhow!("Export range exceeds {} hours", MAX_EXPORT_SPAN_HOURS));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::StorageTier;
+    use chrono::TimeZone;
+    use flux_config::recording::CompressionAlgorithm;
+
+    fn record(start_secs: i64, end_secs: i64) -> SegmentRecord {
+        let base = Utc.with_ymd_and_hms(2024, 5, 1, 8, 0, 0).unwrap();
+        SegmentRecord {
+            stream_id: "rtmp/live/cam1".to_string(),
+            start: base + Duration::seconds(start_secs),
+            end: base + Duration::seconds(end_secs),
+            path: format!("recordings/rtmp/live/cam1/20240501/{}.ts", start_secs),
+            pool: StorageTier::Realtime.pool_name().to_string(),
+            size: 0,
+            compression: CompressionAlgorithm::None,
+            tier: StorageTier::Realtime,
+            event: None,
+        }
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, 8, 0, 0).unwrap() + Duration::seconds(secs)
+    }
+
+    #[test]
+    fn test_seek() {
+        // 0-10、10-20，中断后 60-70
+        let timeline = Timeline::new(vec![record(60, 70), record(0, 10), record(10, 20)]);
+        assert_eq!(timeline.binary_search_by_time(at(10)), 1);
+        assert_eq!(timeline.binary_search_by_time(at(-5)), 0);
+
+        let position = timeline.seek(at(15)).unwrap();
+        assert_eq!((position.index, position.offset_ms), (1, 5000));
+        assert_eq!(position.playlist_offset, 15.0);
+
+        // 落在中断间隔内，跳到下一个分片
+        let position = timeline.seek(at(30)).unwrap();
+        assert_eq!((position.index, position.offset_ms), (2, 0));
+        assert_eq!(position.time, at(60));
+        assert_eq!(position.playlist_offset, 20.0);
+
+        assert_eq!(timeline.seek(at(-5)).unwrap().time, at(0));
+        assert!(timeline.seek(at(70)).is_none());
+        assert!(Timeline::default().seek(at(0)).is_none());
+    }
+
+    #[test]
+    fn test_vod_playlist() {
+        let timeline = Timeline::new(vec![record(0, 10), record(10, 20), record(60, 66)]);
+        let m3u8 = timeline.vod_playlist(|segment| format!("/seg?start_ms={}", segment.start.timestamp_millis()), Some(at(62)));
+
+        assert!(m3u8.contains("#EXT-X-PLAYLIST-TYPE:VOD\n"));
+        assert!(m3u8.contains("#EXT-X-TARGETDURATION:10\n"));
+        assert!(m3u8.contains("#EXT-X-START:TIME-OFFSET=22.000,PRECISE=YES\n"));
+        assert!(m3u8.ends_with("#EXT-X-ENDLIST\n"));
+        assert_eq!(m3u8.matches("#EXT-X-DISCONTINUITY\n").count(), 1);
+        assert_eq!(m3u8.matches("#EXT-X-PROGRAM-DATE-TIME").count(), 2);
+        assert!(m3u8.contains("#EXT-X-PROGRAM-DATE-TIME:2024-05-01T08:00:00.000Z\n"));
+        assert!(m3u8.contains("#EXT-X-DISCONTINUITY\n#EXT-X-PROGRAM-DATE-TIME:2024-05-01T08:01:00.000Z\n#EXTINF:6.000,\n"));
+        assert_eq!(m3u8.matches("/seg?start_ms=").count(), 3);
+    }
+
+    #[test]
+    fn test_ts_delta() {
+        assert_eq!(ts_delta(9000, 0), 9000);
+        assert_eq!(ts_delta(0, 9000), -9000);
+        assert_eq!(ts_delta(100, TS_CLOCK_MASK - 99), 200);
+    }
+
+    #[test]
+    fn test_export_range() {
+        assert!(validate_export_range(at(0), at(60)).is_ok());
+        assert!(validate_export_range(at(60), at(0)).is_err());
+        assert!(validate_export_range(at(0), at(7 * 3600)).is_err());
+    }
+}
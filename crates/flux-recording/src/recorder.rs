@@ -0,0 +1,692 @@
+//! 录像器：订阅 flux-stream 流，按计划录制 MPEG-TS 分片写入存储池并维护索引
+//!
+//! 每个录像任务一个协程负责切分，另一个协程负责压缩、写入存储池和索引，
+//! 避免落盘阻塞媒体接收。
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Duration, Local, Utc};
+use flux_config::recording::{CompressionAlgorithm, RecordingConfig, RecordingStorageConfig};
+use flux_media_core::types::StreamId;
+use flux_storage::{DiskType, PoolConfig, StorageManager};
+use flux_stream::{MediaPacket, PacketType, StreamManager};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::compression;
+use crate::index::{open_index, RecordingIndex, SegmentRecord, StorageTier};
+use crate::playback::{validate_export_range, Mp4Exporter, Timeline};
+use crate::schedule::RecordingSchedule;
+use crate::segmenter::{OpenSegment, PreRollBuffer, SegmentMuxer, SegmentPolicy};
+
+/// 流离线时检查其是否上线的间隔
+const STREAM_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// 待落盘分片队列长度
+const WRITE_QUEUE_CAPACITY: usize = 8;
+
+/// 录像存储池配置：realtime（SSD）、archive / longterm（HDD）
+pub fn pool_configs(storage: &RecordingStorageConfig) -> Vec<PoolConfig> {
+    let pool = |tier: StorageTier, path: &std::path::PathBuf, disk_type: DiskType| PoolConfig {
+        name: tier.pool_name().to_string(),
+        path: path.clone(),
+        disk_type,
+        priority: 1,
+        max_usage_percent: 95.0,
+    };
+
+    let mut pools = vec![
+        pool(StorageTier::Realtime, &storage.realtime_path, DiskType::SSD),
+        pool(StorageTier::Archive, &storage.archive_path, DiskType::HDD),
+    ];
+    if let Some(longterm_path) = &storage.longterm_path {
+        pools.push(pool(StorageTier::Longterm, longterm_path, DiskType::HDD));
+    }
+    pools
+}
+
+/// 录像任务状态
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub stream_id: String,
+    pub schedule: RecordingSchedule,
+    /// 流是否在线
+    pub online: bool,
+    /// 是否正在写分片
+    pub recording: bool,
+    pub segments: u64,
+    pub bytes: u64,
+    pub last_segment_end: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// 一次维护（保留期清理与分级压缩）的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MaintenanceReport {
+    pub expired: usize,
+    pub archived: usize,
+    pub longterm: usize,
+    /// 迁移失败的分片数（保留在原存储池，下次维护重试）
+    pub failed: usize,
+}
+
+enum JobCommand {
+    Trigger(String),
+    Stop,
+}
+
+struct RecordingJob {
+    commands: mpsc::UnboundedSender<JobCommand>,
+    status: Arc<RwLock<JobStatus>>,
+    task: JoinHandle<()>,
+}
+
+/// 录像器
+pub struct Recorder {
+    config: RecordingConfig,
+    stream_manager: Arc<StreamManager>,
+    storage: Arc<StorageManager>,
+    index: Arc<dyn RecordingIndex>,
+    jobs: RwLock<HashMap<String, RecordingJob>>,
+}
+
+impl Recorder {
+    /// 创建录像器；`storage` 中需已按 [`pool_configs`] 初始化存储池
+    pub async fn new(config: RecordingConfig, stream_manager: Arc<StreamManager>, storage: Arc<StorageManager>) -> Result<Self> {
+        let index = open_index(&config.index).await?;
+        Ok(Self::with_index(config, stream_manager, storage, index))
+    }
+
+    pub fn with_index(
+        config: RecordingConfig,
+        stream_manager: Arc<StreamManager>,
+        storage: Arc<StorageManager>,
+        index: Arc<dyn RecordingIndex>,
+    ) -> Self {
+        Self {
+            config,
+            stream_manager,
+            storage,
+            index,
+            jobs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &RecordingConfig {
+        &self.config
+    }
+
+    pub fn index(&self) -> Arc<dyn RecordingIndex> {
+        self.index.clone()
+    }
+
+    pub fn storage(&self) -> Arc<StorageManager> {
+        self.storage.clone()
+    }
+
+    /// 开始录像；已有任务时按新计划重新开始
+    pub async fn start(&self, stream_id: StreamId, schedule: RecordingSchedule) -> Result<()> {
+        if !self.config.enabled {
+            return Err(anyhow!("Recording is disabled"));
+        }
+        if self.has_job(stream_id.as_str()).await {
+            self.stop(&stream_id).await?;
+        }
+
+        let status = Arc::new(RwLock::new(JobStatus {
+            stream_id: stream_id.to_string(),
+            schedule: schedule.clone(),
+            online: false,
+            recording: false,
+            segments: 0,
+            bytes: 0,
+            last_segment_end: None,
+            last_error: None,
+        }));
+
+        let (write_tx, write_rx) = mpsc::channel(WRITE_QUEUE_CAPACITY);
+        let writer = SegmentWriter {
+            stream_id: stream_id.clone(),
+            storage: self.storage.clone(),
+            index: self.index.clone(),
+            compression: self.config.compression.realtime,
+            status: status.clone(),
+        };
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        let job = JobRunner {
+            stream_id: stream_id.clone(),
+            schedule,
+            stream_manager: self.stream_manager.clone(),
+            policy: SegmentPolicy::new(&self.config.segment),
+            writes: write_tx,
+            status: status.clone(),
+        };
+        let task = tokio::spawn(async move {
+            let writer_task = tokio::spawn(writer.run(write_rx));
+            job.run(commands_rx).await;
+            let _ = writer_task.await;
+        });
+
+        info!(target: "recording", stream_id = %stream_id, "Recording job started");
+        self.jobs.write().await.insert(
+            stream_id.to_string(),
+            RecordingJob {
+                commands: commands_tx,
+                status,
+                task,
+            },
+        );
+        Ok(())
+    }
+
+    /// 停止录像，等待当前分片落盘
+    pub async fn stop(&self, stream_id: &StreamId) -> Result<()> {
+        let job = self
+            .jobs
+            .write()
+            .await
+            .remove(stream_id.as_str())
+            .ok_or_else(|| anyhow!("Recording job not found: {}", stream_id))?;
+        let _ = job.commands.send(JobCommand::Stop);
+        let _ = job.task.await;
+        info!(target: "recording", stream_id = %stream_id, "Recording job stopped");
+        Ok(())
+    }
+
+    /// 停止所有录像任务
+    pub async fn shutdown(&self) {
+        let stream_ids: Vec<String> = self.jobs.read().await.keys().cloned().collect();
+        for stream_id in stream_ids {
+            let _ = self.stop(&StreamId::from(stream_id)).await;
+        }
+    }
+
+    /// 触发事件录像（仅事件计划）；录像持续到最后一次触发的延录时长之后
+    pub async fn trigger(&self, stream_id: &StreamId, event: &str) -> Result<()> {
+        let jobs = self.jobs.read().await;
+        let job = jobs
+            .get(stream_id.as_str())
+            .ok_or_else(|| anyhow!("Recording job not found: {}", stream_id))?;
+        if !matches!(job.status.read().await.schedule, RecordingSchedule::Event { .. }) {
+            return Err(anyhow!("Recording job is not event-triggered: {}", stream_id));
+        }
+        job.commands
+            .send(JobCommand::Trigger(event.to_string()))
+            .map_err(|_| anyhow!("Recording job stopped: {}", stream_id))
+    }
+
+    pub async fn has_job(&self, stream_id: &str) -> bool {
+        self.jobs.read().await.contains_key(stream_id)
+    }
+
+    pub async fn job(&self, stream_id: &str) -> Option<JobStatus> {
+        let jobs = self.jobs.read().await;
+        match jobs.get(stream_id) {
+            Some(job) => Some(job.status.read().await.clone()),
+            None => None,
+        }
+    }
+
+    pub async fn jobs(&self) -> Vec<JobStatus> {
+        let jobs = self.jobs.read().await;
+        let mut statuses = Vec::with_capacity(jobs.len());
+        for job in jobs.values() {
+            statuses.push(job.status.read().await.clone());
+        }
+        statuses.sort_by(|a, b| a.stream_id.cmp(&b.stream_id));
+        statuses
+    }
+
+    /// 与 `[start, end)` 相交的分片
+    pub async fn segments(&self, stream_id: &StreamId, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<SegmentRecord>> {
+        self.index.query(stream_id.as_str(), start, end).await
+    }
+
+    /// 读取分片内容（已解压的 MPEG-TS）
+    pub async fn read_segment(&self, record: &SegmentRecord) -> Result<Vec<u8>> {
+        let data = self.storage.read_from_pool(&record.pool, &record.path).await?;
+        let algorithm = record.compression;
+        tokio::task::spawn_blocking(move || compression::decompress(algorithm, &data)).await?
+    }
+
+    /// `[start, end)` 内的回放时间线
+    pub async fn timeline(&self, stream_id: &StreamId, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Timeline> {
+        Ok(Timeline::new(self.segments(stream_id, start, end).await?))
+    }
+
+    /// 按开始时间精确查找分片（播放列表中的分片地址）
+    pub async fn segment_at(&self, stream_id: &StreamId, start: DateTime<Utc>) -> Result<Option<SegmentRecord>> {
+        let segments = self.segments(stream_id, start, start + Duration::milliseconds(1)).await?;
+        Ok(segments.into_iter().find(|segment| segment.start == start))
+    }
+
+    /// 将 `[start, end]` 的录像重封装为单个 MP4
+    pub async fn export_mp4(&self, stream_id: &StreamId, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Bytes> {
+        validate_export_range(start, end)?;
+        let timeline = self.timeline(stream_id, start, end).await?;
+        let Some(first) = timeline.segments().first() else {
+            return Err(anyhow!("No recordings for {} in the requested range", stream_id));
+        };
+
+        let mut exporter = Mp4Exporter::new(first.start.min(start), start, end);
+        for segment in timeline.segments() {
+            let data = self.read_segment(segment).await?;
+            exporter.push_segment(segment, &data);
+        }
+        tokio::task::spawn_blocking(move || exporter.finish()).await?
+    }
+
+    /// 删除超过保留期的分片，并把旧分片迁移到归档 / 长期存储池
+    pub async fn run_maintenance(&self) -> Result<MaintenanceReport> {
+        let now = Utc::now();
+        let mut report = MaintenanceReport::default();
+
+        let retention = now - Duration::days(self.config.retention_days as i64);
+        for record in self.index.ended_before(retention).await? {
+            if let Err(e) = self.storage.delete_from_pool(&record.pool, &record.path).await {
+                warn!(target: "recording", path = %record.path, "Failed to delete expired recording: {}", e);
+            }
+            self.index.remove(&record.stream_id, &record.path).await?;
+            report.expired += 1;
+        }
+
+        // 先迁移长期档，避免同一分片在一次维护中被重复压缩
+        let compression = &self.config.compression;
+        let mut failed = HashSet::new();
+        if self.config.storage.longterm_path.is_some() {
+            let before = now - Duration::days(compression.apply_longterm_after_days as i64);
+            for record in self.index.ended_before(before).await? {
+                if record.tier != StorageTier::Longterm {
+                    match self.migrate(&record, StorageTier::Longterm, compression.longterm).await {
+                        Ok(()) => report.longterm += 1,
+                        Err(e) => {
+                            warn!(target: "recording", path = %record.path, "Failed to migrate recording to longterm: {}", e);
+                            failed.insert(record.path);
+                        }
+                    }
+                }
+            }
+        }
+
+        let before = now - Duration::hours(compression.apply_archive_after_hours as i64);
+        for record in self.index.ended_before(before).await? {
+            if record.tier == StorageTier::Realtime && !failed.contains(&record.path) {
+                match self.migrate(&record, StorageTier::Archive, compression.archive).await {
+                    Ok(()) => report.archived += 1,
+                    Err(e) => {
+                        warn!(target: "recording", path = %record.path, "Failed to migrate recording to archive: {}", e);
+                        failed.insert(record.path);
+                    }
+                }
+            }
+        }
+        report.failed = failed.len();
+
+        if report != MaintenanceReport::default() {
+            info!(
+                target: "recording",
+                expired = report.expired,
+                archived = report.archived,
+                longterm = report.longterm,
+                failed = report.failed,
+                "Recording maintenance finished"
+            );
+        }
+        Ok(report)
+    }
+
+    /// 定期执行维护
+    pub fn spawn_maintenance(self: Arc<Self>, interval: std::time::Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.run_maintenance().await {
+                    warn!(target: "recording", "Recording maintenance failed: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn migrate(&self, record: &SegmentRecord, tier: StorageTier, algorithm: CompressionAlgorithm) -> Result<()> {
+        let data = self.storage.read_from_pool(&record.pool, &record.path).await?;
+        let source = record.compression;
+        let data = tokio::task::spawn_blocking(move || {
+            let raw = compression::decompress(source, &data)?;
+            compression::compress(algorithm, &raw)
+        })
+        .await??;
+
+        let base = record
+            .path
+            .strip_suffix(compression::extension(record.compression))
+            .unwrap_or(&record.path);
+        let migrated = SegmentRecord {
+            path: format!("{}{}", base, compression::extension(algorithm)),
+            pool: tier.pool_name().to_string(),
+            size: data.len() as u64,
+            compression: algorithm,
+            tier,
+            ..record.clone()
+        };
+
+        self.storage.write_to_pool(&migrated.pool, &migrated.path, &data).await?;
+        self.index.replace(&record.path, &migrated).await?;
+        if let Err(e) = self.storage.delete_from_pool(&record.pool, &record.path).await {
+            warn!(target: "recording", path = %record.path, "Failed to delete migrated recording: {}", e);
+        }
+        debug!(target: "recording", from = %record.path, to = %migrated.path, "Recording migrated");
+        Ok(())
+    }
+}
+
+/// 单次在线会话的结束原因
+enum SessionEnd {
+    Offline,
+    Stopped,
+}
+
+/// 切分协程
+struct JobRunner {
+    stream_id: StreamId,
+    schedule: RecordingSchedule,
+    stream_manager: Arc<StreamManager>,
+    policy: SegmentPolicy,
+    writes: mpsc::Sender<OpenSegment>,
+    status: Arc<RwLock<JobStatus>>,
+}
+
+impl JobRunner {
+    async fn run(self, mut commands: mpsc::UnboundedReceiver<JobCommand>) {
+        loop {
+            let receiver = match self.stream_manager.subscribe(&self.stream_id).await {
+                Ok(receiver) => receiver,
+                Err(_) => {
+                    tokio::select! {
+                        command = commands.recv() => match command {
+                            // 流离线时的事件没有画面可录
+                            Some(JobCommand::Trigger(_)) => {}
+                            Some(JobCommand::Stop) | None => return,
+                        },
+                        _ = tokio::time::sleep(STREAM_POLL_INTERVAL) => {}
+                    }
+                    continue;
+                }
+            };
+
+            self.status.write().await.online = true;
+            let end = match self.record_session(receiver, &mut commands).await {
+                Ok(end) => end,
+                Err(e) => {
+                    warn!(target: "recording", stream_id = %self.stream_id, "Recording session failed: {}", e);
+                    self.status.write().await.last_error = Some(e.to_string());
+                    tokio::time::sleep(STREAM_POLL_INTERVAL).await;
+                    SessionEnd::Offline
+                }
+            };
+            {
+                let mut status = self.status.write().await;
+                status.online = false;
+                status.recording = false;
+            }
+            if let SessionEnd::Stopped = end {
+                return;
+            }
+        }
+    }
+
+    async fn record_session(
+        &self,
+        mut receiver: broadcast::Receiver<MediaPacket>,
+        commands: &mut mpsc::UnboundedReceiver<JobCommand>,
+    ) -> Result<SessionEnd> {
+        let metadata = self.stream_manager.get_metadata(&self.stream_id).await.unwrap_or_default();
+        let mut session = Session {
+            muxer: SegmentMuxer::new(&metadata)?,
+            clock: None,
+            segment: None,
+            pre_roll: self.schedule.pre_roll().map(PreRollBuffer::new),
+            event: None,
+        };
+        let mut waiting_keyframe = false;
+        let mut tick = tokio::time::interval(STREAM_POLL_INTERVAL);
+
+        let end = loop {
+            tokio::select! {
+                result = receiver.recv() => {
+                    let packet = match result {
+                        Ok(packet) => packet,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            // 丢包后的数据无法解码，结束当前分片，从下一个关键帧重新开始
+                            debug!(target: "recording", stream_id = %self.stream_id, skipped, "Recorder lagged");
+                            self.finish(&mut session).await?;
+                            waiting_keyframe = true;
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break SessionEnd::Offline,
+                    };
+
+                    // 编码晚于订阅确定时重新读取元数据
+                    let unconfigured = match packet.packet_type {
+                        PacketType::Video => !session.muxer.has_video(),
+                        PacketType::Audio => !session.muxer.has_audio(),
+                    };
+                    if unconfigured {
+                        if let Some(metadata) = self.stream_manager.get_metadata(&self.stream_id).await {
+                            session.muxer.configure(&metadata)?;
+                        }
+                    }
+
+                    let starts_gop = session.starts_gop(&packet);
+                    if waiting_keyframe {
+                        if !starts_gop {
+                            continue;
+                        }
+                        waiting_keyframe = false;
+                    }
+                    self.handle_packet(&mut session, packet, starts_gop).await?;
+                }
+                command = commands.recv() => match command {
+                    Some(JobCommand::Trigger(event)) => self.handle_trigger(&mut session, event).await?,
+                    Some(JobCommand::Stop) | None => break SessionEnd::Stopped,
+                },
+                _ = tick.tick() => {
+                    if self.stream_manager.get_context(&self.stream_id).await.is_none() {
+                        break SessionEnd::Offline;
+                    }
+                    if session.segment.is_some() && !self.is_active(&session, Utc::now()) {
+                        self.finish(&mut session).await?;
+                    }
+                }
+            }
+        };
+
+        self.finish(&mut session).await?;
+        Ok(end)
+    }
+
+    fn is_active(&self, session: &Session, at: DateTime<Utc>) -> bool {
+        match &session.event {
+            Some((_, until)) => at <= *until,
+            None => self.schedule.is_active(at.with_timezone(&Local).naive_local()),
+        }
+    }
+
+    async fn handle_packet(&self, session: &mut Session, packet: MediaPacket, starts_gop: bool) -> Result<()> {
+        let at = session.time_of(packet.timestamp);
+        if let Some(buffer) = session.pre_roll.as_mut() {
+            buffer.push(at, packet.clone(), session.muxer.has_video());
+        }
+        if session.event.as_ref().is_some_and(|(_, until)| at > *until) {
+            session.event = None;
+        }
+
+        if !self.is_active(session, at) {
+            return self.finish(session).await;
+        }
+
+        let cut = match &session.segment {
+            Some(segment) => starts_gop && self.policy.should_cut(at - segment.start, segment.data.len() as u64),
+            None if starts_gop => {
+                self.open(session, at).await;
+                false
+            }
+            None => return Ok(()),
+        };
+        if cut {
+            if let Some(segment) = session.segment.as_mut() {
+                segment.last = at;
+            }
+            self.finish(session).await?;
+            self.open(session, at).await;
+        }
+
+        if let Some(segment) = session.segment.as_mut() {
+            session.muxer.mux(&packet, &mut segment.data)?;
+            segment.last = at;
+        }
+        Ok(())
+    }
+
+    async fn handle_trigger(&self, session: &mut Session, event: String) -> Result<()> {
+        let Some(post_roll) = self.schedule.post_roll() else {
+            return Ok(());
+        };
+        let now = session.now();
+        info!(target: "recording", stream_id = %self.stream_id, event = %event, "Recording event triggered");
+        session.event = Some((event, now + post_roll));
+
+        if session.segment.is_some() {
+            return Ok(());
+        }
+        // 从预录缓存开始写分片；缓存为空时等待下一个关键帧
+        let buffered = session.pre_roll.as_mut().map(PreRollBuffer::drain).unwrap_or_default();
+        let Some((start, _)) = buffered.first() else {
+            return Ok(());
+        };
+        self.open(session, *start).await;
+        if let Some(segment) = session.segment.as_mut() {
+            for (at, packet) in &buffered {
+                session.muxer.mux(packet, &mut segment.data)?;
+                segment.last = *at;
+            }
+        }
+        Ok(())
+    }
+
+    async fn open(&self, session: &mut Session, at: DateTime<Utc>) {
+        session.muxer.start_segment();
+        session.segment = Some(OpenSegment {
+            start: at,
+            last: at,
+            data: Vec::new(),
+            event: session.event.as_ref().map(|(event, _)| event.clone()),
+        });
+        self.status.write().await.recording = true;
+    }
+
+    async fn finish(&self, session: &mut Session) -> Result<()> {
+        let Some(segment) = session.segment.take() else {
+            return Ok(());
+        };
+        // 已录入分片的数据不再作为下次事件的预录
+        if let Some(buffer) = session.pre_roll.as_mut() {
+            buffer.clear();
+        }
+        self.status.write().await.recording = false;
+        if segment.data.is_empty() {
+            return Ok(());
+        }
+        self.writes
+            .send(segment)
+            .await
+            .map_err(|_| anyhow!("Recording writer stopped: {}", self.stream_id))
+    }
+}
+
+/// 单次在线会话的切分状态
+struct Session {
+    muxer: SegmentMuxer,
+    /// 媒体时间戳与墙钟的对应关系
+    clock: Option<(DateTime<Utc>, u32)>,
+    segment: Option<OpenSegment>,
+    pre_roll: Option<PreRollBuffer>,
+    /// 当前事件及其录像截止时间
+    event: Option<(String, DateTime<Utc>)>,
+}
+
+impl Session {
+    fn time_of(&mut self, timestamp: u32) -> DateTime<Utc> {
+        let (anchor, base) = *self.clock.get_or_insert((Utc::now(), timestamp));
+        // 音视频时间戳可能略早于第一个包
+        anchor + Duration::milliseconds(timestamp.wrapping_sub(base) as i32 as i64)
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        self.segment.as_ref().map(|segment| segment.last).unwrap_or_else(Utc::now)
+    }
+
+    fn starts_gop(&self, packet: &MediaPacket) -> bool {
+        !self.muxer.has_video() || (packet.packet_type == PacketType::Video && packet.is_keyframe)
+    }
+}
+
+/// 落盘协程：压缩、写入实时存储池并登记索引
+struct SegmentWriter {
+    stream_id: StreamId,
+    storage: Arc<StorageManager>,
+    index: Arc<dyn RecordingIndex>,
+    compression: CompressionAlgorithm,
+    status: Arc<RwLock<JobStatus>>,
+}
+
+impl SegmentWriter {
+    async fn run(self, mut segments: mpsc::Receiver<OpenSegment>) {
+        while let Some(segment) = segments.recv().await {
+            match self.write(segment).await {
+                Ok(record) => {
+                    let mut status = self.status.write().await;
+                    status.segments += 1;
+                    status.bytes += record.size;
+                    status.last_segment_end = Some(record.end);
+                }
+                Err(e) => {
+                    warn!(target: "recording", stream_id = %self.stream_id, "Failed to write recording segment: {}", e);
+                    self.status.write().await.last_error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    async fn write(&self, segment: OpenSegment) -> Result<SegmentRecord> {
+        let algorithm = self.compression;
+        let OpenSegment { start, last, data, event } = segment;
+        let data = tokio::task::spawn_blocking(move || compression::compress(algorithm, &data)).await??;
+
+        let tier = StorageTier::Realtime;
+        let record = SegmentRecord {
+            stream_id: self.stream_id.to_string(),
+            start,
+            end: last,
+            path: format!(
+                "recordings/{}/{}/{}.ts{}",
+                self.stream_id,
+                start.format("%Y%m%d"),
+                start.timestamp_millis(),
+                compression::extension(algorithm)
+            ),
+            pool: tier.pool_name().to_string(),
+            size: data.len() as u64,
+            compression: algorithm,
+            tier,
+            event,
+        };
+        self.storage.write_to_pool(&record.pool, &record.path, &data).await?;
+        self.index.insert(&record).await?;
+        debug!(target: "recording", path = %record.path, size = record.size, "Recording segment written");
+        Ok(record)
+    }
+}
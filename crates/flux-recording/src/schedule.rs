@@ -0,0 +1,129 @@
+//! 录像计划：持续录像、按时间段录像、事件触发录像（带预录 / 延录）
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// 录像计划
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordingSchedule {
+    /// 流在线时一直录像
+    Continuous,
+
+    /// 仅在时间段内录像（本地时间）
+    Windows { windows: Vec<TimeWindow> },
+
+    /// 事件触发：保留最近 `pre_roll_secs` 秒的画面，触发后持续录到最后一次触发的 `post_roll_secs` 秒后
+    Event { pre_roll_secs: u64, post_roll_secs: u64 },
+}
+
+impl RecordingSchedule {
+    /// 按计划当前是否应录像；事件计划由触发决定，这里总是 false
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        match self {
+            Self::Continuous => true,
+            Self::Windows { windows } => windows.iter().any(|window| window.contains(now)),
+            Self::Event { .. } => false,
+        }
+    }
+
+    /// 预录时长
+    pub fn pre_roll(&self) -> Option<Duration> {
+        match self {
+            Self::Event { pre_roll_secs, .. } => Some(Duration::seconds(*pre_roll_secs as i64)),
+            _ => None,
+        }
+    }
+
+    /// 延录时长
+    pub fn post_roll(&self) -> Option<Duration> {
+        match self {
+            Self::Event { post_roll_secs, .. } => Some(Duration::seconds(*post_roll_secs as i64)),
+            _ => None,
+        }
+    }
+}
+
+/// 每日时间段 `[start, end)`；`end` 早于 `start` 表示跨午夜
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+
+    /// 生效的星期（按时间段开始的那天计算）；为空表示每天
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let day = now.weekday();
+        if self.start <= self.end {
+            time >= self.start && time < self.end && self.applies_on(day)
+        } else if time >= self.start {
+            self.applies_on(day)
+        } else {
+            time < self.end && self.applies_on(day.pred())
+        }
+    }
+
+    fn applies_on(&self, day: Weekday) -> bool {
+        self.weekdays.is_empty() || self.weekdays.contains(&day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2024-01-01 是星期一
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn window(start: u32, end: u32, weekdays: Vec<Weekday>) -> TimeWindow {
+        TimeWindow {
+            start: NaiveTime::from_hms_opt(start, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end, 0, 0).unwrap(),
+            weekdays,
+        }
+    }
+
+    #[test]
+    fn test_time_windows() {
+        let office = window(9, 18, vec![Weekday::Mon, Weekday::Tue]);
+        assert!(office.contains(at(1, 9, 0)));
+        assert!(!office.contains(at(1, 18, 0)));
+        assert!(!office.contains(at(3, 10, 0)));
+
+        // 周一 22:00 到次日 06:00
+        let night = window(22, 6, vec![Weekday::Mon]);
+        assert!(night.contains(at(1, 23, 0)));
+        assert!(night.contains(at(2, 5, 59)));
+        assert!(!night.contains(at(1, 5, 0)));
+        assert!(!night.contains(at(2, 23, 0)));
+
+        let schedule = RecordingSchedule::Windows { windows: vec![office, night] };
+        assert!(schedule.is_active(at(2, 12, 0)));
+        assert!(!schedule.is_active(at(2, 20, 0)));
+        assert!(RecordingSchedule::Continuous.is_active(at(5, 3, 0)));
+    }
+
+    #[test]
+    fn test_schedule_serde() {
+        let schedule: RecordingSchedule =
+            serde_json::from_str(r#"{"type":"event","pre_roll_secs":5,"post_roll_secs":30}"#).unwrap();
+        assert_eq!(schedule.pre_roll(), Some(Duration::seconds(5)));
+        assert_eq!(schedule.post_roll(), Some(Duration::seconds(30)));
+        assert!(!schedule.is_active(at(1, 12, 0)));
+
+        let schedule: RecordingSchedule = serde_json::from_str(
+            r#"{"type":"windows","windows":[{"start":"08:00:00","end":"20:00:00","weekdays":["Sat","Sun"]}]}"#,
+        )
+        .unwrap();
+        assert!(schedule.is_active(at(6, 8, 0)));
+        assert!(!schedule.is_active(at(5, 8, 0)));
+    }
+}
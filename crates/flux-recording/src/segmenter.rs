@@ -0,0 +1,285 @@
+//! 分片切分：按 SegmentStrategy 在关键帧处切分 MPEG-TS 分片，并缓存预录画面
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use flux_config::recording::{RecordingSegmentConfig, SegmentStrategy};
+use flux_media_core::codec::AacConfig;
+use flux_media_core::playback::TsMuxer;
+use flux_media_core::types::{AudioCodec, VideoCodec};
+use flux_stream::{MediaPacket, PacketType, StreamMetadata};
+use std::collections::VecDeque;
+
+/// 切分策略
+#[derive(Debug, Clone)]
+pub struct SegmentPolicy {
+    strategy: SegmentStrategy,
+    min_duration: Duration,
+    max_duration: Duration,
+    target_size: u64,
+}
+
+impl SegmentPolicy {
+    pub fn new(config: &RecordingSegmentConfig) -> Self {
+        Self {
+            strategy: config.strategy,
+            min_duration: Duration::seconds(config.min_duration as i64),
+            max_duration: Duration::seconds(config.max_duration as i64),
+            target_size: config.target_size_mb * 1024 * 1024,
+        }
+    }
+
+    /// 在关键帧处判断当前分片是否应结束
+    ///
+    /// - Fixed：达到 `max_duration`
+    /// - Size：达到 `target_size_mb`
+    /// - Adaptive：至少 `min_duration`，达到目标大小或 `max_duration` 即切分
+    pub fn should_cut(&self, duration: Duration, size: u64) -> bool {
+        match self.strategy {
+            SegmentStrategy::Fixed => duration >= self.max_duration,
+            SegmentStrategy::Size => size >= self.target_size,
+            SegmentStrategy::Adaptive => {
+                duration >= self.min_duration && (size >= self.target_size || duration >= self.max_duration)
+            }
+        }
+    }
+}
+
+/// 录制中的分片
+pub struct OpenSegment {
+    pub start: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+    pub data: Vec<u8>,
+    pub event: Option<String>,
+}
+
+impl OpenSegment {
+    pub fn duration(&self) -> Duration {
+        self.last - self.start
+    }
+}
+
+/// 流元数据 → TS 复用器（视频为 Annex B，AAC 补 ADTS 头）
+pub struct SegmentMuxer {
+    muxer: TsMuxer,
+    audio: Option<AudioCodec>,
+    aac: Option<AacConfig>,
+    has_video: bool,
+}
+
+impl SegmentMuxer {
+    pub fn new(metadata: &StreamMetadata) -> Result<Self> {
+        let mut muxer = Self {
+            muxer: TsMuxer::new(),
+            audio: None,
+            aac: None,
+            has_video: false,
+        };
+        muxer.configure(metadata)?;
+        Ok(muxer)
+    }
+
+    pub fn configure(&mut self, metadata: &StreamMetadata) -> Result<()> {
+        match metadata.video_codec.as_deref() {
+            Some("h265") => self.muxer.set_video_codec(VideoCodec::H265)?,
+            Some(_) => self.muxer.set_video_codec(VideoCodec::H264)?,
+            None if metadata.audio_codec.is_some() => self.muxer.set_video_enabled(false),
+            None => {}
+        }
+        self.has_video = metadata.video_codec.is_some();
+
+        let (audio, aac) = match metadata.audio_codec.as_deref() {
+            Some("aac") => match metadata
+                .audio_sample_rate
+                .zip(metadata.audio_channels)
+                .and_then(|(sample_rate, channels)| AacConfig::lc(sample_rate, channels))
+            {
+                Some(config) => (Some(AudioCodec::AAC), Some(config)),
+                None => (None, None),
+            },
+            Some("pcma") => (Some(AudioCodec::G711A), None),
+            Some("pcmu") => (Some(AudioCodec::G711U), None),
+            _ => (None, None),
+        };
+        if let Some(codec) = audio {
+            self.muxer.set_audio_codec(codec)?;
+        }
+        self.audio = audio;
+        self.aac = aac;
+        Ok(())
+    }
+
+    pub fn has_video(&self) -> bool {
+        self.has_video
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.audio.is_some()
+    }
+
+    /// 分片开头重新输出 PAT/PMT，使每个分片可单独播放
+    pub fn start_segment(&mut self) {
+        self.muxer.force_pat_pmt();
+    }
+
+    /// 复用一个媒体包并追加到 `output`
+    pub fn mux(&mut self, packet: &MediaPacket, output: &mut Vec<u8>) -> Result<()> {
+        let timestamp = packet.timestamp as u64 * 90;
+        let ts_packets = match packet.packet_type {
+            PacketType::Video if self.has_video => {
+                self.muxer
+                    .mux_video_pes(&packet.data, timestamp, timestamp, packet.is_keyframe)?
+            }
+            PacketType::Video => return Ok(()),
+            PacketType::Audio => match (self.audio, self.aac) {
+                (Some(AudioCodec::AAC), Some(aac)) => {
+                    let mut frame = Vec::with_capacity(packet.data.len() + 7);
+                    frame.extend_from_slice(&aac.adts_header(packet.data.len()));
+                    frame.extend_from_slice(&packet.data);
+                    self.muxer.mux_audio_pes(&frame, timestamp)?
+                }
+                (Some(_), _) => self.muxer.mux_audio_pes(&packet.data, timestamp)?,
+                (None, _) => return Ok(()),
+            },
+        };
+        for ts_packet in ts_packets {
+            output.extend_from_slice(&ts_packet);
+        }
+        Ok(())
+    }
+}
+
+/// 预录缓存：保留最近 `window` 时长的数据包，且总是从关键帧开始
+pub struct PreRollBuffer {
+    window: Duration,
+    packets: VecDeque<(DateTime<Utc>, MediaPacket)>,
+}
+
+impl PreRollBuffer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            packets: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, at: DateTime<Utc>, packet: MediaPacket, has_video: bool) {
+        let starts_gop = !has_video || (packet.packet_type == PacketType::Video && packet.is_keyframe);
+        if self.packets.is_empty() && !starts_gop {
+            return;
+        }
+        self.packets.push_back((at, packet));
+
+        // 丢弃整个 GOP，直到剩余数据仍覆盖预录时长
+        let cutoff = at - self.window;
+        loop {
+            let next_start = self
+                .packets
+                .iter()
+                .skip(1)
+                .position(|(_, packet)| !has_video || (packet.packet_type == PacketType::Video && packet.is_keyframe))
+                .map(|position| position + 1);
+            match next_start {
+                Some(position) if self.packets[position].0 <= cutoff => {
+                    self.packets.drain(..position);
+                }
+                _ => break,
+            }
+        }
+    }
+
+    pub fn drain(&mut self) -> Vec<(DateTime<Utc>, MediaPacket)> {
+        self.packets.drain(..).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.packets.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use chrono::TimeZone;
+
+    fn policy(strategy: SegmentStrategy) -> SegmentPolicy {
+        SegmentPolicy::new(&RecordingSegmentConfig {
+            strategy,
+            min_duration: 60,
+            max_duration: 300,
+            target_size_mb: 1,
+        })
+    }
+
+    #[test]
+    fn test_segment_policy() {
+        let mb = 1024 * 1024;
+        let fixed = policy(SegmentStrategy::Fixed);
+        assert!(!fixed.should_cut(Duration::seconds(299), 10 * mb));
+        assert!(fixed.should_cut(Duration::seconds(300), 0));
+
+        let size = policy(SegmentStrategy::Size);
+        assert!(size.should_cut(Duration::seconds(1), mb));
+        assert!(!size.should_cut(Duration::seconds(600), mb - 1));
+
+        let adaptive = policy(SegmentStrategy::Adaptive);
+        assert!(!adaptive.should_cut(Duration::seconds(30), 10 * mb));
+        assert!(adaptive.should_cut(Duration::seconds(60), mb));
+        assert!(!adaptive.should_cut(Duration::seconds(120), 0));
+        assert!(adaptive.should_cut(Duration::seconds(300), 0));
+    }
+
+    fn video(timestamp: u32, is_keyframe: bool) -> MediaPacket {
+        MediaPacket {
+            data: Bytes::from_static(&[0, 0, 0, 1, 0x65, 0x88]),
+            timestamp,
+            is_keyframe,
+            packet_type: PacketType::Video,
+        }
+    }
+
+    #[test]
+    fn test_pre_roll_keeps_whole_gops() {
+        let base = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let mut buffer = PreRollBuffer::new(Duration::seconds(3));
+
+        // 不从关键帧开始的数据不缓存
+        buffer.push(base, video(0, false), true);
+        // 每 2 秒一个关键帧，每秒一帧
+        for second in 1..=8i64 {
+            let timestamp = second as u32 * 1000;
+            buffer.push(base + Duration::seconds(second), video(timestamp, second % 2 == 1), true);
+        }
+
+        let packets = buffer.drain();
+        // 需要覆盖 8 - 3 = 5 秒起的数据，最近一个不晚于 5 秒的关键帧在 5 秒
+        assert_eq!(packets.first().unwrap().1.timestamp, 5000);
+        assert!(packets.first().unwrap().1.is_keyframe);
+        assert_eq!(packets.len(), 4);
+    }
+
+    #[test]
+    fn test_muxer_writes_pat_pmt_per_segment() {
+        let metadata = StreamMetadata {
+            video_codec: Some("h264".to_string()),
+            ..Default::default()
+        };
+        let mut muxer = SegmentMuxer::new(&metadata).unwrap();
+        assert!(muxer.has_video());
+        assert!(!muxer.has_audio());
+
+        let mut first = Vec::new();
+        muxer.start_segment();
+        muxer.mux(&video(0, true), &mut first).unwrap();
+        let mut second = Vec::new();
+        muxer.start_segment();
+        muxer.mux(&video(40, true), &mut second).unwrap();
+
+        for segment in [&first, &second] {
+            assert_eq!(segment.len() % 188, 0);
+            // 第一个 TS 包为 PAT（PID 0）
+            assert_eq!(segment[0], 0x47);
+            assert_eq!(((segment[1] as u16 & 0x1F) << 8) | segment[2] as u16, 0);
+        }
+    }
+}
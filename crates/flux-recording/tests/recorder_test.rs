@@ -0,0 +1,430 @@
+//! 录像器端到端测试：订阅 flux-stream 流，切分、落盘、索引与维护
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{Duration, Utc};
+use flux_config::recording::{CompressionAlgorithm, IndexEngine, RecordingConfig, SegmentStrategy};
+use flux_config::{StreamMode, StreamingConfig};
+use flux_media_core::playback::ts::STREAM_TYPE_H264;
+use flux_media_core::playback::TsDemuxer;
+use flux_media_core::types::StreamId;
+use flux_recording::{compression, pool_configs, Recorder, RecordingSchedule, SegmentRecord, StorageTier};
+use flux_storage::StorageManager;
+use flux_stream::{MediaPacket, PacketType, Protocol, Stream, StreamManager, StreamMetadata, StreamStatus};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+const KEYFRAME: &[u8] = &[
+    0, 0, 0, 1, 0x67, 0x42, 0xC0, 0x1E, 0xD9, 0x00, 0xA0, 0x47, 0xFE, 0xC8, //
+    0, 0, 0, 1, 0x68, 0xCE, 0x3C, 0x80, //
+    0, 0, 0, 1, 0x65, 0x88, 0x84, 0x00, 0x33,
+];
+const DELTA_FRAME: &[u8] = &[0, 0, 0, 1, 0x41, 0x9A, 0x02, 0x03];
+
+struct MockStream {
+    stream_id: StreamId,
+}
+
+#[async_trait]
+impl Stream for MockStream {
+    fn stream_id(&self) -> &StreamId {
+        &self.stream_id
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::RTMP
+    }
+
+    async fn metadata(&self) -> StreamMetadata {
+        StreamMetadata {
+            video_codec: Some("h264".to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn status(&self) -> StreamStatus {
+        StreamStatus::Running
+    }
+
+    async fn start(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn video(timestamp: u32, is_keyframe: bool) -> MediaPacket {
+    MediaPacket {
+        data: Bytes::from_static(if is_keyframe { KEYFRAME } else { DELTA_FRAME }),
+        timestamp,
+        is_keyframe,
+        packet_type: PacketType::Video,
+    }
+}
+
+struct Fixture {
+    _dir: TempDir,
+    stream_manager: Arc<StreamManager>,
+    recorder: Arc<Recorder>,
+    stream_id: StreamId,
+}
+
+async fn fixture(configure: impl FnOnce(&mut RecordingConfig)) -> Fixture {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = RecordingConfig::default();
+    config.index.engine = IndexEngine::Json;
+    config.index.db_path = dir.path().join("index");
+    config.storage.realtime_path = dir.path().join("realtime");
+    config.storage.archive_path = dir.path().join("archive");
+    config.storage.longterm_path = Some(dir.path().join("longterm"));
+    configure(&mut config);
+
+    let pools = pool_configs(&config.storage);
+    for pool in &pools {
+        std::fs::create_dir_all(&pool.path).unwrap();
+    }
+    let storage = Arc::new(StorageManager::new());
+    storage.initialize(pools).await.unwrap();
+
+    let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+    let recorder = Arc::new(Recorder::new(config, stream_manager.clone(), storage).await.unwrap());
+    Fixture {
+        _dir: dir,
+        stream_manager,
+        recorder,
+        stream_id: StreamId::new("rtmp", "live/cam1"),
+    }
+}
+
+impl Fixture {
+    async fn register(&self) {
+        self.stream_manager
+            .register_stream(
+                Box::new(MockStream {
+                    stream_id: self.stream_id.clone(),
+                }),
+                StreamMode::Passthrough { remux: true },
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn wait_online(&self) {
+        for _ in 0..50 {
+            if self.recorder.job(self.stream_id.as_str()).await.is_some_and(|status| status.online) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        panic!("recording job never came online");
+    }
+
+    async fn all_segments(&self) -> Vec<SegmentRecord> {
+        let now = Utc::now();
+        self.recorder
+            .segments(&self.stream_id, now - Duration::hours(1), now + Duration::hours(1))
+            .await
+            .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn test_continuous_recording_cuts_on_keyframes() {
+    let fixture = fixture(|config| {
+        config.segment.strategy = SegmentStrategy::Fixed;
+        config.segment.max_duration = 1;
+    })
+    .await;
+    fixture.register().await;
+    fixture
+        .recorder
+        .start(fixture.stream_id.clone(), RecordingSchedule::Continuous)
+        .await
+        .unwrap();
+    fixture.wait_online().await;
+
+    // 3 秒媒体，每秒一个关键帧；通道容量足够，一次性发布
+    for frame in 0..75u32 {
+        fixture
+            .stream_manager
+            .publish_packet(&fixture.stream_id, video(frame * 40, frame % 25 == 0))
+            .await
+            .unwrap();
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    fixture.recorder.stop(&fixture.stream_id).await.unwrap();
+
+    let segments = fixture.all_segments().await;
+    assert_eq!(segments.len(), 3);
+    for pair in segments.windows(2) {
+        assert_eq!(pair[0].end, pair[1].start);
+        assert_eq!(pair[0].duration_ms(), 1000);
+    }
+    assert!(segments.iter().all(|segment| segment.tier == StorageTier::Realtime
+        && segment.compression == CompressionAlgorithm::Lz4
+        && segment.path.ends_with(".ts.lz4")));
+
+    let mut frames = 0;
+    for segment in &segments {
+        let data = fixture.recorder.read_segment(segment).await.unwrap();
+        let mut demuxer = TsDemuxer::new();
+        let mut segment_frames = demuxer.push(&data);
+        segment_frames.extend(demuxer.flush());
+        assert!(segment_frames.iter().all(|frame| frame.stream_type == STREAM_TYPE_H264));
+        // 每个分片从关键帧开始
+        assert!(segment_frames[0].data.starts_with(KEYFRAME));
+        frames += segment_frames.len();
+    }
+    assert_eq!(frames, 75);
+}
+
+#[tokio::test]
+async fn test_playback_vod_seek_and_export() {
+    let fixture = fixture(|config| {
+        config.segment.strategy = SegmentStrategy::Fixed;
+        config.segment.max_duration = 1;
+    })
+    .await;
+    fixture.register().await;
+    fixture
+        .recorder
+        .start(fixture.stream_id.clone(), RecordingSchedule::Continuous)
+        .await
+        .unwrap();
+    fixture.wait_online().await;
+    for frame in 0..75u32 {
+        fixture
+            .stream_manager
+            .publish_packet(&fixture.stream_id, video(frame * 40, frame % 25 == 0))
+            .await
+            .unwrap();
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    fixture.recorder.stop(&fixture.stream_id).await.unwrap();
+
+    let segments = fixture.all_segments().await;
+    assert_eq!(segments.len(), 3);
+    let (start, end) = (segments[0].start, segments[2].end);
+    let timeline = fixture.recorder.timeline(&fixture.stream_id, start, end).await.unwrap();
+    let m3u8 = timeline.vod_playlist(|segment| segment.path.clone(), Some(start + Duration::milliseconds(1500)));
+    assert_eq!(m3u8.matches("#EXTINF:").count(), 3);
+    assert_eq!(m3u8.matches("#EXTINF:1.000,").count(), 2);
+    assert!(!m3u8.contains("#EXT-X-DISCONTINUITY"));
+    assert!(m3u8.contains("#EXT-X-START:TIME-OFFSET=1.500"));
+
+    let position = timeline.seek(start + Duration::milliseconds(2200)).unwrap();
+    assert_eq!((position.index, position.offset_ms), (2, 200));
+    let record = fixture
+        .recorder
+        .segment_at(&fixture.stream_id, segments[1].start)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.path, segments[1].path);
+
+    // 从第二个分片中间导出：视频回退到该分片的关键帧，到第三个分片中间结束
+    let mp4 = fixture
+        .recorder
+        .export_mp4(&fixture.stream_id, start + Duration::milliseconds(1500), start + Duration::milliseconds(2500))
+        .await
+        .unwrap();
+    assert_eq!(&mp4[4..8], b"ftyp");
+    assert!(mp4.windows(4).any(|w| w == b"avcC"));
+    assert!(mp4.windows(4).any(|w| w == b"co64"));
+    // 1000..=2500ms 共 38 帧
+    let stsz = mp4.windows(4).position(|w| w == b"stsz").unwrap() + 4;
+    let count = u32::from_be_bytes(mp4[stsz + 8..stsz + 12].try_into().unwrap());
+    assert_eq!(count, 38);
+
+    assert!(fixture
+        .recorder
+        .export_mp4(&fixture.stream_id, end + Duration::hours(1), end + Duration::hours(2))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_event_recording_with_pre_and_post_roll() {
+    let fixture = fixture(|_| {}).await;
+    fixture.register().await;
+    fixture
+        .recorder
+        .start(
+            fixture.stream_id.clone(),
+            RecordingSchedule::Event {
+                pre_roll_secs: 1,
+                post_roll_secs: 1,
+            },
+        )
+        .await
+        .unwrap();
+    fixture.wait_online().await;
+
+    // 按实时节奏发布：每 100ms 一帧，每 500ms 一个关键帧
+    let publish = |from: u32, to: u32| {
+        let stream_manager = fixture.stream_manager.clone();
+        let stream_id = fixture.stream_id.clone();
+        async move {
+            for frame in from..to {
+                stream_manager
+                    .publish_packet(&stream_id, video(frame * 100, frame % 5 == 0))
+                    .await
+                    .unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        }
+    };
+
+    publish(0, 20).await;
+    assert!(fixture.all_segments().await.is_empty());
+    fixture.recorder.trigger(&fixture.stream_id, "motion").await.unwrap();
+    publish(20, 45).await;
+    fixture.recorder.stop(&fixture.stream_id).await.unwrap();
+
+    let segments = fixture.all_segments().await;
+    assert_eq!(segments.len(), 1);
+    let segment = &segments[0];
+    assert_eq!(segment.event.as_deref(), Some("motion"));
+    // 约 1 秒预录 + 1 秒延录
+    let duration = segment.duration_ms();
+    assert!((2000..=3000).contains(&duration), "duration {}", duration);
+
+    let data = fixture.recorder.read_segment(segment).await.unwrap();
+    let mut demuxer = TsDemuxer::new();
+    let mut frames = demuxer.push(&data);
+    frames.extend(demuxer.flush());
+    assert!(frames[0].data.starts_with(KEYFRAME));
+
+    assert!(fixture
+        .recorder
+        .start(fixture.stream_id.clone(), RecordingSchedule::Continuous)
+        .await
+        .is_ok());
+    assert!(fixture.recorder.trigger(&fixture.stream_id, "motion").await.is_err());
+    fixture.recorder.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_maintenance_retention_and_tiering() {
+    let fixture = fixture(|config| {
+        config.retention_days = 9;
+        config.compression.realtime = CompressionAlgorithm::None;
+    })
+    .await;
+    let storage = fixture.recorder.storage();
+    let index = fixture.recorder.index();
+    let payload: Vec<u8> = (0..4096u32).map(|i| (i % 188) as u8).collect();
+
+    let now = Utc::now();
+    let insert = |name: &str, age: Duration| {
+        let record = SegmentRecord {
+            stream_id: fixture.stream_id.to_string(),
+            start: now - age - Duration::minutes(1),
+            end: now - age,
+            path: format!("recordings/{}/{}.ts", fixture.stream_id, name),
+            pool: StorageTier::Realtime.pool_name().to_string(),
+            size: payload.len() as u64,
+            compression: CompressionAlgorithm::None,
+            tier: StorageTier::Realtime,
+            event: None,
+        };
+        let storage = storage.clone();
+        let index = index.clone();
+        let payload = payload.clone();
+        async move {
+            storage.write_to_pool(&record.pool, &record.path, &payload).await.unwrap();
+            index.insert(&record).await.unwrap();
+        }
+    };
+    insert("expired", Duration::days(10)).await;
+    insert("longterm", Duration::days(8)).await;
+    insert("archive", Duration::days(2)).await;
+    insert("fresh", Duration::minutes(5)).await;
+
+    let report = fixture.recorder.run_maintenance().await.unwrap();
+    assert_eq!((report.expired, report.longterm, report.archived), (1, 1, 1));
+
+    let segments = fixture.all_segments().await;
+    assert_eq!(segments.len(), 1);
+    let segments = index
+        .query(fixture.stream_id.as_str(), now - Duration::days(30), now)
+        .await
+        .unwrap();
+    let tiers: Vec<(StorageTier, CompressionAlgorithm)> =
+        segments.iter().map(|segment| (segment.tier, segment.compression)).collect();
+    assert_eq!(
+        tiers,
+        vec![
+            (StorageTier::Longterm, CompressionAlgorithm::Brotli),
+            (StorageTier::Archive, CompressionAlgorithm::Zstd),
+            (StorageTier::Realtime, CompressionAlgorithm::None),
+        ]
+    );
+    for segment in &segments {
+        assert!(segment.path.ends_with(&format!(".ts{}", compression::extension(segment.compression))));
+        assert_eq!(fixture.recorder.read_segment(segment).await.unwrap(), payload);
+    }
+    // 原实时分片已删除
+    assert!(storage
+        .read_from_pool("realtime", &format!("recordings/{}/archive.ts", fixture.stream_id))
+        .await
+        .is_err());
+
+    // 再次维护不会重复迁移
+    let report = fixture.recorder.run_maintenance().await.unwrap();
+    assert_eq!((report.expired, report.longterm, report.archived, report.failed), (0, 0, 0, 0));
+}
+
+#[tokio::test]
+async fn test_maintenance_continues_after_migration_failure() {
+    let fixture = fixture(|config| {
+        config.retention_days = 30;
+        config.compression.realtime = CompressionAlgorithm::None;
+    })
+    .await;
+    let storage = fixture.recorder.storage();
+    let index = fixture.recorder.index();
+    let payload = vec![0x47u8; 1024];
+
+    let now = Utc::now();
+    for (name, age) in [("missing", Duration::days(8)), ("broken", Duration::days(2)), ("ok", Duration::days(2))] {
+        let record = SegmentRecord {
+            stream_id: fixture.stream_id.to_string(),
+            start: now - age - Duration::minutes(1),
+            end: now - age,
+            path: format!("recordings/{}/{}.ts", fixture.stream_id, name),
+            pool: StorageTier::Realtime.pool_name().to_string(),
+            size: payload.len() as u64,
+            compression: CompressionAlgorithm::None,
+            tier: StorageTier::Realtime,
+            event: None,
+        };
+        storage.write_to_pool(&record.pool, &record.path, &payload).await.unwrap();
+        index.insert(&record).await.unwrap();
+    }
+    // 分片文件在维护前被外部删除
+    for name in ["missing", "broken"] {
+        storage
+            .delete_from_pool("realtime", &format!("recordings/{}/{}.ts", fixture.stream_id, name))
+            .await
+            .unwrap();
+    }
+
+    let report = fixture.recorder.run_maintenance().await.unwrap();
+    assert_eq!((report.longterm, report.archived, report.failed), (0, 1, 2));
+
+    let segments = index
+        .query(fixture.stream_id.as_str(), now - Duration::days(30), now)
+        .await
+        .unwrap();
+    let ok = segments.iter().find(|segment| segment.path.contains("/ok.ts")).unwrap();
+    assert_eq!(ok.tier, StorageTier::Archive);
+    assert_eq!(fixture.recorder.read_segment(ok).await.unwrap(), payload);
+    // 失败的分片保留在实时池的索引中
+    assert_eq!(
+        segments.iter().filter(|segment| segment.tier == StorageTier::Realtime).count(),
+        2
+    );
+}
@@ -1,24 +1,42 @@
 use anyhow::{anyhow, Result};
 use bytes::{BufMut, Bytes, BytesMut};
 use chrono::{DateTime, Duration, Utc};
-use flux_media_core::playback::{HlsGenerator, TsMuxer};
+use flux_media_core::codec::{
+    length_prefixed_to_annexb, parse_avc_decoder_config, parse_hevc_decoder_config, AacConfig,
+    ParameterSets,
+};
+use flux_media_core::playback::cmaf::{CMAF_AUDIO_TRACK_ID, CMAF_VIDEO_TRACK_ID};
+use flux_media_core::playback::{
+    CmafPackager, CmafResource, CmafTrackType, FlvVideoPacketType, FlvVideoTag, Fmp4Track,
+    HlsGenerator, PartStatus, TsMuxer,
+};
 use flux_media_core::timeshift::{Segment, SegmentFormat, SegmentMetadata, TimeShiftCore};
-use flux_media_core::types::StreamId;
+use flux_media_core::types::{AudioCodec, StreamId, VideoCodec};
 use flux_storage::{LocalSegmentStorage, SegmentStorage, StorageManager};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
-use tracing::{debug, error, info};
+use tokio::sync::{watch, Mutex, RwLock};
+use tracing::{debug, error, info, warn};
 
 use crate::telemetry::TelemetryClient;
 
-/// HLS 管理器：负责将 RTMP 流转换为 HLS
+/// 等待视频追上的音频帧上限（AAC 约 1 秒）；超出后视为视频停滞或纯音频流
+const MAX_PENDING_AUDIO: usize = 50;
+
+/// FLV 音频 SoundFormat
+const FLV_SOUND_G711A: u8 = 7;
+const FLV_SOUND_G711U: u8 = 8;
+const FLV_SOUND_AAC: u8 = 10;
+
+/// HLS 管理器：负责将 RTMP 流转换为 HLS（MPEG-TS）以及 CMAF（fMP4 HLS / DASH）
 pub struct HlsManager {
     generators: Arc<RwLock<HashMap<String, Arc<HlsStreamContext>>>>,
     segment_storage: Arc<dyn SegmentStorage>,
     timeshift: Option<Arc<TimeShiftCore>>,
     telemetry: TelemetryClient,
+    /// 是否直通 G.711 音频（私有 stream_type，仅部分播放器/NVR 支持）
+    g711_passthrough: bool,
 }
 
 /// HLS 流上下文
@@ -29,6 +47,44 @@ pub struct HlsStreamContext {
     pub current_segment: Arc<RwLock<SegmentBuffer>>,
     pub segment_duration: u32,
     pub last_keyframe_ts: Arc<RwLock<u32>>,
+    /// 最近一次序列头解析出的视频编码与参数集
+    pub video_config: Arc<RwLock<Option<VideoConfig>>>,
+    /// 当前音频编码配置
+    pub audio_config: Arc<RwLock<Option<AudioConfig>>>,
+    /// 等待按时间戳与视频交织的音频帧
+    pub pending_audio: Arc<RwLock<VecDeque<AudioFrame>>>,
+    /// CMAF 打包器（fMP4 HLS 与 DASH 共用分片）
+    pub cmaf: Arc<Mutex<CmafPackager>>,
+    /// 产生新的部分分片/分片时递增，唤醒 LL-HLS 阻塞请求
+    pub cmaf_updates: watch::Sender<u64>,
+}
+
+impl HlsStreamContext {
+    fn notify_cmaf_update(&self) {
+        self.cmaf_updates.send_modify(|version| *version = version.wrapping_add(1));
+    }
+}
+
+/// 视频解码配置
+#[derive(Debug, Clone)]
+pub struct VideoConfig {
+    pub codec: VideoCodec,
+    pub parameter_sets: ParameterSets,
+}
+
+/// 音频编码配置
+#[derive(Debug, Clone)]
+pub struct AudioConfig {
+    pub codec: AudioCodec,
+    /// AAC 时用于生成 ADTS 头
+    pub aac: Option<AacConfig>,
+}
+
+/// 待封装的音频帧（AAC 已带 ADTS 头）
+#[derive(Debug, Clone)]
+pub struct AudioFrame {
+    pub data: Vec<u8>,
+    pub timestamp: u32,
 }
 
 /// 分片缓冲区
@@ -72,9 +128,16 @@ impl HlsManager {
             segment_storage,
             timeshift,
             telemetry,
+            g711_passthrough: false,
         }
     }
 
+    /// 启用 G.711 直通（不转码写入 TS）
+    pub fn with_g711_passthrough(mut self, enabled: bool) -> Self {
+        self.g711_passthrough = enabled;
+        self
+    }
+
     /// 注册流（开始 HLS 转换）
     pub async fn register_stream(
         &self,
@@ -104,6 +167,11 @@ impl HlsManager {
             })),
             segment_duration,
             last_keyframe_ts: Arc::new(RwLock::new(0)),
+            video_config: Arc::new(RwLock::new(None)),
+            audio_config: Arc::new(RwLock::new(None)),
+            pending_audio: Arc::new(RwLock::new(VecDeque::new())),
+            cmaf: Arc::new(Mutex::new(CmafPackager::new(segment_duration, 5))),
+            cmaf_updates: watch::channel(0).0,
         };
 
         let mut generators = self.generators.write().await;
@@ -123,7 +191,7 @@ impl HlsManager {
         Ok(())
     }
 
-    /// 处理视频数据
+    /// 处理视频数据（FLV 视频 tag body，支持 AVC 与 HEVC）
     pub async fn process_video(
         &self,
         app_name: &str,
@@ -136,6 +204,55 @@ impl HlsManager {
         let generators = self.generators.read().await;
 
         if let Some(context) = generators.get(&key) {
+            let tag = match FlvVideoTag::parse(data) {
+                Ok(tag) => tag,
+                Err(e) => {
+                    debug!(target: "hls_manager", stream_key = %key, "Skip invalid video tag: {}", e);
+                    return Ok(());
+                }
+            };
+
+            match tag.packet_type {
+                FlvVideoPacketType::SequenceHeader => {
+                    return self.update_video_config(context, &key, &tag).await;
+                }
+                FlvVideoPacketType::CodedFrames => {}
+                _ => return Ok(()),
+            }
+
+            // 转换为 Annex B 访问单元，关键帧前插入参数集
+            let access_unit = {
+                let config = context.video_config.read().await;
+                let Some(config) = config.as_ref().filter(|config| config.codec == tag.codec) else {
+                    debug!(target: "hls_manager", stream_key = %key, "Video frame before sequence header, skipped");
+                    return Ok(());
+                };
+
+                let mut access_unit = if is_keyframe {
+                    config.parameter_sets.to_annexb()
+                } else {
+                    Vec::new()
+                };
+                access_unit.extend(length_prefixed_to_annexb(
+                    tag.payload,
+                    config.parameter_sets.nal_length_size,
+                )?);
+                access_unit
+            };
+
+            let updated = context.cmaf.lock().await.push_video(
+                timestamp,
+                tag.composition_time,
+                Bytes::copy_from_slice(tag.payload),
+                is_keyframe,
+            );
+            if updated {
+                context.notify_cmaf_update();
+            }
+
+            // 先输出时间戳早于本帧的音频，保证分片内音视频按时间交织
+            self.flush_pending_audio(context, timestamp).await?;
+
             // 如果是关键帧，检查是否需要切分片
             if is_keyframe {
                 let last_keyframe_ts = *context.last_keyframe_ts.read().await;
@@ -149,27 +266,23 @@ impl HlsManager {
                 if duration_ms >= context.segment_duration * 1000 {
                     self.finalize_segment(context).await?;
                     *context.last_keyframe_ts.write().await = timestamp;
+                    // 新分片以 PAT/PMT 开头
+                    context.ts_muxer.write().await.force_pat_pmt();
+                } else if last_keyframe_ts == 0 {
+                    *context.last_keyframe_ts.write().await = timestamp;
                 }
             }
 
             // 封装为 TS 包
             let mut ts_muxer = context.ts_muxer.write().await;
-            let pts = timestamp as u64 * 90; // 转换为 90kHz 时钟
-            let dts = pts;
+            let dts = timestamp as u64 * 90; // 转换为 90kHz 时钟
+            let pts = (timestamp as i64 + tag.composition_time as i64).max(0) as u64 * 90;
 
-            match ts_muxer.mux_video_pes(data, pts, dts, is_keyframe) {
+            match ts_muxer.mux_video_pes(&access_unit, pts, dts, is_keyframe) {
                 Ok(ts_packets) => {
+                    drop(ts_muxer);
                     let packet_count = ts_packets.len();
-                    
-                    // 添加到当前分片
-                    let mut segment = context.current_segment.write().await;
-                    if segment.data.is_empty() {
-                        segment.start_timestamp = timestamp;
-                    }
-                    for packet in ts_packets {
-                        segment.data.push(packet);
-                    }
-                    segment.duration = (timestamp - segment.start_timestamp) as f64 / 1000.0;
+                    Self::append_to_segment(context, ts_packets, timestamp).await;
 
                     debug!(target: "hls_manager", 
                         stream_key = %key, 
@@ -186,6 +299,119 @@ impl HlsManager {
         Ok(())
     }
 
+    /// 处理视频序列头：解析参数集并设置 TS 视频 stream_type
+    async fn update_video_config(
+        &self,
+        context: &HlsStreamContext,
+        key: &str,
+        tag: &FlvVideoTag<'_>,
+    ) -> Result<()> {
+        let parameter_sets = match tag.codec {
+            VideoCodec::H264 => parse_avc_decoder_config(tag.payload)?,
+            VideoCodec::H265 => parse_hevc_decoder_config(tag.payload)?,
+            other => {
+                warn!(target: "hls_manager", stream_key = %key, codec = %other, "Video codec not supported in HLS TS, skipped");
+                return Ok(());
+            }
+        };
+
+        context.ts_muxer.write().await.set_video_codec(tag.codec)?;
+        match Fmp4Track::video(CMAF_VIDEO_TRACK_ID, tag.codec, Bytes::copy_from_slice(tag.payload)) {
+            Ok(track) => context.cmaf.lock().await.set_video_track(track),
+            Err(e) => warn!(target: "hls_manager", stream_key = %key, "CMAF video track not created: {}", e),
+        }
+        *context.video_config.write().await = Some(VideoConfig {
+            codec: tag.codec,
+            parameter_sets,
+        });
+
+        info!(target: "hls_manager", stream_key = %key, codec = %tag.codec, "Video sequence header received");
+        Ok(())
+    }
+
+    /// 将 TS 包追加到当前分片
+    async fn append_to_segment(context: &HlsStreamContext, ts_packets: Vec<Bytes>, timestamp: u32) {
+        let mut segment = context.current_segment.write().await;
+        if segment.data.is_empty() {
+            segment.start_timestamp = timestamp;
+            segment.duration = 0.0;
+        }
+        segment.data.extend(ts_packets);
+        let duration = timestamp.saturating_sub(segment.start_timestamp) as f64 / 1000.0;
+        segment.duration = segment.duration.max(duration);
+    }
+
+    /// 输出时间戳早于 `before` 的待交织音频帧
+    async fn flush_pending_audio(&self, context: &HlsStreamContext, before: u32) -> Result<()> {
+        let frames: Vec<AudioFrame> = {
+            let mut pending = context.pending_audio.write().await;
+            let count = pending
+                .iter()
+                .take_while(|frame| frame.timestamp < before)
+                .count();
+            pending.drain(..count).collect()
+        };
+
+        for frame in &frames {
+            self.mux_audio_frame(context, frame).await?;
+        }
+        Ok(())
+    }
+
+    /// 封装单个音频帧到当前分片
+    async fn mux_audio_frame(&self, context: &HlsStreamContext, frame: &AudioFrame) -> Result<()> {
+        let pts = frame.timestamp as u64 * 90;
+        let ts_packets = context.ts_muxer.write().await.mux_audio_pes(&frame.data, pts)?;
+        Self::append_to_segment(context, ts_packets, frame.timestamp).await;
+        Ok(())
+    }
+
+    /// 纯音频流按音频时间戳切分片
+    async fn cut_audio_only_segment(&self, context: &HlsStreamContext, timestamp: u32) -> Result<()> {
+        let start_timestamp = {
+            let segment = context.current_segment.read().await;
+            (!segment.data.is_empty()).then_some(segment.start_timestamp)
+        };
+
+        if let Some(start_timestamp) = start_timestamp {
+            if timestamp.saturating_sub(start_timestamp) >= context.segment_duration * 1000 {
+                self.finalize_segment(context).await?;
+                context.ts_muxer.write().await.force_pat_pmt();
+            }
+        }
+        Ok(())
+    }
+
+    /// 处理 AAC 序列头：解析 AudioSpecificConfig 并在 PMT 中声明 AAC
+    async fn update_aac_config(&self, context: &HlsStreamContext, key: &str, asc: &[u8]) -> Result<()> {
+        let aac = match AacConfig::parse(asc) {
+            Ok(aac) => aac,
+            Err(e) => {
+                warn!(target: "hls_manager", stream_key = %key, "AAC config not supported in HLS TS, audio skipped: {}", e);
+                *context.audio_config.write().await = None;
+                return Ok(());
+            }
+        };
+
+        context.ts_muxer.write().await.set_audio_codec(AudioCodec::AAC)?;
+        match Fmp4Track::aac(CMAF_AUDIO_TRACK_ID, Bytes::copy_from_slice(asc)) {
+            Ok(track) => context.cmaf.lock().await.set_audio_track(track),
+            Err(e) => warn!(target: "hls_manager", stream_key = %key, "CMAF audio track not created: {}", e),
+        }
+        *context.audio_config.write().await = Some(AudioConfig {
+            codec: AudioCodec::AAC,
+            aac: Some(aac),
+        });
+
+        info!(target: "hls_manager",
+            stream_key = %key,
+            sample_rate = aac.sample_rate(),
+            channels = aac.channel_configuration,
+            "AAC sequence header received"
+        );
+        Ok(())
+    }
+
     /// 完成当前分片
     async fn finalize_segment(&self, context: &HlsStreamContext) -> Result<()> {
         let mut segment = context.current_segment.write().await;
@@ -367,7 +593,7 @@ impl HlsManager {
         }
     }
 
-    /// 处理音频数据
+    /// 处理音频数据（FLV 音频 tag body：AAC，或启用直通时的 G.711）
     pub async fn process_audio(
         &self,
         app_name: &str,
@@ -378,20 +604,153 @@ impl HlsManager {
         let key = format!("{}/{}", app_name, stream_key);
         let generators = self.generators.read().await;
 
-        if let Some(_context) = generators.get(&key) {
-            // TODO: 实现音频 TS 封装
-            // 目前只处理视频，音频暂时忽略
-            debug!(target: "hls_manager", 
-                stream_key = %key,
-                size = data.len(),
-                timestamp = timestamp,
-                "Audio data received (not yet processed)"
-            );
+        let Some(context) = generators.get(&key) else {
+            return Ok(());
+        };
+        let Some(&flags) = data.first() else {
+            return Ok(());
+        };
+
+        let frame = match flags >> 4 {
+            FLV_SOUND_AAC => {
+                let Some(&packet_type) = data.get(1) else {
+                    return Ok(());
+                };
+                if packet_type == 0 {
+                    return self.update_aac_config(context, &key, &data[2..]).await;
+                }
+
+                let config = context.audio_config.read().await;
+                let Some(aac) = config.as_ref().and_then(|config| config.aac) else {
+                    debug!(target: "hls_manager", stream_key = %key, "AAC frame before sequence header, skipped");
+                    return Ok(());
+                };
+                let payload = &data[2..];
+                let updated = context
+                    .cmaf
+                    .lock()
+                    .await
+                    .push_audio(timestamp, Bytes::copy_from_slice(payload));
+                if updated {
+                    context.notify_cmaf_update();
+                }
+
+                let mut frame = Vec::with_capacity(payload.len() + 7);
+                frame.extend_from_slice(&aac.adts_header(payload.len()));
+                frame.extend_from_slice(payload);
+                frame
+            }
+            format @ (FLV_SOUND_G711A | FLV_SOUND_G711U) if self.g711_passthrough => {
+                let codec = if format == FLV_SOUND_G711A {
+                    AudioCodec::G711A
+                } else {
+                    AudioCodec::G711U
+                };
+                let mut config = context.audio_config.write().await;
+                if config.as_ref().map(|config| config.codec) != Some(codec) {
+                    context.ts_muxer.write().await.set_audio_codec(codec)?;
+                    *config = Some(AudioConfig { codec, aac: None });
+                    info!(target: "hls_manager", stream_key = %key, codec = ?codec, "G.711 passthrough enabled");
+                }
+                data[1..].to_vec()
+            }
+            format => {
+                debug!(target: "hls_manager", stream_key = %key, sound_format = format, "Audio format not supported in HLS TS, skipped");
+                return Ok(());
+            }
+        };
+
+        // 有视频时缓存音频，等视频时间戳追上后交织输出；否则超过上限按纯音频流处理
+        let has_video = context.video_config.read().await.is_some();
+        let frames: Vec<AudioFrame> = {
+            let mut pending = context.pending_audio.write().await;
+            pending.push_back(AudioFrame { data: frame, timestamp });
+
+            let audio_only = !context.ts_muxer.read().await.video_enabled();
+            if has_video {
+                if pending.len() <= MAX_PENDING_AUDIO {
+                    return Ok(());
+                }
+                let overflow = pending.len() - MAX_PENDING_AUDIO;
+                pending.drain(..overflow).collect()
+            } else if audio_only || pending.len() > MAX_PENDING_AUDIO {
+                if !audio_only {
+                    context.ts_muxer.write().await.set_video_enabled(false);
+                    info!(target: "hls_manager", stream_key = %key, "No video received, muxing audio-only HLS");
+                }
+                pending.drain(..).collect()
+            } else {
+                return Ok(());
+            }
+        };
+
+        for frame in &frames {
+            if !has_video {
+                self.cut_audio_only_segment(context, frame.timestamp).await?;
+            }
+            self.mux_audio_frame(context, frame).await?;
         }
 
         Ok(())
     }
 
+    /// 获取 CMAF 资源（manifest.mpd、master.m3u8、{track}.m3u8、init/media segment）
+    pub async fn get_cmaf_resource(
+        &self,
+        app_name: &str,
+        stream_key: &str,
+        name: &str,
+    ) -> Result<CmafResource> {
+        let key = format!("{}/{}", app_name, stream_key);
+        let generators = self.generators.read().await;
+        let context = generators
+            .get(&key)
+            .ok_or_else(|| anyhow!("Stream not found: {}", key))?;
+
+        let cmaf = context.cmaf.lock().await;
+        cmaf.resource(name)
+            .ok_or_else(|| anyhow!("CMAF resource not found: {}", name))
+    }
+
+    /// 等待 LL-HLS 分片（`part` 为 None）或部分分片生成，最多等待 3 个分片时长
+    ///
+    /// 用于 `_HLS_msn`/`_HLS_part` 阻塞式播放列表刷新与预加载提示请求；超时返回 `Pending`
+    pub async fn wait_cmaf_part(
+        &self,
+        app_name: &str,
+        stream_key: &str,
+        track: CmafTrackType,
+        sequence: u64,
+        part: Option<u32>,
+    ) -> Result<PartStatus> {
+        let key = format!("{}/{}", app_name, stream_key);
+        let context = {
+            let generators = self.generators.read().await;
+            generators
+                .get(&key)
+                .cloned()
+                .ok_or_else(|| anyhow!("Stream not found: {}", key))?
+        };
+
+        // 先订阅再检查，避免错过检查与等待之间的更新
+        let mut updates = context.cmaf_updates.subscribe();
+        let deadline = tokio::time::Instant::now()
+            + std::time::Duration::from_secs(context.segment_duration.max(1) as u64 * 3);
+
+        loop {
+            let status = context.cmaf.lock().await.part_status(track, sequence, part);
+            if status != PartStatus::Pending {
+                return Ok(status);
+            }
+
+            match tokio::time::timeout_at(deadline, updates.changed()).await {
+                Ok(Ok(())) => continue,
+                // 超时或流已注销
+                _ => return Ok(PartStatus::Pending),
+            }
+        }
+    }
+
     /// 检查流是否存在
     pub async fn stream_exists(&self, app_name: &str, stream_key: &str) -> bool {
         let key = format!("{}/{}", app_name, stream_key);
@@ -503,4 +862,297 @@ mod tests {
         // 验证流仍然存在
         assert!(manager.stream_exists("live", "test").await);
     }
+
+    #[tokio::test]
+    async fn test_hls_manager_enhanced_hevc() {
+        use tempfile::tempdir;
+        let temp_dir = tempdir().unwrap();
+        let manager = HlsManager::new(temp_dir.path().to_path_buf());
+        manager
+            .register_stream("live".to_string(), "hevc".to_string(), 6)
+            .await
+            .unwrap();
+
+        // Enhanced RTMP 序列头（HEVCDecoderConfigurationRecord，含 VPS/SPS/PPS）
+        let mut header = vec![0x90];
+        header.extend_from_slice(b"hvc1");
+        let mut config = vec![0u8; 22];
+        config[0] = 0x01;
+        config[21] = 0x0F;
+        config.push(3);
+        for (nal_type, nalu) in [(32u8, [0x40, 0x01]), (33, [0x42, 0x01]), (34, [0x44, 0x01])] {
+            config.push(0x80 | nal_type);
+            config.extend_from_slice(&[0x00, 0x01, 0x00, 0x02]);
+            config.extend_from_slice(&nalu);
+        }
+        header.extend_from_slice(&config);
+        manager
+            .process_video("live", "hevc", &header, 0, true)
+            .await
+            .unwrap();
+
+        // IDR 帧（CodedFramesX）
+        let mut frame = vec![0x93];
+        frame.extend_from_slice(b"hvc1");
+        frame.extend_from_slice(&[0, 0, 0, 3, 0x26, 0x01, 0xAF]);
+        manager
+            .process_video("live", "hevc", &frame, 0, true)
+            .await
+            .unwrap();
+
+        let generators = manager.generators.read().await;
+        let context = generators.get("live/hevc").unwrap();
+        let config = context.video_config.read().await;
+        assert_eq!(config.as_ref().unwrap().codec, VideoCodec::H265);
+
+        let segment = context.current_segment.read().await;
+        // PAT + PMT + 视频包；PMT 中视频 stream_type 为 0x24
+        assert!(segment.data.len() >= 3);
+        assert_eq!(segment.data[1][17], 0x24);
+        // 首个视频包的 PES 负载以 VPS 开头
+        let video = &segment.data[2];
+        let af_len = video[4] as usize + 1;
+        let pes = &video[4 + af_len..];
+        assert_eq!(&pes[..4], &[0x00, 0x00, 0x01, 0xE0]);
+        let pes_header_len = 9 + pes[8] as usize;
+        assert_eq!(&pes[pes_header_len..pes_header_len + 6], &[0, 0, 0, 1, 0x40, 0x01]);
+    }
+
+    /// 解析分片内 PES 起始包：返回 (PID, PTS)
+    fn pes_starts(segment: &SegmentBuffer) -> Vec<(u16, u64)> {
+        segment
+            .data
+            .iter()
+            .filter(|packet| packet[1] & 0x40 != 0)
+            .filter_map(|packet| {
+                let pid = ((packet[1] as u16 & 0x1F) << 8) | packet[2] as u16;
+                if pid != 0x100 && pid != 0x101 {
+                    return None;
+                }
+                let offset = if packet[3] & 0x20 != 0 { 5 + packet[4] as usize } else { 4 };
+                let pes = &packet[offset..];
+                let pts = (((pes[9] as u64 >> 1) & 0x07) << 30)
+                    | ((((pes[10] as u64) << 8 | pes[11] as u64) >> 1) << 15)
+                    | (((pes[12] as u64) << 8 | pes[13] as u64) >> 1);
+                Some((pid, pts))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_hls_manager_aac_interleave() {
+        use tempfile::tempdir;
+        let temp_dir = tempdir().unwrap();
+        let manager = HlsManager::new(temp_dir.path().to_path_buf());
+        manager
+            .register_stream("live".to_string(), "av".to_string(), 6)
+            .await
+            .unwrap();
+
+        // AVC 序列头 + AAC 序列头（AAC-LC 44.1kHz 双声道）
+        let avc_header = [
+            0x17, 0x00, 0, 0, 0, 0x01, 0x64, 0x00, 0x1F, 0xFF, 0xE1, 0x00, 0x03, 0x67, 0x64,
+            0x1F, 0x01, 0x00, 0x02, 0x68, 0xEE,
+        ];
+        manager.process_video("live", "av", &avc_header, 0, true).await.unwrap();
+        manager.process_audio("live", "av", &[0xAF, 0x00, 0x12, 0x10], 0).await.unwrap();
+
+        // 音频先于视频到达，需按时间戳交织
+        manager.process_audio("live", "av", &[0xAF, 0x01, 0x21, 0x00], 0).await.unwrap();
+        manager.process_audio("live", "av", &[0xAF, 0x01, 0x21, 0x00], 23).await.unwrap();
+        manager.process_audio("live", "av", &[0xAF, 0x01, 0x21, 0x00], 46).await.unwrap();
+        let idr = [0x17, 0x01, 0, 0, 0, 0, 0, 0, 2, 0x65, 0x88];
+        manager.process_video("live", "av", &idr, 0, true).await.unwrap();
+        let p_frame = [0x27, 0x01, 0, 0, 0, 0, 0, 0, 2, 0x41, 0x9A];
+        manager.process_video("live", "av", &p_frame, 40, false).await.unwrap();
+
+        let generators = manager.generators.read().await;
+        let context = generators.get("live/av").unwrap();
+        let segment = context.current_segment.read().await;
+
+        // PMT 声明 H264 + AAC
+        assert_eq!(segment.data[1][17], 0x1B);
+        assert_eq!(segment.data[1][22], 0x0F);
+
+        // 视频 0 → 音频 0 → 视频 40（音频 23 早于视频 40）；音频 46 仍在缓存中
+        let starts = pes_starts(&segment);
+        assert_eq!(
+            starts,
+            vec![(0x100, 0), (0x101, 0), (0x101, 23 * 90), (0x100, 40 * 90)]
+        );
+        assert_eq!(context.pending_audio.read().await.len(), 1);
+
+        // 音频 PES 负载为 ADTS 帧
+        let audio = segment
+            .data
+            .iter()
+            .find(|packet| packet[2] == 0x01 && packet[1] & 0x40 != 0)
+            .unwrap();
+        // 单包 PES 末尾以自适应字段填充
+        let pes = &audio[5 + audio[4] as usize..];
+        assert_eq!(&pes[..4], &[0x00, 0x00, 0x01, 0xC0]);
+        assert_eq!(&pes[14..16], &[0xFF, 0xF1]);
+    }
+
+    #[tokio::test]
+    async fn test_hls_manager_audio_only() {
+        use tempfile::tempdir;
+        let temp_dir = tempdir().unwrap();
+        let manager = HlsManager::new(temp_dir.path().to_path_buf());
+        manager
+            .register_stream("live".to_string(), "radio".to_string(), 1)
+            .await
+            .unwrap();
+
+        manager.process_audio("live", "radio", &[0xAF, 0x00, 0x12, 0x10], 0).await.unwrap();
+        for i in 0..=MAX_PENDING_AUDIO as u32 + 40 {
+            manager
+                .process_audio("live", "radio", &[0xAF, 0x01, 0x21, 0x00], i * 23)
+                .await
+                .unwrap();
+        }
+
+        let generators = manager.generators.read().await;
+        let context = generators.get("live/radio").unwrap();
+        assert!(!context.ts_muxer.read().await.video_enabled());
+        assert!(context.pending_audio.read().await.is_empty());
+
+        // 超过 1 秒后按音频时间戳切出分片
+        let playlist = manager.get_playlist("live", "radio").await.unwrap();
+        assert!(playlist.contains("#EXTINF"));
+        let segment = context.current_segment.read().await;
+        assert_eq!(segment.data[1][17], 0x0F);
+    }
+
+    #[tokio::test]
+    async fn test_hls_manager_g711_passthrough() {
+        use tempfile::tempdir;
+        let temp_dir = tempdir().unwrap();
+
+        // 默认不直通 G.711
+        let manager = HlsManager::new(temp_dir.path().to_path_buf());
+        manager
+            .register_stream("live".to_string(), "cam".to_string(), 6)
+            .await
+            .unwrap();
+        manager.process_audio("live", "cam", &[0x72, 0xD5, 0xD5], 0).await.unwrap();
+        {
+            let generators = manager.generators.read().await;
+            let context = generators.get("live/cam").unwrap();
+            assert!(context.audio_config.read().await.is_none());
+        }
+
+        let manager = HlsManager::new(temp_dir.path().to_path_buf()).with_g711_passthrough(true);
+        manager
+            .register_stream("live".to_string(), "cam".to_string(), 6)
+            .await
+            .unwrap();
+        manager.process_audio("live", "cam", &[0x72, 0xD5, 0xD5], 0).await.unwrap();
+
+        let generators = manager.generators.read().await;
+        let context = generators.get("live/cam").unwrap();
+        let config = context.audio_config.read().await;
+        assert_eq!(config.as_ref().unwrap().codec, AudioCodec::G711A);
+        assert_eq!(context.ts_muxer.read().await.audio_stream_type(), Some(0x90));
+        assert_eq!(context.pending_audio.read().await[0].data, vec![0xD5, 0xD5]);
+    }
+
+    #[tokio::test]
+    async fn test_hls_manager_cmaf_output() {
+        use tempfile::tempdir;
+        let temp_dir = tempdir().unwrap();
+        let manager = HlsManager::new(temp_dir.path().to_path_buf());
+        manager
+            .register_stream("live".to_string(), "cmaf".to_string(), 1)
+            .await
+            .unwrap();
+
+        let avc_header = [
+            0x17, 0x00, 0, 0, 0, 0x01, 0x64, 0x00, 0x1F, 0xFF, 0xE1, 0x00, 0x0A, 0x67, 0x64,
+            0x00, 0x1F, 0xAC, 0xD9, 0x40, 0x50, 0x05, 0xB9, 0x01, 0x00, 0x02, 0x68, 0xEE,
+        ];
+        manager.process_video("live", "cmaf", &avc_header, 0, true).await.unwrap();
+        manager.process_audio("live", "cmaf", &[0xAF, 0x00, 0x12, 0x10], 0).await.unwrap();
+
+        // 尚无分片
+        assert!(manager.get_cmaf_resource("live", "cmaf", "manifest.mpd").await.is_err());
+
+        for i in 0..60u32 {
+            let ts = i * 40;
+            let frame = if i % 25 == 0 {
+                [0x17, 0x01, 0, 0, 0, 0, 0, 0, 2, 0x65, 0x88]
+            } else {
+                [0x27, 0x01, 0, 0, 0, 0, 0, 0, 2, 0x41, 0x9A]
+            };
+            manager.process_video("live", "cmaf", &frame, ts, i % 25 == 0).await.unwrap();
+            manager.process_audio("live", "cmaf", &[0xAF, 0x01, 0x21, 0x00], ts).await.unwrap();
+        }
+
+        let mpd = manager.get_cmaf_resource("live", "cmaf", "manifest.mpd").await.unwrap();
+        assert_eq!(mpd.content_type, "application/dash+xml");
+        let mpd = String::from_utf8(mpd.data.to_vec()).unwrap();
+        assert!(mpd.contains("<S t=\"0\" d=\"90000\" r=\"1\"/>"));
+        assert!(mpd.contains("width=\"1280\" height=\"720\""));
+
+        let playlist = manager.get_cmaf_resource("live", "cmaf", "video.m3u8").await.unwrap();
+        assert!(String::from_utf8(playlist.data.to_vec()).unwrap().contains("video_1.m4s"));
+
+        // fMP4 样本为原始长度前缀 NALU（不含 Annex B 起始码）
+        let segment = manager.get_cmaf_resource("live", "cmaf", "video_0.m4s").await.unwrap();
+        let mdat = segment.data.windows(4).position(|w| w == b"mdat").unwrap();
+        assert_eq!(&segment.data[mdat + 4..mdat + 10], &[0, 0, 0, 2, 0x65, 0x88]);
+    }
+
+    #[tokio::test]
+    async fn test_hls_manager_blocking_part() {
+        use tempfile::tempdir;
+        let temp_dir = tempdir().unwrap();
+        let manager = Arc::new(HlsManager::new(temp_dir.path().to_path_buf()));
+        manager
+            .register_stream("live".to_string(), "ll".to_string(), 1)
+            .await
+            .unwrap();
+
+        let avc_header = [
+            0x17, 0x00, 0, 0, 0, 0x01, 0x64, 0x00, 0x1F, 0xFF, 0xE1, 0x00, 0x0A, 0x67, 0x64,
+            0x00, 0x1F, 0xAC, 0xD9, 0x40, 0x50, 0x05, 0xB9, 0x01, 0x00, 0x02, 0x68, 0xEE,
+        ];
+        manager.process_video("live", "ll", &avc_header, 0, true).await.unwrap();
+
+        // 超前过多直接拒绝
+        let status = manager
+            .wait_cmaf_part("live", "ll", CmafTrackType::Video, 5, None)
+            .await
+            .unwrap();
+        assert_eq!(status, PartStatus::TooFarAhead);
+
+        // 阻塞等待第一个部分分片
+        let waiter = {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                manager
+                    .wait_cmaf_part("live", "ll", CmafTrackType::Video, 0, Some(0))
+                    .await
+                    .unwrap()
+            })
+        };
+
+        for i in 0..15u32 {
+            let frame = if i == 0 {
+                [0x17, 0x01, 0, 0, 0, 0, 0, 0, 2, 0x65, 0x88]
+            } else {
+                [0x27, 0x01, 0, 0, 0, 0, 0, 0, 2, 0x41, 0x9A]
+            };
+            manager.process_video("live", "ll", &frame, i * 40, i == 0).await.unwrap();
+        }
+
+        assert_eq!(waiter.await.unwrap(), PartStatus::Available);
+        let part = manager.get_cmaf_resource("live", "ll", "video_0.0.m4s").await.unwrap();
+        assert_eq!(&part.data[4..8], b"moof");
+
+        let playlist = manager.get_cmaf_resource("live", "ll", "video.m3u8").await.unwrap();
+        let playlist = String::from_utf8(playlist.data.to_vec()).unwrap();
+        assert!(playlist.contains("#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES"));
+        assert!(playlist.contains("#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"video_0.1.m4s\""));
+    }
 }
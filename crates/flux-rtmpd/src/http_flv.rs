@@ -12,7 +12,7 @@ use tokio::sync::broadcast;
 use tokio_stream::{wrappers::BroadcastStream, StreamExt};
 use tracing::{debug, error, info};
 
-use crate::stream_manager::StreamManager;
+use crate::stream_manager::{MediaPacket, StreamManager};
 
 /// HTTP-FLV 服务器
 pub struct HttpFlvServer {
@@ -52,8 +52,14 @@ impl HttpFlvServer {
             }
         };
 
+        // 中途加入的播放者需要先收到序列头（AVC/HEVC/AAC 配置）
+        let sequence_headers = self
+            .stream_manager
+            .get_sequence_headers(&app_name, &stream_key)
+            .await;
+
         // 创建 FLV 流
-        let stream = create_flv_stream(video_rx, audio_rx);
+        let stream = create_flv_stream(video_rx, audio_rx, sequence_headers);
 
         // 返回 HTTP 响应
         Ok(Response::builder()
@@ -70,6 +76,7 @@ impl HttpFlvServer {
 fn create_flv_stream(
     video_rx: broadcast::Receiver<crate::stream_manager::MediaPacket>,
     audio_rx: broadcast::Receiver<crate::stream_manager::MediaPacket>,
+    sequence_headers: (Option<MediaPacket>, Option<MediaPacket>),
 ) -> impl futures::Stream<Item = Result<Bytes, std::io::Error>> {
     async_stream::stream! {
         let mut flv_muxer = FlvMuxer::new();
@@ -78,6 +85,24 @@ fn create_flv_stream(
         let header = flv_muxer.generate_header();
         yield Ok(header);
 
+        // 发送缓存的序列头（时间戳置 0，播放器从首个关键帧开始解码）
+        let (video_header, audio_header) = sequence_headers;
+        let header_tags = video_header
+            .map(|packet| (FlvTagType::Video, packet))
+            .into_iter()
+            .chain(audio_header.map(|packet| (FlvTagType::Audio, packet)));
+        for (tag_type, packet) in header_tags {
+            let tag = FlvTag {
+                tag_type,
+                timestamp: 0,
+                data: packet.data,
+            };
+            match flv_muxer.mux_tag(&tag) {
+                Ok(flv_data) => yield Ok(flv_data),
+                Err(e) => error!(target: "http_flv", "Failed to mux sequence header: {}", e),
+            }
+        }
+
         // 创建视频和音频流
         let mut video_stream = BroadcastStream::new(video_rx);
         let mut audio_stream = BroadcastStream::new(audio_rx);
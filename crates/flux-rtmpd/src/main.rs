@@ -3,16 +3,19 @@ mod hls_manager;
 mod http_flv;
 mod media_processor;
 mod middleware;
+mod relay;
+mod rtmp_client;
 mod rtmp_server;
 mod rtmp_stream;
 mod stream_manager;
 mod telemetry;
+mod unified_bridge;
 
 use axum::{
     extract::{Path, State},
     http::StatusCode,
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
 use auth::login;
@@ -20,7 +23,7 @@ use tracing::{error, info};
 use clap::Parser;
 use flux_media_core::{
     playback::{FlvMuxer, FlvTag, HlsGenerator},
-    snapshot::{SnapshotMode, SnapshotOrchestrator, SnapshotRequest},
+    snapshot::{SnapshotMode, SnapshotOrchestrator, SnapshotQuery, SoftwareDecoder},
     storage::{filesystem::FileSystemStorage, StorageConfig},
     types::StreamId,
 };
@@ -50,6 +53,40 @@ struct Args {
 
     #[arg(long, default_value_t = 1000)]
     telemetry_timeout_ms: u64,
+
+    /// HLS 中直通 G.711 音频（私有 stream_type，标准 HLS 播放器不支持）
+    #[arg(long)]
+    hls_g711_passthrough: bool,
+
+    /// WebRTC（WHEP）候选地址，可重复指定；未指定时使用本机出口地址
+    #[arg(long = "webrtc-public-ip")]
+    webrtc_public_ips: Vec<String>,
+
+    /// WebRTC 会话 UDP 端口范围（均为 0 时由系统分配）
+    #[arg(long, default_value_t = 0)]
+    webrtc_udp_port_min: u16,
+
+    #[arg(long, default_value_t = 0)]
+    webrtc_udp_port_max: u16,
+
+    /// RTSP 服务端监听地址（rtsp://host:port/<protocol>/<app>/<stream>）
+    #[arg(long, default_value = "0.0.0.0:8554")]
+    rtsp_bind: String,
+
+    /// RTSP 服务端 Digest 认证用户名（与密码同时指定时启用）
+    #[arg(long)]
+    rtsp_username: Option<String>,
+
+    #[arg(long)]
+    rtsp_password: Option<String>,
+
+    /// SRT 监听地址（streamid `#!::r=<protocol>/<app>/<stream>,m=request` 播放，`m=publish` 推流）；未指定时不启用
+    #[arg(long)]
+    srt_bind: Option<String>,
+
+    /// SRT 加密口令（10~79 个字符）
+    #[arg(long)]
+    srt_passphrase: Option<String>,
 }
 
 #[derive(Clone)]
@@ -63,6 +100,7 @@ struct AppState {
     stream_manager: Arc<stream_manager::StreamManager>,
     unified_stream_manager: Arc<flux_stream::StreamManager>,
     http_flv_server: Arc<http_flv::HttpFlvServer>,
+    relay_manager: Arc<relay::RelayManager>,
     // 安全组件
     jwt_auth: Arc<flux_middleware::JwtAuth>,
     rbac_manager: Arc<flux_middleware::RbacManager>,
@@ -97,6 +135,7 @@ async fn list_streams(State(state): State<AppState>) -> impl IntoResponse {
                     "start_time": info.start_time.to_rfc3339(),
                     "video_frames": info.video_frames,
                     "audio_frames": info.audio_frames,
+                    "video_codec": info.video_codec.map(|codec| codec.as_str()),
                 })
             })
             .collect()
@@ -110,14 +149,10 @@ async fn list_streams(State(state): State<AppState>) -> impl IntoResponse {
 async fn snapshot(
     State(state): State<AppState>,
     Path(stream_id): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<SnapshotQuery>,
 ) -> std::result::Result<Response, StatusCode> {
     let media_stream_id = StreamId::from_string(stream_id);
-    let req = SnapshotRequest {
-        stream_id: media_stream_id,
-        mode: SnapshotMode::Auto,
-        width: None,
-        height: None,
-    };
+    let req = query.into_request(media_stream_id, SnapshotMode::Auto);
 
     let snapshot = state
         .orchestrator
@@ -125,10 +160,11 @@ async fn snapshot(
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
+    let content_type = snapshot.content_type();
     let mut resp: Response = snapshot.data.into_response();
     resp.headers_mut().insert(
         axum::http::header::CONTENT_TYPE,
-        axum::http::HeaderValue::from_static("application/octet-stream"),
+        axum::http::HeaderValue::from_static(content_type),
     );
     Ok(resp)
 }
@@ -155,6 +191,12 @@ async fn hls_playlist(
     let app_name = parts[1];
     let stream_key = parts[2];
 
+    // 未知流按回源规则拉取
+    if !state.hls_manager.stream_exists(app_name, stream_key).await {
+        state.relay_manager.ensure_pull(app_name, stream_key).await;
+    }
+    state.relay_manager.touch(app_name, stream_key).await;
+
     // 计算开始时间
     let start_time = query.start_time.map(|offset| {
         Utc::now() + Duration::seconds(offset) // offset 为负数表示过去
@@ -207,6 +249,65 @@ async fn hls_segment(
     Ok(resp)
 }
 
+/// LL-HLS 阻塞式播放列表刷新参数
+#[derive(serde::Deserialize)]
+struct LlHlsQuery {
+    #[serde(rename = "_HLS_msn")]
+    msn: Option<u64>,
+    #[serde(rename = "_HLS_part")]
+    part: Option<u32>,
+}
+
+/// CMAF 输出：DASH（manifest.mpd）与 fMP4 HLS（master.m3u8）共用 init/media segment，
+/// 媒体播放列表支持 LL-HLS 阻塞式刷新，部分分片支持预加载提示阻塞请求
+async fn cmaf_file(
+    State(state): State<AppState>,
+    Path((app_name, stream_key, file)): Path<(String, String, String)>,
+    axum::extract::Query(query): axum::extract::Query<LlHlsQuery>,
+) -> std::result::Result<Response, StatusCode> {
+    use flux_media_core::playback::{CmafPackager, CmafTrackType, PartStatus};
+
+    // 未知流按回源规则拉取
+    if !state.hls_manager.stream_exists(&app_name, &stream_key).await {
+        state.relay_manager.ensure_pull(&app_name, &stream_key).await;
+    }
+    state.relay_manager.touch(&app_name, &stream_key).await;
+
+    // 阻塞请求：_HLS_msn[/_HLS_part] 或尚未生成的部分分片
+    let blocking = match file.strip_suffix(".m3u8").and_then(CmafTrackType::parse) {
+        Some(track) => match (query.msn, query.part) {
+            (Some(msn), part) => Some((track, msn, part)),
+            (None, Some(_)) => return Err(StatusCode::BAD_REQUEST),
+            (None, None) => None,
+        },
+        None => CmafPackager::parse_part_name(&file).map(|(track, msn, part)| (track, msn, Some(part))),
+    };
+
+    if let Some((track, msn, part)) = blocking {
+        let status = state.hls_manager
+            .wait_cmaf_part(&app_name, &stream_key, track, msn, part)
+            .await
+            .map_err(|_| StatusCode::NOT_FOUND)?;
+        match status {
+            PartStatus::Available => {}
+            PartStatus::Pending => return Err(StatusCode::SERVICE_UNAVAILABLE),
+            PartStatus::TooFarAhead => return Err(StatusCode::BAD_REQUEST),
+        }
+    }
+
+    let resource = state.hls_manager
+        .get_cmaf_resource(&app_name, &stream_key, &file)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    let mut resp: Response = resource.data.into_response();
+    resp.headers_mut().insert(
+        axum::http::header::CONTENT_TYPE,
+        axum::http::HeaderValue::from_static(resource.content_type),
+    );
+    Ok(resp)
+}
+
 async fn http_flv_route(
     State(state): State<AppState>,
     Path((app_name, stream_key)): Path<(String, String)>,
@@ -234,11 +335,92 @@ async fn http_flv_route(
         tracing::warn!(target: "http_flv", "Failed to request output: {}", e);
     }
     
-    // 2. 调用 HttpFlvServer 处理
+    // 2. 未知流按回源规则拉取
     let stream_key_clean = stream_key.trim_end_matches(".flv");
+    state.relay_manager.ensure_pull(&app_name, stream_key_clean).await;
+
+    // 3. 调用 HttpFlvServer 处理
     state.http_flv_server.handle_stream(app_name, stream_key_clean.to_string()).await
 }
 
+async fn list_relays(State(state): State<AppState>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "push_rules": state.relay_manager.list_push_rules().await,
+        "pull_rules": state.relay_manager.list_pull_rules().await,
+        "sessions": state.relay_manager.list_sessions().await,
+    }))
+}
+
+async fn add_push_relay(
+    State(state): State<AppState>,
+    Json(rule): Json<relay::PushRule>,
+) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
+    let rule = state
+        .relay_manager
+        .add_push_rule(rule)
+        .await
+        .map_err(|e| {
+            tracing::warn!(target: "rtmpd", "Invalid push relay rule: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok(Json(serde_json::json!({ "status": "created", "rule": rule })))
+}
+
+async fn add_pull_relay(
+    State(state): State<AppState>,
+    Json(rule): Json<relay::PullRule>,
+) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
+    let rule = state
+        .relay_manager
+        .add_pull_rule(rule)
+        .await
+        .map_err(|e| {
+            tracing::warn!(target: "rtmpd", "Invalid pull relay rule: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok(Json(serde_json::json!({ "status": "created", "rule": rule })))
+}
+
+async fn remove_relay(
+    State(state): State<AppState>,
+    Path(rule_id): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
+    state
+        .relay_manager
+        .remove_rule(&rule_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(Json(serde_json::json!({ "status": "removed", "rule_id": rule_id })))
+}
+
+/// 运动开始事件触发对应流的事件录像（仅对已配置事件录像计划的流生效）
+fn spawn_motion_recording(
+    recorder: Arc<flux_recording::Recorder>,
+    mut events: tokio::sync::broadcast::Receiver<flux_types::message::Message>,
+) {
+    tokio::spawn(async move {
+        loop {
+            let message = match events.recv().await {
+                Ok(message) => message,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+            };
+            let Some(stream_id) = message.topic.strip_prefix(flux_video::ai::MOTION_TOPIC_PREFIX) else {
+                continue;
+            };
+            if message.payload["state"] != "start" || !recorder.has_job(stream_id).await {
+                continue;
+            }
+            if let Err(e) = recorder.trigger(&StreamId::from(stream_id), "motion").await {
+                tracing::debug!(target: "rtmpd", stream_id, "Motion recording trigger skipped: {}", e);
+            }
+        }
+    });
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
@@ -304,9 +486,10 @@ async fn main() -> anyhow::Result<()> {
         segment_duration_secs: 60,
     };
     let storage = Arc::new(RwLock::new(FileSystemStorage::new(storage_config)?));
-    let orchestrator = Arc::new(SnapshotOrchestrator::new(PathBuf::from(
-        &args.keyframe_dir,
-    )));
+    let orchestrator = Arc::new(
+        SnapshotOrchestrator::new(PathBuf::from(&args.keyframe_dir))
+            .with_decoder(Arc::new(SoftwareDecoder::default())),
+    );
 
     // 创建媒体处理器
     let media_processor = Arc::new(media_processor::MediaProcessor::new(
@@ -315,14 +498,33 @@ async fn main() -> anyhow::Result<()> {
         telemetry.clone(),
     ));
 
-    // 创建 RTMP 流管理器（用于 broadcast channel）
-    let stream_manager = Arc::new(stream_manager::StreamManager::new());
-
     // 创建统一流管理器（协议无关）
     use flux_config::StreamingConfig;
     let streaming_config = StreamingConfig::default();
     let unified_stream_manager = Arc::new(flux_stream::StreamManager::new(streaming_config));
 
+    // 创建 RTMP 流管理器（用于 broadcast channel），并把媒体同步到统一流管理器
+    let stream_manager = Arc::new(
+        stream_manager::StreamManager::new()
+            .with_unified_stream_manager(unified_stream_manager.clone()),
+    );
+
+    // 创建 WebRTC 输出（WHEP）与推流接入（WHIP）
+    let webrtc_config = flux_webrtc::WebRtcConfig {
+        public_ips: args.webrtc_public_ips.clone(),
+        udp_port_min: args.webrtc_udp_port_min,
+        udp_port_max: args.webrtc_udp_port_max,
+        ..Default::default()
+    };
+    let whep_server = Arc::new(flux_webrtc::WhepServer::new(
+        unified_stream_manager.clone(),
+        webrtc_config.clone(),
+    )?);
+    let whip_server = Arc::new(flux_webrtc::WhipServer::new(
+        unified_stream_manager.clone(),
+        webrtc_config,
+    )?);
+
     // 创建时移管理器
     use flux_media_core::timeshift::{TimeShiftCore, TimeShiftConfig};
     let timeshift_config = TimeShiftConfig::default();
@@ -333,12 +535,15 @@ async fn main() -> anyhow::Result<()> {
     
     // 创建 HLS 管理器（集成时移）
     let hls_dir = PathBuf::from("./data/hls");
-    let hls_manager = Arc::new(hls_manager::HlsManager::with_storage_manager(
-        hls_dir,
-        storage_manager,
-        Some(timeshift),
-        telemetry.clone(),
-    ));
+    let hls_manager = Arc::new(
+        hls_manager::HlsManager::with_storage_manager(
+            hls_dir,
+            storage_manager,
+            Some(timeshift),
+            telemetry.clone(),
+        )
+        .with_g711_passthrough(args.hls_g711_passthrough),
+    );
 
     // 创建 HTTP-FLV 服务器
     let http_flv_server = Arc::new(http_flv::HttpFlvServer::new(stream_manager.clone()));
@@ -362,13 +567,31 @@ async fn main() -> anyhow::Result<()> {
         flux_middleware::RateLimitStrategy::by_resource(1000),   // 每个流最多1000个客户端
     ]));
 
-    // 启动 RTMP 服务器
-    let rtmp_server = Arc::new(RtmpServer::new(
-        args.rtmp_bind.clone(),
-        media_processor,
+    // 创建 RTMP 转发管理器（转推 / 回源）
+    let relay_manager = Arc::new(relay::RelayManager::new(
         stream_manager.clone(),
         hls_manager.clone(),
     ));
+
+    // 启动 RTMP 服务器
+    let rtmp_server = Arc::new(
+        RtmpServer::new(
+            args.rtmp_bind.clone(),
+            media_processor,
+            stream_manager.clone(),
+            hls_manager.clone(),
+        )
+        .with_relay_manager(relay_manager.clone()),
+    );
+
+    // WHIP 推流与 RTMP 推流一样经 HTTP-FLV / HLS 输出
+    unified_bridge::spawn_webrtc_ingest_bridge(
+        whip_server.subscribe_published(),
+        unified_stream_manager.clone(),
+        stream_manager.clone(),
+        hls_manager.clone(),
+        relay_manager.clone(),
+    );
     
     let state = AppState {
         storage,
@@ -380,6 +603,7 @@ async fn main() -> anyhow::Result<()> {
         stream_manager: stream_manager.clone(),
         unified_stream_manager: unified_stream_manager.clone(),
         http_flv_server,
+        relay_manager,
         jwt_auth,
         rbac_manager,
         rate_limiter,
@@ -392,6 +616,82 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
+    // 所有已注册的流（RTMP / WHIP）同时经 RTSP 输出
+    let rtsp_server = Arc::new(flux_rtspd::rtsp_server::RtspServer::new(
+        unified_stream_manager.clone(),
+        flux_rtspd::rtsp_server::RtspServerConfig {
+            bind: args.rtsp_bind.clone(),
+            credentials: args
+                .rtsp_username
+                .clone()
+                .zip(args.rtsp_password.clone())
+                .map(|(username, password)| flux_rtspd::rtsp_auth::RtspCredentials::new(username, password)),
+            ..Default::default()
+        },
+    ));
+    tokio::spawn(async move {
+        if let Err(e) = rtsp_server.run().await {
+            tracing::error!(target: "rtmpd", "RTSP server error: {}", e);
+        }
+    });
+
+    // 已注册的流同时经 SRT 以 MPEG-TS 输出，并接受 SRT 推流
+    if let Some(srt_bind) = &args.srt_bind {
+        let crypto = args
+            .srt_passphrase
+            .clone()
+            .map(flux_srt::crypto::SrtCryptoConfig::new)
+            .transpose()?;
+        let listener = flux_srt::listener::SrtListener::bind(srt_bind.parse()?).await?;
+        let srt_server = Arc::new(flux_srt::server::SrtServer::new(
+            unified_stream_manager.clone(),
+            flux_srt::server::SrtServerConfig {
+                crypto,
+                ..Default::default()
+            },
+        ));
+        tokio::spawn(async move {
+            if let Err(e) = srt_server.serve(listener).await {
+                tracing::error!(target: "rtmpd", "SRT server error: {}", e);
+            }
+        });
+    }
+
+    // 运动检测：按流通过 HTTP API 启用，事件发布到事件总线
+    let event_bus = Arc::new(flux_core::bus::EventBus::new(1024));
+    let motion_analyzer = Arc::new(
+        flux_video::ai::MotionAnalyzer::new(Some(event_bus.clone()))
+            .with_stream_manager(unified_stream_manager.clone()),
+    );
+
+    // 录像（config/recording.toml），录像任务通过 HTTP API 启停
+    let recording_config = config_loader.load_recording()?;
+    let (recording_read, recording_write) = if recording_config.enabled {
+        let recording_storage = Arc::new(StorageManager::new());
+        if let Err(e) = recording_storage
+            .initialize(flux_recording::pool_configs(&recording_config.storage))
+            .await
+        {
+            tracing::warn!(target: "rtmpd", "Recording storage initialize failed: {}", e);
+        }
+        let recorder = Arc::new(
+            flux_recording::Recorder::new(
+                recording_config,
+                unified_stream_manager.clone(),
+                recording_storage,
+            )
+            .await?,
+        );
+        recorder.clone().spawn_maintenance(std::time::Duration::from_secs(600));
+        spawn_motion_recording(recorder.clone(), event_bus.subscribe());
+        (
+            flux_recording::api::read_router(recorder.clone()),
+            flux_recording::api::write_router(recorder),
+        )
+    } else {
+        (Router::new(), Router::new())
+    };
+
     // 启动 HTTP API 服务器
     tracing::info!(target: "rtmpd", "Setting up HTTP API with security middleware");
     
@@ -401,27 +701,20 @@ async fn main() -> anyhow::Result<()> {
         .route("/login", post(login));  // 登录接口
     
     // 受保护的 API 路由（需要认证和权限）
-    let protected_api = Router::new()
-        .route("/api/v1/rtmp/streams", get(list_streams))
-            .layer(axum::middleware::from_fn_with_state(
-                state.clone(),
-                middleware::require_permission("streams", "read")
-            ))
-        .route("/api/v1/rtmp/streams/:stream_id/snapshot", get(snapshot))
-            .layer(axum::middleware::from_fn_with_state(
-                state.clone(),
-                middleware::require_permission("streams", "read")
-            ))
-        .layer(axum::middleware::from_fn_with_state(
-            state.clone(),
-            middleware::jwt_auth_middleware
-        ));
+    let protected_api = protected_api(
+        &state,
+        recording_read.merge(flux_video::ai::api::read_router(motion_analyzer.clone())),
+        recording_write.merge(flux_video::ai::api::write_router(motion_analyzer)),
+    );
     
     // 流媒体路由（限流保护）
     let streaming_routes = Router::new()
         .route("/hls/:stream_id/index.m3u8", get(hls_playlist))
         .route("/hls/:stream_id/:segment", get(hls_segment))
         .route("/flv/:app/:stream.flv", get(http_flv_route))
+        .route("/cmaf/:app/:stream/:file", get(cmaf_file))
+        .merge(flux_webrtc::whep::router(whep_server))
+        .merge(flux_webrtc::whip::router(whip_server))
         .layer(axum::middleware::from_fn_with_state(
             state.clone(),
             middleware::rate_limit_middleware
@@ -443,6 +736,43 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// 受保护的 API 路由：查询接口需要 `streams:read`，修改接口需要 `streams:write`
+///
+/// `read_routes` / `write_routes` 为其他模块（录像、运动检测）的查询与修改接口。
+/// 权限检查使用 `route_layer`，只作用于本组路由。
+fn protected_api(
+    state: &AppState,
+    read_routes: Router<AppState>,
+    write_routes: Router<AppState>,
+) -> Router<AppState> {
+    let read_api = Router::new()
+        .route("/api/v1/rtmp/streams", get(list_streams))
+        .route("/api/v1/rtmp/streams/:stream_id/snapshot", get(snapshot))
+        .route("/api/v1/rtmp/relays", get(list_relays))
+        .merge(read_routes)
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::require_permission("streams", "read")
+        ));
+    
+    let write_api = Router::new()
+        .route("/api/v1/rtmp/relays/push", post(add_push_relay))
+        .route("/api/v1/rtmp/relays/pull", post(add_pull_relay))
+        .route("/api/v1/rtmp/relays/:rule_id", delete(remove_relay))
+        .merge(write_routes)
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::require_permission("streams", "write")
+        ));
+    
+    read_api
+        .merge(write_api)
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::jwt_auth_middleware
+        ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -459,4 +789,90 @@ mod tests {
         assert_eq!(stream_id.as_str(), "rtmp/live/test123");
         assert_eq!(stream_id.protocol(), Some("rtmp"));
     }
+
+    fn test_state(dir: &std::path::Path) -> AppState {
+        let stream_manager = Arc::new(stream_manager::StreamManager::new());
+        let hls_manager = Arc::new(hls_manager::HlsManager::new(dir.join("hls")));
+        let storage = FileSystemStorage::new(StorageConfig {
+            root_dir: dir.join("storage"),
+            retention_days: 1,
+            segment_duration_secs: 60,
+        })
+        .unwrap();
+        AppState {
+            storage: Arc::new(RwLock::new(storage)),
+            orchestrator: Arc::new(SnapshotOrchestrator::new(dir.join("keyframes"))),
+            streams: Arc::new(RwLock::new(HashMap::new())),
+            hls_generators: Arc::new(RwLock::new(HashMap::new())),
+            rtmp_server: None,
+            hls_manager: hls_manager.clone(),
+            stream_manager: stream_manager.clone(),
+            unified_stream_manager: Arc::new(flux_stream::StreamManager::new(
+                flux_config::StreamingConfig::default(),
+            )),
+            http_flv_server: Arc::new(http_flv::HttpFlvServer::new(stream_manager.clone())),
+            relay_manager: Arc::new(relay::RelayManager::new(stream_manager, hls_manager)),
+            jwt_auth: Arc::new(flux_middleware::JwtAuth::new("test-secret".to_string(), 1)),
+            rbac_manager: Arc::new(flux_middleware::RbacManager::new()),
+            rate_limiter: Arc::new(flux_middleware::RateLimiter::new(vec![])),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_viewer_can_read_but_not_write() {
+        use axum::body::Body;
+        use axum::http::{Method, Request, StatusCode};
+        use tower::ServiceExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path());
+        // 默认角色在后台任务中初始化
+        while state.rbac_manager.get_role("viewer").await.is_none() {
+            tokio::task::yield_now().await;
+        }
+        state.rbac_manager.assign_role("viewer_user", "viewer").await.unwrap();
+        state.rbac_manager.assign_role("admin_user", "admin").await.unwrap();
+        let viewer = state.jwt_auth.generate_token("viewer_user", vec!["viewer".into()]).unwrap();
+        let admin = state.jwt_auth.generate_token("admin_user", vec!["admin".into()]).unwrap();
+
+        let motion = Arc::new(flux_video::ai::MotionAnalyzer::new(None));
+        let app = protected_api(
+            &state,
+            flux_video::ai::api::read_router(motion.clone()),
+            flux_video::ai::api::write_router(motion),
+        )
+        .with_state(state);
+
+        let call = |method: Method, uri: &str, token: &str, body: &str| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("Authorization", format!("Bearer {}", token))
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        for uri in ["/api/v1/rtmp/streams", "/api/v1/rtmp/relays", "/api/v1/motion/streams"] {
+            let response = call(Method::GET, uri, &viewer, "").await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        }
+
+        let response = call(Method::PUT, "/api/v1/motion/streams/rtmp/live/cam", &viewer, "{}")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = call(Method::DELETE, "/api/v1/rtmp/relays/rule_1", &viewer, "").await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = call(Method::PUT, "/api/v1/motion/streams/rtmp/live/cam", &admin, "{}")
+            .await
+            .unwrap();
+        assert_ne!(response.status(), StatusCode::FORBIDDEN);
+
+        let request = Request::builder().uri("/api/v1/rtmp/streams").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
 }
+
@@ -1,13 +1,16 @@
 use anyhow::Result;
 use bytes::Bytes;
 use flux_media_core::{
+    codec::{self, ParameterSets},
+    playback::{FlvVideoPacketType, FlvVideoTag},
     snapshot::SnapshotOrchestrator,
     storage::{filesystem::FileSystemStorage, MediaStorage},
     types::{AudioCodec, AudioSample, StreamId, VideoCodec, VideoSample},
 };
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::telemetry::TelemetryClient;
 
@@ -16,6 +19,8 @@ pub struct MediaProcessor {
     storage: Arc<RwLock<FileSystemStorage>>,
     orchestrator: Arc<SnapshotOrchestrator>,
     telemetry: TelemetryClient,
+    /// 各流序列头中的参数集（关键帧转 Annex B 时前置）
+    parameter_sets: RwLock<HashMap<StreamId, ParameterSets>>,
 }
 
 impl MediaProcessor {
@@ -28,9 +33,15 @@ impl MediaProcessor {
             storage,
             orchestrator,
             telemetry,
+            parameter_sets: RwLock::new(HashMap::new()),
         }
     }
 
+    /// 推流结束时清理该流缓存的参数集
+    pub async fn end_stream(&self, stream_id: &StreamId) {
+        self.parameter_sets.write().await.remove(stream_id);
+    }
+
     /// 处理视频数据
     pub async fn process_video(
         &self,
@@ -102,11 +113,35 @@ impl MediaProcessor {
 
         drop(storage);
 
-        // 如果是关键帧，提取 snapshot
-        if video_info.is_keyframe {
+        // 序列头只缓存参数集，不作为关键帧处理
+        if video_info.packet_type == FlvVideoPacketType::SequenceHeader {
+            let parsed = match video_info.codec {
+                VideoCodec::H264 => codec::parse_avc_decoder_config(&video_info.payload),
+                VideoCodec::H265 => codec::parse_hevc_decoder_config(&video_info.payload),
+                _ => return Ok(()),
+            };
+            match parsed {
+                Ok(sets) => {
+                    self.parameter_sets
+                        .write()
+                        .await
+                        .insert(stream_id.clone(), sets);
+                }
+                Err(e) => {
+                    warn!(target: "rtmpd", stream_id = %stream_id, "Invalid decoder config: {}", e);
+                }
+            }
+            return Ok(());
+        }
+
+        // 如果是关键帧，转为带参数集的 Annex B 后提取 snapshot
+        if video_info.is_keyframe && video_info.packet_type == FlvVideoPacketType::CodedFrames {
+            let Some(access_unit) = self.keyframe_access_unit(stream_id, &video_info).await else {
+                return Ok(());
+            };
             if let Err(e) = self
                 .orchestrator
-                .process_keyframe(stream_id, &video_info.payload, sample.timestamp)
+                .process_keyframe(stream_id, &access_unit, sample.timestamp)
                 .await
             {
                 error!(target: "rtmpd", stream_id = %stream_id, "Keyframe extraction failed: {}", e);
@@ -118,6 +153,35 @@ impl MediaProcessor {
         Ok(())
     }
 
+    /// 将长度前缀的关键帧转为 Annex B，并前置序列头中的 VPS/SPS/PPS
+    async fn keyframe_access_unit(
+        &self,
+        stream_id: &StreamId,
+        video_info: &VideoInfo,
+    ) -> Option<Vec<u8>> {
+        if !matches!(video_info.codec, VideoCodec::H264 | VideoCodec::H265) {
+            return None;
+        }
+
+        let parameter_sets = self.parameter_sets.read().await;
+        let Some(sets) = parameter_sets.get(stream_id) else {
+            debug!(target: "rtmpd", stream_id = %stream_id, "Keyframe before sequence header, skipped");
+            return None;
+        };
+
+        match codec::length_prefixed_to_annexb(&video_info.payload, sets.nal_length_size) {
+            Ok(nalus) => {
+                let mut access_unit = sets.to_annexb();
+                access_unit.extend_from_slice(&nalus);
+                Some(access_unit)
+            }
+            Err(e) => {
+                warn!(target: "rtmpd", stream_id = %stream_id, "Invalid keyframe payload: {}", e);
+                None
+            }
+        }
+    }
+
     /// 处理音频数据
     pub async fn process_audio(
         &self,
@@ -157,47 +221,16 @@ impl MediaProcessor {
         Ok(())
     }
 
-    /// 解析 FLV 视频标签
+    /// 解析 FLV 视频标签（兼容 Enhanced RTMP 的 HEVC/AV1 FourCC 扩展头）
     fn parse_flv_video_tag(&self, data: &[u8]) -> Result<VideoInfo> {
-        if data.is_empty() {
-            return Err(anyhow::anyhow!("Empty video data"));
-        }
-
-        let first_byte = data[0];
-        let frame_type = (first_byte >> 4) & 0x0F;
-        let codec_id = first_byte & 0x0F;
-
-        // 判断是否为关键帧
-        let is_keyframe = frame_type == 1; // 1 = keyframe, 2 = inter frame
-
-        // 解析编码格式
-        let codec = match codec_id {
-            7 => VideoCodec::H264,  // AVC
-            12 => VideoCodec::H265, // HEVC
-            _ => VideoCodec::Unknown,
-        };
-
-        // 对于 H264，需要跳过 AVC packet header
-        let payload = if codec == VideoCodec::H264 && data.len() > 5 {
-            let avc_packet_type = data[1];
-            if avc_packet_type == 0 {
-                // AVC sequence header (SPS/PPS)
-                &data[5..]
-            } else if avc_packet_type == 1 {
-                // AVC NALU
-                &data[5..]
-            } else {
-                // AVC end of sequence
-                &data[1..]
-            }
-        } else {
-            &data[1..]
-        };
+        let tag = FlvVideoTag::parse(data)
+            .map_err(|e| anyhow::anyhow!("Invalid video tag: {}", e))?;
 
         Ok(VideoInfo {
-            codec,
-            is_keyframe,
-            payload: payload.to_vec(),
+            codec: tag.codec,
+            is_keyframe: tag.is_keyframe,
+            packet_type: tag.packet_type,
+            payload: tag.payload.to_vec(),
         })
     }
 
@@ -258,6 +291,7 @@ impl MediaProcessor {
 struct VideoInfo {
     codec: VideoCodec,
     is_keyframe: bool,
+    packet_type: FlvVideoPacketType,
     payload: Vec<u8>,
 }
 
@@ -271,6 +305,8 @@ struct AudioInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use flux_media_core::codec::ANNEXB_START_CODE;
+    use flux_media_core::snapshot::{SnapshotMode, SnapshotRequest, SoftwareDecoder};
     use flux_media_core::storage::StorageConfig;
     use std::path::PathBuf;
     use tempfile::tempdir;
@@ -286,7 +322,7 @@ mod tests {
         let storage = Arc::new(RwLock::new(FileSystemStorage::new(config).unwrap()));
         let orchestrator = Arc::new(SnapshotOrchestrator::new(temp_dir.path().to_path_buf()));
 
-        let processor = MediaProcessor::new(storage, orchestrator);
+        let processor = MediaProcessor::new(storage, orchestrator, TelemetryClient::new(None, 1000));
         assert!(true); // 确保能够创建
     }
 
@@ -300,7 +336,7 @@ mod tests {
         };
         let storage = Arc::new(RwLock::new(FileSystemStorage::new(config).unwrap()));
         let orchestrator = Arc::new(SnapshotOrchestrator::new(temp_dir.path().to_path_buf()));
-        let processor = MediaProcessor::new(storage, orchestrator);
+        let processor = MediaProcessor::new(storage, orchestrator, TelemetryClient::new(None, 1000));
 
         // 模拟 H264 关键帧数据
         // Frame type = 1 (keyframe), Codec ID = 7 (AVC)
@@ -314,6 +350,151 @@ mod tests {
         assert!(result.is_keyframe);
     }
 
+    #[test]
+    fn test_parse_enhanced_hevc_keyframe() {
+        let temp_dir = tempdir().unwrap();
+        let config = StorageConfig {
+            root_dir: temp_dir.path().to_path_buf(),
+            retention_days: 7,
+            segment_duration_secs: 60,
+        };
+        let storage = Arc::new(RwLock::new(FileSystemStorage::new(config).unwrap()));
+        let orchestrator = Arc::new(SnapshotOrchestrator::new(temp_dir.path().to_path_buf()));
+        let processor = MediaProcessor::new(storage, orchestrator, TelemetryClient::new(None, 1000));
+
+        // IsExHeader | keyframe | CodedFramesX + FourCC "hvc1"
+        let mut data = vec![0x93];
+        data.extend_from_slice(b"hvc1");
+        data.extend_from_slice(&[0, 0, 0, 2, 0x26, 0x01]); // IDR_W_RADL
+
+        let result = processor.parse_flv_video_tag(&data).unwrap();
+        assert_eq!(result.codec, VideoCodec::H265);
+        assert!(result.is_keyframe);
+        assert_eq!(result.payload, vec![0, 0, 0, 2, 0x26, 0x01]);
+    }
+
+    /// 用 OpenH264 编码一帧 64x48 灰色画面（Annex B：SPS + PPS + IDR）
+    fn encode_keyframe() -> Vec<u8> {
+        use openh264::encoder::Encoder;
+        use openh264::formats::{RgbSliceU8, YUVBuffer};
+
+        let rgb = vec![128u8; 64 * 48 * 3];
+        let yuv = YUVBuffer::from_rgb8_source(RgbSliceU8::new(&rgb, (64, 48)));
+        Encoder::new().unwrap().encode(&yuv).unwrap().to_vec()
+    }
+
+    /// 按 FLV 格式封装视频 tag body（legacy 头）
+    fn flv_video_tag(codec_id: u8, packet_type: u8, body: &[u8]) -> Vec<u8> {
+        let mut tag = vec![0x10 | codec_id, packet_type, 0, 0, 0];
+        tag.extend_from_slice(body);
+        tag
+    }
+
+    fn snapshot_request(stream_id: &StreamId, mode: SnapshotMode) -> SnapshotRequest {
+        SnapshotRequest {
+            stream_id: stream_id.clone(),
+            mode,
+            width: None,
+            height: None,
+            quality: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flv_keyframe_to_decoded_snapshot() {
+        let temp_dir = tempdir().unwrap();
+        let config = StorageConfig {
+            root_dir: temp_dir.path().join("storage"),
+            retention_days: 7,
+            segment_duration_secs: 60,
+        };
+        let storage = Arc::new(RwLock::new(FileSystemStorage::new(config).unwrap()));
+        let orchestrator = Arc::new(
+            SnapshotOrchestrator::new(temp_dir.path().join("keyframes"))
+                .with_decoder(Arc::new(SoftwareDecoder::default())),
+        );
+        let processor = MediaProcessor::new(storage, orchestrator.clone(), TelemetryClient::new(None, 1000));
+        let stream_id = StreamId::new("rtmp", "live/cam1");
+
+        let keyframe = encode_keyframe();
+        let nalus = codec::split_annexb(&keyframe);
+        let sps = nalus.iter().find(|nalu| nalu[0] & 0x1F == 7).unwrap();
+        let pps = nalus.iter().find(|nalu| nalu[0] & 0x1F == 8).unwrap();
+        let idr: Vec<u8> = nalus
+            .iter()
+            .filter(|nalu| nalu[0] & 0x1F == 5)
+            .flat_map(|nalu| [&ANNEXB_START_CODE[..], nalu].concat())
+            .collect();
+
+        // 关键帧早于序列头时不缓存
+        let coded = flv_video_tag(7, 1, &codec::annexb_to_length_prefixed(&idr));
+        processor.process_video(&stream_id, &coded, 0).await.unwrap();
+        assert!(orchestrator.get_snapshot(snapshot_request(&stream_id, SnapshotMode::Keyframe)).await.is_err());
+
+        // 序列头（帧类型同为关键帧）只缓存参数集
+        let config = codec::build_avc_decoder_config(sps, pps).unwrap();
+        processor
+            .process_video(&stream_id, &flv_video_tag(7, 0, &config), 0)
+            .await
+            .unwrap();
+        assert!(orchestrator.get_snapshot(snapshot_request(&stream_id, SnapshotMode::Keyframe)).await.is_err());
+
+        processor.process_video(&stream_id, &coded, 40).await.unwrap();
+        let jpeg = orchestrator
+            .get_snapshot(snapshot_request(&stream_id, SnapshotMode::Decode))
+            .await
+            .unwrap()
+            .data;
+        assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+
+        processor.end_stream(&stream_id).await;
+        assert!(processor.parameter_sets.read().await.is_empty());
+    }
+
+    #[cfg(feature = "hevc-decoder")]
+    #[tokio::test]
+    async fn test_flv_hevc_keyframe_to_decoded_snapshot() {
+        let temp_dir = tempdir().unwrap();
+        let config = StorageConfig {
+            root_dir: temp_dir.path().join("storage"),
+            retention_days: 7,
+            segment_duration_secs: 60,
+        };
+        let storage = Arc::new(RwLock::new(FileSystemStorage::new(config).unwrap()));
+        let orchestrator = Arc::new(
+            SnapshotOrchestrator::new(temp_dir.path().join("keyframes"))
+                .with_decoder(Arc::new(SoftwareDecoder::default())),
+        );
+        let processor = MediaProcessor::new(storage, orchestrator.clone(), TelemetryClient::new(None, 1000));
+        let stream_id = StreamId::new("rtmp", "live/hevc");
+
+        let keyframe = include_bytes!("../../flux-media-core/testdata/hevc_idr_316x240.h265");
+        let nalus = codec::split_annexb(keyframe);
+        let find = |nal_type| *nalus.iter().find(|nalu| codec::hevc_nal_type(nalu) == Some(nal_type)).unwrap();
+        let irap: Vec<u8> = nalus
+            .iter()
+            .filter(|nalu| codec::is_hevc_irap(nalu))
+            .flat_map(|nalu| [&ANNEXB_START_CODE[..], nalu].concat())
+            .collect();
+
+        let config = codec::build_hevc_decoder_config(find(32), find(33), find(34)).unwrap();
+        processor
+            .process_video(&stream_id, &flv_video_tag(12, 0, &config), 0)
+            .await
+            .unwrap();
+        let coded = flv_video_tag(12, 1, &codec::annexb_to_length_prefixed(&irap));
+        processor.process_video(&stream_id, &coded, 0).await.unwrap();
+
+        let info = orchestrator.get_snapshot(snapshot_request(&stream_id, SnapshotMode::Keyframe)).await.unwrap();
+        assert!(codec::is_hevc_annexb(&info.data));
+        let jpeg = orchestrator
+            .get_snapshot(snapshot_request(&stream_id, SnapshotMode::Decode))
+            .await
+            .unwrap()
+            .data;
+        assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+    }
+
     #[test]
     fn test_parse_aac_audio() {
         let temp_dir = tempdir().unwrap();
@@ -324,7 +505,7 @@ mod tests {
         };
         let storage = Arc::new(RwLock::new(FileSystemStorage::new(config).unwrap()));
         let orchestrator = Arc::new(SnapshotOrchestrator::new(temp_dir.path().to_path_buf()));
-        let processor = MediaProcessor::new(storage, orchestrator);
+        let processor = MediaProcessor::new(storage, orchestrator, TelemetryClient::new(None, 1000));
 
         // 模拟 AAC 音频数据
         // Sound format = 10 (AAC), Rate = 3 (44kHz), Size = 1 (16-bit), Type = 1 (stereo)
@@ -0,0 +1,824 @@
+use anyhow::{anyhow, Result};
+use flux_media_core::playback::FlvVideoTag;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::hls_manager::HlsManager;
+use crate::rtmp_client::{ClientMedia, RtmpClient, RtmpUrl};
+use crate::stream_manager::{MediaPacket, StreamManager};
+
+/// 重连退避：初始间隔
+const BACKOFF_INITIAL: Duration = Duration::from_secs(1);
+/// 重连退避：最大间隔
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// 拉流无人观看多久后自动停止
+const PULL_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+/// 拉流空闲检查间隔
+const PULL_IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 转推规则：app/key 发布时推送到多个远端地址
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushRule {
+    #[serde(default)]
+    pub id: String,
+    pub app: String,
+    /// 为空表示匹配该 app 下的所有流
+    #[serde(default)]
+    pub stream_key: Option<String>,
+    /// 目标地址，支持 `{app}` / `{key}` 占位符
+    pub targets: Vec<String>,
+}
+
+/// 回源规则：播放未知流时从源站拉取
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRule {
+    #[serde(default)]
+    pub id: String,
+    pub app: String,
+    /// 为空表示匹配该 app 下的所有流
+    #[serde(default)]
+    pub stream_key: Option<String>,
+    /// 源站地址，支持 `{app}` / `{key}` 占位符
+    pub origin: String,
+}
+
+/// 转发方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayDirection {
+    Push,
+    Pull,
+}
+
+/// 转发会话状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayState {
+    Connecting,
+    Active,
+    Backoff,
+}
+
+/// 转发会话信息
+#[derive(Debug, Clone, Serialize)]
+pub struct RelaySessionInfo {
+    pub rule_id: String,
+    pub direction: RelayDirection,
+    pub app_name: String,
+    pub stream_key: String,
+    pub url: String,
+    pub state: RelayState,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+struct RelaySession {
+    info: Arc<RwLock<RelaySessionInfo>>,
+    last_access: Arc<RwLock<Instant>>,
+    handle: JoinHandle<()>,
+}
+
+/// 指数退避
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    current: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            current: initial,
+            max,
+        }
+    }
+
+    /// 返回本次等待时长，并将下次等待时长翻倍
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self, initial: Duration) {
+        self.current = initial;
+    }
+}
+
+/// RTMP 转发管理器：负责转推到 CDN 与从源站回源拉流
+pub struct RelayManager {
+    push_rules: Arc<RwLock<HashMap<String, PushRule>>>,
+    pull_rules: Arc<RwLock<HashMap<String, PullRule>>>,
+    sessions: Arc<RwLock<HashMap<String, RelaySession>>>,
+    stream_manager: Arc<StreamManager>,
+    hls_manager: Arc<HlsManager>,
+}
+
+impl RelayManager {
+    pub fn new(stream_manager: Arc<StreamManager>, hls_manager: Arc<HlsManager>) -> Self {
+        Self {
+            push_rules: Arc::new(RwLock::new(HashMap::new())),
+            pull_rules: Arc::new(RwLock::new(HashMap::new())),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            stream_manager,
+            hls_manager,
+        }
+    }
+
+    /// 添加转推规则，对已在发布中的匹配流立即开始转推
+    pub async fn add_push_rule(&self, mut rule: PushRule) -> Result<PushRule> {
+        if rule.targets.is_empty() {
+            return Err(anyhow!("Push rule requires at least one target"));
+        }
+        for target in &rule.targets {
+            RtmpUrl::parse(&expand_url(target, &rule.app, "key"))?;
+        }
+        if rule.id.is_empty() {
+            rule.id = uuid::Uuid::new_v4().to_string();
+        }
+        if self.pull_rules.read().await.contains_key(&rule.id) {
+            return Err(anyhow!("Relay rule id already used by a pull rule: {}", rule.id));
+        }
+
+        // 同 id 重新添加视为替换，先停止旧规则的会话
+        if self.push_rules.write().await.insert(rule.id.clone(), rule.clone()).is_some() {
+            self.stop_rule_sessions(&rule.id).await;
+        }
+        info!(target: "relay", rule_id = %rule.id, app = %rule.app, "Push rule added");
+
+        for stream in self.stream_manager.list_streams().await {
+            if rule_matches(&rule.app, &rule.stream_key, &stream.app_name, &stream.stream_key) {
+                self.start_push_sessions(&rule, &stream.app_name, &stream.stream_key)
+                    .await;
+            }
+        }
+
+        Ok(rule)
+    }
+
+    /// 添加回源规则
+    pub async fn add_pull_rule(&self, mut rule: PullRule) -> Result<PullRule> {
+        RtmpUrl::parse(&expand_url(&rule.origin, &rule.app, "key"))?;
+        if rule.id.is_empty() {
+            rule.id = uuid::Uuid::new_v4().to_string();
+        }
+        if self.push_rules.read().await.contains_key(&rule.id) {
+            return Err(anyhow!("Relay rule id already used by a push rule: {}", rule.id));
+        }
+
+        // 同 id 重新添加视为替换，先停止旧规则的会话
+        if self.pull_rules.write().await.insert(rule.id.clone(), rule.clone()).is_some() {
+            self.stop_rule_sessions(&rule.id).await;
+        }
+        info!(target: "relay", rule_id = %rule.id, app = %rule.app, "Pull rule added");
+
+        Ok(rule)
+    }
+
+    /// 删除规则，并停止该规则产生的所有会话
+    pub async fn remove_rule(&self, rule_id: &str) -> Result<()> {
+        let removed_push = self.push_rules.write().await.remove(rule_id).is_some();
+        let removed_pull = self.pull_rules.write().await.remove(rule_id).is_some();
+        if !removed_push && !removed_pull {
+            return Err(anyhow!("Relay rule not found: {}", rule_id));
+        }
+
+        self.stop_rule_sessions(rule_id).await;
+        info!(target: "relay", rule_id = %rule_id, "Relay rule removed");
+        Ok(())
+    }
+
+    /// 停止某条规则产生的所有会话，回源会话同时注销拉取的本地流
+    async fn stop_rule_sessions(&self, rule_id: &str) {
+        let mut stopped = Vec::new();
+        {
+            let mut sessions = self.sessions.write().await;
+            let keys: Vec<String> = sessions.keys().cloned().collect();
+            for key in keys {
+                let matches = sessions[&key].info.read().await.rule_id == rule_id;
+                if matches {
+                    if let Some(session) = sessions.remove(&key) {
+                        session.handle.abort();
+                        stopped.push(session.info.read().await.clone());
+                    }
+                }
+            }
+        }
+
+        for info in stopped {
+            if info.direction == RelayDirection::Pull {
+                self.teardown_pulled_stream(&info.app_name, &info.stream_key)
+                    .await;
+            }
+        }
+    }
+
+    pub async fn list_push_rules(&self) -> Vec<PushRule> {
+        self.push_rules.read().await.values().cloned().collect()
+    }
+
+    pub async fn list_pull_rules(&self) -> Vec<PullRule> {
+        self.pull_rules.read().await.values().cloned().collect()
+    }
+
+    pub async fn list_sessions(&self) -> Vec<RelaySessionInfo> {
+        let sessions = self.sessions.read().await;
+        let mut result = Vec::with_capacity(sessions.len());
+        for session in sessions.values() {
+            result.push(session.info.read().await.clone());
+        }
+        result
+    }
+
+    /// 流开始发布：为所有匹配的转推规则启动会话
+    pub async fn on_publish(&self, app_name: &str, stream_key: &str) {
+        let rules: Vec<PushRule> = self
+            .push_rules
+            .read()
+            .await
+            .values()
+            .filter(|rule| rule_matches(&rule.app, &rule.stream_key, app_name, stream_key))
+            .cloned()
+            .collect();
+
+        for rule in rules {
+            self.start_push_sessions(&rule, app_name, stream_key).await;
+        }
+    }
+
+    /// 流停止发布：停止该流的转推会话
+    pub async fn on_unpublish(&self, app_name: &str, stream_key: &str) {
+        let mut sessions = self.sessions.write().await;
+        let keys: Vec<String> = sessions.keys().cloned().collect();
+        for key in keys {
+            let matches = {
+                let info = sessions[&key].info.read().await;
+                info.direction == RelayDirection::Push
+                    && info.app_name == app_name
+                    && info.stream_key == stream_key
+            };
+            if matches {
+                if let Some(session) = sessions.remove(&key) {
+                    session.handle.abort();
+                    info!(target: "relay", session = %key, "Push relay stopped");
+                }
+            }
+        }
+    }
+
+    /// 播放请求：流不存在时按回源规则启动拉流
+    ///
+    /// 返回流是否可用（本地已存在或已开始回源）。
+    pub async fn ensure_pull(&self, app_name: &str, stream_key: &str) -> bool {
+        let session_key = session_key(RelayDirection::Pull, app_name, stream_key, "");
+
+        // 持有写锁直到会话登记完成，避免并发播放请求重复回源
+        let mut sessions = self.sessions.write().await;
+        if let Some(session) = sessions.get(&session_key) {
+            *session.last_access.write().await = Instant::now();
+            return true;
+        }
+
+        if self.stream_manager.stream_exists(app_name, stream_key).await {
+            return true;
+        }
+
+        let rule = self
+            .pull_rules
+            .read()
+            .await
+            .values()
+            .find(|rule| rule_matches(&rule.app, &rule.stream_key, app_name, stream_key))
+            .cloned();
+        let Some(rule) = rule else {
+            return false;
+        };
+
+        let url = expand_url(&rule.origin, app_name, stream_key);
+        let parsed = match RtmpUrl::parse(&url) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                error!(target: "relay", url = %url, "Invalid origin url: {}", e);
+                return false;
+            }
+        };
+
+        // 先注册本地流，使播放者可以立即订阅
+        if let Err(e) = self
+            .stream_manager
+            .register_stream(app_name.to_string(), stream_key.to_string())
+            .await
+        {
+            error!(target: "relay", "Failed to register pulled stream: {}", e);
+            return false;
+        }
+        if let Err(e) = self
+            .hls_manager
+            .register_stream(app_name.to_string(), stream_key.to_string(), 6)
+            .await
+        {
+            error!(target: "relay", "Failed to register pulled HLS stream: {}", e);
+        }
+
+        let info = Arc::new(RwLock::new(RelaySessionInfo {
+            rule_id: rule.id.clone(),
+            direction: RelayDirection::Pull,
+            app_name: app_name.to_string(),
+            stream_key: stream_key.to_string(),
+            url,
+            state: RelayState::Connecting,
+            attempts: 0,
+            last_error: None,
+            started_at: chrono::Utc::now(),
+        }));
+        let last_access = Arc::new(RwLock::new(Instant::now()));
+
+        let handle = tokio::spawn(pull_task(
+            parsed,
+            app_name.to_string(),
+            stream_key.to_string(),
+            info.clone(),
+            last_access.clone(),
+            self.stream_manager.clone(),
+            self.hls_manager.clone(),
+            self.sessions.clone(),
+            session_key.clone(),
+        ));
+
+        info!(target: "relay", session = %session_key, "Pull relay started");
+        sessions.insert(
+            session_key,
+            RelaySession {
+                info,
+                last_access,
+                handle,
+            },
+        );
+
+        true
+    }
+
+    /// 记录一次回源流的访问（HLS 播放者不持有订阅）
+    pub async fn touch(&self, app_name: &str, stream_key: &str) {
+        let session_key = session_key(RelayDirection::Pull, app_name, stream_key, "");
+        if let Some(session) = self.sessions.read().await.get(&session_key) {
+            *session.last_access.write().await = Instant::now();
+        }
+    }
+
+    async fn start_push_sessions(&self, rule: &PushRule, app_name: &str, stream_key: &str) {
+        let mut sessions = self.sessions.write().await;
+        for target in &rule.targets {
+            let url = expand_url(target, app_name, stream_key);
+            let key = session_key(RelayDirection::Push, app_name, stream_key, &url);
+            if sessions.contains_key(&key) {
+                continue;
+            }
+
+            let parsed = match RtmpUrl::parse(&url) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    error!(target: "relay", url = %url, "Invalid push target: {}", e);
+                    continue;
+                }
+            };
+
+            let info = Arc::new(RwLock::new(RelaySessionInfo {
+                rule_id: rule.id.clone(),
+                direction: RelayDirection::Push,
+                app_name: app_name.to_string(),
+                stream_key: stream_key.to_string(),
+                url,
+                state: RelayState::Connecting,
+                attempts: 0,
+                last_error: None,
+                started_at: chrono::Utc::now(),
+            }));
+
+            let handle = tokio::spawn(push_task(
+                parsed,
+                app_name.to_string(),
+                stream_key.to_string(),
+                info.clone(),
+                self.stream_manager.clone(),
+            ));
+
+            info!(target: "relay", session = %key, "Push relay started");
+            sessions.insert(
+                key,
+                RelaySession {
+                    info,
+                    last_access: Arc::new(RwLock::new(Instant::now())),
+                    handle,
+                },
+            );
+        }
+    }
+
+    async fn teardown_pulled_stream(&self, app_name: &str, stream_key: &str) {
+        let _ = self.stream_manager.unregister_stream(app_name, stream_key).await;
+        let _ = self.hls_manager.unregister_stream(app_name, stream_key).await;
+    }
+}
+
+/// 规则是否匹配指定流
+fn rule_matches(
+    rule_app: &str,
+    rule_key: &Option<String>,
+    app_name: &str,
+    stream_key: &str,
+) -> bool {
+    rule_app == app_name && rule_key.as_deref().is_none_or(|key| key == stream_key)
+}
+
+/// 替换地址模板中的 `{app}` / `{key}` 占位符
+fn expand_url(template: &str, app_name: &str, stream_key: &str) -> String {
+    template
+        .replace("{app}", app_name)
+        .replace("{key}", stream_key)
+}
+
+fn session_key(direction: RelayDirection, app_name: &str, stream_key: &str, url: &str) -> String {
+    match direction {
+        RelayDirection::Push => format!("push:{}/{}:{}", app_name, stream_key, url),
+        RelayDirection::Pull => format!("pull:{}/{}", app_name, stream_key),
+    }
+}
+
+async fn set_state(info: &RwLock<RelaySessionInfo>, state: RelayState, error: Option<String>) {
+    let mut info = info.write().await;
+    info.state = state;
+    if error.is_some() {
+        info.last_error = error;
+    }
+}
+
+/// 转推任务：订阅本地流并推送到远端，断线后按退避重连，本地流结束时退出
+async fn push_task(
+    url: RtmpUrl,
+    app_name: String,
+    stream_key: String,
+    info: Arc<RwLock<RelaySessionInfo>>,
+    stream_manager: Arc<StreamManager>,
+) {
+    let mut backoff = Backoff::new(BACKOFF_INITIAL, BACKOFF_MAX);
+
+    loop {
+        info.write().await.attempts += 1;
+        set_state(&info, RelayState::Connecting, None).await;
+
+        match push_once(&url, &app_name, &stream_key, &info, &mut backoff, &stream_manager).await {
+            Ok(()) => return,
+            Err(e) if e.is::<SourceEnded>() => {
+                info!(target: "relay", app = %app_name, key = %stream_key, "Source stream ended, push relay finished");
+                return;
+            }
+            Err(e) => {
+                let delay = backoff.next_delay();
+                warn!(target: "relay", url = %url.tc_url(), "Push relay failed, retry in {:?}: {}", delay, e);
+                set_state(&info, RelayState::Backoff, Some(e.to_string())).await;
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+/// 单次推流，直到连接异常或本地流结束（`SourceEnded`）
+async fn push_once(
+    url: &RtmpUrl,
+    app_name: &str,
+    stream_key: &str,
+    info: &RwLock<RelaySessionInfo>,
+    backoff: &mut Backoff,
+    stream_manager: &StreamManager,
+) -> Result<()> {
+    let mut client = RtmpClient::connect(url.clone()).await?;
+    client.publish().await?;
+
+    let (mut video_rx, mut audio_rx) = stream_manager
+        .subscribe(app_name, stream_key)
+        .await
+        .map_err(|_| SourceEnded)?;
+    set_state(info, RelayState::Active, None).await;
+    backoff.reset(BACKOFF_INITIAL);
+
+    // 中途加入：先补发序列头
+    let (video_header, audio_header) = stream_manager
+        .get_sequence_headers(app_name, stream_key)
+        .await;
+    if let Some(header) = video_header {
+        client.send_video(header.data, header.timestamp).await?;
+    }
+    if let Some(header) = audio_header {
+        client.send_audio(header.data, header.timestamp).await?;
+    }
+
+    loop {
+        tokio::select! {
+            packet = video_rx.recv() => {
+                match next_packet(packet)? {
+                    Some(packet) => client.send_video(packet.data, packet.timestamp).await?,
+                    None => continue,
+                }
+            }
+            packet = audio_rx.recv() => {
+                match next_packet(packet)? {
+                    Some(packet) => client.send_audio(packet.data, packet.timestamp).await?,
+                    None => continue,
+                }
+            }
+            readable = client.readable() => {
+                readable?;
+                client.drain_input().await?;
+            }
+        }
+    }
+}
+
+/// 处理 broadcast 接收结果：通道关闭视为流结束
+fn next_packet(
+    packet: std::result::Result<MediaPacket, broadcast::error::RecvError>,
+) -> Result<Option<MediaPacket>> {
+    match packet {
+        Ok(packet) => Ok(Some(packet)),
+        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+            warn!(target: "relay", skipped = skipped, "Push relay lagged behind source");
+            Ok(None)
+        }
+        Err(broadcast::error::RecvError::Closed) => Err(SourceEnded.into()),
+    }
+}
+
+#[derive(Debug)]
+struct SourceEnded;
+
+impl std::fmt::Display for SourceEnded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "source stream ended")
+    }
+}
+
+impl std::error::Error for SourceEnded {}
+
+/// 回源任务：从源站拉流并发布到本地，无人观看时自动退出
+#[allow(clippy::too_many_arguments)]
+async fn pull_task(
+    url: RtmpUrl,
+    app_name: String,
+    stream_key: String,
+    info: Arc<RwLock<RelaySessionInfo>>,
+    last_access: Arc<RwLock<Instant>>,
+    stream_manager: Arc<StreamManager>,
+    hls_manager: Arc<HlsManager>,
+    sessions: Arc<RwLock<HashMap<String, RelaySession>>>,
+    session_key: String,
+) {
+    let mut backoff = Backoff::new(BACKOFF_INITIAL, BACKOFF_MAX);
+
+    loop {
+        info.write().await.attempts += 1;
+        set_state(&info, RelayState::Connecting, None).await;
+
+        let result = pull_once(
+            &url,
+            &app_name,
+            &stream_key,
+            &info,
+            &last_access,
+            &mut backoff,
+            &stream_manager,
+            &hls_manager,
+        )
+        .await;
+
+        match result {
+            Ok(()) => break,
+            Err(e) => {
+                if is_idle(&last_access, &stream_manager, &app_name, &stream_key).await {
+                    break;
+                }
+                let delay = backoff.next_delay();
+                warn!(target: "relay", url = %url.tc_url(), "Pull relay failed, retry in {:?}: {}", delay, e);
+                set_state(&info, RelayState::Backoff, Some(e.to_string())).await;
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+
+    info!(target: "relay", app = %app_name, key = %stream_key, "Pull relay idle, stopping");
+    sessions.write().await.remove(&session_key);
+    let _ = stream_manager.unregister_stream(&app_name, &stream_key).await;
+    let _ = hls_manager.unregister_stream(&app_name, &stream_key).await;
+}
+
+/// 单次拉流；空闲超时返回 Ok，连接异常返回 Err
+#[allow(clippy::too_many_arguments)]
+async fn pull_once(
+    url: &RtmpUrl,
+    app_name: &str,
+    stream_key: &str,
+    info: &RwLock<RelaySessionInfo>,
+    last_access: &RwLock<Instant>,
+    backoff: &mut Backoff,
+    stream_manager: &StreamManager,
+    hls_manager: &HlsManager,
+) -> Result<()> {
+    let mut client = RtmpClient::connect(url.clone()).await?;
+    client.play().await?;
+    set_state(info, RelayState::Active, None).await;
+    backoff.reset(BACKOFF_INITIAL);
+
+    let mut idle_check = tokio::time::interval(PULL_IDLE_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            media = client.next_media() => {
+                match media? {
+                    ClientMedia::Video { data, timestamp } => {
+                        let is_keyframe = FlvVideoTag::parse(&data).is_ok_and(|tag| tag.is_keyframe);
+                        stream_manager
+                            .publish_video(app_name, stream_key, data.clone(), timestamp, is_keyframe)
+                            .await?;
+                        hls_manager
+                            .process_video(app_name, stream_key, &data, timestamp, is_keyframe)
+                            .await?;
+                    }
+                    ClientMedia::Audio { data, timestamp } => {
+                        stream_manager
+                            .publish_audio(app_name, stream_key, data.clone(), timestamp)
+                            .await?;
+                        hls_manager
+                            .process_audio(app_name, stream_key, &data, timestamp)
+                            .await?;
+                    }
+                }
+            }
+            _ = idle_check.tick() => {
+                if is_idle(last_access, stream_manager, app_name, stream_key).await {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// 没有 FLV 订阅者且超过空闲时长未被访问
+async fn is_idle(
+    last_access: &RwLock<Instant>,
+    stream_manager: &StreamManager,
+    app_name: &str,
+    stream_key: &str,
+) -> bool {
+    stream_manager.receiver_count(app_name, stream_key).await == 0
+        && last_access.read().await.elapsed() >= PULL_IDLE_TIMEOUT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_manager() -> (RelayManager, tempfile::TempDir) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let stream_manager = Arc::new(StreamManager::new());
+        let hls_manager = Arc::new(HlsManager::new(temp_dir.path().to_path_buf()));
+        (RelayManager::new(stream_manager, hls_manager), temp_dir)
+    }
+
+    #[test]
+    fn test_backoff_doubles_until_max() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(2));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(4));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(5));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(5));
+
+        backoff.reset(Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_rule_matching_and_expansion() {
+        assert!(rule_matches("live", &None, "live", "cam1"));
+        assert!(rule_matches("live", &Some("cam1".to_string()), "live", "cam1"));
+        assert!(!rule_matches("live", &Some("cam1".to_string()), "live", "cam2"));
+        assert!(!rule_matches("live", &None, "vod", "cam1"));
+
+        assert_eq!(
+            expand_url("rtmp://cdn/{app}/{key}?auth=x", "live", "cam1"),
+            "rtmp://cdn/live/cam1?auth=x"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_rules() {
+        let (manager, _dir) = test_manager();
+
+        let rule = manager
+            .add_push_rule(PushRule {
+                id: String::new(),
+                app: "live".to_string(),
+                stream_key: None,
+                targets: vec!["rtmp://cdn.example.com/live/{key}".to_string()],
+            })
+            .await
+            .unwrap();
+        assert!(!rule.id.is_empty());
+        assert_eq!(manager.list_push_rules().await.len(), 1);
+
+        assert!(manager
+            .add_push_rule(PushRule {
+                id: String::new(),
+                app: "live".to_string(),
+                stream_key: None,
+                targets: vec!["http://not-rtmp/live".to_string()],
+            })
+            .await
+            .is_err());
+
+        manager.remove_rule(&rule.id).await.unwrap();
+        assert!(manager.list_push_rules().await.is_empty());
+        assert!(manager.remove_rule(&rule.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_readd_rule_stops_old_sessions() {
+        let (manager, _dir) = test_manager();
+        manager
+            .stream_manager
+            .register_stream("live".to_string(), "cam1".to_string())
+            .await
+            .unwrap();
+
+        let push_rule = |target: &str| PushRule {
+            id: "cdn".to_string(),
+            app: "live".to_string(),
+            stream_key: None,
+            targets: vec![target.to_string()],
+        };
+        manager.add_push_rule(push_rule("rtmp://127.0.0.1:1/old/{key}")).await.unwrap();
+        let old = manager.sessions.read().await.values().next().unwrap().handle.abort_handle();
+
+        manager.add_push_rule(push_rule("rtmp://127.0.0.1:1/new/{key}")).await.unwrap();
+        let sessions = manager.list_sessions().await;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].url, "rtmp://127.0.0.1:1/new/cam1");
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !old.is_finished() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("old push session stopped");
+
+        // 同一 id 不能同时用于回源规则
+        assert!(manager
+            .add_pull_rule(PullRule {
+                id: "cdn".to_string(),
+                app: "live".to_string(),
+                stream_key: None,
+                origin: "rtmp://127.0.0.1:1/{app}/{key}".to_string(),
+            })
+            .await
+            .is_err());
+        assert_eq!(manager.list_push_rules().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ensure_pull_without_rule() {
+        let (manager, _dir) = test_manager();
+        assert!(!manager.ensure_pull("live", "unknown").await);
+    }
+
+    #[tokio::test]
+    async fn test_ensure_pull_registers_stream() {
+        let (manager, _dir) = test_manager();
+        manager
+            .add_pull_rule(PullRule {
+                id: "origin".to_string(),
+                app: "live".to_string(),
+                stream_key: None,
+                // 不可达地址：会话进入退避状态，但本地流已注册
+                origin: "rtmp://127.0.0.1:1/{app}/{key}".to_string(),
+            })
+            .await
+            .unwrap();
+
+        assert!(manager.ensure_pull("live", "cam1").await);
+        assert!(manager.stream_manager.stream_exists("live", "cam1").await);
+
+        let sessions = manager.list_sessions().await;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].direction, RelayDirection::Pull);
+        assert_eq!(sessions[0].url, "rtmp://127.0.0.1:1/live/cam1");
+
+        manager.remove_rule("origin").await.unwrap();
+        assert!(manager.list_sessions().await.is_empty());
+        assert!(!manager.stream_manager.stream_exists("live", "cam1").await);
+    }
+}
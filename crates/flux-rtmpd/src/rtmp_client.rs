@@ -0,0 +1,419 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use rml_rtmp::handshake::{Handshake, HandshakeProcessResult, PeerType};
+use rml_rtmp::sessions::{
+    ClientSession, ClientSessionConfig, ClientSessionEvent, ClientSessionResult,
+    PublishRequestType,
+};
+use rml_rtmp::time::RtmpTimestamp;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, info};
+
+/// RTMP 默认端口
+const DEFAULT_RTMP_PORT: u16 = 1935;
+
+/// 建连/握手/命令交互的超时时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// RTMP 地址：rtmp://host[:port]/app/stream_key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtmpUrl {
+    pub host: String,
+    pub port: u16,
+    pub app: String,
+    pub stream_key: String,
+}
+
+impl RtmpUrl {
+    /// 解析 RTMP 地址
+    ///
+    /// app 取路径的第一段，剩余部分（可包含 `?token=...` 查询参数）作为 stream key。
+    pub fn parse(url: &str) -> Result<Self> {
+        let rest = url
+            .strip_prefix("rtmp://")
+            .ok_or_else(|| anyhow!("Unsupported RTMP url scheme: {}", url))?;
+
+        let (authority, path) = rest
+            .split_once('/')
+            .ok_or_else(|| anyhow!("RTMP url missing app: {}", url))?;
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host.to_string(),
+                port.parse::<u16>()
+                    .map_err(|_| anyhow!("Invalid RTMP port in url: {}", url))?,
+            ),
+            None => (authority.to_string(), DEFAULT_RTMP_PORT),
+        };
+        if host.is_empty() {
+            return Err(anyhow!("RTMP url missing host: {}", url));
+        }
+
+        let (app, stream_key) = path
+            .split_once('/')
+            .ok_or_else(|| anyhow!("RTMP url missing stream key: {}", url))?;
+        if app.is_empty() || stream_key.is_empty() {
+            return Err(anyhow!("RTMP url missing app or stream key: {}", url));
+        }
+
+        Ok(Self {
+            host,
+            port,
+            app: app.to_string(),
+            stream_key: stream_key.to_string(),
+        })
+    }
+
+    /// connect 命令中携带的 tcUrl（部分 CDN 要求必须提供）
+    pub fn tc_url(&self) -> String {
+        format!("rtmp://{}:{}/{}", self.host, self.port, self.app)
+    }
+}
+
+/// 从远端拉取到的媒体数据
+#[derive(Debug, Clone)]
+pub enum ClientMedia {
+    Video { data: Bytes, timestamp: u32 },
+    Audio { data: Bytes, timestamp: u32 },
+}
+
+/// RTMP 客户端：基于 rml_rtmp ClientSession 实现推流与拉流
+pub struct RtmpClient {
+    url: RtmpUrl,
+    socket: TcpStream,
+    session: ClientSession,
+    events: VecDeque<ClientSessionEvent>,
+    read_buffer: Vec<u8>,
+}
+
+impl RtmpClient {
+    /// 建立 TCP 连接、完成握手并连接到 app
+    pub async fn connect(url: RtmpUrl) -> Result<Self> {
+        tokio::time::timeout(REQUEST_TIMEOUT, Self::connect_inner(url))
+            .await
+            .map_err(|_| anyhow!("RTMP connect timed out"))?
+    }
+
+    async fn connect_inner(url: RtmpUrl) -> Result<Self> {
+        let mut socket = TcpStream::connect((url.host.as_str(), url.port)).await?;
+        socket.set_nodelay(true)?;
+
+        let remaining = Self::handshake(&mut socket).await?;
+
+        let mut config = ClientSessionConfig::new();
+        config.tc_url = Some(url.tc_url());
+        let (session, initial_results) = ClientSession::new(config)?;
+
+        let mut client = Self {
+            url,
+            socket,
+            session,
+            events: VecDeque::new(),
+            read_buffer: vec![0u8; 4096],
+        };
+
+        client.handle_results(initial_results).await?;
+        if !remaining.is_empty() {
+            let results = client.session.handle_input(&remaining)?;
+            client.handle_results(results).await?;
+        }
+
+        let result = client.session.request_connection(client.url.app.clone())?;
+        client.handle_results(vec![result]).await?;
+        client
+            .wait_for(|event| matches!(event, ClientSessionEvent::ConnectionRequestAccepted))
+            .await?;
+
+        info!(target: "rtmp_client", host = %client.url.host, app = %client.url.app, "RTMP connection established");
+        Ok(client)
+    }
+
+    /// 客户端握手，返回握手完成后多读到的字节
+    async fn handshake(socket: &mut TcpStream) -> Result<Vec<u8>> {
+        let mut handshake = Handshake::new(PeerType::Client);
+        let p0_and_p1 = handshake.generate_outbound_p0_and_p1()?;
+        socket.write_all(&p0_and_p1).await?;
+
+        let mut buffer = vec![0u8; 4096];
+        loop {
+            let n = socket.read(&mut buffer).await?;
+            if n == 0 {
+                return Err(anyhow!("Connection closed during RTMP handshake"));
+            }
+
+            match handshake.process_bytes(&buffer[..n])? {
+                HandshakeProcessResult::InProgress { response_bytes } => {
+                    if !response_bytes.is_empty() {
+                        socket.write_all(&response_bytes).await?;
+                    }
+                }
+                HandshakeProcessResult::Completed {
+                    response_bytes,
+                    remaining_bytes,
+                } => {
+                    if !response_bytes.is_empty() {
+                        socket.write_all(&response_bytes).await?;
+                    }
+                    return Ok(remaining_bytes);
+                }
+            }
+        }
+    }
+
+    /// 请求以 live 模式发布到 url 中的 stream key
+    pub async fn publish(&mut self) -> Result<()> {
+        let result = self
+            .session
+            .request_publishing(self.url.stream_key.clone(), PublishRequestType::Live)?;
+        self.handle_results(vec![result]).await?;
+        self.wait_for_timeout(|event| matches!(event, ClientSessionEvent::PublishRequestAccepted))
+            .await?;
+
+        info!(target: "rtmp_client", url = %self.url.tc_url(), key = %self.url.stream_key, "RTMP publish accepted");
+        Ok(())
+    }
+
+    /// 请求播放 url 中的 stream key
+    pub async fn play(&mut self) -> Result<()> {
+        let result = self.session.request_playback(self.url.stream_key.clone())?;
+        self.handle_results(vec![result]).await?;
+        self.wait_for_timeout(|event| matches!(event, ClientSessionEvent::PlaybackRequestAccepted))
+            .await?;
+
+        info!(target: "rtmp_client", url = %self.url.tc_url(), key = %self.url.stream_key, "RTMP playback accepted");
+        Ok(())
+    }
+
+    /// 发送一个 FLV 视频 tag body
+    pub async fn send_video(&mut self, data: Bytes, timestamp: u32) -> Result<()> {
+        let result = self
+            .session
+            .publish_video_data(data, RtmpTimestamp::new(timestamp), false)?;
+        self.handle_results(vec![result]).await
+    }
+
+    /// 发送一个 FLV 音频 tag body
+    pub async fn send_audio(&mut self, data: Bytes, timestamp: u32) -> Result<()> {
+        let result = self
+            .session
+            .publish_audio_data(data, RtmpTimestamp::new(timestamp), false)?;
+        self.handle_results(vec![result]).await
+    }
+
+    /// 等待 socket 可读（可安全用于 `tokio::select!`）
+    pub async fn readable(&self) -> Result<()> {
+        self.socket.readable().await?;
+        Ok(())
+    }
+
+    /// 非阻塞地处理已到达的服务端消息（ack、ping 等）
+    pub async fn drain_input(&mut self) -> Result<()> {
+        loop {
+            match self.socket.try_read(&mut self.read_buffer) {
+                Ok(0) => return Err(anyhow!("RTMP connection closed by peer")),
+                Ok(n) => {
+                    let data = self.read_buffer[..n].to_vec();
+                    let results = self.session.handle_input(&data)?;
+                    self.handle_results(results).await?;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// 读取下一个媒体数据（播放模式）
+    pub async fn next_media(&mut self) -> Result<ClientMedia> {
+        loop {
+            while let Some(event) = self.events.pop_front() {
+                match event {
+                    ClientSessionEvent::VideoDataReceived { data, timestamp } => {
+                        return Ok(ClientMedia::Video {
+                            data,
+                            timestamp: timestamp.value,
+                        });
+                    }
+                    ClientSessionEvent::AudioDataReceived { data, timestamp } => {
+                        return Ok(ClientMedia::Audio {
+                            data,
+                            timestamp: timestamp.value,
+                        });
+                    }
+                    other => {
+                        debug!(target: "rtmp_client", "Ignored event: {:?}", other);
+                    }
+                }
+            }
+
+            self.read_once().await?;
+        }
+    }
+
+    /// 读取一次 socket 并处理
+    async fn read_once(&mut self) -> Result<()> {
+        let n = self.socket.read(&mut self.read_buffer).await?;
+        if n == 0 {
+            return Err(anyhow!("RTMP connection closed by peer"));
+        }
+        let data = self.read_buffer[..n].to_vec();
+        let results = self.session.handle_input(&data)?;
+        self.handle_results(results).await
+    }
+
+    async fn handle_results(&mut self, results: Vec<ClientSessionResult>) -> Result<()> {
+        for result in results {
+            match result {
+                ClientSessionResult::OutboundResponse(packet) => {
+                    self.socket.write_all(&packet.bytes).await?;
+                }
+                ClientSessionResult::RaisedEvent(event) => {
+                    self.events.push_back(event);
+                }
+                ClientSessionResult::UnhandleableMessageReceived(_) => {
+                    debug!(target: "rtmp_client", "Unhandleable message");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn wait_for_timeout<F>(&mut self, predicate: F) -> Result<()>
+    where
+        F: Fn(&ClientSessionEvent) -> bool,
+    {
+        tokio::time::timeout(REQUEST_TIMEOUT, self.wait_for(predicate))
+            .await
+            .map_err(|_| anyhow!("RTMP request timed out"))?
+    }
+
+    /// 等待满足条件的事件；媒体事件保留在队列中供 `next_media` 使用
+    async fn wait_for<F>(&mut self, predicate: F) -> Result<()>
+    where
+        F: Fn(&ClientSessionEvent) -> bool,
+    {
+        loop {
+            if let Some(pos) = self.events.iter().position(&predicate) {
+                self.events.remove(pos);
+                return Ok(());
+            }
+
+            if let Some(description) = self.events.iter().find_map(|event| match event {
+                ClientSessionEvent::ConnectionRequestRejected { description } => {
+                    Some(description.clone())
+                }
+                _ => None,
+            }) {
+                return Err(anyhow!("RTMP connection rejected: {}", description));
+            }
+
+            self.read_once().await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rml_rtmp::sessions::{ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_rtmp_url() {
+        let url = RtmpUrl::parse("rtmp://cdn.example.com/live/abc?token=1").unwrap();
+        assert_eq!(url.host, "cdn.example.com");
+        assert_eq!(url.port, 1935);
+        assert_eq!(url.app, "live");
+        assert_eq!(url.stream_key, "abc?token=1");
+        assert_eq!(url.tc_url(), "rtmp://cdn.example.com:1935/live");
+
+        let url = RtmpUrl::parse("rtmp://10.0.0.1:19350/app/key").unwrap();
+        assert_eq!(url.port, 19350);
+    }
+
+    #[test]
+    fn test_parse_invalid_rtmp_url() {
+        assert!(RtmpUrl::parse("http://host/live/key").is_err());
+        assert!(RtmpUrl::parse("rtmp://host").is_err());
+        assert!(RtmpUrl::parse("rtmp://host/live").is_err());
+        assert!(RtmpUrl::parse("rtmp://host:abc/live/key").is_err());
+    }
+
+    /// 最小 RTMP 服务端：接受连接和发布，并把收到的第一个视频数据返回
+    async fn accept_one_publish(listener: TcpListener) -> Bytes {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut handshake = Handshake::new(PeerType::Server);
+        let mut buffer = vec![0u8; 4096];
+
+        let remaining = loop {
+            let n = socket.read(&mut buffer).await.unwrap();
+            match handshake.process_bytes(&buffer[..n]).unwrap() {
+                HandshakeProcessResult::InProgress { response_bytes } => {
+                    socket.write_all(&response_bytes).await.unwrap();
+                }
+                HandshakeProcessResult::Completed {
+                    response_bytes,
+                    remaining_bytes,
+                } => {
+                    socket.write_all(&response_bytes).await.unwrap();
+                    break remaining_bytes;
+                }
+            }
+        };
+
+        let (mut session, initial) = ServerSession::new(ServerSessionConfig::new()).unwrap();
+        let mut pending = initial;
+        pending.extend(session.handle_input(&remaining).unwrap());
+
+        loop {
+            let mut next = Vec::new();
+            for result in pending.drain(..) {
+                match result {
+                    ServerSessionResult::OutboundResponse(packet) => {
+                        socket.write_all(&packet.bytes).await.unwrap();
+                    }
+                    ServerSessionResult::RaisedEvent(ServerSessionEvent::ConnectionRequested {
+                        request_id,
+                        ..
+                    })
+                    | ServerSessionResult::RaisedEvent(
+                        ServerSessionEvent::PublishStreamRequested { request_id, .. },
+                    ) => {
+                        next.extend(session.accept_request(request_id).unwrap());
+                    }
+                    ServerSessionResult::RaisedEvent(ServerSessionEvent::VideoDataReceived {
+                        data,
+                        ..
+                    }) => return data,
+                    _ => {}
+                }
+            }
+
+            if next.is_empty() {
+                let n = socket.read(&mut buffer).await.unwrap();
+                next = session.handle_input(&buffer[..n]).unwrap();
+            }
+            pending = next;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_publish_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(accept_one_publish(listener));
+
+        let url = RtmpUrl::parse(&format!("rtmp://127.0.0.1:{}/live/relay", port)).unwrap();
+        let mut client = RtmpClient::connect(url).await.unwrap();
+        client.publish().await.unwrap();
+        client
+            .send_video(Bytes::from_static(&[0x17, 0x01, 0, 0, 0, 0x65]), 40)
+            .await
+            .unwrap();
+
+        let received = server.await.unwrap();
+        assert_eq!(&received[..], &[0x17, 0x01, 0, 0, 0, 0x65]);
+    }
+}
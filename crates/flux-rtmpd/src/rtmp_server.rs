@@ -1,9 +1,11 @@
 use crate::hls_manager::HlsManager;
 use crate::media_processor::MediaProcessor;
+use crate::relay::RelayManager;
 use crate::stream_manager::StreamManager;
 use anyhow::Result;
 use bytes::Bytes;
-use flux_media_core::types::StreamId;
+use flux_media_core::playback::FlvVideoTag;
+use flux_media_core::types::{StreamId, VideoCodec};
 use rml_rtmp::sessions::{
     ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult,
 };
@@ -22,6 +24,7 @@ pub struct RtmpServer {
     active_streams: Arc<RwLock<HashMap<String, ActiveStream>>>,
     stream_manager: Arc<StreamManager>,
     hls_manager: Arc<HlsManager>,
+    relay_manager: Option<Arc<RelayManager>>,
 }
 
 #[derive(Debug, Clone)]
@@ -32,6 +35,8 @@ pub struct ActiveStream {
     pub start_time: chrono::DateTime<chrono::Utc>,
     pub video_frames: u64,
     pub audio_frames: u64,
+    /// 从视频序列头识别出的编码（含 Enhanced RTMP 的 HEVC/AV1）
+    pub video_codec: Option<VideoCodec>,
 }
 
 struct RtmpSession {
@@ -56,9 +61,16 @@ impl RtmpServer {
             active_streams: Arc::new(RwLock::new(HashMap::new())),
             stream_manager,
             hls_manager,
+            relay_manager: None,
         }
     }
 
+    /// 启用 RTMP 转推（发布时按规则推送到远端）
+    pub fn with_relay_manager(mut self, relay_manager: Arc<RelayManager>) -> Self {
+        self.relay_manager = Some(relay_manager);
+        self
+    }
+
     pub async fn start(self: Arc<Self>) -> Result<()> {
         let listener = TcpListener::bind(&self.bind_addr).await?;
         info!(target: "rtmpd", "RTMP server listening on {}", self.bind_addr);
@@ -134,9 +146,24 @@ impl RtmpServer {
         }
 
         // 清理会话
-        {
+        let closed = {
             let mut sessions = self.sessions.write().await;
-            sessions.remove(&session_id);
+            sessions.remove(&session_id)
+        };
+
+        // 推流端断开时停止转推
+        if let Some(RtmpSession {
+            app_name: Some(app_name),
+            stream_key: Some(stream_key),
+            ..
+        }) = closed
+        {
+            if let Some(relay) = &self.relay_manager {
+                relay.on_unpublish(&app_name, &stream_key).await;
+            }
+            self.media_processor
+                .end_stream(&StreamId::new("rtmp", &format!("{}/{}", app_name, stream_key)))
+                .await;
         }
 
         Ok(())
@@ -226,6 +253,7 @@ impl RtmpServer {
                     start_time: chrono::Utc::now(),
                     video_frames: 0,
                     audio_frames: 0,
+                    video_codec: None,
                 });
                 drop(active_streams);
 
@@ -251,6 +279,30 @@ impl RtmpServer {
                         }
                     }
                 }
+                drop(sessions);
+
+                // 按转推规则推送到远端
+                if let Some(relay) = &self.relay_manager {
+                    relay.on_publish(&app_name, &stream_key).await;
+                }
+            }
+            ServerSessionEvent::PublishStreamFinished {
+                app_name,
+                stream_key,
+            } => {
+                info!(target: "rtmpd",
+                    session_id = session_id,
+                    app = %app_name,
+                    key = %stream_key,
+                    "Publish finished"
+                );
+
+                if let Some(relay) = &self.relay_manager {
+                    relay.on_unpublish(&app_name, &stream_key).await;
+                }
+                self.media_processor
+                    .end_stream(&StreamId::new("rtmp", &format!("{}/{}", app_name, stream_key)))
+                    .await;
             }
             ServerSessionEvent::StreamMetadataChanged {
                 app_name,
@@ -272,13 +324,19 @@ impl RtmpServer {
                 let stream_id = StreamId::new("rtmp", &format!("{}/{}", app_name, stream_key));
                 let stream_key_full = format!("{}/{}", app_name, stream_key);
                 
-                // 更新视频帧计数
+                // 解析视频 tag（兼容 Enhanced RTMP 扩展头）
+                let tag = FlvVideoTag::parse(&data).ok();
+                let is_keyframe = tag.as_ref().is_some_and(|tag| tag.is_keyframe);
+
+                // 更新视频帧计数与编码
                 if let Some(stream) = self.active_streams.write().await.get_mut(&stream_key_full) {
                     stream.video_frames += 1;
+                    if let Some(tag) = tag.as_ref().filter(|tag| tag.is_sequence_header()) {
+                        stream.video_codec = Some(tag.codec);
+                    }
                 }
                 
                 // 发布到流管理器（用于播放分发）
-                let is_keyframe = !data.is_empty() && (data[0] >> 4) == 1;
                 if let Err(e) = self.stream_manager.publish_video(
                     &app_name,
                     &stream_key,
@@ -399,7 +457,7 @@ mod tests {
         };
         let storage = Arc::new(RwLock::new(FileSystemStorage::new(config).unwrap()));
         let orchestrator = Arc::new(SnapshotOrchestrator::new(temp_dir.path().to_path_buf()));
-        let media_processor = Arc::new(MediaProcessor::new(storage, orchestrator));
+        let media_processor = Arc::new(MediaProcessor::new(storage, orchestrator, crate::telemetry::TelemetryClient::new(None, 1000)));
         let stream_manager = Arc::new(StreamManager::new());
         let hls_dir = temp_dir.path().join("hls");
         let hls_manager = Arc::new(HlsManager::new(hls_dir));
@@ -425,7 +483,7 @@ mod tests {
         };
         let storage = Arc::new(RwLock::new(FileSystemStorage::new(config).unwrap()));
         let orchestrator = Arc::new(SnapshotOrchestrator::new(temp_dir.path().to_path_buf()));
-        let media_processor = Arc::new(MediaProcessor::new(storage, orchestrator));
+        let media_processor = Arc::new(MediaProcessor::new(storage, orchestrator, crate::telemetry::TelemetryClient::new(None, 1000)));
         let stream_manager = Arc::new(StreamManager::new());
         let hls_dir = temp_dir.path().join("hls");
         let hls_manager = Arc::new(HlsManager::new(hls_dir));
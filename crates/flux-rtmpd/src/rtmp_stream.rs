@@ -4,8 +4,6 @@ use flux_stream::{Protocol, Stream, StreamMetadata, StreamStatus};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::stream_manager::StreamChannel;
-
 /// RTMP 流实现（实现 flux-stream 的 Stream trait）
 pub struct RtmpStream {
     stream_id: StreamId,
@@ -13,14 +11,14 @@ pub struct RtmpStream {
     stream_key: String,
     metadata: Arc<RwLock<StreamMetadata>>,
     status: Arc<RwLock<StreamStatus>>,
-    channel: Arc<StreamChannel>,
 }
 
 impl RtmpStream {
+    /// `metadata` 与发布端的 FLV 转换器共享，收到序列头后更新编码信息
     pub fn new(
         app_name: String,
         stream_key: String,
-        channel: Arc<StreamChannel>,
+        metadata: Arc<RwLock<StreamMetadata>>,
     ) -> Self {
         let stream_id = StreamId::new("rtmp", &format!("{}/{}", app_name, stream_key));
         
@@ -28,9 +26,8 @@ impl RtmpStream {
             stream_id,
             app_name,
             stream_key,
-            metadata: Arc::new(RwLock::new(StreamMetadata::default())),
-            status: Arc::new(RwLock::new(StreamStatus::Idle)),
-            channel,
+            metadata,
+            status: Arc::new(RwLock::new(StreamStatus::Running)),
         }
     }
 
@@ -42,10 +39,6 @@ impl RtmpStream {
         &self.stream_key
     }
 
-    pub fn channel(&self) -> &Arc<StreamChannel> {
-        &self.channel
-    }
-
     pub async fn update_metadata(&self, metadata: StreamMetadata) {
         let mut meta = self.metadata.write().await;
         *meta = metadata;
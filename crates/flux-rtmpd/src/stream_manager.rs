@@ -1,14 +1,21 @@
+use crate::rtmp_stream::RtmpStream;
+use crate::unified_bridge::FlvPacketConverter;
 use anyhow::Result;
 use bytes::Bytes;
+use flux_config::StreamMode;
+use flux_media_core::playback::FlvVideoTag;
 use flux_media_core::types::StreamId;
+use flux_stream::StreamMetadata;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
-use tracing::{debug, info};
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tracing::{debug, info, warn};
 
 /// 流管理器：管理所有活跃的 RTMP 流
 pub struct StreamManager {
     streams: Arc<RwLock<HashMap<String, StreamChannel>>>,
+    /// 统一流管理器：RTMP 发布同时注册到 flux-stream，供 WebRTC 等输出订阅
+    unified: Option<Arc<flux_stream::StreamManager>>,
 }
 
 /// 流通道：用于分发音视频数据到多个订阅者
@@ -19,6 +26,12 @@ pub struct StreamChannel {
     pub video_tx: broadcast::Sender<MediaPacket>,
     pub audio_tx: broadcast::Sender<MediaPacket>,
     pub subscriber_count: Arc<RwLock<usize>>,
+    /// 最近的视频序列头（AVC/HEVC sequence header），供中途加入的订阅者使用
+    pub video_sequence_header: Arc<RwLock<Option<MediaPacket>>>,
+    /// 最近的音频序列头（AAC AudioSpecificConfig）
+    pub audio_sequence_header: Arc<RwLock<Option<MediaPacket>>>,
+    /// FLV → 统一媒体包转换器（仅在接入统一流管理器时存在）
+    converter: Option<Arc<Mutex<FlvPacketConverter>>>,
 }
 
 /// 媒体数据包
@@ -33,9 +46,16 @@ impl StreamManager {
     pub fn new() -> Self {
         Self {
             streams: Arc::new(RwLock::new(HashMap::new())),
+            unified: None,
         }
     }
 
+    /// 接入统一流管理器
+    pub fn with_unified_stream_manager(mut self, unified: Arc<flux_stream::StreamManager>) -> Self {
+        self.unified = Some(unified);
+        self
+    }
+
     /// 注册新流（发布者）
     pub async fn register_stream(&self, app_name: String, stream_key: String) -> Result<()> {
         let stream_id = StreamId::new("rtmp", &format!("{}/{}", app_name, stream_key));
@@ -44,6 +64,18 @@ impl StreamManager {
         let (video_tx, _) = broadcast::channel(100);
         let (audio_tx, _) = broadcast::channel(100);
 
+        let converter = match &self.unified {
+            Some(unified) => {
+                let metadata = Arc::new(RwLock::new(StreamMetadata::default()));
+                let stream = RtmpStream::new(app_name.clone(), stream_key.clone(), metadata.clone());
+                unified
+                    .register_stream(Box::new(stream), StreamMode::Passthrough { remux: true })
+                    .await?;
+                Some(Arc::new(Mutex::new(FlvPacketConverter::new(metadata))))
+            }
+            None => None,
+        };
+
         let channel = StreamChannel {
             stream_id,
             app_name,
@@ -51,6 +83,9 @@ impl StreamManager {
             video_tx,
             audio_tx,
             subscriber_count: Arc::new(RwLock::new(0)),
+            video_sequence_header: Arc::new(RwLock::new(None)),
+            audio_sequence_header: Arc::new(RwLock::new(None)),
+            converter,
         };
 
         let mut streams = self.streams.write().await;
@@ -65,6 +100,12 @@ impl StreamManager {
         let key = format!("{}/{}", app_name, stream_key);
         let mut streams = self.streams.write().await;
         streams.remove(&key);
+        drop(streams);
+
+        if let Some(unified) = &self.unified {
+            let stream_id = StreamId::new("rtmp", &key);
+            unified.unregister_stream(&stream_id).await?;
+        }
 
         info!(target: "stream_manager", stream_key = %key, "Stream unregistered");
         Ok(())
@@ -89,6 +130,19 @@ impl StreamManager {
                 is_keyframe,
             };
 
+            if is_video_sequence_header(&packet.data) {
+                *channel.video_sequence_header.write().await = Some(packet.clone());
+            }
+
+            if let Some(converter) = &channel.converter {
+                let unified_packet = converter
+                    .lock()
+                    .await
+                    .convert_video(&packet.data, timestamp)
+                    .await;
+                self.publish_unified(&channel.stream_id, unified_packet).await;
+            }
+
             // 忽略发送错误（没有订阅者时会失败）
             let _ = channel.video_tx.send(packet);
             debug!(target: "stream_manager", stream_key = %key, "Video packet published");
@@ -115,6 +169,19 @@ impl StreamManager {
                 is_keyframe: false,
             };
 
+            if is_audio_sequence_header(&packet.data) {
+                *channel.audio_sequence_header.write().await = Some(packet.clone());
+            }
+
+            if let Some(converter) = &channel.converter {
+                let unified_packet = converter
+                    .lock()
+                    .await
+                    .convert_audio(&packet.data, timestamp)
+                    .await;
+                self.publish_unified(&channel.stream_id, unified_packet).await;
+            }
+
             let _ = channel.audio_tx.send(packet);
             debug!(target: "stream_manager", stream_key = %key, "Audio packet published");
         }
@@ -122,6 +189,15 @@ impl StreamManager {
         Ok(())
     }
 
+    async fn publish_unified(&self, stream_id: &StreamId, packet: Option<flux_stream::MediaPacket>) {
+        let (Some(unified), Some(packet)) = (&self.unified, packet) else {
+            return;
+        };
+        if let Err(e) = unified.publish_packet(stream_id, packet).await {
+            warn!(target: "stream_manager", stream_id = %stream_id, "Unified publish failed: {}", e);
+        }
+    }
+
     /// 订阅流（播放者）
     pub async fn subscribe(
         &self,
@@ -199,6 +275,34 @@ impl StreamManager {
         result
     }
 
+    /// 获取缓存的音视频序列头
+    pub async fn get_sequence_headers(
+        &self,
+        app_name: &str,
+        stream_key: &str,
+    ) -> (Option<MediaPacket>, Option<MediaPacket>) {
+        let key = format!("{}/{}", app_name, stream_key);
+        let streams = self.streams.read().await;
+
+        if let Some(channel) = streams.get(&key) {
+            let video = channel.video_sequence_header.read().await.clone();
+            let audio = channel.audio_sequence_header.read().await.clone();
+            (video, audio)
+        } else {
+            (None, None)
+        }
+    }
+
+    /// 当前仍持有视频接收端的订阅者数量
+    pub async fn receiver_count(&self, app_name: &str, stream_key: &str) -> usize {
+        let key = format!("{}/{}", app_name, stream_key);
+        let streams = self.streams.read().await;
+        streams
+            .get(&key)
+            .map(|channel| channel.video_tx.receiver_count())
+            .unwrap_or(0)
+    }
+
     /// 检查流是否存在
     pub async fn stream_exists(&self, app_name: &str, stream_key: &str) -> bool {
         let key = format!("{}/{}", app_name, stream_key);
@@ -207,6 +311,16 @@ impl StreamManager {
     }
 }
 
+/// FLV 视频 tag body 是否为序列头（AVC/HEVC，含 Enhanced RTMP）
+fn is_video_sequence_header(data: &[u8]) -> bool {
+    FlvVideoTag::parse(data).is_ok_and(|tag| tag.is_sequence_header())
+}
+
+/// FLV 音频 tag body 是否为 AAC sequence header
+fn is_audio_sequence_header(data: &[u8]) -> bool {
+    data.len() > 1 && (data[0] >> 4) == 10 && data[1] == 0
+}
+
 #[derive(Debug, Clone)]
 pub struct StreamInfo {
     pub stream_id: StreamId,
@@ -280,6 +394,74 @@ mod tests {
         assert!(!manager.stream_exists("live", "test").await);
     }
 
+    #[tokio::test]
+    async fn test_stream_manager_sequence_headers() {
+        let manager = StreamManager::new();
+        manager
+            .register_stream("live".to_string(), "test".to_string())
+            .await
+            .unwrap();
+
+        // AVC sequence header + AAC sequence header
+        manager
+            .publish_video("live", "test", Bytes::from(vec![0x17, 0x00, 0, 0, 0, 1]), 0, true)
+            .await
+            .unwrap();
+        manager
+            .publish_audio("live", "test", Bytes::from(vec![0xAF, 0x00, 0x12, 0x10]), 0)
+            .await
+            .unwrap();
+        // 普通帧不覆盖序列头
+        manager
+            .publish_video("live", "test", Bytes::from(vec![0x27, 0x01, 0, 0, 0]), 40, false)
+            .await
+            .unwrap();
+
+        let (video, audio) = manager.get_sequence_headers("live", "test").await;
+        assert_eq!(video.unwrap().data[1], 0x00);
+        assert_eq!(audio.unwrap().data[..2], [0xAF, 0x00]);
+
+        // Enhanced RTMP HEVC 序列头
+        let mut hevc_header = vec![0x90];
+        hevc_header.extend_from_slice(b"hvc1");
+        hevc_header.push(0x01);
+        manager
+            .publish_video("live", "test", Bytes::from(hevc_header), 0, true)
+            .await
+            .unwrap();
+        let (video, _) = manager.get_sequence_headers("live", "test").await;
+        assert_eq!(&video.unwrap().data[1..5], b"hvc1");
+    }
+
+    #[tokio::test]
+    async fn test_stream_manager_unified_bridge() {
+        let unified = Arc::new(flux_stream::StreamManager::new(
+            flux_config::StreamingConfig::default(),
+        ));
+        let manager = StreamManager::new().with_unified_stream_manager(unified.clone());
+        manager
+            .register_stream("live".to_string(), "test".to_string())
+            .await
+            .unwrap();
+
+        let stream_id = StreamId::new("rtmp", "live/test");
+        let mut rx = unified.subscribe(&stream_id).await.unwrap();
+
+        manager
+            .publish_audio("live", "test", Bytes::from(vec![0x72, 0x55, 0x55]), 20)
+            .await
+            .unwrap();
+
+        let packet = rx.recv().await.unwrap();
+        assert_eq!(packet.packet_type, flux_stream::PacketType::Audio);
+        assert_eq!(packet.data, Bytes::from(vec![0x55, 0x55]));
+        let metadata = unified.get_metadata(&stream_id).await.unwrap();
+        assert_eq!(metadata.audio_codec.as_deref(), Some("pcma"));
+
+        manager.unregister_stream("live", "test").await.unwrap();
+        assert_eq!(unified.stream_count().await, 0);
+    }
+
     #[tokio::test]
     async fn test_stream_manager_subscriber_count() {
         let manager = StreamManager::new();
@@ -0,0 +1,433 @@
+//! RTMP ↔ 统一流管理器（flux-stream）桥接
+//!
+//! 把 FLV 音视频 tag 转换为协议无关的 `MediaPacket`：视频为 Annex B 访问单元
+//! （关键帧前插入参数集），音频为去掉 tag 头的原始帧，供 WebRTC 等输出订阅。
+//! 反方向把 WebRTC（WHIP）推流转换回 FLV tag，与 RTMP 推流一样经 HTTP-FLV / HLS 输出。
+
+use crate::hls_manager::HlsManager;
+use crate::relay::RelayManager;
+use crate::stream_manager::StreamManager;
+use bytes::Bytes;
+use flux_media_core::codec::{
+    annexb_to_length_prefixed, build_avc_decoder_config, length_prefixed_to_annexb,
+    parse_avc_decoder_config, parse_avc_sps_dimensions, parse_hevc_decoder_config,
+    parse_hevc_sps_dimensions, split_annexb, AacConfig, ParameterSets, ANNEXB_START_CODE,
+};
+use flux_media_core::playback::{FlvVideoPacketType, FlvVideoTag};
+use flux_media_core::types::{StreamId, VideoCodec};
+use flux_stream::{MediaPacket, PacketType, StreamMetadata};
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use tracing::{info, warn};
+
+/// FLV SoundFormat
+const FLV_SOUND_G711A: u8 = 7;
+const FLV_SOUND_G711U: u8 = 8;
+const FLV_SOUND_AAC: u8 = 10;
+
+/// H.264 NALU 类型
+const NAL_TYPE_SPS: u8 = 7;
+const NAL_TYPE_PPS: u8 = 8;
+const NAL_TYPE_AUD: u8 = 9;
+
+/// FLV tag → MediaPacket 转换器（每路流一个）
+pub struct FlvPacketConverter {
+    video_config: Option<(VideoCodec, ParameterSets)>,
+    /// 与注册到 flux-stream 的 RtmpStream 共享
+    metadata: Arc<RwLock<StreamMetadata>>,
+}
+
+impl FlvPacketConverter {
+    pub fn new(metadata: Arc<RwLock<StreamMetadata>>) -> Self {
+        Self {
+            video_config: None,
+            metadata,
+        }
+    }
+
+    /// 转换视频 tag；序列头只更新参数集与元数据，不产出数据包
+    pub async fn convert_video(&mut self, data: &[u8], timestamp: u32) -> Option<MediaPacket> {
+        let tag = FlvVideoTag::parse(data).ok()?;
+
+        match tag.packet_type {
+            FlvVideoPacketType::SequenceHeader => {
+                self.update_video_config(&tag).await;
+                None
+            }
+            FlvVideoPacketType::CodedFrames => {
+                let (codec, sets) = self.video_config.as_ref()?;
+                if *codec != tag.codec {
+                    return None;
+                }
+
+                let mut access_unit = if tag.is_keyframe {
+                    sets.to_annexb()
+                } else {
+                    Vec::new()
+                };
+                access_unit.extend(length_prefixed_to_annexb(tag.payload, sets.nal_length_size).ok()?);
+
+                Some(MediaPacket {
+                    data: Bytes::from(access_unit),
+                    timestamp,
+                    is_keyframe: tag.is_keyframe,
+                    packet_type: PacketType::Video,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// 转换音频 tag（G.711 直通、AAC 原始帧）
+    pub async fn convert_audio(&mut self, data: &[u8], timestamp: u32) -> Option<MediaPacket> {
+        let sound_format = data.first()? >> 4;
+        let (codec, payload) = match sound_format {
+            FLV_SOUND_G711A => ("pcma", data.get(1..)?),
+            FLV_SOUND_G711U => ("pcmu", data.get(1..)?),
+            // AAC sequence header 只更新采样率与声道数，不产出数据包
+            FLV_SOUND_AAC if data.get(1) == Some(&0) => {
+                if let Ok(config) = AacConfig::parse(data.get(2..)?) {
+                    let mut metadata = self.metadata.write().await;
+                    metadata.audio_codec = Some("aac".to_string());
+                    metadata.audio_sample_rate = Some(config.sample_rate());
+                    metadata.audio_channels = Some(config.channel_count() as u8);
+                }
+                return None;
+            }
+            FLV_SOUND_AAC if data.get(1) == Some(&1) => ("aac", data.get(2..)?),
+            _ => return None,
+        };
+
+        if payload.is_empty() {
+            return None;
+        }
+
+        {
+            let mut metadata = self.metadata.write().await;
+            if metadata.audio_codec.as_deref() != Some(codec) {
+                metadata.audio_codec = Some(codec.to_string());
+                if codec != "aac" {
+                    metadata.audio_sample_rate = Some(8000);
+                    metadata.audio_channels = Some(1);
+                }
+            }
+        }
+
+        Some(MediaPacket {
+            data: Bytes::copy_from_slice(payload),
+            timestamp,
+            is_keyframe: false,
+            packet_type: PacketType::Audio,
+        })
+    }
+
+    async fn update_video_config(&mut self, tag: &FlvVideoTag<'_>) {
+        let sets = match tag.codec {
+            VideoCodec::H264 => parse_avc_decoder_config(tag.payload),
+            VideoCodec::H265 => parse_hevc_decoder_config(tag.payload),
+            _ => return,
+        };
+        let Ok(sets) = sets else {
+            return;
+        };
+
+        let dimensions = sets.sps.first().and_then(|sps| match tag.codec {
+            VideoCodec::H264 => parse_avc_sps_dimensions(sps).ok(),
+            _ => parse_hevc_sps_dimensions(sps).ok(),
+        });
+
+        let mut metadata = self.metadata.write().await;
+        metadata.video_codec = Some(tag.codec.as_str().to_string());
+        if let Some((width, height)) = dimensions {
+            metadata.width = Some(width);
+            metadata.height = Some(height);
+        }
+        drop(metadata);
+
+        self.video_config = Some((tag.codec, sets));
+    }
+}
+
+/// MediaPacket → FLV tag 转换器（每路流一个，仅支持 H.264 与 G.711）
+///
+/// 参数集首次出现或变化时先输出 AVC sequence header；Opus 等 FLV 不支持的音频丢弃。
+pub struct MediaPacketFlvConverter {
+    parameter_sets: Option<(Vec<u8>, Vec<u8>)>,
+    sound_flags: Option<u8>,
+}
+
+impl MediaPacketFlvConverter {
+    pub fn new(audio_codec: Option<&str>) -> Self {
+        // SoundFormat | 5.5kHz | 16bit | mono（G.711 的采样率位被忽略）
+        let sound_flags = match audio_codec {
+            Some("pcma") => Some(FLV_SOUND_G711A << 4 | 0x02),
+            Some("pcmu") => Some(FLV_SOUND_G711U << 4 | 0x02),
+            _ => None,
+        };
+        Self {
+            parameter_sets: None,
+            sound_flags,
+        }
+    }
+
+    /// 转换 Annex B 访问单元，返回待发布的视频 tag body（可能含 sequence header）
+    pub fn convert_video(&mut self, packet: &MediaPacket) -> Vec<Bytes> {
+        let mut tags = Vec::with_capacity(2);
+        let (mut sps, mut pps) = (None, None);
+        let mut frame = Vec::with_capacity(packet.data.len() + 8);
+
+        for nalu in split_annexb(&packet.data) {
+            match nalu.first().map(|header| header & 0x1F) {
+                Some(NAL_TYPE_SPS) => sps = Some(nalu),
+                Some(NAL_TYPE_PPS) => pps = Some(nalu),
+                Some(NAL_TYPE_AUD) | None => {}
+                Some(_) => {
+                    frame.extend_from_slice(&ANNEXB_START_CODE);
+                    frame.extend_from_slice(nalu);
+                }
+            }
+        }
+
+        if let (Some(sps), Some(pps)) = (sps, pps) {
+            let changed = self
+                .parameter_sets
+                .as_ref()
+                .is_none_or(|(cached_sps, cached_pps)| cached_sps != sps || cached_pps != pps);
+            if changed {
+                if let Ok(config) = build_avc_decoder_config(sps, pps) {
+                    let mut tag = vec![0x17, 0x00, 0, 0, 0];
+                    tag.extend(config);
+                    tags.push(Bytes::from(tag));
+                    self.parameter_sets = Some((sps.to_vec(), pps.to_vec()));
+                }
+            }
+        }
+
+        // 序列头之前的帧无法解码
+        if self.parameter_sets.is_none() || frame.is_empty() {
+            return tags;
+        }
+
+        let frame_type = if packet.is_keyframe { 0x17 } else { 0x27 };
+        let mut tag = vec![frame_type, 0x01, 0, 0, 0];
+        tag.extend(annexb_to_length_prefixed(&frame));
+        tags.push(Bytes::from(tag));
+        tags
+    }
+
+    /// 转换音频帧，返回音频 tag body
+    pub fn convert_audio(&self, packet: &MediaPacket) -> Option<Bytes> {
+        let flags = self.sound_flags?;
+        let mut tag = Vec::with_capacity(packet.data.len() + 1);
+        tag.push(flags);
+        tag.extend_from_slice(&packet.data);
+        Some(Bytes::from(tag))
+    }
+}
+
+/// 把 WebRTC 推流接入 RTMP 流管理器与 HLS：每个新推流注册为同名 `app/stream`，
+/// 转发到其结束。同名 RTMP 流已存在时不接管。
+pub fn spawn_webrtc_ingest_bridge(
+    mut published: broadcast::Receiver<StreamId>,
+    unified: Arc<flux_stream::StreamManager>,
+    stream_manager: Arc<StreamManager>,
+    hls_manager: Arc<HlsManager>,
+    relay_manager: Arc<RelayManager>,
+) {
+    tokio::spawn(async move {
+        loop {
+            let stream_id = match published.recv().await {
+                Ok(stream_id) => stream_id,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            tokio::spawn(forward_webrtc_stream(
+                stream_id,
+                unified.clone(),
+                stream_manager.clone(),
+                hls_manager.clone(),
+                relay_manager.clone(),
+            ));
+        }
+    });
+}
+
+async fn forward_webrtc_stream(
+    stream_id: StreamId,
+    unified: Arc<flux_stream::StreamManager>,
+    stream_manager: Arc<StreamManager>,
+    hls_manager: Arc<HlsManager>,
+    relay_manager: Arc<RelayManager>,
+) {
+    let Some((app_name, stream_key)) = stream_id.identifier().and_then(|id| id.split_once('/')) else {
+        return;
+    };
+    let (app_name, stream_key) = (app_name.to_string(), stream_key.to_string());
+
+    if stream_manager.stream_exists(&app_name, &stream_key).await {
+        warn!(target: "rtmpd", stream_id = %stream_id, "RTMP stream with the same name exists, WebRTC publish not bridged");
+        return;
+    }
+    let (Some(metadata), Ok(mut receiver)) = (
+        unified.get_metadata(&stream_id).await,
+        unified.subscribe(&stream_id).await,
+    ) else {
+        return;
+    };
+
+    if let Err(e) = stream_manager
+        .register_stream(app_name.clone(), stream_key.clone())
+        .await
+    {
+        warn!(target: "rtmpd", stream_id = %stream_id, "Failed to register WebRTC stream: {}", e);
+        return;
+    }
+    if let Err(e) = hls_manager
+        .register_stream(app_name.clone(), stream_key.clone(), 6)
+        .await
+    {
+        warn!(target: "rtmpd", stream_id = %stream_id, "Failed to register WebRTC HLS stream: {}", e);
+    }
+    relay_manager.on_publish(&app_name, &stream_key).await;
+    info!(target: "rtmpd", stream_id = %stream_id, "WebRTC publish bridged to RTMP/HLS");
+
+    let mut converter = MediaPacketFlvConverter::new(metadata.audio_codec.as_deref());
+    loop {
+        let packet = match receiver.recv().await {
+            Ok(packet) => packet,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!(target: "rtmpd", stream_id = %stream_id, skipped, "WebRTC bridge lagged");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        match packet.packet_type {
+            PacketType::Video => {
+                for data in converter.convert_video(&packet) {
+                    let is_keyframe = FlvVideoTag::parse(&data).is_ok_and(|tag| tag.is_keyframe);
+                    let _ = stream_manager
+                        .publish_video(&app_name, &stream_key, data.clone(), packet.timestamp, is_keyframe)
+                        .await;
+                    let _ = hls_manager
+                        .process_video(&app_name, &stream_key, &data, packet.timestamp, is_keyframe)
+                        .await;
+                }
+            }
+            PacketType::Audio => {
+                if let Some(data) = converter.convert_audio(&packet) {
+                    let _ = stream_manager
+                        .publish_audio(&app_name, &stream_key, data.clone(), packet.timestamp)
+                        .await;
+                    let _ = hls_manager
+                        .process_audio(&app_name, &stream_key, &data, packet.timestamp)
+                        .await;
+                }
+            }
+        }
+    }
+
+    relay_manager.on_unpublish(&app_name, &stream_key).await;
+    let _ = stream_manager.unregister_stream(&app_name, &stream_key).await;
+    let _ = hls_manager.unregister_stream(&app_name, &stream_key).await;
+    info!(target: "rtmpd", stream_id = %stream_id, "WebRTC publish ended");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_convert_avc_and_g711() {
+        let metadata = Arc::new(RwLock::new(StreamMetadata::default()));
+        let mut converter = FlvPacketConverter::new(metadata.clone());
+
+        // 序列头之前的帧被丢弃
+        assert!(converter
+            .convert_video(&[0x17, 0x01, 0, 0, 0, 0, 0, 0, 1, 0x65], 0)
+            .await
+            .is_none());
+
+        // AVCDecoderConfigurationRecord：SPS = [0x67, 0x42]，PPS = [0x68]
+        let header = [
+            0x17, 0x00, 0, 0, 0, 0x01, 0x42, 0x00, 0x1E, 0xFF, 0xE1, 0x00, 0x02, 0x67, 0x42, 0x01,
+            0x00, 0x01, 0x68,
+        ];
+        assert!(converter.convert_video(&header, 0).await.is_none());
+        assert_eq!(metadata.read().await.video_codec.as_deref(), Some("h264"));
+
+        let packet = converter
+            .convert_video(&[0x17, 0x01, 0, 0, 0, 0, 0, 0, 2, 0x65, 0x88], 40)
+            .await
+            .unwrap();
+        assert!(packet.is_keyframe);
+        assert_eq!(packet.packet_type, PacketType::Video);
+        assert_eq!(
+            packet.data.as_ref(),
+            &[0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x68, 0, 0, 0, 1, 0x65, 0x88]
+        );
+
+        let packet = converter
+            .convert_video(&[0x27, 0x01, 0, 0, 0, 0, 0, 0, 1, 0x41], 80)
+            .await
+            .unwrap();
+        assert_eq!(packet.data.as_ref(), &[0, 0, 0, 1, 0x41]);
+
+        let audio = converter.convert_audio(&[0x82, 0xD5, 0xD5], 40).await.unwrap();
+        assert_eq!(audio.data.as_ref(), &[0xD5, 0xD5]);
+        assert_eq!(metadata.read().await.audio_codec.as_deref(), Some("pcmu"));
+
+        // AAC sequence header 不产出数据包，只更新采样率与声道数
+        assert!(converter.convert_audio(&[0xAF, 0x00, 0x12, 0x10], 0).await.is_none());
+        let snapshot = metadata.read().await.clone();
+        assert_eq!(snapshot.audio_codec.as_deref(), Some("aac"));
+        assert_eq!((snapshot.audio_sample_rate, snapshot.audio_channels), (Some(44100), Some(2)));
+    }
+
+    #[tokio::test]
+    async fn test_media_packet_to_flv_roundtrip() {
+        let mut converter = MediaPacketFlvConverter::new(Some("pcma"));
+
+        let packet = |data: &[u8], timestamp, is_keyframe| MediaPacket {
+            data: Bytes::copy_from_slice(data),
+            timestamp,
+            is_keyframe,
+            packet_type: PacketType::Video,
+        };
+
+        // 序列头之前的 P 帧被丢弃
+        assert!(converter.convert_video(&packet(&[0, 0, 0, 1, 0x41, 0x01], 0, false)).is_empty());
+
+        let keyframe = [
+            0, 0, 0, 1, 0x09, 0xF0, 0, 0, 0, 1, 0x67, 0x42, 0xE0, 0x1F, 0, 0, 0, 1, 0x68, 0xCE, 0, 0, 0, 1,
+            0x65, 0x88,
+        ];
+        let tags = converter.convert_video(&packet(&keyframe, 40, true));
+        assert_eq!(tags.len(), 2);
+        assert_eq!(&tags[1][..], &[0x17, 0x01, 0, 0, 0, 0, 0, 0, 2, 0x65, 0x88]);
+
+        // 转换回 MediaPacket：参数集与帧数据保持一致（AUD 被去掉）
+        let metadata = Arc::new(RwLock::new(StreamMetadata::default()));
+        let mut reverse = FlvPacketConverter::new(metadata.clone());
+        assert!(reverse.convert_video(&tags[0], 40).await.is_none());
+        let restored = reverse.convert_video(&tags[1], 40).await.unwrap();
+        assert_eq!(restored.data.as_ref(), &keyframe[6..]);
+        assert_eq!(metadata.read().await.video_codec.as_deref(), Some("h264"));
+
+        // 参数集不变时不重复输出序列头
+        assert_eq!(converter.convert_video(&packet(&keyframe, 80, true)).len(), 1);
+        let tags = converter.convert_video(&packet(&[0, 0, 0, 1, 0x41, 0x02], 120, false));
+        assert_eq!(&tags[0][..2], &[0x27, 0x01]);
+
+        let audio = MediaPacket {
+            data: Bytes::from_static(&[0xD5, 0xD5]),
+            timestamp: 40,
+            is_keyframe: false,
+            packet_type: PacketType::Audio,
+        };
+        let tag = converter.convert_audio(&audio).unwrap();
+        assert_eq!(&tag[..], &[0x72, 0xD5, 0xD5]);
+        assert_eq!(reverse.convert_audio(&tag, 40).await.unwrap().data.as_ref(), &[0xD5, 0xD5]);
+        assert!(MediaPacketFlvConverter::new(Some("opus")).convert_audio(&audio).is_none());
+    }
+}
@@ -170,8 +170,8 @@ impl H265Depacketizer {
         let first_packet = &self.buffer[0];
         let payload_header = u16::from_be_bytes([first_packet.payload[0], first_packet.payload[1]]);
         
-        // 替换 Type 字段为 FuType
-        let new_payload_header = (payload_header & 0xFE00) | ((fu_type as u16) << 9);
+        // 替换 Type 字段为 FuType（保留 F、LayerId 与 TID）
+        let new_payload_header = (payload_header & 0x81FF) | ((fu_type as u16) << 9);
         data.extend_from_slice(&new_payload_header.to_be_bytes());
         
         // 组装所有分片的 payload
@@ -4,8 +4,11 @@ pub mod h264_depacketizer;
 pub mod h265_depacketizer;
 pub mod multicast_receiver;
 pub mod rtcp_receiver;
+pub mod rtp_packetizer;
 pub mod rtp_receiver;
+pub mod rtsp_auth;
 pub mod rtsp_client;
+pub mod rtsp_server;
 pub mod sdp_parser;
 pub mod stream_manager;
 pub mod telemetry;
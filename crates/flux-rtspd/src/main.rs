@@ -4,6 +4,7 @@ mod h265_depacketizer;
 mod multicast_receiver;
 mod rtcp_receiver;
 mod rtp_receiver;
+mod rtsp_auth;
 mod rtsp_client;
 mod sdp_parser;
 mod stream_manager;
@@ -18,7 +19,7 @@ use axum::{
 };
 use clap::Parser;
 use flux_media_core::{
-    snapshot::{SnapshotMode, SnapshotOrchestrator, SnapshotRequest},
+    snapshot::{SnapshotMode, SnapshotOrchestrator, SnapshotQuery, SoftwareDecoder},
     storage::{filesystem::FileSystemStorage, StorageConfig},
     types::StreamId,
 };
@@ -26,6 +27,7 @@ use flux_storage::{DiskType, PoolConfig, StorageManager};
 use std::{collections::HashMap, path::PathBuf, sync::Arc};
 use tokio::sync::RwLock;
 use tracing::{error, info};
+use rtsp_auth::RtspCredentials;
 use telemetry::TelemetryClient;
 
 #[derive(Parser, Debug)]
@@ -72,6 +74,10 @@ async fn list_streams(State(state): State<AppState>) -> impl IntoResponse {
                 "url": info.url,
                 "start_time": info.start_time.to_rfc3339(),
                 "is_active": info.is_active,
+                "state": info.state,
+                "reconnect_attempts": info.reconnect_attempts,
+                "last_error": info.last_error,
+                "next_retry_at": info.next_retry_at.map(|t| t.to_rfc3339()),
                 "frame_count": info.frame_count,
                 "last_keyframe_time": info.last_keyframe_time.map(|t| t.to_rfc3339()),
             })
@@ -84,14 +90,10 @@ async fn list_streams(State(state): State<AppState>) -> impl IntoResponse {
 async fn snapshot(
     State(state): State<AppState>,
     Path(stream_id): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<SnapshotQuery>,
 ) -> std::result::Result<Response, StatusCode> {
     let media_stream_id = StreamId::from_string(stream_id);
-    let req = SnapshotRequest {
-        stream_id: media_stream_id,
-        mode: SnapshotMode::Auto,
-        width: None,
-        height: None,
-    };
+    let req = query.into_request(media_stream_id, SnapshotMode::Auto);
 
     let snapshot = state
         .orchestrator
@@ -99,10 +101,11 @@ async fn snapshot(
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
+    let content_type = snapshot.content_type();
     let mut resp: Response = snapshot.data.into_response();
     resp.headers_mut().insert(
         axum::http::header::CONTENT_TYPE,
-        axum::http::HeaderValue::from_static("application/octet-stream"),
+        axum::http::HeaderValue::from_static(content_type),
     );
     Ok(resp)
 }
@@ -110,20 +113,35 @@ async fn snapshot(
 #[derive(serde::Deserialize)]
 struct StartStreamRequest {
     url: String,
+    /// 未指定时使用 URL 中的 user:pass@
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    /// RTSPS 跳过证书校验（自签名证书的摄像机）
+    #[serde(default)]
+    insecure_tls: bool,
 }
 
 async fn start_stream(
     State(state): State<AppState>,
     Json(req): Json<StartStreamRequest>,
 ) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
+    let options = stream_manager::RtspSourceOptions {
+        credentials: req
+            .username
+            .map(|username| RtspCredentials::new(username, req.password.unwrap_or_default())),
+        tls_verify: !req.insecure_tls,
+    };
+    let (url, _) = rtsp_client::split_credentials(&req.url);
     state.stream_manager
-        .start_stream(req.url.clone())
+        .start_stream_with_options(req.url, options)
         .await
         .map_err(|_| StatusCode::BAD_REQUEST)?;
     
     Ok(Json(serde_json::json!({
         "status": "started",
-        "url": req.url
+        "url": url
     })))
 }
 
@@ -220,9 +238,10 @@ async fn main() -> anyhow::Result<()> {
         segment_duration_secs: 60,
     };
     let storage = Arc::new(RwLock::new(FileSystemStorage::new(storage_config)?));
-    let orchestrator = Arc::new(SnapshotOrchestrator::new(PathBuf::from(
-        &args.keyframe_dir,
-    )));
+    let orchestrator = Arc::new(
+        SnapshotOrchestrator::new(PathBuf::from(&args.keyframe_dir))
+            .with_decoder(Arc::new(SoftwareDecoder::default())),
+    );
 
     // 创建时移核心
     let timeshift = if timeshift_config.enabled {
@@ -0,0 +1,240 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use flux_media_core::codec::split_annexb;
+
+use crate::rtp_receiver::RtpPacket;
+
+/// 默认 RTP 负载上限（UDP 单播 / 多播 / TCP interleaved 通用）
+pub const DEFAULT_MTU: usize = 1400;
+
+/// H.264 FU-A / H.265 FU NALU 类型
+const H264_NAL_TYPE_FU_A: u8 = 28;
+const H265_NAL_TYPE_FU: u8 = 49;
+
+/// RTP 负载格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadFormat {
+    /// RFC 6184：单 NALU + FU-A
+    H264,
+    /// RFC 7798：单 NALU + FU
+    H265,
+    /// RFC 3640 AAC-hbr：每包一个 AU，超过 MTU 时分片
+    Aac,
+    /// 整帧作为负载（G.711、Opus）
+    Frame,
+}
+
+/// RTP 打包器（每条轨道一个，维护序号与时钟换算）
+pub struct RtpPacketizer {
+    format: PayloadFormat,
+    payload_type: u8,
+    ssrc: u32,
+    clock_rate: u32,
+    sequence_number: u16,
+    mtu: usize,
+}
+
+impl RtpPacketizer {
+    pub fn new(format: PayloadFormat, payload_type: u8, ssrc: u32, clock_rate: u32) -> Self {
+        Self {
+            format,
+            payload_type,
+            ssrc,
+            clock_rate,
+            sequence_number: rand::random(),
+            mtu: DEFAULT_MTU,
+        }
+    }
+
+    pub fn with_mtu(mut self, mtu: usize) -> Self {
+        self.mtu = mtu.max(16);
+        self
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    pub fn clock_rate(&self) -> u32 {
+        self.clock_rate
+    }
+
+    /// 下一个包的序号（PLAY 响应 RTP-Info 使用）
+    pub fn sequence_number(&self) -> u16 {
+        self.sequence_number
+    }
+
+    /// 毫秒时间戳 → RTP 时间戳
+    pub fn rtp_timestamp(&self, timestamp_ms: u32) -> u32 {
+        (timestamp_ms as u64 * self.clock_rate as u64 / 1000) as u32
+    }
+
+    /// 打包一帧：视频为 Annex B 访问单元（最后一个包置 marker 位），音频为单个编码帧
+    pub fn packetize(&mut self, data: &[u8], timestamp_ms: u32) -> Vec<RtpPacket> {
+        let payloads = match self.format {
+            PayloadFormat::H264 => self.fragment_nalus(data, 1, |header| {
+                let indicator = (header[0] & 0xE0) | H264_NAL_TYPE_FU_A;
+                (vec![indicator], header[0] & 0x1F)
+            }),
+            PayloadFormat::H265 => self.fragment_nalus(data, 2, |header| {
+                let indicator = (header[0] & 0x81) | (H265_NAL_TYPE_FU << 1);
+                (vec![indicator, header[1]], (header[0] >> 1) & 0x3F)
+            }),
+            PayloadFormat::Aac => self.fragment_aac(data),
+            PayloadFormat::Frame => vec![Bytes::copy_from_slice(data)],
+        };
+
+        let timestamp = self.rtp_timestamp(timestamp_ms);
+        let count = payloads.len();
+        payloads
+            .into_iter()
+            .enumerate()
+            .map(|(index, payload)| {
+                let sequence_number = self.sequence_number;
+                self.sequence_number = self.sequence_number.wrapping_add(1);
+                RtpPacket {
+                    version: 2,
+                    padding: false,
+                    extension: false,
+                    csrc_count: 0,
+                    // 视频标记访问单元结束，AAC 标记 AU 最后一个分片
+                    marker: self.format != PayloadFormat::Frame && index + 1 == count,
+                    payload_type: self.payload_type,
+                    sequence_number,
+                    timestamp,
+                    ssrc: self.ssrc,
+                    payload,
+                }
+            })
+            .collect()
+    }
+
+    /// 按 MTU 拆分 NALU；`fu_header` 由原 NALU 头生成 FU 负载头与原 NALU 类型
+    fn fragment_nalus(
+        &self,
+        access_unit: &[u8],
+        header_len: usize,
+        fu_header: impl Fn(&[u8]) -> (Vec<u8>, u8),
+    ) -> Vec<Bytes> {
+        let mut payloads = Vec::new();
+
+        for nalu in split_annexb(access_unit) {
+            if nalu.len() <= header_len {
+                continue;
+            }
+            if nalu.len() <= self.mtu {
+                payloads.push(Bytes::copy_from_slice(nalu));
+                continue;
+            }
+
+            let (indicator, nal_type) = fu_header(&nalu[..header_len]);
+            let chunk_size = self.mtu - indicator.len() - 1;
+            let body = &nalu[header_len..];
+            let chunks = body.len().div_ceil(chunk_size);
+            for (index, chunk) in body.chunks(chunk_size).enumerate() {
+                let mut flags = nal_type;
+                if index == 0 {
+                    flags |= 0x80;
+                }
+                if index + 1 == chunks {
+                    flags |= 0x40;
+                }
+                let mut payload = BytesMut::with_capacity(indicator.len() + 1 + chunk.len());
+                payload.extend_from_slice(&indicator);
+                payload.put_u8(flags);
+                payload.extend_from_slice(chunk);
+                payloads.push(payload.freeze());
+            }
+        }
+
+        payloads
+    }
+
+    /// AU-headers-length(16) + AU-header(AU-size 13 位，AU-Index 3 位)；分片的 AU-size 为整帧长度
+    fn fragment_aac(&self, frame: &[u8]) -> Vec<Bytes> {
+        let au_header = ((frame.len() as u16) << 3).to_be_bytes();
+        frame
+            .chunks(self.mtu - 4)
+            .map(|chunk| {
+                let mut payload = BytesMut::with_capacity(4 + chunk.len());
+                payload.put_u16(16);
+                payload.extend_from_slice(&au_header);
+                payload.extend_from_slice(chunk);
+                payload.freeze()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aac_depacketizer::AacDepacketizer;
+    use crate::h264_depacketizer::H264Depacketizer;
+    use crate::h265_depacketizer::H265Depacketizer;
+
+    #[test]
+    fn test_packetize_h264_fu_a() {
+        let mut packetizer = RtpPacketizer::new(PayloadFormat::H264, 96, 0x1234, 90000).with_mtu(100);
+        let mut access_unit = vec![0, 0, 0, 1, 0x67, 0x42, 0xE0, 0x1F, 0, 0, 0, 1, 0x68, 0xCE, 0, 0, 0, 1, 0x65];
+        access_unit.extend((0..250).map(|i| i as u8));
+
+        let packets = packetizer.packetize(&access_unit, 1000);
+        assert_eq!(packets.len(), 5);
+        assert!(packets.iter().all(|packet| packet.timestamp == 90000));
+        assert!(packets[4].marker && !packets[3].marker);
+        assert_eq!(packets[2].payload[0], 0x60 | 28);
+        assert_eq!(packets[2].payload[1], 0x80 | 5);
+        assert_eq!(
+            packets[1].sequence_number.wrapping_add(1),
+            packets[2].sequence_number
+        );
+
+        let mut depacketizer = H264Depacketizer::new();
+        let nalus: Vec<_> = packets
+            .into_iter()
+            .flat_map(|packet| depacketizer.process_rtp(packet).unwrap())
+            .collect();
+        assert_eq!(nalus.len(), 3);
+        assert_eq!(nalus[2].data.as_ref(), &access_unit[18..]);
+    }
+
+    #[test]
+    fn test_packetize_h265_fu() {
+        let mut packetizer = RtpPacketizer::new(PayloadFormat::H265, 96, 1, 90000).with_mtu(64);
+        let mut access_unit = vec![0, 0, 0, 1, 0x26, 0x01];
+        access_unit.extend(std::iter::repeat_n(0xAB, 150));
+
+        let packets = packetizer.packetize(&access_unit, 40);
+        assert_eq!(packets.len(), 3);
+        // FU 负载头类型 49，FU 头携带 IDR_W_RADL(19)
+        assert_eq!((packets[0].payload[0] >> 1) & 0x3F, 49);
+        assert_eq!(packets[0].payload[2], 0x80 | 19);
+        assert_eq!(packets[2].payload[2], 0x40 | 19);
+
+        let mut depacketizer = H265Depacketizer::new();
+        let nalus: Vec<_> = packets
+            .into_iter()
+            .flat_map(|packet| depacketizer.process_rtp(packet).unwrap())
+            .collect();
+        assert_eq!(nalus.len(), 1);
+        assert_eq!(nalus[0].data.as_ref(), &access_unit[4..]);
+    }
+
+    #[test]
+    fn test_packetize_aac_and_frame() {
+        let mut packetizer = RtpPacketizer::new(PayloadFormat::Aac, 97, 2, 44100);
+        let packets = packetizer.packetize(&[0x21, 0x10, 0x05], 1000);
+        assert_eq!(packets.len(), 1);
+        assert!(packets[0].marker);
+        assert_eq!(packets[0].timestamp, 44100);
+
+        let frames = AacDepacketizer::new().process_rtp(packets[0].clone()).unwrap();
+        assert_eq!(frames[0].data.as_ref(), &[0x21, 0x10, 0x05]);
+
+        let mut packetizer = RtpPacketizer::new(PayloadFormat::Frame, 8, 3, 8000);
+        let packets = packetizer.packetize(&[0xD5; 160], 20);
+        assert_eq!(packets.len(), 1);
+        assert!(!packets[0].marker);
+        assert_eq!(packets[0].timestamp, 160);
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::net::SocketAddr;
 use tokio::net::UdpSocket;
 use tokio::sync::mpsc;
@@ -20,6 +20,20 @@ pub struct RtpPacket {
     pub payload: Bytes,
 }
 
+impl RtpPacket {
+    /// 序列化为 RTP 包（不含 CSRC / 扩展头）
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(12 + self.payload.len());
+        buf.put_u8(0x80);
+        buf.put_u8(((self.marker as u8) << 7) | (self.payload_type & 0x7F));
+        buf.put_u16(self.sequence_number);
+        buf.put_u32(self.timestamp);
+        buf.put_u32(self.ssrc);
+        buf.extend_from_slice(&self.payload);
+        buf.freeze()
+    }
+}
+
 /// RTP 接收器
 pub struct RtpReceiver {
     socket: UdpSocket,
@@ -197,5 +211,12 @@ mod tests {
         
         assert!(packet.marker);
         assert_eq!(packet.payload_type, 96);
+
+        // 序列化后重新解析
+        let reparsed = RtpReceiver::parse_rtp_packet(&packet.to_bytes()).unwrap();
+        assert!(reparsed.marker);
+        assert_eq!(reparsed.sequence_number, 2);
+        assert_eq!(reparsed.ssrc, 0xABCDEF00);
+        assert_eq!(reparsed.payload.as_ref(), b"test");
     }
 }
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+/// RTSP 认证凭据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtspCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl RtspCredentials {
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+}
+
+/// 解析 `Digest realm="x", nonce="y"` 形式的参数列表（键名转为小写）
+pub fn parse_auth_params(value: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let mut rest = value.trim();
+
+    while !rest.is_empty() {
+        let Some((key, after)) = rest.split_once('=') else {
+            break;
+        };
+        let key = key.trim().trim_start_matches(',').trim().to_ascii_lowercase();
+        let after = after.trim_start();
+
+        let (value, remaining) = if let Some(quoted) = after.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            }
+        } else {
+            match after.find(',') {
+                Some(end) => (after[..end].trim(), &after[end..]),
+                None => (after.trim(), ""),
+            }
+        };
+
+        params.insert(key, value.to_string());
+        rest = remaining.trim_start().trim_start_matches(',').trim_start();
+    }
+
+    params
+}
+
+/// RFC 2617 digest response；qop 为 auth 时需提供 nc 与 cnonce
+#[allow(clippy::too_many_arguments)]
+pub fn digest_response(
+    credentials: &RtspCredentials,
+    realm: &str,
+    nonce: &str,
+    method: &str,
+    uri: &str,
+    qop: Option<&str>,
+    nc: &str,
+    cnonce: &str,
+) -> String {
+    let ha1 = md5_hex(&format!("{}:{}:{}", credentials.username, realm, credentials.password));
+    let ha2 = md5_hex(&format!("{}:{}", method, uri));
+    match qop {
+        Some(qop) => md5_hex(&format!("{}:{}:{}:{}:{}:{}", ha1, nonce, nc, cnonce, qop, ha2)),
+        None => md5_hex(&format!("{}:{}:{}", ha1, nonce, ha2)),
+    }
+}
+
+fn md5_hex(input: &str) -> String {
+    format!("{:x}", md5::compute(input.as_bytes()))
+}
+
+/// 服务端 Digest 认证（每个连接一个，nonce 在首次质询时生成）
+pub struct DigestAuthenticator {
+    credentials: RtspCredentials,
+    realm: String,
+    nonce: String,
+}
+
+impl DigestAuthenticator {
+    pub fn new(credentials: RtspCredentials, realm: impl Into<String>) -> Self {
+        Self {
+            credentials,
+            realm: realm.into(),
+            nonce: hex::encode(rand::random::<[u8; 16]>()),
+        }
+    }
+
+    /// 401 响应的 WWW-Authenticate 头
+    pub fn challenge(&self) -> String {
+        format!("Digest realm=\"{}\", nonce=\"{}\"", self.realm, self.nonce)
+    }
+
+    /// 校验请求的 Authorization 头
+    pub fn verify(&self, method: &str, authorization: Option<&str>) -> bool {
+        let Some(params) = authorization
+            .and_then(|value| value.trim().strip_prefix("Digest "))
+            .map(parse_auth_params)
+        else {
+            return false;
+        };
+
+        let field = |name: &str| params.get(name).map(String::as_str);
+        if field("username") != Some(self.credentials.username.as_str())
+            || field("realm") != Some(self.realm.as_str())
+            || field("nonce") != Some(self.nonce.as_str())
+        {
+            return false;
+        }
+        let (Some(uri), Some(response)) = (field("uri"), field("response")) else {
+            return false;
+        };
+
+        let expected = digest_response(
+            &self.credentials,
+            &self.realm,
+            &self.nonce,
+            method,
+            uri,
+            field("qop"),
+            field("nc").unwrap_or_default(),
+            field("cnonce").unwrap_or_default(),
+        );
+        expected.eq_ignore_ascii_case(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_auth_params() {
+        let params = parse_auth_params(r#"realm="IP Camera(12345)", nonce="abc,def", stale=FALSE, qop="auth""#);
+        assert_eq!(params["realm"], "IP Camera(12345)");
+        assert_eq!(params["nonce"], "abc,def");
+        assert_eq!(params["stale"], "FALSE");
+        assert_eq!(params["qop"], "auth");
+    }
+
+    #[test]
+    fn test_digest_response_rfc2617() {
+        // RFC 2617 3.5 示例
+        let credentials = RtspCredentials::new("Mufasa", "Circle Of Life");
+        let response = digest_response(
+            &credentials,
+            "testrealm@host.com",
+            "dcd98b7102dd2f0e8b11d0f600bfb0c093",
+            "GET",
+            "/dir/index.html",
+            Some("auth"),
+            "00000001",
+            "0a4f113b",
+        );
+        assert_eq!(response, "6629fae49393a05397450978507c4ef1");
+    }
+
+    #[test]
+    fn test_authenticator_verify() {
+        let credentials = RtspCredentials::new("admin", "secret");
+        let authenticator = DigestAuthenticator::new(credentials.clone(), "flux");
+        let params = parse_auth_params(authenticator.challenge().strip_prefix("Digest ").unwrap());
+        let nonce = &params["nonce"];
+
+        let uri = "rtsp://127.0.0.1/rtmp/live/cam1";
+        let response = digest_response(&credentials, "flux", nonce, "DESCRIBE", uri, None, "", "");
+        let header = format!(
+            r#"Digest username="admin", realm="flux", nonce="{}", uri="{}", response="{}""#,
+            nonce, uri, response
+        );
+        assert!(authenticator.verify("DESCRIBE", Some(&header)));
+        assert!(!authenticator.verify("SETUP", Some(&header)));
+        assert!(!authenticator.verify("DESCRIBE", None));
+    }
+}
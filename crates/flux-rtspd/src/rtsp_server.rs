@@ -0,0 +1,1272 @@
+//! RTSP 服务端：把 flux-stream 中注册的任意流（RTMP、WebRTC、SRT、GB28181 等）以 RTSP 输出
+//!
+//! URL 路径即流 ID（如 `rtsp://host:8554/rtmp/live/cam1`），轨道控制 URL 为 `trackID=0`（视频）
+//! 与 `trackID=1`（音频）。传输支持 UDP 单播、TCP interleaved 与 UDP 多播，
+//! 同一流的多播客户端共享一个发送任务。
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use bytes::{BufMut, Bytes, BytesMut};
+use flux_media_core::codec::{split_annexb, AacConfig};
+use flux_media_core::types::StreamId;
+use flux_stream::{MediaPacket, PacketType, StreamManager, StreamMetadata};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::rtp_packetizer::{PayloadFormat, RtpPacketizer};
+use crate::rtsp_auth::{DigestAuthenticator, RtspCredentials};
+
+/// 视频 / 音频轨道的 trackID
+const VIDEO_TRACK_ID: u8 = 0;
+const AUDIO_TRACK_ID: u8 = 1;
+
+/// 连接写队列容量（RTSP 响应与 interleaved 数据共用）
+const WRITE_QUEUE_CAPACITY: usize = 1024;
+
+/// RTCP 发送报告间隔
+const SENDER_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 会话超时检查间隔
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 请求头部上限
+const MAX_HEADER_LINES: usize = 64;
+
+/// NTP 与 Unix 纪元的秒数差
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// RTSP 服务端配置
+#[derive(Debug, Clone)]
+pub struct RtspServerConfig {
+    pub bind: String,
+    /// 配置后除 OPTIONS 外的请求都需要 Digest 认证
+    pub credentials: Option<RtspCredentials>,
+    pub realm: String,
+    /// 多播地址池起点，每个流分配一个组地址
+    pub multicast_address_base: Ipv4Addr,
+    /// 多播端口起点：视频 RTP/RTCP 使用 port、port+1，音频使用 port+2、port+3
+    pub multicast_port: u16,
+    pub multicast_ttl: u32,
+    pub session_timeout: Duration,
+    /// DESCRIBE 等待关键帧以获取 SPS/PPS 的最长时间
+    pub parameter_set_wait: Duration,
+}
+
+impl Default for RtspServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:8554".to_string(),
+            credentials: None,
+            realm: "flux-rtspd".to_string(),
+            multicast_address_base: Ipv4Addr::new(239, 255, 42, 1),
+            multicast_port: 30000,
+            multicast_ttl: 16,
+            session_timeout: Duration::from_secs(60),
+            parameter_set_wait: Duration::from_secs(3),
+        }
+    }
+}
+
+/// 轨道编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrackCodec {
+    H264,
+    H265,
+    Aac,
+    Pcma,
+    Pcmu,
+    Opus,
+}
+
+/// SDP 中的一条轨道
+#[derive(Debug, Clone)]
+struct Track {
+    track_id: u8,
+    packet_type: PacketType,
+    codec: TrackCodec,
+    payload_type: u8,
+    clock_rate: u32,
+    channels: u8,
+    fmtp: Option<String>,
+}
+
+impl Track {
+    fn payload_format(&self) -> PayloadFormat {
+        match self.codec {
+            TrackCodec::H264 => PayloadFormat::H264,
+            TrackCodec::H265 => PayloadFormat::H265,
+            TrackCodec::Aac => PayloadFormat::Aac,
+            _ => PayloadFormat::Frame,
+        }
+    }
+
+    fn rtpmap(&self) -> String {
+        let encoding = match self.codec {
+            TrackCodec::H264 => "H264",
+            TrackCodec::H265 => "H265",
+            TrackCodec::Aac => "MPEG4-GENERIC",
+            TrackCodec::Pcma => "PCMA",
+            TrackCodec::Pcmu => "PCMU",
+            TrackCodec::Opus => "opus",
+        };
+        match self.packet_type {
+            PacketType::Audio if self.channels > 1 => {
+                format!("{} {}/{}/{}", self.payload_type, encoding, self.clock_rate, self.channels)
+            }
+            _ => format!("{} {}/{}", self.payload_type, encoding, self.clock_rate),
+        }
+    }
+
+    fn packetizer(&self) -> RtpPacketizer {
+        RtpPacketizer::new(self.payload_format(), self.payload_type, rand::random(), self.clock_rate)
+    }
+}
+
+/// 由流元数据与参数集（SPS/PPS，H.265 另含 VPS）生成轨道列表；不支持的编码被忽略
+fn describe_tracks(metadata: &StreamMetadata, parameter_sets: &[Vec<u8>]) -> Vec<Track> {
+    let mut tracks = Vec::with_capacity(2);
+    let base64 = &base64::engine::general_purpose::STANDARD;
+
+    let video = match metadata.video_codec.as_deref() {
+        Some("h264") => {
+            let sps = parameter_sets.iter().find(|nalu| nalu[0] & 0x1F == 7);
+            let pps = parameter_sets.iter().find(|nalu| nalu[0] & 0x1F == 8);
+            let mut fmtp = "packetization-mode=1".to_string();
+            if let (Some(sps), Some(pps)) = (sps, pps) {
+                if sps.len() >= 4 {
+                    fmtp.push_str(&format!(";profile-level-id={}", hex::encode_upper(&sps[1..4])));
+                }
+                fmtp.push_str(&format!(
+                    ";sprop-parameter-sets={},{}",
+                    base64.encode(sps),
+                    base64.encode(pps)
+                ));
+            }
+            Some((TrackCodec::H264, Some(fmtp)))
+        }
+        Some("h265") => {
+            let sprop: Vec<String> = [(32, "vps"), (33, "sps"), (34, "pps")]
+                .into_iter()
+                .filter_map(|(nal_type, name)| {
+                    parameter_sets
+                        .iter()
+                        .find(|nalu| (nalu[0] >> 1) & 0x3F == nal_type)
+                        .map(|nalu| format!("sprop-{}={}", name, base64.encode(nalu)))
+                })
+                .collect();
+            Some((TrackCodec::H265, (sprop.len() == 3).then(|| sprop.join(";"))))
+        }
+        _ => None,
+    };
+    if let Some((codec, fmtp)) = video {
+        tracks.push(Track {
+            track_id: VIDEO_TRACK_ID,
+            packet_type: PacketType::Video,
+            codec,
+            payload_type: 96,
+            clock_rate: 90000,
+            channels: 1,
+            fmtp,
+        });
+    }
+
+    let audio = match metadata.audio_codec.as_deref() {
+        Some("pcma") => Some((TrackCodec::Pcma, 8, 8000, 1, None)),
+        Some("pcmu") => Some((TrackCodec::Pcmu, 0, 8000, 1, None)),
+        Some("opus") => Some((TrackCodec::Opus, 97, 48000, 2, None)),
+        Some("aac") => metadata
+            .audio_sample_rate
+            .zip(metadata.audio_channels)
+            .and_then(|(sample_rate, channels)| AacConfig::lc(sample_rate, channels))
+            .map(|config| {
+                let fmtp = format!(
+                    "streamtype=5;profile-level-id=1;mode=AAC-hbr;sizelength=13;indexlength=3;indexdeltalength=3;config={}",
+                    hex::encode_upper(config.to_bytes())
+                );
+                (TrackCodec::Aac, 97, config.sample_rate(), config.channel_count() as u8, Some(fmtp))
+            }),
+        _ => None,
+    };
+    if let Some((codec, payload_type, clock_rate, channels, fmtp)) = audio {
+        tracks.push(Track {
+            track_id: AUDIO_TRACK_ID,
+            packet_type: PacketType::Audio,
+            codec,
+            payload_type,
+            clock_rate,
+            channels,
+            fmtp,
+        });
+    }
+
+    tracks
+}
+
+/// 生成 DESCRIBE 响应的 SDP
+fn build_sdp(stream_id: &StreamId, tracks: &[Track], server_ip: IpAddr) -> String {
+    let session_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+
+    let mut sdp = format!(
+        "v=0\r\n\
+o=- {session_id} 1 IN IP4 {server_ip}\r\n\
+s={stream_id}\r\n\
+c=IN IP4 0.0.0.0\r\n\
+t=0 0\r\n\
+a=tool:flux-rtspd\r\n\
+a=range:npt=0-\r\n\
+a=control:*\r\n"
+    );
+    for track in tracks {
+        let kind = match track.packet_type {
+            PacketType::Video => "video",
+            PacketType::Audio => "audio",
+        };
+        sdp.push_str(&format!("m={} 0 RTP/AVP {}\r\n", kind, track.payload_type));
+        sdp.push_str(&format!("a=rtpmap:{}\r\n", track.rtpmap()));
+        if let Some(fmtp) = &track.fmtp {
+            sdp.push_str(&format!("a=fmtp:{} {}\r\n", track.payload_type, fmtp));
+        }
+        sdp.push_str(&format!("a=control:trackID={}\r\n", track.track_id));
+    }
+    sdp
+}
+
+/// `rtsp://host[:port]/path[/trackID=N][?query]` → (流 ID, trackID)
+fn parse_request_path(uri: &str) -> Option<(StreamId, Option<u8>)> {
+    let rest = uri.strip_prefix("rtsp://").or_else(|| uri.strip_prefix("rtsps://"))?;
+    let path = rest.split_once('/').map(|(_, path)| path).unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default().trim_end_matches('/');
+
+    let (path, track_id) = match path.rsplit_once('/') {
+        Some((stream, last)) if last.starts_with("trackID=") => {
+            (stream, last["trackID=".len()..].parse().ok())
+        }
+        _ => (path, None),
+    };
+    if !path.contains('/') {
+        return None;
+    }
+    Some((StreamId::from_string(path.to_string()), track_id))
+}
+
+/// SETUP 请求的 Transport
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransportRequest {
+    Udp { rtp_port: u16, rtcp_port: u16 },
+    Interleaved { rtp_channel: u8, rtcp_channel: u8 },
+    Multicast,
+}
+
+/// 解析 Transport 头，返回第一个支持的传输方式
+fn parse_transport(header: &str) -> Option<TransportRequest> {
+    header.split(',').find_map(|spec| {
+        let params: Vec<&str> = spec.split(';').map(str::trim).collect();
+        let profile = params.first()?;
+        if !profile.starts_with("RTP/AVP") {
+            return None;
+        }
+        let param = |name: &str| {
+            params
+                .iter()
+                .find_map(|param| param.strip_prefix(name).and_then(|value| value.strip_prefix('=')))
+        };
+        let range = |value: &str| -> Option<(u16, u16)> {
+            let (first, second) = value.split_once('-').unwrap_or((value, ""));
+            let first: u16 = first.parse().ok()?;
+            Some((first, second.parse().unwrap_or(first.wrapping_add(1))))
+        };
+
+        if *profile == "RTP/AVP/TCP" {
+            let (rtp, rtcp) = param("interleaved").and_then(range).unwrap_or((0, 1));
+            return Some(TransportRequest::Interleaved {
+                rtp_channel: rtp as u8,
+                rtcp_channel: rtcp as u8,
+            });
+        }
+        if params.contains(&"multicast") {
+            return Some(TransportRequest::Multicast);
+        }
+        let (rtp_port, rtcp_port) = param("client_port").and_then(range)?;
+        Some(TransportRequest::Udp { rtp_port, rtcp_port })
+    })
+}
+
+/// 绑定相邻的 RTP / RTCP 端口（RTP 为偶数）
+async fn bind_udp_pair() -> Result<(UdpSocket, UdpSocket)> {
+    for _ in 0..16 {
+        let rtp = UdpSocket::bind("0.0.0.0:0").await?;
+        let port = rtp.local_addr()?.port();
+        if port % 2 != 0 || port == u16::MAX {
+            continue;
+        }
+        if let Ok(rtcp) = UdpSocket::bind(("0.0.0.0", port + 1)).await {
+            return Ok((rtp, rtcp));
+        }
+    }
+    Err(anyhow!("No free UDP port pair"))
+}
+
+/// RTP 输出
+enum RtpSink {
+    Udp {
+        rtp: Arc<UdpSocket>,
+        rtcp: Arc<UdpSocket>,
+        rtp_target: SocketAddr,
+        rtcp_target: SocketAddr,
+    },
+    Interleaved {
+        tx: mpsc::Sender<Bytes>,
+        rtp_channel: u8,
+        rtcp_channel: u8,
+    },
+}
+
+impl RtpSink {
+    async fn send(&self, data: &[u8], rtcp: bool) -> Result<()> {
+        match self {
+            Self::Udp {
+                rtp,
+                rtcp: rtcp_socket,
+                rtp_target,
+                rtcp_target,
+            } => {
+                // UDP 发送失败（如 ICMP 不可达）不终止会话，由会话超时清理
+                let result = if rtcp {
+                    rtcp_socket.send_to(data, rtcp_target).await
+                } else {
+                    rtp.send_to(data, rtp_target).await
+                };
+                if let Err(e) = result {
+                    debug!(target: "rtsp_server", "UDP send failed: {}", e);
+                }
+                Ok(())
+            }
+            Self::Interleaved {
+                tx,
+                rtp_channel,
+                rtcp_channel,
+            } => {
+                let channel = if rtcp { *rtcp_channel } else { *rtp_channel };
+                let mut frame = BytesMut::with_capacity(4 + data.len());
+                frame.put_u8(b'$');
+                frame.put_u8(channel);
+                frame.put_u16(data.len() as u16);
+                frame.extend_from_slice(data);
+                tx.send(frame.freeze())
+                    .await
+                    .map_err(|_| anyhow!("Connection closed"))
+            }
+        }
+    }
+}
+
+/// 单条轨道的发送状态
+struct TrackSender {
+    packet_type: PacketType,
+    packetizer: RtpPacketizer,
+    sink: RtpSink,
+    /// 最近一个包的发送时刻与 RTP 时间戳（用于生成 SR）
+    last_sent: Option<(Instant, u32)>,
+    packet_count: u32,
+    octet_count: u32,
+}
+
+impl TrackSender {
+    fn new(track: &Track, packetizer: RtpPacketizer, sink: RtpSink) -> Self {
+        Self {
+            packet_type: track.packet_type,
+            packetizer,
+            sink,
+            last_sent: None,
+            packet_count: 0,
+            octet_count: 0,
+        }
+    }
+
+    async fn send(&mut self, packet: &MediaPacket) -> Result<()> {
+        for rtp in self.packetizer.packetize(&packet.data, packet.timestamp) {
+            self.packet_count = self.packet_count.wrapping_add(1);
+            self.octet_count = self.octet_count.wrapping_add(rtp.payload.len() as u32);
+            self.last_sent = Some((Instant::now(), rtp.timestamp));
+            self.sink.send(&rtp.to_bytes(), false).await?;
+        }
+        Ok(())
+    }
+
+    /// RTCP SR：把当前 NTP 时间映射到按发送时刻外推的 RTP 时间戳
+    async fn send_report(&self) -> Result<()> {
+        let Some((sent_at, rtp_timestamp)) = self.last_sent else {
+            return Ok(());
+        };
+        let elapsed = sent_at.elapsed();
+        let rtp_timestamp = rtp_timestamp
+            .wrapping_add((elapsed.as_millis() as u64 * self.packetizer.clock_rate() as u64 / 1000) as u32);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let ntp_seconds = (now.as_secs() + NTP_UNIX_OFFSET) as u32;
+        let ntp_fraction = ((now.subsec_nanos() as u64) << 32) / 1_000_000_000;
+
+        let mut report = BytesMut::with_capacity(28);
+        report.put_u8(0x80);
+        report.put_u8(200);
+        report.put_u16(6);
+        report.put_u32(self.packetizer.ssrc());
+        report.put_u32(ntp_seconds);
+        report.put_u32(ntp_fraction as u32);
+        report.put_u32(rtp_timestamp);
+        report.put_u32(self.packet_count);
+        report.put_u32(self.octet_count);
+        self.sink.send(&report, true).await
+    }
+}
+
+/// 发送任务：订阅流并按轨道打包发送，视频从关键帧开始（落后丢包后重新等待关键帧）
+async fn run_sender(stream_id: StreamId, mut receiver: broadcast::Receiver<MediaPacket>, mut tracks: Vec<TrackSender>) {
+    let has_video = tracks.iter().any(|track| track.packet_type == PacketType::Video);
+    let mut waiting_keyframe = has_video;
+    let mut report = tokio::time::interval(SENDER_REPORT_INTERVAL);
+
+    loop {
+        tokio::select! {
+            result = receiver.recv() => {
+                let packet = match result {
+                    Ok(packet) => packet,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!(target: "rtsp_server", stream_id = %stream_id, skipped, "RTSP sender lagged");
+                        waiting_keyframe = has_video;
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                if packet.packet_type == PacketType::Video && waiting_keyframe {
+                    if !packet.is_keyframe {
+                        continue;
+                    }
+                    waiting_keyframe = false;
+                }
+
+                let Some(track) = tracks.iter_mut().find(|track| track.packet_type == packet.packet_type) else {
+                    continue;
+                };
+                if let Err(e) = track.send(&packet).await {
+                    debug!(target: "rtsp_server", stream_id = %stream_id, "RTSP sender stopped: {}", e);
+                    break;
+                }
+            }
+            _ = report.tick() => {
+                for track in &tracks {
+                    let _ = track.send_report().await;
+                }
+            }
+        }
+    }
+
+    info!(target: "rtsp_server", stream_id = %stream_id, "RTSP sender finished");
+}
+
+/// 一个流的多播组（所有多播客户端共享）
+struct MulticastGroup {
+    address: Ipv4Addr,
+    clients: usize,
+    sender: Option<JoinHandle<()>>,
+}
+
+/// 已 SETUP 的轨道
+struct SetupTrack {
+    track: Track,
+    packetizer: RtpPacketizer,
+    transport: TrackTransport,
+}
+
+enum TrackTransport {
+    Udp {
+        rtp: Arc<UdpSocket>,
+        rtcp: Arc<UdpSocket>,
+        rtp_target: SocketAddr,
+        rtcp_target: SocketAddr,
+    },
+    Interleaved {
+        rtp_channel: u8,
+        rtcp_channel: u8,
+    },
+    Multicast,
+}
+
+/// RTSP 会话（每个连接最多一个）
+struct Session {
+    id: String,
+    stream_id: StreamId,
+    tracks: Vec<SetupTrack>,
+    multicast: bool,
+    playing: bool,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+/// RTSP 请求
+#[derive(Debug)]
+struct RtspRequest {
+    method: String,
+    uri: String,
+    headers: Vec<(String, String)>,
+}
+
+impl RtspRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// 客户端发来的消息
+enum ClientMessage {
+    Request(RtspRequest),
+    /// interleaved 数据（客户端 RTCP），仅用于保活
+    Interleaved,
+}
+
+/// 读取一条 RTSP 请求或 interleaved 帧；连接关闭返回 None
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<ClientMessage>> {
+    let first = match reader.fill_buf().await?.first() {
+        Some(&byte) => byte,
+        None => return Ok(None),
+    };
+
+    if first == b'$' {
+        let mut header = [0u8; 4];
+        reader.read_exact(&mut header).await?;
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let mut data = vec![0u8; length];
+        reader.read_exact(&mut data).await?;
+        return Ok(Some(ClientMessage::Interleaved));
+    }
+
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if lines.is_empty() {
+                continue;
+            }
+            break;
+        }
+        if lines.len() >= MAX_HEADER_LINES {
+            return Err(anyhow!("Too many header lines"));
+        }
+        lines.push(line.to_string());
+    }
+
+    let mut parts = lines[0].split_whitespace();
+    let (Some(method), Some(uri), Some(_version)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(anyhow!("Invalid request line: {}", lines[0]));
+    };
+    let headers: Vec<(String, String)> = lines[1..]
+        .iter()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let request = RtspRequest {
+        method: method.to_ascii_uppercase(),
+        uri: uri.to_string(),
+        headers,
+    };
+
+    // 丢弃请求体（SET_PARAMETER 等）
+    let content_length: usize = request
+        .header("Content-Length")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    if content_length > 0 {
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body).await?;
+    }
+
+    Ok(Some(ClientMessage::Request(request)))
+}
+
+/// RTSP 响应
+struct RtspResponse {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Option<String>,
+}
+
+impl RtspResponse {
+    fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    fn body(mut self, content_type: &str, body: String) -> Self {
+        self.headers.push(("Content-Type", content_type.to_string()));
+        self.body = Some(body);
+        self
+    }
+
+    fn to_bytes(&self, cseq: Option<&str>) -> Bytes {
+        let mut response = format!("RTSP/1.0 {} {}\r\n", self.status, reason_phrase(self.status));
+        if let Some(cseq) = cseq {
+            response.push_str(&format!("CSeq: {}\r\n", cseq));
+        }
+        response.push_str("Server: flux-rtspd\r\n");
+        for (name, value) in &self.headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        match &self.body {
+            Some(body) => {
+                response.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+                response.push_str(body);
+            }
+            None => response.push_str("\r\n"),
+        }
+        Bytes::from(response)
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        454 => "Session Not Found",
+        455 => "Method Not Valid in This State",
+        459 => "Aggregate Operation Not Allowed",
+        461 => "Unsupported Transport",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        _ => "Unknown",
+    }
+}
+
+/// RTSP 服务端
+pub struct RtspServer {
+    stream_manager: Arc<StreamManager>,
+    config: RtspServerConfig,
+    multicast_groups: Mutex<HashMap<StreamId, MulticastGroup>>,
+    next_multicast_offset: AtomicU32,
+}
+
+impl RtspServer {
+    pub fn new(stream_manager: Arc<StreamManager>, config: RtspServerConfig) -> Self {
+        Self {
+            stream_manager,
+            config,
+            multicast_groups: Mutex::new(HashMap::new()),
+            next_multicast_offset: AtomicU32::new(0),
+        }
+    }
+
+    /// 绑定配置的地址并开始服务
+    pub async fn run(self: Arc<Self>) -> Result<()> {
+        let listener = TcpListener::bind(&self.config.bind).await?;
+        info!(target: "rtsp_server", "RTSP server listening on {}", self.config.bind);
+        self.serve(listener).await
+    }
+
+    /// 在已绑定的监听器上服务
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(stream, peer).await {
+                    debug!(target: "rtsp_server", peer = %peer, "RTSP connection closed: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(self: Arc<Self>, stream: TcpStream, peer: SocketAddr) -> Result<()> {
+        let _ = stream.set_nodelay(true);
+        let local_ip = stream.local_addr()?.ip();
+        let (read_half, mut write_half) = stream.into_split();
+
+        let (tx, mut rx) = mpsc::channel::<Bytes>(WRITE_QUEUE_CAPACITY);
+        let writer = tokio::spawn(async move {
+            while let Some(data) = rx.recv().await {
+                if write_half.write_all(&data).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // 读取放在独立任务中：read_line 不可取消，不能直接参与 select
+        let (message_tx, mut messages) = mpsc::channel(16);
+        let reader = tokio::spawn(async move {
+            let mut reader = BufReader::new(read_half);
+            loop {
+                match read_message(&mut reader).await {
+                    Ok(Some(message)) => {
+                        if message_tx.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        debug!(target: "rtsp_server", "Invalid RTSP message: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut connection = Connection {
+            server: self.clone(),
+            peer,
+            local_ip,
+            tx: tx.clone(),
+            authenticator: self
+                .config
+                .credentials
+                .clone()
+                .map(|credentials| DigestAuthenticator::new(credentials, self.config.realm.clone())),
+            session: None,
+            activity: Arc::new(StdMutex::new(Instant::now())),
+        };
+        debug!(target: "rtsp_server", peer = %peer, "RTSP connection accepted");
+
+        let mut check = tokio::time::interval(SESSION_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                message = messages.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    connection.touch();
+                    if let ClientMessage::Request(request) = message {
+                        let cseq = request.header("CSeq").map(str::to_string);
+                        let response = connection.handle(&request).await;
+                        if tx.send(response.to_bytes(cseq.as_deref())).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                _ = check.tick() => {
+                    if connection.session.is_some() && connection.idle_time() > self.config.session_timeout {
+                        warn!(target: "rtsp_server", peer = %peer, "RTSP session timed out");
+                        break;
+                    }
+                }
+            }
+        }
+
+        connection.teardown().await;
+        reader.abort();
+        drop(connection);
+        drop(tx);
+        let _ = writer.await;
+        Ok(())
+    }
+
+    /// DESCRIBE：等待关键帧取参数集（流尚未出现关键帧时不带 sprop）
+    async fn parameter_sets(&self, stream_id: &StreamId) -> Vec<Vec<u8>> {
+        let Ok(mut receiver) = self.stream_manager.subscribe(stream_id).await else {
+            return Vec::new();
+        };
+        let wait = async {
+            loop {
+                match receiver.recv().await {
+                    Ok(packet) if packet.packet_type == PacketType::Video && packet.is_keyframe => {
+                        return split_annexb(&packet.data)
+                            .into_iter()
+                            .filter(|nalu| !nalu.is_empty())
+                            .filter(|nalu| matches!(nalu[0] & 0x1F, 7 | 8) || matches!((nalu[0] >> 1) & 0x3F, 32..=34))
+                            .map(<[u8]>::to_vec)
+                            .collect();
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return Vec::new(),
+                }
+            }
+        };
+        tokio::time::timeout(self.config.parameter_set_wait, wait)
+            .await
+            .unwrap_or_default()
+    }
+
+    /// 获取（必要时创建）流的多播组地址
+    async fn multicast_address(&self, stream_id: &StreamId) -> Ipv4Addr {
+        let mut groups = self.multicast_groups.lock().await;
+        groups
+            .entry(stream_id.clone())
+            .or_insert_with(|| {
+                let offset = self.next_multicast_offset.fetch_add(1, Ordering::Relaxed);
+                MulticastGroup {
+                    address: Ipv4Addr::from(u32::from(self.config.multicast_address_base).wrapping_add(offset)),
+                    clients: 0,
+                    sender: None,
+                }
+            })
+            .address
+    }
+
+    fn multicast_port(&self, track_id: u8) -> u16 {
+        self.config.multicast_port + track_id as u16 * 2
+    }
+
+    /// 加入多播组，首个客户端启动发送任务
+    async fn join_multicast(&self, stream_id: &StreamId, tracks: &[Track]) -> Result<()> {
+        let address = self.multicast_address(stream_id).await;
+        let mut groups = self.multicast_groups.lock().await;
+        let group = groups
+            .get_mut(stream_id)
+            .ok_or_else(|| anyhow!("Multicast group missing"))?;
+        group.clients += 1;
+        if group.sender.is_some() {
+            return Ok(());
+        }
+
+        let receiver = self
+            .stream_manager
+            .subscribe(stream_id)
+            .await
+            .map_err(|e| anyhow!("{}", e))?;
+        let rtp = Arc::new(UdpSocket::bind("0.0.0.0:0").await?);
+        rtp.set_multicast_ttl_v4(self.config.multicast_ttl)?;
+        let senders = tracks
+            .iter()
+            .map(|track| {
+                let port = self.multicast_port(track.track_id);
+                TrackSender::new(
+                    track,
+                    track.packetizer(),
+                    RtpSink::Udp {
+                        rtp: rtp.clone(),
+                        rtcp: rtp.clone(),
+                        rtp_target: SocketAddr::new(IpAddr::V4(address), port),
+                        rtcp_target: SocketAddr::new(IpAddr::V4(address), port + 1),
+                    },
+                )
+            })
+            .collect();
+        group.sender = Some(tokio::spawn(run_sender(stream_id.clone(), receiver, senders)));
+        info!(target: "rtsp_server", stream_id = %stream_id, group = %address, "RTSP multicast started");
+        Ok(())
+    }
+
+    /// 离开多播组，最后一个客户端离开时停止发送
+    async fn leave_multicast(&self, stream_id: &StreamId) {
+        let mut groups = self.multicast_groups.lock().await;
+        let Some(group) = groups.get_mut(stream_id) else {
+            return;
+        };
+        group.clients = group.clients.saturating_sub(1);
+        if group.clients == 0 {
+            if let Some(sender) = group.sender.take() {
+                sender.abort();
+            }
+            groups.remove(stream_id);
+            info!(target: "rtsp_server", stream_id = %stream_id, "RTSP multicast stopped");
+        }
+    }
+}
+
+/// 单个 RTSP 连接的状态
+struct Connection {
+    server: Arc<RtspServer>,
+    peer: SocketAddr,
+    local_ip: IpAddr,
+    tx: mpsc::Sender<Bytes>,
+    authenticator: Option<DigestAuthenticator>,
+    session: Option<Session>,
+    /// 最近一次活动（请求、interleaved RTCP、UDP RTCP）
+    activity: Arc<StdMutex<Instant>>,
+}
+
+impl Connection {
+    fn touch(&self) {
+        if let Ok(mut activity) = self.activity.lock() {
+            *activity = Instant::now();
+        }
+    }
+
+    fn idle_time(&self) -> Duration {
+        self.activity
+            .lock()
+            .map(|activity| activity.elapsed())
+            .unwrap_or_default()
+    }
+
+    async fn handle(&mut self, request: &RtspRequest) -> RtspResponse {
+        debug!(target: "rtsp_server", peer = %self.peer, method = %request.method, uri = %request.uri, "RTSP request");
+
+        if request.method != "OPTIONS" {
+            if let Some(authenticator) = &self.authenticator {
+                if !authenticator.verify(&request.method, request.header("Authorization")) {
+                    return RtspResponse::new(401).header("WWW-Authenticate", authenticator.challenge());
+                }
+            }
+        }
+
+        let result = match request.method.as_str() {
+            "OPTIONS" => Ok(RtspResponse::new(200).header(
+                "Public",
+                "OPTIONS, DESCRIBE, SETUP, PLAY, TEARDOWN, GET_PARAMETER, SET_PARAMETER",
+            )),
+            "DESCRIBE" => self.describe(request).await,
+            "SETUP" => self.setup(request).await,
+            "PLAY" => self.play(request).await,
+            "TEARDOWN" => {
+                self.teardown().await;
+                Ok(RtspResponse::new(200))
+            }
+            "GET_PARAMETER" | "SET_PARAMETER" => Ok(self.with_session(RtspResponse::new(200))),
+            _ => Ok(RtspResponse::new(501)),
+        };
+
+        result.unwrap_or_else(|e| {
+            warn!(target: "rtsp_server", peer = %self.peer, method = %request.method, "RTSP request failed: {}", e);
+            RtspResponse::new(500)
+        })
+    }
+
+    fn with_session(&self, response: RtspResponse) -> RtspResponse {
+        match &self.session {
+            Some(session) => response.header(
+                "Session",
+                format!("{};timeout={}", session.id, self.server.config.session_timeout.as_secs()),
+            ),
+            None => response,
+        }
+    }
+
+    async fn describe(&mut self, request: &RtspRequest) -> Result<RtspResponse> {
+        let Some((stream_id, _)) = parse_request_path(&request.uri) else {
+            return Ok(RtspResponse::new(400));
+        };
+        let Some(metadata) = self.server.stream_manager.get_metadata(&stream_id).await else {
+            return Ok(RtspResponse::new(404));
+        };
+
+        let parameter_sets = self.server.parameter_sets(&stream_id).await;
+        let tracks = describe_tracks(&metadata, &parameter_sets);
+        if tracks.is_empty() {
+            return Ok(RtspResponse::new(404));
+        }
+
+        let sdp = build_sdp(&stream_id, &tracks, self.local_ip);
+        Ok(RtspResponse::new(200)
+            .header("Content-Base", format!("{}/", request.uri.trim_end_matches('/')))
+            .body("application/sdp", sdp))
+    }
+
+    async fn setup(&mut self, request: &RtspRequest) -> Result<RtspResponse> {
+        let Some((stream_id, track_id)) = parse_request_path(&request.uri) else {
+            return Ok(RtspResponse::new(400));
+        };
+        let Some(metadata) = self.server.stream_manager.get_metadata(&stream_id).await else {
+            return Ok(RtspResponse::new(404));
+        };
+        let tracks = describe_tracks(&metadata, &[]);
+        // 未带 trackID 时视为唯一轨道
+        let track = match track_id {
+            Some(track_id) => tracks.into_iter().find(|track| track.track_id == track_id),
+            None if tracks.len() == 1 => tracks.into_iter().next(),
+            None => None,
+        };
+        let Some(track) = track else {
+            return Ok(RtspResponse::new(404));
+        };
+        let Some(transport) = request.header("Transport").and_then(parse_transport) else {
+            return Ok(RtspResponse::new(461));
+        };
+
+        if let Some(session) = &self.session {
+            if session.stream_id != stream_id {
+                return Ok(RtspResponse::new(459));
+            }
+            if session.playing {
+                return Ok(RtspResponse::new(455));
+            }
+            if session.multicast != (transport == TransportRequest::Multicast) {
+                return Ok(RtspResponse::new(461));
+            }
+        }
+
+        if self.session.is_none() {
+            self.session = Some(Session {
+                id: hex::encode_upper(rand::random::<[u8; 8]>()),
+                stream_id: stream_id.clone(),
+                tracks: Vec::new(),
+                multicast: transport == TransportRequest::Multicast,
+                playing: false,
+                tasks: Vec::new(),
+            });
+        }
+
+        let packetizer = track.packetizer();
+        let ssrc = packetizer.ssrc();
+        let (transport, reply) = match transport {
+            TransportRequest::Udp { rtp_port, rtcp_port } => {
+                let (rtp, rtcp) = bind_udp_pair().await?;
+                let server_port = rtp.local_addr()?.port();
+                let (rtp, rtcp) = (Arc::new(rtp), Arc::new(rtcp));
+                self.spawn_rtcp_listener(rtcp.clone());
+                (
+                    TrackTransport::Udp {
+                        rtp,
+                        rtcp,
+                        rtp_target: SocketAddr::new(self.peer.ip(), rtp_port),
+                        rtcp_target: SocketAddr::new(self.peer.ip(), rtcp_port),
+                    },
+                    format!(
+                        "RTP/AVP;unicast;client_port={}-{};server_port={}-{};ssrc={:08X}",
+                        rtp_port,
+                        rtcp_port,
+                        server_port,
+                        server_port + 1,
+                        ssrc
+                    ),
+                )
+            }
+            TransportRequest::Interleaved {
+                rtp_channel,
+                rtcp_channel,
+            } => (
+                TrackTransport::Interleaved {
+                    rtp_channel,
+                    rtcp_channel,
+                },
+                format!(
+                    "RTP/AVP/TCP;unicast;interleaved={}-{};ssrc={:08X}",
+                    rtp_channel, rtcp_channel, ssrc
+                ),
+            ),
+            TransportRequest::Multicast => {
+                let address = self.server.multicast_address(&stream_id).await;
+                let port = self.server.multicast_port(track.track_id);
+                (
+                    TrackTransport::Multicast,
+                    format!(
+                        "RTP/AVP;multicast;destination={};port={}-{};ttl={}",
+                        address,
+                        port,
+                        port + 1,
+                        self.server.config.multicast_ttl
+                    ),
+                )
+            }
+        };
+
+        let Some(session) = self.session.as_mut() else {
+            return Ok(RtspResponse::new(454));
+        };
+        session.tracks.retain(|setup| setup.track.track_id != track.track_id);
+        session.tracks.push(SetupTrack {
+            track,
+            packetizer,
+            transport,
+        });
+
+        info!(target: "rtsp_server", peer = %self.peer, stream_id = %stream_id, transport = %reply, "RTSP SETUP");
+        Ok(self.with_session(RtspResponse::new(200).header("Transport", reply)))
+    }
+
+    /// UDP 单播：客户端的 RTCP 接收报告用于保活
+    fn spawn_rtcp_listener(&mut self, socket: Arc<UdpSocket>) {
+        let activity = self.activity.clone();
+        let task = tokio::spawn(async move {
+            let mut buf = vec![0u8; 1500];
+            while socket.recv_from(&mut buf).await.is_ok() {
+                if let Ok(mut activity) = activity.lock() {
+                    *activity = Instant::now();
+                }
+            }
+        });
+        match &mut self.session {
+            Some(session) => session.tasks.push(task),
+            None => task.abort(),
+        }
+    }
+
+    async fn play(&mut self, request: &RtspRequest) -> Result<RtspResponse> {
+        let Some(session) = self.session.as_mut() else {
+            return Ok(RtspResponse::new(454));
+        };
+        if session.tracks.is_empty() {
+            return Ok(RtspResponse::new(455));
+        }
+        if session.playing {
+            return Ok(self.with_session(RtspResponse::new(200)));
+        }
+
+        let base = request.uri.trim_end_matches('/');
+        let rtp_info: Vec<String> = session
+            .tracks
+            .iter()
+            .map(|setup| {
+                format!(
+                    "url={}/trackID={};seq={}",
+                    base,
+                    setup.track.track_id,
+                    setup.packetizer.sequence_number()
+                )
+            })
+            .collect();
+
+        let stream_id = session.stream_id.clone();
+        if session.multicast {
+            let tracks: Vec<Track> = session.tracks.iter().map(|setup| setup.track.clone()).collect();
+            self.server.join_multicast(&stream_id, &tracks).await?;
+        } else {
+            let receiver = self
+                .server
+                .stream_manager
+                .subscribe(&stream_id)
+                .await
+                .map_err(|e| anyhow!("{}", e))?;
+            let senders = std::mem::take(&mut session.tracks)
+                .into_iter()
+                .map(|setup| {
+                    let sink = match setup.transport {
+                        TrackTransport::Udp {
+                            rtp,
+                            rtcp,
+                            rtp_target,
+                            rtcp_target,
+                        } => RtpSink::Udp {
+                            rtp,
+                            rtcp,
+                            rtp_target,
+                            rtcp_target,
+                        },
+                        TrackTransport::Interleaved {
+                            rtp_channel,
+                            rtcp_channel,
+                        } => RtpSink::Interleaved {
+                            tx: self.tx.clone(),
+                            rtp_channel,
+                            rtcp_channel,
+                        },
+                        TrackTransport::Multicast => unreachable!("multicast handled above"),
+                    };
+                    TrackSender::new(&setup.track, setup.packetizer, sink)
+                })
+                .collect();
+            session
+                .tasks
+                .push(tokio::spawn(run_sender(stream_id.clone(), receiver, senders)));
+        }
+        session.playing = true;
+
+        info!(target: "rtsp_server", peer = %self.peer, stream_id = %stream_id, "RTSP PLAY");
+        Ok(self.with_session(
+            RtspResponse::new(200)
+                .header("Range", "npt=0.000-")
+                .header("RTP-Info", rtp_info.join(",")),
+        ))
+    }
+
+    async fn teardown(&mut self) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+        for task in session.tasks {
+            task.abort();
+        }
+        if session.multicast && session.playing {
+            self.server.leave_multicast(&session.stream_id).await;
+        }
+        info!(target: "rtsp_server", peer = %self.peer, stream_id = %session.stream_id, "RTSP session closed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_path() {
+        let (stream_id, track_id) = parse_request_path("rtsp://10.0.0.1:8554/rtmp/live/cam1/trackID=1").unwrap();
+        assert_eq!(stream_id.as_str(), "rtmp/live/cam1");
+        assert_eq!(track_id, Some(1));
+
+        let (stream_id, track_id) = parse_request_path("rtsp://10.0.0.1/webrtc/live/obs/?token=1").unwrap();
+        assert_eq!(stream_id.as_str(), "webrtc/live/obs");
+        assert_eq!(track_id, None);
+
+        assert!(parse_request_path("rtsp://10.0.0.1/cam1").is_none());
+        assert!(parse_request_path("http://10.0.0.1/rtmp/live/cam1").is_none());
+    }
+
+    #[test]
+    fn test_parse_transport() {
+        assert_eq!(
+            parse_transport("RTP/AVP;unicast;client_port=5000-5001"),
+            Some(TransportRequest::Udp { rtp_port: 5000, rtcp_port: 5001 })
+        );
+        assert_eq!(
+            parse_transport("RTP/AVP/TCP;unicast;interleaved=2-3"),
+            Some(TransportRequest::Interleaved { rtp_channel: 2, rtcp_channel: 3 })
+        );
+        assert_eq!(parse_transport("RTP/AVP;multicast"), Some(TransportRequest::Multicast));
+        // 不支持的选项被跳过
+        assert_eq!(
+            parse_transport("RTP/SAVP;unicast;client_port=6000-6001,RTP/AVP/TCP;unicast"),
+            Some(TransportRequest::Interleaved { rtp_channel: 0, rtcp_channel: 1 })
+        );
+        assert_eq!(parse_transport("RTP/AVP;unicast"), None);
+    }
+
+    #[test]
+    fn test_build_sdp() {
+        let metadata = StreamMetadata {
+            video_codec: Some("h264".to_string()),
+            audio_codec: Some("aac".to_string()),
+            audio_sample_rate: Some(44100),
+            audio_channels: Some(2),
+            ..Default::default()
+        };
+        let parameter_sets = vec![vec![0x67, 0x42, 0xE0, 0x1F], vec![0x68, 0xCE, 0x3C, 0x80]];
+        let tracks = describe_tracks(&metadata, &parameter_sets);
+        let sdp = build_sdp(&StreamId::new("rtmp", "live/cam1"), &tracks, IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        assert!(sdp.contains("m=video 0 RTP/AVP 96\r\na=rtpmap:96 H264/90000\r\n"));
+        assert!(sdp.contains("a=fmtp:96 packetization-mode=1;profile-level-id=42E01F;sprop-parameter-sets=Z0LgHw==,aM48gA==\r\n"));
+        assert!(sdp.contains("a=rtpmap:97 MPEG4-GENERIC/44100/2\r\n"));
+        assert!(sdp.contains("config=1210\r\na=control:trackID=1\r\n"));
+
+        // AAC 缺少采样率时不输出音频轨道；G.711 使用静态负载类型
+        let metadata = StreamMetadata {
+            video_codec: Some("h265".to_string()),
+            audio_codec: Some("aac".to_string()),
+            ..Default::default()
+        };
+        let tracks = describe_tracks(&metadata, &[]);
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].fmtp, None);
+
+        let metadata = StreamMetadata {
+            audio_codec: Some("pcma".to_string()),
+            ..Default::default()
+        };
+        let tracks = describe_tracks(&metadata, &[]);
+        assert_eq!((tracks[0].track_id, tracks[0].rtpmap()), (AUDIO_TRACK_ID, "8 PCMA/8000".to_string()));
+    }
+}
@@ -0,0 +1,238 @@
+//! RTSP 客户端认证测试：Digest 对接内置 RTSP 服务端，Basic 走 RTSPS
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use flux_config::{StreamMode, StreamingConfig};
+use flux_media_core::types::StreamId;
+use flux_rtspd::rtsp_auth::RtspCredentials;
+use flux_rtspd::rtsp_client::{RtspClient, TransportMode};
+use flux_rtspd::rtsp_server::{RtspServer, RtspServerConfig};
+use flux_stream::{MediaPacket, PacketType, Protocol, Stream, StreamManager, StreamMetadata, StreamStatus};
+use openssl::asn1::Asn1Time;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::x509::{X509NameBuilder, X509};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+struct MockStream {
+    stream_id: StreamId,
+}
+
+#[async_trait]
+impl Stream for MockStream {
+    fn stream_id(&self) -> &StreamId {
+        &self.stream_id
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::RTMP
+    }
+
+    async fn metadata(&self) -> StreamMetadata {
+        StreamMetadata {
+            video_codec: Some("h264".to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn status(&self) -> StreamStatus {
+        StreamStatus::Running
+    }
+
+    async fn start(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_client_digest_auth_and_keepalive() {
+    let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+    let stream_id = StreamId::new("rtmp", "live/cam1");
+    stream_manager
+        .register_stream(
+            Box::new(MockStream {
+                stream_id: stream_id.clone(),
+            }),
+            StreamMode::Passthrough { remux: true },
+        )
+        .await
+        .unwrap();
+    let publisher = {
+        let stream_manager = stream_manager.clone();
+        tokio::spawn(async move {
+            for frame in 0u32.. {
+                let packet = MediaPacket {
+                    data: Bytes::from_static(&[0, 0, 0, 1, 0x67, 0x42, 0xE0, 0x1F, 0, 0, 0, 1, 0x68, 0xCE, 0x3C, 0x80, 0, 0, 0, 1, 0x65, 0x88]),
+                    timestamp: frame * 40,
+                    is_keyframe: true,
+                    packet_type: PacketType::Video,
+                };
+                let _ = stream_manager.publish_packet(&stream_id, packet).await;
+                tokio::time::sleep(Duration::from_millis(40)).await;
+            }
+        })
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = Arc::new(RtspServer::new(
+        stream_manager,
+        RtspServerConfig {
+            credentials: Some(RtspCredentials::new("admin", "p@ss")),
+            session_timeout: Duration::from_secs(30),
+            ..Default::default()
+        },
+    ));
+    tokio::spawn(server.serve(listener));
+
+    // 错误的密码
+    let mut client = RtspClient::new(format!("rtsp://admin:wrong@{}/rtmp/live/cam1", addr));
+    client.connect().await.unwrap();
+    client.options().await.unwrap();
+    let error = client.describe().await.unwrap_err();
+    assert!(error.to_string().contains("authentication failed"), "{}", error);
+
+    // URL 中的凭据（百分号编码）
+    let mut client = RtspClient::new(format!("rtsp://admin:p%40ss@{}/rtmp/live/cam1", addr));
+    assert_eq!(client.url(), format!("rtsp://{}/rtmp/live/cam1", addr));
+    client.set_transport_mode(TransportMode::Tcp);
+    client.connect().await.unwrap();
+    client.options().await.unwrap();
+
+    let response = client.describe().await.unwrap();
+    assert!(response.body.contains("sprop-parameter-sets="));
+
+    client
+        .setup(&format!("rtsp://{}/rtmp/live/cam1/trackID=0", addr), 0)
+        .await
+        .unwrap();
+    assert_eq!(client.session_timeout(), Duration::from_secs(30));
+    assert_eq!(client.keepalive_interval(), Duration::from_secs(15));
+
+    client.play().await.unwrap();
+    // 播放期间的保活响应需要跳过之前到达的 interleaved 数据
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    client.keepalive().await.unwrap();
+    client.teardown().await.unwrap();
+    publisher.abort();
+}
+
+/// 生成自签名证书（DER 证书，PKCS#8 私钥）
+fn self_signed_certificate() -> (Vec<u8>, Vec<u8>) {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let private_key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(Nid::COMMONNAME, "camera.local").unwrap();
+    let name = name.build();
+
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder.set_issuer_name(&name).unwrap();
+    builder.set_pubkey(&private_key).unwrap();
+    builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+    builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+    builder.sign(&private_key, MessageDigest::sha256()).unwrap();
+
+    (
+        builder.build().to_der().unwrap(),
+        private_key.private_key_to_pkcs8().unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_client_basic_auth_over_rtsps() {
+    let (certificate, private_key) = self_signed_certificate();
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(vec![rustls::Certificate(certificate)], rustls::PrivateKey(private_key))
+        .unwrap();
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (authorization_tx, mut authorization_rx) = mpsc::unbounded_channel();
+
+    // 模拟只支持 Basic 认证的摄像机
+    tokio::spawn(async move {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let tls = acceptor.accept(tcp).await.unwrap();
+        let mut reader = BufReader::new(tls);
+        loop {
+            let mut cseq = String::new();
+            let mut authorization = None;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await.unwrap() == 0 {
+                    return;
+                }
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.strip_prefix("CSeq: ") {
+                    cseq = value.to_string();
+                }
+                if let Some(value) = line.strip_prefix("Authorization: ") {
+                    authorization = Some(value.to_string());
+                }
+            }
+
+            let response = match &authorization {
+                Some(_) => format!("RTSP/1.0 200 OK\r\nCSeq: {}\r\nPublic: OPTIONS, DESCRIBE\r\n\r\n", cseq),
+                None => format!(
+                    "RTSP/1.0 401 Unauthorized\r\nCSeq: {}\r\nWWW-Authenticate: Basic realm=\"camera\"\r\n\r\n",
+                    cseq
+                ),
+            };
+            authorization_tx.send(authorization).unwrap();
+            reader.get_mut().write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    let mut client = RtspClient::new(format!("rtsps://127.0.0.1:{}/live", addr.port()))
+        .with_credentials(RtspCredentials::new("user", "pw"))
+        .with_tls_verification(false);
+    client.connect().await.unwrap();
+    client.options().await.unwrap();
+
+    assert_eq!(authorization_rx.recv().await.unwrap(), None);
+    assert_eq!(authorization_rx.recv().await.unwrap().as_deref(), Some("Basic dXNlcjpwdw=="));
+
+    // 质询之后的请求直接携带认证
+    client.options().await.unwrap();
+    assert_eq!(authorization_rx.recv().await.unwrap().as_deref(), Some("Basic dXNlcjpwdw=="));
+}
+
+#[tokio::test]
+async fn test_client_rejects_self_signed_certificate_by_default() {
+    let (certificate, private_key) = self_signed_certificate();
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(vec![rustls::Certificate(certificate)], rustls::PrivateKey(private_key))
+        .unwrap();
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let _ = acceptor.accept(tcp).await;
+    });
+
+    let mut client = RtspClient::new(format!("rtsps://127.0.0.1:{}/live", port));
+    assert!(client.connect().await.is_err());
+}
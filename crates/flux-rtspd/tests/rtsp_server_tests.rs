@@ -0,0 +1,433 @@
+//! RTSP 服务端回环测试：把 flux-stream 中的流通过 TCP interleaved 与 UDP 单播输出，
+//! 并由 RtspStreamManager 拉流生成快照
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use flux_config::{StreamMode, StreamingConfig};
+use flux_media_core::snapshot::{SnapshotMode, SnapshotOrchestrator, SnapshotRequest, SoftwareDecoder};
+use flux_media_core::storage::{filesystem::FileSystemStorage, StorageConfig};
+use flux_media_core::types::StreamId;
+use flux_rtspd::rtsp_auth::{digest_response, parse_auth_params, RtspCredentials};
+use flux_rtspd::rtsp_server::{RtspServer, RtspServerConfig};
+use flux_rtspd::stream_manager::RtspStreamManager;
+use flux_rtspd::telemetry::TelemetryClient;
+use flux_stream::{MediaPacket, PacketType, Protocol, Stream, StreamManager, StreamMetadata, StreamStatus};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+struct MockStream {
+    stream_id: StreamId,
+}
+
+#[async_trait]
+impl Stream for MockStream {
+    fn stream_id(&self) -> &StreamId {
+        &self.stream_id
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::RTMP
+    }
+
+    async fn metadata(&self) -> StreamMetadata {
+        StreamMetadata {
+            video_codec: Some("h264".to_string()),
+            audio_codec: Some("pcma".to_string()),
+            audio_sample_rate: Some(8000),
+            audio_channels: Some(1),
+            ..Default::default()
+        }
+    }
+
+    async fn status(&self) -> StreamStatus {
+        StreamStatus::Running
+    }
+
+    async fn start(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// 占位关键帧：SPS + PPS + IDR
+fn placeholder_keyframe() -> Vec<u8> {
+    let mut keyframe = vec![0, 0, 0, 1, 0x67, 0x42, 0xE0, 0x1F, 0, 0, 0, 1, 0x68, 0xCE, 0x3C, 0x80, 0, 0, 0, 1, 0x65];
+    keyframe.extend(std::iter::repeat_n(0x11, 3000));
+    keyframe
+}
+
+/// 注册流并以 25fps 持续发布（每 10 帧一个关键帧）与 G.711 音频
+async fn start_publisher(stream_manager: Arc<StreamManager>, stream_id: StreamId, keyframe: Vec<u8>) -> JoinHandle<()> {
+    stream_manager
+        .register_stream(
+            Box::new(MockStream {
+                stream_id: stream_id.clone(),
+            }),
+            StreamMode::Passthrough { remux: true },
+        )
+        .await
+        .unwrap();
+
+    tokio::spawn(async move {
+        for frame in 0u32.. {
+            let is_keyframe = frame % 10 == 0;
+            let video = MediaPacket {
+                data: if is_keyframe {
+                    Bytes::from(keyframe.clone())
+                } else {
+                    Bytes::from_static(&[0, 0, 0, 1, 0x41, 0x9A, 0x22])
+                },
+                timestamp: frame * 40,
+                is_keyframe,
+                packet_type: PacketType::Video,
+            };
+            let audio = MediaPacket {
+                data: Bytes::from(vec![0xD5; 320]),
+                timestamp: frame * 40,
+                is_keyframe: false,
+                packet_type: PacketType::Audio,
+            };
+            let _ = stream_manager.publish_packet(&stream_id, video).await;
+            let _ = stream_manager.publish_packet(&stream_id, audio).await;
+            tokio::time::sleep(Duration::from_millis(40)).await;
+        }
+    })
+}
+
+async fn start_server(stream_manager: Arc<StreamManager>, config: RtspServerConfig) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = Arc::new(RtspServer::new(stream_manager, config));
+    tokio::spawn(server.serve(listener));
+    addr
+}
+
+/// 简单的 RTSP 测试客户端：请求 / 响应与 interleaved 帧共用一个读缓冲
+struct TestClient {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    cseq: u32,
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl TestClient {
+    async fn connect(addr: SocketAddr) -> Self {
+        Self {
+            stream: TcpStream::connect(addr).await.unwrap(),
+            buffer: Vec::new(),
+            cseq: 0,
+        }
+    }
+
+    async fn fill(&mut self) {
+        let mut chunk = [0u8; 4096];
+        let len = timeout(Duration::from_secs(5), self.stream.read(&mut chunk))
+            .await
+            .expect("data before timeout")
+            .unwrap();
+        assert!(len > 0, "connection closed");
+        self.buffer.extend_from_slice(&chunk[..len]);
+    }
+
+    async fn request(&mut self, method: &str, uri: &str, headers: &[(&str, String)]) -> Response {
+        self.cseq += 1;
+        let mut request = format!("{} {} RTSP/1.0\r\nCSeq: {}\r\n", method, uri, self.cseq);
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        self.stream.write_all(request.as_bytes()).await.unwrap();
+
+        loop {
+            // 跳过响应之前的 interleaved 数据
+            while self.buffer.first() == Some(&b'$') && self.buffer.len() >= 4 {
+                let length = u16::from_be_bytes([self.buffer[2], self.buffer[3]]) as usize;
+                if self.buffer.len() < 4 + length {
+                    break;
+                }
+                self.buffer.drain(..4 + length);
+            }
+            if let Some(end) = self.buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                if self.buffer.first() != Some(&b'$') {
+                    let head = String::from_utf8_lossy(&self.buffer[..end]).to_string();
+                    let mut lines = head.lines();
+                    let status = lines.next().unwrap().split_whitespace().nth(1).unwrap().parse().unwrap();
+                    let headers: Vec<(String, String)> = lines
+                        .filter_map(|line| line.split_once(':'))
+                        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                        .collect();
+                    let length: usize = headers
+                        .iter()
+                        .find(|(key, _)| key.eq_ignore_ascii_case("Content-Length"))
+                        .map(|(_, value)| value.parse().unwrap())
+                        .unwrap_or(0);
+                    while self.buffer.len() < end + 4 + length {
+                        self.fill().await;
+                    }
+                    let body = String::from_utf8_lossy(&self.buffer[end + 4..end + 4 + length]).to_string();
+                    self.buffer.drain(..end + 4 + length);
+                    return Response { status, headers, body };
+                }
+            }
+            self.fill().await;
+        }
+    }
+
+    /// 读取下一个 interleaved 帧 (channel, data)
+    async fn interleaved(&mut self) -> (u8, Vec<u8>) {
+        loop {
+            if self.buffer.len() >= 4 {
+                assert_eq!(self.buffer[0], b'$');
+                let length = u16::from_be_bytes([self.buffer[2], self.buffer[3]]) as usize;
+                if self.buffer.len() >= 4 + length {
+                    let channel = self.buffer[1];
+                    let data = self.buffer[4..4 + length].to_vec();
+                    self.buffer.drain(..4 + length);
+                    return (channel, data);
+                }
+            }
+            self.fill().await;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_rtsp_server_tcp_interleaved_with_digest() {
+    let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+    let stream_id = StreamId::new("rtmp", "live/cam1");
+    let publisher = start_publisher(stream_manager.clone(), stream_id.clone(), placeholder_keyframe()).await;
+
+    let credentials = RtspCredentials::new("admin", "secret");
+    let addr = start_server(
+        stream_manager,
+        RtspServerConfig {
+            credentials: Some(credentials.clone()),
+            ..Default::default()
+        },
+    )
+    .await;
+    let url = format!("rtsp://{}/rtmp/live/cam1", addr);
+    let mut client = TestClient::connect(addr).await;
+
+    let response = client.request("OPTIONS", &url, &[]).await;
+    assert_eq!(response.status, 200);
+    assert!(response.header("Public").unwrap().contains("DESCRIBE"));
+
+    // 未认证的 DESCRIBE 返回质询
+    let response = client.request("DESCRIBE", &url, &[]).await;
+    assert_eq!(response.status, 401);
+    let challenge = parse_auth_params(response.header("WWW-Authenticate").unwrap().strip_prefix("Digest ").unwrap());
+    let authorization = |method: &str, uri: &str| {
+        let response = digest_response(&credentials, &challenge["realm"], &challenge["nonce"], method, uri, None, "", "");
+        format!(
+            r#"Digest username="admin", realm="{}", nonce="{}", uri="{}", response="{}""#,
+            challenge["realm"], challenge["nonce"], uri, response
+        )
+    };
+
+    let response = client
+        .request("DESCRIBE", &url, &[("Authorization", authorization("DESCRIBE", &url))])
+        .await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("Content-Type"), Some("application/sdp"));
+    assert!(response.body.contains("sprop-parameter-sets=Z0LgHw==,aM48gA=="));
+    assert!(response.body.contains("a=rtpmap:8 PCMA/8000"));
+
+    let video_url = format!("{}/trackID=0", url);
+    let response = client
+        .request(
+            "SETUP",
+            &video_url,
+            &[
+                ("Authorization", authorization("SETUP", &video_url)),
+                ("Transport", "RTP/AVP/TCP;unicast;interleaved=0-1".to_string()),
+            ],
+        )
+        .await;
+    assert_eq!(response.status, 200);
+    assert!(response.header("Transport").unwrap().starts_with("RTP/AVP/TCP;unicast;interleaved=0-1"));
+    let session = response.header("Session").unwrap().split(';').next().unwrap().to_string();
+
+    let audio_url = format!("{}/trackID=1", url);
+    let response = client
+        .request(
+            "SETUP",
+            &audio_url,
+            &[
+                ("Authorization", authorization("SETUP", &audio_url)),
+                ("Transport", "RTP/AVP/TCP;unicast;interleaved=2-3".to_string()),
+                ("Session", session.clone()),
+            ],
+        )
+        .await;
+    assert_eq!(response.status, 200);
+
+    let response = client
+        .request(
+            "PLAY",
+            &url,
+            &[("Authorization", authorization("PLAY", &url)), ("Session", session.clone())],
+        )
+        .await;
+    assert_eq!(response.status, 200);
+    assert!(response.header("RTP-Info").unwrap().contains("trackID=0;seq="));
+
+    // 视频从关键帧开始：第一个视频包是 STAP 之外的单 NALU（SPS）
+    let mut first_video = None;
+    let mut audio_seen = false;
+    while first_video.is_none() || !audio_seen {
+        let (channel, data) = client.interleaved().await;
+        match channel {
+            0 if first_video.is_none() => first_video = Some(data),
+            2 => {
+                assert_eq!(data[1] & 0x7F, 8);
+                assert_eq!(data.len(), 12 + 320);
+                audio_seen = true;
+            }
+            _ => {}
+        }
+    }
+    let first_video = first_video.unwrap();
+    assert_eq!(first_video[1] & 0x7F, 96);
+    assert_eq!(first_video[12], 0x67);
+
+    let response = client
+        .request(
+            "TEARDOWN",
+            &url,
+            &[("Authorization", authorization("TEARDOWN", &url)), ("Session", session)],
+        )
+        .await;
+    assert_eq!(response.status, 200);
+    publisher.abort();
+}
+
+#[tokio::test]
+async fn test_rtsp_server_udp_unicast() {
+    let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+    let stream_id = StreamId::new("rtmp", "live/cam2");
+    let publisher = start_publisher(stream_manager.clone(), stream_id, placeholder_keyframe()).await;
+    let addr = start_server(stream_manager, RtspServerConfig::default()).await;
+    let url = format!("rtsp://{}/rtmp/live/cam2", addr);
+    let mut client = TestClient::connect(addr).await;
+
+    // 不存在的流
+    let response = client.request("DESCRIBE", &format!("rtsp://{}/rtmp/live/missing", addr), &[]).await;
+    assert_eq!(response.status, 404);
+
+    let rtp_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let rtp_port = rtp_socket.local_addr().unwrap().port();
+    let response = client
+        .request(
+            "SETUP",
+            &format!("{}/trackID=0", url),
+            &[("Transport", format!("RTP/AVP;unicast;client_port={}-{}", rtp_port, rtp_port + 1))],
+        )
+        .await;
+    assert_eq!(response.status, 200);
+    let transport = response.header("Transport").unwrap();
+    assert!(transport.contains(&format!("client_port={}-{}", rtp_port, rtp_port + 1)));
+    assert!(transport.contains("server_port="));
+    let session = response.header("Session").unwrap().split(';').next().unwrap().to_string();
+
+    let response = client.request("PLAY", &url, &[("Session", session)]).await;
+    assert_eq!(response.status, 200);
+
+    let mut buf = vec![0u8; 2048];
+    let (len, _) = timeout(Duration::from_secs(5), rtp_socket.recv_from(&mut buf))
+        .await
+        .expect("RTP before timeout")
+        .unwrap();
+    assert!(len > 12);
+    assert_eq!(buf[1] & 0x7F, 96);
+    assert_eq!(buf[12], 0x67);
+
+    // 未 SETUP 的方法在无会话时被拒绝
+    let mut other = TestClient::connect(addr).await;
+    let response = other.request("PLAY", &url, &[]).await;
+    assert_eq!(response.status, 454);
+    publisher.abort();
+}
+
+/// 用 OpenH264 编码一帧 64x48 灰色画面（Annex B：SPS + PPS + IDR）
+fn encoded_keyframe() -> Vec<u8> {
+    use openh264::encoder::Encoder;
+    use openh264::formats::{RgbSliceU8, YUVBuffer};
+
+    let rgb = vec![128u8; 64 * 48 * 3];
+    let yuv = YUVBuffer::from_rgb8_source(RgbSliceU8::new(&rgb, (64, 48)));
+    Encoder::new().unwrap().encode(&yuv).unwrap().to_vec()
+}
+
+#[tokio::test]
+async fn test_stream_manager_pull_decodes_keyframe() {
+    let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+    let stream_id = StreamId::new("rtmp", "live/cam3");
+    let publisher = start_publisher(stream_manager.clone(), stream_id, encoded_keyframe()).await;
+    let addr = start_server(stream_manager, RtspServerConfig::default()).await;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = Arc::new(RwLock::new(
+        FileSystemStorage::new(StorageConfig {
+            root_dir: temp_dir.path().join("storage"),
+            retention_days: 7,
+            segment_duration_secs: 60,
+        })
+        .unwrap(),
+    ));
+    let orchestrator = Arc::new(
+        SnapshotOrchestrator::new(temp_dir.path().join("keyframes"))
+            .with_decoder(Arc::new(SoftwareDecoder::default())),
+    );
+    let puller = RtspStreamManager::new(storage, orchestrator.clone(), None, TelemetryClient::new(None, 1000));
+    let url = format!("rtsp://{}/rtmp/live/cam3", addr);
+    puller.start_stream(url.clone()).await.unwrap();
+
+    // RTP 裸 NALU 经组装（SPS + PPS + IDR）后可解码为 JPEG
+    let pulled_id = StreamId::new("rtsp", &format!("{}/rtmp/live/cam3", addr));
+    let request = SnapshotRequest {
+        stream_id: pulled_id,
+        mode: SnapshotMode::Decode,
+        width: None,
+        height: None,
+        quality: None,
+    };
+    let snapshot = timeout(Duration::from_secs(10), async {
+        loop {
+            if let Ok(snapshot) = orchestrator.get_snapshot(request.clone()).await {
+                return snapshot;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("decoded snapshot before timeout");
+    assert_eq!(snapshot.mode_used, SnapshotMode::Decode);
+    assert_eq!(&snapshot.data[..2], &[0xFF, 0xD8]);
+
+    puller.stop_stream(&url).await.unwrap();
+    publisher.abort();
+}
@@ -1,4 +1,4 @@
-use flux_rule::{RuleEngine, Rule, RuleTrigger, RuleContext, TriggerManager, RateLimit};
+use flux_rule::{DeviceSelector, RuleEngine, Rule, RuleTrigger, RuleContext, TriggerManager, RateLimit};
 use std::sync::Arc;
 
 #[tokio::main]
@@ -79,7 +79,7 @@ async fn main() -> anyhow::Result<()> {
         name: "高温告警".to_string(),
         description: "温度超过80度时告警".to_string(),
         trigger: RuleTrigger::DataChange {
-            device_id: "sensor_001".to_string(),
+            selector: DeviceSelector::device("sensor_001"),
             metric: Some("temperature".to_string()),
         },
         script: r#"
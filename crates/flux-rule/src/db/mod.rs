@@ -0,0 +1,4 @@
+pub mod entities;
+pub mod storage;
+
+pub use storage::DbRuleStorage;
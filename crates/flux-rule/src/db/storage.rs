@@ -0,0 +1,389 @@
+use crate::db::entities::{rule_definition, rule_execution, rule_version, rule_window_checkpoint};
+use crate::execution::{ExecutionStatus, RuleExecution};
+use crate::model::Rule;
+use crate::storage::RuleStore;
+use crate::window::WindowCheckpoint;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    entity::prelude::*, ConnectionTrait, Database, DatabaseConnection, QueryOrder, QuerySelect, Schema, Set,
+    TransactionTrait,
+};
+use std::sync::Arc;
+use tracing::debug;
+
+/// 规则存储（SeaORM 实现，支持 SQLite 与 PostgreSQL）
+///
+/// 表：`rule_definitions`（当前版本）、`rule_versions`（全部版本快照）、`rule_executions`（执行历史）、
+/// `rule_window_checkpoints`（窗口状态检查点）。
+pub struct DbRuleStorage {
+    db: Arc<DatabaseConnection>,
+}
+
+impl DbRuleStorage {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    /// 连接数据库并建表
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let db = Database::connect(url)
+            .await
+            .with_context(|| format!("Failed to connect rule database {}", url))?;
+        let storage = Self::new(Arc::new(db));
+        storage.init_schema().await?;
+        Ok(storage)
+    }
+
+    /// 建表（已存在时跳过）
+    pub async fn init_schema(&self) -> anyhow::Result<()> {
+        let backend = self.db.get_database_backend();
+        let schema = Schema::new(backend);
+
+        let stmt = schema
+            .create_table_from_entity(rule_definition::Entity)
+            .if_not_exists()
+            .to_owned();
+        self.db.execute(backend.build(&stmt)).await?;
+
+        let stmt = schema
+            .create_table_from_entity(rule_version::Entity)
+            .if_not_exists()
+            .to_owned();
+        self.db.execute(backend.build(&stmt)).await?;
+
+        let stmt = schema
+            .create_table_from_entity(rule_execution::Entity)
+            .if_not_exists()
+            .to_owned();
+        self.db.execute(backend.build(&stmt)).await?;
+
+        let stmt = schema
+            .create_table_from_entity(rule_window_checkpoint::Entity)
+            .if_not_exists()
+            .to_owned();
+        self.db.execute(backend.build(&stmt)).await?;
+
+        Ok(())
+    }
+}
+
+const WINDOW_CHECKPOINT_ID: &str = "windows";
+
+fn decode_rule(definition: Json) -> anyhow::Result<Rule> {
+    serde_json::from_value(definition).context("Invalid stored rule definition")
+}
+
+fn decode_execution(model: rule_execution::Model) -> anyhow::Result<RuleExecution> {
+    let status: ExecutionStatus = serde_json::from_value(Json::String(model.status))
+        .context("Invalid stored execution status")?;
+    Ok(RuleExecution {
+        id: model.id,
+        rule_id: model.rule_id,
+        rule_name: model.rule_name,
+        trigger_type: model.trigger_type,
+        started_at: model.started_at,
+        finished_at: model.finished_at,
+        status,
+        error: model.error,
+        context: model.context,
+        side_effects: model
+            .side_effects
+            .map(serde_json::from_value)
+            .transpose()
+            .context("Invalid stored side effects")?,
+    })
+}
+
+#[async_trait]
+impl RuleStore for DbRuleStorage {
+    async fn save(&self, rule: &Rule) -> anyhow::Result<()> {
+        let definition = serde_json::to_value(rule)?;
+        let txn = self.db.begin().await?;
+
+        let current = rule_definition::ActiveModel {
+            id: Set(rule.id.clone()),
+            name: Set(rule.name.clone()),
+            rule_group: Set(rule.group.clone()),
+            enabled: Set(rule.enabled),
+            version: Set(rule.version),
+            definition: Set(definition.clone()),
+            updated_at: Set(Utc::now()),
+        };
+        rule_definition::Entity::insert(current)
+            .on_conflict(
+                OnConflict::column(rule_definition::Column::Id)
+                    .update_columns([
+                        rule_definition::Column::Name,
+                        rule_definition::Column::RuleGroup,
+                        rule_definition::Column::Enabled,
+                        rule_definition::Column::Version,
+                        rule_definition::Column::Definition,
+                        rule_definition::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&txn)
+            .await?;
+
+        let snapshot = rule_version::ActiveModel {
+            rule_id: Set(rule.id.clone()),
+            version: Set(rule.version),
+            definition: Set(definition),
+            created_at: Set(rule.metadata.updated_at),
+        };
+        rule_version::Entity::insert(snapshot)
+            .on_conflict(
+                OnConflict::columns([rule_version::Column::RuleId, rule_version::Column::Version])
+                    .update_columns([rule_version::Column::Definition, rule_version::Column::CreatedAt])
+                    .to_owned(),
+            )
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+        debug!(rule_id = %rule.id, version = rule.version, "Rule saved to database");
+        Ok(())
+    }
+
+    async fn get(&self, rule_id: &str) -> anyhow::Result<Option<Rule>> {
+        rule_definition::Entity::find_by_id(rule_id.to_string())
+            .one(&*self.db)
+            .await?
+            .map(|m| decode_rule(m.definition))
+            .transpose()
+    }
+
+    async fn delete(&self, rule_id: &str) -> anyhow::Result<()> {
+        let txn = self.db.begin().await?;
+        rule_definition::Entity::delete_by_id(rule_id.to_string())
+            .exec(&txn)
+            .await?;
+        rule_version::Entity::delete_many()
+            .filter(rule_version::Column::RuleId.eq(rule_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<Rule>> {
+        rule_definition::Entity::find()
+            .order_by_asc(rule_definition::Column::Id)
+            .all(&*self.db)
+            .await?
+            .into_iter()
+            .map(|m| decode_rule(m.definition))
+            .collect()
+    }
+
+    async fn find_by_group(&self, group: &str) -> anyhow::Result<Vec<Rule>> {
+        rule_definition::Entity::find()
+            .filter(rule_definition::Column::RuleGroup.eq(group))
+            .order_by_asc(rule_definition::Column::Id)
+            .all(&*self.db)
+            .await?
+            .into_iter()
+            .map(|m| decode_rule(m.definition))
+            .collect()
+    }
+
+    async fn versions(&self, rule_id: &str) -> anyhow::Result<Vec<Rule>> {
+        rule_version::Entity::find()
+            .filter(rule_version::Column::RuleId.eq(rule_id))
+            .order_by_asc(rule_version::Column::Version)
+            .all(&*self.db)
+            .await?
+            .into_iter()
+            .map(|m| decode_rule(m.definition))
+            .collect()
+    }
+
+    async fn get_version(&self, rule_id: &str, version: i32) -> anyhow::Result<Option<Rule>> {
+        rule_version::Entity::find_by_id((rule_id.to_string(), version))
+            .one(&*self.db)
+            .await?
+            .map(|m| decode_rule(m.definition))
+            .transpose()
+    }
+
+    async fn save_execution(&self, execution: &RuleExecution) -> anyhow::Result<()> {
+        let status = match serde_json::to_value(&execution.status)? {
+            Json::String(status) => status,
+            other => other.to_string(),
+        };
+        let model = rule_execution::ActiveModel {
+            id: Set(execution.id.clone()),
+            rule_id: Set(execution.rule_id.clone()),
+            rule_name: Set(execution.rule_name.clone()),
+            trigger_type: Set(execution.trigger_type.clone()),
+            started_at: Set(execution.started_at),
+            finished_at: Set(execution.finished_at),
+            status: Set(status),
+            error: Set(execution.error.clone()),
+            context: Set(execution.context.clone()),
+            side_effects: Set(execution.side_effects.as_ref().map(serde_json::to_value).transpose()?),
+        };
+        rule_execution::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(rule_execution::Column::Id)
+                    .update_columns([
+                        rule_execution::Column::FinishedAt,
+                        rule_execution::Column::Status,
+                        rule_execution::Column::Error,
+                        rule_execution::Column::SideEffects,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&*self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn executions(&self, rule_id: Option<&str>, limit: usize) -> anyhow::Result<Vec<RuleExecution>> {
+        let mut query = rule_execution::Entity::find();
+        if let Some(rule_id) = rule_id {
+            query = query.filter(rule_execution::Column::RuleId.eq(rule_id));
+        }
+        query
+            .order_by_desc(rule_execution::Column::StartedAt)
+            .limit(limit as u64)
+            .all(&*self.db)
+            .await?
+            .into_iter()
+            .map(decode_execution)
+            .collect()
+    }
+
+    async fn save_window_checkpoint(&self, checkpoint: &WindowCheckpoint) -> anyhow::Result<()> {
+        let model = rule_window_checkpoint::ActiveModel {
+            id: Set(WINDOW_CHECKPOINT_ID.to_string()),
+            data: Set(serde_json::to_value(checkpoint)?),
+            updated_at: Set(Utc::now()),
+        };
+        rule_window_checkpoint::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(rule_window_checkpoint::Column::Id)
+                    .update_columns([
+                        rule_window_checkpoint::Column::Data,
+                        rule_window_checkpoint::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&*self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn load_window_checkpoint(&self) -> anyhow::Result<Option<WindowCheckpoint>> {
+        rule_window_checkpoint::Entity::find_by_id(WINDOW_CHECKPOINT_ID.to_string())
+            .one(&*self.db)
+            .await?
+            .map(|m| serde_json::from_value(m.data).context("Invalid stored window checkpoint"))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RuleContext;
+    use crate::engine::RuleEngine;
+
+    #[tokio::test]
+    async fn test_rules_and_history_survive_restart() {
+        let db = Arc::new(Database::connect("sqlite::memory:").await.unwrap());
+        let storage = Arc::new(DbRuleStorage::new(db.clone()));
+        storage.init_schema().await.unwrap();
+
+        let engine = RuleEngine::with_storage(storage);
+        let rule = Rule {
+            name: "persisted".to_string(),
+            group: Some("scene".to_string()),
+            script: "let x = 1;".to_string(),
+            ..Default::default()
+        };
+        let rule_id = engine.add_rule(rule.clone()).await.unwrap();
+        engine
+            .update_rule(Rule {
+                id: rule_id.clone(),
+                script: "let x = 2;".to_string(),
+                ..rule
+            })
+            .await
+            .unwrap();
+        engine.trigger_manual(&rule_id, RuleContext::new()).await.unwrap();
+
+        // 新引擎实例（模拟重启）读取同一数据库
+        let storage = Arc::new(DbRuleStorage::new(db));
+        storage.init_schema().await.unwrap();
+        let engine = RuleEngine::with_storage(storage);
+
+        let restored = engine.get_rule(&rule_id).await.unwrap();
+        assert_eq!(restored.version, 2);
+        assert_eq!(restored.script, "let x = 2;");
+        assert_eq!(engine.rule_versions(&rule_id).await.unwrap().len(), 2);
+        assert_eq!(engine.enable_group("scene", false).await.unwrap(), 1);
+
+        let history = engine.get_execution_history(Some(&rule_id), 10).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].status, ExecutionStatus::Success);
+        assert!(history[0].finished_at.is_some());
+
+        let rolled_back = engine.rollback_rule(&rule_id, 1).await.unwrap();
+        assert_eq!(rolled_back.version, 4);
+        assert_eq!(rolled_back.script, "let x = 1;");
+
+        engine.delete_rule(&rule_id).await.unwrap();
+        assert!(engine.get_rule(&rule_id).await.is_err());
+        assert!(engine.rule_versions(&rule_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_window_checkpoint_survives_restart() {
+        use crate::window::{Aggregate, CompareOp, WindowCondition, WindowKey};
+
+        let db = Arc::new(Database::connect("sqlite::memory:").await.unwrap());
+        let storage = Arc::new(DbRuleStorage::new(db.clone()));
+        storage.init_schema().await.unwrap();
+        assert!(storage.load_window_checkpoint().await.unwrap().is_none());
+
+        let engine = RuleEngine::with_storage(storage);
+        let condition = WindowCondition::Sliding {
+            metric: Some("temperature".to_string()),
+            aggregate: Aggregate::Avg,
+            window_seconds: 600,
+            op: CompareOp::Gt,
+            threshold: 30.0,
+        };
+        let rule_id = engine
+            .add_rule(Rule {
+                name: "windowed".to_string(),
+                script: "let x = 1;".to_string(),
+                condition: Some(condition.clone()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let key = WindowKey::new(&rule_id, "th_01", "condition");
+        engine.windows().sliding(&key, 20.0, 600_000, 0);
+        engine.windows().sliding(&key, 40.0, 600_000, 1_000);
+        assert_eq!(engine.checkpoint_windows().await.unwrap(), 1);
+        // 覆盖保存
+        assert_eq!(engine.checkpoint_windows().await.unwrap(), 1);
+
+        let storage = Arc::new(DbRuleStorage::new(db));
+        storage.init_schema().await.unwrap();
+        let engine = RuleEngine::with_storage(storage);
+        assert_eq!(engine.get_rule(&rule_id).await.unwrap().condition, Some(condition));
+        assert_eq!(engine.restore_windows().await.unwrap(), 1);
+        let stats = engine.windows().sliding(&key, 60.0, 600_000, 2_000);
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.avg, 40.0);
+
+        engine.delete_rule(&rule_id).await.unwrap();
+        assert!(engine.windows().is_empty());
+    }
+}
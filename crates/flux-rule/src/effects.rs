@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::RefCell;
+
+/// 测试模式下被拦截的副作用（下发指令、修改设备状态、发送通知、写入事件）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SideEffect {
+    /// 动作，如 `control_device`、`send_notification`
+    pub action: String,
+    /// 调用参数
+    pub args: Value,
+}
+
+impl std::fmt::Display for SideEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}({})", self.action, self.args)
+    }
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Vec<SideEffect>>> = const { RefCell::new(None) };
+}
+
+/// 恢复外层记录状态（脚本 panic 时也需恢复，阻塞线程池的线程会被复用）
+struct RecordGuard {
+    previous: Option<Vec<SideEffect>>,
+}
+
+impl Drop for RecordGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        RECORDER.with(|recorder| *recorder.borrow_mut() = previous);
+    }
+}
+
+/// 在当前线程执行 `f`，期间内置函数的副作用只记录不执行
+pub(crate) fn record<T>(f: impl FnOnce() -> T) -> (T, Vec<SideEffect>) {
+    let guard = RecordGuard {
+        previous: RECORDER.with(|recorder| recorder.replace(Some(Vec::new()))),
+    };
+    let result = f();
+    let effects = RECORDER.with(|recorder| recorder.borrow_mut().take()).unwrap_or_default();
+    drop(guard);
+    (result, effects)
+}
+
+/// 处于记录状态时记录副作用并返回 true，调用方应跳过实际执行
+pub(crate) fn intercept(action: &str, args: impl FnOnce() -> Value) -> bool {
+    RECORDER.with(|recorder| match recorder.borrow_mut().as_mut() {
+        Some(effects) => {
+            effects.push(SideEffect {
+                action: action.to_string(),
+                args: args(),
+            });
+            true
+        }
+        None => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_side_effects() {
+        assert!(!intercept("send_sms", || json!({})));
+
+        let (value, effects) = record(|| {
+            assert!(intercept("send_sms", || json!({"phone": "10086"})));
+            let (_, inner) = record(|| intercept("record_event", || json!("inner")));
+            assert_eq!(inner.len(), 1);
+            42
+        });
+        assert_eq!(value, 42);
+        assert_eq!(effects.len(), 1);
+        assert_eq!(effects[0].to_string(), r#"send_sms({"phone":"10086"})"#);
+
+        // panic 后恢复为不记录
+        let _ = std::panic::catch_unwind(|| record(|| panic!("script panicked")));
+        assert!(!intercept("send_sms", || json!({})));
+    }
+}
@@ -1,14 +1,21 @@
 use crate::context::RuleContext;
-use crate::execution::{ExecutionStatus, RuleExecution, TestResult};
-use crate::model::Rule;
-use crate::storage::RuleStorage;
+use crate::effects::{self, SideEffect};
+use crate::execution::{ExecutionStatus, RuleExecution, RuleOutcome, TestResult};
+use crate::functions::{parse_channel, register_builtin_functions};
+use crate::model::{ConflictStrategy, Rule, RuleMetadata};
+use crate::ruleset::{ImportSummary, RuleSet, RuleSetFormat};
+use crate::services::RuleServices;
+use crate::storage::{RuleStorage, RuleStore};
+use crate::window::{register_window_functions, WindowStore};
 use anyhow::Result;
 use chrono::Utc;
+use flux_notify::{NotifyManager, NotifyMessage};
 use flux_script::ScriptEngine;
-use std::collections::HashMap;
+use futures::future::join_all;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use tracing::{debug, error, info, warn};
 
 /// 规则引擎
@@ -16,29 +23,80 @@ pub struct RuleEngine {
     /// Rhai 脚本引擎
     script_engine: Arc<ScriptEngine>,
     
-    /// 规则存储
-    storage: Arc<RuleStorage>,
-    
-    /// 执行历史
-    executions: Arc<RwLock<Vec<RuleExecution>>>,
+    /// 规则存储（含版本与执行历史）
+    storage: Arc<dyn RuleStore>,
     
     /// 限流计数器 (rule_id -> (timestamp, count))
     rate_limit_counters: Arc<RwLock<HashMap<String, Vec<i64>>>>,
+    
+    /// 窗口状态（规则窗口条件与脚本窗口函数共用）
+    windows: Arc<WindowStore>,
+    
+    /// 执行结果通知
+    notify: Option<Arc<NotifyManager>>,
+    
+    /// 互斥组锁 (group -> lock)
+    exclusive_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
 }
 
 impl RuleEngine {
+    /// 使用内存存储（重启后规则丢失）
     pub fn new() -> Self {
-        let script_engine = ScriptEngine::new();
-        
+        Self::with_storage(Arc::new(RuleStorage::new()))
+    }
+    
+    /// 使用指定存储，如 [`crate::DbRuleStorage`]
+    pub fn with_storage(storage: Arc<dyn RuleStore>) -> Self {
+        let windows = Arc::new(WindowStore::new());
         Self {
-            script_engine: Arc::new(script_engine),
-            storage: Arc::new(RuleStorage::new()),
-            executions: Arc::new(RwLock::new(Vec::new())),
+            script_engine: Arc::new(Self::build_script_engine(&RuleServices::default(), &windows)),
+            storage,
             rate_limit_counters: Arc::new(RwLock::new(HashMap::new())),
+            windows,
+            notify: None,
+            exclusive_locks: Arc::new(Mutex::new(HashMap::new())),
         }
     }
     
-    /// 添加规则
+    /// 注入内置函数使用的服务（设备控制、设备数据、时序数据、通知）；通知服务同时用于执行结果通知
+    pub fn with_services(mut self, services: RuleServices) -> Self {
+        self.script_engine = Arc::new(Self::build_script_engine(&services, &self.windows));
+        self.notify = services.notify;
+        self
+    }
+    
+    fn build_script_engine(services: &RuleServices, windows: &Arc<WindowStore>) -> ScriptEngine {
+        let mut script_engine = ScriptEngine::new();
+        register_builtin_functions(&mut script_engine, services);
+        register_window_functions(&mut script_engine, windows.clone());
+        script_engine
+    }
+    
+    /// 窗口状态
+    pub fn windows(&self) -> &Arc<WindowStore> {
+        &self.windows
+    }
+    
+    /// 保存窗口状态检查点到规则存储，返回状态数量
+    pub async fn checkpoint_windows(&self) -> Result<usize> {
+        let checkpoint = self.windows.checkpoint();
+        self.storage.save_window_checkpoint(&checkpoint).await?;
+        debug!(states = checkpoint.len(), "Window checkpoint saved");
+        Ok(checkpoint.len())
+    }
+    
+    /// 从最近的检查点恢复窗口状态，返回状态数量
+    pub async fn restore_windows(&self) -> Result<usize> {
+        let Some(checkpoint) = self.storage.load_window_checkpoint().await? else {
+            return Ok(0);
+        };
+        let count = checkpoint.len();
+        self.windows.restore(checkpoint);
+        info!(states = count, "Window states restored");
+        Ok(count)
+    }
+    
+    /// 添加规则（ID 已存在时返回错误，保存新版本请使用 `update_rule`）
     pub async fn add_rule(&self, mut rule: Rule) -> Result<String> {
         // 验证脚本语法
         self.script_engine.compile(&rule.script)?;
@@ -48,23 +106,98 @@ impl RuleEngine {
             rule.id = uuid::Uuid::new_v4().to_string();
         }
         
+        if self.storage.get(&rule.id).await?.is_some() {
+            return Err(anyhow::anyhow!("Rule already exists: {}", rule.id));
+        }
+        self.check_dependencies(&rule).await?;
+        
         // 保存规则
-        self.storage.save(rule.clone()).await?;
+        rule.version = rule.version.max(1);
+        self.storage.save(&rule).await?;
         
         info!(rule_id = %rule.id, rule_name = %rule.name, "Rule added");
         
         Ok(rule.id)
     }
     
+    /// 更新规则，保存为新版本（版本号递增，旧版本保留）
+    pub async fn update_rule(&self, mut rule: Rule) -> Result<Rule> {
+        self.script_engine.compile(&rule.script)?;
+        let current = self.get_rule(&rule.id).await?;
+        self.check_dependencies(&rule).await?;
+        
+        // 窗口条件变化后旧状态不再适用
+        if rule.condition != current.condition {
+            self.windows.clear_scope(&rule.id);
+        }
+        
+        rule.version = current.version + 1;
+        rule.previous_version = Some(current.version_id());
+        rule.metadata = RuleMetadata {
+            updated_at: Utc::now(),
+            ..current.metadata
+        };
+        self.storage.save(&rule).await?;
+        
+        info!(rule_id = %rule.id, version = rule.version, "Rule updated");
+        Ok(rule)
+    }
+    
+    /// 回滚到指定版本：以该版本内容保存一个新版本
+    pub async fn rollback_rule(&self, rule_id: &str, version: i32) -> Result<Rule> {
+        let target = self.storage.get_version(rule_id, version).await?
+            .ok_or_else(|| anyhow::anyhow!("Rule version not found: {}@v{}", rule_id, version))?;
+        
+        let rule = self.update_rule(target).await?;
+        info!(rule_id = %rule_id, from_version = version, version = rule.version, "Rule rolled back");
+        Ok(rule)
+    }
+    
+    /// 规则的全部版本（按版本号升序）
+    pub async fn rule_versions(&self, rule_id: &str) -> Result<Vec<Rule>> {
+        self.storage.versions(rule_id).await
+    }
+    
+    /// 检查依赖：依赖的规则必须已存在，且不能依赖自身或形成循环
+    async fn check_dependencies(&self, rule: &Rule) -> Result<()> {
+        if rule.dependencies.is_empty() {
+            return Ok(());
+        }
+        
+        let mut graph: HashMap<String, Vec<String>> = self.storage.list().await?
+            .into_iter()
+            .map(|r| (r.id, r.dependencies))
+            .collect();
+        if let Some(missing) = rule.dependencies.iter().find(|dep| *dep != &rule.id && !graph.contains_key(*dep)) {
+            return Err(anyhow::anyhow!("Rule '{}' depends on missing rule: {}", rule.id, missing));
+        }
+        graph.insert(rule.id.clone(), rule.dependencies.clone());
+        
+        match dependency_cycle(&graph, &rule.id) {
+            Some(cycle) => Err(anyhow::anyhow!("Rule dependency cycle: {}", cycle.join(" -> "))),
+            None => Ok(()),
+        }
+    }
+    
     /// 获取规则
     pub async fn get_rule(&self, rule_id: &str) -> Result<Rule> {
         self.storage.get(rule_id).await?
             .ok_or_else(|| anyhow::anyhow!("Rule not found: {}", rule_id))
     }
     
-    /// 删除规则
+    /// 删除规则（同时清除其窗口状态），仍被其他规则依赖时返回错误
     pub async fn delete_rule(&self, rule_id: &str) -> Result<()> {
+        let dependents: Vec<String> = self.storage.list().await?
+            .into_iter()
+            .filter(|rule| rule.dependencies.iter().any(|dep| dep == rule_id))
+            .map(|rule| rule.id)
+            .collect();
+        if !dependents.is_empty() {
+            return Err(anyhow::anyhow!("Rule '{}' is required by: {}", rule_id, dependents.join(", ")));
+        }
+        
         self.storage.delete(rule_id).await?;
+        self.windows.clear_scope(rule_id);
         info!(rule_id = %rule_id, "Rule deleted");
         Ok(())
     }
@@ -85,13 +218,113 @@ impl RuleEngine {
         self.execute_rule(&rule, context).await
     }
     
+    /// 执行同一事件触发的一批规则，返回各规则的结果（按完成顺序）
+    ///
+    /// - 按优先级从高到低执行；批次内被依赖的规则先执行，依赖未成功时跳过
+    /// - `Parallel` 规则并发执行，`Sequential` 与 `Exclusive` 规则按优先级逐条执行
+    /// - `Exclusive` 同组只执行优先级最高的一条，且不与其他批次中的同组规则同时执行
+    pub async fn execute_rules(&self, mut batch: Vec<(Rule, RuleContext)>) -> Vec<(String, RuleOutcome)> {
+        batch.sort_by(|(a, _), (b, _)| b.priority.cmp(&a.priority).then_with(|| a.id.cmp(&b.id)));
+        let batch_ids: HashSet<String> = batch.iter().map(|(rule, _)| rule.id.clone()).collect();
+        
+        let mut outcomes = Vec::with_capacity(batch.len());
+        let mut pending = Vec::with_capacity(batch.len());
+        let mut exclusive_winners: HashMap<String, String> = HashMap::new();
+        for (rule, context) in batch {
+            if !rule.enabled {
+                outcomes.push((rule.id, RuleOutcome::Skipped("Rule is disabled".to_string())));
+                continue;
+            }
+            if let ConflictStrategy::Exclusive { group } = &rule.conflict_strategy {
+                if let Some(winner) = exclusive_winners.get(group) {
+                    let reason = format!("Exclusive group '{}' runs rule '{}'", group, winner);
+                    outcomes.push((rule.id, RuleOutcome::Skipped(reason)));
+                    continue;
+                }
+                exclusive_winners.insert(group.clone(), rule.id.clone());
+            }
+            pending.push((rule, context));
+        }
+        
+        // 已结束的规则是否成功
+        let mut finished: HashMap<String, bool> = outcomes.iter().map(|(id, _)| (id.clone(), false)).collect();
+        while !pending.is_empty() {
+            let (ready, waiting): (Vec<_>, Vec<_>) = pending.into_iter().partition(|(rule, _)| {
+                rule.dependencies.iter().all(|dep| !batch_ids.contains(dep) || finished.contains_key(dep))
+            });
+            pending = waiting;
+            if ready.is_empty() {
+                // 添加规则时已拒绝循环依赖，这里只防御存储中的旧数据
+                for (rule, _) in pending.drain(..) {
+                    outcomes.push((rule.id, RuleOutcome::Skipped("Rule dependency cycle".to_string())));
+                }
+                break;
+            }
+            
+            let mut parallel = Vec::new();
+            let mut sequential = Vec::new();
+            for (rule, context) in ready {
+                if let Some(dep) = rule.dependencies.iter().find(|dep| finished.get(*dep) == Some(&false)) {
+                    let reason = format!("Dependency '{}' did not succeed", dep);
+                    debug!(rule_id = %rule.id, dependency = %dep, "Rule skipped");
+                    finished.insert(rule.id.clone(), false);
+                    outcomes.push((rule.id, RuleOutcome::Skipped(reason)));
+                    continue;
+                }
+                match rule.conflict_strategy {
+                    ConflictStrategy::Parallel => parallel.push((rule, context)),
+                    _ => sequential.push((rule, context)),
+                }
+            }
+            
+            let parallel_runs = join_all(parallel.into_iter().map(|(rule, context)| async move {
+                let outcome = self.run_rule(&rule, context).await;
+                (rule.id, outcome)
+            }));
+            let sequential_runs = async {
+                let mut results = Vec::with_capacity(sequential.len());
+                for (rule, context) in sequential {
+                    let outcome = self.run_rule(&rule, context).await;
+                    results.push((rule.id, outcome));
+                }
+                results
+            };
+            let (parallel_results, sequential_results) = tokio::join!(parallel_runs, sequential_runs);
+            for (rule_id, outcome) in parallel_results.into_iter().chain(sequential_results) {
+                finished.insert(rule_id.clone(), outcome.is_success());
+                outcomes.push((rule_id, outcome));
+            }
+        }
+        
+        outcomes
+    }
+    
     /// 执行规则
     async fn execute_rule(&self, rule: &Rule, context: RuleContext) -> Result<()> {
+        match self.run_rule(rule, context).await {
+            RuleOutcome::Success => Ok(()),
+            RuleOutcome::Failed(error) | RuleOutcome::Skipped(error) => Err(anyhow::anyhow!(error)),
+        }
+    }
+    
+    async fn run_rule(&self, rule: &Rule, context: RuleContext) -> RuleOutcome {
+        // 互斥组内同时只执行一条规则
+        let _exclusive = match &rule.conflict_strategy {
+            ConflictStrategy::Exclusive { group } => match self.exclusive_lock(group).await.try_lock_owned() {
+                Ok(guard) => Some(guard),
+                Err(_) => {
+                    debug!(rule_id = %rule.id, group = %group, "Exclusive group busy, rule skipped");
+                    return RuleOutcome::Skipped(format!("Exclusive group '{}' is busy", group));
+                }
+            },
+            _ => None,
+        };
+        
         // 检查限流
         if let Some(rate_limit) = &rule.rate_limit {
             if !self.check_rate_limit(&rule.id, rate_limit).await {
                 warn!(rule_id = %rule.id, "Rate limit exceeded");
-                return Err(anyhow::anyhow!("Rate limit exceeded"));
+                return RuleOutcome::Skipped("Rate limit exceeded".to_string());
             }
         }
         
@@ -107,43 +340,45 @@ impl RuleEngine {
             status: ExecutionStatus::Running,
             error: None,
             context: serde_json::to_value(&context).unwrap_or_default(),
+            side_effects: None,
         };
         
         // 保存执行记录
-        {
-            let mut executions = self.executions.write().await;
-            executions.push(execution.clone());
-        }
+        self.save_execution(&execution).await;
         
         // 执行脚本（带超时）
         let timeout = Duration::from_secs(rule.timeout_seconds);
         let script = rule.script.clone();
         let engine = self.script_engine.clone();
         
-        let result = tokio::time::timeout(timeout, async move {
-            // 准备脚本上下文
-            let mut scope = rhai::Scope::new();
-            
-            // 注入设备数据
-            scope.push("device", context.device_data.clone());
-            
-            // 注入系统变量
-            scope.push("system", context.system_vars.clone());
-            
-            // 注入参数
-            scope.push("params", rule.parameters.clone());
-            
-            // 执行脚本
-            engine.eval_with_scope(&mut scope, &script)
-        }).await;
+        // 准备脚本上下文
+        let mut scope = script_scope(rule, &context);
+        
+        // 内置函数会同步等待服务调用，脚本在阻塞线程池中执行；测试模式只记录副作用
+        let test_mode = rule.test_mode;
+        let mut eval = move || engine.eval_with_scope(&mut scope, &script);
+        let run = move || if test_mode { effects::record(eval) } else { (eval(), Vec::new()) };
+        let mut side_effects = Vec::new();
+        let result = tokio::time::timeout(timeout, tokio::task::spawn_blocking(run))
+            .await
+            .map(|joined| match joined {
+                Ok((result, effects)) => {
+                    side_effects = effects;
+                    result
+                }
+                Err(e) => Err(anyhow::Error::from(e)),
+            });
         
         // 更新执行记录
         execution.finished_at = Some(Utc::now());
+        if test_mode {
+            execution.side_effects = Some(side_effects);
+        }
         
         match result {
             Ok(Ok(_)) => {
                 execution.status = ExecutionStatus::Success;
-                debug!(rule_id = %rule.id, "Rule executed successfully");
+                debug!(rule_id = %rule.id, test_mode, "Rule executed successfully");
             }
             Ok(Err(e)) => {
                 execution.status = ExecutionStatus::Failed;
@@ -157,13 +392,10 @@ impl RuleEngine {
             }
         }
         
+        self.notify_result(rule, &mut execution).await;
+        
         // 更新执行历史
-        {
-            let mut executions = self.executions.write().await;
-            if let Some(exec) = executions.iter_mut().find(|e| e.id == execution_id) {
-                *exec = execution.clone();
-            }
-        }
+        self.save_execution(&execution).await;
         
         // 记录限流计数
         if rule.rate_limit.is_some() {
@@ -171,9 +403,63 @@ impl RuleEngine {
         }
         
         if execution.status == ExecutionStatus::Success {
-            Ok(())
+            RuleOutcome::Success
         } else {
-            Err(anyhow::anyhow!("{}", execution.error.unwrap_or_default()))
+            RuleOutcome::Failed(execution.error.unwrap_or_default())
+        }
+    }
+    
+    async fn exclusive_lock(&self, group: &str) -> Arc<Mutex<()>> {
+        self.exclusive_locks.lock().await.entry(group.to_string()).or_default().clone()
+    }
+    
+    /// 按规则配置通过 `notification_channels` 发送执行结果（`all` 为全部渠道）；测试模式只记录
+    async fn notify_result(&self, rule: &Rule, execution: &mut RuleExecution) {
+        let success = execution.status == ExecutionStatus::Success;
+        let wanted = if success { rule.notification_on_success } else { rule.notification_on_failure };
+        if !wanted || rule.notification_channels.is_empty() {
+            return;
+        }
+        
+        let message = if success {
+            NotifyMessage::info(format!("Rule '{}' succeeded", rule.name), format!("Execution {} succeeded", execution.id))
+        } else {
+            NotifyMessage::error(format!("Rule '{}' failed", rule.name), execution.error.clone().unwrap_or_default())
+        }
+        .with_metadata(serde_json::json!({
+            "rule_id": rule.id,
+            "execution_id": execution.id,
+            "status": execution.status,
+        }));
+        
+        if let Some(side_effects) = execution.side_effects.as_mut() {
+            side_effects.push(SideEffect {
+                action: "notify_result".to_string(),
+                args: serde_json::json!({
+                    "channels": rule.notification_channels,
+                    "title": message.title,
+                    "content": message.content,
+                }),
+            });
+            return;
+        }
+        let Some(notify) = &self.notify else {
+            warn!(rule_id = %rule.id, "Notify manager not configured, rule notification skipped");
+            return;
+        };
+        
+        for name in &rule.notification_channels {
+            let result = match parse_channel(name) {
+                Some(channel) => notify.send(channel, &message).await,
+                None if name.eq_ignore_ascii_case("all") => notify.broadcast(&message).await,
+                None => {
+                    warn!(rule_id = %rule.id, channel = %name, "Unknown rule notification channel");
+                    continue;
+                }
+            };
+            if let Err(e) = result {
+                warn!(rule_id = %rule.id, channel = %name, error = %e, "Failed to send rule notification");
+            }
         }
     }
     
@@ -183,16 +469,21 @@ impl RuleEngine {
         
         let start = std::time::Instant::now();
         let mut logs = Vec::new();
-        let mut actions = Vec::new();
         
         // 准备测试环境
-        let mut scope = rhai::Scope::new();
-        scope.push("device", mock_context.device_data.clone());
-        scope.push("system", mock_context.system_vars.clone());
-        scope.push("params", rule.parameters.clone());
+        let mut scope = script_scope(&rule, &mock_context);
         
-        // 执行脚本
-        let result = self.script_engine.eval_with_scope(&mut scope, &rule.script);
+        // 执行脚本，副作用只记录为动作
+        let engine = self.script_engine.clone();
+        let script = rule.script.clone();
+        let (result, actions) = match tokio::task::spawn_blocking(move || {
+            effects::record(|| engine.eval_with_scope(&mut scope, &script))
+        })
+        .await
+        {
+            Ok((result, effects)) => (result, effects.iter().map(ToString::to_string).collect()),
+            Err(e) => (Err(anyhow::Error::from(e)), Vec::new()),
+        };
         
         let duration_ms = start.elapsed().as_millis() as u64;
         
@@ -220,26 +511,16 @@ impl RuleEngine {
         }
     }
     
-    /// 获取执行历史
+    /// 获取执行历史（按时间倒序）
     pub async fn get_execution_history(&self, rule_id: Option<&str>, limit: usize) -> Result<Vec<RuleExecution>> {
-        let executions = self.executions.read().await;
-        
-        let mut filtered: Vec<_> = if let Some(rid) = rule_id {
-            executions.iter()
-                .filter(|e| e.rule_id == rid)
-                .cloned()
-                .collect()
-        } else {
-            executions.clone()
-        };
-        
-        // 按时间倒序
-        filtered.sort_by(|a, b| b.started_at.cmp(&a.started_at));
-        
-        // 限制数量
-        filtered.truncate(limit);
-        
-        Ok(filtered)
+        self.storage.executions(rule_id, limit).await
+    }
+    
+    /// 保存执行记录（存储失败不影响规则执行）
+    async fn save_execution(&self, execution: &RuleExecution) {
+        if let Err(e) = self.storage.save_execution(execution).await {
+            warn!(execution_id = %execution.id, error = %e, "Failed to save rule execution");
+        }
     }
     
     /// 检查限流
@@ -273,9 +554,10 @@ impl RuleEngine {
         let rules = self.storage.find_by_group(group).await?;
         let mut count = 0;
         
-        for mut rule in rules {
+        // 只为状态变化的规则保存新版本
+        for mut rule in rules.into_iter().filter(|r| r.enabled != enabled) {
             rule.enabled = enabled;
-            self.storage.save(rule).await?;
+            self.update_rule(rule).await?;
             count += 1;
         }
         
@@ -287,6 +569,120 @@ impl RuleEngine {
     pub async fn find_by_tag(&self, tag: &str) -> Result<Vec<Rule>> {
         self.storage.find_by_tag(tag).await
     }
+    
+    /// 导出全部规则（按名称排序）
+    pub async fn export_rules(&self, format: RuleSetFormat) -> Result<String> {
+        let mut rules = self.storage.list().await?;
+        rules.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        RuleSet::new(rules).serialize(format)
+    }
+    
+    /// 导入规则集：先校验全部脚本与依赖，任一失败则不导入
+    ///
+    /// 已存在的规则在 `overwrite` 为 true 时保存为新版本，否则整体不导入。
+    /// 依赖可指向已有规则或同一规则集中的规则，被依赖的规则先保存。
+    pub async fn import_rules(&self, content: &str, format: RuleSetFormat, overwrite: bool) -> Result<ImportSummary> {
+        let set = RuleSet::parse(content, format)?;
+        let mut seen = HashSet::new();
+        let mut existing = HashSet::new();
+        for rule in &set.rules {
+            self.script_engine.compile(&rule.script)
+                .map_err(|e| anyhow::anyhow!("Rule '{}' ({}) has an invalid script: {}", rule.name, rule.id, e))?;
+            if !rule.id.is_empty() && !seen.insert(rule.id.as_str()) {
+                return Err(anyhow::anyhow!("Duplicate rule id in rule set: {}", rule.id));
+            }
+            if self.storage.get(&rule.id).await?.is_some() {
+                if !overwrite {
+                    return Err(anyhow::anyhow!("Rule '{}' already exists: {}", rule.name, rule.id));
+                }
+                existing.insert(rule.id.clone());
+            }
+        }
+        
+        let stored: HashSet<String> = self.storage.list().await?.into_iter().map(|rule| rule.id).collect();
+        let rules = dependency_order(set.rules, &stored)?;
+        
+        let mut summary = ImportSummary::default();
+        for rule in rules {
+            if existing.contains(&rule.id) {
+                summary.updated.push(self.update_rule(rule).await?.id);
+            } else {
+                summary.created.push(self.add_rule(rule).await?);
+            }
+        }
+        
+        info!(created = summary.created.len(), updated = summary.updated.len(), "Rules imported");
+        Ok(summary)
+    }
+}
+
+/// 脚本变量：`device`（设备数据）、`system`（系统变量）、`params`（规则参数），均为 Rhai Map
+fn script_scope(rule: &Rule, context: &RuleContext) -> rhai::Scope<'static> {
+    let to_map = |values: &HashMap<String, serde_json::Value>| {
+        rhai::serde::to_dynamic(values).unwrap_or_else(|_| rhai::Dynamic::from_map(rhai::Map::new()))
+    };
+    
+    let mut scope = rhai::Scope::new();
+    scope.push("device", to_map(&context.device_data));
+    scope.push("system", to_map(&context.system_vars));
+    scope.push("params", to_map(&rule.parameters));
+    scope
+}
+
+/// 从 `start` 沿依赖能回到 `start` 时返回环路径（如 `a -> b -> a`）
+fn dependency_cycle(graph: &HashMap<String, Vec<String>>, start: &str) -> Option<Vec<String>> {
+    fn visit(
+        graph: &HashMap<String, Vec<String>>,
+        node: &str,
+        start: &str,
+        visited: &mut HashSet<String>,
+        path: &mut Vec<String>,
+    ) -> bool {
+        for dependency in graph.get(node).into_iter().flatten() {
+            if dependency == start {
+                path.push(dependency.clone());
+                return true;
+            }
+            if visited.insert(dependency.clone()) {
+                path.push(dependency.clone());
+                if visit(graph, dependency, start, visited, path) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+    
+    let mut path = vec![start.to_string()];
+    visit(graph, start, start, &mut HashSet::new(), &mut path).then_some(path)
+}
+
+/// 按依赖排序待导入的规则：依赖须为已有规则或集合内规则，集合内被依赖的规则排在前面
+fn dependency_order(rules: Vec<Rule>, stored: &HashSet<String>) -> Result<Vec<Rule>> {
+    let incoming: HashSet<String> = rules.iter().map(|rule| rule.id.clone()).collect();
+    for rule in &rules {
+        if let Some(missing) = rule.dependencies.iter().find(|dep| !stored.contains(*dep) && !incoming.contains(*dep)) {
+            return Err(anyhow::anyhow!("Rule '{}' depends on missing rule: {}", rule.id, missing));
+        }
+    }
+    
+    let mut placed = HashSet::new();
+    let mut ordered = Vec::with_capacity(rules.len());
+    let mut pending = rules;
+    while !pending.is_empty() {
+        let (ready, waiting): (Vec<Rule>, Vec<Rule>) = pending.into_iter().partition(|rule| {
+            rule.dependencies.iter().all(|dep| !incoming.contains(dep) || placed.contains(dep))
+        });
+        if ready.is_empty() {
+            let ids: Vec<&str> = waiting.iter().map(|rule| rule.id.as_str()).collect();
+            return Err(anyhow::anyhow!("Rule dependency cycle among: {}", ids.join(", ")));
+        }
+        placed.extend(ready.iter().map(|rule| rule.id.clone()));
+        ordered.extend(ready);
+        pending = waiting;
+    }
+    Ok(ordered)
 }
 
 impl Default for RuleEngine {
@@ -342,5 +738,248 @@ mod tests {
         // 第三次应该失败（超过限流）
         assert!(engine.trigger_manual(&rule_id, context).await.is_err());
     }
+
+    #[tokio::test]
+    async fn test_update_and_rollback() {
+        let engine = RuleEngine::new();
+        let rule = Rule {
+            name: "versioned".to_string(),
+            script: "let x = 1;".to_string(),
+            ..Default::default()
+        };
+        let rule_id = engine.add_rule(rule.clone()).await.unwrap();
+        
+        // 重复 ID 不会静默生成新版本
+        let duplicate = Rule { id: rule_id.clone(), ..rule.clone() };
+        assert!(engine.add_rule(duplicate).await.is_err());
+        assert_eq!(engine.rule_versions(&rule_id).await.unwrap().len(), 1);
+        
+        let updated = engine.update_rule(Rule {
+            id: rule_id.clone(),
+            script: "let x = 2;".to_string(),
+            ..rule.clone()
+        }).await.unwrap();
+        assert_eq!(updated.version, 2);
+        assert_eq!(updated.previous_version, Some(format!("{}@v1", rule_id)));
+        
+        // 脚本无效时不产生新版本
+        let invalid = Rule { id: rule_id.clone(), script: "let = ;".to_string(), ..rule };
+        assert!(engine.update_rule(invalid).await.is_err());
+        
+        let rolled_back = engine.rollback_rule(&rule_id, 1).await.unwrap();
+        assert_eq!(rolled_back.version, 3);
+        assert_eq!(rolled_back.script, "let x = 1;");
+        assert_eq!(engine.rule_versions(&rule_id).await.unwrap().len(), 3);
+        assert!(engine.rollback_rule(&rule_id, 9).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import() {
+        let engine = RuleEngine::new();
+        let rule_id = engine.add_rule(Rule {
+            name: "exported".to_string(),
+            script: "let x = 1;".to_string(),
+            tags: vec!["demo".to_string()],
+            ..Default::default()
+        }).await.unwrap();
+        let yaml = engine.export_rules(RuleSetFormat::Yaml).await.unwrap();
+        
+        // 导入到另一个引擎为新建，再次导入为新版本
+        let other = RuleEngine::new();
+        let summary = other.import_rules(&yaml, RuleSetFormat::Yaml, false).await.unwrap();
+        assert_eq!(summary.created, vec![rule_id.clone()]);
+        // 已存在时需显式覆盖
+        assert!(other.import_rules(&yaml, RuleSetFormat::Yaml, false).await.is_err());
+        assert_eq!(other.get_rule(&rule_id).await.unwrap().version, 1);
+        let summary = other.import_rules(&yaml, RuleSetFormat::Yaml, true).await.unwrap();
+        assert_eq!(summary.updated, vec![rule_id.clone()]);
+        assert_eq!(other.get_rule(&rule_id).await.unwrap().version, 2);
+        
+        // 任一脚本无效时整体不导入
+        let invalid = r#"{"rules": [{"name": "ok", "script": "let a = 1;"}, {"name": "bad", "script": "let = ;"}]}"#;
+        assert!(other.import_rules(invalid, RuleSetFormat::Json, true).await.is_err());
+        assert_eq!(other.list_rules().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dependency_cycle_rejected() {
+        let engine = RuleEngine::new();
+        let rule = |id: &str, dependencies: &[&str]| Rule {
+            id: id.to_string(),
+            script: "let x = 1;".to_string(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            ..Default::default()
+        };
+        engine.add_rule(rule("a", &[])).await.unwrap();
+        engine.add_rule(rule("b", &["a"])).await.unwrap();
+        engine.add_rule(rule("c", &["b"])).await.unwrap();
+        
+        let err = engine.update_rule(rule("a", &["c"])).await.unwrap_err();
+        assert_eq!(err.to_string(), "Rule dependency cycle: a -> c -> b -> a");
+        assert!(engine.add_rule(rule("d", &["d"])).await.is_err());
+        assert_eq!(engine.get_rule("a").await.unwrap().version, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_missing_dependency_rejected() {
+        let engine = RuleEngine::new();
+        let rule = |id: &str, dependencies: &[&str]| Rule {
+            id: id.to_string(),
+            script: "let x = 1;".to_string(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            ..Default::default()
+        };
+        
+        let err = engine.add_rule(rule("b", &["a"])).await.unwrap_err();
+        assert_eq!(err.to_string(), "Rule 'b' depends on missing rule: a");
+        assert!(engine.list_rules().await.unwrap().is_empty());
+        
+        engine.add_rule(rule("a", &[])).await.unwrap();
+        engine.add_rule(rule("b", &["a"])).await.unwrap();
+        assert!(engine.update_rule(rule("b", &["a", "gone"])).await.is_err());
+        assert_eq!(engine.get_rule("b").await.unwrap().version, 1);
+        
+        // 被依赖的规则不能删除
+        assert!(engine.delete_rule("a").await.is_err());
+        engine.delete_rule("b").await.unwrap();
+        engine.delete_rule("a").await.unwrap();
+        
+        // 导入时依赖可来自同一规则集（顺序无关），缺失时整体不导入
+        let set = r#"{"rules": [
+            {"id": "y", "name": "y", "script": "let a = 1;", "dependencies": ["x"]},
+            {"id": "x", "name": "x", "script": "let a = 1;"}
+        ]}"#;
+        let summary = engine.import_rules(set, RuleSetFormat::Json, false).await.unwrap();
+        assert_eq!(summary.created, vec!["x".to_string(), "y".to_string()]);
+        let missing = r#"{"rules": [
+            {"id": "z", "name": "z", "script": "let a = 1;"},
+            {"id": "w", "name": "w", "script": "let a = 1;", "dependencies": ["nope"]}
+        ]}"#;
+        assert!(engine.import_rules(missing, RuleSetFormat::Json, false).await.is_err());
+        assert!(engine.get_rule("z").await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_execute_rules_with_conflict_strategies() {
+        let engine = RuleEngine::new();
+        let rules = [
+            ("seq_low", 10, ConflictStrategy::Sequential, "let x = 1;", vec![]),
+            ("seq_high", 90, ConflictStrategy::Sequential, "let x = 1;", vec![]),
+            ("after_low", 95, ConflictStrategy::Parallel, "let x = 1;", vec!["seq_low"]),
+            ("valve_open", 80, ConflictStrategy::Exclusive { group: "valve".into() }, "let x = 1;", vec![]),
+            ("valve_close", 20, ConflictStrategy::Exclusive { group: "valve".into() }, "let x = 1;", vec![]),
+            ("broken", 50, ConflictStrategy::Parallel, "throw \"broken\";", vec![]),
+            ("child", 50, ConflictStrategy::Parallel, "let x = 1;", vec!["broken"]),
+            ("grandchild", 50, ConflictStrategy::Sequential, "let x = 1;", vec!["child"]),
+        ];
+        let mut batch = Vec::new();
+        for (id, priority, conflict_strategy, script, dependencies) in rules {
+            let rule = Rule {
+                id: id.to_string(),
+                priority,
+                conflict_strategy,
+                script: script.to_string(),
+                dependencies: dependencies.into_iter().map(str::to_string).collect(),
+                ..Default::default()
+            };
+            engine.add_rule(rule.clone()).await.unwrap();
+            batch.push((rule, RuleContext::new()));
+        }
+        
+        let outcomes = engine.execute_rules(batch).await;
+        let position = |id: &str| outcomes.iter().position(|(rule_id, _)| rule_id == id).unwrap();
+        let outcome = |id: &str| outcomes[position(id)].1.clone();
+        assert_eq!(outcomes.len(), 8);
+        assert!(position("seq_high") < position("valve_open"));
+        assert!(position("valve_open") < position("seq_low"));
+        assert!(position("seq_low") < position("after_low"));
+        assert_eq!(outcome("after_low"), RuleOutcome::Success);
+        assert_eq!(
+            outcome("valve_close"),
+            RuleOutcome::Skipped("Exclusive group 'valve' runs rule 'valve_open'".to_string())
+        );
+        assert!(matches!(outcome("broken"), RuleOutcome::Failed(_)));
+        assert_eq!(outcome("child"), RuleOutcome::Skipped("Dependency 'broken' did not succeed".to_string()));
+        assert_eq!(outcome("grandchild"), RuleOutcome::Skipped("Dependency 'child' did not succeed".to_string()));
+        
+        // 同组规则执行期间，其他触发跳过
+        let busy = engine.exclusive_lock("valve").await.lock_owned().await;
+        let err = engine.trigger_manual("valve_close", RuleContext::new()).await.unwrap_err();
+        assert_eq!(err.to_string(), "Exclusive group 'valve' is busy");
+        drop(busy);
+        engine.trigger_manual("valve_close", RuleContext::new()).await.unwrap();
+    }
+    
+    #[derive(Default)]
+    struct RecordingNotifier {
+        messages: Arc<std::sync::Mutex<Vec<NotifyMessage>>>,
+    }
+    
+    #[async_trait::async_trait]
+    impl flux_notify::Notifier for RecordingNotifier {
+        async fn send(&self, message: &NotifyMessage) -> anyhow::Result<flux_notify::NotifyResult> {
+            self.messages.lock().unwrap().push(message.clone());
+            Ok(flux_notify::NotifyResult::success())
+        }
+        
+        fn name(&self) -> &str {
+            "recording"
+        }
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_test_mode_and_notifications() {
+        use flux_notify::{NotifyChannel, NotifyLevel};
+        
+        let email = RecordingNotifier::default();
+        let emails = email.messages.clone();
+        let notify = Arc::new(NotifyManager::new(NotifyLevel::Info));
+        notify.register(NotifyChannel::Email, Box::new(email)).await;
+        let engine = RuleEngine::new().with_services(RuleServices::new().with_notify_manager(notify));
+        
+        let script = r#"
+            let id = control_device("relay_01", "set_state", #{ state: false });
+            if id != "test_mode" { throw "command executed"; }
+            send_sms("10086", "relay off");
+            record_event("relay_01", "switched", #{ state: false });
+        "#;
+        let rule_id = engine.add_rule(Rule {
+            name: "dry_run".to_string(),
+            script: script.to_string(),
+            test_mode: true,
+            notification_on_success: true,
+            notification_channels: vec!["email".to_string()],
+            ..Default::default()
+        }).await.unwrap();
+        engine.trigger_manual(&rule_id, RuleContext::new()).await.unwrap();
+        
+        let history = engine.get_execution_history(Some(&rule_id), 1).await.unwrap();
+        let effects = history[0].side_effects.clone().unwrap();
+        let actions: Vec<_> = effects.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, vec!["control_device", "send_notification", "record_event", "notify_result"]);
+        assert_eq!(effects[0].args["params"]["state"], false);
+        assert!(emails.lock().unwrap().is_empty());
+        
+        let result = engine.test_rule(&rule_id, RuleContext::new()).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.actions.len(), 3);
+        assert!(result.actions[1].starts_with("send_notification("));
+        
+        // 非测试模式失败时通过配置的渠道通知
+        let rule_id = engine.add_rule(Rule {
+            name: "failing".to_string(),
+            script: "throw \"valve stuck\";".to_string(),
+            notification_channels: vec!["email".to_string(), "pager".to_string()],
+            ..Default::default()
+        }).await.unwrap();
+        assert!(engine.trigger_manual(&rule_id, RuleContext::new()).await.is_err());
+        let history = engine.get_execution_history(Some(&rule_id), 1).await.unwrap();
+        assert!(history[0].side_effects.is_none());
+        let emails = emails.lock().unwrap();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].title, "Rule 'failing' failed");
+        assert!(emails[0].content.contains("valve stuck"));
+        assert_eq!(emails[0].metadata.as_ref().unwrap()["rule_id"], rule_id);
+    }
 }
 
@@ -0,0 +1,164 @@
+use flux_types::message::Message;
+use serde_json::{Map, Value};
+
+/// 触发规则的设备消息
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerEvent {
+    /// 设备事件
+    DeviceEvent {
+        device_id: String,
+        event_type: String,
+        data: Value,
+        topic: Option<String>,
+    },
+
+    /// 数据变化（一条消息可包含多个指标）
+    DataChange {
+        device_id: String,
+        metrics: Map<String, Value>,
+        topic: Option<String>,
+    },
+}
+
+/// 不作为指标的载荷字段
+const RESERVED_FIELDS: [&str; 3] = ["device_id", "timestamp", "ts"];
+
+impl TriggerEvent {
+    /// 从 EventBus 消息解析
+    ///
+    /// - 设备 ID：主题 `device/{id}/...` 或 `devices/{id}/...`，否则取载荷中的 `device_id`
+    /// - 设备事件：主题 `.../event/{type}`（或 `events`），或载荷含 `event_type`；事件数据取 `data` 字段，没有时为整个载荷
+    /// - 数据变化：载荷 `{"metric": .., "value": ..}`、`{"metrics": {..}}`，或载荷对象的其余字段
+    ///
+    /// 无法识别设备的消息返回 None。
+    pub fn from_message(message: &Message) -> Option<Self> {
+        let segments: Vec<&str> = message.topic.split('/').collect();
+        let payload = &message.payload;
+        let topic = Some(message.topic.clone());
+
+        let (device_id, rest) = match segments.as_slice() {
+            ["device" | "devices", id, rest @ ..] if !id.is_empty() => (id.to_string(), rest),
+            _ => (payload.get("device_id")?.as_str()?.to_string(), segments.as_slice()),
+        };
+
+        let topic_event = rest
+            .windows(2)
+            .find(|w| w[0] == "event" || w[0] == "events")
+            .map(|w| w[1].to_string());
+        let event_type = topic_event.or_else(|| payload.get("event_type")?.as_str().map(str::to_string));
+        if let Some(event_type) = event_type {
+            let data = payload.get("data").cloned().unwrap_or_else(|| payload.clone());
+            return Some(Self::DeviceEvent {
+                device_id,
+                event_type,
+                data,
+                topic,
+            });
+        }
+
+        let metrics = match (payload.get("metric").and_then(Value::as_str), payload.get("metrics")) {
+            (Some(metric), _) => {
+                let mut metrics = Map::new();
+                metrics.insert(metric.to_string(), payload.get("value").cloned().unwrap_or(Value::Null));
+                metrics
+            }
+            (None, Some(Value::Object(metrics))) => metrics.clone(),
+            _ => payload
+                .as_object()?
+                .iter()
+                .filter(|(k, _)| !RESERVED_FIELDS.contains(&k.as_str()))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        };
+        if metrics.is_empty() {
+            return None;
+        }
+        Some(Self::DataChange {
+            device_id,
+            metrics,
+            topic,
+        })
+    }
+
+    pub fn device_id(&self) -> &str {
+        match self {
+            Self::DeviceEvent { device_id, .. } | Self::DataChange { device_id, .. } => device_id,
+        }
+    }
+
+    pub fn topic(&self) -> Option<&str> {
+        match self {
+            Self::DeviceEvent { topic, .. } | Self::DataChange { topic, .. } => topic.as_deref(),
+        }
+    }
+}
+
+/// MQTT 风格的主题匹配（`+` 匹配一层，`#` 匹配剩余全部层级）
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut filter_parts = filter.split('/');
+    let mut topic_parts = topic.split('/');
+    loop {
+        match (filter_parts.next(), topic_parts.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(f), Some(t)) if f == t => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(topic: &str, payload: Value) -> Message {
+        Message::new(topic.to_string(), payload)
+    }
+
+    #[test]
+    fn test_classify_messages() {
+        let event = TriggerEvent::from_message(&message("device/door_01/event/open", json!({"by": "card"})));
+        assert_eq!(
+            event,
+            Some(TriggerEvent::DeviceEvent {
+                device_id: "door_01".into(),
+                event_type: "open".into(),
+                data: json!({"by": "card"}),
+                topic: Some("device/door_01/event/open".into()),
+            })
+        );
+
+        let event = TriggerEvent::from_message(&message(
+            "sensors/alarm",
+            json!({"device_id": "smoke_01", "event_type": "alarm", "data": {"level": 3}}),
+        ));
+        assert!(matches!(event, Some(TriggerEvent::DeviceEvent { ref device_id, ref data, .. })
+            if device_id == "smoke_01" && data == &json!({"level": 3})));
+
+        let Some(TriggerEvent::DataChange { device_id, metrics, .. }) = TriggerEvent::from_message(&message(
+            "devices/th_01/telemetry",
+            json!({"temperature": 21.5, "humidity": 40, "timestamp": 1}),
+        )) else {
+            panic!("expected data change");
+        };
+        assert_eq!(device_id, "th_01");
+        assert_eq!(metrics.len(), 2);
+
+        let event = TriggerEvent::from_message(&message("ingest", json!({"device_id": "m1", "metric": "power", "value": 5})));
+        assert!(matches!(event, Some(TriggerEvent::DataChange { ref metrics, .. }) if metrics["power"] == 5));
+
+        assert!(TriggerEvent::from_message(&message("system/heartbeat", json!({"uptime": 5}))).is_none());
+        assert!(TriggerEvent::from_message(&message("device/th_01/telemetry", json!({"device_id": "th_01"}))).is_none());
+    }
+
+    #[test]
+    fn test_topic_matches() {
+        assert!(topic_matches("device/+/telemetry", "device/th_01/telemetry"));
+        assert!(topic_matches("device/#", "device/th_01/event/open"));
+        assert!(topic_matches("device/th_01/telemetry", "device/th_01/telemetry"));
+        assert!(!topic_matches("device/+/telemetry", "device/th_01/event/open"));
+        assert!(!topic_matches("device/+", "device/th_01/telemetry"));
+    }
+}
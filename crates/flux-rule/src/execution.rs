@@ -1,3 +1,4 @@
+use crate::effects::SideEffect;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -14,6 +15,9 @@ pub struct RuleExecution {
     pub status: ExecutionStatus,
     pub error: Option<String>,
     pub context: Value,
+    /// 测试模式下记录的副作用（非测试模式为 None）
+    #[serde(default)]
+    pub side_effects: Option<Vec<SideEffect>>,
 }
 
 /// 执行状态
@@ -26,6 +30,21 @@ pub enum ExecutionStatus {
     Timeout,
 }
 
+/// 批量执行中单条规则的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleOutcome {
+    Success,
+    Failed(String),
+    /// 未执行：限流、互斥组中有其他规则执行、依赖的规则未成功
+    Skipped(String),
+}
+
+impl RuleOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success)
+    }
+}
+
 /// 测试结果
 #[derive(Debug, Clone)]
 pub struct TestResult {
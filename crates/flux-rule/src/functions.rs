@@ -1,148 +1,608 @@
+use crate::effects;
+use crate::services::RuleServices;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use flux_core::runtime::block_on;
+use flux_control::{CommandType, DeviceCommand};
+use flux_device::DeviceStatus;
+use flux_notify::{NotifyChannel, NotifyLevel, NotifyMessage};
 use flux_script::ScriptEngine;
+use flux_timeseries::{EventPoint, TimeSeriesQuery};
+use rhai::{Dynamic, EvalAltResult, Map};
 use tracing::{debug, error, info, warn};
 
+pub(crate) type FnResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// 测试模式下 `control_device` 返回的指令 ID
+pub const TEST_MODE_COMMAND_ID: &str = "test_mode";
+
+pub(crate) fn script_error(message: impl std::fmt::Display) -> Box<EvalAltResult> {
+    message.to_string().into()
+}
+
 /// 注册所有内置函数
-pub fn register_builtin_functions(engine: &mut ScriptEngine) {
+///
+/// 测试模式（[`crate::Rule::test_mode`]）下有副作用的函数只记录调用，不实际执行。
+pub fn register_builtin_functions(engine: &mut ScriptEngine, services: &RuleServices) {
     let rhai_engine = engine.engine_mut();
-    register_device_functions(rhai_engine);
-    register_notification_functions(rhai_engine);
-    register_data_functions(rhai_engine);
-    register_time_functions(rhai_engine);
+    register_device_functions(rhai_engine, services);
+    register_notification_functions(rhai_engine, services);
+    register_data_functions(rhai_engine, services);
+    register_time_functions(rhai_engine, services.utc_offset);
     register_log_functions(rhai_engine);
 }
 
 /// 注册设备控制函数
-fn register_device_functions(engine: &mut rhai::Engine) {
-    // control_device(device_id, command, params)
-    engine.register_fn("control_device", |device_id: &str, command: &str, params: rhai::Map| {
-        info!(
-            device_id = %device_id,
-            command = %command,
-            "Control device (mock)"
-        );
-        // TODO: 实际实现需要调用设备控制服务
-        debug!("Params: {:?}", params);
+fn register_device_functions(engine: &mut rhai::Engine, services: &RuleServices) {
+    // control_device(device_id, command, params) -> 指令 ID
+    let commands = services.commands.clone();
+    engine.register_fn("control_device", move |device_id: &str, command: &str, params: Map| -> FnResult<String> {
+        let params = map_to_json(params)?;
+        let kind = command_type(command, params.clone())?;
+        let args = || serde_json::json!({ "device_id": device_id, "command": command, "params": params });
+        if effects::intercept("control_device", args) {
+            return Ok(TEST_MODE_COMMAND_ID.to_string());
+        }
+        let Some(executor) = commands.clone() else {
+            warn!(device_id = %device_id, command = %command, "Command executor not configured, control_device skipped");
+            return Ok(String::new());
+        };
+        let command = DeviceCommand::new(device_id.to_string(), kind);
+        let command_id = block_on(executor.submit(command.clone())).map_err(script_error)?;
+
+        // 等待设备响应可能较久，在后台执行
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if let Err(e) = executor.execute(command).await {
+                        error!(error = %e, "Failed to execute rule command");
+                    }
+                });
+            }
+            Err(_) => block_on(executor.execute(command)).map_err(script_error)?,
+        }
+
+        info!(device_id = %device_id, command_id = %command_id, "Rule command submitted");
+        Ok(command_id)
     });
-    
-    // read_device(device_id, metric)
-    engine.register_fn("read_device", |device_id: &str, metric: &str| -> rhai::Dynamic {
-        debug!(device_id = %device_id, metric = %metric, "Read device (mock)");
-        // TODO: 实际实现需要调用设备读取服务
-        rhai::Dynamic::from(0.0)
+
+    // command_status(command_id) -> pending/sent/executing/success/failed/timeout/cancelled/unknown
+    let commands = services.commands.clone();
+    engine.register_fn("command_status", move |command_id: &str| -> String {
+        let status = commands.as_ref().and_then(|executor| block_on(executor.get_status(command_id)));
+        status
+            .and_then(|s| serde_json::to_value(s).ok())
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string())
     });
-    
+
+    // read_device(device_id, metric) -> 最新指标值，无数据时为 ()
+    let devices = services.devices.clone();
+    let timeseries = services.timeseries.clone();
+    engine.register_fn("read_device", move |device_id: &str, metric: &str| -> FnResult<Dynamic> {
+        if let Some(devices) = &devices {
+            let metrics = block_on(devices.get_metrics(device_id)).map_err(script_error)?;
+            if let Some(m) = metrics.iter().find(|m| m.metric_name == metric) {
+                return Ok(Dynamic::from_float(m.metric_value));
+            }
+        }
+        if let Some(store) = &timeseries {
+            let now = Utc::now();
+            let query = TimeSeriesQuery::new(now - Duration::days(30), now)
+                .with_device(device_id.to_string())
+                .with_metric(metric.to_string())
+                .with_limit(1);
+            let points = block_on(store.query_metrics(&query)).map_err(script_error)?;
+            if let Some(point) = points.first() {
+                return Ok(Dynamic::from_float(point.metric_value));
+            }
+        }
+        debug!(device_id = %device_id, metric = %metric, "No device metric found");
+        Ok(Dynamic::UNIT)
+    });
+
+    // device_status(device_id) -> Online/Offline/Fault/Maintenance/Inactive/unknown
+    let devices = services.devices.clone();
+    engine.register_fn("device_status", move |device_id: &str| -> FnResult<String> {
+        match &devices {
+            Some(devices) => block_on(devices.get_status(device_id))
+                .map(|status| status.as_str().to_string())
+                .map_err(script_error),
+            None => Ok("unknown".to_string()),
+        }
+    });
+
     // update_device_status(device_id, status)
-    engine.register_fn("update_device_status", |device_id: &str, status: &str| {
-        info!(device_id = %device_id, status = %status, "Update device status (mock)");
-        // TODO: 实际实现需要调用设备状态更新服务
+    let devices = services.devices.clone();
+    engine.register_fn("update_device_status", move |device_id: &str, status: &str| -> FnResult<bool> {
+        let parsed = parse_device_status(status)
+            .ok_or_else(|| script_error(format!("Unknown device status '{}'", status)))?;
+        if effects::intercept("update_device_status", || serde_json::json!({ "device_id": device_id, "status": status })) {
+            return Ok(true);
+        }
+        let Some(devices) = &devices else {
+            warn!(device_id = %device_id, status = %status, "Device manager not configured, update_device_status skipped");
+            return Ok(false);
+        };
+        block_on(devices.set_status(device_id, parsed)).map_err(script_error)?;
+        info!(device_id = %device_id, status = %status, "Device status updated by rule");
+        Ok(true)
     });
 }
 
+/// 按指令名构造指令，未知指令作为自定义指令下发
+fn command_type(command: &str, params: serde_json::Value) -> FnResult<CommandType> {
+    let int = |key: &str| params.get(key).and_then(|v| v.as_i64());
+    let missing = |key: &str| script_error(format!("Command '{}' requires parameter '{}'", command, key));
+    Ok(match command {
+        "reboot" => CommandType::Reboot,
+        "reset" => CommandType::Reset,
+        "update" => CommandType::Update,
+        "start_stream" => CommandType::StartStream,
+        "stop_stream" => CommandType::StopStream,
+        "take_snapshot" => CommandType::TakeSnapshot,
+        "read_value" => CommandType::ReadValue,
+        "set_state" => CommandType::SetState {
+            state: params.get("state").and_then(|v| v.as_bool()).ok_or_else(|| missing("state"))?,
+        },
+        "set_value" => CommandType::SetValue {
+            value: params.get("value").and_then(|v| v.as_f64()).ok_or_else(|| missing("value"))?,
+        },
+        "set_sampling_rate" => CommandType::SetSamplingRate {
+            rate: int("rate").and_then(|v| u32::try_from(v).ok()).ok_or_else(|| missing("rate"))?,
+        },
+        "ptz_control" => CommandType::PTZControl {
+            pan: int("pan").unwrap_or(0) as i32,
+            tilt: int("tilt").unwrap_or(0) as i32,
+            zoom: int("zoom").unwrap_or(0) as i32,
+        },
+        _ => CommandType::Custom {
+            name: command.to_string(),
+            params,
+        },
+    })
+}
+
+fn parse_device_status(status: &str) -> Option<DeviceStatus> {
+    match status.to_ascii_lowercase().as_str() {
+        "online" => Some(DeviceStatus::Online),
+        "offline" => Some(DeviceStatus::Offline),
+        "fault" => Some(DeviceStatus::Fault),
+        "maintenance" => Some(DeviceStatus::Maintenance),
+        "inactive" => Some(DeviceStatus::Inactive),
+        _ => None,
+    }
+}
+
 /// 注册通知函数
-fn register_notification_functions(engine: &mut rhai::Engine) {
-    // send_notification(channel, title, message)
-    engine.register_fn("send_notification", |channel: &str, title: &str, message: &str| {
-        info!(
-            channel = %channel,
-            title = %title,
-            message = %message,
-            "Send notification (mock)"
-        );
-        // TODO: 实际实现需要调用通知服务
+fn register_notification_functions(engine: &mut rhai::Engine, services: &RuleServices) {
+    // send_notification(target, title, message)
+    // target 为渠道名（email/webhook/dingtalk/wechat/slack/sms）时发送到该渠道，
+    // 为 all 或级别名（info/warning/error/critical/urgent）时广播到全部渠道
+    let notify = services.notify.clone();
+    engine.register_fn("send_notification", move |target: &str, title: &str, message: &str| -> FnResult<bool> {
+        let level = parse_level(target).unwrap_or(NotifyLevel::Warning);
+        notify_target(&notify, target, NotifyMessage::new(title, message, level))
     });
-    
-    // send_email(to, subject, body)
-    engine.register_fn("send_email", |params: rhai::Map| {
-        info!("Send email (mock): {:?}", params);
-        // TODO: 实际实现需要调用邮件服务
+
+    // send_notification(target, title, message, level)
+    let notify = services.notify.clone();
+    engine.register_fn(
+        "send_notification",
+        move |target: &str, title: &str, message: &str, level: &str| -> FnResult<bool> {
+            let level = parse_level(level)
+                .ok_or_else(|| script_error(format!("Unknown notification level '{}'", level)))?;
+            notify_target(&notify, target, NotifyMessage::new(title, message, level))
+        },
+    );
+
+    // send_email(#{to, subject, body, level})
+    let notify = services.notify.clone();
+    engine.register_fn("send_email", move |params: Map| -> FnResult<bool> {
+        let params = map_to_json(params)?;
+        let field = |key: &str| params.get(key).and_then(|v| v.as_str());
+        let subject = field("subject").ok_or_else(|| script_error("send_email requires 'subject'"))?;
+        let body = field("body").unwrap_or_default();
+        let level = field("level").and_then(parse_level).unwrap_or(NotifyLevel::Warning);
+        let message = NotifyMessage::new(subject, body, level)
+            .with_metadata(serde_json::json!({ "to": params.get("to") }));
+        send(&notify, Some(NotifyChannel::Email), message)
     });
-    
+
     // send_sms(phone, message)
-    engine.register_fn("send_sms", |phone: &str, message: &str| {
-        info!(phone = %phone, message = %message, "Send SMS (mock)");
-        // TODO: 实际实现需要调用短信服务
+    let notify = services.notify.clone();
+    engine.register_fn("send_sms", move |phone: &str, message: &str| -> FnResult<bool> {
+        let message = NotifyMessage::warning("SMS", message)
+            .with_metadata(serde_json::json!({ "phone": phone }));
+        send(&notify, Some(NotifyChannel::SMS), message)
     });
-    
-    // send_push(user_id, title, message)
-    engine.register_fn("send_push", |user_id: &str, title: &str, message: &str| {
-        info!(
-            user_id = %user_id,
-            title = %title,
-            message = %message,
-            "Send push notification (mock)"
-        );
-        // TODO: 实际实现需要调用推送服务
+
+    // send_push(user_id, title, message)：通过 Webhook 渠道推送
+    let notify = services.notify.clone();
+    engine.register_fn("send_push", move |user_id: &str, title: &str, message: &str| -> FnResult<bool> {
+        let message = NotifyMessage::warning(title, message)
+            .with_metadata(serde_json::json!({ "user_id": user_id }));
+        send(&notify, Some(NotifyChannel::Webhook), message)
     });
 }
 
+pub(crate) fn parse_channel(channel: &str) -> Option<NotifyChannel> {
+    match channel.to_ascii_lowercase().as_str() {
+        "email" => Some(NotifyChannel::Email),
+        "webhook" => Some(NotifyChannel::Webhook),
+        "dingtalk" => Some(NotifyChannel::DingTalk),
+        "wechat" => Some(NotifyChannel::WeChat),
+        "slack" => Some(NotifyChannel::Slack),
+        "sms" => Some(NotifyChannel::SMS),
+        _ => None,
+    }
+}
+
+fn parse_level(level: &str) -> Option<NotifyLevel> {
+    match level.to_ascii_lowercase().as_str() {
+        "info" => Some(NotifyLevel::Info),
+        "warn" | "warning" => Some(NotifyLevel::Warning),
+        "error" => Some(NotifyLevel::Error),
+        "critical" | "urgent" => Some(NotifyLevel::Critical),
+        _ => None,
+    }
+}
+
+fn notify_target(
+    notify: &Option<std::sync::Arc<flux_notify::NotifyManager>>,
+    target: &str,
+    message: NotifyMessage,
+) -> FnResult<bool> {
+    if let Some(channel) = parse_channel(target) {
+        return send(notify, Some(channel), message);
+    }
+    if target.eq_ignore_ascii_case("all") || parse_level(target).is_some() {
+        return send(notify, None, message);
+    }
+    Err(script_error(format!("Unknown notification channel '{}'", target)))
+}
+
+/// 发送到指定渠道，`channel` 为 None 时广播
+fn send(
+    notify: &Option<std::sync::Arc<flux_notify::NotifyManager>>,
+    channel: Option<NotifyChannel>,
+    message: NotifyMessage,
+) -> FnResult<bool> {
+    let args = || {
+        serde_json::json!({
+            "channel": channel,
+            "title": message.title,
+            "content": message.content,
+            "level": message.level,
+            "metadata": message.metadata,
+        })
+    };
+    if effects::intercept("send_notification", args) {
+        return Ok(true);
+    }
+    let Some(notify) = notify else {
+        warn!(title = %message.title, "Notify manager not configured, notification skipped");
+        return Ok(false);
+    };
+    let result = match channel {
+        Some(channel) => block_on(notify.send(channel, &message)),
+        None => block_on(notify.broadcast(&message)),
+    };
+    result.map_err(script_error)?;
+    Ok(true)
+}
+
 /// 注册数据查询函数
-fn register_data_functions(engine: &mut rhai::Engine) {
-    // query_metrics(params)
-    engine.register_fn("query_metrics", |params: rhai::Map| -> rhai::Map {
-        debug!("Query metrics (mock): {:?}", params);
-        // TODO: 实际实现需要调用数据查询服务
-        let mut result = rhai::Map::new();
-        result.insert("total".into(), rhai::Dynamic::from(100.0));
-        result.insert("average".into(), rhai::Dynamic::from(50.0));
-        result.insert("peak".into(), rhai::Dynamic::from(80.0));
-        result
+fn register_data_functions(engine: &mut rhai::Engine, services: &RuleServices) {
+    // query_metrics(#{device_id, metric, range: "1h"}) 或 #{..., start, end}
+    // -> #{count, total, average, peak, min, latest}，无数据时 average/peak/min/latest 为 ()
+    let timeseries = services.timeseries.clone();
+    let offset = services.utc_offset;
+    engine.register_fn("query_metrics", move |params: Map| -> FnResult<Map> {
+        let (start, end) = time_range_from_params(&params, offset)?;
+        let mut query = TimeSeriesQuery::new(start, end);
+        if let Some(device_id) = params.get("device_id") {
+            query = query.with_device(device_id.to_string());
+        }
+        if let Some(metric) = params.get("metric") {
+            query = query.with_metric(metric.to_string());
+        }
+
+        let points = match &timeseries {
+            Some(store) => block_on(store.query_metrics(&query)).map_err(script_error)?,
+            None => {
+                warn!("Time series store not configured, query_metrics returns no data");
+                Vec::new()
+            }
+        };
+
+        let values: Vec<f64> = points.iter().map(|p| p.metric_value).collect();
+        let total: f64 = values.iter().sum();
+        let float_or_unit = |v: Option<f64>| v.map(Dynamic::from_float).unwrap_or(Dynamic::UNIT);
+        // 查询结果按时间倒序
+        let latest = points.first().map(|p| p.metric_value);
+
+        let mut result = Map::new();
+        result.insert("count".into(), Dynamic::from_int(values.len() as i64));
+        result.insert("total".into(), Dynamic::from_float(total));
+        result.insert(
+            "average".into(),
+            float_or_unit((!values.is_empty()).then(|| total / values.len() as f64)),
+        );
+        result.insert("peak".into(), float_or_unit(values.iter().copied().reduce(f64::max)));
+        result.insert("min".into(), float_or_unit(values.iter().copied().reduce(f64::min)));
+        result.insert("latest".into(), float_or_unit(latest));
+        Ok(result)
     });
-    
-    // count_events(event_type, time_range)
-    engine.register_fn("count_events", |event_type: &str, time_range: &str| -> i64 {
-        debug!(event_type = %event_type, time_range = %time_range, "Count events (mock)");
-        // TODO: 实际实现需要调用事件统计服务
-        0
+
+    // count_events(event_type, time_range)，time_range 如 "30m"、"24h"、"7d"
+    let timeseries = services.timeseries.clone();
+    engine.register_fn("count_events", move |event_type: &str, time_range: &str| -> FnResult<i64> {
+        count_events(&timeseries, None, event_type, time_range)
     });
-    
-    // record_event(event_type, data)
-    engine.register_fn("record_event", |event_type: &str, data: rhai::Map| {
-        info!(event_type = %event_type, "Record event (mock)");
-        debug!("Event data: {:?}", data);
-        // TODO: 实际实现需要调用事件记录服务
+
+    // count_events(device_id, event_type, time_range)
+    let timeseries = services.timeseries.clone();
+    engine.register_fn(
+        "count_events",
+        move |device_id: &str, event_type: &str, time_range: &str| -> FnResult<i64> {
+            count_events(&timeseries, Some(device_id), event_type, time_range)
+        },
+    );
+
+    // record_event(event_type, data)，设备 ID 取 data.device_id
+    let timeseries = services.timeseries.clone();
+    engine.register_fn("record_event", move |event_type: &str, data: Map| -> FnResult<bool> {
+        let device_id = data.get("device_id").map(|v| v.to_string()).unwrap_or_default();
+        record_event(&timeseries, &device_id, event_type, data)
     });
+
+    // record_event(device_id, event_type, data)
+    let timeseries = services.timeseries.clone();
+    engine.register_fn(
+        "record_event",
+        move |device_id: &str, event_type: &str, data: Map| -> FnResult<bool> {
+            record_event(&timeseries, device_id, event_type, data)
+        },
+    );
+}
+
+fn count_events(
+    timeseries: &Option<std::sync::Arc<dyn flux_timeseries::TimeSeriesStore>>,
+    device_id: Option<&str>,
+    event_type: &str,
+    time_range: &str,
+) -> FnResult<i64> {
+    let range = parse_duration(time_range)?;
+    let Some(store) = timeseries else {
+        warn!(event_type = %event_type, "Time series store not configured, count_events returns 0");
+        return Ok(0);
+    };
+    let end = Utc::now();
+    let count = block_on(store.count_events(device_id, event_type, end - range, end)).map_err(script_error)?;
+    Ok(count as i64)
+}
+
+fn record_event(
+    timeseries: &Option<std::sync::Arc<dyn flux_timeseries::TimeSeriesStore>>,
+    device_id: &str,
+    event_type: &str,
+    data: Map,
+) -> FnResult<bool> {
+    let data = map_to_json(data)?;
+    let args = || serde_json::json!({ "device_id": device_id, "event_type": event_type, "data": data });
+    if effects::intercept("record_event", args) {
+        return Ok(true);
+    }
+    let Some(store) = timeseries else {
+        warn!(event_type = %event_type, "Time series store not configured, record_event skipped");
+        return Ok(false);
+    };
+    let point = EventPoint::new(device_id.to_string(), event_type.to_string(), data);
+    block_on(store.write_event(&point)).map_err(script_error)?;
+    debug!(device_id = %device_id, event_type = %event_type, "Event recorded by rule");
+    Ok(true)
+}
+
+/// 查询时间范围：`start`/`end`（日期或时间戳），否则取最近 `range`（默认 1h）
+fn time_range_from_params(params: &Map, offset: FixedOffset) -> FnResult<(DateTime<Utc>, DateTime<Utc>)> {
+    let end = match params.get("end") {
+        Some(end) => to_datetime(end, offset)?.with_timezone(&Utc),
+        None => Utc::now(),
+    };
+    let start = match (params.get("start"), params.get("range")) {
+        (Some(start), _) => to_datetime(start, offset)?.with_timezone(&Utc),
+        (None, Some(range)) => end - parse_duration(&range.to_string())?,
+        (None, None) => end - Duration::hours(1),
+    };
+    Ok((start, end))
+}
+
+/// 解析时长：`30s`、`15m`、`1h`、`7d`、`2w`，纯数字按秒
+pub(crate) fn parse_duration(text: &str) -> FnResult<Duration> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (amount, unit) = text.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| script_error(format!("Invalid time range '{}'", text)))?;
+    let unit = if unit.is_empty() { "s" } else { unit };
+    duration_of(amount, unit).ok_or_else(|| script_error(format!("Invalid time range '{}'", text)))
+}
+
+fn duration_of(amount: i64, unit: &str) -> Option<Duration> {
+    match unit {
+        "s" | "sec" | "second" | "seconds" => Duration::try_seconds(amount),
+        "m" | "min" | "minute" | "minutes" => Duration::try_minutes(amount),
+        "h" | "hour" | "hours" => Duration::try_hours(amount),
+        "d" | "day" | "days" => Duration::try_days(amount),
+        "w" | "week" | "weeks" => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+fn map_to_json(map: Map) -> FnResult<serde_json::Value> {
+    rhai::serde::from_dynamic(&Dynamic::from_map(map))
 }
 
 /// 注册时间函数
-fn register_time_functions(engine: &mut rhai::Engine) {
-    use chrono::{Datelike, Timelike};
-    
-    // now() - 返回当前时间戳
-    engine.register_fn("now", || -> rhai::Map {
-        let now = chrono::Utc::now();
-        let mut map = rhai::Map::new();
-        map.insert("timestamp".into(), rhai::Dynamic::from(now.timestamp()));
-        map.insert("hour".into(), rhai::Dynamic::from(now.hour() as i64));
-        map.insert("minute".into(), rhai::Dynamic::from(now.minute() as i64));
-        map.insert("month".into(), rhai::Dynamic::from(now.month() as i64));
-        map.insert("day".into(), rhai::Dynamic::from(now.day() as i64));
-        map.insert("weekday".into(), rhai::Dynamic::from(now.weekday().num_days_from_monday() as i64));
-        map
+///
+/// 日期以 Map 表示：`timestamp`（秒）、`timestamp_ms`、`year`、`month`、`day`、`hour`、`minute`、
+/// `second`、`weekday`（周一为 0）、`offset`（时区偏移秒数）。函数参数也接受秒级时间戳，
+/// 或只含 `year`/`month`/`day`（可选 `hour`/`minute`/`second`）的 Map。
+fn register_time_functions(engine: &mut rhai::Engine, offset: FixedOffset) {
+    // now() - 当前时间
+    engine.register_fn("now", move || -> Map { date_map(&Utc::now().with_timezone(&offset)) });
+
+    // date(timestamp) - 秒级时间戳转日期
+    engine.register_fn("date", move |timestamp: i64| -> FnResult<Map> {
+        to_datetime(&Dynamic::from_int(timestamp), offset).map(|d| date_map(&d))
     });
-    
-    // date_add(date, amount, unit)
-    engine.register_fn("date_add", |_date: rhai::Map, _amount: i64, _unit: &str| -> rhai::Map {
-        // TODO: 实现日期加减
-        rhai::Map::new()
+
+    // parse_date(text) - RFC 3339、"%Y-%m-%d %H:%M:%S" 或 "%Y-%m-%d"
+    engine.register_fn("parse_date", move |text: &str| -> FnResult<Map> {
+        parse_date(text, offset).map(|d| date_map(&d))
     });
-    
-    // format_date(date, format)
-    engine.register_fn("format_date", |_date: rhai::Map, _format: &str| -> String {
-        // TODO: 实现日期格式化
-        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+
+    // date_add(date, amount, unit) - unit: seconds/minutes/hours/days/weeks/months/years
+    engine.register_fn("date_add", move |date: Dynamic, amount: i64, unit: &str| -> FnResult<Map> {
+        let date = to_datetime(&date, offset)?;
+        let result = match unit {
+            "month" | "months" | "M" => add_months(date, amount),
+            "year" | "years" | "y" => amount.checked_mul(12).and_then(|months| add_months(date, months)),
+            _ => {
+                let delta = duration_of(amount, unit)
+                    .ok_or_else(|| script_error(format!("Unknown date unit '{}'", unit)))?;
+                date.checked_add_signed(delta)
+            }
+        };
+        result
+            .map(|d| date_map(&d))
+            .ok_or_else(|| script_error("Date out of range"))
     });
-    
-    // date_start_of_day(date)
-    engine.register_fn("date_start_of_day", |_date: rhai::Map| -> rhai::Map {
-        // TODO: 实现获取日期开始
-        rhai::Map::new()
+
+    // date_diff(a, b, unit) - a - b，按单位向零取整
+    engine.register_fn("date_diff", move |a: Dynamic, b: Dynamic, unit: &str| -> FnResult<i64> {
+        let diff = to_datetime(&a, offset)? - to_datetime(&b, offset)?;
+        let unit_len = duration_of(1, unit).ok_or_else(|| script_error(format!("Unknown date unit '{}'", unit)))?;
+        Ok(diff.num_milliseconds() / unit_len.num_milliseconds())
     });
-    
-    // date_end_of_day(date)
-    engine.register_fn("date_end_of_day", |_date: rhai::Map| -> rhai::Map {
-        // TODO: 实现获取日期结束
-        rhai::Map::new()
+
+    // format_date(date, format) - strftime 格式
+    engine.register_fn("format_date", move |date: Dynamic, format: &str| -> FnResult<String> {
+        use chrono::format::{Item, StrftimeItems};
+        let items: Vec<Item> = StrftimeItems::new(format).collect();
+        if items.iter().any(|item| matches!(item, Item::Error)) {
+            return Err(script_error(format!("Invalid date format '{}'", format)));
+        }
+        Ok(to_datetime(&date, offset)?.format_with_items(items.into_iter()).to_string())
+    });
+
+    // date_start_of_day(date) - 当天 00:00:00
+    engine.register_fn("date_start_of_day", move |date: Dynamic| -> FnResult<Map> {
+        let date = to_datetime(&date, offset)?;
+        let start = date.date_naive().and_hms_opt(0, 0, 0).expect("midnight is valid");
+        local(start, *date.offset()).map(|d| date_map(&d))
     });
+
+    // date_end_of_day(date) - 当天 23:59:59.999
+    engine.register_fn("date_end_of_day", move |date: Dynamic| -> FnResult<Map> {
+        let date = to_datetime(&date, offset)?;
+        let end = date.date_naive().and_hms_milli_opt(23, 59, 59, 999).expect("end of day is valid");
+        local(end, *date.offset()).map(|d| date_map(&d))
+    });
+}
+
+fn date_map(date: &DateTime<FixedOffset>) -> Map {
+    let mut map = Map::new();
+    map.insert("timestamp".into(), Dynamic::from_int(date.timestamp()));
+    map.insert("timestamp_ms".into(), Dynamic::from_int(date.timestamp_millis()));
+    map.insert("year".into(), Dynamic::from_int(date.year() as i64));
+    map.insert("month".into(), Dynamic::from_int(date.month() as i64));
+    map.insert("day".into(), Dynamic::from_int(date.day() as i64));
+    map.insert("hour".into(), Dynamic::from_int(date.hour() as i64));
+    map.insert("minute".into(), Dynamic::from_int(date.minute() as i64));
+    map.insert("second".into(), Dynamic::from_int(date.second() as i64));
+    map.insert("weekday".into(), Dynamic::from_int(date.weekday().num_days_from_monday() as i64));
+    map.insert("offset".into(), Dynamic::from_int(date.offset().local_minus_utc() as i64));
+    map
+}
+
+/// 脚本中的日期值（Map 或秒级时间戳）转为带时区的时间
+fn to_datetime(value: &Dynamic, default_offset: FixedOffset) -> FnResult<DateTime<FixedOffset>> {
+    if let Ok(timestamp) = value.as_int() {
+        return DateTime::from_timestamp(timestamp, 0)
+            .map(|d| d.with_timezone(&default_offset))
+            .ok_or_else(|| script_error(format!("Timestamp out of range: {}", timestamp)));
+    }
+    let Some(map) = value.read_lock::<Map>() else {
+        return Err(script_error(format!("Expected a date map or timestamp, got {}", value.type_name())));
+    };
+    let int = |key: &str| map.get(key).and_then(|v| v.as_int().ok());
+    let offset = int("offset")
+        .and_then(|secs| i32::try_from(secs).ok())
+        .and_then(FixedOffset::east_opt)
+        .unwrap_or(default_offset);
+
+    if let Some(ms) = int("timestamp_ms") {
+        return DateTime::from_timestamp_millis(ms)
+            .map(|d| d.with_timezone(&offset))
+            .ok_or_else(|| script_error(format!("Timestamp out of range: {}", ms)));
+    }
+    if let Some(secs) = int("timestamp") {
+        return DateTime::from_timestamp(secs, 0)
+            .map(|d| d.with_timezone(&offset))
+            .ok_or_else(|| script_error(format!("Timestamp out of range: {}", secs)));
+    }
+
+    let (Some(year), Some(month), Some(day)) = (int("year"), int("month"), int("day")) else {
+        return Err(script_error("Date map requires 'timestamp' or 'year', 'month' and 'day'"));
+    };
+    let field = |v: i64| u32::try_from(v).ok();
+    let naive = i32::try_from(year)
+        .ok()
+        .zip(field(month))
+        .zip(field(day))
+        .and_then(|((y, m), d)| NaiveDate::from_ymd_opt(y, m, d))
+        .and_then(|date| {
+            date.and_hms_opt(
+                field(int("hour").unwrap_or(0))?,
+                field(int("minute").unwrap_or(0))?,
+                field(int("second").unwrap_or(0))?,
+            )
+        })
+        .ok_or_else(|| script_error("Invalid date fields"))?;
+    local(naive, offset)
+}
+
+fn local(naive: NaiveDateTime, offset: FixedOffset) -> FnResult<DateTime<FixedOffset>> {
+    offset
+        .from_local_datetime(&naive)
+        .single()
+        .ok_or_else(|| script_error("Invalid local time"))
+}
+
+fn parse_date(text: &str, offset: FixedOffset) -> FnResult<DateTime<FixedOffset>> {
+    let text = text.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Ok(date);
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S") {
+        return local(naive, offset);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return local(date.and_hms_opt(0, 0, 0).expect("midnight is valid"), offset);
+    }
+    Err(script_error(format!("Invalid date '{}'", text)))
+}
+
+/// 按月加减，月末日期取目标月的最后一天（1 月 31 日加 1 个月为 2 月 28/29 日）
+fn add_months(date: DateTime<FixedOffset>, months: i64) -> Option<DateTime<FixedOffset>> {
+    let delta = chrono::Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+    if months >= 0 {
+        date.checked_add_months(delta)
+    } else {
+        date.checked_sub_months(delta)
+    }
 }
 
 /// 注册日志函数
@@ -157,22 +617,22 @@ fn register_log_functions(engine: &mut rhai::Engine) {
             _ => info!("{}", message),
         }
     });
-    
+
     // debug(message)
     engine.register_fn("debug", |message: &str| {
         debug!("{}", message);
     });
-    
+
     // info(message)
     engine.register_fn("info", |message: &str| {
         info!("{}", message);
     });
-    
+
     // warn(message)
     engine.register_fn("warn", |message: &str| {
         warn!("{}", message);
     });
-    
+
     // error(message)
     engine.register_fn("error", |message: &str| {
         error!("{}", message);
@@ -201,22 +661,314 @@ pub fn register_ticket_functions(engine: &mut rhai::Engine) {
     });
 }
 
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use async_trait::async_trait;
+    use flux_control::{CommandChannel, CommandExecutor};
+    use flux_notify::{Notifier, NotifyManager, NotifyResult};
+    use flux_timeseries::{AggregatedResult, LogPoint, MetricPoint, TimeSeriesStore};
+    use std::sync::{Arc, Mutex};
+
+    fn engine_with(services: &RuleServices) -> ScriptEngine {
+        let mut engine = ScriptEngine::new();
+        register_builtin_functions(&mut engine, services);
+        engine
+    }
 
     #[test]
     fn test_register_functions() {
-        let mut engine = ScriptEngine::new();
-        register_builtin_functions(&mut engine);
-        
-        // 测试函数是否注册成功
+        let engine = engine_with(&RuleServices::default());
+
+        // 未配置服务时函数返回空结果
         let script = r#"
             log("info", "Test log");
             let time = now();
-            control_device("test_device", "turn_on", #{});
+            let id = control_device("test_device", "turn_on", #{});
+            let stats = query_metrics(#{ device_id: "test_device", metric: "power", range: "1h" });
+            id == "" && read_device("test_device", "temperature") == () && stats.count == 0
+                && !send_notification("urgent", "title", "message")
+        "#;
+
+        assert!(engine.eval(script).unwrap().as_bool().unwrap());
+    }
+
+    #[test]
+    fn test_time_functions() {
+        let engine = engine_with(&RuleServices::default());
+        let eval_str = |script: &str| engine.eval(script).unwrap().into_string().unwrap();
+        let eval_int = |script: &str| engine.eval(script).unwrap().as_int().unwrap();
+
+        assert_eq!(
+            eval_str(r#"format_date(date_add(parse_date("2024-01-31 10:30:00"), 1, "months"), "%Y-%m-%d %H:%M")"#),
+            "2024-02-29 10:30"
+        );
+        assert_eq!(
+            eval_str(r#"format_date(date_add(parse_date("2024-03-01"), -2, "days"), "%Y-%m-%d")"#),
+            "2024-02-28"
+        );
+        assert_eq!(
+            eval_str(r#"format_date(date_end_of_day(#{ year: 2024, month: 5, day: 6, hour: 9 }), "%H:%M:%S")"#),
+            "23:59:59"
+        );
+        assert_eq!(eval_int(r#"date_start_of_day(parse_date("2024-05-06T18:00:00Z")).hour"#), 0);
+        assert_eq!(eval_int(r#"parse_date("2024-05-06").weekday"#), 0);
+        assert_eq!(
+            eval_int(r#"date_diff(parse_date("2024-05-08 12:00:00"), parse_date("2024-05-06"), "days")"#),
+            2
+        );
+        assert_eq!(eval_int(r#"date(1700000000).timestamp_ms"#), 1_700_000_000_000);
+        assert!(engine.eval(r#"format_date(now(), "%Q")"#).is_err());
+        assert!(engine.eval(r#"date_add(now(), 1, "fortnights")"#).is_err());
+
+        // 按配置的时区计算日期
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        let engine = engine_with(&RuleServices::new().with_utc_offset(offset));
+        let start = engine.eval(r#"date_start_of_day(date(1700000000))"#).unwrap().cast::<Map>();
+        assert_eq!(start["day"].as_int().unwrap(), 15);
+        assert_eq!(start["timestamp"].as_int().unwrap(), 1_699_977_600);
+    }
+
+    #[derive(Default)]
+    struct RecordingNotifier {
+        messages: Arc<Mutex<Vec<NotifyMessage>>>,
+    }
+
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        async fn send(&self, message: &NotifyMessage) -> anyhow::Result<NotifyResult> {
+            self.messages.lock().unwrap().push(message.clone());
+            Ok(NotifyResult::success())
+        }
+
+        fn name(&self) -> &str {
+            "recording"
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingChannel {
+        sent: Mutex<Vec<DeviceCommand>>,
+    }
+
+    #[async_trait]
+    impl CommandChannel for RecordingChannel {
+        async fn send_command(&self, command: &DeviceCommand) -> anyhow::Result<()> {
+            self.sent.lock().unwrap().push(command.clone());
+            Ok(())
+        }
+
+        async fn wait_response(&self, _command_id: &str) -> anyhow::Result<serde_json::Value> {
+            Ok(serde_json::json!({ "ok": true }))
+        }
+
+        async fn subscribe_device(&self, _device_id: &str) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn unsubscribe_device(&self, _device_id: &str) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_command_and_notification_functions() {
+        let channel = Arc::new(RecordingChannel::default());
+        let executor = Arc::new(CommandExecutor::new(channel.clone()));
+        let email = RecordingNotifier::default();
+        let emails = email.messages.clone();
+        let notify = Arc::new(NotifyManager::new(NotifyLevel::Info));
+        notify.register(NotifyChannel::Email, Box::new(email)).await;
+
+        let engine = engine_with(
+            &RuleServices::new()
+                .with_command_executor(executor.clone())
+                .with_notify_manager(notify),
+        );
+
+        let command_id = engine
+            .eval(r#"control_device("lamp_01", "set_value", #{ value: 42.5 })"#)
+            .unwrap()
+            .into_string()
+            .unwrap();
+        assert!(!command_id.is_empty());
+        for _ in 0..50 {
+            if executor.get_status(&command_id).await == Some(flux_control::CommandStatus::Success) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let status = engine.eval(&format!(r#"command_status("{}")"#, command_id)).unwrap();
+        assert_eq!(status.into_string().unwrap(), "success");
+        let sent = channel.sent.lock().unwrap().clone();
+        assert_eq!(sent[0].device_id, "lamp_01");
+        assert!(matches!(sent[0].command_type, CommandType::SetValue { value } if value == 42.5));
+        assert!(engine.eval(r#"control_device("lamp_01", "set_value", #{})"#).is_err());
+
+        let script = r#"
+            send_email(#{ to: "ops@example.com", subject: "高温", body: "温度过高" })
+                && send_notification("critical", "广播", "全部渠道")
+                && send_notification("email", "低级别", "info 消息", "info")
+        "#;
+        assert!(engine.eval(script).unwrap().as_bool().unwrap());
+        assert!(engine.eval(r#"send_notification("pager", "t", "m")"#).is_err());
+
+        let emails = emails.lock().unwrap();
+        assert_eq!(emails.len(), 3);
+        assert_eq!(emails[0].title, "高温");
+        assert_eq!(emails[0].metadata.as_ref().unwrap()["to"], "ops@example.com");
+        assert_eq!(emails[1].level, NotifyLevel::Critical);
+        assert_eq!(emails[2].level, NotifyLevel::Info);
+    }
+
+    #[derive(Default)]
+    struct MemoryStore {
+        metrics: Mutex<Vec<MetricPoint>>,
+        events: Mutex<Vec<EventPoint>>,
+    }
+
+    #[async_trait]
+    impl TimeSeriesStore for MemoryStore {
+        async fn write_metric(&self, point: &MetricPoint) -> anyhow::Result<()> {
+            self.metrics.lock().unwrap().push(point.clone());
+            Ok(())
+        }
+
+        async fn write_metrics(&self, points: &[MetricPoint]) -> anyhow::Result<()> {
+            self.metrics.lock().unwrap().extend_from_slice(points);
+            Ok(())
+        }
+
+        async fn write_log(&self, _point: &LogPoint) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn write_event(&self, point: &EventPoint) -> anyhow::Result<()> {
+            self.events.lock().unwrap().push(point.clone());
+            Ok(())
+        }
+
+        async fn query_metrics(&self, query: &TimeSeriesQuery) -> anyhow::Result<Vec<MetricPoint>> {
+            let mut points: Vec<_> = self
+                .metrics
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|p| query.device_id.as_ref().is_none_or(|d| &p.device_id == d))
+                .filter(|p| query.metric_name.as_ref().is_none_or(|m| &p.metric_name == m))
+                .filter(|p| p.timestamp >= query.start_time && p.timestamp <= query.end_time)
+                .cloned()
+                .collect();
+            points.sort_by_key(|p| std::cmp::Reverse(p.timestamp));
+            if let Some(limit) = query.limit {
+                points.truncate(limit as usize);
+            }
+            Ok(points)
+        }
+
+        async fn query_aggregated(&self, _query: &TimeSeriesQuery) -> anyhow::Result<Vec<AggregatedResult>> {
+            Ok(Vec::new())
+        }
+
+        async fn count_events(
+            &self,
+            device_id: Option<&str>,
+            event_type: &str,
+            start_time: DateTime<Utc>,
+            end_time: DateTime<Utc>,
+        ) -> anyhow::Result<u64> {
+            Ok(self
+                .events
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|e| device_id.is_none_or(|d| e.device_id == d) && e.event_type == event_type)
+                .filter(|e| e.timestamp >= start_time && e.timestamp <= end_time)
+                .count() as u64)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_timeseries_functions() {
+        let store = Arc::new(MemoryStore::default());
+        let now = Utc::now();
+        store
+            .write_metrics(&[
+                MetricPoint::new("meter".into(), "power".into(), 10.0).with_timestamp(now - Duration::minutes(50)),
+                MetricPoint::new("meter".into(), "power".into(), 30.0).with_timestamp(now - Duration::minutes(10)),
+                MetricPoint::new("meter".into(), "power".into(), 20.0).with_timestamp(now - Duration::minutes(30)),
+                MetricPoint::new("meter".into(), "power".into(), 99.0).with_timestamp(now - Duration::hours(3)),
+            ])
+            .await
+            .unwrap();
+
+        let engine = engine_with(&RuleServices::new().with_timeseries(store.clone()));
+        let stats = engine
+            .eval(r#"query_metrics(#{ device_id: "meter", metric: "power", range: "1h" })"#)
+            .unwrap()
+            .cast::<Map>();
+        assert_eq!(stats["count"].as_int().unwrap(), 3);
+        assert_eq!(stats["total"].as_float().unwrap(), 60.0);
+        assert_eq!(stats["average"].as_float().unwrap(), 20.0);
+        assert_eq!(stats["peak"].as_float().unwrap(), 30.0);
+        assert_eq!(stats["min"].as_float().unwrap(), 10.0);
+        assert_eq!(stats["latest"].as_float().unwrap(), 30.0);
+        assert_eq!(engine.eval(r#"read_device("meter", "power")"#).unwrap().as_float().unwrap(), 30.0);
+
+        let script = r#"
+            record_event("door_open", #{ device_id: "door_01" });
+            record_event("door_02", "door_open", #{ by: "rule" });
+            record_event("door_01", "door_close", #{});
+            [count_events("door_open", "30m"), count_events("door_01", "door_open", "1h")]
         "#;
-        
-        assert!(engine.eval(script).is_ok());
+        let counts = engine.eval(script).unwrap().into_typed_array::<i64>().unwrap();
+        assert_eq!(counts, vec![2, 1]);
+        assert!(engine.eval(r#"count_events("door_open", "soon")"#).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_device_functions() {
+        use flux_device::{device, device_group, device_metrics, Device, DeviceManager, DeviceType, Protocol};
+        use sea_orm::{ConnectionTrait, Database, Schema};
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let backend = db.get_database_backend();
+        let schema = Schema::new(backend);
+        db.execute(backend.build(&schema.create_table_from_entity(device_group::Entity)))
+            .await
+            .unwrap();
+        db.execute(backend.build(&schema.create_table_from_entity(device::Entity)))
+            .await
+            .unwrap();
+        db.execute(backend.build(&schema.create_table_from_entity(device_metrics::Entity)))
+            .await
+            .unwrap();
+
+        let devices = Arc::new(DeviceManager::new(Arc::new(db), 30, 60));
+        let device = devices
+            .register_device(Device::new("thermo".into(), DeviceType::Sensor, Protocol::MQTT))
+            .await
+            .unwrap();
+        devices
+            .record_metric(&device.id, "temperature".into(), 21.5, Some("°C".into()))
+            .await
+            .unwrap();
+
+        let engine = engine_with(&RuleServices::new().with_device_manager(devices.clone()));
+        let script = format!(
+            r#"
+            let t = read_device("{id}", "temperature");
+            update_device_status("{id}", "fault");
+            [t, read_device("{id}", "humidity"), device_status("{id}")]
+            "#,
+            id = device.id
+        );
+        let result = engine.eval(&script).unwrap().into_array().unwrap();
+        assert_eq!(result[0].as_float().unwrap(), 21.5);
+        assert!(result[1].is_unit());
+        assert_eq!(result[2].clone().into_string().unwrap(), "Fault");
+        assert_eq!(devices.get_status(&device.id).await.unwrap(), DeviceStatus::Fault);
+        assert!(engine.eval(&format!(r#"update_device_status("{}", "sleeping")"#, device.id)).is_err());
     }
 }
@@ -0,0 +1,241 @@
+use crate::event::{topic_matches, TriggerEvent};
+use crate::model::{DeviceSelector, Rule, RuleTrigger};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// 设备所属分组（含上级分组）与标签
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInfo {
+    pub groups: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+enum TriggerKind {
+    Event(String),
+    Data(Option<String>),
+}
+
+#[derive(Debug, Clone)]
+struct IndexedTrigger {
+    selector: DeviceSelector,
+    kind: TriggerKind,
+}
+
+impl IndexedTrigger {
+    fn matches(&self, event: &TriggerEvent, device: &DeviceInfo) -> bool {
+        let selector = &self.selector;
+        let selected = selector.device_id.as_deref().is_none_or(|id| id == event.device_id())
+            && selector
+                .topic
+                .as_deref()
+                .is_none_or(|filter| event.topic().is_some_and(|topic| topic_matches(filter, topic)))
+            && selector.group.as_ref().is_none_or(|g| device.groups.contains(g))
+            && selector.tag.as_ref().is_none_or(|t| device.tags.contains(t));
+        if !selected {
+            return false;
+        }
+
+        match (&self.kind, event) {
+            (TriggerKind::Event(expected), TriggerEvent::DeviceEvent { event_type, .. }) => {
+                expected == "*" || expected == event_type
+            }
+            (TriggerKind::Data(metric), TriggerEvent::DataChange { metrics, .. }) => {
+                metric.as_ref().is_none_or(|m| metrics.contains_key(m))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 设备事件/数据变化触发器索引
+///
+/// 规则按选择器中最具体的条件（设备 ID > 主题 > 分组 > 标签）归入一个桶，
+/// 匹配时只检查事件可能命中的桶，再校验选择器的全部条件。
+#[derive(Debug, Default)]
+pub struct TriggerIndex {
+    triggers: HashMap<String, IndexedTrigger>,
+    by_device: HashMap<String, HashSet<String>>,
+    by_topic: HashMap<String, HashSet<String>>,
+    by_group: HashMap<String, HashSet<String>>,
+    by_tag: HashMap<String, HashSet<String>>,
+    any_device: HashSet<String>,
+}
+
+impl TriggerIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入规则（已存在时替换）；非设备事件/数据变化触发的规则不入索引，返回 false
+    pub fn insert(&mut self, rule: &Rule) -> bool {
+        self.remove(&rule.id);
+
+        let trigger = match &rule.trigger {
+            RuleTrigger::DeviceEvent { selector, event_type } => IndexedTrigger {
+                selector: selector.clone(),
+                kind: TriggerKind::Event(event_type.clone()),
+            },
+            RuleTrigger::DataChange { selector, metric } => IndexedTrigger {
+                selector: selector.clone(),
+                kind: TriggerKind::Data(metric.clone()),
+            },
+            _ => return false,
+        };
+
+        if let Some((bucket, key)) = self.bucket_mut(&trigger.selector) {
+            bucket.entry(key).or_default().insert(rule.id.clone());
+        } else {
+            self.any_device.insert(rule.id.clone());
+        }
+        self.triggers.insert(rule.id.clone(), trigger);
+        true
+    }
+
+    pub fn remove(&mut self, rule_id: &str) -> bool {
+        let Some(trigger) = self.triggers.remove(rule_id) else {
+            return false;
+        };
+        if let Some((bucket, key)) = self.bucket_mut(&trigger.selector) {
+            if let Some(ids) = bucket.get_mut(&key) {
+                ids.remove(rule_id);
+                if ids.is_empty() {
+                    bucket.remove(&key);
+                }
+            }
+        } else {
+            self.any_device.remove(rule_id);
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn len(&self) -> usize {
+        self.triggers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty()
+    }
+
+    /// 是否有规则按分组或标签选择设备（需要查询设备信息）
+    pub fn needs_device_info(&self) -> bool {
+        self.triggers.values().any(|t| t.selector.needs_device_info())
+    }
+
+    /// 事件命中的规则 ID（有序）
+    pub fn matches(&self, event: &TriggerEvent, device: &DeviceInfo) -> Vec<String> {
+        let mut candidates: BTreeSet<&String> = self.any_device.iter().collect();
+        candidates.extend(self.by_device.get(event.device_id()).into_iter().flatten());
+        if let Some(topic) = event.topic() {
+            candidates.extend(
+                self.by_topic
+                    .iter()
+                    .filter(|(filter, _)| topic_matches(filter, topic))
+                    .flat_map(|(_, ids)| ids),
+            );
+        }
+        candidates.extend(device.groups.iter().filter_map(|g| self.by_group.get(g)).flatten());
+        candidates.extend(device.tags.iter().filter_map(|t| self.by_tag.get(t)).flatten());
+
+        candidates
+            .into_iter()
+            .filter(|id| self.triggers.get(*id).is_some_and(|t| t.matches(event, device)))
+            .cloned()
+            .collect()
+    }
+
+    fn bucket_mut(&mut self, selector: &DeviceSelector) -> Option<(&mut HashMap<String, HashSet<String>>, String)> {
+        if let Some(device_id) = &selector.device_id {
+            Some((&mut self.by_device, device_id.clone()))
+        } else if let Some(topic) = &selector.topic {
+            Some((&mut self.by_topic, topic.clone()))
+        } else if let Some(group) = &selector.group {
+            Some((&mut self.by_group, group.clone()))
+        } else {
+            selector.tag.as_ref().map(|tag| (&mut self.by_tag, tag.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(id: &str, trigger: RuleTrigger) -> Rule {
+        Rule {
+            id: id.to_string(),
+            trigger,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_index_matches_selectors() {
+        let mut index = TriggerIndex::new();
+        index.insert(&rule(
+            "by_device",
+            RuleTrigger::DataChange {
+                selector: DeviceSelector::device("th_01"),
+                metric: Some("temperature".into()),
+            },
+        ));
+        index.insert(&rule(
+            "by_group_and_tag",
+            RuleTrigger::DataChange {
+                selector: DeviceSelector {
+                    group: Some("building_1".into()),
+                    tag: Some("cold_chain".into()),
+                    ..Default::default()
+                },
+                metric: None,
+            },
+        ));
+        index.insert(&rule(
+            "by_topic",
+            RuleTrigger::DeviceEvent {
+                selector: DeviceSelector::topic("device/+/event/#"),
+                event_type: "*".into(),
+            },
+        ));
+        index.insert(&rule("manual", RuleTrigger::Manual));
+        assert_eq!(index.len(), 3);
+        assert!(index.needs_device_info());
+
+        let data = TriggerEvent::DataChange {
+            device_id: "th_01".into(),
+            metrics: json!({"temperature": 4.5}).as_object().unwrap().clone(),
+            topic: Some("device/th_01/telemetry".into()),
+        };
+        let in_group = DeviceInfo {
+            groups: vec!["floor_2".into(), "building_1".into()],
+            tags: vec!["cold_chain".into()],
+        };
+        assert_eq!(index.matches(&data, &in_group), vec!["by_device", "by_group_and_tag"]);
+        assert_eq!(index.matches(&data, &DeviceInfo::default()), vec!["by_device"]);
+
+        let event = TriggerEvent::DeviceEvent {
+            device_id: "door_01".into(),
+            event_type: "open".into(),
+            data: json!({}),
+            topic: Some("device/door_01/event/open".into()),
+        };
+        assert_eq!(index.matches(&event, &in_group), vec!["by_topic"]);
+
+        // 规则更新后按新的触发器重新索引
+        index.insert(&rule(
+            "by_device",
+            RuleTrigger::DataChange {
+                selector: DeviceSelector::device("th_02"),
+                metric: None,
+            },
+        ));
+        assert_eq!(index.matches(&data, &DeviceInfo::default()), Vec::<String>::new());
+        assert!(index.remove("by_topic"));
+        assert!(index.matches(&event, &in_group).is_empty());
+        assert_eq!(index.len(), 2);
+    }
+}
@@ -1,15 +1,29 @@
 pub mod model;
 pub mod engine;
 pub mod trigger;
+pub mod event;
+pub mod index;
+pub mod window;
 pub mod context;
 pub mod functions;
+pub mod services;
 pub mod execution;
+pub mod effects;
 pub mod storage;
+pub mod ruleset;
+pub mod db;
 
-pub use model::{Rule, RuleTrigger, RuleMetadata, ConflictStrategy, RateLimit};
+pub use model::{Rule, RuleTrigger, DeviceSelector, RuleMetadata, ConflictStrategy, RateLimit};
 pub use engine::RuleEngine;
 pub use context::RuleContext;
-pub use execution::{RuleExecution, ExecutionStatus, TestResult};
-pub use storage::RuleStorage;
+pub use execution::{RuleExecution, ExecutionStatus, RuleOutcome, TestResult};
+pub use effects::SideEffect;
+pub use storage::{RuleStorage, RuleStore};
+pub use ruleset::{ImportSummary, RuleSet, RuleSetFormat};
+pub use db::DbRuleStorage;
 pub use trigger::TriggerManager;
+pub use event::TriggerEvent;
+pub use index::{DeviceInfo, TriggerIndex};
+pub use window::{Aggregate, CompareOp, WindowCheckpoint, WindowCondition, WindowKey, WindowStats, WindowStore};
 pub use functions::register_builtin_functions;
+pub use services::RuleServices;
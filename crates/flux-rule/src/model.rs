@@ -1,9 +1,11 @@
+use crate::window::WindowCondition;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-/// 规则定义
+/// 规则定义（反序列化时缺省字段取默认值）
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Rule {
     /// 规则 ID
     pub id: String,
@@ -26,6 +28,9 @@ pub struct Rule {
     /// 触发器
     pub trigger: RuleTrigger,
     
+    /// 窗口条件（设备事件/数据变化触发时判断，满足才执行脚本）
+    pub condition: Option<WindowCondition>,
+    
     /// Rhai 脚本
     pub script: String,
     
@@ -59,7 +64,7 @@ pub struct Rule {
     /// 版本号
     pub version: i32,
     
-    /// 上一版本 ID
+    /// 上一版本 ID（见 [`Rule::version_id`]）
     pub previous_version: Option<String>,
     
     /// 规则依赖
@@ -79,6 +84,7 @@ impl Default for Rule {
             tags: Vec::new(),
             enabled: true,
             trigger: RuleTrigger::Manual,
+            condition: None,
             script: String::new(),
             priority: 50,
             conflict_strategy: ConflictStrategy::Parallel,
@@ -97,6 +103,13 @@ impl Default for Rule {
     }
 }
 
+impl Rule {
+    /// 版本 ID：`<规则 ID>@v<版本号>`
+    pub fn version_id(&self) -> String {
+        format!("{}@v{}", self.id, self.version)
+    }
+}
+
 /// 触发器类型
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -112,21 +125,81 @@ pub enum RuleTrigger {
     
     /// 条件触发 - 设备事件
     DeviceEvent {
-        /// 设备 ID
-        device_id: String,
-        /// 事件类型
+        /// 触发的设备范围
+        #[serde(flatten)]
+        selector: DeviceSelector,
+        /// 事件类型（`*` 表示任何事件）
         event_type: String,
     },
     
     /// 条件触发 - 数据变化
     DataChange {
-        /// 设备 ID
-        device_id: String,
+        /// 触发的设备范围
+        #[serde(flatten)]
+        selector: DeviceSelector,
         /// 指标名称（None 表示任何指标）
         metric: Option<String>,
     },
 }
 
+/// 设备选择器
+///
+/// 填写的条件需同时满足，全部为空时匹配任何设备。`group` 同时匹配子分组下的设备，
+/// `topic` 为 MQTT 风格的主题过滤器（支持 `+`、`#`）。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceSelector {
+    /// 设备 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    
+    /// 设备分组 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    
+    /// 设备标签
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    
+    /// 消息主题过滤器
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+}
+
+impl DeviceSelector {
+    pub fn device(device_id: impl Into<String>) -> Self {
+        Self {
+            device_id: Some(device_id.into()),
+            ..Default::default()
+        }
+    }
+    
+    pub fn group(group: impl Into<String>) -> Self {
+        Self {
+            group: Some(group.into()),
+            ..Default::default()
+        }
+    }
+    
+    pub fn tag(tag: impl Into<String>) -> Self {
+        Self {
+            tag: Some(tag.into()),
+            ..Default::default()
+        }
+    }
+    
+    pub fn topic(filter: impl Into<String>) -> Self {
+        Self {
+            topic: Some(filter.into()),
+            ..Default::default()
+        }
+    }
+    
+    /// 是否需要查询设备的分组与标签
+    pub fn needs_device_info(&self) -> bool {
+        self.group.is_some() || self.tag.is_some()
+    }
+}
+
 /// 冲突策略
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -155,6 +228,7 @@ pub struct RateLimit {
 
 /// 规则元数据
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RuleMetadata {
     /// 创建时间
     pub created_at: DateTime<Utc>,
@@ -217,4 +291,26 @@ mod tests {
         
         assert_eq!(rule.name, deserialized.name);
     }
+
+    #[test]
+    fn test_selector_trigger_serialization() {
+        let trigger: RuleTrigger = serde_json::from_str(
+            r#"{"type": "device_event", "group": "floor1", "tag": "smoke", "event_type": "alarm"}"#,
+        )
+        .unwrap();
+        let RuleTrigger::DeviceEvent { selector, event_type } = &trigger else {
+            panic!("unexpected trigger: {:?}", trigger);
+        };
+        assert_eq!(selector.group.as_deref(), Some("floor1"));
+        assert_eq!(selector.tag.as_deref(), Some("smoke"));
+        assert!(selector.device_id.is_none());
+        assert_eq!(event_type, "alarm");
+
+        let json = serde_json::to_value(RuleTrigger::DataChange {
+            selector: DeviceSelector::device("sensor_001"),
+            metric: None,
+        })
+        .unwrap();
+        assert_eq!(json, serde_json::json!({"type": "data_change", "device_id": "sensor_001", "metric": null}));
+    }
 }
@@ -0,0 +1,118 @@
+use crate::model::Rule;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 规则集文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSetFormat {
+    Json,
+    Yaml,
+}
+
+impl RuleSetFormat {
+    /// 按文件扩展名识别格式
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// 规则集（导入导出）
+///
+/// ```yaml
+/// rules:
+///   - id: high_temp
+///     name: 高温告警
+///     trigger:
+///       type: data_change
+///       device_id: sensor_001
+///       metric: temperature
+///     script: |
+///       if device.temperature > 80.0 { send_notification("urgent", "高温告警", "温度过高"); }
+/// ```
+///
+/// 规则中未填写的字段取默认值，未指定 `id` 时作为新规则导入。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleSet {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exported_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self {
+            exported_at: Some(Utc::now()),
+            rules,
+        }
+    }
+
+    pub fn parse(content: &str, format: RuleSetFormat) -> anyhow::Result<Self> {
+        match format {
+            RuleSetFormat::Json => serde_json::from_str(content).context("Invalid JSON rule set"),
+            RuleSetFormat::Yaml => serde_yaml::from_str(content).context("Invalid YAML rule set"),
+        }
+    }
+
+    pub fn serialize(&self, format: RuleSetFormat) -> anyhow::Result<String> {
+        match format {
+            RuleSetFormat::Json => serde_json::to_string_pretty(self).context("Failed to serialize rule set"),
+            RuleSetFormat::Yaml => serde_yaml::to_string(self).context("Failed to serialize rule set"),
+        }
+    }
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportSummary {
+    /// 新建的规则 ID
+    pub created: Vec<String>,
+    /// 已存在、保存为新版本的规则 ID
+    pub updated: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::RuleTrigger;
+
+    #[test]
+    fn test_parse_minimal_yaml() {
+        let set = RuleSet::parse(
+            r#"
+rules:
+  - id: high_temp
+    name: 高温告警
+    trigger:
+      type: data_change
+      device_id: sensor_001
+      metric: temperature
+    script: "let t = device.temperature;"
+    tags: [alarm]
+"#,
+            RuleSetFormat::Yaml,
+        )
+        .unwrap();
+
+        let rule = &set.rules[0];
+        assert_eq!(rule.id, "high_temp");
+        assert!(matches!(&rule.trigger, RuleTrigger::DataChange { metric: Some(m), .. } if m == "temperature"));
+        assert_eq!(rule.priority, 50);
+        assert!(rule.enabled);
+
+        let json = set.serialize(RuleSetFormat::Json).unwrap();
+        let round_trip = RuleSet::parse(&json, RuleSetFormat::Json).unwrap();
+        assert_eq!(round_trip.rules[0].tags, vec!["alarm".to_string()]);
+        assert!(RuleSet::parse("rules: 1", RuleSetFormat::Yaml).is_err());
+        assert_eq!(
+            RuleSetFormat::from_path(std::path::Path::new("rules.YML")),
+            Some(RuleSetFormat::Yaml)
+        );
+    }
+}
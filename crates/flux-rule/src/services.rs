@@ -0,0 +1,63 @@
+use chrono::FixedOffset;
+use flux_control::CommandExecutor;
+use flux_device::DeviceManager;
+use flux_notify::NotifyManager;
+use flux_timeseries::TimeSeriesStore;
+use std::sync::Arc;
+
+/// 内置函数使用的外部服务
+///
+/// 未配置的服务对应的函数只记录日志并返回空结果（`control_device` 返回空字符串，
+/// `read_device` 返回 `()`，通知函数返回 `false`）。
+#[derive(Clone)]
+pub struct RuleServices {
+    pub commands: Option<Arc<CommandExecutor>>,
+    pub devices: Option<Arc<DeviceManager>>,
+    pub timeseries: Option<Arc<dyn TimeSeriesStore>>,
+    pub notify: Option<Arc<NotifyManager>>,
+    /// 日期函数使用的时区（默认 UTC）
+    pub utc_offset: FixedOffset,
+}
+
+impl Default for RuleServices {
+    fn default() -> Self {
+        Self {
+            commands: None,
+            devices: None,
+            timeseries: None,
+            notify: None,
+            utc_offset: FixedOffset::east_opt(0).expect("zero offset is valid"),
+        }
+    }
+}
+
+impl RuleServices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_command_executor(mut self, commands: Arc<CommandExecutor>) -> Self {
+        self.commands = Some(commands);
+        self
+    }
+
+    pub fn with_device_manager(mut self, devices: Arc<DeviceManager>) -> Self {
+        self.devices = Some(devices);
+        self
+    }
+
+    pub fn with_timeseries(mut self, timeseries: Arc<dyn TimeSeriesStore>) -> Self {
+        self.timeseries = Some(timeseries);
+        self
+    }
+
+    pub fn with_notify_manager(mut self, notify: Arc<NotifyManager>) -> Self {
+        self.notify = Some(notify);
+        self
+    }
+
+    pub fn with_utc_offset(mut self, utc_offset: FixedOffset) -> Self {
+        self.utc_offset = utc_offset;
+        self
+    }
+}
@@ -1,56 +1,155 @@
+use crate::execution::RuleExecution;
 use crate::model::Rule;
-use std::collections::HashMap;
+use crate::window::WindowCheckpoint;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// 规则存储
+///
+/// 每次保存都会记录该版本的完整快照，用于查看历史与回滚。
+#[async_trait]
+pub trait RuleStore: Send + Sync {
+    /// 保存规则当前版本，同时记录该版本的快照
+    async fn save(&self, rule: &Rule) -> anyhow::Result<()>;
+
+    async fn get(&self, rule_id: &str) -> anyhow::Result<Option<Rule>>;
+
+    /// 删除规则及其全部版本（执行历史保留）
+    async fn delete(&self, rule_id: &str) -> anyhow::Result<()>;
+
+    async fn list(&self) -> anyhow::Result<Vec<Rule>>;
+
+    async fn find_by_group(&self, group: &str) -> anyhow::Result<Vec<Rule>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|r| r.group.as_deref() == Some(group))
+            .collect())
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> anyhow::Result<Vec<Rule>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|r| r.tags.iter().any(|t| t == tag))
+            .collect())
+    }
+
+    /// 规则的全部版本（按版本号升序）
+    async fn versions(&self, rule_id: &str) -> anyhow::Result<Vec<Rule>>;
+
+    async fn get_version(&self, rule_id: &str, version: i32) -> anyhow::Result<Option<Rule>> {
+        Ok(self
+            .versions(rule_id)
+            .await?
+            .into_iter()
+            .find(|r| r.version == version))
+    }
+
+    /// 保存执行记录（同 ID 覆盖）
+    async fn save_execution(&self, execution: &RuleExecution) -> anyhow::Result<()>;
+
+    /// 执行历史（按开始时间倒序）
+    async fn executions(&self, rule_id: Option<&str>, limit: usize) -> anyhow::Result<Vec<RuleExecution>>;
+    
+    /// 保存窗口状态检查点（覆盖上一次）
+    async fn save_window_checkpoint(&self, checkpoint: &WindowCheckpoint) -> anyhow::Result<()>;
+    
+    async fn load_window_checkpoint(&self) -> anyhow::Result<Option<WindowCheckpoint>>;
+}
+
 /// 规则存储（内存实现）
 pub struct RuleStorage {
     rules: Arc<RwLock<HashMap<String, Rule>>>,
+    versions: Arc<RwLock<HashMap<String, BTreeMap<i32, Rule>>>>,
+    executions: Arc<RwLock<Vec<RuleExecution>>>,
+    window_checkpoint: Arc<RwLock<Option<WindowCheckpoint>>>,
 }
 
 impl RuleStorage {
     pub fn new() -> Self {
         Self {
             rules: Arc::new(RwLock::new(HashMap::new())),
+            versions: Arc::new(RwLock::new(HashMap::new())),
+            executions: Arc::new(RwLock::new(Vec::new())),
+            window_checkpoint: Arc::new(RwLock::new(None)),
         }
     }
-    
-    pub async fn save(&self, rule: Rule) -> anyhow::Result<()> {
+}
+
+#[async_trait]
+impl RuleStore for RuleStorage {
+    async fn save(&self, rule: &Rule) -> anyhow::Result<()> {
         let mut rules = self.rules.write().await;
-        rules.insert(rule.id.clone(), rule);
+        let mut versions = self.versions.write().await;
+        rules.insert(rule.id.clone(), rule.clone());
+        versions
+            .entry(rule.id.clone())
+            .or_default()
+            .insert(rule.version, rule.clone());
         Ok(())
     }
-    
-    pub async fn get(&self, rule_id: &str) -> anyhow::Result<Option<Rule>> {
+
+    async fn get(&self, rule_id: &str) -> anyhow::Result<Option<Rule>> {
         let rules = self.rules.read().await;
         Ok(rules.get(rule_id).cloned())
     }
-    
-    pub async fn delete(&self, rule_id: &str) -> anyhow::Result<()> {
+
+    async fn delete(&self, rule_id: &str) -> anyhow::Result<()> {
         let mut rules = self.rules.write().await;
+        let mut versions = self.versions.write().await;
         rules.remove(rule_id);
+        versions.remove(rule_id);
         Ok(())
     }
-    
-    pub async fn list(&self) -> anyhow::Result<Vec<Rule>> {
+
+    async fn list(&self) -> anyhow::Result<Vec<Rule>> {
         let rules = self.rules.read().await;
         Ok(rules.values().cloned().collect())
     }
-    
-    pub async fn find_by_group(&self, group: &str) -> anyhow::Result<Vec<Rule>> {
-        let rules = self.rules.read().await;
-        Ok(rules.values()
-            .filter(|r| r.group.as_deref() == Some(group))
+
+    async fn versions(&self, rule_id: &str) -> anyhow::Result<Vec<Rule>> {
+        let versions = self.versions.read().await;
+        Ok(versions
+            .get(rule_id)
+            .map(|v| v.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn save_execution(&self, execution: &RuleExecution) -> anyhow::Result<()> {
+        let mut executions = self.executions.write().await;
+        match executions.iter_mut().find(|e| e.id == execution.id) {
+            Some(existing) => *existing = execution.clone(),
+            None => executions.push(execution.clone()),
+        }
+        Ok(())
+    }
+
+    async fn executions(&self, rule_id: Option<&str>, limit: usize) -> anyhow::Result<Vec<RuleExecution>> {
+        let executions = self.executions.read().await;
+        let mut filtered: Vec<_> = executions
+            .iter()
+            .filter(|e| rule_id.is_none_or(|rid| e.rule_id == rid))
             .cloned()
-            .collect())
+            .collect();
+
+        // 按时间倒序
+        filtered.sort_by_key(|e| std::cmp::Reverse(e.started_at));
+        filtered.truncate(limit);
+        Ok(filtered)
     }
     
-    pub async fn find_by_tag(&self, tag: &str) -> anyhow::Result<Vec<Rule>> {
-        let rules = self.rules.read().await;
-        Ok(rules.values()
-            .filter(|r| r.tags.contains(&tag.to_string()))
-            .cloned()
-            .collect())
+    async fn save_window_checkpoint(&self, checkpoint: &WindowCheckpoint) -> anyhow::Result<()> {
+        *self.window_checkpoint.write().await = Some(checkpoint.clone());
+        Ok(())
+    }
+    
+    async fn load_window_checkpoint(&self) -> anyhow::Result<Option<WindowCheckpoint>> {
+        Ok(self.window_checkpoint.read().await.clone())
     }
 }
 
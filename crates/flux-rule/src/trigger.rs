@@ -1,16 +1,53 @@
-use crate::context::RuleContext;
+use crate::context::{RuleContext, TriggerInfo};
 use crate::engine::RuleEngine;
+use crate::event::TriggerEvent;
+use crate::execution::RuleOutcome;
+use crate::index::{DeviceInfo, TriggerIndex};
 use crate::model::{Rule, RuleTrigger};
+use crate::window::functions::report_key;
 use anyhow::Result;
+use chrono::Utc;
+use flux_core::bus::EventBus;
+use flux_device::DeviceManager;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use tokio_cron_scheduler::{Job, JobScheduler};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+/// 分组层级的最大查询深度（防止分组成环）
+const MAX_GROUP_DEPTH: usize = 16;
+
+/// 缺失数据检查间隔
+const NO_DATA_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 默认窗口状态检查点间隔
+const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
 
 /// 触发器管理器
+///
+/// 定时触发由内部调度器执行；设备事件与数据变化触发通过 [`TriggerManager::subscribe`]
+/// 订阅 EventBus，或由外部调用 [`TriggerManager::handle_event`]。规则新增、修改后需调用
+/// [`TriggerManager::register_rule`]，删除后调用 [`TriggerManager::unregister_rule`]。
+///
+/// 带窗口条件的规则在条件满足时才执行；启动时从检查点恢复窗口状态，运行中定期检查缺失数据
+/// 并保存检查点，停止时再保存一次。
+#[derive(Clone)]
 pub struct TriggerManager {
     engine: Arc<RuleEngine>,
     scheduler: Arc<RwLock<Option<JobScheduler>>>,
+    /// 定时任务 (rule_id -> job_id)
+    jobs: Arc<RwLock<HashMap<String, Uuid>>>,
+    index: Arc<RwLock<TriggerIndex>>,
+    /// 用于按分组/标签匹配设备
+    devices: Option<Arc<DeviceManager>>,
+    checkpoint_interval: Duration,
+    /// 缺失数据检查与检查点任务
+    tickers: Arc<RwLock<Vec<JoinHandle<()>>>>,
 }
 
 impl TriggerManager {
@@ -18,32 +55,88 @@ impl TriggerManager {
         Self {
             engine,
             scheduler: Arc::new(RwLock::new(None)),
+            jobs: Arc::new(RwLock::new(HashMap::new())),
+            index: Arc::new(RwLock::new(TriggerIndex::new())),
+            devices: None,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            tickers: Arc::new(RwLock::new(Vec::new())),
         }
     }
     
+    /// 按分组、标签选择设备的触发器需要设备管理器
+    pub fn with_device_manager(mut self, devices: Arc<DeviceManager>) -> Self {
+        self.devices = Some(devices);
+        self
+    }
+    
+    /// 窗口状态检查点间隔（默认 60 秒）
+    pub fn with_checkpoint_interval(mut self, interval: Duration) -> Self {
+        self.checkpoint_interval = interval;
+        self
+    }
+    
     /// 启动触发器系统
     pub async fn start(&self) -> Result<()> {
+        if let Err(e) = self.engine.restore_windows().await {
+            warn!(error = %e, "Failed to restore window states");
+        }
+        
         let scheduler = JobScheduler::new().await?;
         scheduler.start().await?;
         
         *self.scheduler.write().await = Some(scheduler);
         
+        let mut tickers = self.tickers.write().await;
+        tickers.push(self.spawn_no_data_checker());
+        tickers.push(self.spawn_checkpointer());
+        
         info!("Trigger manager started");
         Ok(())
     }
     
     /// 停止触发器系统
     pub async fn stop(&self) -> Result<()> {
+        for ticker in self.tickers.write().await.drain(..) {
+            ticker.abort();
+        }
         if let Some(mut scheduler) = self.scheduler.write().await.take() {
             scheduler.shutdown().await?;
         }
+        self.jobs.write().await.clear();
+        
+        if let Err(e) = self.engine.checkpoint_windows().await {
+            warn!(error = %e, "Failed to save window checkpoint");
+        }
         
         info!("Trigger manager stopped");
         Ok(())
     }
     
-    /// 注册规则触发器
+    /// 从规则引擎重新加载全部规则的触发器
+    pub async fn sync_rules(&self) -> Result<usize> {
+        let rules = self.engine.list_rules().await?;
+        let existing: Vec<String> = self.jobs.read().await.keys().cloned().collect();
+        for rule_id in existing {
+            self.unregister_rule(&rule_id).await?;
+        }
+        self.index.write().await.clear();
+        
+        for rule in &rules {
+            self.register_rule(rule).await?;
+        }
+        
+        info!(count = rules.len(), "Rule triggers synchronized");
+        Ok(rules.len())
+    }
+    
+    /// 注册规则触发器（已注册时替换）；禁用的规则只取消注册
     pub async fn register_rule(&self, rule: &Rule) -> Result<()> {
+        self.unregister_rule(&rule.id).await?;
+        if !rule.enabled {
+            debug!(rule_id = %rule.id, "Rule disabled, trigger not registered");
+            return Ok(());
+        }
+        
         match &rule.trigger {
             RuleTrigger::Schedule { cron } => {
                 self.register_schedule_trigger(rule, cron).await?;
@@ -52,27 +145,33 @@ impl TriggerManager {
                 // 手动触发不需要注册
                 debug!(rule_id = %rule.id, "Manual trigger, no registration needed");
             }
-            RuleTrigger::DeviceEvent { device_id, event_type } => {
-                debug!(
-                    rule_id = %rule.id,
-                    device_id = %device_id,
-                    event_type = %event_type,
-                    "Device event trigger registered (requires external event bus)"
-                );
-            }
-            RuleTrigger::DataChange { device_id, metric } => {
-                debug!(
-                    rule_id = %rule.id,
-                    device_id = %device_id,
-                    metric = ?metric,
-                    "Data change trigger registered (requires external event bus)"
-                );
+            RuleTrigger::DeviceEvent { selector, .. } | RuleTrigger::DataChange { selector, .. } => {
+                self.index.write().await.insert(rule);
+                debug!(rule_id = %rule.id, selector = ?selector, "Device trigger registered");
             }
         }
         
         Ok(())
     }
     
+    /// 取消注册规则触发器
+    pub async fn unregister_rule(&self, rule_id: &str) -> Result<()> {
+        self.index.write().await.remove(rule_id);
+        
+        if let Some(job_id) = self.jobs.write().await.remove(rule_id) {
+            if let Some(scheduler) = self.scheduler.read().await.as_ref() {
+                scheduler.remove(&job_id).await?;
+            }
+            debug!(rule_id = %rule_id, "Schedule trigger removed");
+        }
+        Ok(())
+    }
+    
+    /// 已注册的设备事件/数据变化触发器数量
+    pub async fn indexed_rules(&self) -> usize {
+        self.index.read().await.len()
+    }
+    
     /// 注册定时触发器
     async fn register_schedule_trigger(&self, rule: &Rule, cron: &str) -> Result<()> {
         let scheduler_lock = self.scheduler.read().await;
@@ -98,7 +197,8 @@ impl TriggerManager {
             })
         })?;
         
-        scheduler.add(job).await?;
+        let job_id = scheduler.add(job).await?;
+        self.jobs.write().await.insert(rule.id.clone(), job_id);
         
         info!(
             rule_id = %rule.id,
@@ -110,46 +210,181 @@ impl TriggerManager {
         Ok(())
     }
     
-    /// 处理设备事件（外部调用）
-    pub async fn handle_device_event(
-        &self,
-        device_id: &str,
-        event_type: &str,
-        data: serde_json::Value,
-    ) -> Result<()> {
-        // 查找匹配的规则
-        let rules = self.engine.list_rules().await?;
+    /// 订阅 EventBus，将设备消息分发给匹配的规则
+    ///
+    /// 每条消息在独立任务中处理，规则执行不会阻塞订阅。
+    pub fn subscribe(self: &Arc<Self>, bus: &EventBus) -> JoinHandle<()> {
+        let mut rx = bus.subscribe();
+        let manager = self.clone();
+        
+        tokio::spawn(async move {
+            info!("Rule trigger subscribed to event bus");
+            loop {
+                match rx.recv().await {
+                    Ok(message) => {
+                        let Some(event) = TriggerEvent::from_message(&message) else {
+                            continue;
+                        };
+                        let manager = manager.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = manager.handle_event(event).await {
+                                error!(topic = %message.topic, error = %e, "Failed to dispatch rule trigger");
+                            }
+                        });
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(skipped, "Rule trigger lagged behind event bus, messages skipped");
+                    }
+                    Err(RecvError::Closed) => {
+                        info!("Event bus closed, rule trigger subscription stopped");
+                        break;
+                    }
+                }
+            }
+        })
+    }
+    
+    /// 处理设备消息，返回执行了的规则 ID（未满足窗口条件、被跳过的规则不计入）
+    pub async fn handle_event(&self, event: TriggerEvent) -> Result<Vec<String>> {
+        let now_ms = Utc::now().timestamp_millis();
+        self.engine.windows().touch(&report_key(event.device_id()), now_ms);
+        
+        let device = {
+            let needs_device_info = self.index.read().await.needs_device_info();
+            if needs_device_info {
+                self.device_info(event.device_id()).await
+            } else {
+                DeviceInfo::default()
+            }
+        };
+        let rule_ids = self.index.read().await.matches(&event, &device);
+        if rule_ids.is_empty() {
+            return Ok(rule_ids);
+        }
+        
+        let context = Self::build_context(&event);
+        let mut batch = Vec::with_capacity(rule_ids.len());
+        for rule_id in rule_ids {
+            let mut context = context.clone();
+            let rule = match self.engine.get_rule(&rule_id).await {
+                Ok(rule) => rule,
+                Err(e) => {
+                    warn!(rule_id = %rule_id, error = %e, "Indexed rule not found");
+                    continue;
+                }
+            };
+            if let Some(condition) = &rule.condition {
+                match condition.evaluate(self.engine.windows(), &rule.id, &event, now_ms) {
+                    Some(window) => {
+                        context.system_vars.insert("window".to_string(), window);
+                    }
+                    None => continue,
+                }
+            }
+            
+            info!(rule_id = %rule_id, device_id = %event.device_id(), "Triggering rule by device message");
+            batch.push((rule, context));
+        }
+        
+        // 按冲突策略、优先级与依赖执行
+        let mut triggered = Vec::with_capacity(batch.len());
+        for (rule_id, outcome) in self.engine.execute_rules(batch).await {
+            match outcome {
+                RuleOutcome::Success => {}
+                RuleOutcome::Failed(e) => error!(rule_id = %rule_id, error = %e, "Failed to execute rule"),
+                RuleOutcome::Skipped(reason) => {
+                    debug!(rule_id = %rule_id, reason = %reason, "Rule skipped");
+                    continue;
+                }
+            }
+            triggered.push(rule_id);
+        }
+        triggered.sort();
         
-        for rule in rules {
+        Ok(triggered)
+    }
+    
+    /// 检查 `no_data` 窗口条件，为超时未上报的设备执行规则，返回 (rule_id, device_id)
+    ///
+    /// 只检查已启用且收到过该设备消息的规则；设备恢复上报前同一中断不会重复触发。
+    pub async fn check_missing_data(&self) -> Result<Vec<(String, String)>> {
+        self.check_missing_data_at(Utc::now().timestamp_millis()).await
+    }
+    
+    async fn check_missing_data_at(&self, now_ms: i64) -> Result<Vec<(String, String)>> {
+        let mut fired = Vec::new();
+        for rule in self.engine.list_rules().await? {
+            let Some(condition) = rule.condition.as_ref().filter(|c| c.is_no_data()) else {
+                continue;
+            };
             if !rule.enabled {
                 continue;
             }
             
-            if let RuleTrigger::DeviceEvent {
-                device_id: trigger_device_id,
-                event_type: trigger_event_type,
-            } = &rule.trigger
-            {
-                if trigger_device_id == device_id && trigger_event_type == event_type {
-                    let mut context = RuleContext::new();
-                    context.device_data.insert("event_data".to_string(), data.clone());
-                    context.device_data.insert("device_id".to_string(), device_id.into());
-                    context.device_data.insert("event_type".to_string(), event_type.into());
-                    
-                    info!(
-                        rule_id = %rule.id,
-                        device_id = %device_id,
-                        event_type = %event_type,
-                        "Triggering rule by device event"
-                    );
-                    
-                    if let Err(e) = self.engine.trigger_manual(&rule.id, context).await {
-                        error!(rule_id = %rule.id, error = %e, "Failed to execute rule");
-                    }
+            for (device_id, window) in condition.overdue(self.engine.windows(), &rule.id, now_ms) {
+                let mut context = RuleContext::new();
+                context.device_data.insert("device_id".to_string(), device_id.as_str().into());
+                context.system_vars.insert("window".to_string(), window);
+                context.trigger_info = Some(TriggerInfo {
+                    trigger_type: "no_data".to_string(),
+                    device_id: Some(device_id.clone()),
+                    event_type: None,
+                    metric: None,
+                });
+                
+                info!(rule_id = %rule.id, device_id = %device_id, "Triggering rule by missing data");
+                if let Err(e) = self.engine.trigger_manual(&rule.id, context).await {
+                    error!(rule_id = %rule.id, error = %e, "Failed to execute rule");
                 }
+                fired.push((rule.id.clone(), device_id));
             }
         }
-        
+        Ok(fired)
+    }
+    
+    fn spawn_no_data_checker(&self) -> JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(NO_DATA_CHECK_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = manager.check_missing_data().await {
+                    warn!(error = %e, "Failed to check missing device data");
+                }
+            }
+        })
+    }
+    
+    fn spawn_checkpointer(&self) -> JoinHandle<()> {
+        let engine = self.engine.clone();
+        let period = self.checkpoint_interval;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = engine.checkpoint_windows().await {
+                    warn!(error = %e, "Failed to save window checkpoint");
+                }
+            }
+        })
+    }
+    
+    /// 处理设备事件（外部调用）
+    pub async fn handle_device_event(
+        &self,
+        device_id: &str,
+        event_type: &str,
+        data: serde_json::Value,
+    ) -> Result<()> {
+        self.handle_event(TriggerEvent::DeviceEvent {
+            device_id: device_id.to_string(),
+            event_type: event_type.to_string(),
+            data,
+            topic: None,
+        })
+        .await?;
         Ok(())
     }
     
@@ -160,56 +395,93 @@ impl TriggerManager {
         metric: &str,
         value: serde_json::Value,
     ) -> Result<()> {
-        // 查找匹配的规则
-        let rules = self.engine.list_rules().await?;
+        let mut metrics = serde_json::Map::new();
+        metrics.insert(metric.to_string(), value);
+        self.handle_event(TriggerEvent::DataChange {
+            device_id: device_id.to_string(),
+            metrics,
+            topic: None,
+        })
+        .await?;
+        Ok(())
+    }
+    
+    fn build_context(event: &TriggerEvent) -> RuleContext {
+        let mut context = RuleContext::new();
+        context.device_data.insert("device_id".to_string(), event.device_id().into());
+        if let Some(topic) = event.topic() {
+            context.system_vars.insert("topic".to_string(), topic.into());
+        }
         
-        for rule in rules {
-            if !rule.enabled {
-                continue;
-            }
-            
-            if let RuleTrigger::DataChange {
-                device_id: trigger_device_id,
-                metric: trigger_metric,
-            } = &rule.trigger
-            {
-                // 检查设备 ID 是否匹配
-                if trigger_device_id != device_id {
-                    continue;
+        context.trigger_info = Some(match event {
+            TriggerEvent::DeviceEvent { device_id, event_type, data, .. } => {
+                context.device_data.insert("event_data".to_string(), data.clone());
+                context.device_data.insert("event_type".to_string(), event_type.as_str().into());
+                TriggerInfo {
+                    trigger_type: "device_event".to_string(),
+                    device_id: Some(device_id.clone()),
+                    event_type: Some(event_type.clone()),
+                    metric: None,
                 }
-                
-                // 检查指标是否匹配（None 表示任何指标）
-                if let Some(tm) = trigger_metric {
-                    if tm != metric {
-                        continue;
-                    }
+            }
+            TriggerEvent::DataChange { device_id, metrics, .. } => {
+                for (metric, value) in metrics {
+                    context.device_data.insert(metric.clone(), value.clone());
                 }
-                
-                let mut context = RuleContext::new();
-                context.device_data.insert(metric.to_string(), value.clone());
-                context.device_data.insert("device_id".to_string(), device_id.into());
-                
-                info!(
-                    rule_id = %rule.id,
-                    device_id = %device_id,
-                    metric = %metric,
-                    "Triggering rule by data change"
-                );
-                
-                if let Err(e) = self.engine.trigger_manual(&rule.id, context).await {
-                    error!(rule_id = %rule.id, error = %e, "Failed to execute rule");
+                TriggerInfo {
+                    trigger_type: "data_change".to_string(),
+                    device_id: Some(device_id.clone()),
+                    event_type: None,
+                    // 单指标消息记录指标名
+                    metric: (metrics.len() == 1).then(|| metrics.keys().next().cloned()).flatten(),
                 }
             }
+        });
+        context
+    }
+    
+    /// 查询设备的标签及所属分组（沿上级分组向上）
+    async fn device_info(&self, device_id: &str) -> DeviceInfo {
+        let Some(devices) = &self.devices else {
+            return DeviceInfo::default();
+        };
+        let device = match devices.get_device(device_id).await {
+            Ok(Some(device)) => device,
+            Ok(None) => return DeviceInfo::default(),
+            Err(e) => {
+                warn!(device_id = %device_id, error = %e, "Failed to load device for rule trigger");
+                return DeviceInfo::default();
+            }
+        };
+        
+        let mut groups = Vec::new();
+        let mut next = device.group_id;
+        while let Some(group_id) = next {
+            if groups.contains(&group_id) || groups.len() >= MAX_GROUP_DEPTH {
+                break;
+            }
+            next = match devices.get_group(&group_id).await {
+                Ok(group) => group.and_then(|g| g.parent_id),
+                Err(e) => {
+                    warn!(group_id = %group_id, error = %e, "Failed to load device group for rule trigger");
+                    None
+                }
+            };
+            groups.push(group_id);
         }
         
-        Ok(())
+        DeviceInfo {
+            groups,
+            tags: device.tags,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::Rule;
+    use crate::model::{DeviceSelector, Rule};
+    use flux_types::message::Message;
 
     #[tokio::test]
     async fn test_trigger_manager() {
@@ -229,7 +501,195 @@ mod tests {
         };
         
         manager.register_rule(&rule).await.unwrap();
+        // 重复注册替换原有任务
+        manager.register_rule(&rule).await.unwrap();
+        assert_eq!(manager.jobs.read().await.len(), 1);
+        manager.unregister_rule(&rule.id).await.unwrap();
+        assert!(manager.jobs.read().await.is_empty());
         
         manager.stop().await.unwrap();
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_event_bus_triggers_rules() {
+        use flux_device::{device, device_group, Device, DeviceGroup, DeviceType, Protocol};
+        use sea_orm::{ConnectionTrait, Database, Schema};
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let backend = db.get_database_backend();
+        let schema = Schema::new(backend);
+        db.execute(backend.build(&schema.create_table_from_entity(device_group::Entity)))
+            .await
+            .unwrap();
+        db.execute(backend.build(&schema.create_table_from_entity(device::Entity)))
+            .await
+            .unwrap();
+        let devices = Arc::new(flux_device::DeviceManager::new(Arc::new(db), 30, 60));
+
+        let building = devices
+            .create_group(DeviceGroup::new("building".into(), None))
+            .await
+            .unwrap();
+        let floor = devices
+            .create_group(DeviceGroup::new("floor".into(), Some(building.id.clone())))
+            .await
+            .unwrap();
+        let mut fridge = Device::new("fridge".into(), DeviceType::Sensor, Protocol::MQTT);
+        fridge.add_tag("cold_chain".into());
+        fridge.group_id = Some(floor.id.clone());
+        let fridge = devices.register_device(fridge).await.unwrap();
+
+        let engine = Arc::new(RuleEngine::new());
+        let manager = Arc::new(TriggerManager::new(engine.clone()).with_device_manager(devices));
+        manager.start().await.unwrap();
+
+        let rules = [
+            ("building_temp", DeviceSelector::group(building.id.clone()), Some("temperature")),
+            ("other_tag", DeviceSelector::tag("hvac"), None),
+        ];
+        for (id, selector, metric) in rules {
+            engine
+                .add_rule(Rule {
+                    id: id.to_string(),
+                    trigger: RuleTrigger::DataChange {
+                        selector,
+                        metric: metric.map(str::to_string),
+                    },
+                    script: "if device.temperature > 8.0 { throw \"too warm\"; }".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        engine
+            .add_rule(Rule {
+                id: "door_open".to_string(),
+                trigger: RuleTrigger::DeviceEvent {
+                    selector: DeviceSelector::device("door_01"),
+                    event_type: "open".to_string(),
+                },
+                script: "let by = device.event_data.by;".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(manager.sync_rules().await.unwrap(), 3);
+        assert_eq!(manager.indexed_rules().await, 3);
+
+        let bus = EventBus::new(16);
+        let subscription = manager.subscribe(&bus);
+        let topic = format!("device/{}/telemetry", fridge.id);
+        bus.publish(Message::new(topic, serde_json::json!({"temperature": 12.5}))).unwrap();
+        bus.publish(Message::new(
+            "device/door_01/event/open".to_string(),
+            serde_json::json!({"by": "card"}),
+        ))
+        .unwrap();
+        bus.publish(Message::new(
+            "device/door_01/event/close".to_string(),
+            serde_json::json!({}),
+        ))
+        .unwrap();
+
+        let mut history = Vec::new();
+        for _ in 0..100 {
+            history = engine.get_execution_history(None, 10).await.unwrap();
+            if history.len() >= 2 && history.iter().all(|e| e.finished_at.is_some()) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let mut triggered: Vec<_> = history.iter().map(|e| e.rule_id.as_str()).collect();
+        triggered.sort();
+        assert_eq!(triggered, vec!["building_temp", "door_open"]);
+        let building_run = history.iter().find(|e| e.rule_id == "building_temp").unwrap();
+        assert!(building_run.error.as_deref().unwrap_or_default().contains("too warm"), "{:?}", building_run.error);
+
+        // 禁用后不再触发
+        let mut rule = engine.get_rule("door_open").await.unwrap();
+        rule.enabled = false;
+        manager.register_rule(&engine.update_rule(rule).await.unwrap()).await.unwrap();
+        let triggered = manager
+            .handle_event(TriggerEvent::DeviceEvent {
+                device_id: "door_01".into(),
+                event_type: "open".into(),
+                data: serde_json::json!({}),
+                topic: None,
+            })
+            .await
+            .unwrap();
+        assert!(triggered.is_empty());
+
+        subscription.abort();
+        manager.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_window_conditions() {
+        use crate::window::{CompareOp, WindowCondition};
+
+        let engine = Arc::new(RuleEngine::new());
+        let manager = TriggerManager::new(engine.clone());
+        let selector = DeviceSelector::device("th_01");
+        let rules = [
+            (
+                "hot",
+                WindowCondition::Sustained {
+                    metric: Some("temperature".into()),
+                    op: CompareOp::Gt,
+                    threshold: 30.0,
+                    duration_seconds: 0,
+                },
+                "if system.window.value <= 30.0 { throw \"unexpected\"; }",
+            ),
+            ("silent", WindowCondition::NoData { timeout_seconds: 60 }, "let s = system.window.silent_seconds;"),
+        ];
+        for (id, condition, script) in rules {
+            let rule = Rule {
+                id: id.to_string(),
+                trigger: RuleTrigger::DataChange {
+                    selector: selector.clone(),
+                    metric: None,
+                },
+                condition: Some(condition),
+                script: script.to_string(),
+                ..Default::default()
+            };
+            engine.add_rule(rule.clone()).await.unwrap();
+            manager.register_rule(&rule).await.unwrap();
+        }
+
+        let temperature = |value: f64| manager.handle_data_change("th_01", "temperature", value.into());
+        temperature(25.0).await.unwrap();
+        temperature(31.0).await.unwrap();
+        // 同一持续期间只触发一次
+        temperature(32.0).await.unwrap();
+        let fired = manager
+            .handle_event(TriggerEvent::DataChange {
+                device_id: "th_01".into(),
+                metrics: serde_json::json!({"temperature": 33.0}).as_object().unwrap().clone(),
+                topic: None,
+            })
+            .await
+            .unwrap();
+        assert!(fired.is_empty());
+
+        let history = engine.get_execution_history(Some("hot"), 10).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].error.is_none(), "{:?}", history[0].error);
+        assert!(history[0].context["system_vars"]["window"]["type"] == "sustained");
+
+        let now = Utc::now().timestamp_millis();
+        assert!(manager.check_missing_data_at(now).await.unwrap().is_empty());
+        let fired = manager.check_missing_data_at(now + 120_000).await.unwrap();
+        assert_eq!(fired, vec![("silent".to_string(), "th_01".to_string())]);
+        assert!(manager.check_missing_data_at(now + 180_000).await.unwrap().is_empty());
+        let history = engine.get_execution_history(Some("silent"), 10).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].error.is_none(), "{:?}", history[0].error);
+
+        // 脚本窗口函数可读取触发器记录的上报时间
+        let last = engine.windows().last_seen(&report_key("th_01"));
+        assert!(last.is_some_and(|ms| ms <= Utc::now().timestamp_millis()));
+    }
 }
@@ -0,0 +1,322 @@
+use super::{WindowKey, WindowStats, WindowStore};
+use crate::event::TriggerEvent;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// 条件状态在窗口存储中的名称
+const CONDITION_KEY: &str = "condition";
+
+/// 比较运算
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompareOp {
+    #[serde(rename = "gt", alias = ">")]
+    Gt,
+    #[serde(rename = "gte", alias = ">=")]
+    Gte,
+    #[serde(rename = "lt", alias = "<")]
+    Lt,
+    #[serde(rename = "lte", alias = "<=")]
+    Lte,
+    #[serde(rename = "eq", alias = "==")]
+    Eq,
+    #[serde(rename = "ne", alias = "!=")]
+    Ne,
+}
+
+impl CompareOp {
+    pub fn test(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Self::Gt => value > threshold,
+            Self::Gte => value >= threshold,
+            Self::Lt => value < threshold,
+            Self::Lte => value <= threshold,
+            Self::Eq => value == threshold,
+            Self::Ne => value != threshold,
+        }
+    }
+}
+
+/// 窗口聚合方式（`rate` 为每秒样本数）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregate {
+    Avg,
+    Min,
+    Max,
+    Count,
+    Sum,
+    Rate,
+}
+
+impl Aggregate {
+    pub fn pick(self, stats: &WindowStats) -> f64 {
+        match self {
+            Self::Avg => stats.avg,
+            Self::Min => stats.min,
+            Self::Max => stats.max,
+            Self::Count => stats.count as f64,
+            Self::Sum => stats.sum,
+            Self::Rate => stats.rate,
+        }
+    }
+}
+
+/// 规则的窗口条件，按设备分别计算
+///
+/// `metric` 为空时：数据变化取唯一的指标，设备事件按每次事件计 1（用于统计事件次数）。
+/// 条件满足时脚本中可通过 `system.window` 读取计算结果。
+///
+/// ```yaml
+/// condition:
+///   type: sustained
+///   metric: temperature
+///   op: ">"
+///   threshold: 30.0
+///   duration_seconds: 300
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WindowCondition {
+    /// 指标持续满足比较条件达到时长，每次持续期间只触发一次
+    Sustained {
+        #[serde(default)]
+        metric: Option<String>,
+        op: CompareOp,
+        threshold: f64,
+        duration_seconds: u64,
+    },
+
+    /// 滑动窗口聚合值满足条件（每条消息都会判断）
+    Sliding {
+        #[serde(default)]
+        metric: Option<String>,
+        aggregate: Aggregate,
+        window_seconds: u64,
+        op: CompareOp,
+        threshold: f64,
+    },
+
+    /// 滚动窗口结束时聚合值满足条件（窗口按时间对齐，在下一窗口的首条消息时判断）
+    Tumbling {
+        #[serde(default)]
+        metric: Option<String>,
+        aggregate: Aggregate,
+        window_seconds: u64,
+        op: CompareOp,
+        threshold: f64,
+    },
+
+    /// 相邻两次上报的变化率（每秒）满足条件
+    RateOfChange {
+        #[serde(default)]
+        metric: Option<String>,
+        op: CompareOp,
+        threshold: f64,
+    },
+
+    /// 设备超过时长未上报（由触发器定时检查，每次中断只触发一次）
+    NoData { timeout_seconds: u64 },
+}
+
+impl WindowCondition {
+    pub fn is_no_data(&self) -> bool {
+        matches!(self, Self::NoData { .. })
+    }
+
+    /// 设备消息到达时更新状态并判断，满足时返回写入 `system.window` 的结果
+    pub fn evaluate(&self, store: &WindowStore, rule_id: &str, event: &TriggerEvent, now_ms: i64) -> Option<Value> {
+        let key = WindowKey::new(rule_id, event.device_id(), CONDITION_KEY);
+        match self {
+            Self::Sustained {
+                metric,
+                op,
+                threshold,
+                duration_seconds,
+            } => {
+                let value = event_value(event, metric.as_deref())?;
+                let sustained = store.sustained(&key, op.test(value, *threshold), secs_to_ms(*duration_seconds), now_ms);
+                sustained.first_reached.then(|| {
+                    json!({
+                        "type": "sustained",
+                        "value": value,
+                        "held_seconds": sustained.held_ms / 1000,
+                    })
+                })
+            }
+            Self::Sliding {
+                metric,
+                aggregate,
+                window_seconds,
+                op,
+                threshold,
+            } => {
+                let value = event_value(event, metric.as_deref())?;
+                let stats = store.sliding(&key, value, secs_to_ms(*window_seconds), now_ms);
+                window_result("sliding", *aggregate, stats, *op, *threshold)
+            }
+            Self::Tumbling {
+                metric,
+                aggregate,
+                window_seconds,
+                op,
+                threshold,
+            } => {
+                let value = event_value(event, metric.as_deref())?;
+                let stats = store.tumbling(&key, value, secs_to_ms(*window_seconds), now_ms)?;
+                window_result("tumbling", *aggregate, stats, *op, *threshold)
+            }
+            Self::RateOfChange { metric, op, threshold } => {
+                let value = event_value(event, metric.as_deref())?;
+                let rate = store.rate_of_change(&key, value, now_ms)?;
+                op.test(rate, *threshold).then(|| {
+                    json!({
+                        "type": "rate_of_change",
+                        "value": value,
+                        "rate": rate,
+                    })
+                })
+            }
+            Self::NoData { .. } => {
+                store.touch(&key, now_ms);
+                None
+            }
+        }
+    }
+
+    /// 超时未上报的设备及对应的 `system.window` 结果（仅 `no_data` 条件）
+    pub fn overdue(&self, store: &WindowStore, rule_id: &str, now_ms: i64) -> Vec<(String, Value)> {
+        let Self::NoData { timeout_seconds } = self else {
+            return Vec::new();
+        };
+        store
+            .take_overdue(rule_id, secs_to_ms(*timeout_seconds), now_ms)
+            .into_iter()
+            .filter(|(key, _)| key.name == CONDITION_KEY)
+            .map(|(key, last_ms)| {
+                let result = json!({
+                    "type": "no_data",
+                    "last_seen": last_ms,
+                    "silent_seconds": (now_ms - last_ms) / 1000,
+                });
+                (key.device_id, result)
+            })
+            .collect()
+    }
+}
+
+fn secs_to_ms(seconds: u64) -> i64 {
+    i64::try_from(seconds).unwrap_or(i64::MAX / 1000) * 1000
+}
+
+fn window_result(kind: &str, aggregate: Aggregate, stats: WindowStats, op: CompareOp, threshold: f64) -> Option<Value> {
+    let value = aggregate.pick(&stats);
+    op.test(value, threshold).then(|| {
+        json!({
+            "type": kind,
+            "value": value,
+            "stats": stats,
+        })
+    })
+}
+
+/// 条件使用的数值
+fn event_value(event: &TriggerEvent, metric: Option<&str>) -> Option<f64> {
+    match (event, metric) {
+        (TriggerEvent::DataChange { metrics, .. }, Some(metric)) => metrics.get(metric)?.as_f64(),
+        (TriggerEvent::DataChange { metrics, .. }, None) if metrics.len() == 1 => metrics.values().next()?.as_f64(),
+        (TriggerEvent::DataChange { .. }, None) => None,
+        (TriggerEvent::DeviceEvent { data, .. }, Some(metric)) => data.get(metric)?.as_f64(),
+        (TriggerEvent::DeviceEvent { .. }, None) => Some(1.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temperature(device_id: &str, value: f64) -> TriggerEvent {
+        TriggerEvent::DataChange {
+            device_id: device_id.to_string(),
+            metrics: json!({ "temperature": value }).as_object().unwrap().clone(),
+            topic: None,
+        }
+    }
+
+    #[test]
+    fn test_sustained_condition_per_device() {
+        let condition: WindowCondition = serde_json::from_value(json!({
+            "type": "sustained",
+            "metric": "temperature",
+            "op": ">",
+            "threshold": 30.0,
+            "duration_seconds": 300
+        }))
+        .unwrap();
+        let store = WindowStore::new();
+        let eval = |device: &str, value: f64, minute: i64| {
+            condition.evaluate(&store, "hot", &temperature(device, value), minute * 60_000)
+        };
+
+        assert!(eval("a", 31.0, 0).is_none());
+        assert!(eval("b", 35.0, 1).is_none());
+        assert!(eval("a", 32.0, 4).is_none());
+        let fired = eval("a", 33.0, 5).unwrap();
+        assert_eq!(fired["held_seconds"], 300);
+        // 同一持续期间不重复触发，回落后重新计时
+        assert!(eval("a", 34.0, 6).is_none());
+        assert!(eval("a", 25.0, 7).is_none());
+        assert!(eval("a", 31.0, 8).is_none());
+        assert!(eval("b", 36.0, 6).is_some());
+    }
+
+    #[test]
+    fn test_window_and_rate_conditions() {
+        let store = WindowStore::new();
+        let sliding = WindowCondition::Sliding {
+            metric: None,
+            aggregate: Aggregate::Avg,
+            window_seconds: 600,
+            op: CompareOp::Gte,
+            threshold: 30.0,
+        };
+        assert!(sliding.evaluate(&store, "avg", &temperature("a", 20.0), 0).is_none());
+        let fired = sliding.evaluate(&store, "avg", &temperature("a", 40.0), 60_000).unwrap();
+        assert_eq!(fired["value"], 30.0);
+        assert_eq!(fired["stats"]["count"], 2);
+
+        // 10 分钟内开门 3 次
+        let door_events = WindowCondition::Tumbling {
+            metric: None,
+            aggregate: Aggregate::Count,
+            window_seconds: 600,
+            op: CompareOp::Gte,
+            threshold: 3.0,
+        };
+        let open = TriggerEvent::DeviceEvent {
+            device_id: "door".into(),
+            event_type: "open".into(),
+            data: json!({}),
+            topic: None,
+        };
+        for second in [10, 20, 30] {
+            assert!(door_events.evaluate(&store, "door", &open, second * 1000).is_none());
+        }
+        assert!(door_events.evaluate(&store, "door", &open, 601_000).is_some());
+
+        let rate = WindowCondition::RateOfChange {
+            metric: Some("temperature".into()),
+            op: CompareOp::Gt,
+            threshold: 0.5,
+        };
+        assert!(rate.evaluate(&store, "rate", &temperature("a", 20.0), 0).is_none());
+        assert!(rate.evaluate(&store, "rate", &temperature("a", 24.0), 10_000).is_none());
+        assert_eq!(rate.evaluate(&store, "rate", &temperature("a", 34.0), 20_000).unwrap()["rate"], 1.0);
+
+        let no_data = WindowCondition::NoData { timeout_seconds: 60 };
+        assert!(no_data.evaluate(&store, "silent", &temperature("a", 1.0), 0).is_none());
+        assert!(no_data.overdue(&store, "silent", 30_000).is_empty());
+        let overdue = no_data.overdue(&store, "silent", 90_000);
+        assert_eq!(overdue[0].0, "a");
+        assert_eq!(overdue[0].1["silent_seconds"], 90);
+    }
+}
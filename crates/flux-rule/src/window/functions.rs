@@ -0,0 +1,133 @@
+use super::{WindowKey, WindowStore};
+use crate::functions::{parse_duration, script_error, FnResult};
+use chrono::Utc;
+use flux_script::ScriptEngine;
+use rhai::Dynamic;
+use std::sync::Arc;
+
+/// 脚本窗口函数的作用域（所有规则共享，按设备 + 名称区分）
+pub const SCRIPT_SCOPE: &str = "$script";
+
+/// 设备最后上报时间的作用域（由触发器在收到设备消息时更新）
+pub const REPORT_SCOPE: &str = "$report";
+
+pub(crate) fn report_key(device_id: &str) -> WindowKey {
+    WindowKey::new(REPORT_SCOPE, device_id, "last")
+}
+
+/// 注册窗口函数
+///
+/// - `window_stats(device_id, name, value, "5m")`：滑动窗口，返回 `#{count, sum, avg, min, max, rate}`
+/// - `tumbling_stats(device_id, name, value, "1h")`：滚动窗口，窗口结束时返回上一窗口的统计，否则为 `()`
+/// - `sustained(device_id, name, condition, "5m")`：条件连续成立达到时长时为 true
+/// - `rate_of_change(device_id, name, value)`：与上次值相比的每秒变化率，首次为 `()`
+/// - `no_data(device_id, "10m")`：设备超过时长未上报（从未上报也为 true）
+/// - `last_report(device_id)`：最后上报的毫秒时间戳，未上报为 `()`
+pub fn register_window_functions(engine: &mut ScriptEngine, windows: Arc<WindowStore>) {
+    let engine = engine.engine_mut();
+
+    let store = windows.clone();
+    engine.register_fn(
+        "window_stats",
+        move |device_id: &str, name: &str, value: Dynamic, window: &str| -> FnResult<Dynamic> {
+            let window = parse_duration(window)?.num_milliseconds();
+            let stats = store.sliding(&script_key(device_id, name), number(&value)?, window, now_ms());
+            rhai::serde::to_dynamic(stats)
+        },
+    );
+
+    let store = windows.clone();
+    engine.register_fn(
+        "tumbling_stats",
+        move |device_id: &str, name: &str, value: Dynamic, window: &str| -> FnResult<Dynamic> {
+            let window = parse_duration(window)?.num_milliseconds();
+            match store.tumbling(&script_key(device_id, name), number(&value)?, window, now_ms()) {
+                Some(stats) => rhai::serde::to_dynamic(stats),
+                None => Ok(Dynamic::UNIT),
+            }
+        },
+    );
+
+    let store = windows.clone();
+    engine.register_fn(
+        "sustained",
+        move |device_id: &str, name: &str, condition: bool, duration: &str| -> FnResult<bool> {
+            let duration = parse_duration(duration)?.num_milliseconds();
+            Ok(store.sustained(&script_key(device_id, name), condition, duration, now_ms()).reached)
+        },
+    );
+
+    let store = windows.clone();
+    engine.register_fn(
+        "rate_of_change",
+        move |device_id: &str, name: &str, value: Dynamic| -> FnResult<Dynamic> {
+            let rate = store.rate_of_change(&script_key(device_id, name), number(&value)?, now_ms());
+            Ok(rate.map(Dynamic::from_float).unwrap_or(Dynamic::UNIT))
+        },
+    );
+
+    let store = windows.clone();
+    engine.register_fn("no_data", move |device_id: &str, duration: &str| -> FnResult<bool> {
+        let duration = parse_duration(duration)?.num_milliseconds();
+        Ok(store
+            .last_seen(&report_key(device_id))
+            .is_none_or(|last| now_ms() - last >= duration))
+    });
+
+    let store = windows;
+    engine.register_fn("last_report", move |device_id: &str| -> Dynamic {
+        store
+            .last_seen(&report_key(device_id))
+            .map(Dynamic::from_int)
+            .unwrap_or(Dynamic::UNIT)
+    });
+}
+
+fn script_key(device_id: &str, name: &str) -> WindowKey {
+    WindowKey::new(SCRIPT_SCOPE, device_id, name)
+}
+
+fn now_ms() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+fn number(value: &Dynamic) -> FnResult<f64> {
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|v| v as f64))
+        .map_err(|_| script_error(format!("Expected a number, got {}", value.type_name())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_functions() {
+        let windows = Arc::new(WindowStore::new());
+        let mut engine = ScriptEngine::new();
+        register_window_functions(&mut engine, windows.clone());
+
+        let script = r#"
+            window_stats("th_01", "temp", 20, "5m");
+            let stats = window_stats("th_01", "temp", 30.0, "5m");
+            let first_rate = rate_of_change("th_01", "level", 1.0);
+            [stats.count, stats.avg, stats.max, sustained("th_01", "hot", true, "0s"),
+             sustained("th_01", "cold", true, "1h"), first_rate, tumbling_stats("th_01", "t", 1, "1h")]
+        "#;
+        let result = engine.eval(script).unwrap().into_array().unwrap();
+        assert_eq!(result[0].as_int().unwrap(), 2);
+        assert_eq!(result[1].as_float().unwrap(), 25.0);
+        assert_eq!(result[2].as_float().unwrap(), 30.0);
+        assert!(result[3].as_bool().unwrap());
+        assert!(!result[4].as_bool().unwrap());
+        assert!(result[5].is_unit());
+        assert!(result[6].is_unit());
+
+        assert!(engine.eval(r#"no_data("th_01", "10m")"#).unwrap().as_bool().unwrap());
+        windows.touch(&report_key("th_01"), now_ms());
+        assert!(!engine.eval(r#"no_data("th_01", "10m")"#).unwrap().as_bool().unwrap());
+        assert!(engine.eval(r#"last_report("th_01")"#).unwrap().is_int());
+        assert!(engine.eval(r#"window_stats("th_01", "temp", "hot", "5m")"#).is_err());
+    }
+}
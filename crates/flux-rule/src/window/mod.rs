@@ -0,0 +1,371 @@
+pub mod condition;
+pub mod functions;
+
+pub use condition::{Aggregate, CompareOp, WindowCondition};
+pub use functions::register_window_functions;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// 单个滑动窗口保留的最大样本数
+pub const MAX_WINDOW_SAMPLES: usize = 10_000;
+
+/// 窗口状态的键：作用域（规则 ID，脚本函数为 `$script`）+ 设备 + 名称
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct WindowKey {
+    pub scope: String,
+    pub device_id: String,
+    pub name: String,
+}
+
+impl WindowKey {
+    pub fn new(scope: impl Into<String>, device_id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            scope: scope.into(),
+            device_id: device_id.into(),
+            name: name.into(),
+        }
+    }
+}
+
+/// 窗口统计结果（`rate` 为每秒样本数）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowStats {
+    pub count: u64,
+    pub sum: f64,
+    pub avg: f64,
+    pub min: f64,
+    pub max: f64,
+    pub rate: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct Aggregator {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Aggregator {
+    fn add(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+
+    fn stats(&self, window_ms: i64) -> WindowStats {
+        let avg = if self.count == 0 { 0.0 } else { self.sum / self.count as f64 };
+        let rate = if window_ms > 0 {
+            self.count as f64 * 1000.0 / window_ms as f64
+        } else {
+            0.0
+        };
+        WindowStats {
+            count: self.count,
+            sum: self.sum,
+            avg,
+            min: self.min,
+            max: self.max,
+            rate,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WindowState {
+    Sliding { samples: VecDeque<(i64, f64)> },
+    Tumbling { start_ms: i64, aggregator: Aggregator },
+    Sustained { since_ms: Option<i64>, fired: bool },
+    Last { ts_ms: i64, value: f64 },
+    Seen { last_ms: i64, alerted: bool },
+}
+
+/// 持续条件的判断结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sustained {
+    /// 条件已持续的毫秒数（条件不满足时为 0）
+    pub held_ms: i64,
+    /// 已达到要求时长
+    pub reached: bool,
+    /// 本次持续期间首次达到要求时长
+    pub first_reached: bool,
+}
+
+/// 窗口状态检查点
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WindowCheckpoint {
+    entries: Vec<(WindowKey, WindowState)>,
+}
+
+impl WindowCheckpoint {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// 规则窗口状态（按设备分键，可做检查点）
+///
+/// 所有方法显式传入当前时间（毫秒时间戳），同一个键只保存一种窗口，类型变化时重置。
+#[derive(Debug, Default)]
+pub struct WindowStore {
+    states: Mutex<HashMap<WindowKey, WindowState>>,
+}
+
+impl WindowStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 滑动窗口：加入样本并返回最近 `window_ms` 内的统计
+    pub fn sliding(&self, key: &WindowKey, value: f64, window_ms: i64, now_ms: i64) -> WindowStats {
+        self.with_state(
+            key,
+            |s| matches!(s, WindowState::Sliding { .. }),
+            || WindowState::Sliding { samples: VecDeque::new() },
+            |state| {
+                let WindowState::Sliding { samples } = state else { unreachable!() };
+                samples.push_back((now_ms, value));
+                while samples.front().is_some_and(|(ts, _)| *ts <= now_ms - window_ms) {
+                    samples.pop_front();
+                }
+                while samples.len() > MAX_WINDOW_SAMPLES {
+                    samples.pop_front();
+                }
+
+                let mut aggregator = Aggregator::default();
+                samples.iter().for_each(|(_, v)| aggregator.add(*v));
+                aggregator.stats(window_ms)
+            },
+        )
+    }
+
+    /// 滚动窗口（按 `window_ms` 对齐）：加入样本，上一个窗口结束时返回其统计
+    pub fn tumbling(&self, key: &WindowKey, value: f64, window_ms: i64, now_ms: i64) -> Option<WindowStats> {
+        let window_ms = window_ms.max(1);
+        let start = now_ms - now_ms.rem_euclid(window_ms);
+        self.with_state(
+            key,
+            |s| matches!(s, WindowState::Tumbling { .. }),
+            || WindowState::Tumbling {
+                start_ms: start,
+                aggregator: Aggregator::default(),
+            },
+            |state| {
+                let WindowState::Tumbling { start_ms, aggregator } = state else { unreachable!() };
+                let closed = (*start_ms != start && aggregator.count > 0).then(|| aggregator.stats(window_ms));
+                if *start_ms != start {
+                    *start_ms = start;
+                    *aggregator = Aggregator::default();
+                }
+                aggregator.add(value);
+                closed
+            },
+        )
+    }
+
+    /// 持续条件：`condition` 连续成立达到 `duration_ms`
+    pub fn sustained(&self, key: &WindowKey, condition: bool, duration_ms: i64, now_ms: i64) -> Sustained {
+        self.with_state(
+            key,
+            |s| matches!(s, WindowState::Sustained { .. }),
+            || WindowState::Sustained {
+                since_ms: None,
+                fired: false,
+            },
+            |state| {
+                let WindowState::Sustained { since_ms, fired } = state else { unreachable!() };
+                if !condition {
+                    *since_ms = None;
+                    *fired = false;
+                    return Sustained {
+                        held_ms: 0,
+                        reached: false,
+                        first_reached: false,
+                    };
+                }
+
+                let held_ms = now_ms - *since_ms.get_or_insert(now_ms);
+                let reached = held_ms >= duration_ms;
+                let first_reached = reached && !*fired;
+                *fired |= reached;
+                Sustained {
+                    held_ms,
+                    reached,
+                    first_reached,
+                }
+            },
+        )
+    }
+
+    /// 变化率（每秒），首个样本或时间未前进时为 None
+    pub fn rate_of_change(&self, key: &WindowKey, value: f64, now_ms: i64) -> Option<f64> {
+        let mut states = self.states.lock().expect("window store lock poisoned");
+        let previous = match states.get(key) {
+            Some(WindowState::Last { ts_ms, value }) => Some((*ts_ms, *value)),
+            _ => None,
+        };
+        states.insert(key.clone(), WindowState::Last { ts_ms: now_ms, value });
+
+        let (ts_ms, last) = previous?;
+        (now_ms > ts_ms).then(|| (value - last) * 1000.0 / (now_ms - ts_ms) as f64)
+    }
+
+    /// 记录设备上报
+    pub fn touch(&self, key: &WindowKey, now_ms: i64) {
+        let mut states = self.states.lock().expect("window store lock poisoned");
+        states.insert(
+            key.clone(),
+            WindowState::Seen {
+                last_ms: now_ms,
+                alerted: false,
+            },
+        );
+    }
+
+    /// 最后上报时间
+    pub fn last_seen(&self, key: &WindowKey) -> Option<i64> {
+        let states = self.states.lock().expect("window store lock poisoned");
+        match states.get(key) {
+            Some(WindowState::Seen { last_ms, .. }) => Some(*last_ms),
+            _ => None,
+        }
+    }
+
+    /// 作用域内超过 `timeout_ms` 未上报、且尚未告警的键（返回后标记为已告警，再次上报后重置）
+    pub fn take_overdue(&self, scope: &str, timeout_ms: i64, now_ms: i64) -> Vec<(WindowKey, i64)> {
+        let mut states = self.states.lock().expect("window store lock poisoned");
+        let mut overdue: Vec<_> = states
+            .iter_mut()
+            .filter(|(key, _)| key.scope == scope)
+            .filter_map(|(key, state)| match state {
+                WindowState::Seen { last_ms, alerted } if !*alerted && now_ms - *last_ms >= timeout_ms => {
+                    *alerted = true;
+                    Some((key.clone(), *last_ms))
+                }
+                _ => None,
+            })
+            .collect();
+        overdue.sort();
+        overdue
+    }
+
+    /// 删除作用域内的全部状态
+    pub fn clear_scope(&self, scope: &str) {
+        let mut states = self.states.lock().expect("window store lock poisoned");
+        states.retain(|key, _| key.scope != scope);
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.lock().expect("window store lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn checkpoint(&self) -> WindowCheckpoint {
+        let states = self.states.lock().expect("window store lock poisoned");
+        let mut entries: Vec<_> = states.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        WindowCheckpoint { entries }
+    }
+
+    /// 从检查点恢复（替换当前全部状态）
+    pub fn restore(&self, checkpoint: WindowCheckpoint) {
+        let mut states = self.states.lock().expect("window store lock poisoned");
+        *states = checkpoint.entries.into_iter().collect();
+    }
+
+    fn with_state<T>(
+        &self,
+        key: &WindowKey,
+        is_kind: impl Fn(&WindowState) -> bool,
+        init: impl FnOnce() -> WindowState,
+        update: impl FnOnce(&mut WindowState) -> T,
+    ) -> T {
+        let mut states = self.states.lock().expect("window store lock poisoned");
+        let state = match states.get_mut(key) {
+            Some(state) if is_kind(state) => state,
+            _ => {
+                states.insert(key.clone(), init());
+                states.get_mut(key).expect("window state just inserted")
+            }
+        };
+        update(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: i64 = 60_000;
+
+    #[test]
+    fn test_window_primitives() {
+        let store = WindowStore::new();
+        let key = WindowKey::new("script", "th_01", "temperature");
+
+        store.sliding(&key, 20.0, 5 * MIN, 0);
+        store.sliding(&key, 30.0, 5 * MIN, 2 * MIN);
+        let stats = store.sliding(&key, 40.0, 5 * MIN, 6 * MIN);
+        assert_eq!((stats.count, stats.avg, stats.min, stats.max), (2, 35.0, 30.0, 40.0));
+        assert_eq!(stats.rate, 2.0 / 300.0);
+
+        let key = WindowKey::new("script", "th_01", "hourly");
+        assert_eq!(store.tumbling(&key, 1.0, 60 * MIN, 10 * MIN), None);
+        assert_eq!(store.tumbling(&key, 3.0, 60 * MIN, 50 * MIN), None);
+        let closed = store.tumbling(&key, 7.0, 60 * MIN, 61 * MIN).unwrap();
+        assert_eq!((closed.count, closed.sum), (2, 4.0));
+
+        let key = WindowKey::new("rule_1", "th_01", "hot");
+        assert!(!store.sustained(&key, true, 5 * MIN, 0).reached);
+        let hit = store.sustained(&key, true, 5 * MIN, 5 * MIN);
+        assert!(hit.reached && hit.first_reached);
+        let again = store.sustained(&key, true, 5 * MIN, 6 * MIN);
+        assert!(again.reached && !again.first_reached);
+        assert_eq!(store.sustained(&key, false, 5 * MIN, 7 * MIN).held_ms, 0);
+        assert!(!store.sustained(&key, true, 5 * MIN, 8 * MIN).reached);
+
+        let key = WindowKey::new("script", "th_01", "level");
+        assert_eq!(store.rate_of_change(&key, 10.0, 0), None);
+        assert_eq!(store.rate_of_change(&key, 16.0, 2_000), Some(3.0));
+    }
+
+    #[test]
+    fn test_overdue_and_checkpoint() {
+        let store = WindowStore::new();
+        store.touch(&WindowKey::new("rule_1", "a", "seen"), 0);
+        store.touch(&WindowKey::new("rule_1", "b", "seen"), 8 * MIN);
+        store.touch(&WindowKey::new("rule_2", "a", "seen"), 0);
+
+        let overdue = store.take_overdue("rule_1", 10 * MIN, 12 * MIN);
+        assert_eq!(overdue, vec![(WindowKey::new("rule_1", "a", "seen"), 0)]);
+        // 已告警的设备在再次上报前不重复返回
+        assert!(store.take_overdue("rule_1", 10 * MIN, 13 * MIN).is_empty());
+
+        store.sustained(&WindowKey::new("rule_1", "a", "hot"), true, MIN, 0);
+        let json = serde_json::to_value(store.checkpoint()).unwrap();
+        let restored = WindowStore::new();
+        restored.restore(serde_json::from_value(json).unwrap());
+        assert_eq!(restored.len(), 4);
+        assert!(restored.sustained(&WindowKey::new("rule_1", "a", "hot"), true, MIN, MIN).first_reached);
+        assert_eq!(restored.take_overdue("rule_1", 10 * MIN, 18 * MIN).len(), 1);
+
+        restored.clear_scope("rule_1");
+        assert_eq!(restored.len(), 1);
+    }
+}
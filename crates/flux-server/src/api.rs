@@ -11,6 +11,7 @@ use axum::{
 use config::{Config, File, FileFormat};
 use chrono::Utc;
 use flux_core::entity::events;
+use flux_server::require_admin_auth;
 use flux_types::message::Message;
 use serde::Deserialize;
 use serde_json::Value;
@@ -1110,35 +1111,6 @@ pub async fn gb_list_device_channels(
     }
 }
 
-fn require_admin_auth(headers: &HeaderMap) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
-    let token = match std::env::var("FLUX_ADMIN_TOKEN") {
-        Ok(v) if !v.is_empty() => v,
-        _ => {
-            return Err((
-                StatusCode::SERVICE_UNAVAILABLE,
-                Json(serde_json::json!({
-                    "error": "FLUX_ADMIN_TOKEN is not configured"
-                })),
-            ))
-        }
-    };
-
-    let auth = headers
-        .get(header::AUTHORIZATION)
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
-
-    let expected = format!("Bearer {}", token);
-    if auth != expected {
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            Json(serde_json::json!({ "error": "unauthorized" })),
-        ));
-    }
-
-    Ok(())
-}
-
 async fn ensure_app_config_audit_table(db: &sea_orm::DatabaseConnection) -> anyhow::Result<()> {
     let backend = db.get_database_backend();
 
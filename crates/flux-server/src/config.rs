@@ -15,6 +15,8 @@ pub struct AppConfig {
     pub logging: LoggingConfig,
     #[serde(default)]
     pub gb28181: Gb28181Config,
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
 }
 
 #[derive(Debug, Deserialize, Clone, Default)]
@@ -129,6 +131,66 @@ pub struct DatabaseConfig {
 #[derive(Debug, Deserialize, Clone)]
 pub struct PluginConfig {
     pub directory: String,
+    /// 监听插件目录，插件包变化时自动重新加载
+    #[serde(default = "default_plugin_watch")]
+    pub watch: bool,
+    /// 单次调用的 fuel 上限（约等于执行的 Wasm 指令数）
+    #[serde(default = "default_plugin_fuel_per_call")]
+    pub fuel_per_call: u64,
+    /// 单次调用超时（毫秒）
+    #[serde(default = "default_plugin_call_timeout_ms")]
+    pub call_timeout_ms: u64,
+    /// 连续 trap 达到该次数后隔离插件（0 表示不隔离）
+    #[serde(default = "default_plugin_max_consecutive_traps")]
+    pub max_consecutive_traps: u32,
+    /// 隔离时长（秒）
+    #[serde(default = "default_plugin_quarantine_secs")]
+    pub quarantine_secs: u64,
+}
+
+/// 规则引擎前的插件处理链
+///
+/// ```toml
+/// [[pipeline.stages]]
+/// plugin = "decoder"
+/// topics = ["sensors/#"]
+/// on_error = "dead_letter"
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct PipelineConfig {
+    /// 按顺序执行的处理阶段
+    #[serde(default)]
+    pub stages: Vec<PipelineStageConfig>,
+    /// 死信主题（阶段失败且策略为 dead_letter 时发布）
+    #[serde(default = "default_dead_letter_topic")]
+    pub dead_letter_topic: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PipelineStageConfig {
+    /// 已加载的插件 ID
+    pub plugin: String,
+    /// 插件导出函数
+    #[serde(default = "default_stage_function")]
+    pub function: String,
+    /// 主题过滤（MQTT 通配符），为空时匹配所有主题
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// 插件失败时的处理方式
+    #[serde(default)]
+    pub on_error: StageErrorPolicy,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StageErrorPolicy {
+    /// 跳过该阶段，原消息继续向后传递
+    #[default]
+    Skip,
+    /// 丢弃消息
+    Drop,
+    /// 丢弃消息并发布到死信主题
+    DeadLetter,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -172,6 +234,26 @@ pub struct LoggingConfig {
 }
 
 // 默认值函数
+fn default_plugin_watch() -> bool {
+    true
+}
+
+fn default_plugin_fuel_per_call() -> u64 {
+    1_000_000_000
+}
+
+fn default_plugin_call_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_plugin_max_consecutive_traps() -> u32 {
+    5
+}
+
+fn default_plugin_quarantine_secs() -> u64 {
+    60
+}
+
 fn default_eventbus_capacity() -> usize {
     1024
 }
@@ -188,7 +270,24 @@ fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_dead_letter_topic() -> String {
+    "$dead_letter/pipeline".to_string()
+}
+
+fn default_stage_function() -> String {
+    "on_msg".to_string()
+}
+
 // Default trait 实现
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            stages: Vec::new(),
+            dead_letter_topic: default_dead_letter_topic(),
+        }
+    }
+}
+
 impl Default for EventBusConfig {
     fn default() -> Self {
         Self {
@@ -231,11 +330,17 @@ impl Default for AppConfig {
             },
             plugins: PluginConfig {
                 directory: "plugins".to_string(),
+                watch: true,
+                fuel_per_call: default_plugin_fuel_per_call(),
+                call_timeout_ms: default_plugin_call_timeout_ms(),
+                max_consecutive_traps: default_plugin_max_consecutive_traps(),
+                quarantine_secs: default_plugin_quarantine_secs(),
             },
             eventbus: EventBusConfig::default(),
             mqtt: MqttConfig::default(),
             logging: LoggingConfig::default(),
             gb28181: Gb28181Config::default(),
+            pipeline: PipelineConfig::default(),
         }
     }
 }
@@ -3,7 +3,11 @@ pub mod config;
 pub mod config_provider;
 pub mod config_manager;
 pub mod gb28181_backend;
+pub mod pipeline;
+pub mod plugins;
 
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::Json;
 use flux_core::bus::EventBus;
 use flux_plugin::PluginManager;
 use flux_script::ScriptEngine;
@@ -30,6 +34,36 @@ pub struct AppState {
     pub gb28181_backend: Option<Gb28181BackendRef>,
 }
 
+/// 校验管理员令牌（`Authorization: Bearer $FLUX_ADMIN_TOKEN`）
+pub fn require_admin_auth(headers: &HeaderMap) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let token = match std::env::var("FLUX_ADMIN_TOKEN") {
+        Ok(v) if !v.is_empty() => v,
+        _ => {
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "error": "FLUX_ADMIN_TOKEN is not configured"
+                })),
+            ))
+        }
+    };
+
+    let auth = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    let expected = format!("Bearer {}", token);
+    if auth != expected {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({ "error": "unauthorized" })),
+        ));
+    }
+
+    Ok(())
+}
+
 // 为了测试，重新导出 api 模块的关键类型和函数
 // 注意：这里需要包含完整的 api 模块代码，因为测试需要访问 create_router
 pub mod api {
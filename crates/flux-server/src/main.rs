@@ -5,7 +5,8 @@ use std::sync::Arc; // Entities
 
 // Import our core crates
 use flux_core::bus::EventBus;
-use flux_plugin::PluginManager;
+use flux_device::DeviceRegistry;
+use flux_plugin::{PluginDirectory, PluginManager, QuarantinePolicy, WasmResourceLimits};
 use flux_script::ScriptEngine;
 use flux_video::gb28181::sip::SipServer;
 
@@ -110,7 +111,22 @@ async fn main() -> anyhow::Result<()> {
 
     // 2. Initialize Core Components
     let event_bus = Arc::new(EventBus::new(app_config.eventbus.capacity));
-    let plugin_manager = Arc::new(PluginManager::new()?);
+    let device_registry = Arc::new(DeviceRegistry::new(Arc::new(db.clone())));
+    let plugin_limits = WasmResourceLimits {
+        fuel_per_call: app_config.plugins.fuel_per_call,
+        call_timeout: std::time::Duration::from_millis(app_config.plugins.call_timeout_ms),
+        ..Default::default()
+    };
+    let quarantine = QuarantinePolicy {
+        max_consecutive_traps: app_config.plugins.max_consecutive_traps,
+        duration: std::time::Duration::from_secs(app_config.plugins.quarantine_secs),
+    };
+    let plugin_manager = Arc::new(
+        PluginManager::with_limits(flux_plugin::manager::DEFAULT_POOL_SIZE, plugin_limits)?
+            .with_quarantine(quarantine)
+            .with_event_bus(event_bus.clone())
+            .with_device_lookup(device_registry),
+    );
     let script_engine = Arc::new(ScriptEngine::new());
 
     // 2.1 Initialize StorageManager (multi-pool from ./config)
@@ -200,36 +216,25 @@ async fn main() -> anyhow::Result<()> {
         rule.insert(&db).await?;
     }
 
-    // Load Plugins
-    // TODO: move to a proper loader service
+    // Load Plugins（目录包或单文件 + 同名清单，见 flux_plugin::directory）
     let plugin_dir = &app_config.plugins.directory;
     tracing::info!("Loading plugins from: {}", plugin_dir);
-
-    if let Ok(entries) = std::fs::read_dir(plugin_dir) {
-        for entry in entries.filter_map(Result::ok) {
-            let path = entry.path();
-            if path.extension().is_some_and(|ext| ext == "wasm") {
-                tracing::info!("Found plugin: {:?}", path);
-                if let Ok(bytes) = std::fs::read(&path) {
-                    let filename = match path.file_stem() {
-                        Some(name) => name.to_string_lossy(),
-                        None => {
-                            tracing::warn!("Invalid plugin filename: {:?}", path);
-                            continue;
-                        }
-                    };
-                    // Load the plugin
-                    if let Err(e) = plugin_manager.load_plugin(&filename, &bytes) {
-                        tracing::error!("Failed to load plugin {}: {:?}", filename, e);
-                    } else {
-                        tracing::info!("Successfully loaded plugin: {}", filename);
-                    }
-                }
+    let plugin_directory = Arc::new(PluginDirectory::new(plugin_dir, plugin_manager.clone()));
+    if let Err(e) = plugin_directory.sync() {
+        tracing::warn!("{:#}", e);
+    }
+    // 句柄需保持到进程退出
+    let _plugin_watcher = if app_config.plugins.watch && plugin_directory.path().is_dir() {
+        match plugin_directory.watch() {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                tracing::warn!("Failed to watch plugin directory {}: {}", plugin_dir, e);
+                None
             }
         }
     } else {
-        tracing::warn!("Plugin directory not found: {}", plugin_dir);
-    }
+        None
+    };
 
     // Prepare optional GB28181 backend (embedded or remote)
     let (gb28181_sip, gb28181_backend): (Option<Arc<SipServer>>, Option<Gb28181BackendRef>) =
@@ -280,7 +285,8 @@ async fn main() -> anyhow::Result<()> {
     metrics::set_database_connections(1);
 
     // 4. Start API Server (Axum)
-    let app = api::create_router(state.clone());
+    let app = api::create_router(state.clone())
+        .merge(flux_server::plugins::router(plugin_directory.clone()));
 
     // 4.1 Start GB28181 SIP Server (embedded only)
     if let Some(sip) = gb28181_sip {
@@ -68,6 +68,14 @@ fn describe_metrics() {
         "flux_plugin_pool_available",
         "Available instances in plugin pool"
     );
+    describe_counter!(
+        "flux_plugin_traps_total",
+        "Plugin traps (labeled by kind: fuel, timeout, trap)"
+    );
+    describe_gauge!(
+        "flux_plugin_quarantined",
+        "Whether the plugin is quarantined after repeated traps (1 quarantined)"
+    );
 
     // HTTP API 相关指标
     describe_counter!("flux_http_requests_total", "Total number of HTTP requests");
@@ -179,45 +187,6 @@ pub fn set_active_rules(count: usize) {
     gauge!("flux_rules_active", count as f64);
 }
 
-/// 记录插件调用
-pub fn record_plugin_call() {
-    counter!("flux_plugin_calls_total", 1);
-}
-
-/// 记录插件失败
-pub fn record_plugin_failure() {
-    counter!("flux_plugin_failures_total", 1);
-}
-
-/// 记录插件执行时间
-pub fn record_plugin_duration(duration_secs: f64) {
-    histogram!("flux_plugin_duration_seconds", duration_secs);
-}
-
-/// 设置已加载插件数量
-#[allow(dead_code)]
-pub fn set_loaded_plugins(count: usize) {
-    gauge!("flux_plugins_loaded", count as f64);
-}
-
-/// 记录插件池命中（复用实例）
-#[allow(dead_code)]
-pub fn record_plugin_pool_hit() {
-    counter!("flux_plugin_pool_hits_total", 1);
-}
-
-/// 记录插件池未命中（创建新实例）
-#[allow(dead_code)]
-pub fn record_plugin_pool_miss() {
-    counter!("flux_plugin_pool_misses_total", 1);
-}
-
-/// 设置插件池可用实例数
-#[allow(dead_code)]
-pub fn set_plugin_pool_available(plugin_id: &str, count: usize) {
-    gauge!("flux_plugin_pool_available", count as f64, "plugin" => plugin_id.to_string());
-}
-
 /// 记录 HTTP 请求
 pub fn record_http_request() {
     counter!("flux_http_requests_total", 1);
@@ -0,0 +1,187 @@
+//! 插件处理链
+//!
+//! 规则引擎执行前，消息按配置顺序经过各插件阶段。每个阶段只处理主题匹配的消息，
+//! 插件输出决定消息去向：
+//! - 无输出：消息原样传递
+//! - `null`：丢弃消息
+//! - 对象 `{"topic"?, "payload"?}`：改写消息，缺省字段沿用输入
+//! - 数组：拆分为多条消息，后续阶段对每条分别处理（空数组等同丢弃）
+//!
+//! 插件通过 ABI v1 返回数据（见 `flux_types::plugin`），旧 ABI 插件只有状态码，成功时消息原样通过。
+
+use crate::config::{PipelineConfig, PipelineStageConfig, StageErrorPolicy};
+use anyhow::{anyhow, bail, Result};
+use flux_mqtt::topic_matcher::TopicMatcher;
+use flux_plugin::PluginManager;
+use flux_types::message::Message;
+use serde_json::Value;
+
+/// 单个阶段最多拆分出的消息数
+pub const MAX_FANOUT: usize = 256;
+
+/// 插件调用抽象（便于替换实现和统计）
+pub trait PluginInvoker {
+    /// 调用插件，返回 `None` 表示消息不变
+    fn invoke(&self, plugin: &str, function: &str, input: &str) -> Result<Option<Value>>;
+}
+
+impl PluginInvoker for PluginManager {
+    fn invoke(&self, plugin: &str, function: &str, input: &str) -> Result<Option<Value>> {
+        self.call_plugin_json(plugin, function, input)
+    }
+}
+
+/// 处理结果
+#[derive(Debug, Default)]
+pub struct PipelineOutcome {
+    /// 交给规则引擎的消息
+    pub messages: Vec<Message>,
+    /// 需要发布到死信主题的消息
+    pub dead_letters: Vec<Message>,
+    /// 被插件或错误策略丢弃的消息数
+    pub dropped: usize,
+}
+
+pub struct Pipeline {
+    stages: Vec<PipelineStageConfig>,
+    dead_letter_topic: String,
+}
+
+impl Pipeline {
+    pub fn new(config: &PipelineConfig) -> Self {
+        Self {
+            stages: config.stages.clone(),
+            dead_letter_topic: config.dead_letter_topic.clone(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// 依次执行各阶段
+    pub fn process(&self, msg: Message, invoker: &dyn PluginInvoker) -> PipelineOutcome {
+        let mut outcome = PipelineOutcome::default();
+        // 死信不再进入处理链，避免循环
+        if msg.topic.starts_with(&self.dead_letter_topic) {
+            outcome.messages.push(msg);
+            return outcome;
+        }
+
+        let mut current = vec![msg];
+        for (index, stage) in self.stages.iter().enumerate() {
+            let mut next = Vec::with_capacity(current.len());
+            for msg in current {
+                if !stage_matches(stage, &msg.topic) {
+                    next.push(msg);
+                    continue;
+                }
+                match run_stage(stage, &msg, invoker) {
+                    Ok(Some(outputs)) => {
+                        if outputs.is_empty() {
+                            outcome.dropped += 1;
+                        }
+                        next.extend(outputs);
+                    }
+                    Ok(None) => next.push(msg),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Pipeline stage {} ('{}::{}') failed for message {}: {}",
+                            index,
+                            stage.plugin,
+                            stage.function,
+                            msg.id,
+                            e
+                        );
+                        match stage.on_error {
+                            StageErrorPolicy::Skip => next.push(msg),
+                            StageErrorPolicy::Drop => outcome.dropped += 1,
+                            StageErrorPolicy::DeadLetter => {
+                                outcome.dropped += 1;
+                                outcome.dead_letters.push(self.dead_letter(index, stage, &msg, &e));
+                            }
+                        }
+                    }
+                }
+            }
+            current = next;
+        }
+
+        outcome.messages = current;
+        outcome
+    }
+
+    fn dead_letter(&self, index: usize, stage: &PipelineStageConfig, msg: &Message, error: &anyhow::Error) -> Message {
+        Message::new(
+            self.dead_letter_topic.clone(),
+            serde_json::json!({
+                "stage": index,
+                "plugin": stage.plugin,
+                "function": stage.function,
+                "error": error.to_string(),
+                "message": msg,
+            }),
+        )
+    }
+}
+
+fn stage_matches(stage: &PipelineStageConfig, topic: &str) -> bool {
+    stage.topics.is_empty() || stage.topics.iter().any(|filter| TopicMatcher::matches(filter, topic))
+}
+
+/// 执行单个阶段；`None` 表示消息不变
+fn run_stage(stage: &PipelineStageConfig, msg: &Message, invoker: &dyn PluginInvoker) -> Result<Option<Vec<Message>>> {
+    let input = serde_json::to_string(msg)?;
+    match invoker.invoke(&stage.plugin, &stage.function, &input)? {
+        None => Ok(None),
+        Some(output) => parse_output(msg, output).map(Some),
+    }
+}
+
+/// 将插件输出解析为消息列表
+pub fn parse_output(input: &Message, output: Value) -> Result<Vec<Message>> {
+    match output {
+        Value::Null => Ok(Vec::new()),
+        Value::Object(_) => Ok(vec![derive_message(input, output, true)?]),
+        Value::Array(items) => {
+            if items.len() > MAX_FANOUT {
+                bail!("Plugin fanned out {} messages (max {})", items.len(), MAX_FANOUT);
+            }
+            items
+                .into_iter()
+                .map(|item| derive_message(input, item, false))
+                .collect()
+        }
+        other => Err(anyhow!("Unexpected plugin output: {}", other)),
+    }
+}
+
+/// 由插件输出构造消息
+///
+/// 输出带有效（非空）ID 和时间戳时沿用；否则改写保留原消息 ID，拆分出的消息使用新 ID，时间戳沿用输入
+fn derive_message(input: &Message, output: Value, keep_id: bool) -> Result<Message> {
+    let Value::Object(mut fields) = output else {
+        bail!("Plugin output message must be an object");
+    };
+    let topic = match fields.remove("topic") {
+        Some(Value::String(topic)) => topic,
+        Some(other) => bail!("Plugin output topic must be a string, got {}", other),
+        None => input.topic.clone(),
+    };
+    let payload = fields.remove("payload").unwrap_or_else(|| input.payload.clone());
+    let id = fields
+        .get("id")
+        .and_then(Value::as_str)
+        .and_then(|id| id.parse::<uuid::Uuid>().ok())
+        .filter(|id| !id.is_nil());
+    let timestamp = fields.get("timestamp").and_then(Value::as_i64).filter(|ts| *ts > 0);
+
+    let mut msg = Message::new(topic, payload);
+    match id {
+        Some(id) => msg.id = id,
+        None if keep_id => msg.id = input.id,
+        None => {}
+    }
+    msg.timestamp = timestamp.unwrap_or(input.timestamp);
+    Ok(msg)
+}
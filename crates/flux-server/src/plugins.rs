@@ -0,0 +1,154 @@
+//! 插件管理 API
+//!
+//! - `GET /api/v1/plugins`：已加载插件列表
+//! - `GET /api/v1/plugins/:id`：插件信息
+//! - `PUT /api/v1/plugins/:id`：上传插件包 `{"manifest": {...}, "wasm": "<base64>"}`，已存在时热更新
+//! - `DELETE /api/v1/plugins/:id`：卸载并删除插件包
+//! - `POST /api/v1/plugins/:id/enable`、`POST /api/v1/plugins/:id/disable`：启用/停用（不持久化，重启后恢复启用）
+//!
+//! 除查询外均需管理员令牌。
+
+use crate::require_admin_auth;
+use axum::{
+    extract::{DefaultBodyLimit, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::Engine;
+use flux_plugin::{PluginDirectory, PluginManifest};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// 上传请求体大小上限（base64 编码后）
+pub const MAX_UPLOAD_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct UploadPluginRequest {
+    #[serde(default)]
+    pub manifest: PluginManifest,
+    /// base64 编码的 Wasm 模块
+    pub wasm: String,
+}
+
+pub fn router<S>(directory: Arc<PluginDirectory>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/api/v1/plugins", get(list_plugins))
+        .route(
+            "/api/v1/plugins/:id",
+            get(get_plugin)
+                .put(upload_plugin)
+                .delete(delete_plugin)
+                .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route("/api/v1/plugins/:id/enable", post(enable_plugin))
+        .route("/api/v1/plugins/:id/disable", post(disable_plugin))
+        .with_state(directory)
+}
+
+fn error(status: StatusCode, message: impl std::fmt::Display) -> Response {
+    (status, Json(serde_json::json!({ "error": message.to_string() }))).into_response()
+}
+
+async fn list_plugins(State(directory): State<Arc<PluginDirectory>>) -> Response {
+    match directory.manager().list_plugins() {
+        Ok(plugins) => Json(plugins).into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+async fn get_plugin(State(directory): State<Arc<PluginDirectory>>, Path(id): Path<String>) -> Response {
+    match directory.manager().plugin_info(&id) {
+        Ok(info) => Json(info).into_response(),
+        Err(e) => error(StatusCode::NOT_FOUND, e),
+    }
+}
+
+async fn upload_plugin(
+    State(directory): State<Arc<PluginDirectory>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<UploadPluginRequest>,
+) -> Response {
+    if let Err(e) = require_admin_auth(&headers) {
+        return e.into_response();
+    }
+
+    let mut manifest = req.manifest;
+    if manifest.id.is_empty() {
+        manifest.id = id.clone();
+    } else if manifest.id != id {
+        return error(
+            StatusCode::BAD_REQUEST,
+            format!("Manifest id '{}' does not match path id '{}'", manifest.id, id),
+        );
+    }
+    let wasm = match base64::engine::general_purpose::STANDARD.decode(req.wasm.trim()) {
+        Ok(wasm) => wasm,
+        Err(e) => return error(StatusCode::BAD_REQUEST, format!("Invalid base64 wasm: {}", e)),
+    };
+
+    // 编译 Wasm 较耗时，放到阻塞线程池
+    let install_dir = directory.clone();
+    let result = tokio::task::spawn_blocking(move || install_dir.install(manifest, &wasm)).await;
+    match result {
+        Ok(Ok(())) => match directory.manager().plugin_info(&id) {
+            Ok(info) => {
+                tracing::info!("Plugin '{}' v{} installed via API", id, info.version);
+                Json(info).into_response()
+            }
+            Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+        },
+        Ok(Err(e)) => error(StatusCode::BAD_REQUEST, format!("{:#}", e)),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+async fn delete_plugin(
+    State(directory): State<Arc<PluginDirectory>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(e) = require_admin_auth(&headers) {
+        return e.into_response();
+    }
+    match directory.remove(&id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => error(StatusCode::NOT_FOUND, format!("Plugin not found: {}", id)),
+        Err(e) => error(StatusCode::BAD_REQUEST, format!("{:#}", e)),
+    }
+}
+
+async fn enable_plugin(
+    State(directory): State<Arc<PluginDirectory>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    set_enabled(&directory, &id, &headers, true)
+}
+
+async fn disable_plugin(
+    State(directory): State<Arc<PluginDirectory>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    set_enabled(&directory, &id, &headers, false)
+}
+
+fn set_enabled(directory: &PluginDirectory, id: &str, headers: &HeaderMap, enabled: bool) -> Response {
+    if let Err(e) = require_admin_auth(headers) {
+        return e.into_response();
+    }
+    let manager = directory.manager();
+    match manager.set_enabled(id, enabled).and_then(|_| manager.plugin_info(id)) {
+        Ok(info) => {
+            tracing::info!("Plugin '{}' {}", id, if enabled { "enabled" } else { "disabled" });
+            Json(info).into_response()
+        }
+        Err(e) => error(StatusCode::NOT_FOUND, e),
+    }
+}
@@ -1,4 +1,6 @@
 use crate::{metrics, AppState};
+use flux_server::pipeline::Pipeline;
+use flux_types::message::Message;
 use std::sync::Arc;
 
 pub async fn start_rule_worker(state: Arc<AppState>) {
@@ -27,6 +29,12 @@ pub async fn start_rule_worker(state: Arc<AppState>) {
     // Subscribe to EventBus
     let mut rx = state.event_bus.subscribe();
 
+    // 插件处理链随配置热更新
+    let mut config_rx = state.config.clone();
+    let mut pipeline = Pipeline::new(&config_rx.borrow_and_update().pipeline);
+    // 插件调用指标由 PluginManager 按插件记录
+    let invoker = &*state.plugin_manager;
+
     loop {
         match rx.recv().await {
             Ok(msg) => {
@@ -34,75 +42,22 @@ pub async fn start_rule_worker(state: Arc<AppState>) {
                 metrics::record_event_received();
                 tracing::debug!("Worker received message: {}", msg.id);
 
-                // 🔥 阶段 1: 插件预处理
-                // 将消息序列化为 JSON 传递给插件
-                let msg_json = match serde_json::to_string(&msg) {
-                    Ok(json) => json,
-                    Err(e) => {
-                        tracing::error!("Failed to serialize message: {}", e);
-                        continue;
-                    }
-                };
-
-                // 调用所有已加载的插件进行预处理
-                // 注意：这里简化处理，实际可以配置每个规则使用哪些插件
-                tracing::debug!("Calling plugins for message preprocessing");
+                if config_rx.has_changed().unwrap_or(false) {
+                    pipeline = Pipeline::new(&config_rx.borrow_and_update().pipeline);
+                    tracing::info!("Plugin pipeline reloaded");
+                }
 
-                // 示例：调用 dummy_plugin 的 on_msg 函数
-                // 返回值是处理后的消息长度（示例插件的简单逻辑）
-                let plugin_start = std::time::Instant::now();
-                match state
-                    .plugin_manager
-                    .call_plugin("dummy_plugin", "on_msg", &msg_json)
-                {
-                    Ok(result) => {
-                        metrics::record_plugin_call();
-                        metrics::record_plugin_duration(plugin_start.elapsed().as_secs_f64());
-                        tracing::info!(
-                            "Plugin 'dummy_plugin' processed message, result: {}",
-                            result
-                        );
-                        // 实际应用中，插件可能返回修改后的 JSON，这里简化处理
-                    }
-                    Err(e) => {
-                        // 插件失败不应该阻止规则执行
-                        metrics::record_plugin_failure();
-                        tracing::warn!(
-                            "Plugin 'dummy_plugin' failed: {}, continuing with original message",
-                            e
-                        );
+                // 🔥 阶段 1: 插件处理链（改写、补充、丢弃或拆分消息）
+                let outcome = pipeline.process(msg, invoker);
+                for dead_letter in outcome.dead_letters {
+                    if let Err(e) = state.event_bus.publish(dead_letter) {
+                        tracing::warn!("Failed to publish dead letter: {}", e);
                     }
                 }
 
                 // 🔥 阶段 2: 规则引擎执行
-                // 注意：这里使用原始消息，实际应用中应该使用插件处理后的消息
-                let script_ids = state.script_engine.get_script_ids();
-                for script_id in script_ids {
-                    metrics::record_rule_executed();
-
-                    match state.script_engine.eval_message(&script_id, &msg) {
-                        Ok(triggered) => {
-                            if triggered {
-                                metrics::record_rule_triggered();
-                                tracing::warn!(
-                                    "!!! RULE TRIGGERED: {} (msg {}) !!!",
-                                    script_id,
-                                    msg.id
-                                );
-
-                                // 🔥 阶段 3: 规则触发后的动作插件（可选）
-                                // 这里可以调用动作插件，例如发送通知、控制设备等
-                                tracing::info!(
-                                    "Rule '{}' triggered, executing actions...",
-                                    script_id
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            metrics::record_rule_failed();
-                            tracing::error!("Failed to execute rule {}: {}", script_id, e);
-                        }
-                    }
+                for msg in &outcome.messages {
+                    evaluate_rules(&state, msg);
                 }
             }
             Err(e) => {
@@ -114,3 +69,27 @@ pub async fn start_rule_worker(state: Arc<AppState>) {
         }
     }
 }
+
+fn evaluate_rules(state: &AppState, msg: &Message) {
+    let script_ids = state.script_engine.get_script_ids();
+    for script_id in script_ids {
+        metrics::record_rule_executed();
+
+        match state.script_engine.eval_message(&script_id, msg) {
+            Ok(triggered) => {
+                if triggered {
+                    metrics::record_rule_triggered();
+                    tracing::warn!("!!! RULE TRIGGERED: {} (msg {}) !!!", script_id, msg.id);
+
+                    // 🔥 阶段 3: 规则触发后的动作插件（可选）
+                    // 这里可以调用动作插件，例如发送通知、控制设备等
+                    tracing::info!("Rule '{}' triggered, executing actions...", script_id);
+                }
+            }
+            Err(e) => {
+                metrics::record_rule_failed();
+                tracing::error!("Failed to execute rule {}: {}", script_id, e);
+            }
+        }
+    }
+}
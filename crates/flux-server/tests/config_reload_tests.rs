@@ -215,6 +215,11 @@ fn test_gb28181_auth_config_mapping() {
         },
         plugins: PluginConfig {
             directory: "plugins".to_string(),
+            watch: true,
+            fuel_per_call: 1_000_000_000,
+            call_timeout_ms: 5000,
+            max_consecutive_traps: 5,
+            quarantine_secs: 60,
         },
         eventbus: EventBusConfig { capacity: 1 },
         mqtt: MqttConfig::default(),
@@ -238,6 +243,7 @@ fn test_gb28181_auth_config_mapping() {
                 },
             },
         },
+        pipeline: Default::default(),
     };
 
     let sip_cfg = cfg.gb28181_sip_server_config();
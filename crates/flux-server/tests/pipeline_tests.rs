@@ -0,0 +1,187 @@
+use flux_server::config::{PipelineConfig, PipelineStageConfig, StageErrorPolicy};
+use flux_server::pipeline::{Pipeline, PluginInvoker};
+use flux_types::message::Message;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+/// 按插件 ID 模拟插件行为，并记录调用顺序
+struct FakeInvoker {
+    calls: Mutex<Vec<String>>,
+}
+
+impl FakeInvoker {
+    fn new() -> Self {
+        Self {
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl PluginInvoker for FakeInvoker {
+    fn invoke(&self, plugin: &str, _function: &str, input: &str) -> anyhow::Result<Option<Value>> {
+        let msg: Message = serde_json::from_str(input)?;
+        self.calls.lock().unwrap().push(format!("{}:{}", plugin, msg.topic));
+        match plugin {
+            "passthrough" => Ok(None),
+            "enrich" => {
+                let mut payload = msg.payload.clone();
+                payload["site"] = json!("factory-1");
+                Ok(Some(json!({ "payload": payload })))
+            }
+            "rename" => Ok(Some(json!({ "topic": format!("normalized/{}", msg.topic) }))),
+            "split" => Ok(Some(json!([
+                { "topic": "split/a", "payload": { "v": msg.payload["a"] } },
+                { "topic": "split/b", "payload": { "v": msg.payload["b"] } },
+            ]))),
+            "filter" => Ok(Some(Value::Null)),
+            _ => anyhow::bail!("plugin crashed"),
+        }
+    }
+}
+
+fn stage(plugin: &str, topics: &[&str], on_error: StageErrorPolicy) -> PipelineStageConfig {
+    PipelineStageConfig {
+        plugin: plugin.to_string(),
+        function: "on_msg".to_string(),
+        topics: topics.iter().map(|t| t.to_string()).collect(),
+        on_error,
+    }
+}
+
+fn pipeline(stages: Vec<PipelineStageConfig>) -> Pipeline {
+    Pipeline::new(&PipelineConfig {
+        stages,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_stages_run_in_order_for_matching_topics() {
+    let pipeline = pipeline(vec![
+        stage("enrich", &["sensors/#"], StageErrorPolicy::Skip),
+        stage("passthrough", &["other/+"], StageErrorPolicy::Skip),
+        stage("rename", &[], StageErrorPolicy::Skip),
+    ]);
+    let invoker = FakeInvoker::new();
+    let input = Message::new("sensors/room1/temp".to_string(), json!({ "value": 21 }));
+    let id = input.id;
+
+    let outcome = pipeline.process(input, &invoker);
+    assert_eq!(invoker.calls(), vec!["enrich:sensors/room1/temp", "rename:sensors/room1/temp"]);
+    assert_eq!(outcome.messages.len(), 1);
+    let msg = &outcome.messages[0];
+    assert_eq!(msg.id, id);
+    assert_eq!(msg.topic, "normalized/sensors/room1/temp");
+    assert_eq!(msg.payload, json!({ "value": 21, "site": "factory-1" }));
+}
+
+#[test]
+fn test_fan_out_and_drop() {
+    let pipeline = pipeline(vec![
+        stage("split", &["batch"], StageErrorPolicy::Skip),
+        stage("filter", &["split/b"], StageErrorPolicy::Skip),
+    ]);
+    let invoker = FakeInvoker::new();
+    let outcome = pipeline.process(Message::new("batch".to_string(), json!({ "a": 1, "b": 2 })), &invoker);
+
+    assert_eq!(outcome.messages.len(), 1);
+    assert_eq!(outcome.messages[0].topic, "split/a");
+    assert_eq!(outcome.messages[0].payload, json!({ "v": 1 }));
+    assert_eq!(outcome.dropped, 1);
+}
+
+#[test]
+fn test_error_policies() {
+    let invoker = FakeInvoker::new();
+    let msg = || Message::new("sensors/x".to_string(), json!({}));
+
+    let skip = pipeline(vec![stage("broken", &[], StageErrorPolicy::Skip)]).process(msg(), &invoker);
+    assert_eq!(skip.messages.len(), 1);
+    assert_eq!(skip.dropped, 0);
+
+    let drop = pipeline(vec![stage("broken", &[], StageErrorPolicy::Drop)]).process(msg(), &invoker);
+    assert!(drop.messages.is_empty());
+    assert!(drop.dead_letters.is_empty());
+    assert_eq!(drop.dropped, 1);
+
+    let pipeline = pipeline(vec![stage("broken", &[], StageErrorPolicy::DeadLetter)]);
+    let dead = pipeline.process(msg(), &invoker);
+    assert!(dead.messages.is_empty());
+    assert_eq!(dead.dead_letters.len(), 1);
+    let letter = &dead.dead_letters[0];
+    assert_eq!(letter.topic, "$dead_letter/pipeline");
+    assert_eq!(letter.payload["plugin"], "broken");
+    assert_eq!(letter.payload["message"]["topic"], "sensors/x");
+    assert!(letter.payload["error"].as_str().unwrap().contains("plugin crashed"));
+
+    // 死信不会再次进入处理链
+    let calls = invoker.calls().len();
+    let replay = pipeline.process(letter.clone(), &invoker);
+    assert_eq!(replay.messages.len(), 1);
+    assert_eq!(invoker.calls().len(), calls);
+}
+
+#[test]
+fn test_pipeline_config_from_toml() {
+    let config: flux_server::AppConfig = toml_config(
+        r#"
+[[pipeline.stages]]
+plugin = "decoder"
+topics = ["sensors/#"]
+on_error = "dead_letter"
+
+[[pipeline.stages]]
+plugin = "enricher"
+function = "enrich"
+"#,
+    );
+    let stages = &config.pipeline.stages;
+    assert_eq!(stages.len(), 2);
+    assert_eq!(stages[0].function, "on_msg");
+    assert_eq!(stages[0].on_error, StageErrorPolicy::DeadLetter);
+    assert_eq!(stages[1].on_error, StageErrorPolicy::Skip);
+    assert!(stages[1].topics.is_empty());
+    assert_eq!(config.pipeline.dead_letter_topic, "$dead_letter/pipeline");
+}
+
+fn toml_config(extra: &str) -> flux_server::AppConfig {
+    let toml = format!(
+        r#"
+[server]
+host = "127.0.0.1"
+port = 3000
+
+[database]
+url = "sqlite::memory:"
+
+[plugins]
+directory = "plugins"
+{extra}"#
+    );
+    config::Config::builder()
+        .add_source(config::File::from_str(&toml, config::FileFormat::Toml))
+        .build()
+        .unwrap()
+        .try_deserialize()
+        .unwrap()
+}
+
+#[test]
+fn test_output_preserves_identity_of_round_tripped_messages() {
+    let input = Message::new("sensors/x".to_string(), json!({ "v": 1 }));
+
+    // 插件原样返回完整消息（含 ID）时沿用 ID；派生消息（空 ID）分配新 ID
+    let echoed = serde_json::to_value(vec![input.clone(), input.derive("derived", json!({}))]).unwrap();
+    let outputs = flux_server::pipeline::parse_output(&input, echoed).unwrap();
+    assert_eq!(outputs[0].id, input.id);
+    assert_ne!(outputs[1].id, input.id);
+    assert!(!outputs[1].id.is_nil());
+    assert_eq!(outputs[1].timestamp, input.timestamp);
+
+    assert!(flux_server::pipeline::parse_output(&input, json!(42)).is_err());
+    assert!(flux_server::pipeline::parse_output(&input, json!({ "topic": 1 })).is_err());
+}
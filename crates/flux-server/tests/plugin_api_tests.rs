@@ -0,0 +1,132 @@
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use base64::Engine;
+use flux_plugin::{PluginDirectory, PluginManager};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const TOKEN: &str = "plugin-test-token";
+
+/// 返回固定值的旧 ABI 测试插件
+fn module(value: i32) -> String {
+    format!(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "dealloc") (param i32 i32))
+            (func (export "on_msg") (param i32 i32) (result i32) i32.const {}))"#,
+        value
+    )
+}
+
+fn setup() -> (tempfile::TempDir, Arc<PluginManager>, Router) {
+    std::env::set_var("FLUX_ADMIN_TOKEN", TOKEN);
+    let dir = tempfile::tempdir().unwrap();
+    let manager = Arc::new(PluginManager::new().unwrap());
+    let directory = Arc::new(PluginDirectory::new(dir.path(), manager.clone()));
+    (dir, manager, flux_server::plugins::router(directory))
+}
+
+fn request(method: &str, uri: &str, body: Option<Value>, auth: bool) -> Request<Body> {
+    let mut builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json");
+    if auth {
+        builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", TOKEN));
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    builder.body(body).unwrap()
+}
+
+async fn send(app: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(req).await.unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn upload(value: i32, version: &str) -> Value {
+    json!({
+        "manifest": { "version": version, "entry_points": ["on_msg"], "capabilities": ["kv"] },
+        "wasm": base64::engine::general_purpose::STANDARD.encode(module(value)),
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upload_upgrade_and_list() {
+    let (dir, manager, app) = setup();
+
+    let (status, info) = send(&app, request("PUT", "/api/v1/plugins/counter", Some(upload(1, "1.0.0")), true)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(info["id"], "counter");
+    assert_eq!(info["version"], "1.0.0");
+    assert_eq!(info["capabilities"], json!(["kv"]));
+    assert!(dir.path().join("counter").join("plugin.toml").is_file());
+    assert_eq!(manager.call_plugin("counter", "on_msg", "").unwrap(), 1);
+
+    // 再次上传即热更新
+    let (status, info) = send(&app, request("PUT", "/api/v1/plugins/counter", Some(upload(2, "1.1.0")), true)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(info["version"], "1.1.0");
+    assert_eq!(manager.call_plugin("counter", "on_msg", "").unwrap(), 2);
+
+    let (status, list) = send(&app, request("GET", "/api/v1/plugins", None, false)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    let (status, _) = send(&app, request("GET", "/api/v1/plugins/missing", None, false)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upload_rejects_invalid_packages() {
+    let (dir, _manager, app) = setup();
+
+    let (status, _) = send(&app, request("PUT", "/api/v1/plugins/p", Some(upload(1, "1.0.0")), false)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let mut mismatched = upload(1, "1.0.0");
+    mismatched["manifest"]["id"] = json!("other");
+    let (status, _) = send(&app, request("PUT", "/api/v1/plugins/p", Some(mismatched), true)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let invalid = json!({ "wasm": base64::engine::general_purpose::STANDARD.encode("not wasm") });
+    let (status, body) = send(&app, request("PUT", "/api/v1/plugins/p", Some(invalid), true)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].is_string());
+
+    let mut missing_entry = upload(1, "1.0.0");
+    missing_entry["manifest"]["entry_points"] = json!(["absent"]);
+    let (status, _) = send(&app, request("PUT", "/api/v1/plugins/p", Some(missing_entry), true)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(!dir.path().join("p").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_enable_disable_and_delete() {
+    let (dir, manager, app) = setup();
+    send(&app, request("PUT", "/api/v1/plugins/toggle", Some(upload(5, "1.0.0")), true)).await;
+
+    let (status, info) = send(&app, request("POST", "/api/v1/plugins/toggle/disable", None, true)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(info["enabled"], false);
+    assert!(manager.call_plugin("toggle", "on_msg", "").is_err());
+
+    let (status, info) = send(&app, request("POST", "/api/v1/plugins/toggle/enable", None, true)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(info["enabled"], true);
+    assert_eq!(manager.call_plugin("toggle", "on_msg", "").unwrap(), 5);
+
+    let (status, _) = send(&app, request("POST", "/api/v1/plugins/missing/enable", None, true)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(&app, request("DELETE", "/api/v1/plugins/toggle", None, true)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(!dir.path().join("toggle").exists());
+    let (status, _) = send(&app, request("DELETE", "/api/v1/plugins/toggle", None, true)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
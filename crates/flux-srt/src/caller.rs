@@ -4,20 +4,28 @@ use std::sync::Arc;
 use tokio::time::{timeout, Duration};
 use tracing::{debug, info};
 
-use crate::handshake::{HandshakePacket, HandshakeState, HandshakeType};
+use crate::crypto::{CryptoContext, SrtCryptoConfig};
+use crate::handshake::{
+    HandshakeExtension, HandshakePacket, HandshakeState, HandshakeType, SrtHandshakeOptions, SRT_OPT_HAICRYPT,
+};
 use crate::packet::ControlType;
 use crate::socket::{ConnectionState, SrtSocket, SrtSocketConfig};
+use crate::stream_id::SrtStreamId;
 
 /// SRT Caller（客户端模式）
 pub struct SrtCaller {
     socket: Arc<SrtSocket>,
     remote_addr: SocketAddr,
+    crypto: Option<SrtCryptoConfig>,
+    stream_id: Option<String>,
+    latency_ms: u32,
 }
 
 impl SrtCaller {
     /// 创建 Caller
     pub async fn new(remote_addr: SocketAddr) -> Result<Self> {
         let config = SrtSocketConfig::default();
+        let config_latency_ms = config.latency_ms;
         let socket = SrtSocket::new("0.0.0.0:0".parse().unwrap(), config).await?;
 
         info!(
@@ -30,9 +38,30 @@ impl SrtCaller {
         Ok(Self {
             socket: Arc::new(socket),
             remote_addr,
+            crypto: None,
+            stream_id: None,
+            latency_ms: config_latency_ms,
         })
     }
 
+    /// 期望的 TSBPD 延迟，最终取双方较大值
+    pub fn with_latency(mut self, latency_ms: u32) -> Self {
+        self.latency_ms = latency_ms;
+        self
+    }
+
+    /// 使用口令加密连接
+    pub fn with_crypto(mut self, config: SrtCryptoConfig) -> Self {
+        self.crypto = Some(config);
+        self
+    }
+
+    /// 握手时携带 streamid（如 `#!::r=live/cam1,m=publish`）
+    pub fn with_stream_id(mut self, stream_id: impl Into<String>) -> Self {
+        self.stream_id = Some(stream_id.into());
+        self
+    }
+
     /// 连接到服务器
     pub async fn connect(self) -> Result<Arc<SrtSocket>> {
         info!(target: "srt_caller", "Connecting to {}", self.remote_addr);
@@ -52,7 +81,7 @@ impl SrtCaller {
         debug!(target: "srt_caller", "Sent Induction request");
 
         // 等待第二次握手：接收 Induction 响应
-        let induction_resp = self.wait_for_handshake(HandshakeType::Agreement).await?;
+        let (induction_resp, _) = self.wait_for_handshake(HandshakeType::Agreement).await?;
 
         debug!(
             target: "srt_caller",
@@ -61,11 +90,26 @@ impl SrtCaller {
         );
 
         // 第三次握手：发送 Conclusion 请求
-        let conclusion_req = HandshakePacket::create_conclusion_request(
+        let mut conclusion_req = HandshakePacket::create_conclusion_request(
             self.socket.local_socket_id(),
             induction_resp.syn_cookie,
             1000, // 初始序列号
         );
+
+        let crypto = self.crypto.clone().map(CryptoContext::new).transpose()?;
+        let mut options = SrtHandshakeOptions::new(self.latency_ms.min(u16::MAX as u32) as u16);
+        if crypto.is_some() {
+            options.flags |= SRT_OPT_HAICRYPT;
+        }
+        let mut extensions = vec![HandshakeExtension::HandshakeRequest(options)];
+        if let Some(crypto) = &crypto {
+            conclusion_req.encryption_field = crypto.key_length().handshake_code();
+            extensions.push(HandshakeExtension::KeyMaterialRequest(crypto.key_material()?));
+        }
+        if let Some(stream_id) = &self.stream_id {
+            extensions.push(HandshakeExtension::StreamId(stream_id.clone()));
+        }
+        conclusion_req.set_extension_blocks(&extensions);
         self.socket
             .send_handshake(&conclusion_req, self.remote_addr)
             .await?;
@@ -75,13 +119,32 @@ impl SrtCaller {
         debug!(target: "srt_caller", "Sent Conclusion request");
 
         // 等待第四次握手：接收 Conclusion 响应
-        let _conclusion_resp = self.wait_for_handshake(HandshakeType::Agreement).await?;
+        let (conclusion_resp, conclusion_addr) = self.wait_for_handshake(HandshakeType::Agreement).await?;
 
-        debug!(target: "srt_caller", "Received Conclusion response");
+        debug!(target: "srt_caller", "Received Conclusion response from {}", conclusion_addr);
 
-        // 连接建立
-        *self.socket.remote_addr.write().await = Some(self.remote_addr);
-        *self.socket.remote_socket_id.write().await = Some(induction_resp.srt_socket_id);
+        let response_extensions = conclusion_resp.extension_blocks().unwrap_or_default();
+        for extension in &response_extensions {
+            if let HandshakeExtension::HandshakeResponse(options) = extension {
+                self.socket.set_latency(options.negotiate(self.latency_ms.min(u16::MAX as u32) as u16) as u32);
+            }
+        }
+        if let Some(crypto) = crypto {
+            let accepted = response_extensions
+                .iter()
+                .any(|extension| matches!(extension, HandshakeExtension::KeyMaterialResponse(_)));
+            if !accepted {
+                return Err(anyhow::anyhow!("SRT peer did not accept key material"));
+            }
+            self.socket.set_crypto(crypto);
+        }
+        if let Some(stream_id) = &self.stream_id {
+            self.socket.set_stream_id(SrtStreamId::parse(stream_id)?).await;
+        }
+
+        // 连接建立：对端使用独立的连接 Socket 应答
+        *self.socket.remote_addr.write().await = Some(conclusion_addr);
+        *self.socket.remote_socket_id.write().await = Some(conclusion_resp.srt_socket_id);
         *self.socket.state.write().await = ConnectionState::Connected;
         *self.socket.handshake_state.write().await = HandshakeState::Connected;
 
@@ -93,17 +156,17 @@ impl SrtCaller {
         Ok(self.socket)
     }
 
-    /// 等待握手响应
+    /// 等待握手响应，返回握手包及其来源地址；对端拒绝时返回错误
     async fn wait_for_handshake(
         &self,
         expected_type: HandshakeType,
-    ) -> Result<HandshakePacket> {
+    ) -> Result<(HandshakePacket, SocketAddr)> {
         let timeout_duration = Duration::from_millis(self.socket.config.connection_timeout_ms);
 
         timeout(timeout_duration, async {
             loop {
                 let mut buf = vec![0u8; 65536];
-                let (len, _addr) = self.socket.socket.recv_from(&mut buf).await?;
+                let (len, addr) = self.socket.socket.recv_from(&mut buf).await?;
 
                 if len < 16 {
                     continue;
@@ -125,8 +188,11 @@ impl SrtCaller {
                     let handshake = HandshakePacket::parse(&control_packet.payload)
                         .map_err(|e| anyhow::anyhow!("Failed to parse handshake: {}", e))?;
 
+                    if let HandshakeType::Rejected(reason) = handshake.handshake_type {
+                        return Err(anyhow::anyhow!("SRT connection rejected: {}", reason));
+                    }
                     if handshake.handshake_type == expected_type {
-                        return Ok(handshake);
+                        return Ok((handshake, addr));
                     }
                 }
             }
@@ -0,0 +1,427 @@
+use anyhow::{anyhow, Result};
+use bytes::{BufMut, Bytes, BytesMut};
+use openssl::aes::{unwrap_key, wrap_key, AesKey};
+use openssl::hash::MessageDigest;
+use openssl::pkcs5::pbkdf2_hmac;
+use openssl::symm::{Cipher, Crypter, Mode};
+
+/// KM 消息签名（"HAI" PnP Vendor ID）
+const KM_SIGNATURE: u16 = 0x2029;
+/// 版本 1，包类型 2（KMmsg）
+const KM_VERSION_AND_TYPE: u8 = 0x12;
+const KM_CIPHER_AES_CTR: u8 = 2;
+const KM_SE_SRT: u8 = 2;
+const KM_HEADER_LEN: usize = 16;
+
+const SALT_LEN: usize = 16;
+/// PBKDF2 使用盐的最后 64 位
+const PBKDF2_SALT_LEN: usize = 8;
+const PBKDF2_ITERATIONS: usize = 2048;
+/// RFC 3394 完整性校验值长度
+const WRAP_ICV_LEN: usize = 8;
+
+/// 数据包头中的密钥标志：偶数密钥 / 奇数密钥
+pub const KEY_FLAG_EVEN: u8 = 0b01;
+pub const KEY_FLAG_ODD: u8 = 0b10;
+
+/// AES 密钥长度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyLength {
+    Aes128,
+    Aes192,
+    Aes256,
+}
+
+impl KeyLength {
+    pub fn from_bytes(len: usize) -> Option<Self> {
+        match len {
+            16 => Some(Self::Aes128),
+            24 => Some(Self::Aes192),
+            32 => Some(Self::Aes256),
+            _ => None,
+        }
+    }
+
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::Aes128 => 16,
+            Self::Aes192 => 24,
+            Self::Aes256 => 32,
+        }
+    }
+
+    /// 握手包 encryption_field 取值（2/3/4）
+    pub fn handshake_code(self) -> u16 {
+        match self {
+            Self::Aes128 => 2,
+            Self::Aes192 => 3,
+            Self::Aes256 => 4,
+        }
+    }
+
+    fn ctr_cipher(self) -> Cipher {
+        match self {
+            Self::Aes128 => Cipher::aes_128_ctr(),
+            Self::Aes192 => Cipher::aes_192_ctr(),
+            Self::Aes256 => Cipher::aes_256_ctr(),
+        }
+    }
+}
+
+/// SRT 加密配置
+#[derive(Debug, Clone)]
+pub struct SrtCryptoConfig {
+    pub passphrase: String,
+    pub key_length: KeyLength,
+    /// 每个密钥加密的包数，达到后切换到新密钥
+    pub refresh_rate: u32,
+    /// 切换前提前多少个包下发新密钥
+    pub pre_announce: u32,
+}
+
+impl SrtCryptoConfig {
+    /// 口令长度需为 10~79 个字符（与 libsrt 一致）
+    pub fn new(passphrase: impl Into<String>) -> Result<Self> {
+        let passphrase = passphrase.into();
+        if !(10..=79).contains(&passphrase.len()) {
+            return Err(anyhow!("SRT passphrase must be 10 to 79 characters"));
+        }
+
+        Ok(Self {
+            passphrase,
+            key_length: KeyLength::Aes128,
+            refresh_rate: 1 << 24,
+            pre_announce: 1 << 12,
+        })
+    }
+
+    pub fn with_key_length(mut self, key_length: KeyLength) -> Self {
+        self.key_length = key_length;
+        self
+    }
+
+    pub fn with_refresh_rate(mut self, refresh_rate: u32, pre_announce: u32) -> Self {
+        self.refresh_rate = refresh_rate.max(2);
+        self.pre_announce = pre_announce.clamp(1, self.refresh_rate - 1);
+        self
+    }
+
+    /// PBKDF2-HMAC-SHA1 派生 KEK
+    fn derive_kek(&self, salt: &[u8; SALT_LEN], key_length: KeyLength) -> Result<Vec<u8>> {
+        let mut kek = vec![0u8; key_length.bytes()];
+        pbkdf2_hmac(
+            self.passphrase.as_bytes(),
+            &salt[SALT_LEN - PBKDF2_SALT_LEN..],
+            PBKDF2_ITERATIONS,
+            MessageDigest::sha1(),
+            &mut kek,
+        )?;
+        Ok(kek)
+    }
+}
+
+/// 密钥材料消息（KMREQ / KMRSP 的内容）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMaterial {
+    /// KEY_FLAG_EVEN | KEY_FLAG_ODD
+    pub key_flags: u8,
+    pub key_length: KeyLength,
+    pub salt: [u8; SALT_LEN],
+    /// RFC 3394 包装后的密钥（偶数密钥在前）
+    pub wrapped_keys: Bytes,
+}
+
+impl KeyMaterial {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < KM_HEADER_LEN {
+            return Err(anyhow!("Key material too short"));
+        }
+        if data[0] != KM_VERSION_AND_TYPE || u16::from_be_bytes([data[1], data[2]]) != KM_SIGNATURE {
+            return Err(anyhow!("Invalid key material header"));
+        }
+        if data[8] != KM_CIPHER_AES_CTR {
+            return Err(anyhow!("Unsupported key material cipher: {}", data[8]));
+        }
+
+        let key_flags = data[3] & 0x03;
+        let salt_len = data[14] as usize * 4;
+        let key_length = KeyLength::from_bytes(data[15] as usize * 4)
+            .ok_or_else(|| anyhow!("Invalid key length: {}", data[15] as usize * 4))?;
+        let key_count = key_flags.count_ones() as usize;
+        if key_count == 0 || salt_len != SALT_LEN {
+            return Err(anyhow!("Invalid key material"));
+        }
+
+        let wrapped_len = WRAP_ICV_LEN + key_count * key_length.bytes();
+        if data.len() < KM_HEADER_LEN + SALT_LEN + wrapped_len {
+            return Err(anyhow!("Key material truncated"));
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&data[KM_HEADER_LEN..KM_HEADER_LEN + SALT_LEN]);
+        let wrapped_start = KM_HEADER_LEN + SALT_LEN;
+
+        Ok(Self {
+            key_flags,
+            key_length,
+            salt,
+            wrapped_keys: Bytes::copy_from_slice(&data[wrapped_start..wrapped_start + wrapped_len]),
+        })
+    }
+
+    pub fn serialize(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(KM_HEADER_LEN + SALT_LEN + self.wrapped_keys.len());
+        buf.put_u8(KM_VERSION_AND_TYPE);
+        buf.put_u16(KM_SIGNATURE);
+        buf.put_u8(self.key_flags);
+        buf.put_u32(0); // KEKI
+        buf.put_u8(KM_CIPHER_AES_CTR);
+        buf.put_u8(0); // Auth
+        buf.put_u8(KM_SE_SRT);
+        buf.put_u8(0);
+        buf.put_u16(0);
+        buf.put_u8((SALT_LEN / 4) as u8);
+        buf.put_u8((self.key_length.bytes() / 4) as u8);
+        buf.put_slice(&self.salt);
+        buf.put_slice(&self.wrapped_keys);
+        buf.freeze()
+    }
+}
+
+/// 每个连接的加密上下文：偶数 / 奇数两把流加密密钥（SEK）轮换使用
+#[derive(Clone)]
+pub struct CryptoContext {
+    config: SrtCryptoConfig,
+    key_length: KeyLength,
+    salt: [u8; SALT_LEN],
+    kek: Vec<u8>,
+    even_key: Option<Vec<u8>>,
+    odd_key: Option<Vec<u8>>,
+    active_flag: u8,
+    packets_with_key: u32,
+}
+
+impl CryptoContext {
+    /// 发起方：随机生成盐与首个（偶数）密钥
+    pub fn new(config: SrtCryptoConfig) -> Result<Self> {
+        let salt: [u8; SALT_LEN] = rand_bytes(SALT_LEN).try_into().expect("salt length");
+        let key_length = config.key_length;
+        let kek = config.derive_kek(&salt, key_length)?;
+
+        Ok(Self {
+            key_length,
+            salt,
+            kek,
+            even_key: Some(rand_bytes(key_length.bytes())),
+            odd_key: None,
+            active_flag: KEY_FLAG_EVEN,
+            packets_with_key: 0,
+            config,
+        })
+    }
+
+    /// 应答方：用本地口令解出对端下发的密钥；口令不一致时失败
+    pub fn from_key_material(config: SrtCryptoConfig, data: &[u8]) -> Result<Self> {
+        let key_material = KeyMaterial::parse(data)?;
+        let kek = config.derive_kek(&key_material.salt, key_material.key_length)?;
+
+        let mut context = Self {
+            key_length: key_material.key_length,
+            salt: key_material.salt,
+            kek,
+            even_key: None,
+            odd_key: None,
+            active_flag: if key_material.key_flags & KEY_FLAG_EVEN != 0 {
+                KEY_FLAG_EVEN
+            } else {
+                KEY_FLAG_ODD
+            },
+            packets_with_key: 0,
+            config,
+        };
+        context.install(&key_material)?;
+        Ok(context)
+    }
+
+    pub fn key_length(&self) -> KeyLength {
+        self.key_length
+    }
+
+    /// 当前持有的全部密钥（握手 KMREQ / 密钥刷新时发送）
+    pub fn key_material(&self) -> Result<Bytes> {
+        let mut key_flags = 0;
+        let mut keys = Vec::with_capacity(2 * self.key_length.bytes());
+        if let Some(key) = &self.even_key {
+            key_flags |= KEY_FLAG_EVEN;
+            keys.extend_from_slice(key);
+        }
+        if let Some(key) = &self.odd_key {
+            key_flags |= KEY_FLAG_ODD;
+            keys.extend_from_slice(key);
+        }
+
+        let kek = AesKey::new_encrypt(&self.kek).map_err(|_| anyhow!("Invalid KEK"))?;
+        let mut wrapped = vec![0u8; keys.len() + WRAP_ICV_LEN];
+        wrap_key(&kek, None, &mut wrapped, &keys).map_err(|_| anyhow!("AES key wrap failed"))?;
+
+        Ok(KeyMaterial {
+            key_flags,
+            key_length: self.key_length,
+            salt: self.salt,
+            wrapped_keys: Bytes::from(wrapped),
+        }
+        .serialize())
+    }
+
+    /// 安装对端在密钥刷新时下发的密钥
+    pub fn apply_key_material(&mut self, data: &[u8]) -> Result<()> {
+        let key_material = KeyMaterial::parse(data)?;
+        if key_material.salt != self.salt || key_material.key_length != self.key_length {
+            return Err(anyhow!("Key material does not match the session"));
+        }
+        self.install(&key_material)
+    }
+
+    fn install(&mut self, key_material: &KeyMaterial) -> Result<()> {
+        let kek = AesKey::new_decrypt(&self.kek).map_err(|_| anyhow!("Invalid KEK"))?;
+        let mut keys = vec![0u8; key_material.wrapped_keys.len() - WRAP_ICV_LEN];
+        unwrap_key(&kek, None, &mut keys, &key_material.wrapped_keys)
+            .map_err(|_| anyhow!("Key material unwrap failed (wrong passphrase)"))?;
+
+        let mut keys = keys.chunks(self.key_length.bytes());
+        if key_material.key_flags & KEY_FLAG_EVEN != 0 {
+            self.even_key = keys.next().map(<[u8]>::to_vec);
+        }
+        if key_material.key_flags & KEY_FLAG_ODD != 0 {
+            self.odd_key = keys.next().map(<[u8]>::to_vec);
+        }
+        Ok(())
+    }
+
+    /// 加密数据包负载，返回 (密钥标志, 密文, 需要下发的新密钥材料)
+    pub fn encrypt(&mut self, sequence: u32, payload: &[u8]) -> Result<(u8, Bytes, Option<Bytes>)> {
+        let mut announcement = None;
+        if self.packets_with_key == self.config.refresh_rate - self.config.pre_announce {
+            // 预先下发下一把密钥，对端同时持有新旧两把
+            let next_key = Some(rand_bytes(self.key_length.bytes()));
+            if self.active_flag == KEY_FLAG_EVEN {
+                self.odd_key = next_key;
+            } else {
+                self.even_key = next_key;
+            }
+            announcement = Some(self.key_material()?);
+        } else if self.packets_with_key >= self.config.refresh_rate {
+            self.active_flag ^= KEY_FLAG_EVEN | KEY_FLAG_ODD;
+            self.packets_with_key = 0;
+        }
+        self.packets_with_key += 1;
+
+        let key_flag = self.active_flag;
+        let ciphertext = self.apply_ctr(key_flag, sequence, payload)?;
+        Ok((key_flag, ciphertext, announcement))
+    }
+
+    /// 用当前密钥加密且不计入刷新计数（重传、无控制通道的发送端）
+    pub fn encrypt_with_current_key(&self, sequence: u32, payload: &[u8]) -> Result<(u8, Bytes)> {
+        Ok((self.active_flag, self.apply_ctr(self.active_flag, sequence, payload)?))
+    }
+
+    /// 解密数据包负载（CTR 模式加解密相同）
+    pub fn decrypt(&self, key_flag: u8, sequence: u32, payload: &[u8]) -> Result<Bytes> {
+        self.apply_ctr(key_flag, sequence, payload)
+    }
+
+    /// IV = 盐前 112 位 XOR（包序号置于第 10~13 字节），最后 16 位为块计数
+    fn apply_ctr(&self, key_flag: u8, sequence: u32, payload: &[u8]) -> Result<Bytes> {
+        let key = match key_flag {
+            KEY_FLAG_EVEN => self.even_key.as_ref(),
+            KEY_FLAG_ODD => self.odd_key.as_ref(),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow!("No key for key flag {}", key_flag))?;
+
+        let mut iv = [0u8; 16];
+        iv[10..14].copy_from_slice(&sequence.to_be_bytes());
+        for (byte, salt) in iv[..14].iter_mut().zip(&self.salt) {
+            *byte ^= salt;
+        }
+
+        let mut crypter = Crypter::new(self.key_length.ctr_cipher(), Mode::Encrypt, key, Some(&iv))?;
+        let mut out = vec![0u8; payload.len() + 16];
+        let mut len = crypter.update(payload, &mut out)?;
+        len += crypter.finalize(&mut out[len..])?;
+        out.truncate(len);
+        Ok(Bytes::from(out))
+    }
+}
+
+fn rand_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    openssl::rand::rand_bytes(&mut bytes).expect("OpenSSL RNG");
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SrtCryptoConfig {
+        SrtCryptoConfig::new("correct horse battery").unwrap()
+    }
+
+    #[test]
+    fn test_passphrase_length() {
+        assert!(SrtCryptoConfig::new("short").is_err());
+        assert!(SrtCryptoConfig::new("x".repeat(80)).is_err());
+        assert!(SrtCryptoConfig::new("0123456789").is_ok());
+    }
+
+    #[test]
+    fn test_key_material_exchange() {
+        let config = config().with_key_length(KeyLength::Aes256);
+        let mut sender = CryptoContext::new(config.clone()).unwrap();
+        let key_material = sender.key_material().unwrap();
+
+        let parsed = KeyMaterial::parse(&key_material).unwrap();
+        assert_eq!(parsed.key_flags, KEY_FLAG_EVEN);
+        assert_eq!(parsed.key_length, KeyLength::Aes256);
+        assert_eq!(parsed.wrapped_keys.len(), 8 + 32);
+        assert_eq!(parsed.serialize(), key_material);
+
+        let receiver = CryptoContext::from_key_material(config, &key_material).unwrap();
+        assert_eq!(receiver.key_length(), KeyLength::Aes256);
+
+        let (key_flag, ciphertext, announcement) = sender.encrypt(7, b"transport stream").unwrap();
+        assert_eq!(key_flag, KEY_FLAG_EVEN);
+        assert!(announcement.is_none());
+        assert_ne!(ciphertext.as_ref(), b"transport stream");
+        assert_eq!(receiver.decrypt(key_flag, 7, &ciphertext).unwrap().as_ref(), b"transport stream");
+        // 序号参与 IV
+        assert_ne!(receiver.decrypt(key_flag, 8, &ciphertext).unwrap().as_ref(), b"transport stream");
+
+        let wrong = SrtCryptoConfig::new("wrong passphrase").unwrap();
+        assert!(CryptoContext::from_key_material(wrong, &key_material).is_err());
+    }
+
+    #[test]
+    fn test_key_refresh() {
+        let config = config().with_refresh_rate(10, 3);
+        let mut sender = CryptoContext::new(config.clone()).unwrap();
+        let mut receiver = CryptoContext::from_key_material(config, &sender.key_material().unwrap()).unwrap();
+
+        let mut flags = Vec::new();
+        for sequence in 0..25u32 {
+            let (key_flag, ciphertext, announcement) = sender.encrypt(sequence, b"payload").unwrap();
+            if let Some(key_material) = announcement {
+                assert_eq!(KeyMaterial::parse(&key_material).unwrap().key_flags, KEY_FLAG_EVEN | KEY_FLAG_ODD);
+                receiver.apply_key_material(&key_material).unwrap();
+            }
+            assert_eq!(receiver.decrypt(key_flag, sequence, &ciphertext).unwrap().as_ref(), b"payload");
+            flags.push(key_flag);
+        }
+
+        assert!(flags[..10].iter().all(|&flag| flag == KEY_FLAG_EVEN));
+        assert!(flags[10..20].iter().all(|&flag| flag == KEY_FLAG_ODD));
+        assert!(flags[20..].iter().all(|&flag| flag == KEY_FLAG_EVEN));
+    }
+}
@@ -0,0 +1,274 @@
+//! SRT 拉流（request 模式）：订阅 flux-stream 流，复用为 MPEG-TS 发送
+//!
+//! 每个 SRT 数据包承载 7 个 TS 包（1316 字节）；发送落后超过协商延迟时
+//! 按 TLPKTDROP 语义丢弃，直到下一个关键帧重新同步。
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use flux_media_core::codec::AacConfig;
+use flux_media_core::playback::TsMuxer;
+use flux_media_core::types::{AudioCodec, StreamId, VideoCodec};
+use flux_stream::{MediaPacket, PacketType, StreamManager, StreamMetadata};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::{debug, info};
+
+use crate::socket::SrtSocket;
+
+/// 每个 SRT 负载的 TS 包数（7 × 188 = 1316 字节）
+const TS_PACKETS_PER_DATAGRAM: usize = 7;
+const TS_PACKET_SIZE: usize = 188;
+/// 对端静默超过该时长视为断开（KeepAlive 间隔为 1 秒）
+const PEER_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 流元数据 → TS 复用器
+struct TsOutput {
+    muxer: TsMuxer,
+    /// 音频编码；None 表示尚未确定或不支持
+    audio: Option<AudioCodec>,
+    aac: Option<AacConfig>,
+    /// 待发送的 TS 包，凑满 7 个再发送
+    pending: Vec<u8>,
+}
+
+impl TsOutput {
+    fn new(metadata: &StreamMetadata) -> Result<Self> {
+        let mut output = Self {
+            muxer: TsMuxer::new(),
+            audio: None,
+            aac: None,
+            pending: Vec::with_capacity(TS_PACKETS_PER_DATAGRAM * TS_PACKET_SIZE),
+        };
+        output.configure(metadata)?;
+        Ok(output)
+    }
+
+    fn configure(&mut self, metadata: &StreamMetadata) -> Result<()> {
+        match metadata.video_codec.as_deref() {
+            Some("h265") => self.muxer.set_video_codec(VideoCodec::H265)?,
+            Some(_) => self.muxer.set_video_codec(VideoCodec::H264)?,
+            // 只有音频时 PCR 随音频 PID 输出
+            None if metadata.audio_codec.is_some() => self.muxer.set_video_enabled(false),
+            None => {}
+        }
+
+        let (audio, aac) = match metadata.audio_codec.as_deref() {
+            Some("aac") => match metadata
+                .audio_sample_rate
+                .zip(metadata.audio_channels)
+                .and_then(|(sample_rate, channels)| AacConfig::lc(sample_rate, channels))
+            {
+                Some(config) => (Some(AudioCodec::AAC), Some(config)),
+                None => (None, None),
+            },
+            Some("pcma") => (Some(AudioCodec::G711A), None),
+            Some("pcmu") => (Some(AudioCodec::G711U), None),
+            _ => (None, None),
+        };
+        if let Some(codec) = audio {
+            self.muxer.set_audio_codec(codec)?;
+        }
+        self.audio = audio;
+        self.aac = aac;
+        Ok(())
+    }
+
+    /// 复用一个媒体包，返回凑满的 SRT 负载
+    fn mux(&mut self, packet: &MediaPacket) -> Result<Vec<Bytes>> {
+        let timestamp = packet.timestamp as u64 * 90;
+        let ts_packets = match packet.packet_type {
+            PacketType::Video => self
+                .muxer
+                .mux_video_pes(&packet.data, timestamp, timestamp, packet.is_keyframe)?,
+            PacketType::Audio => match (self.audio, self.aac) {
+                (Some(AudioCodec::AAC), Some(aac)) => {
+                    let mut frame = Vec::with_capacity(packet.data.len() + 7);
+                    frame.extend_from_slice(&aac.adts_header(packet.data.len()));
+                    frame.extend_from_slice(&packet.data);
+                    self.muxer.mux_audio_pes(&frame, timestamp)?
+                }
+                (Some(_), _) => self.muxer.mux_audio_pes(&packet.data, timestamp)?,
+                (None, _) => return Ok(Vec::new()),
+            },
+        };
+
+        let mut datagrams = Vec::new();
+        for ts_packet in ts_packets {
+            self.pending.extend_from_slice(&ts_packet);
+            if self.pending.len() >= TS_PACKETS_PER_DATAGRAM * TS_PACKET_SIZE {
+                datagrams.push(Bytes::from(std::mem::take(&mut self.pending)));
+            }
+        }
+        Ok(datagrams)
+    }
+
+    /// 丢弃未发送的 TS 包
+    fn discard_pending(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// 发送进度：媒体时间与墙钟对齐，用于判断是否落后超过延迟
+struct Pacing {
+    anchor: Option<(Instant, u32)>,
+}
+
+impl Pacing {
+    fn lag(&mut self, timestamp: u32) -> Duration {
+        let (started, base) = *self.anchor.get_or_insert((Instant::now(), timestamp));
+        let media_elapsed = Duration::from_millis(timestamp.wrapping_sub(base) as u64);
+        started.elapsed().saturating_sub(media_elapsed)
+    }
+
+    fn reset(&mut self) {
+        self.anchor = None;
+    }
+}
+
+/// 向 request 模式的 SRT 连接发送流，直到对端断开或流结束
+pub async fn serve_stream(stream_manager: Arc<StreamManager>, stream_id: StreamId, socket: Arc<SrtSocket>) -> Result<()> {
+    let mut receiver = stream_manager.subscribe(&stream_id).await?;
+    let metadata = stream_manager
+        .get_metadata(&stream_id)
+        .await
+        .ok_or_else(|| anyhow!("Stream not found: {}", stream_id))?;
+    let mut output = TsOutput::new(&metadata)?;
+    let has_video = metadata.video_codec.is_some();
+
+    // 接收任务：处理 ACK/NAK/KeepAlive，对端 Shutdown 后结束
+    let recv_socket = socket.clone();
+    let mut recv_task = tokio::spawn(async move { while recv_socket.recv().await.is_ok() {} });
+
+    info!(target: "srt_egress", stream_id = %stream_id, latency = ?socket.latency(), "SRT playback started");
+
+    let mut waiting_keyframe = has_video;
+    let mut pacing = Pacing { anchor: None };
+    let mut idle_check = tokio::time::interval(Duration::from_secs(1));
+
+    let result = loop {
+        tokio::select! {
+            result = receiver.recv() => {
+                let packet = match result {
+                    Ok(packet) => packet,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!(target: "srt_egress", stream_id = %stream_id, skipped, "SRT sender lagged");
+                        waiting_keyframe = has_video;
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break Ok(()),
+                };
+
+                if has_video && packet.packet_type == PacketType::Video && packet.is_keyframe && waiting_keyframe {
+                    waiting_keyframe = false;
+                    pacing.reset();
+                    output.discard_pending();
+                }
+                if waiting_keyframe {
+                    continue;
+                }
+
+                // TLPKTDROP：落后超过协商延迟的数据已无意义，丢到下一个关键帧
+                if has_video && pacing.lag(packet.timestamp) > socket.latency() {
+                    debug!(target: "srt_egress", stream_id = %stream_id, "SRT sender too late, dropping until next keyframe");
+                    waiting_keyframe = true;
+                    continue;
+                }
+
+                // 音频编码晚于订阅确定时重新读取元数据
+                if packet.packet_type == PacketType::Audio && output.audio.is_none() {
+                    if let Some(metadata) = stream_manager.get_metadata(&stream_id).await {
+                        output.configure(&metadata)?;
+                    }
+                }
+
+                let datagrams = output.mux(&packet)?;
+                let mut sent = Ok(());
+                for datagram in datagrams {
+                    sent = socket.send_data(&datagram).await;
+                    if sent.is_err() {
+                        break;
+                    }
+                }
+                if let Err(e) = sent {
+                    break Err(e);
+                }
+            }
+            _ = &mut recv_task => break Ok(()),
+            _ = idle_check.tick() => {
+                if socket.idle_time() > PEER_IDLE_TIMEOUT {
+                    debug!(target: "srt_egress", stream_id = %stream_id, "SRT peer idle timeout");
+                    break Ok(());
+                }
+            }
+        }
+    };
+
+    recv_task.abort();
+    let _ = socket.close().await;
+    info!(target: "srt_egress", stream_id = %stream_id, "SRT playback stopped");
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flux_media_core::playback::TsDemuxer;
+    use flux_media_core::playback::ts::{STREAM_TYPE_AAC, STREAM_TYPE_H265};
+
+    fn metadata(video: Option<&str>, audio: Option<&str>) -> StreamMetadata {
+        StreamMetadata {
+            video_codec: video.map(str::to_string),
+            audio_codec: audio.map(str::to_string),
+            audio_sample_rate: Some(44100),
+            audio_channels: Some(2),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_output_datagrams() {
+        let mut output = TsOutput::new(&metadata(Some("h265"), Some("aac"))).unwrap();
+        let video = MediaPacket {
+            data: Bytes::from([0u8, 0, 0, 1, 0x26, 0x01].repeat(400)),
+            timestamp: 40,
+            is_keyframe: true,
+            packet_type: PacketType::Video,
+        };
+        let audio = MediaPacket {
+            data: Bytes::from_static(&[0x21, 0x10, 0x04]),
+            timestamp: 40,
+            is_keyframe: false,
+            packet_type: PacketType::Audio,
+        };
+
+        let mut datagrams = output.mux(&video).unwrap();
+        for _ in 0..10 {
+            datagrams.extend(output.mux(&audio).unwrap());
+        }
+        assert!(!datagrams.is_empty());
+        assert!(datagrams.iter().all(|datagram| datagram.len() == 1316));
+
+        let mut demuxer = TsDemuxer::new();
+        let mut frames = Vec::new();
+        for datagram in &datagrams {
+            frames.extend(demuxer.push(datagram));
+        }
+        let mut streams = demuxer.streams();
+        streams.sort();
+        assert_eq!(streams, vec![(0x100, STREAM_TYPE_H265), (0x101, STREAM_TYPE_AAC)]);
+        let audio_frame = frames.iter().find(|frame| frame.stream_type == STREAM_TYPE_AAC).unwrap();
+        assert_eq!(audio_frame.pts, 3600);
+        assert_eq!(&audio_frame.data[7..], &[0x21, 0x10, 0x04]);
+    }
+
+    #[test]
+    fn test_pacing_lag() {
+        let mut pacing = Pacing { anchor: None };
+        assert!(pacing.lag(1000) < Duration::from_millis(100));
+        // 媒体时间领先墙钟时不算落后
+        assert_eq!(pacing.lag(5000), Duration::ZERO);
+        pacing.anchor = Some((Instant::now() - Duration::from_secs(2), 1000));
+        assert!(pacing.lag(1500) >= Duration::from_millis(1500));
+    }
+}
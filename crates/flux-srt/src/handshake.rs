@@ -1,20 +1,26 @@
 use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fmt;
 use std::io::Cursor;
 
 /// SRT 握手类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HandshakeType {
-    Induction = 1,      // 第一次握手（客户端 -> 服务器）
-    Conclusion = -1,    // 第三次握手（客户端 -> 服务器）
-    Agreement = -2,     // 第二次/第四次握手（服务器 -> 客户端）
+    Induction,      // 第一次握手（客户端 -> 服务器）
+    Conclusion,     // 第三次握手（客户端 -> 服务器）
+    Agreement,      // 第二次/第四次握手（服务器 -> 客户端）
+    Rejected(RejectReason), // 拒绝连接（1000 + 拒绝码）
 }
 
+/// 拒绝握手时的类型基数
+const REJECTION_BASE: i32 = 1000;
+
 impl HandshakeType {
     pub fn from_i32(value: i32) -> Option<Self> {
         match value {
             1 => Some(Self::Induction),
             -1 => Some(Self::Conclusion),
             -2 => Some(Self::Agreement),
+            v if v >= REJECTION_BASE => Some(Self::Rejected(RejectReason::from_code((v - REJECTION_BASE) as u32))),
             _ => None,
         }
     }
@@ -24,8 +30,184 @@ impl HandshakeType {
             Self::Induction => 1,
             Self::Conclusion => -1,
             Self::Agreement => -2,
+            Self::Rejected(reason) => REJECTION_BASE + reason.code() as i32,
+        }
+    }
+}
+
+/// 连接拒绝原因（与 libsrt SRT_REJ_* / SRT_REJX_* 取值一致）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    /// 口令错误，无法解出密钥
+    BadSecret,
+    /// 一端加密另一端未加密
+    Unsecure,
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    /// 不支持的访问模式
+    BadMode,
+    /// 资源已被占用（如重复推流）
+    Conflict,
+    Other(u32),
+}
+
+impl RejectReason {
+    pub fn code(self) -> u32 {
+        match self {
+            Self::BadSecret => 10,
+            Self::Unsecure => 11,
+            Self::BadRequest => 1400,
+            Self::Unauthorized => 1401,
+            Self::Forbidden => 1403,
+            Self::NotFound => 1404,
+            Self::BadMode => 1405,
+            Self::Conflict => 1409,
+            Self::Other(code) => code,
+        }
+    }
+
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            10 => Self::BadSecret,
+            11 => Self::Unsecure,
+            1400 => Self::BadRequest,
+            1401 => Self::Unauthorized,
+            1403 => Self::Forbidden,
+            1404 => Self::NotFound,
+            1405 => Self::BadMode,
+            1409 => Self::Conflict,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadSecret => write!(f, "BADSECRET: wrong passphrase"),
+            Self::Unsecure => write!(f, "UNSECURE: encryption mismatch"),
+            Self::BadRequest => write!(f, "bad request"),
+            Self::Unauthorized => write!(f, "unauthorized"),
+            Self::Forbidden => write!(f, "forbidden"),
+            Self::NotFound => write!(f, "resource not found"),
+            Self::BadMode => write!(f, "unsupported mode"),
+            Self::Conflict => write!(f, "resource already in use"),
+            Self::Other(code) => write!(f, "rejected ({})", code),
+        }
+    }
+}
+
+/// 握手扩展块类型（SRT_CMD_*）
+pub const SRT_CMD_HSREQ: u16 = 1;
+pub const SRT_CMD_HSRSP: u16 = 2;
+pub const SRT_CMD_KMREQ: u16 = 3;
+pub const SRT_CMD_KMRSP: u16 = 4;
+pub const SRT_CMD_SID: u16 = 5;
+
+/// extension_field 标志位
+pub const HS_EXT_HSREQ: u16 = 0x1;
+pub const HS_EXT_KMREQ: u16 = 0x2;
+pub const HS_EXT_CONFIG: u16 = 0x4;
+
+/// HSREQ/HSRSP 能力标志（SRT_OPT_*）
+pub const SRT_OPT_TSBPDSND: u32 = 0x01;
+pub const SRT_OPT_TSBPDRCV: u32 = 0x02;
+pub const SRT_OPT_HAICRYPT: u32 = 0x04;
+pub const SRT_OPT_TLPKTDROP: u32 = 0x08;
+
+/// HSREQ/HSRSP 内容：SRT 版本、能力标志与 TSBPD 延迟
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SrtHandshakeOptions {
+    pub version: u32,
+    pub flags: u32,
+    /// 接收端 TSBPD 延迟（毫秒）
+    pub receiver_latency_ms: u16,
+    /// 期望对端作为接收端使用的延迟（毫秒）
+    pub sender_latency_ms: u16,
+}
+
+impl SrtHandshakeOptions {
+    pub fn new(latency_ms: u16) -> Self {
+        Self {
+            version: SRT_VERSION,
+            flags: SRT_OPT_TSBPDSND | SRT_OPT_TSBPDRCV | SRT_OPT_TLPKTDROP,
+            receiver_latency_ms: latency_ms,
+            sender_latency_ms: latency_ms,
         }
     }
+
+    /// 协商后的延迟：双方取较大值
+    pub fn negotiate(&self, local_latency_ms: u16) -> u16 {
+        local_latency_ms
+            .max(self.receiver_latency_ms)
+            .max(self.sender_latency_ms)
+    }
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut cursor = Cursor::new(data);
+        if cursor.remaining() < 12 {
+            return None;
+        }
+        Some(Self {
+            version: cursor.get_u32(),
+            flags: cursor.get_u32(),
+            receiver_latency_ms: cursor.get_u16(),
+            sender_latency_ms: cursor.get_u16(),
+        })
+    }
+
+    fn to_bytes(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(12);
+        buf.put_u32(self.version);
+        buf.put_u32(self.flags);
+        buf.put_u16(self.receiver_latency_ms);
+        buf.put_u16(self.sender_latency_ms);
+        buf
+    }
+}
+
+/// 握手扩展块
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeExtension {
+    HandshakeRequest(SrtHandshakeOptions),
+    HandshakeResponse(SrtHandshakeOptions),
+    KeyMaterialRequest(Bytes),
+    KeyMaterialResponse(Bytes),
+    StreamId(String),
+    Other { extension_type: u16, data: Bytes },
+}
+
+impl HandshakeExtension {
+    fn extension_type(&self) -> u16 {
+        match self {
+            Self::HandshakeRequest(_) => SRT_CMD_HSREQ,
+            Self::HandshakeResponse(_) => SRT_CMD_HSRSP,
+            Self::KeyMaterialRequest(_) => SRT_CMD_KMREQ,
+            Self::KeyMaterialResponse(_) => SRT_CMD_KMRSP,
+            Self::StreamId(_) => SRT_CMD_SID,
+            Self::Other { extension_type, .. } => *extension_type,
+        }
+    }
+
+    /// 扩展内容，按 4 字节补齐
+    fn content(&self) -> Vec<u8> {
+        let mut content = match self {
+            Self::HandshakeRequest(options) | Self::HandshakeResponse(options) => options.to_bytes(),
+            Self::KeyMaterialRequest(data) | Self::KeyMaterialResponse(data) | Self::Other { data, .. } => {
+                data.to_vec()
+            }
+            Self::StreamId(stream_id) => stream_id.as_bytes().to_vec(),
+        };
+        content.resize(content.len().div_ceil(4) * 4, 0);
+
+        // streamid 按 32 位小端字序存放
+        if let Self::StreamId(_) = self {
+            content.chunks_mut(4).for_each(<[u8]>::reverse);
+        }
+        content
+    }
 }
 
 /// SRT 版本
@@ -134,6 +316,71 @@ impl HandshakePacket {
         buf.freeze()
     }
 
+    /// 解析握手包后的扩展块
+    pub fn extension_blocks(&self) -> Result<Vec<HandshakeExtension>, String> {
+        let mut blocks = Vec::new();
+        let mut cursor = Cursor::new(self.extensions.as_slice());
+
+        while cursor.remaining() >= 4 {
+            let extension_type = cursor.get_u16();
+            let length = cursor.get_u16() as usize * 4;
+            if cursor.remaining() < length {
+                return Err(format!("Handshake extension {} truncated", extension_type));
+            }
+
+            let mut data = vec![0u8; length];
+            cursor.copy_to_slice(&mut data);
+            blocks.push(match extension_type {
+                SRT_CMD_HSREQ | SRT_CMD_HSRSP => {
+                    let options = SrtHandshakeOptions::parse(&data)
+                        .ok_or_else(|| "Handshake options too short".to_string())?;
+                    if extension_type == SRT_CMD_HSREQ {
+                        HandshakeExtension::HandshakeRequest(options)
+                    } else {
+                        HandshakeExtension::HandshakeResponse(options)
+                    }
+                }
+                SRT_CMD_KMREQ => HandshakeExtension::KeyMaterialRequest(Bytes::from(data)),
+                SRT_CMD_KMRSP => HandshakeExtension::KeyMaterialResponse(Bytes::from(data)),
+                SRT_CMD_SID => {
+                    data.chunks_mut(4).for_each(<[u8]>::reverse);
+                    while data.last() == Some(&0) {
+                        data.pop();
+                    }
+                    let stream_id = String::from_utf8(data).map_err(|_| "Stream ID is not UTF-8".to_string())?;
+                    HandshakeExtension::StreamId(stream_id)
+                }
+                extension_type => HandshakeExtension::Other {
+                    extension_type,
+                    data: Bytes::from(data),
+                },
+            });
+        }
+
+        Ok(blocks)
+    }
+
+    /// 写入扩展块并设置 extension_field 标志
+    pub fn set_extension_blocks(&mut self, blocks: &[HandshakeExtension]) {
+        let mut buf = BytesMut::new();
+        self.extension_field = 0;
+
+        for block in blocks {
+            let content = block.content();
+            buf.put_u16(block.extension_type());
+            buf.put_u16((content.len() / 4) as u16);
+            buf.put_slice(&content);
+
+            self.extension_field |= match block.extension_type() {
+                SRT_CMD_HSREQ | SRT_CMD_HSRSP => HS_EXT_HSREQ,
+                SRT_CMD_KMREQ | SRT_CMD_KMRSP => HS_EXT_KMREQ,
+                _ => HS_EXT_CONFIG,
+            };
+        }
+
+        self.extensions = buf.to_vec();
+    }
+
     /// 创建 Induction 请求（第一次握手）
     pub fn create_induction_request(socket_id: u32) -> Self {
         Self {
@@ -192,6 +439,17 @@ impl HandshakePacket {
             ..Default::default()
         }
     }
+
+    /// 创建拒绝响应
+    pub fn create_rejection(request: &HandshakePacket, reason: RejectReason) -> Self {
+        Self {
+            version: SRT_VERSION,
+            handshake_type: HandshakeType::Rejected(reason),
+            srt_socket_id: 0,
+            initial_packet_sequence: request.initial_packet_sequence,
+            ..Default::default()
+        }
+    }
 }
 
 /// 握手状态机
@@ -217,6 +475,42 @@ mod tests {
         assert_eq!(HandshakeType::from_i32(1), Some(HandshakeType::Induction));
         assert_eq!(HandshakeType::from_i32(-1), Some(HandshakeType::Conclusion));
         assert_eq!(HandshakeType::from_i32(-2), Some(HandshakeType::Agreement));
+
+        let rejected = HandshakeType::Rejected(RejectReason::BadSecret);
+        assert_eq!(rejected.to_i32(), 1010);
+        assert_eq!(HandshakeType::from_i32(2403), Some(HandshakeType::Rejected(RejectReason::Forbidden)));
+        assert_eq!(HandshakeType::from_i32(0), None);
+    }
+
+    #[test]
+    fn test_extension_blocks() {
+        let mut packet = HandshakePacket::create_conclusion_request(1, 2, 3);
+        packet.set_extension_blocks(&[
+            HandshakeExtension::HandshakeRequest(SrtHandshakeOptions::new(200)),
+            HandshakeExtension::KeyMaterialRequest(Bytes::from_static(&[1, 2, 3, 4, 5, 6, 7, 8])),
+            HandshakeExtension::StreamId("#!::r=live/cam,m=publish".to_string()),
+        ]);
+        assert_eq!(packet.extension_field, HS_EXT_HSREQ | HS_EXT_KMREQ | HS_EXT_CONFIG);
+        // HSREQ 块：类型 1，长度 3 个字，末字为两个延迟
+        assert_eq!(&packet.extensions[..4], &[0, 1, 0, 3]);
+        assert_eq!(&packet.extensions[12..16], &[0, 200, 0, 200]);
+
+        // SID 块：类型 5，长度 6 个字，每个字内字节倒序
+        let sid_offset = 16 + 4 + 8;
+        assert_eq!(&packet.extensions[sid_offset..sid_offset + 4], &[0, 5, 0, 6]);
+        assert_eq!(&packet.extensions[sid_offset + 4..sid_offset + 8], b"::!#");
+
+        let parsed = HandshakePacket::parse(&packet.serialize()).unwrap();
+        assert_eq!(
+            parsed.extension_blocks().unwrap(),
+            vec![
+                HandshakeExtension::HandshakeRequest(SrtHandshakeOptions::new(200)),
+                HandshakeExtension::KeyMaterialRequest(Bytes::from_static(&[1, 2, 3, 4, 5, 6, 7, 8])),
+                HandshakeExtension::StreamId("#!::r=live/cam,m=publish".to_string()),
+            ]
+        );
+        assert_eq!(SrtHandshakeOptions::new(200).negotiate(120), 200);
+        assert_eq!(SrtHandshakeOptions::new(80).negotiate(120), 120);
     }
 
     #[test]
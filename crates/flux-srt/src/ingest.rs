@@ -0,0 +1,328 @@
+//! MPEG-TS over SRT 接入：解封装为 flux-stream 媒体包（Listener 推流与 Caller 拉流共用）
+//!
+//! 视频输出 Annex B 访问单元，AAC 去掉 ADTS 头按帧输出，G.711 原样透传；
+//! 时间戳由 DTS 换算为毫秒，重连后保持单调递增。
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use flux_config::StreamMode;
+use flux_media_core::codec::{parse_avc_sps_dimensions, parse_hevc_sps_dimensions, split_annexb, AacConfig};
+use flux_media_core::playback::ts::{
+    TsDemuxer, TsFrame, STREAM_TYPE_AAC, STREAM_TYPE_G711A, STREAM_TYPE_G711U, STREAM_TYPE_H264, STREAM_TYPE_H265,
+};
+use flux_media_core::types::StreamId;
+use flux_stream::{MediaPacket, PacketType, Protocol, Stream, StreamManager, StreamMetadata, StreamStatus};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::debug;
+
+/// 33 位 PTS/DTS 回绕掩码
+const TIMESTAMP_MASK: u64 = 0x1_FFFF_FFFF;
+
+/// 注册到 flux-stream 的 SRT 流
+pub struct SrtStream {
+    stream_id: StreamId,
+    metadata: Arc<RwLock<StreamMetadata>>,
+}
+
+#[async_trait]
+impl Stream for SrtStream {
+    fn stream_id(&self) -> &StreamId {
+        &self.stream_id
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::SRT
+    }
+
+    async fn metadata(&self) -> StreamMetadata {
+        self.metadata.read().await.clone()
+    }
+
+    async fn status(&self) -> StreamStatus {
+        StreamStatus::Running
+    }
+
+    async fn start(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// TS 负载 → flux-stream 媒体包
+pub struct TsIngest {
+    stream_id: StreamId,
+    stream_manager: Arc<StreamManager>,
+    metadata: Arc<RwLock<StreamMetadata>>,
+    demuxer: TsDemuxer,
+    /// 当前连接的首个 DTS
+    base_dts: Option<u64>,
+    /// 当前连接时间戳的起点（毫秒）
+    base_timestamp: u32,
+    last_timestamp: u32,
+}
+
+impl TsIngest {
+    /// 在 flux-stream 中注册流；同名流已存在时失败
+    pub async fn register(stream_manager: Arc<StreamManager>, stream_id: StreamId) -> Result<Self> {
+        if stream_manager.get_metadata(&stream_id).await.is_some() {
+            return Err(anyhow!("Stream already exists: {}", stream_id));
+        }
+
+        let metadata = Arc::new(RwLock::new(StreamMetadata::default()));
+        stream_manager
+            .register_stream(
+                Box::new(SrtStream {
+                    stream_id: stream_id.clone(),
+                    metadata: metadata.clone(),
+                }),
+                StreamMode::Passthrough { remux: true },
+            )
+            .await?;
+
+        Ok(Self {
+            stream_id,
+            stream_manager,
+            metadata,
+            demuxer: TsDemuxer::new(),
+            base_dts: None,
+            base_timestamp: 0,
+            last_timestamp: 0,
+        })
+    }
+
+    pub fn stream_id(&self) -> &StreamId {
+        &self.stream_id
+    }
+
+    /// 输入一个 SRT 负载（若干 188 字节 TS 包）
+    pub async fn push(&mut self, data: &[u8]) -> Result<()> {
+        for frame in self.demuxer.push(data) {
+            self.publish_frame(frame).await?;
+        }
+        Ok(())
+    }
+
+    /// 重连后重置解封装状态，时间戳从上次位置继续
+    pub fn reset(&mut self) {
+        self.demuxer = TsDemuxer::new();
+        self.base_dts = None;
+        self.base_timestamp = self.last_timestamp.saturating_add(1);
+    }
+
+    /// 从 flux-stream 注销
+    pub async fn unregister(self) -> Result<()> {
+        self.stream_manager.unregister_stream(&self.stream_id).await
+    }
+
+    fn timestamp(&mut self, dts: u64) -> u32 {
+        let base_dts = *self.base_dts.get_or_insert(dts);
+        let elapsed_ms = (dts.wrapping_sub(base_dts) & TIMESTAMP_MASK) / 90;
+        let timestamp = self.base_timestamp.wrapping_add(elapsed_ms as u32);
+        self.last_timestamp = self.last_timestamp.max(timestamp);
+        timestamp
+    }
+
+    async fn publish_frame(&mut self, frame: TsFrame) -> Result<()> {
+        match frame.stream_type {
+            STREAM_TYPE_H264 | STREAM_TYPE_H265 => self.publish_video(frame).await,
+            STREAM_TYPE_AAC => self.publish_aac(frame).await,
+            STREAM_TYPE_G711A | STREAM_TYPE_G711U => {
+                let codec = if frame.stream_type == STREAM_TYPE_G711A { "pcma" } else { "pcmu" };
+                self.update_audio(codec, 8000, 1).await;
+                let timestamp = self.timestamp(frame.pts);
+                self.publish(frame.data, timestamp, false, PacketType::Audio).await
+            }
+            stream_type => {
+                debug!(target: "srt_ingest", "Ignoring TS stream type 0x{:02x}", stream_type);
+                Ok(())
+            }
+        }
+    }
+
+    async fn publish_video(&mut self, frame: TsFrame) -> Result<()> {
+        let h265 = frame.stream_type == STREAM_TYPE_H265;
+        let nalus = split_annexb(&frame.data);
+        let is_keyframe = frame.random_access
+            || nalus.iter().any(|nalu| match h265 {
+                true => (16..=21).contains(&((nalu[0] >> 1) & 0x3F)),
+                false => nalu[0] & 0x1F == 5,
+            });
+
+        let codec = if h265 { "h265" } else { "h264" };
+        let needs_dimensions = {
+            let metadata = self.metadata.read().await;
+            metadata.video_codec.as_deref() != Some(codec) || metadata.width.is_none()
+        };
+        if needs_dimensions {
+            let dimensions = nalus.iter().find_map(|nalu| match h265 {
+                true if (nalu[0] >> 1) & 0x3F == 33 => parse_hevc_sps_dimensions(nalu).ok(),
+                false if nalu[0] & 0x1F == 7 => parse_avc_sps_dimensions(nalu).ok(),
+                _ => None,
+            });
+            let mut metadata = self.metadata.write().await;
+            metadata.video_codec = Some(codec.to_string());
+            if let Some((width, height)) = dimensions {
+                metadata.width = Some(width);
+                metadata.height = Some(height);
+            }
+        }
+
+        let timestamp = self.timestamp(frame.dts);
+        self.publish(frame.data, timestamp, is_keyframe, PacketType::Video).await
+    }
+
+    /// 一个 PES 可包含多个 ADTS 帧，每帧 1024 个采样
+    async fn publish_aac(&mut self, frame: TsFrame) -> Result<()> {
+        let timestamp = self.timestamp(frame.pts);
+        let mut offset = 0;
+        let mut index = 0u32;
+
+        while let Some((config, header_len, frame_len)) = parse_adts(&frame.data[offset..]) {
+            if frame_len < header_len || offset + frame_len > frame.data.len() {
+                break;
+            }
+            self.update_audio("aac", config.sample_rate(), config.channel_count() as u8).await;
+
+            let frame_timestamp = timestamp + index * 1024 * 1000 / config.sample_rate();
+            let payload = frame.data.slice(offset + header_len..offset + frame_len);
+            self.publish(payload, frame_timestamp, false, PacketType::Audio).await?;
+
+            offset += frame_len;
+            index += 1;
+        }
+        Ok(())
+    }
+
+    async fn update_audio(&self, codec: &str, sample_rate: u32, channels: u8) {
+        let unchanged = {
+            let metadata = self.metadata.read().await;
+            metadata.audio_codec.as_deref() == Some(codec)
+                && metadata.audio_sample_rate == Some(sample_rate)
+                && metadata.audio_channels == Some(channels)
+        };
+        if !unchanged {
+            let mut metadata = self.metadata.write().await;
+            metadata.audio_codec = Some(codec.to_string());
+            metadata.audio_sample_rate = Some(sample_rate);
+            metadata.audio_channels = Some(channels);
+        }
+    }
+
+    async fn publish(&self, data: Bytes, timestamp: u32, is_keyframe: bool, packet_type: PacketType) -> Result<()> {
+        self.stream_manager
+            .publish_packet(
+                &self.stream_id,
+                MediaPacket {
+                    data,
+                    timestamp,
+                    is_keyframe,
+                    packet_type,
+                },
+            )
+            .await
+    }
+}
+
+/// 解析 ADTS 头，返回 (配置, 头长度, 帧总长度)
+fn parse_adts(data: &[u8]) -> Option<(AacConfig, usize, usize)> {
+    if data.len() < 7 || data[0] != 0xFF || data[1] & 0xF0 != 0xF0 {
+        return None;
+    }
+
+    let protection_absent = data[1] & 0x01 != 0;
+    let config = AacConfig {
+        object_type: (data[2] >> 6) + 1,
+        sampling_frequency_index: (data[2] >> 2) & 0x0F,
+        channel_configuration: ((data[2] & 0x01) << 2) | (data[3] >> 6),
+    };
+    if config.sampling_frequency_index > 12 {
+        return None;
+    }
+
+    let frame_len = (((data[3] & 0x03) as usize) << 11) | ((data[4] as usize) << 3) | (data[5] as usize >> 5);
+    let header_len = if protection_absent { 7 } else { 9 };
+    Some((config, header_len, frame_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flux_config::StreamingConfig;
+    use flux_media_core::playback::TsMuxer;
+    use flux_media_core::types::AudioCodec;
+
+    #[test]
+    fn test_parse_adts() {
+        let config = AacConfig::lc(44100, 2).unwrap();
+        let header = config.adts_header(100);
+        let (parsed, header_len, frame_len) = parse_adts(&header).unwrap();
+        assert_eq!(parsed, config);
+        assert_eq!((header_len, frame_len), (7, 107));
+        assert!(parse_adts(&[0x47, 0x40, 0, 0, 0, 0, 0]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ingest_publishes_packets() {
+        let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+        let stream_id = StreamId::new("srt", "live/cam1");
+        let mut ingest = TsIngest::register(stream_manager.clone(), stream_id.clone()).await.unwrap();
+        assert!(TsIngest::register(stream_manager.clone(), stream_id.clone()).await.is_err());
+        let mut rx = stream_manager.subscribe(&stream_id).await.unwrap();
+
+        let mut muxer = TsMuxer::new();
+        muxer.set_audio_codec(AudioCodec::AAC).unwrap();
+        let aac = AacConfig::lc(48000, 2).unwrap();
+        let mut ts = Vec::new();
+        let keyframe = [0u8, 0, 0, 1, 0x65, 0x88, 0x84];
+        let audio = [&aac.adts_header(4)[..], &[1, 2, 3, 4], &aac.adts_header(2), &[5, 6]].concat();
+        for packet in muxer.mux_video_pes(&keyframe, 900_000, 900_000, true).unwrap() {
+            ts.extend_from_slice(&packet);
+        }
+        for packet in muxer.mux_audio_pes(&audio, 900_000).unwrap() {
+            ts.extend_from_slice(&packet);
+        }
+        for packet in muxer.mux_video_pes(&[0, 0, 0, 1, 0x41], 903_600, 903_600, false).unwrap() {
+            ts.extend_from_slice(&packet);
+        }
+        for chunk in ts.chunks(1316) {
+            ingest.push(chunk).await.unwrap();
+        }
+
+        let first = rx.recv().await.unwrap();
+        assert_eq!(first.packet_type, PacketType::Audio);
+        assert_eq!((first.data.as_ref(), first.timestamp), (&[1u8, 2, 3, 4][..], 0));
+        let second = rx.recv().await.unwrap();
+        assert_eq!((second.data.as_ref(), second.timestamp), (&[5u8, 6][..], 21));
+        let video = rx.recv().await.unwrap();
+        assert_eq!(video.packet_type, PacketType::Video);
+        assert!(video.is_keyframe);
+        assert_eq!(video.data.as_ref(), &keyframe);
+
+        let metadata = stream_manager.get_metadata(&stream_id).await.unwrap();
+        assert_eq!(metadata.video_codec.as_deref(), Some("h264"));
+        assert_eq!(metadata.audio_codec.as_deref(), Some("aac"));
+        assert_eq!(metadata.audio_sample_rate, Some(48000));
+        assert_eq!(metadata.audio_channels, Some(2));
+
+        // 重连后时间戳继续递增
+        ingest.reset();
+        let mut muxer = TsMuxer::new();
+        let mut ts = Vec::new();
+        for pts in [90, 3690] {
+            for packet in muxer.mux_video_pes(&keyframe, pts, pts, true).unwrap() {
+                ts.extend_from_slice(&packet);
+            }
+        }
+        ingest.push(&ts).await.unwrap();
+        let resumed = rx.recv().await.unwrap();
+        assert_eq!(resumed.timestamp, 1);
+
+        ingest.unregister().await.unwrap();
+        assert!(stream_manager.get_metadata(&stream_id).await.is_none());
+    }
+}
@@ -4,11 +4,17 @@ pub mod bandwidth;
 pub mod buffer;
 pub mod caller;
 pub mod congestion;
+pub mod crypto;
+pub mod egress;
 pub mod handshake;
+pub mod ingest;
 pub mod listener;
 pub mod packet;
+pub mod pull;
 pub mod receiver;
 pub mod sender;
+pub mod server;
 pub mod socket;
 pub mod statistics;
+pub mod stream_id;
 pub mod telemetry;
@@ -1,13 +1,49 @@
 use anyhow::Result;
+use async_trait::async_trait;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, info, warn};
 
-use crate::handshake::{HandshakePacket, HandshakeState, HandshakeType};
+use crate::crypto::{CryptoContext, SrtCryptoConfig};
+use crate::handshake::{
+    HandshakeExtension, HandshakePacket, HandshakeState, HandshakeType, RejectReason, SrtHandshakeOptions,
+};
 use crate::packet::ControlType;
 use crate::socket::{ConnectionState, SrtSocket, SrtSocketConfig};
+use crate::stream_id::SrtStreamId;
+
+/// streamid 访问控制：握手 Conclusion 阶段决定是否接受连接
+#[async_trait]
+pub trait SrtAccessControl: Send + Sync {
+    async fn authorize(&self, peer: SocketAddr, stream_id: &SrtStreamId) -> Result<(), RejectReason>;
+}
+
+/// 接受所有连接
+pub struct AllowAll;
+
+#[async_trait]
+impl SrtAccessControl for AllowAll {
+    async fn authorize(&self, _peer: SocketAddr, _stream_id: &SrtStreamId) -> Result<(), RejectReason> {
+        Ok(())
+    }
+}
+
+/// 握手策略：加密、访问控制与延迟
+struct ListenerPolicy {
+    crypto: Option<SrtCryptoConfig>,
+    access_control: Arc<dyn SrtAccessControl>,
+    latency_ms: u16,
+}
+
+/// Conclusion 协商结果
+struct Negotiated {
+    stream_id: Option<SrtStreamId>,
+    crypto: Option<CryptoContext>,
+    /// 对端未发送 HSREQ 时为 None
+    latency_ms: Option<u16>,
+}
 
 /// SRT Listener（服务器模式）
 pub struct SrtListener {
@@ -15,6 +51,7 @@ pub struct SrtListener {
     pending_connections: Arc<RwLock<HashMap<SocketAddr, PendingConnection>>>,
     accept_tx: mpsc::Sender<Arc<SrtSocket>>,
     accept_rx: Option<mpsc::Receiver<Arc<SrtSocket>>>,
+    policy: ListenerPolicy,
 }
 
 /// 待处理的连接
@@ -27,6 +64,7 @@ impl SrtListener {
     /// 创建 Listener
     pub async fn bind(addr: SocketAddr) -> Result<Self> {
         let config = SrtSocketConfig::default();
+        let latency_ms = config.latency_ms as u16;
         let socket = SrtSocket::new(addr, config).await?;
 
         let (accept_tx, accept_rx) = mpsc::channel(10);
@@ -38,9 +76,37 @@ impl SrtListener {
             pending_connections: Arc::new(RwLock::new(HashMap::new())),
             accept_tx,
             accept_rx: Some(accept_rx),
+            policy: ListenerPolicy {
+                crypto: None,
+                access_control: Arc::new(AllowAll),
+                latency_ms,
+            },
         })
     }
 
+    /// 要求连接使用口令加密
+    pub fn with_crypto(mut self, config: SrtCryptoConfig) -> Self {
+        self.policy.crypto = Some(config);
+        self
+    }
+
+    /// 按 streamid 授权连接
+    pub fn with_access_control(mut self, access_control: Arc<dyn SrtAccessControl>) -> Self {
+        self.policy.access_control = access_control;
+        self
+    }
+
+    /// 本端 TSBPD 延迟，与对端 HSREQ 取较大值
+    pub fn with_latency(mut self, latency_ms: u32) -> Self {
+        self.policy.latency_ms = latency_ms.min(u16::MAX as u32) as u16;
+        self
+    }
+
+    /// 监听地址
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
     /// 启动监听
     pub async fn start(mut self) -> Result<mpsc::Receiver<Arc<SrtSocket>>> {
         let accept_rx = self
@@ -51,9 +117,10 @@ impl SrtListener {
         let socket = self.socket.clone();
         let pending = self.pending_connections.clone();
         let accept_tx = self.accept_tx.clone();
+        let policy = Arc::new(self.policy);
 
         tokio::spawn(async move {
-            if let Err(e) = Self::listen_loop(socket, pending, accept_tx).await {
+            if let Err(e) = Self::listen_loop(socket, pending, accept_tx, policy).await {
                 warn!(target: "srt_listener", "Listen loop error: {}", e);
             }
         });
@@ -66,6 +133,7 @@ impl SrtListener {
         socket: Arc<SrtSocket>,
         pending: Arc<RwLock<HashMap<SocketAddr, PendingConnection>>>,
         accept_tx: mpsc::Sender<Arc<SrtSocket>>,
+        policy: Arc<ListenerPolicy>,
     ) -> Result<()> {
         let mut buf = vec![0u8; 65536];
 
@@ -94,6 +162,7 @@ impl SrtListener {
                     &socket,
                     &pending,
                     &accept_tx,
+                    &policy,
                     &control_packet.payload,
                     addr,
                 )
@@ -107,6 +176,7 @@ impl SrtListener {
         socket: &Arc<SrtSocket>,
         pending: &Arc<RwLock<HashMap<SocketAddr, PendingConnection>>>,
         accept_tx: &mpsc::Sender<Arc<SrtSocket>>,
+        policy: &ListenerPolicy,
         payload: &[u8],
         addr: SocketAddr,
     ) -> Result<()> {
@@ -156,19 +226,45 @@ impl SrtListener {
 
                 if let Some(pending_conn) = pending_map.get(&addr) {
                     if pending_conn.syn_cookie == handshake.syn_cookie {
-                        // Cookie 验证通过，发送最终响应
-                        let response = HandshakePacket::create_conclusion_response(
-                            &handshake,
-                            socket.local_socket_id(),
-                        );
-
-                        socket.send_handshake(&response, addr).await?;
-
-                        // 创建新的连接 Socket
+                        // Cookie 验证通过，协商加密并鉴权
+                        let negotiated = match Self::negotiate(policy, &handshake, addr).await {
+                            Ok(negotiated) => negotiated,
+                            Err(reason) => {
+                                warn!(target: "srt_listener", "Rejected connection from {}: {}", addr, reason);
+                                let rejection = HandshakePacket::create_rejection(&handshake, reason);
+                                socket.send_handshake(&rejection, addr).await?;
+                                pending_map.remove(&addr);
+                                return Ok(());
+                            }
+                        };
+
+                        // 创建新的连接 Socket，后续数据直接发往该端口
                         let config = SrtSocketConfig::default();
-                        let new_socket = SrtSocket::new("0.0.0.0:0".parse().unwrap(), config).await?;
+                        let bind_addr = SocketAddr::new(socket.local_addr()?.ip(), 0);
+                        let new_socket = SrtSocket::new(bind_addr, config).await?;
                         *new_socket.remote_addr.write().await = Some(addr);
                         *new_socket.remote_socket_id.write().await = Some(handshake.srt_socket_id);
+
+                        let mut response = HandshakePacket::create_conclusion_response(
+                            &handshake,
+                            new_socket.local_socket_id(),
+                        );
+                        let mut extensions = Vec::new();
+                        if let Some(latency_ms) = negotiated.latency_ms {
+                            extensions.push(HandshakeExtension::HandshakeResponse(SrtHandshakeOptions::new(latency_ms)));
+                            new_socket.set_latency(latency_ms as u32);
+                        }
+                        if let Some(crypto) = negotiated.crypto {
+                            response.encryption_field = crypto.key_length().handshake_code();
+                            extensions.push(HandshakeExtension::KeyMaterialResponse(crypto.key_material()?));
+                            new_socket.set_crypto(crypto);
+                        }
+                        response.set_extension_blocks(&extensions);
+                        if let Some(stream_id) = negotiated.stream_id {
+                            new_socket.set_stream_id(stream_id).await;
+                        }
+
+                        new_socket.send_handshake(&response, addr).await?;
                         *new_socket.state.write().await = ConnectionState::Connected;
 
                         let new_socket = Arc::new(new_socket);
@@ -210,6 +306,48 @@ impl SrtListener {
         Ok(())
     }
 
+    /// 解析 Conclusion 扩展：口令一致才建立加密，streamid 交由访问控制授权
+    async fn negotiate(
+        policy: &ListenerPolicy,
+        handshake: &HandshakePacket,
+        addr: SocketAddr,
+    ) -> Result<Negotiated, RejectReason> {
+        let extensions = handshake.extension_blocks().map_err(|_| RejectReason::BadRequest)?;
+
+        let mut stream_id = None;
+        let mut key_material = None;
+        let mut latency_ms = None;
+        for extension in extensions {
+            match extension {
+                HandshakeExtension::HandshakeRequest(options) => latency_ms = Some(options.negotiate(policy.latency_ms)),
+                HandshakeExtension::StreamId(value) => {
+                    stream_id = Some(SrtStreamId::parse(&value).map_err(|_| RejectReason::BadRequest)?);
+                }
+                HandshakeExtension::KeyMaterialRequest(data) => key_material = Some(data),
+                _ => {}
+            }
+        }
+
+        let crypto = match (&policy.crypto, key_material) {
+            (Some(config), Some(data)) => Some(
+                CryptoContext::from_key_material(config.clone(), &data).map_err(|_| RejectReason::BadSecret)?,
+            ),
+            (None, None) => None,
+            _ => return Err(RejectReason::Unsecure),
+        };
+
+        policy
+            .access_control
+            .authorize(addr, stream_id.as_ref().unwrap_or(&SrtStreamId::default()))
+            .await?;
+
+        Ok(Negotiated {
+            stream_id,
+            crypto,
+            latency_ms,
+        })
+    }
+
     /// 生成 SYN Cookie
     fn generate_syn_cookie() -> u32 {
         use std::time::{SystemTime, UNIX_EPOCH};
@@ -3,13 +3,19 @@ mod bandwidth;
 mod buffer;
 mod caller;
 mod congestion;
+mod crypto;
+mod egress;
 mod handshake;
+mod ingest;
 mod listener;
 mod packet;
+mod pull;
 mod receiver;
 mod sender;
+mod server;
 mod socket;
 mod statistics;
+mod stream_id;
 mod telemetry;
 
 use axum::{
@@ -25,6 +31,7 @@ use flux_media_core::{
     storage::filesystem::FileSystemStorage,
     types::StreamId,
 };
+use bytes::Bytes;
 use flux_storage::{DiskType, PoolConfig, StorageManager};
 use std::{collections::HashMap, path::PathBuf, sync::Arc};
 use tokio::sync::RwLock;
@@ -32,7 +39,11 @@ use tracing::info;
 
 use telemetry::TelemetryClient;
 
+use crypto::{KeyLength, SrtCryptoConfig};
+use listener::SrtListener;
+use pull::SrtPullManager;
 use receiver::SrtReceiver;
+use server::{SrtServer, SrtServerConfig};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "FLUX SRT Media Server")]
@@ -51,6 +62,22 @@ struct Args {
 
     #[arg(long, default_value_t = 1000)]
     telemetry_timeout_ms: u64,
+
+    /// SRT Listener 地址（按 streamid 接收推流或提供播放）
+    #[arg(long)]
+    srt_listen: Option<String>,
+
+    /// SRT 接收延迟（毫秒），与对端协商取较大值
+    #[arg(long, default_value_t = 120)]
+    srt_latency_ms: u32,
+
+    /// 加密口令（10~79 个字符），未设置时拒绝加密连接
+    #[arg(long)]
+    srt_passphrase: Option<String>,
+
+    /// AES 密钥长度（16/24/32 字节）
+    #[arg(long, default_value_t = 16)]
+    srt_key_length: usize,
 }
 
 #[derive(Clone)]
@@ -59,6 +86,7 @@ struct AppState {
     orchestrator: Arc<SnapshotOrchestrator>,
     streams: Arc<RwLock<HashMap<String, StreamInfo>>>,
     timeshift: Option<Arc<flux_media_core::timeshift::TimeShiftCore>>,
+    pulls: Arc<SrtPullManager>,
 }
 
 #[derive(Debug, Clone)]
@@ -142,32 +170,15 @@ async fn start_stream(
         // 处理接收到的数据
         while let Some(packet) = rx.recv().await {
             if !packet.is_control {
-                // 添加到时移
-                if let Some(ref ts) = timeshift_clone {
-                    use flux_media_core::timeshift::{Segment, SegmentFormat, SegmentMetadata};
-                    use chrono::Utc;
-                    
-                    let segment = Segment {
-                        sequence: packet.timestamp as u64,
-                        start_time: Utc::now(),
-                        duration: 0.04,
-                        data: packet.data.clone(),
-                        metadata: SegmentMetadata {
-                            format: SegmentFormat::Raw,
-                            has_keyframe: false,
-                            file_path: None,
-                            size: packet.data.len() as u64,
-                        },
-                    };
-                    
-                    let _ = ts.add_segment(&stream_id_clone.as_str(), segment).await;
-                }
-                
-                // 更新包计数
-                let mut streams = streams_clone.write().await;
-                if let Some(info) = streams.get_mut(&key) {
-                    info.packet_count += 1;
-                }
+                record_packet(
+                    &streams_clone,
+                    timeshift_clone.as_ref(),
+                    &key,
+                    &stream_id_clone,
+                    packet.timestamp as u64,
+                    packet.data,
+                )
+                .await;
             }
         }
     });
@@ -179,6 +190,87 @@ async fn start_stream(
     })))
 }
 
+/// 写入时移并更新包计数
+async fn record_packet(
+    streams: &RwLock<HashMap<String, StreamInfo>>,
+    timeshift: Option<&Arc<flux_media_core::timeshift::TimeShiftCore>>,
+    key: &str,
+    stream_id: &StreamId,
+    sequence: u64,
+    data: Bytes,
+) {
+    // 添加到时移
+    if let Some(ts) = timeshift {
+        use flux_media_core::timeshift::{Segment, SegmentFormat, SegmentMetadata};
+        use chrono::Utc;
+
+        let segment = Segment {
+            sequence,
+            start_time: Utc::now(),
+            duration: 0.04,
+            data: data.clone(),
+            metadata: SegmentMetadata {
+                format: SegmentFormat::Raw,
+                has_keyframe: false,
+                file_path: None,
+                size: data.len() as u64,
+            },
+        };
+
+        let _ = ts.add_segment(stream_id.as_str(), segment).await;
+    }
+
+    // 更新包计数
+    let mut streams = streams.write().await;
+    if let Some(info) = streams.get_mut(key) {
+        info.packet_count += 1;
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct StartPullRequest {
+    url: String,
+    stream_name: String,
+}
+
+#[derive(serde::Deserialize)]
+struct StopPullRequest {
+    stream_name: String,
+}
+
+async fn list_pulls(State(state): State<AppState>) -> impl IntoResponse {
+    Json(serde_json::json!({ "pulls": state.pulls.list_pulls().await }))
+}
+
+async fn start_pull(
+    State(state): State<AppState>,
+    Json(req): Json<StartPullRequest>,
+) -> std::result::Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let stream_id = state
+        .pulls
+        .start_pull(&req.stream_name, &req.url)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    Ok(Json(serde_json::json!({
+        "status": "started",
+        "stream_id": stream_id.as_str(),
+    })))
+}
+
+async fn stop_pull(
+    State(state): State<AppState>,
+    Json(req): Json<StopPullRequest>,
+) -> std::result::Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state
+        .pulls
+        .stop_pull(&req.stream_name)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+
+    Ok(Json(serde_json::json!({ "status": "stopped" })))
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
@@ -275,18 +367,50 @@ async fn main() -> anyhow::Result<()> {
         None
     };
 
+    // 推流、拉流与播放共用的流注册表
+    let stream_manager = Arc::new(flux_stream::StreamManager::new(
+        flux_config::StreamingConfig::default(),
+    ));
+
     let state = AppState {
         storage,
         orchestrator,
         streams: Arc::new(RwLock::new(HashMap::new())),
         timeshift,
+        pulls: Arc::new(SrtPullManager::new(stream_manager.clone())),
     };
 
+    if let Some(listen) = &args.srt_listen {
+        let crypto = match &args.srt_passphrase {
+            Some(passphrase) => {
+                let key_length = KeyLength::from_bytes(args.srt_key_length)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid SRT key length: {}", args.srt_key_length))?;
+                Some(SrtCryptoConfig::new(passphrase.clone())?.with_key_length(key_length))
+            }
+            None => None,
+        };
+        let listener = SrtListener::bind(listen.parse()?).await?;
+        let server = Arc::new(SrtServer::new(
+            stream_manager.clone(),
+            SrtServerConfig {
+                crypto,
+                latency_ms: args.srt_latency_ms,
+            },
+        ));
+        tokio::spawn(async move {
+            if let Err(e) = server.serve(listener).await {
+                tracing::error!(target: "srt", "SRT server error: {}", e);
+            }
+        });
+    }
+
     info!(target: "srt", "SRT Media Server ready");
 
     let app = Router::new()
         .route("/health", get(health))
         .route("/api/v1/srt/streams", get(list_streams).post(start_stream))
+        .route("/api/v1/srt/pulls", get(list_pulls).post(start_pull))
+        .route("/api/v1/srt/pulls/stop", post(stop_pull))
         .with_state(state);
 
     let addr = args.http_bind;
@@ -48,6 +48,8 @@ pub struct SrtHeader {
     pub packet_seq_number: u32,
     pub timestamp: u32,
     pub dest_socket_id: u32,
+    /// 数据包加密密钥标志（0 未加密，1 偶数密钥，2 奇数密钥）
+    pub key_flags: u8,
 }
 
 /// SRT 数据包
@@ -86,8 +88,9 @@ impl SrtHeader {
         // Byte 8-11: Destination Socket ID
         let dest_socket_id = cursor.get_u32();
         
-        // Byte 12-15: Reserved (skip)
-        let _ = cursor.get_u32();
+        // Byte 12-15: Reserved，数据包第 27-28 位为 KK 加密标志
+        let word3 = cursor.get_u32();
+        let key_flags = if is_control { 0 } else { ((word3 >> 27) & 0x03) as u8 };
 
         Ok((
             Self {
@@ -95,6 +98,7 @@ impl SrtHeader {
                 packet_seq_number,
                 timestamp,
                 dest_socket_id,
+                key_flags,
             },
             16,
         ))
@@ -110,7 +114,7 @@ impl SrtHeader {
         buf.put_u32(word0);
         buf.put_u32(self.timestamp);
         buf.put_u32(self.dest_socket_id);
-        buf.put_u32(0); // Reserved
+        buf.put_u32(((self.key_flags & 0x03) as u32) << 27); // Reserved + KK
     }
 }
 
@@ -247,14 +251,17 @@ mod tests {
                 packet_seq_number: 42,
                 timestamp: 1000,
                 dest_socket_id: 0x12345678,
+                key_flags: 0b10,
             },
             payload: Bytes::from("test"),
         };
 
         let serialized = packet.serialize();
+        assert_eq!(serialized[12], 0x10);
         let parsed = SrtDataPacket::parse(&serialized).unwrap();
 
         assert_eq!(parsed.header.packet_seq_number, 42);
+        assert_eq!(parsed.header.key_flags, 0b10);
         assert_eq!(parsed.header.timestamp, 1000);
         assert_eq!(parsed.payload.as_ref(), b"test");
     }
@@ -0,0 +1,388 @@
+//! SRT Caller 拉流：主动连接远端 Listener，把 TS 注册为 flux-stream 流 `srt/{name}`
+//!
+//! 连接失败或断流后按指数退避重连；流在拉流启动时注册，停止时注销。
+
+use anyhow::{anyhow, Result};
+use flux_media_core::types::StreamId;
+use flux_stream::StreamManager;
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::caller::SrtCaller;
+use crate::crypto::{KeyLength, SrtCryptoConfig};
+use crate::ingest::TsIngest;
+use crate::socket::SrtSocket;
+
+/// 超过该时间未收到数据视为断流并重连
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 解析后的拉流地址：`srt://host:port?streamid=..&passphrase=..&pbkeylen=16&latency=200`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrtUrl {
+    pub host: String,
+    pub port: u16,
+    pub stream_id: Option<String>,
+    pub passphrase: Option<String>,
+    pub key_length: Option<KeyLength>,
+    pub latency_ms: Option<u32>,
+}
+
+impl SrtUrl {
+    pub fn parse(url: &str) -> Result<Self> {
+        let rest = url
+            .strip_prefix("srt://")
+            .ok_or_else(|| anyhow!("Invalid SRT URL: {}", url))?;
+        let (authority, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let authority = authority.trim_end_matches('/');
+        let (host, port) = authority
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("SRT URL missing port: {}", url))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(anyhow!("SRT URL missing host: {}", url));
+        }
+
+        let mut parsed = Self {
+            host: host.to_string(),
+            port: port.parse().map_err(|_| anyhow!("Invalid SRT port: {}", port))?,
+            stream_id: None,
+            passphrase: None,
+            key_length: None,
+            latency_ms: None,
+        };
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode_str(value).decode_utf8()?.into_owned();
+            match key {
+                "streamid" => parsed.stream_id = Some(value),
+                "passphrase" => parsed.passphrase = Some(value),
+                "pbkeylen" => {
+                    let key_length = value
+                        .parse()
+                        .ok()
+                        .and_then(KeyLength::from_bytes)
+                        .ok_or_else(|| anyhow!("Invalid pbkeylen: {}", value))?;
+                    parsed.key_length = Some(key_length);
+                }
+                "latency" => {
+                    parsed.latency_ms = Some(value.parse().map_err(|_| anyhow!("Invalid latency: {}", value))?)
+                }
+                _ => {}
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// 加密配置（设置了 passphrase 时）
+    pub fn crypto(&self) -> Result<Option<SrtCryptoConfig>> {
+        let Some(passphrase) = &self.passphrase else {
+            return Ok(None);
+        };
+        let mut config = SrtCryptoConfig::new(passphrase.clone())?;
+        if let Some(key_length) = self.key_length {
+            config = config.with_key_length(key_length);
+        }
+        Ok(Some(config))
+    }
+
+    /// 不含口令的地址，用于日志与 API 输出
+    pub fn redacted(&self) -> String {
+        let mut url = format!("srt://{}:{}", self.host, self.port);
+        if let Some(stream_id) = &self.stream_id {
+            url.push_str(&format!("?streamid={}", stream_id));
+        }
+        url
+    }
+}
+
+/// 拉流状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PullState {
+    Connecting,
+    Connected,
+    /// 连接失败或断流，等待重连
+    Reconnecting,
+}
+
+/// 拉流信息
+#[derive(Debug, Clone, Serialize)]
+pub struct PullInfo {
+    pub stream_id: String,
+    /// 不含口令的源地址
+    pub url: String,
+    pub state: PullState,
+    /// 连续失败次数（连接成功后清零）
+    pub reconnect_attempts: u32,
+    pub last_error: Option<String>,
+    pub packet_count: u64,
+    pub start_time: chrono::DateTime<chrono::Utc>,
+}
+
+/// 运行中的拉流任务
+struct PullTask {
+    handle: JoinHandle<()>,
+    /// 当前连接，停止时主动关闭
+    socket: Arc<Mutex<Option<Arc<SrtSocket>>>>,
+}
+
+type PullMap = Arc<RwLock<HashMap<String, PullInfo>>>;
+
+/// SRT 拉流管理器
+pub struct SrtPullManager {
+    stream_manager: Arc<StreamManager>,
+    pulls: PullMap,
+    tasks: Mutex<HashMap<String, PullTask>>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl SrtPullManager {
+    pub fn new(stream_manager: Arc<StreamManager>) -> Self {
+        Self {
+            stream_manager,
+            pulls: Arc::new(RwLock::new(HashMap::new())),
+            tasks: Mutex::new(HashMap::new()),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+
+    /// 重连退避：从 initial 开始每次翻倍，不超过 max
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// 启动拉流，返回注册的流 ID
+    pub async fn start_pull(&self, name: &str, url: &str) -> Result<StreamId> {
+        let url = SrtUrl::parse(url)?;
+        let crypto = url.crypto()?;
+        let stream_id = StreamId::new("srt", name);
+
+        let mut tasks = self.tasks.lock().await;
+        if tasks.contains_key(name) {
+            return Err(anyhow!("Pull already exists: {}", name));
+        }
+        let ingest = TsIngest::register(self.stream_manager.clone(), stream_id.clone()).await?;
+
+        self.pulls.write().await.insert(
+            name.to_string(),
+            PullInfo {
+                stream_id: stream_id.to_string(),
+                url: url.redacted(),
+                state: PullState::Connecting,
+                reconnect_attempts: 0,
+                last_error: None,
+                packet_count: 0,
+                start_time: chrono::Utc::now(),
+            },
+        );
+        info!(target: "srt_pull", stream_id = %stream_id, url = %url.redacted(), "Starting SRT pull");
+
+        let socket = Arc::new(Mutex::new(None));
+        let worker = PullWorker {
+            name: name.to_string(),
+            url,
+            crypto,
+            pulls: self.pulls.clone(),
+            socket: socket.clone(),
+            initial_backoff: self.initial_backoff,
+            max_backoff: self.max_backoff,
+        };
+        let handle = tokio::spawn(worker.run(ingest));
+        tasks.insert(name.to_string(), PullTask { handle, socket });
+
+        Ok(stream_id)
+    }
+
+    /// 停止拉流并注销流
+    pub async fn stop_pull(&self, name: &str) -> Result<()> {
+        let task = self
+            .tasks
+            .lock()
+            .await
+            .remove(name)
+            .ok_or_else(|| anyhow!("Pull not found: {}", name))?;
+        task.handle.abort();
+        if let Some(socket) = task.socket.lock().await.take() {
+            let _ = socket.close().await;
+        }
+
+        self.pulls.write().await.remove(name);
+        let stream_id = StreamId::new("srt", name);
+        if let Err(e) = self.stream_manager.unregister_stream(&stream_id).await {
+            warn!(target: "srt_pull", stream_id = %stream_id, "Failed to unregister stream: {}", e);
+        }
+        info!(target: "srt_pull", stream_id = %stream_id, "SRT pull stopped");
+        Ok(())
+    }
+
+    pub async fn get_pull(&self, name: &str) -> Option<PullInfo> {
+        self.pulls.read().await.get(name).cloned()
+    }
+
+    pub async fn list_pulls(&self) -> Vec<PullInfo> {
+        self.pulls.read().await.values().cloned().collect()
+    }
+}
+
+/// 单个拉流的连接/重连循环
+struct PullWorker {
+    name: String,
+    url: SrtUrl,
+    crypto: Option<SrtCryptoConfig>,
+    pulls: PullMap,
+    socket: Arc<Mutex<Option<Arc<SrtSocket>>>>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl PullWorker {
+    async fn run(self, mut ingest: TsIngest) {
+        loop {
+            self.update(|info| info.state = PullState::Connecting).await;
+
+            let error = match self.connect().await {
+                Ok(socket) => {
+                    info!(target: "srt_pull", stream_id = %ingest.stream_id(), "SRT pull connected");
+                    self.update(|info| {
+                        info.state = PullState::Connected;
+                        info.reconnect_attempts = 0;
+                        info.last_error = None;
+                    })
+                    .await;
+                    *self.socket.lock().await = Some(socket.clone());
+
+                    ingest.reset();
+                    let error = self.receive(&socket, &mut ingest).await;
+                    self.socket.lock().await.take();
+                    let _ = socket.close().await;
+                    error
+                }
+                Err(e) => e,
+            };
+
+            let mut attempts = 0;
+            self.update(|info| {
+                info.state = PullState::Reconnecting;
+                info.reconnect_attempts += 1;
+                info.last_error = Some(error.to_string());
+                attempts = info.reconnect_attempts;
+            })
+            .await;
+            let delay = self.backoff(attempts);
+            warn!(
+                target: "srt_pull",
+                stream_id = %ingest.stream_id(),
+                "SRT pull error: {}, retry #{} in {:?}",
+                error,
+                attempts,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn connect(&self) -> Result<Arc<SrtSocket>> {
+        let addr = tokio::net::lookup_host((self.url.host.as_str(), self.url.port))
+            .await?
+            .next()
+            .ok_or_else(|| anyhow!("Failed to resolve {}", self.url.host))?;
+
+        let mut caller = SrtCaller::new(addr).await?;
+        if let Some(stream_id) = &self.url.stream_id {
+            caller = caller.with_stream_id(stream_id.clone());
+        }
+        if let Some(crypto) = &self.crypto {
+            caller = caller.with_crypto(crypto.clone());
+        }
+        if let Some(latency_ms) = self.url.latency_ms {
+            caller = caller.with_latency(latency_ms);
+        }
+        caller.connect().await
+    }
+
+    /// 接收直至出错，返回断开原因
+    async fn receive(&self, socket: &SrtSocket, ingest: &mut TsIngest) -> anyhow::Error {
+        loop {
+            let data = match tokio::time::timeout(RECEIVE_TIMEOUT, socket.recv()).await {
+                Ok(Ok((data, _))) => data,
+                Ok(Err(e)) => return e,
+                Err(_) => return anyhow!("No data received for {:?}", RECEIVE_TIMEOUT),
+            };
+            if let Err(e) = ingest.push(&data).await {
+                return e;
+            }
+            self.update(|info| info.packet_count += 1).await;
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff)
+    }
+
+    async fn update(&self, f: impl FnOnce(&mut PullInfo)) {
+        if let Some(info) = self.pulls.write().await.get_mut(&self.name) {
+            f(info);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let url = SrtUrl::parse(
+            "srt://encoder.local:9000?streamid=%23%21%3A%3Ar%3Dlive%2Fcam1%2Cm%3Drequest&passphrase=contribution-link&pbkeylen=32&latency=200",
+        )
+        .unwrap();
+        assert_eq!(url.host, "encoder.local");
+        assert_eq!(url.port, 9000);
+        assert_eq!(url.stream_id.as_deref(), Some("#!::r=live/cam1,m=request"));
+        assert_eq!(url.key_length, Some(KeyLength::Aes256));
+        assert_eq!(url.latency_ms, Some(200));
+        assert!(url.crypto().unwrap().is_some());
+        assert_eq!(url.redacted(), "srt://encoder.local:9000?streamid=#!::r=live/cam1,m=request");
+
+        let url = SrtUrl::parse("srt://[::1]:9000").unwrap();
+        assert_eq!((url.host.as_str(), url.port), ("::1", 9000));
+        assert!(url.crypto().unwrap().is_none());
+
+        assert!(SrtUrl::parse("udp://127.0.0.1:9000").is_err());
+        assert!(SrtUrl::parse("srt://127.0.0.1").is_err());
+        assert!(SrtUrl::parse("srt://127.0.0.1:9000?pbkeylen=20").is_err());
+        assert!(SrtUrl::parse("srt://127.0.0.1:9000?passphrase=short").unwrap().crypto().is_err());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let worker = PullWorker {
+            name: "cam1".to_string(),
+            url: SrtUrl::parse("srt://127.0.0.1:9000").unwrap(),
+            crypto: None,
+            pulls: Arc::new(RwLock::new(HashMap::new())),
+            socket: Arc::new(Mutex::new(None)),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        };
+        assert_eq!(worker.backoff(1), Duration::from_secs(1));
+        assert_eq!(worker.backoff(3), Duration::from_secs(4));
+        assert_eq!(worker.backoff(10), Duration::from_secs(30));
+        assert_eq!(worker.backoff(u32::MAX), Duration::from_secs(30));
+    }
+}
@@ -4,11 +4,14 @@ use std::net::SocketAddr;
 use tokio::net::UdpSocket;
 use tracing::{debug, info};
 
+use crate::crypto::CryptoContext;
+
 /// SRT 发送器（简化实现）
 pub struct SrtSender {
     socket: UdpSocket,
     dest_addr: SocketAddr,
     sequence: u32,
+    crypto: Option<CryptoContext>,
 }
 
 impl SrtSender {
@@ -22,12 +25,19 @@ impl SrtSender {
             socket,
             dest_addr,
             sequence: 0,
+            crypto: None,
         })
     }
 
+    /// 使用已分发的密钥加密负载（无控制通道，不做密钥刷新）
+    pub fn with_crypto(mut self, crypto: CryptoContext) -> Self {
+        self.crypto = Some(crypto);
+        self
+    }
+
     /// 发送数据
     pub async fn send(&mut self, data: &[u8], timestamp: u32) -> Result<()> {
-        let packet = self.build_data_packet(data, timestamp);
+        let packet = self.build_data_packet(data, timestamp)?;
         
         self.socket.send_to(&packet, self.dest_addr).await?;
         
@@ -44,11 +54,16 @@ impl SrtSender {
     }
 
     /// 构建 SRT 数据包
-    fn build_data_packet(&self, data: &[u8], timestamp: u32) -> Vec<u8> {
+    fn build_data_packet(&self, data: &[u8], timestamp: u32) -> Result<Vec<u8>> {
         let mut packet = Vec::with_capacity(16 + data.len());
-        
-        // Flags (data packet, no encryption)
-        packet.extend_from_slice(&0u32.to_be_bytes());
+
+        let (key_flags, payload) = match &self.crypto {
+            Some(crypto) => crypto.encrypt_with_current_key(self.sequence, data)?,
+            None => (0, Bytes::copy_from_slice(data)),
+        };
+
+        // Flags (data packet, KK encryption flags at bits 27-28)
+        packet.extend_from_slice(&((key_flags as u32) << 27).to_be_bytes());
         
         // Timestamp
         packet.extend_from_slice(&timestamp.to_be_bytes());
@@ -60,9 +75,9 @@ impl SrtSender {
         packet.extend_from_slice(&self.sequence.to_be_bytes());
         
         // Payload
-        packet.extend_from_slice(data);
-        
-        packet
+        packet.extend_from_slice(&payload);
+
+        Ok(packet)
     }
 }
 
@@ -78,7 +93,7 @@ mod tests {
         
         if let Ok(mut sender) = sender_result {
             sender.sequence = 42; // 设置测试序列号
-            let packet = sender.build_data_packet(b"test", 100);
+            let packet = sender.build_data_packet(b"test", 100).unwrap();
             
             assert_eq!(packet.len(), 16 + 4);
             assert_eq!(&packet[16..], b"test");
@@ -92,4 +107,19 @@ mod tests {
             assert_eq!(seq, 42);
         }
     }
+
+    #[tokio::test]
+    async fn test_build_encrypted_packet() {
+        let config = crate::crypto::SrtCryptoConfig::new("sender passphrase").unwrap();
+        let crypto = CryptoContext::new(config).unwrap();
+        let sender = SrtSender::new("127.0.0.1:9000".parse().unwrap())
+            .await
+            .unwrap()
+            .with_crypto(crypto.clone());
+
+        let packet = sender.build_data_packet(b"test", 100).unwrap();
+        assert_eq!(packet[0] >> 3, crate::crypto::KEY_FLAG_EVEN);
+        assert_ne!(&packet[16..], b"test");
+        assert_eq!(crypto.decrypt(crate::crypto::KEY_FLAG_EVEN, 0, &packet[16..]).unwrap().as_ref(), b"test");
+    }
 }
@@ -0,0 +1,201 @@
+//! SRT 服务器：按 streamid 区分推流（publish）与拉流（request）
+//!
+//! - `#!::r=live/cam1,m=publish`：TS 推流注册为 flux-stream 流 `srt/live/cam1`
+//! - `#!::r=rtmp/live/cam1,m=request`：把任意 flux-stream 流以 TS 发送给播放器；
+//!   资源名不带协议前缀时按 `srt/` 查找
+
+use anyhow::Result;
+use async_trait::async_trait;
+use flux_media_core::types::StreamId;
+use flux_stream::StreamManager;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use crate::crypto::SrtCryptoConfig;
+use crate::egress;
+use crate::handshake::RejectReason;
+use crate::ingest::TsIngest;
+use crate::listener::{SrtAccessControl, SrtListener};
+use crate::socket::SrtSocket;
+use crate::stream_id::{SrtAccessMode, SrtStreamId};
+
+/// SRT 服务器配置
+#[derive(Clone)]
+pub struct SrtServerConfig {
+    /// 配置后只接受加密连接
+    pub crypto: Option<SrtCryptoConfig>,
+    /// 本端 TSBPD 延迟，与对端协商取较大值
+    pub latency_ms: u32,
+}
+
+impl Default for SrtServerConfig {
+    fn default() -> Self {
+        Self {
+            crypto: None,
+            latency_ms: 120,
+        }
+    }
+}
+
+/// SRT 服务器
+pub struct SrtServer {
+    stream_manager: Arc<StreamManager>,
+    config: SrtServerConfig,
+}
+
+impl SrtServer {
+    pub fn new(stream_manager: Arc<StreamManager>, config: SrtServerConfig) -> Self {
+        Self { stream_manager, config }
+    }
+
+    /// 在已绑定的 Listener 上接受连接
+    pub async fn serve(self: Arc<Self>, listener: SrtListener) -> Result<()> {
+        let mut listener = listener
+            .with_latency(self.config.latency_ms)
+            .with_access_control(Arc::new(StreamAccessControl {
+                stream_manager: self.stream_manager.clone(),
+            }));
+        if let Some(crypto) = self.config.crypto.clone() {
+            listener = listener.with_crypto(crypto);
+        }
+
+        info!(target: "srt_server", "SRT server listening on {}", listener.local_addr()?);
+        let mut accept_rx = listener.start().await?;
+        while let Some(socket) = accept_rx.recv().await {
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(socket).await {
+                    debug!(target: "srt_server", "SRT connection closed: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    async fn handle_connection(&self, socket: Arc<SrtSocket>) -> Result<()> {
+        let stream_id = socket.stream_id().await.unwrap_or_default();
+        match stream_id.mode {
+            SrtAccessMode::Publish => self.ingest(socket, &stream_id).await,
+            _ => {
+                let target = resolve_stream(&self.stream_manager, &stream_id.resource)
+                    .await
+                    .ok_or_else(|| anyhow::anyhow!("Stream not found: {}", stream_id.resource))?;
+                egress::serve_stream(self.stream_manager.clone(), target, socket).await
+            }
+        }
+    }
+
+    /// 接收推流直至断开，然后注销流
+    async fn ingest(&self, socket: Arc<SrtSocket>, stream_id: &SrtStreamId) -> Result<()> {
+        let mut ingest = match TsIngest::register(self.stream_manager.clone(), publish_stream_id(stream_id)).await {
+            Ok(ingest) => ingest,
+            Err(e) => {
+                let _ = socket.close().await;
+                return Err(e);
+            }
+        };
+        info!(
+            target: "srt_server",
+            stream_id = %ingest.stream_id(),
+            encrypted = socket.is_encrypted(),
+            "SRT publisher started"
+        );
+
+        let result = loop {
+            match socket.recv().await {
+                Ok((data, _)) => {
+                    if let Err(e) = ingest.push(&data).await {
+                        break Err(e);
+                    }
+                }
+                Err(_) => break Ok(()),
+            }
+        };
+
+        info!(target: "srt_server", stream_id = %ingest.stream_id(), "SRT publisher stopped");
+        ingest.unregister().await?;
+        result
+    }
+}
+
+/// 推流注册到 flux-stream 的流 ID
+fn publish_stream_id(stream_id: &SrtStreamId) -> StreamId {
+    StreamId::new("srt", &stream_id.resource)
+}
+
+/// 拉流资源名 → 已注册的流：先按完整流 ID 查找，再按 SRT 推流查找
+async fn resolve_stream(stream_manager: &StreamManager, resource: &str) -> Option<StreamId> {
+    let candidates = [
+        StreamId::from_string(resource.to_string()),
+        StreamId::new("srt", resource),
+    ];
+    for candidate in candidates {
+        if stream_manager.get_metadata(&candidate).await.is_some() {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+/// 握手阶段按 streamid 校验：推流资源不可重复，拉流资源必须存在
+struct StreamAccessControl {
+    stream_manager: Arc<StreamManager>,
+}
+
+#[async_trait]
+impl SrtAccessControl for StreamAccessControl {
+    async fn authorize(&self, peer: SocketAddr, stream_id: &SrtStreamId) -> Result<(), RejectReason> {
+        if stream_id.resource.is_empty() {
+            return Err(RejectReason::BadRequest);
+        }
+
+        match stream_id.mode {
+            SrtAccessMode::Publish => {
+                if self.stream_manager.get_metadata(&publish_stream_id(stream_id)).await.is_some() {
+                    warn!(target: "srt_server", "Duplicate publisher {} for {}", peer, stream_id.resource);
+                    return Err(RejectReason::Conflict);
+                }
+                Ok(())
+            }
+            SrtAccessMode::Request => match resolve_stream(&self.stream_manager, &stream_id.resource).await {
+                Some(_) => Ok(()),
+                None => Err(RejectReason::NotFound),
+            },
+            SrtAccessMode::Bidirectional => Err(RejectReason::BadMode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flux_config::StreamingConfig;
+
+    #[tokio::test]
+    async fn test_access_control() {
+        let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+        let access = StreamAccessControl {
+            stream_manager: stream_manager.clone(),
+        };
+        let peer: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+
+        let ingest = TsIngest::register(stream_manager.clone(), StreamId::new("srt", "live/cam1"))
+            .await
+            .unwrap();
+
+        let check = |value: &str| SrtStreamId::parse(value).unwrap();
+        assert_eq!(access.authorize(peer, &check("#!::r=live/cam1,m=publish")).await, Err(RejectReason::Conflict));
+        assert_eq!(access.authorize(peer, &check("#!::r=live/cam2,m=publish")).await, Ok(()));
+        assert_eq!(access.authorize(peer, &check("#!::r=live/cam1,m=request")).await, Ok(()));
+        assert_eq!(access.authorize(peer, &check("srt/live/cam1")).await, Ok(()));
+        assert_eq!(access.authorize(peer, &check("rtmp/live/cam1")).await, Err(RejectReason::NotFound));
+        assert_eq!(access.authorize(peer, &check("#!::r=,m=publish")).await, Err(RejectReason::BadRequest));
+        assert_eq!(
+            access.authorize(peer, &check("#!::r=live/cam1,m=bidirectional")).await,
+            Err(RejectReason::BadMode)
+        );
+
+        ingest.unregister().await.unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use bytes::Bytes;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use tokio::net::UdpSocket;
 use tokio::sync::{mpsc, RwLock};
@@ -9,8 +10,10 @@ use tracing::{debug, error, info, warn};
 
 use crate::ack::{AckPacket, NakPacket};
 use crate::buffer::{ReceiveBuffer, SendBuffer};
-use crate::handshake::{HandshakePacket, HandshakeState};
+use crate::crypto::CryptoContext;
+use crate::handshake::{HandshakePacket, HandshakeState, SRT_CMD_KMREQ, SRT_CMD_KMRSP};
 use crate::packet::{ControlType, SrtControlPacket, SrtDataPacket, SrtHeader};
+use crate::stream_id::SrtStreamId;
 
 /// SRT Socket 配置
 #[derive(Debug, Clone)]
@@ -57,6 +60,14 @@ pub struct SrtSocket {
     // ARQ 支持
     send_buffer: Arc<RwLock<SendBuffer>>,
     receive_buffer: Arc<RwLock<ReceiveBuffer>>,
+    // 加密：发送与接收方向各自刷新密钥
+    send_crypto: std::sync::Mutex<Option<CryptoContext>>,
+    recv_crypto: std::sync::Mutex<Option<CryptoContext>>,
+    stream_id: RwLock<Option<SrtStreamId>>,
+    /// 握手协商后的 TSBPD 延迟（毫秒）
+    latency_ms: AtomicU32,
+    /// 最近一次收到对端任意包的时间（含 KeepAlive）
+    last_received: std::sync::Mutex<std::time::Instant>,
 }
 
 impl SrtSocket {
@@ -88,6 +99,11 @@ impl SrtSocket {
                 1,
                 config.max_flow_window_size as usize,
             ))),
+            send_crypto: std::sync::Mutex::new(None),
+            recv_crypto: std::sync::Mutex::new(None),
+            stream_id: RwLock::new(None),
+            latency_ms: AtomicU32::new(config.latency_ms),
+            last_received: std::sync::Mutex::new(std::time::Instant::now()),
         })
     }
 
@@ -107,6 +123,61 @@ impl SrtSocket {
         self.local_socket_id
     }
 
+    /// 本地绑定地址
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// 对端地址
+    pub async fn remote_addr(&self) -> Option<SocketAddr> {
+        *self.remote_addr.read().await
+    }
+
+    /// 握手时对端携带的 streamid
+    pub async fn stream_id(&self) -> Option<SrtStreamId> {
+        self.stream_id.read().await.clone()
+    }
+
+    pub(crate) async fn set_stream_id(&self, stream_id: SrtStreamId) {
+        *self.stream_id.write().await = Some(stream_id);
+    }
+
+    /// 协商后的延迟
+    pub fn latency(&self) -> Duration {
+        Duration::from_millis(self.latency_ms.load(Ordering::Relaxed) as u64)
+    }
+
+    pub(crate) fn set_latency(&self, latency_ms: u32) {
+        self.latency_ms.store(latency_ms, Ordering::Relaxed);
+    }
+
+    /// 距最近一次收到对端数据的时间（对端静默断开检测）
+    pub fn idle_time(&self) -> Duration {
+        self.last_received.lock().unwrap().elapsed()
+    }
+
+    /// 关闭连接：通知对端并停止 KeepAlive
+    pub async fn close(&self) -> Result<()> {
+        let previous = std::mem::replace(&mut *self.state.write().await, ConnectionState::Closed);
+        if previous == ConnectionState::Connected {
+            if let Some(addr) = *self.remote_addr.read().await {
+                self.send_control(ControlType::Shutdown, Bytes::new(), addr).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 是否已协商加密
+    pub fn is_encrypted(&self) -> bool {
+        self.recv_crypto.lock().unwrap().is_some()
+    }
+
+    /// 安装握手协商出的密钥
+    pub(crate) fn set_crypto(&self, crypto: CryptoContext) {
+        *self.recv_crypto.lock().unwrap() = Some(crypto.clone());
+        *self.send_crypto.lock().unwrap() = Some(crypto);
+    }
+
     /// 获取连接状态
     pub async fn state(&self) -> ConnectionState {
         *self.state.read().await
@@ -125,15 +196,26 @@ impl SrtSocket {
         let current_seq = *seq;
         
         let payload = Bytes::copy_from_slice(data);
-        
+
+        // 发送缓冲区保留明文，重传时用当前密钥重新加密
+        let (key_flags, wire_payload, announcement) = match self.send_crypto.lock().unwrap().as_mut() {
+            Some(crypto) => crypto.encrypt(current_seq, data)?,
+            None => (0, payload.clone(), None),
+        };
+        if let Some(key_material) = announcement {
+            self.send_control_packet(ControlType::UserDefined, SRT_CMD_KMREQ as u32, key_material, remote_addr)
+                .await?;
+        }
+
         let packet = SrtDataPacket {
             header: SrtHeader {
                 is_control: false,
                 packet_seq_number: current_seq,
                 timestamp: Self::get_timestamp(),
                 dest_socket_id: remote_socket_id.unwrap_or(0),
+                key_flags,
             },
-            payload: payload.clone(),
+            payload: wire_payload,
         };
 
         *seq = seq.wrapping_add(1);
@@ -162,18 +244,30 @@ impl SrtSocket {
         control_type: ControlType,
         payload: Bytes,
         addr: SocketAddr,
+    ) -> Result<()> {
+        self.send_control_packet(control_type, 0, payload, addr).await
+    }
+
+    /// 发送带 type-specific 信息的控制包（如 UserDefined 的 KMREQ/KMRSP）
+    async fn send_control_packet(
+        &self,
+        control_type: ControlType,
+        type_specific_info: u32,
+        payload: Bytes,
+        addr: SocketAddr,
     ) -> Result<()> {
         let remote_socket_id = *self.remote_socket_id.read().await;
-        
+
         let packet = SrtControlPacket {
             header: SrtHeader {
                 is_control: true,
                 packet_seq_number: 0,
                 timestamp: Self::get_timestamp(),
                 dest_socket_id: remote_socket_id.unwrap_or(0),
+                key_flags: 0,
             },
             control_type,
-            type_specific_info: 0,
+            type_specific_info,
             payload,
         };
 
@@ -211,6 +305,7 @@ impl SrtSocket {
             if len < 16 {
                 continue;
             }
+            *self.last_received.lock().unwrap() = std::time::Instant::now();
 
             // 解析包头判断类型
             let (header, _) = SrtHeader::parse(&buf[..len])
@@ -219,13 +314,41 @@ impl SrtSocket {
             if header.is_control {
                 // 控制包，内部处理
                 self.handle_control_packet(&buf[..len], addr).await?;
+                if self.state().await == ConnectionState::Closed {
+                    return Err(anyhow::anyhow!("Connection closed by peer"));
+                }
                 continue;
             }
 
             // 数据包
             let packet = SrtDataPacket::parse(&buf[..len])
                 .map_err(|e| anyhow::anyhow!("Failed to parse data packet: {}", e))?;
-            return Ok((packet.payload, addr));
+
+            let payload = {
+                let crypto = self.recv_crypto.lock().unwrap();
+                match (crypto.as_ref(), packet.header.key_flags) {
+                    (None, 0) => packet.payload,
+                    (Some(crypto), key_flags) if key_flags != 0 => {
+                        match crypto.decrypt(key_flags, packet.header.packet_seq_number, &packet.payload) {
+                            Ok(payload) => payload,
+                            Err(e) => {
+                                warn!(target: "srt_socket", "Failed to decrypt packet: {}", e);
+                                continue;
+                            }
+                        }
+                    }
+                    _ => {
+                        warn!(
+                            target: "srt_socket",
+                            "Dropped packet with mismatched encryption: seq={}, kk={}",
+                            packet.header.packet_seq_number,
+                            packet.header.key_flags
+                        );
+                        continue;
+                    }
+                }
+            };
+            return Ok((payload, addr));
         }
     }
 
@@ -251,6 +374,9 @@ impl SrtSocket {
                 info!(target: "srt_socket", "Received Shutdown from {}", addr);
                 *self.state.write().await = ConnectionState::Closed;
             }
+            ControlType::UserDefined if packet.type_specific_info == SRT_CMD_KMREQ as u32 => {
+                self.handle_key_material(packet.payload, addr).await?;
+            }
             _ => {
                 debug!(
                     target: "srt_socket",
@@ -279,6 +405,26 @@ impl SrtSocket {
         Ok(())
     }
 
+    /// 处理对端密钥刷新（KMREQ），安装后回复 KMRSP
+    async fn handle_key_material(&self, key_material: Bytes, addr: SocketAddr) -> Result<()> {
+        let applied = match self.recv_crypto.lock().unwrap().as_mut() {
+            Some(crypto) => crypto.apply_key_material(&key_material),
+            None => Err(anyhow::anyhow!("Connection is not encrypted")),
+        };
+
+        match applied {
+            Ok(()) => {
+                debug!(target: "srt_socket", "Installed refreshed key material from {}", addr);
+                self.send_control_packet(ControlType::UserDefined, SRT_CMD_KMRSP as u32, key_material, addr)
+                    .await
+            }
+            Err(e) => {
+                warn!(target: "srt_socket", "Rejected key material from {}: {}", addr, e);
+                Ok(())
+            }
+        }
+    }
+
     /// 处理 ACK 包
     async fn handle_ack(&self, payload: &[u8]) -> Result<()> {
         let ack = AckPacket::parse(payload)
@@ -328,14 +474,19 @@ impl SrtSocket {
 
         for &seq in sequences {
             if let Some(item) = send_buf.get(seq) {
+                let (key_flags, payload) = match self.send_crypto.lock().unwrap().as_ref() {
+                    Some(crypto) => crypto.encrypt_with_current_key(seq, &item.data)?,
+                    None => (0, item.data.clone()),
+                };
                 let packet = SrtDataPacket {
                     header: SrtHeader {
                         is_control: false,
                         packet_seq_number: seq,
                         timestamp: Self::get_timestamp(),
                         dest_socket_id: remote_socket_id.unwrap_or(0),
+                        key_flags,
                     },
-                    payload: item.data.clone(),
+                    payload,
                 };
 
                 let serialized = packet.serialize();
@@ -0,0 +1,138 @@
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// streamid 结构化格式前缀（SRT Access Control 规范）
+const STRUCTURED_PREFIX: &str = "#!::";
+
+/// 访问模式（m=）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SrtAccessMode {
+    /// 拉流播放
+    #[default]
+    Request,
+    /// 推流
+    Publish,
+    Bidirectional,
+}
+
+impl SrtAccessMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Request => "request",
+            Self::Publish => "publish",
+            Self::Bidirectional => "bidirectional",
+        }
+    }
+}
+
+/// 解析后的 streamid，如 `#!::r=live/cam1,m=publish,u=alice`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SrtStreamId {
+    /// 资源名（r=），通常为 app/stream
+    pub resource: String,
+    pub mode: SrtAccessMode,
+    /// 用户名（u=）
+    pub user: Option<String>,
+    /// 会话 ID（s=）
+    pub session: Option<String>,
+    /// 其他键值
+    pub params: BTreeMap<String, String>,
+}
+
+impl SrtStreamId {
+    /// 解析 streamid；非结构化格式整体作为资源名，模式为 request
+    pub fn parse(value: &str) -> Result<Self> {
+        let Some(body) = value.strip_prefix(STRUCTURED_PREFIX) else {
+            return Ok(Self {
+                resource: value.to_string(),
+                ..Default::default()
+            });
+        };
+
+        let mut stream_id = Self::default();
+        for pair in body.split(',').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid streamid entry: {}", pair))?;
+
+            match key {
+                "r" => stream_id.resource = value.to_string(),
+                "m" => {
+                    stream_id.mode = match value {
+                        "request" => SrtAccessMode::Request,
+                        "publish" => SrtAccessMode::Publish,
+                        "bidirectional" => SrtAccessMode::Bidirectional,
+                        other => return Err(anyhow!("Unknown streamid mode: {}", other)),
+                    }
+                }
+                "u" => stream_id.user = Some(value.to_string()),
+                "s" => stream_id.session = Some(value.to_string()),
+                _ => {
+                    stream_id.params.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+
+        Ok(stream_id)
+    }
+
+    /// 推流 streamid
+    pub fn publish(resource: impl Into<String>) -> Self {
+        Self {
+            resource: resource.into(),
+            mode: SrtAccessMode::Publish,
+            ..Default::default()
+        }
+    }
+
+    /// 拉流 streamid
+    pub fn request(resource: impl Into<String>) -> Self {
+        Self {
+            resource: resource.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for SrtStreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}r={},m={}", STRUCTURED_PREFIX, self.resource, self.mode.as_str())?;
+        if let Some(user) = &self.user {
+            write!(f, ",u={}", user)?;
+        }
+        if let Some(session) = &self.session {
+            write!(f, ",s={}", session)?;
+        }
+        for (key, value) in &self.params {
+            write!(f, ",{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_structured() {
+        let stream_id = SrtStreamId::parse("#!::r=live/cam1,m=publish,u=alice,t=stream").unwrap();
+        assert_eq!(stream_id.resource, "live/cam1");
+        assert_eq!(stream_id.mode, SrtAccessMode::Publish);
+        assert_eq!(stream_id.user.as_deref(), Some("alice"));
+        assert_eq!(stream_id.params.get("t").map(String::as_str), Some("stream"));
+        assert_eq!(stream_id.to_string(), "#!::r=live/cam1,m=publish,u=alice,t=stream");
+
+        assert_eq!(SrtStreamId::parse("#!::r=live/cam1").unwrap(), SrtStreamId::request("live/cam1"));
+        assert!(SrtStreamId::parse("#!::r=live/cam1,m=push").is_err());
+        assert!(SrtStreamId::parse("#!::r").is_err());
+    }
+
+    #[test]
+    fn test_parse_plain() {
+        let stream_id = SrtStreamId::parse("live/cam1").unwrap();
+        assert_eq!(stream_id, SrtStreamId::request("live/cam1"));
+        assert_eq!(SrtStreamId::publish("a/b").to_string(), "#!::r=a/b,m=publish");
+    }
+}
@@ -0,0 +1,127 @@
+//! SRT 加密（KMREQ/KMRSP）与 streamid 访问控制测试
+
+use async_trait::async_trait;
+use flux_srt::{
+    caller::SrtCaller,
+    crypto::{KeyLength, SrtCryptoConfig},
+    handshake::RejectReason,
+    listener::{SrtAccessControl, SrtListener},
+    stream_id::{SrtAccessMode, SrtStreamId},
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+struct PublishOnly;
+
+#[async_trait]
+impl SrtAccessControl for PublishOnly {
+    async fn authorize(&self, _peer: SocketAddr, stream_id: &SrtStreamId) -> Result<(), RejectReason> {
+        match stream_id.mode {
+            SrtAccessMode::Publish => Ok(()),
+            _ => Err(RejectReason::BadMode),
+        }
+    }
+}
+
+fn crypto(passphrase: &str) -> SrtCryptoConfig {
+    SrtCryptoConfig::new(passphrase).unwrap()
+}
+
+async fn start_listener(crypto: Option<SrtCryptoConfig>) -> (SocketAddr, tokio::sync::mpsc::Receiver<Arc<flux_srt::socket::SrtSocket>>) {
+    let mut listener = SrtListener::bind("127.0.0.1:0".parse().unwrap())
+        .await
+        .unwrap()
+        .with_access_control(Arc::new(PublishOnly));
+    if let Some(crypto) = crypto {
+        listener = listener.with_crypto(crypto);
+    }
+    let addr = listener.local_addr().unwrap();
+    (addr, listener.start().await.unwrap())
+}
+
+async fn rejection(caller: SrtCaller) -> String {
+    match caller.connect().await {
+        Ok(_) => panic!("connection should be rejected"),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_encrypted_publish() {
+    let config = crypto("contribution-link").with_key_length(KeyLength::Aes256);
+    let (addr, mut accept_rx) = start_listener(Some(config.clone())).await;
+
+    let caller = SrtCaller::new(addr)
+        .await
+        .unwrap()
+        .with_crypto(config)
+        .with_stream_id("#!::r=live/cam1,m=publish");
+    let client = timeout(Duration::from_secs(5), caller.connect()).await.unwrap().unwrap();
+    let server = timeout(Duration::from_secs(5), accept_rx.recv()).await.unwrap().unwrap();
+
+    assert!(client.is_encrypted());
+    assert!(server.is_encrypted());
+    let stream_id = server.stream_id().await.unwrap();
+    assert_eq!(stream_id.resource, "live/cam1");
+    assert_eq!(stream_id.mode, SrtAccessMode::Publish);
+
+    for index in 0..3u8 {
+        client.send_data(&[0x47, index, 1, 2, 3]).await.unwrap();
+        let (data, _) = timeout(Duration::from_secs(2), server.recv()).await.unwrap().unwrap();
+        assert_eq!(data.as_ref(), &[0x47, index, 1, 2, 3]);
+    }
+
+    // 反方向同样加密
+    server.send_data(b"reply").await.unwrap();
+    let (data, _) = timeout(Duration::from_secs(2), client.recv()).await.unwrap().unwrap();
+    assert_eq!(data.as_ref(), b"reply");
+}
+
+#[tokio::test]
+async fn test_wrong_passphrase_rejected() {
+    let (addr, _accept_rx) = start_listener(Some(crypto("contribution-link"))).await;
+
+    let caller = SrtCaller::new(addr)
+        .await
+        .unwrap()
+        .with_crypto(crypto("not-the-passphrase"))
+        .with_stream_id("#!::r=live/cam1,m=publish");
+    let error = rejection(caller).await;
+    assert!(error.contains("BADSECRET"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_encryption_mismatch_rejected() {
+    // Listener 要求加密，Caller 未加密
+    let (addr, _accept_rx) = start_listener(Some(crypto("contribution-link"))).await;
+    let caller = SrtCaller::new(addr)
+        .await
+        .unwrap()
+        .with_stream_id("#!::r=live/cam1,m=publish");
+    let error = rejection(caller).await;
+    assert!(error.contains("UNSECURE"), "{}", error);
+
+    // Listener 未加密，Caller 加密
+    let (addr, _accept_rx) = start_listener(None).await;
+    let caller = SrtCaller::new(addr)
+        .await
+        .unwrap()
+        .with_crypto(crypto("contribution-link"))
+        .with_stream_id("#!::r=live/cam1,m=publish");
+    let error = rejection(caller).await;
+    assert!(error.contains("UNSECURE"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_access_control_rejects_mode() {
+    let (addr, _accept_rx) = start_listener(None).await;
+
+    let caller = SrtCaller::new(addr)
+        .await
+        .unwrap()
+        .with_stream_id("#!::r=live/cam1,m=request");
+    let error = rejection(caller).await;
+    assert!(error.contains("unsupported mode"), "{}", error);
+}
@@ -0,0 +1,292 @@
+//! SRT 服务器（publish/request）与 Caller 拉流测试
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use flux_config::{StreamMode, StreamingConfig};
+use flux_media_core::codec::AacConfig;
+use flux_media_core::playback::ts::{STREAM_TYPE_AAC, STREAM_TYPE_H264};
+use flux_media_core::playback::{TsDemuxer, TsMuxer};
+use flux_media_core::types::StreamId;
+use flux_srt::{
+    caller::SrtCaller,
+    listener::SrtListener,
+    pull::{PullState, SrtPullManager},
+    server::{SrtServer, SrtServerConfig},
+};
+use flux_stream::{MediaPacket, PacketType, Protocol, Stream, StreamManager, StreamMetadata, StreamStatus};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+/// 参数集 + IDR
+const KEYFRAME: &[u8] = &[
+    0, 0, 0, 1, 0x67, 0x42, 0xC0, 0x1E, 0xD9, 0x00, 0xA0, 0x47, 0xFE, 0xC8, //
+    0, 0, 0, 1, 0x68, 0xCE, 0x3C, 0x80, //
+    0, 0, 0, 1, 0x65, 0x88, 0x84, 0x00, 0x33,
+];
+const DELTA_FRAME: &[u8] = &[0, 0, 0, 1, 0x41, 0x9A, 0x02, 0x03];
+const AAC_FRAME: &[u8] = &[0x21, 0x10, 0x04, 0x60, 0x8C];
+
+struct MockStream {
+    stream_id: StreamId,
+}
+
+#[async_trait]
+impl Stream for MockStream {
+    fn stream_id(&self) -> &StreamId {
+        &self.stream_id
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::RTMP
+    }
+
+    async fn metadata(&self) -> StreamMetadata {
+        StreamMetadata {
+            video_codec: Some("h264".to_string()),
+            audio_codec: Some("aac".to_string()),
+            audio_sample_rate: Some(48000),
+            audio_channels: Some(2),
+            ..Default::default()
+        }
+    }
+
+    async fn status(&self) -> StreamStatus {
+        StreamStatus::Running
+    }
+
+    async fn start(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// 注册源流并按实时节奏发布：每 40ms 一帧视频和一帧音频，每 10 帧一个关键帧
+async fn start_source(stream_manager: &Arc<StreamManager>, stream_id: StreamId) -> JoinHandle<()> {
+    stream_manager
+        .register_stream(
+            Box::new(MockStream {
+                stream_id: stream_id.clone(),
+            }),
+            StreamMode::Passthrough { remux: true },
+        )
+        .await
+        .unwrap();
+
+    let stream_manager = stream_manager.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(40));
+        for frame in 0u32.. {
+            interval.tick().await;
+            let timestamp = frame * 40;
+            let (data, is_keyframe) = match frame % 10 {
+                0 => (KEYFRAME, true),
+                _ => (DELTA_FRAME, false),
+            };
+            let video = MediaPacket {
+                data: Bytes::from_static(data),
+                timestamp,
+                is_keyframe,
+                packet_type: PacketType::Video,
+            };
+            let audio = MediaPacket {
+                data: Bytes::from_static(AAC_FRAME),
+                timestamp,
+                is_keyframe: false,
+                packet_type: PacketType::Audio,
+            };
+            if stream_manager.publish_packet(&stream_id, video).await.is_err()
+                || stream_manager.publish_packet(&stream_id, audio).await.is_err()
+            {
+                break;
+            }
+        }
+    })
+}
+
+async fn start_server(stream_manager: &Arc<StreamManager>) -> SocketAddr {
+    let listener = SrtListener::bind("127.0.0.1:0".parse().unwrap()).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = Arc::new(SrtServer::new(stream_manager.clone(), SrtServerConfig::default()));
+    tokio::spawn(server.serve(listener));
+    addr
+}
+
+/// 连续 TS 负载 → 按 PES 输出
+fn encode_ts(frames: &[(&[u8], u64, bool)]) -> Vec<u8> {
+    let mut muxer = TsMuxer::new();
+    let mut ts = Vec::new();
+    for (data, pts, key) in frames {
+        for packet in muxer.mux_video_pes(data, *pts, *pts, *key).unwrap() {
+            ts.extend_from_slice(&packet);
+        }
+    }
+    ts
+}
+
+async fn wait_for_stream(stream_manager: &StreamManager, stream_id: &StreamId, registered: bool) {
+    timeout(Duration::from_secs(5), async {
+        while stream_manager.get_metadata(stream_id).await.is_some() != registered {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_request_mode_playback() {
+    let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+    let source = start_source(&stream_manager, StreamId::new("rtmp", "live/cam1")).await;
+    let addr = start_server(&stream_manager).await;
+
+    // 不存在的资源在握手阶段被拒绝
+    let caller = SrtCaller::new(addr).await.unwrap().with_stream_id("#!::r=rtmp/live/none,m=request");
+    match caller.connect().await {
+        Ok(_) => panic!("connection should be rejected"),
+        Err(e) => assert!(e.to_string().contains("resource not found"), "{}", e),
+    }
+
+    let client = SrtCaller::new(addr)
+        .await
+        .unwrap()
+        .with_latency(300)
+        .with_stream_id("#!::r=rtmp/live/cam1,m=request")
+        .connect()
+        .await
+        .unwrap();
+    // 延迟协商取双方较大值
+    assert_eq!(client.latency(), Duration::from_millis(300));
+
+    let mut demuxer = TsDemuxer::new();
+    let mut video = None;
+    let mut audio = None;
+    timeout(Duration::from_secs(5), async {
+        while video.is_none() || audio.is_none() {
+            let (data, _) = client.recv().await.unwrap();
+            assert_eq!(data.len(), 1316);
+            for frame in demuxer.push(&data) {
+                match frame.stream_type {
+                    STREAM_TYPE_H264 if video.is_none() => video = Some(frame),
+                    STREAM_TYPE_AAC if audio.is_none() => audio = Some(frame),
+                    _ => {}
+                }
+            }
+        }
+    })
+    .await
+    .unwrap();
+
+    // 从关键帧开始发送，AAC 补 ADTS 头
+    let video = video.unwrap();
+    assert_eq!(video.data.as_ref(), KEYFRAME);
+    let audio = audio.unwrap();
+    let adts = AacConfig::lc(48000, 2).unwrap().adts_header(AAC_FRAME.len());
+    assert_eq!(&audio.data[..7], &adts);
+    assert_eq!(&audio.data[7..], AAC_FRAME);
+
+    client.close().await.unwrap();
+    source.abort();
+}
+
+#[tokio::test]
+async fn test_publish_registers_stream() {
+    let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+    let addr = start_server(&stream_manager).await;
+    let stream_id = StreamId::new("srt", "live/pub");
+
+    let publisher = SrtCaller::new(addr)
+        .await
+        .unwrap()
+        .with_stream_id("#!::r=live/pub,m=publish")
+        .connect()
+        .await
+        .unwrap();
+    wait_for_stream(&stream_manager, &stream_id, true).await;
+    let mut rx = stream_manager.subscribe(&stream_id).await.unwrap();
+
+    // 同一资源的第二个推流端被拒绝
+    let duplicate = SrtCaller::new(addr).await.unwrap().with_stream_id("#!::r=live/pub,m=publish");
+    match duplicate.connect().await {
+        Ok(_) => panic!("connection should be rejected"),
+        Err(e) => assert!(e.to_string().contains("already in use"), "{}", e),
+    }
+
+    let ts = encode_ts(&[(KEYFRAME, 90_000, true), (DELTA_FRAME, 93_600, false), (KEYFRAME, 97_200, true)]);
+    for chunk in ts.chunks(1316) {
+        publisher.send_data(chunk).await.unwrap();
+    }
+
+    let first = timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+    assert_eq!(first.packet_type, PacketType::Video);
+    assert!(first.is_keyframe);
+    assert_eq!(first.data.as_ref(), KEYFRAME);
+    let second = timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+    assert!(!second.is_keyframe);
+    assert_eq!(second.timestamp - first.timestamp, 40);
+
+    let metadata = stream_manager.get_metadata(&stream_id).await.unwrap();
+    assert_eq!(metadata.video_codec.as_deref(), Some("h264"));
+
+    // 推流端断开后注销
+    publisher.close().await.unwrap();
+    wait_for_stream(&stream_manager, &stream_id, false).await;
+}
+
+#[tokio::test]
+async fn test_pull_reconnects_until_source_available() {
+    let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+    let addr = start_server(&stream_manager).await;
+
+    let pulls = SrtPullManager::new(stream_manager.clone())
+        .with_backoff(Duration::from_millis(100), Duration::from_millis(200));
+    let url = format!("srt://{}?streamid=%23%21%3A%3Ar%3Drtmp%2Flive%2Fsrc%2Cm%3Drequest", addr);
+    let stream_id = pulls.start_pull("relay", &url).await.unwrap();
+    assert_eq!(stream_id, StreamId::new("srt", "relay"));
+    assert!(pulls.start_pull("relay", &url).await.is_err());
+
+    // 源流尚未注册：握手被拒绝并进入重连
+    timeout(Duration::from_secs(5), async {
+        loop {
+            let info = pulls.get_pull("relay").await.unwrap();
+            if info.reconnect_attempts > 0 {
+                assert_eq!(info.state, PullState::Reconnecting);
+                assert!(info.last_error.unwrap().contains("resource not found"));
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    let source = start_source(&stream_manager, StreamId::new("rtmp", "live/src")).await;
+    let mut rx = stream_manager.subscribe(&stream_id).await.unwrap();
+    let packet = timeout(Duration::from_secs(10), async {
+        loop {
+            let packet = rx.recv().await.unwrap();
+            if packet.packet_type == PacketType::Video {
+                break packet;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert!(packet.is_keyframe);
+    assert_eq!(packet.data.as_ref(), KEYFRAME);
+
+    let info = pulls.list_pulls().await.pop().unwrap();
+    assert_eq!(info.state, PullState::Connected);
+    assert_eq!(info.reconnect_attempts, 0);
+    assert!(info.packet_count > 0);
+
+    pulls.stop_pull("relay").await.unwrap();
+    assert!(pulls.list_pulls().await.is_empty());
+    assert!(stream_manager.get_metadata(&stream_id).await.is_none());
+    source.abort();
+}
@@ -1,9 +1,12 @@
-use crate::stream::{ClientInfo, ClientType, Protocol, StreamStatus};
+use crate::stream::{ClientInfo, ClientType, MediaPacket, Protocol, StreamStatus};
 use flux_config::{StreamMode, TranscodeTrigger};
 use flux_media_core::types::StreamId;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+
+/// 媒体分发通道容量（约数秒的音视频包）
+const MEDIA_CHANNEL_CAPACITY: usize = 512;
 
 /// 流上下文（管理单个流的状态）
 #[derive(Clone)]
@@ -14,10 +17,14 @@ pub struct StreamContext {
     pub status: StreamStatus,
     pub is_transcoding: bool,
     pub clients: Arc<RwLock<HashMap<String, ClientInfo>>>,
+    /// 协议无关的媒体分发通道（输入协议发布，输出协议订阅）
+    pub media_tx: broadcast::Sender<MediaPacket>,
 }
 
 impl StreamContext {
     pub fn new(stream_id: StreamId, input_protocol: Protocol, mode: StreamMode) -> Self {
+        let (media_tx, _) = broadcast::channel(MEDIA_CHANNEL_CAPACITY);
+
         Self {
             stream_id,
             input_protocol,
@@ -25,6 +32,7 @@ impl StreamContext {
             status: StreamStatus::Idle,
             is_transcoding: false,
             clients: Arc::new(RwLock::new(HashMap::new())),
+            media_tx,
         }
     }
 
@@ -1,12 +1,14 @@
 use crate::context::StreamContext;
-use crate::stream::{ClientInfo, OutputStream, Protocol, QualityLevel, Stream};
+use crate::stream::{
+    ClientInfo, MediaPacket, OutputStream, Protocol, QualityLevel, Stream, StreamMetadata,
+};
 use crate::trigger::TriggerDetector;
 use anyhow::{anyhow, Result};
 use flux_config::{StreamMode, StreamingConfig};
 use flux_media_core::types::StreamId;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use tracing::info;
 
 /// 统一流管理器（协议无关）
@@ -150,6 +152,36 @@ impl StreamManager {
         }
     }
 
+    /// 发布媒体数据包（由输入协议调用）
+    pub async fn publish_packet(&self, stream_id: &StreamId, packet: MediaPacket) -> Result<()> {
+        let contexts = self.contexts.read().await;
+        let context = contexts
+            .get(stream_id)
+            .ok_or_else(|| anyhow!("Stream not found: {}", stream_id))?;
+
+        // 忽略发送错误（没有订阅者时会失败）
+        let _ = context.media_tx.send(packet);
+        Ok(())
+    }
+
+    /// 订阅流的媒体数据包（由输出协议调用）
+    pub async fn subscribe(&self, stream_id: &StreamId) -> Result<broadcast::Receiver<MediaPacket>> {
+        let contexts = self.contexts.read().await;
+        contexts
+            .get(stream_id)
+            .map(|context| context.media_tx.subscribe())
+            .ok_or_else(|| anyhow!("Stream not found: {}", stream_id))
+    }
+
+    /// 获取流元数据（编码、分辨率等）
+    pub async fn get_metadata(&self, stream_id: &StreamId) -> Option<StreamMetadata> {
+        let streams = self.streams.read().await;
+        match streams.get(stream_id) {
+            Some(stream) => Some(stream.metadata().await),
+            None => None,
+        }
+    }
+
     /// 获取流上下文
     pub async fn get_context(&self, stream_id: &StreamId) -> Option<StreamContext> {
         let contexts = self.contexts.read().await;
@@ -238,6 +270,53 @@ mod tests {
         assert_eq!(manager.stream_count().await, 0);
     }
 
+    #[tokio::test]
+    async fn test_publish_and_subscribe() {
+        let manager = StreamManager::new(StreamingConfig::default());
+        let stream_id = StreamId::new("test", "stream-002");
+
+        manager
+            .register_stream(
+                Box::new(MockStream {
+                    stream_id: stream_id.clone(),
+                    protocol: Protocol::RTMP,
+                    metadata: StreamMetadata {
+                        video_codec: Some("h264".to_string()),
+                        ..Default::default()
+                    },
+                    status: StreamStatus::Running,
+                }),
+                StreamMode::Passthrough { remux: true },
+            )
+            .await
+            .unwrap();
+
+        let mut rx = manager.subscribe(&stream_id).await.unwrap();
+        manager
+            .publish_packet(
+                &stream_id,
+                MediaPacket {
+                    data: bytes::Bytes::from_static(&[0, 0, 0, 1, 0x65]),
+                    timestamp: 40,
+                    is_keyframe: true,
+                    packet_type: crate::stream::PacketType::Video,
+                },
+            )
+            .await
+            .unwrap();
+
+        let packet = rx.recv().await.unwrap();
+        assert_eq!(packet.timestamp, 40);
+        assert!(packet.is_keyframe);
+
+        let metadata = manager.get_metadata(&stream_id).await.unwrap();
+        assert_eq!(metadata.video_codec.as_deref(), Some("h264"));
+
+        let unknown = StreamId::new("test", "missing");
+        assert!(manager.subscribe(&unknown).await.is_err());
+        assert!(manager.get_metadata(&unknown).await.is_none());
+    }
+
     #[tokio::test]
     async fn test_auto_transcode_trigger() {
         let config = StreamingConfig::default();
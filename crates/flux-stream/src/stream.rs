@@ -34,6 +34,8 @@ pub struct StreamMetadata {
     pub framerate: Option<f32>,
     pub video_codec: Option<String>,
     pub audio_codec: Option<String>,
+    pub audio_sample_rate: Option<u32>,
+    pub audio_channels: Option<u8>,
     pub bitrate: Option<u32>,
 }
 
@@ -45,6 +47,8 @@ impl Default for StreamMetadata {
             framerate: None,
             video_codec: None,
             audio_codec: None,
+            audio_sample_rate: None,
+            audio_channels: None,
             bitrate: None,
         }
     }
@@ -76,6 +80,9 @@ pub trait Stream: Send + Sync {
 }
 
 /// 媒体数据包
+///
+/// 视频为 Annex B 格式的完整访问单元（关键帧需携带参数集），
+/// 音频为单个编码帧；时间戳单位为毫秒。
 #[derive(Debug, Clone)]
 pub struct MediaPacket {
     pub data: Bytes,
@@ -1,6 +1,7 @@
 use crate::model::{EventPoint, LogPoint, MetricPoint};
 use crate::query::{AggregatedResult, TimeSeriesQuery};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use sea_orm::{Database, DatabaseConnection, DbErr};
 use std::sync::Arc;
 use tracing::{debug, info};
@@ -28,6 +29,15 @@ pub trait TimeSeriesStore: Send + Sync {
         &self,
         query: &TimeSeriesQuery,
     ) -> anyhow::Result<Vec<AggregatedResult>>;
+    
+    /// 统计时间范围内的事件数量（`device_id` 为 None 时统计全部设备）
+    async fn count_events(
+        &self,
+        device_id: Option<&str>,
+        event_type: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> anyhow::Result<u64>;
 }
 
 /// TimescaleDB 存储实现
@@ -308,6 +318,43 @@ impl TimeSeriesStore for TimescaleStore {
 
         Ok(aggregated)
     }
+
+    async fn count_events(
+        &self,
+        device_id: Option<&str>,
+        event_type: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> anyhow::Result<u64> {
+        use sea_orm::{ConnectionTrait, Statement};
+
+        let mut sql = String::from(
+            "SELECT COUNT(*) AS count FROM device_events WHERE event_type = $1 AND time >= $2 AND time <= $3"
+        );
+        let mut params: Vec<sea_orm::Value> = vec![
+            event_type.into(),
+            start_time.into(),
+            end_time.into(),
+        ];
+        
+        if let Some(device_id) = device_id {
+            sql.push_str(" AND device_id = $4");
+            params.push(device_id.into());
+        }
+
+        let stmt = Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Postgres,
+            sql,
+            params,
+        );
+
+        let count: i64 = match self.db.query_one(stmt).await? {
+            Some(row) => row.try_get("", "count")?,
+            None => 0,
+        };
+        
+        Ok(count.max(0) as u64)
+    }
 }
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
 pub mod config;
 pub mod device;
 pub mod message;
+pub mod plugin;
 
 // Re-exports
 pub use serde;
@@ -19,4 +19,14 @@ impl Message {
             timestamp: chrono::Utc::now().timestamp_millis(),
         }
     }
+
+    /// 派生新消息（沿用时间戳，ID 由 Host 分配），用于插件改写或拆分消息
+    pub fn derive(&self, topic: impl Into<String>, payload: serde_json::Value) -> Self {
+        Self {
+            id: Uuid::nil(),
+            topic: topic.into(),
+            payload,
+            timestamp: self.timestamp,
+        }
+    }
 }
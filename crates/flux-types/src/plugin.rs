@@ -0,0 +1,125 @@
+//! 插件调用 ABI（Host 与 Wasm 插件共用）
+//!
+//! v1 约定：
+//! - 插件导出 `flux_abi_version() -> i32`，返回 [`PLUGIN_ABI_VERSION`]
+//! - 入口函数签名为 `fn(ptr: i32, len: i32) -> i64`，输入为 JSON 编码的数据
+//! - 返回值高 32 位为结果缓冲区指针、低 32 位为长度（由插件 `alloc` 分配，Host 读取后调用 `dealloc` 释放），
+//!   长度为 0 表示无输出
+//! - 结果缓冲区为 JSON 编码的 [`PluginResponse`]
+//!
+//! 入口函数返回 `i32` 的插件视为 v0（旧 ABI），只返回状态码。
+//!
+//! Host 函数（导入模块 `env`）需插件在清单中声明对应能力，未声明时返回 [`HOST_ERR_DENIED`]：
+//! - `flux_time_now_ms() -> i64`（`time`）
+//! - `flux_kv_get(key_ptr, key_len) -> i64`、`flux_kv_set(key_ptr, key_len, val_ptr, val_len) -> i32`、
+//!   `flux_kv_delete(key_ptr, key_len) -> i32`（`kv`）
+//! - `flux_publish(topic_ptr, topic_len, payload_ptr, payload_len) -> i32`，payload 为 JSON（`publish`）
+//! - `flux_device_get(id_ptr, id_len) -> i64`，返回设备 JSON（`device_read`）
+//! - `flux_http_request(req_ptr, req_len) -> i64`，请求/响应为 JSON 编码的 [`HttpRequest`]/[`HttpResponse`]（`http`）
+//!
+//! 返回 `i64` 的 Host 函数与入口函数相同，结果为打包的指针与长度（由插件 `alloc` 分配，插件负责释放），
+//! 0 表示无数据，负数为错误码。
+
+use serde::{Deserialize, Serialize};
+
+/// 当前插件 ABI 版本
+pub const PLUGIN_ABI_VERSION: i32 = 1;
+
+/// 插件导出的 ABI 版本函数名
+pub const ABI_VERSION_EXPORT: &str = "flux_abi_version";
+
+/// 插件调用结果
+///
+/// `data` 缺省表示无输出（如消息原样通过），显式的 `null` 是有效数据（如丢弃消息）。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PluginResponse {
+    Ok {
+        #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+        data: Option<serde_json::Value>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Host 函数返回码：成功
+pub const HOST_OK: i32 = 0;
+/// Host 函数返回码：插件未声明对应能力
+pub const HOST_ERR_DENIED: i32 = -1;
+/// Host 函数返回码：参数无效（内存越界、编码错误、不在允许列表等）
+pub const HOST_ERR_INVALID: i32 = -2;
+/// Host 函数返回码：执行失败
+pub const HOST_ERR_FAILED: i32 = -3;
+/// Host 函数返回码：超出大小或数量限制
+pub const HOST_ERR_LIMIT: i32 = -4;
+
+/// 插件发起的 HTTP 请求（`flux_http_request`）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpRequest {
+    #[serde(default = "default_http_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+fn default_http_method() -> String {
+    "GET".to_string()
+}
+
+/// HTTP 响应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub body: String,
+}
+
+/// 字段存在即为 `Some`（包括 `null`）
+fn present<'de, D>(deserializer: D) -> Result<Option<serde_json::Value>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    serde_json::Value::deserialize(deserializer).map(Some)
+}
+
+/// 打包结果缓冲区指针与长度
+pub fn pack_ptr_len(ptr: u32, len: u32) -> i64 {
+    (((ptr as u64) << 32) | len as u64) as i64
+}
+
+/// 拆分结果缓冲区指针与长度
+pub fn unpack_ptr_len(packed: i64) -> (u32, u32) {
+    let packed = packed as u64;
+    ((packed >> 32) as u32, packed as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_ptr_len() {
+        let packed = pack_ptr_len(0x8000_0010, 42);
+        assert_eq!(unpack_ptr_len(packed), (0x8000_0010, 42));
+        assert_eq!(unpack_ptr_len(0), (0, 0));
+    }
+
+    #[test]
+    fn test_response_encoding() {
+        let ok: PluginResponse = serde_json::from_str(r#"{"status":"ok","data":[1]}"#).unwrap();
+        assert_eq!(ok, PluginResponse::Ok { data: Some(serde_json::json!([1])) });
+        let empty: PluginResponse = serde_json::from_str(r#"{"status":"ok"}"#).unwrap();
+        assert_eq!(empty, PluginResponse::Ok { data: None });
+        let null: PluginResponse = serde_json::from_str(r#"{"status":"ok","data":null}"#).unwrap();
+        assert_eq!(null, PluginResponse::Ok { data: Some(serde_json::Value::Null) });
+        assert_eq!(serde_json::to_string(&null).unwrap(), r#"{"status":"ok","data":null}"#);
+        let error = serde_json::to_value(PluginResponse::Error { message: "bad".into() }).unwrap();
+        assert_eq!(error, serde_json::json!({"status": "error", "message": "bad"}));
+    }
+}
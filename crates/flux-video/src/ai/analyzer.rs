@@ -0,0 +1,312 @@
+// 运动分析服务：按流管理检测器，订阅直播流关键帧软解分析，
+// 运动开始/结束事件发布到事件总线（主题 video/motion/{stream_id}）
+use super::motion::{GrayFrame, MotionConfig, MotionDetector, MotionEvent, MotionState};
+use crate::{Result, VideoError};
+use chrono::{DateTime, Utc};
+use flux_core::bus::SharedEventBus;
+use flux_media_core::snapshot::SoftwareDecoder;
+use flux_media_core::types::StreamId;
+use flux_stream::{MediaPacket, PacketType, StreamManager};
+use flux_types::message::Message;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// 运动事件主题前缀，完整主题为 `video/motion/{stream_id}`
+pub const MOTION_TOPIC_PREFIX: &str = "video/motion/";
+
+/// 流离线时的重试间隔
+const STREAM_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 单路分析状态
+#[derive(Debug, Clone, Serialize)]
+pub struct MotionStreamStatus {
+    pub stream_id: String,
+    pub config: MotionConfig,
+    /// 是否正在订阅直播流
+    pub live: bool,
+    /// 是否处于运动中
+    pub active: bool,
+    pub frames_analyzed: u64,
+    pub events: u64,
+    pub decode_errors: u64,
+    pub last_event: Option<MotionEvent>,
+}
+
+struct StreamState {
+    detector: MotionDetector,
+    frames_analyzed: u64,
+    events: u64,
+    decode_errors: u64,
+    last_event: Option<MotionEvent>,
+}
+
+struct MotionStream {
+    state: Arc<Mutex<StreamState>>,
+    task: Option<JoinHandle<()>>,
+}
+
+/// 运动分析服务
+pub struct MotionAnalyzer {
+    bus: Option<SharedEventBus>,
+    stream_manager: Option<Arc<StreamManager>>,
+    streams: RwLock<HashMap<String, MotionStream>>,
+}
+
+impl MotionAnalyzer {
+    pub fn new(bus: Option<SharedEventBus>) -> Self {
+        Self {
+            bus,
+            stream_manager: None,
+            streams: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 关联流管理器后，启用的流会自动订阅直播关键帧进行分析
+    pub fn with_stream_manager(mut self, stream_manager: Arc<StreamManager>) -> Self {
+        self.stream_manager = Some(stream_manager);
+        self
+    }
+
+    /// 设置（或替换）某路流的检测配置；替换时检测器重新学习背景
+    pub async fn set_config(&self, stream_id: &str, config: MotionConfig) -> Result<()> {
+        validate_config(&config)?;
+        self.remove(stream_id).await;
+
+        let enabled = config.enabled;
+        let state = Arc::new(Mutex::new(StreamState {
+            detector: MotionDetector::new(config),
+            frames_analyzed: 0,
+            events: 0,
+            decode_errors: 0,
+            last_event: None,
+        }));
+        let task = match (&self.stream_manager, enabled) {
+            (Some(stream_manager), true) => {
+                let runner = LiveRunner {
+                    stream_id: StreamId::from(stream_id),
+                    stream_manager: stream_manager.clone(),
+                    bus: self.bus.clone(),
+                    state: state.clone(),
+                };
+                Some(tokio::spawn(runner.run()))
+            }
+            _ => None,
+        };
+
+        info!(target: "motion", stream_id, enabled, "Motion detection configured");
+        self.streams
+            .write()
+            .await
+            .insert(stream_id.to_string(), MotionStream { state, task });
+        Ok(())
+    }
+
+    /// 移除某路流的检测；处于运动中时补发结束事件
+    pub async fn remove(&self, stream_id: &str) -> bool {
+        let Some(stream) = self.streams.write().await.remove(stream_id) else {
+            return false;
+        };
+        if let Some(task) = stream.task {
+            task.abort();
+        }
+        let event = stream.state.lock().unwrap().detector.finish(Utc::now());
+        if let Some(event) = event {
+            record_event(&stream.state, &event);
+            publish(self.bus.as_ref(), stream_id, &event);
+        }
+        true
+    }
+
+    /// 移除所有流的检测
+    pub async fn shutdown(&self) {
+        let stream_ids: Vec<String> = self.streams.read().await.keys().cloned().collect();
+        for stream_id in stream_ids {
+            self.remove(&stream_id).await;
+        }
+    }
+
+    /// 分析一帧（外部解码的帧或测试输入），状态变化时发布并返回事件
+    pub async fn process_frame(&self, stream_id: &str, frame: &GrayFrame, at: DateTime<Utc>) -> Result<Option<MotionEvent>> {
+        let state = self
+            .streams
+            .read()
+            .await
+            .get(stream_id)
+            .map(|stream| stream.state.clone())
+            .ok_or_else(|| VideoError::StreamNotFound(stream_id.to_string()))?;
+        Ok(analyze(&state, self.bus.as_ref(), stream_id, frame, at))
+    }
+
+    pub async fn status(&self, stream_id: &str) -> Option<MotionStreamStatus> {
+        self.streams
+            .read()
+            .await
+            .get(stream_id)
+            .map(|stream| stream_status(stream_id, stream))
+    }
+
+    pub async fn statuses(&self) -> Vec<MotionStreamStatus> {
+        let mut statuses: Vec<_> = self
+            .streams
+            .read()
+            .await
+            .iter()
+            .map(|(stream_id, stream)| stream_status(stream_id, stream))
+            .collect();
+        statuses.sort_by(|a, b| a.stream_id.cmp(&b.stream_id));
+        statuses
+    }
+}
+
+fn validate_config(config: &MotionConfig) -> Result<()> {
+    if !(1..=100).contains(&config.sensitivity) {
+        return Err(VideoError::Other("sensitivity must be within 1-100".to_string()));
+    }
+    if !(0.0..=1.0).contains(&config.min_area_ratio) || !(0.0..=1.0).contains(&config.learning_rate) {
+        return Err(VideoError::Other("min_area_ratio and learning_rate must be within 0-1".to_string()));
+    }
+    for region in &config.regions {
+        let valid = region.x >= 0.0
+            && region.y >= 0.0
+            && region.width > 0.0
+            && region.height > 0.0
+            && region.x + region.width <= 1.0
+            && region.y + region.height <= 1.0;
+        if !valid {
+            return Err(VideoError::Other(format!(
+                "Region {} must lie within normalized 0-1 frame coordinates",
+                region.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn stream_status(stream_id: &str, stream: &MotionStream) -> MotionStreamStatus {
+    let state = stream.state.lock().unwrap();
+    MotionStreamStatus {
+        stream_id: stream_id.to_string(),
+        config: state.detector.config().clone(),
+        live: stream.task.as_ref().is_some_and(|task| !task.is_finished()),
+        active: state.detector.is_active(),
+        frames_analyzed: state.frames_analyzed,
+        events: state.events,
+        decode_errors: state.decode_errors,
+        last_event: state.last_event.clone(),
+    }
+}
+
+fn analyze(
+    state: &Mutex<StreamState>,
+    bus: Option<&SharedEventBus>,
+    stream_id: &str,
+    frame: &GrayFrame,
+    at: DateTime<Utc>,
+) -> Option<MotionEvent> {
+    let event = {
+        let mut state = state.lock().unwrap();
+        if !state.detector.config().enabled {
+            return None;
+        }
+        state.frames_analyzed += 1;
+        state.detector.process(frame, at)
+    }?;
+    record_event(state, &event);
+    publish(bus, stream_id, &event);
+    Some(event)
+}
+
+fn record_event(state: &Mutex<StreamState>, event: &MotionEvent) {
+    let mut state = state.lock().unwrap();
+    state.events += 1;
+    state.last_event = Some(event.clone());
+}
+
+fn publish(bus: Option<&SharedEventBus>, stream_id: &str, event: &MotionEvent) {
+    info!(
+        target: "motion",
+        stream_id,
+        state = ?event.state,
+        score = event.score,
+        regions = ?event.regions,
+        "Motion event"
+    );
+    let Some(bus) = bus else {
+        return;
+    };
+    let payload = serde_json::json!({
+        "stream_id": stream_id,
+        "state": event.state,
+        "motion": event.state == MotionState::Start,
+        "score": event.score,
+        "regions": event.regions,
+        "timestamp": event.timestamp.timestamp_millis(),
+    });
+    // 没有订阅者时发送失败，属正常情况
+    let _ = bus.publish(Message::new(format!("{}{}", MOTION_TOPIC_PREFIX, stream_id), payload));
+}
+
+/// 直播流分析协程：只取视频关键帧，按最小间隔降采样后软解分析
+struct LiveRunner {
+    stream_id: StreamId,
+    stream_manager: Arc<StreamManager>,
+    bus: Option<SharedEventBus>,
+    state: Arc<Mutex<StreamState>>,
+}
+
+impl LiveRunner {
+    async fn run(self) {
+        loop {
+            match self.stream_manager.subscribe(&self.stream_id).await {
+                Ok(receiver) => self.analyze_session(receiver).await,
+                Err(_) => tokio::time::sleep(STREAM_POLL_INTERVAL).await,
+            }
+        }
+    }
+
+    async fn analyze_session(&self, mut receiver: broadcast::Receiver<MediaPacket>) {
+        debug!(target: "motion", stream_id = %self.stream_id, "Motion analysis attached to live stream");
+        loop {
+            let packet = match receiver.recv().await {
+                Ok(packet) => packet,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if packet.packet_type != PacketType::Video || !packet.is_keyframe {
+                continue;
+            }
+            let now = Utc::now();
+            if !self.state.lock().unwrap().detector.should_analyze(now) {
+                continue;
+            }
+
+            let data = packet.data.clone();
+            let decoded = tokio::task::spawn_blocking(move || {
+                SoftwareDecoder::decode_rgb(&data).map(|image| GrayFrame::from_rgb(image.width, image.height, &image.data))
+            })
+            .await;
+            match decoded {
+                Ok(Ok(frame)) => {
+                    analyze(&self.state, self.bus.as_ref(), self.stream_id.as_str(), &frame, now);
+                }
+                Ok(Err(e)) => {
+                    self.state.lock().unwrap().decode_errors += 1;
+                    debug!(target: "motion", stream_id = %self.stream_id, "Keyframe decode failed: {}", e);
+                }
+                Err(e) => warn!(target: "motion", stream_id = %self.stream_id, "Decode task failed: {}", e),
+            }
+        }
+
+        // 流下线时结束未完成的运动事件
+        let event = self.state.lock().unwrap().detector.finish(Utc::now());
+        if let Some(event) = event {
+            record_event(&self.state, &event);
+            publish(self.bus.as_ref(), self.stream_id.as_str(), &event);
+        }
+    }
+}
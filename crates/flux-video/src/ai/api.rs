@@ -0,0 +1,80 @@
+// 运动检测 HTTP 接口
+//
+// - `GET /api/v1/motion/streams`：各路检测状态
+// - `GET /api/v1/motion/streams/*stream_id`：单路检测状态
+// - `PUT /api/v1/motion/streams/*stream_id`：设置检测配置（`enabled` 控制启停，其余字段缺省取默认值）
+// - `DELETE /api/v1/motion/streams/*stream_id`：移除检测
+use super::analyzer::MotionAnalyzer;
+use super::motion::MotionConfig;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, put},
+    Json, Router,
+};
+use std::sync::Arc;
+
+pub fn router<S>(analyzer: Arc<MotionAnalyzer>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    read_router(analyzer.clone()).merge(write_router(analyzer))
+}
+
+/// 只读接口（检测状态），便于调用方单独设置权限
+pub fn read_router<S>(analyzer: Arc<MotionAnalyzer>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/api/v1/motion/streams", get(list_streams))
+        .route("/api/v1/motion/streams/*stream_id", get(get_stream))
+        .with_state(analyzer)
+}
+
+/// 修改接口（设置、移除检测）
+pub fn write_router<S>(analyzer: Arc<MotionAnalyzer>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route(
+            "/api/v1/motion/streams/*stream_id",
+            put(configure_stream).delete(remove_stream),
+        )
+        .with_state(analyzer)
+}
+
+async fn list_streams(State(analyzer): State<Arc<MotionAnalyzer>>) -> Response {
+    Json(analyzer.statuses().await).into_response()
+}
+
+async fn get_stream(State(analyzer): State<Arc<MotionAnalyzer>>, Path(stream_id): Path<String>) -> Response {
+    match analyzer.status(&stream_id).await {
+        Some(status) => Json(status).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn configure_stream(
+    State(analyzer): State<Arc<MotionAnalyzer>>,
+    Path(stream_id): Path<String>,
+    Json(config): Json<MotionConfig>,
+) -> Response {
+    if let Err(e) = analyzer.set_config(&stream_id, config).await {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+    match analyzer.status(&stream_id).await {
+        Some(status) => Json(status).into_response(),
+        None => StatusCode::OK.into_response(),
+    }
+}
+
+async fn remove_stream(State(analyzer): State<Arc<MotionAnalyzer>>, Path(stream_id): Path<String>) -> Response {
+    if analyzer.remove(&stream_id).await {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
@@ -1 +1,10 @@
-// AI 分析模块（占位实现）
+// AI 分析模块：CPU 运动检测（关键帧软解 + 背景差分），事件发布到事件总线
+pub mod analyzer;
+pub mod api;
+pub mod motion;
+
+pub use analyzer::{MotionAnalyzer, MotionStreamStatus, MOTION_TOPIC_PREFIX};
+pub use motion::{GrayFrame, MotionConfig, MotionDetector, MotionEvent, MotionRegion, MotionState};
+
+#[cfg(test)]
+mod motion_test;
@@ -0,0 +1,317 @@
+// 运动检测：灰度帧差 + 滑动平均背景模型，按检测区域统计变化像素比例，
+// 连续多帧触发开始事件，静止超过设定时间后发出结束事件
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 未配置检测区域时全画面区域的名称
+pub const FULL_FRAME_REGION: &str = "full";
+
+/// 运动检测配置（每路摄像头）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MotionConfig {
+    pub enabled: bool,
+    /// 灵敏度 1-100，越高越容易触发
+    pub sensitivity: u8,
+    /// 区域内变化像素比例达到该值视为运动（0-1）
+    pub min_area_ratio: f32,
+    /// 背景模型学习率（0-1）
+    pub learning_rate: f32,
+    /// 分析宽度（像素），帧先等比缩小到该宽度
+    pub analysis_width: u32,
+    /// 两次分析的最小间隔（毫秒），用于对直播流降采样
+    pub min_interval_ms: u64,
+    /// 连续多少帧检测到运动才发出开始事件
+    pub start_frames: u32,
+    /// 运动消失超过该时长（毫秒）后发出结束事件
+    pub stop_after_ms: u64,
+    /// 检测区域（归一化坐标），为空时检测全画面
+    pub regions: Vec<MotionRegion>,
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sensitivity: 50,
+            min_area_ratio: 0.01,
+            learning_rate: 0.05,
+            analysis_width: 160,
+            min_interval_ms: 1000,
+            start_frames: 2,
+            stop_after_ms: 5000,
+            regions: Vec::new(),
+        }
+    }
+}
+
+impl MotionConfig {
+    /// 像素差阈值（0-255）：灵敏度 100 时为 5，1 时约为 55
+    pub fn pixel_threshold(&self) -> f32 {
+        5.0 + (100 - self.sensitivity.clamp(1, 100)) as f32 * 0.5
+    }
+}
+
+/// 检测区域（相对画面宽高的 0-1 坐标）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MotionRegion {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl MotionRegion {
+    /// 换算为像素矩形 (x0, y0, x1, y1)，至少 1 像素
+    fn to_pixels(&self, width: u32, height: u32) -> (usize, usize, usize, usize) {
+        let clamp = |value: f32| value.clamp(0.0, 1.0);
+        let x0 = (clamp(self.x) * width as f32) as usize;
+        let y0 = (clamp(self.y) * height as f32) as usize;
+        let x1 = ((clamp(self.x + self.width) * width as f32).ceil() as usize).clamp(x0 + 1, width.max(1) as usize);
+        let y1 = ((clamp(self.y + self.height) * height as f32).ceil() as usize).clamp(y0 + 1, height.max(1) as usize);
+        (x0.min(x1 - 1), y0.min(y1 - 1), x1, y1)
+    }
+}
+
+/// 8 位灰度帧
+#[derive(Debug, Clone)]
+pub struct GrayFrame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl GrayFrame {
+    /// 由 RGB24 转换（BT.601 亮度）
+    pub fn from_rgb(width: u32, height: u32, rgb: &[u8]) -> Self {
+        let data = rgb
+            .chunks_exact(3)
+            .map(|pixel| ((pixel[0] as u32 * 77 + pixel[1] as u32 * 150 + pixel[2] as u32 * 29) >> 8) as u8)
+            .collect();
+        Self { width, height, data }
+    }
+
+    /// 数据长度与宽高一致且非空（截断的解码结果不满足）
+    pub fn is_valid(&self) -> bool {
+        !self.data.is_empty() && self.data.len() as u64 == self.width as u64 * self.height as u64
+    }
+
+    /// 等比缩小到不超过 `max_width`（区域平均）；无效帧原样返回
+    pub fn downscale(&self, max_width: u32) -> GrayFrame {
+        if max_width == 0 || self.width <= max_width || !self.is_valid() {
+            return self.clone();
+        }
+        let width = max_width as usize;
+        let height = ((self.height as u64 * max_width as u64 / self.width as u64) as usize).max(1);
+        let (source_width, source_height) = (self.width as usize, self.height as usize);
+
+        let mut data = vec![0u8; width * height];
+        for y in 0..height {
+            let y0 = y * source_height / height;
+            let y1 = ((y + 1) * source_height / height).max(y0 + 1);
+            for x in 0..width {
+                let x0 = x * source_width / width;
+                let x1 = ((x + 1) * source_width / width).max(x0 + 1);
+                let mut sum = 0u32;
+                for row in y0..y1 {
+                    sum += self.data[row * source_width + x0..row * source_width + x1]
+                        .iter()
+                        .map(|&value| value as u32)
+                        .sum::<u32>();
+                }
+                data[y * width + x] = (sum / ((y1 - y0) * (x1 - x0)) as u32) as u8;
+            }
+        }
+
+        GrayFrame {
+            width: width as u32,
+            height: height as u32,
+            data,
+        }
+    }
+}
+
+/// 运动状态变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionState {
+    Start,
+    Stop,
+}
+
+/// 运动事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MotionEvent {
+    pub state: MotionState,
+    pub timestamp: DateTime<Utc>,
+    /// 触发时各区域中最大的变化像素比例
+    pub score: f32,
+    /// 触发运动的区域
+    pub regions: Vec<String>,
+}
+
+/// 单路运动检测器
+pub struct MotionDetector {
+    config: MotionConfig,
+    background: Option<Vec<f32>>,
+    dimensions: (u32, u32),
+    active: bool,
+    consecutive: u32,
+    last_motion: Option<DateTime<Utc>>,
+    last_analyzed: Option<DateTime<Utc>>,
+    peak_score: f32,
+    triggered: Vec<String>,
+}
+
+impl MotionDetector {
+    pub fn new(config: MotionConfig) -> Self {
+        Self {
+            config,
+            background: None,
+            dimensions: (0, 0),
+            active: false,
+            consecutive: 0,
+            last_motion: None,
+            last_analyzed: None,
+            peak_score: 0.0,
+            triggered: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> &MotionConfig {
+        &self.config
+    }
+
+    /// 是否处于运动中
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// 距上次分析是否已超过最小间隔
+    pub fn should_analyze(&self, at: DateTime<Utc>) -> bool {
+        self.last_analyzed
+            .is_none_or(|last| at - last >= Duration::milliseconds(self.config.min_interval_ms as i64))
+    }
+
+    /// 分析一帧，状态变化时返回事件
+    pub fn process(&mut self, frame: &GrayFrame, at: DateTime<Utc>) -> Option<MotionEvent> {
+        self.last_analyzed = Some(at);
+        if !frame.is_valid() {
+            return None;
+        }
+        let frame = frame.downscale(self.config.analysis_width);
+
+        // 首帧或分辨率变化时重建背景
+        let background = match &mut self.background {
+            Some(background) if self.dimensions == (frame.width, frame.height) => background,
+            _ => {
+                self.background = Some(frame.data.iter().map(|&value| value as f32).collect());
+                self.dimensions = (frame.width, frame.height);
+                return None;
+            }
+        };
+
+        let threshold = self.config.pixel_threshold();
+        let mask: Vec<bool> = frame
+            .data
+            .iter()
+            .zip(background.iter())
+            .map(|(&value, &bg)| (value as f32 - bg).abs() > threshold)
+            .collect();
+
+        // 前景像素以较低学习率更新，避免运动物体很快被吸收进背景
+        let learning_rate = self.config.learning_rate.clamp(0.0, 1.0);
+        for ((bg, &value), &changed) in background.iter_mut().zip(frame.data.iter()).zip(mask.iter()) {
+            let rate = if changed { learning_rate * 0.1 } else { learning_rate };
+            *bg += rate * (value as f32 - *bg);
+        }
+
+        let (score, triggered) = self.evaluate_regions(&mask, frame.width, frame.height);
+        self.update_state(score, triggered, at)
+    }
+
+    /// 流结束时收尾：处于运动中则发出结束事件
+    pub fn finish(&mut self, at: DateTime<Utc>) -> Option<MotionEvent> {
+        self.consecutive = 0;
+        if !self.active {
+            return None;
+        }
+        self.active = false;
+        Some(self.event(MotionState::Stop, at))
+    }
+
+    fn evaluate_regions(&self, mask: &[bool], width: u32, height: u32) -> (f32, Vec<String>) {
+        let full = [MotionRegion {
+            name: FULL_FRAME_REGION.to_string(),
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        }];
+        let regions = if self.config.regions.is_empty() {
+            &full[..]
+        } else {
+            &self.config.regions[..]
+        };
+
+        let mut score = 0.0f32;
+        let mut triggered = Vec::new();
+        for region in regions {
+            let (x0, y0, x1, y1) = region.to_pixels(width, height);
+            let changed: usize = (y0..y1)
+                .map(|y| mask[y * width as usize + x0..y * width as usize + x1].iter().filter(|&&c| c).count())
+                .sum();
+            let ratio = changed as f32 / ((x1 - x0) * (y1 - y0)) as f32;
+            score = score.max(ratio);
+            if ratio >= self.config.min_area_ratio {
+                triggered.push(region.name.clone());
+            }
+        }
+        (score, triggered)
+    }
+
+    fn update_state(&mut self, score: f32, triggered: Vec<String>, at: DateTime<Utc>) -> Option<MotionEvent> {
+        if !triggered.is_empty() {
+            self.consecutive += 1;
+            self.last_motion = Some(at);
+            self.peak_score = if self.active { self.peak_score.max(score) } else { score };
+            for region in triggered {
+                if !self.triggered.contains(&region) {
+                    self.triggered.push(region);
+                }
+            }
+            if !self.active && self.consecutive >= self.config.start_frames.max(1) {
+                self.active = true;
+                return Some(self.event(MotionState::Start, at));
+            }
+            return None;
+        }
+
+        self.consecutive = 0;
+        if !self.active {
+            self.triggered.clear();
+            return None;
+        }
+        let quiet = self.last_motion.map_or(Duration::zero(), |last| at - last);
+        if quiet >= Duration::milliseconds(self.config.stop_after_ms as i64) {
+            self.active = false;
+            return Some(self.event(MotionState::Stop, at));
+        }
+        None
+    }
+
+    fn event(&mut self, state: MotionState, at: DateTime<Utc>) -> MotionEvent {
+        let regions = if state == MotionState::Stop {
+            std::mem::take(&mut self.triggered)
+        } else {
+            self.triggered.clone()
+        };
+        MotionEvent {
+            state,
+            timestamp: at,
+            score: self.peak_score,
+            regions,
+        }
+    }
+}
@@ -0,0 +1,283 @@
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::Router;
+    use chrono::{DateTime, Duration, Utc};
+    use flux_core::bus::EventBus;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 48;
+
+    /// 灰色背景上在 (x, y) 处画一个 16x16 的白色方块
+    fn frame_with_square(square: Option<(u32, u32)>) -> GrayFrame {
+        let mut data = vec![80u8; (WIDTH * HEIGHT) as usize];
+        if let Some((x0, y0)) = square {
+            for y in y0..(y0 + 16).min(HEIGHT) {
+                for x in x0..(x0 + 16).min(WIDTH) {
+                    data[(y * WIDTH + x) as usize] = 240;
+                }
+            }
+        }
+        GrayFrame {
+            width: WIDTH,
+            height: HEIGHT,
+            data,
+        }
+    }
+
+    fn at(base: DateTime<Utc>, secs: i64) -> DateTime<Utc> {
+        base + Duration::seconds(secs)
+    }
+
+    #[test]
+    fn test_static_scene_has_no_motion() {
+        let mut detector = MotionDetector::new(MotionConfig::default());
+        let base = Utc::now();
+        for i in 0..10 {
+            assert!(detector.process(&frame_with_square(None), at(base, i)).is_none());
+        }
+        assert!(!detector.is_active());
+    }
+
+    #[test]
+    fn test_motion_start_and_debounced_stop() {
+        let config = MotionConfig {
+            start_frames: 2,
+            stop_after_ms: 3000,
+            ..Default::default()
+        };
+        let mut detector = MotionDetector::new(config);
+        let base = Utc::now();
+        assert!(detector.process(&frame_with_square(None), base).is_none());
+
+        // 第一帧运动不触发，连续第二帧触发开始
+        assert!(detector.process(&frame_with_square(Some((0, 0))), at(base, 1)).is_none());
+        let start = detector.process(&frame_with_square(Some((20, 10))), at(base, 2)).unwrap();
+        assert_eq!(start.state, MotionState::Start);
+        assert_eq!(start.regions, vec!["full".to_string()]);
+        assert!(start.score > 0.05);
+
+        // 静止未满 3 秒不结束
+        assert!(detector.process(&frame_with_square(None), at(base, 3)).is_none());
+        assert!(detector.process(&frame_with_square(None), at(base, 4)).is_none());
+        assert!(detector.is_active());
+        let stop = detector.process(&frame_with_square(None), at(base, 5)).unwrap();
+        assert_eq!(stop.state, MotionState::Stop);
+        assert!(!detector.is_active());
+    }
+
+    #[test]
+    fn test_regions_of_interest() {
+        let config = MotionConfig {
+            start_frames: 1,
+            regions: vec![MotionRegion {
+                name: "door".to_string(),
+                x: 0.5,
+                y: 0.0,
+                width: 0.5,
+                height: 1.0,
+            }],
+            ..Default::default()
+        };
+        let mut detector = MotionDetector::new(config);
+        let base = Utc::now();
+        detector.process(&frame_with_square(None), base);
+
+        // 左半边的运动不在检测区域内
+        assert!(detector.process(&frame_with_square(Some((0, 16))), at(base, 1)).is_none());
+        let event = detector.process(&frame_with_square(Some((44, 16))), at(base, 2)).unwrap();
+        assert_eq!(event.regions, vec!["door".to_string()]);
+    }
+
+    #[test]
+    fn test_sensitivity_threshold() {
+        let low = MotionConfig {
+            sensitivity: 1,
+            start_frames: 1,
+            ..Default::default()
+        };
+        let high = MotionConfig {
+            sensitivity: 100,
+            ..low.clone()
+        };
+        assert!(low.pixel_threshold() > high.pixel_threshold());
+
+        // 亮度变化 30：低灵敏度忽略，高灵敏度触发
+        let dim = GrayFrame {
+            width: WIDTH,
+            height: HEIGHT,
+            data: vec![110u8; (WIDTH * HEIGHT) as usize],
+        };
+        let base = Utc::now();
+        let mut detector = MotionDetector::new(low);
+        detector.process(&frame_with_square(None), base);
+        assert!(detector.process(&dim, at(base, 1)).is_none());
+        let mut detector = MotionDetector::new(high);
+        detector.process(&frame_with_square(None), base);
+        assert!(detector.process(&dim, at(base, 1)).is_some());
+    }
+
+    #[test]
+    fn test_gray_frame_conversion() {
+        let frame = GrayFrame::from_rgb(2, 1, &[255, 255, 255, 0, 0, 0]);
+        assert_eq!(frame.data, vec![255, 0]);
+
+        let frame = GrayFrame {
+            width: 4,
+            height: 2,
+            data: vec![0, 100, 200, 200, 0, 100, 200, 200],
+        };
+        let scaled = frame.downscale(2);
+        assert_eq!((scaled.width, scaled.height), (2, 1));
+        assert_eq!(scaled.data, vec![50, 200]);
+    }
+
+    #[test]
+    fn test_truncated_frame_is_ignored() {
+        let mut detector = MotionDetector::new(MotionConfig {
+            analysis_width: 32,
+            ..Default::default()
+        });
+        let base = Utc::now();
+        detector.process(&frame_with_square(None), base);
+
+        // 截断的 RGB 解码结果：数据不足 width*height
+        let rgb = vec![200u8; (WIDTH * HEIGHT * 3 / 2) as usize];
+        let truncated = GrayFrame::from_rgb(WIDTH, HEIGHT, &rgb);
+        assert!(!truncated.is_valid());
+        assert_eq!(truncated.downscale(32).data.len(), truncated.data.len());
+        assert!(detector.process(&truncated, at(base, 1)).is_none());
+        let empty = GrayFrame {
+            width: 0,
+            height: 0,
+            data: Vec::new(),
+        };
+        assert!(detector.process(&empty, at(base, 2)).is_none());
+
+        // 之后的正常帧仍按原背景检测
+        assert!(detector.process(&frame_with_square(None), at(base, 3)).is_none());
+        assert!(!detector.is_active());
+    }
+
+    #[tokio::test]
+    async fn test_analyzer_publishes_events() {
+        let bus = Arc::new(EventBus::new(16));
+        let mut events = bus.subscribe();
+        let analyzer = MotionAnalyzer::new(Some(bus.clone()));
+        let config = MotionConfig {
+            start_frames: 1,
+            ..Default::default()
+        };
+        analyzer.set_config("rtsp/cam1", config).await.unwrap();
+
+        let base = Utc::now();
+        analyzer.process_frame("rtsp/cam1", &frame_with_square(None), base).await.unwrap();
+        let event = analyzer
+            .process_frame("rtsp/cam1", &frame_with_square(Some((8, 8))), at(base, 1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.state, MotionState::Start);
+
+        let message = events.recv().await.unwrap();
+        assert_eq!(message.topic, format!("{}rtsp/cam1", MOTION_TOPIC_PREFIX));
+        assert_eq!(message.payload["state"], "start");
+        assert_eq!(message.payload["stream_id"], "rtsp/cam1");
+
+        // 移除时补发结束事件
+        let status = analyzer.status("rtsp/cam1").await.unwrap();
+        assert!(status.active);
+        assert_eq!(status.frames_analyzed, 2);
+        assert!(analyzer.remove("rtsp/cam1").await);
+        let message = events.recv().await.unwrap();
+        assert_eq!(message.payload["state"], "stop");
+
+        assert!(analyzer
+            .process_frame("rtsp/cam1", &frame_with_square(None), base)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_stream_is_not_analyzed() {
+        let analyzer = MotionAnalyzer::new(None);
+        let config = MotionConfig {
+            enabled: false,
+            start_frames: 1,
+            ..Default::default()
+        };
+        analyzer.set_config("cam", config).await.unwrap();
+        let base = Utc::now();
+        analyzer.process_frame("cam", &frame_with_square(None), base).await.unwrap();
+        let event = analyzer
+            .process_frame("cam", &frame_with_square(Some((8, 8))), at(base, 1))
+            .await
+            .unwrap();
+        assert!(event.is_none());
+        assert_eq!(analyzer.status("cam").await.unwrap().frames_analyzed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_motion_router() {
+        let analyzer = Arc::new(MotionAnalyzer::new(None));
+        let app: Router = api::router(analyzer.clone());
+
+        let request = |method: &str, uri: &str, body: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                "PUT",
+                "/api/v1/motion/streams/rtmp/live/cam1",
+                r#"{"sensitivity":80,"regions":[{"name":"gate","x":0.1,"y":0.1,"width":0.5,"height":0.5}]}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let config = analyzer.status("rtmp/live/cam1").await.unwrap().config;
+        assert_eq!(config.sensitivity, 80);
+        assert!(config.enabled);
+        assert_eq!(config.regions[0].name, "gate");
+
+        // 区域越界
+        let response = app
+            .clone()
+            .oneshot(request(
+                "PUT",
+                "/api/v1/motion/streams/cam2",
+                r#"{"regions":[{"name":"bad","x":0.8,"y":0.0,"width":0.5,"height":1.0}]}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.clone().oneshot(request("GET", "/api/v1/motion/streams", "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let statuses: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(statuses.as_array().unwrap().len(), 1);
+
+        let response = app
+            .clone()
+            .oneshot(request("DELETE", "/api/v1/motion/streams/rtmp/live/cam1", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = app
+            .oneshot(request("GET", "/api/v1/motion/streams/rtmp/live/cam1", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
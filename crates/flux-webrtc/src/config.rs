@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// WebRTC 输出配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebRtcConfig {
+    /// 对外公布的 IP（NAT 1:1 映射）；为空时使用本机网卡地址
+    pub public_ips: Vec<String>,
+    /// ICE 使用的 UDP 端口范围；均为 0 时使用系统分配的随机端口
+    pub udp_port_min: u16,
+    pub udp_port_max: u16,
+    /// 最大并发会话数
+    pub max_sessions: usize,
+}
+
+impl Default for WebRtcConfig {
+    fn default() -> Self {
+        Self {
+            public_ips: Vec::new(),
+            udp_port_min: 0,
+            udp_port_max: 0,
+            max_sessions: 256,
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! DTLS-SRTP（RFC 5764）：基于 OpenSSL 的 DTLS 握手与 SRTP 密钥导出
+//!
+//! 握手运行在内存数据报通道上，由会话任务把 UDP 数据报喂入并取出待发送的记录，
+//! 不直接持有 socket。对端证书为自签名，身份通过 SDP 中的 fingerprint 校验。
+
+use crate::error::{Result, WebRtcError};
+use crate::srtp::{MASTER_KEY_LEN, MASTER_SALT_LEN, SRTP_PROFILE};
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{ErrorCode, Ssl, SslContext, SslContextBuilder, SslMethod, SslOptions, SslStream, SslVerifyMode};
+use openssl::x509::{X509NameBuilder, X509Ref, X509};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+/// DTLS 记录的 MTU（与 RTP 负载上限一致，避免 IP 分片）
+const DTLS_MTU: u32 = 1200;
+
+/// RFC 5764 4.2 导出标签
+const SRTP_EXPORTER_LABEL: &str = "EXTRACTOR-dtls_srtp";
+
+/// 自签名证书（每个服务实例一张，fingerprint 写入 SDP answer）
+pub struct DtlsCertificate {
+    certificate: X509,
+    private_key: PKey<Private>,
+    fingerprint: String,
+}
+
+impl DtlsCertificate {
+    /// 生成 ECDSA P-256 自签名证书
+    pub fn generate() -> Result<Self> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let private_key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_nid(Nid::COMMONNAME, "flux-webrtc")?;
+        let name = name.build();
+
+        let mut serial = BigNum::new()?;
+        serial.rand(64, MsbOption::MAYBE_ZERO, false)?;
+
+        let mut builder = X509::builder()?;
+        builder.set_version(2)?;
+        let serial = serial.to_asn1_integer()?;
+        let not_before = Asn1Time::days_from_now(0)?;
+        let not_after = Asn1Time::days_from_now(365)?;
+        builder.set_serial_number(&serial)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&private_key)?;
+        builder.set_not_before(&not_before)?;
+        builder.set_not_after(&not_after)?;
+        builder.sign(&private_key, MessageDigest::sha256())?;
+        let certificate = builder.build();
+
+        let fingerprint = certificate_fingerprint(&certificate)?;
+        Ok(Self {
+            certificate,
+            private_key,
+            fingerprint,
+        })
+    }
+
+    /// SHA-256 指纹（`AB:CD:...`，用于 `a=fingerprint:sha-256`）
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    fn context(&self) -> Result<SslContext> {
+        let mut builder = SslContextBuilder::new(SslMethod::dtls())?;
+        builder.set_certificate(&self.certificate)?;
+        builder.set_private_key(&self.private_key)?;
+        builder.check_private_key()?;
+        builder.set_tlsext_use_srtp(SRTP_PROFILE)?;
+        builder.set_options(SslOptions::NO_QUERY_MTU);
+        // 自签名证书：握手阶段放行，完成后比对 SDP fingerprint
+        builder.set_verify_callback(
+            SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+            |_, _| true,
+        );
+        Ok(builder.build())
+    }
+}
+
+/// 计算证书的 SHA-256 指纹
+pub fn certificate_fingerprint(certificate: &X509Ref) -> Result<String> {
+    let digest = certificate.digest(MessageDigest::sha256())?;
+    Ok(digest
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(":"))
+}
+
+/// DTLS 角色（由 SDP `a=setup` 协商：passive 为服务端，active 为客户端）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtlsRole {
+    Client,
+    Server,
+}
+
+/// 从 DTLS 导出的 SRTP 主密钥（本端用于发送，对端用于接收）
+#[derive(Debug, Clone)]
+pub struct SrtpKeys {
+    pub local_key: Vec<u8>,
+    pub local_salt: Vec<u8>,
+    pub remote_key: Vec<u8>,
+    pub remote_salt: Vec<u8>,
+}
+
+/// 内存数据报通道：每次 read 返回一个完整数据报
+#[derive(Default)]
+struct DatagramChannel {
+    incoming: VecDeque<Vec<u8>>,
+    outgoing: Vec<Vec<u8>>,
+}
+
+impl Read for DatagramChannel {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.incoming.pop_front() {
+            Some(datagram) => {
+                let len = datagram.len().min(buf.len());
+                buf[..len].copy_from_slice(&datagram[..len]);
+                Ok(len)
+            }
+            None => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl Write for DatagramChannel {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.outgoing.push(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// DTLS 传输（单个 ICE 组件上的一次握手）
+pub struct DtlsTransport {
+    stream: SslStream<DatagramChannel>,
+    role: DtlsRole,
+    connected: bool,
+}
+
+impl DtlsTransport {
+    pub fn new(certificate: &DtlsCertificate, role: DtlsRole) -> Result<Self> {
+        let context = certificate.context()?;
+        let mut ssl = Ssl::new(&context)?;
+        ssl.set_mtu(DTLS_MTU)?;
+        let stream = SslStream::new(ssl, DatagramChannel::default())?;
+
+        Ok(Self {
+            stream,
+            role,
+            connected: false,
+        })
+    }
+
+    pub fn role(&self) -> DtlsRole {
+        self.role
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// 客户端发起握手（ClientHello）；服务端无需调用
+    pub fn start(&mut self) -> Result<Vec<Vec<u8>>> {
+        self.advance()?;
+        Ok(self.take_outgoing())
+    }
+
+    /// 处理收到的 DTLS 数据报，返回需要发送的数据报
+    pub fn handle_datagram(&mut self, datagram: &[u8]) -> Result<Vec<Vec<u8>>> {
+        self.stream.get_mut().incoming.push_back(datagram.to_vec());
+
+        if self.connected {
+            // 握手完成后只会收到告警或重传，读出丢弃即可
+            let mut buf = [0u8; 1500];
+            loop {
+                match self.stream.ssl_read(&mut buf) {
+                    Ok(_) => continue,
+                    Err(e) if e.code() == ErrorCode::WANT_READ => break,
+                    Err(e) if e.code() == ErrorCode::ZERO_RETURN => {
+                        return Err(WebRtcError::Dtls("Peer closed DTLS".to_string()));
+                    }
+                    Err(e) => return Err(WebRtcError::Dtls(e.to_string())),
+                }
+            }
+        } else {
+            self.advance()?;
+        }
+
+        Ok(self.take_outgoing())
+    }
+
+    /// 对端证书指纹（握手完成后可用）
+    pub fn remote_fingerprint(&self) -> Option<String> {
+        self.stream
+            .ssl()
+            .peer_certificate()
+            .and_then(|certificate| certificate_fingerprint(&certificate).ok())
+    }
+
+    /// 导出 SRTP 主密钥：client_key | server_key | client_salt | server_salt
+    pub fn srtp_keys(&self) -> Result<SrtpKeys> {
+        if !self.connected {
+            return Err(WebRtcError::Dtls("Handshake not complete".to_string()));
+        }
+
+        let profile = self
+            .stream
+            .ssl()
+            .selected_srtp_profile()
+            .map(|profile| profile.name().to_string());
+        if profile.as_deref() != Some(SRTP_PROFILE) {
+            return Err(WebRtcError::Dtls(format!("Unsupported SRTP profile: {:?}", profile)));
+        }
+
+        let mut material = [0u8; 2 * (MASTER_KEY_LEN + MASTER_SALT_LEN)];
+        self.stream
+            .ssl()
+            .export_keying_material(&mut material, SRTP_EXPORTER_LABEL, None)?;
+
+        let (client_key, rest) = material.split_at(MASTER_KEY_LEN);
+        let (server_key, rest) = rest.split_at(MASTER_KEY_LEN);
+        let (client_salt, server_salt) = rest.split_at(MASTER_SALT_LEN);
+
+        Ok(match self.role {
+            DtlsRole::Client => SrtpKeys {
+                local_key: client_key.to_vec(),
+                local_salt: client_salt.to_vec(),
+                remote_key: server_key.to_vec(),
+                remote_salt: server_salt.to_vec(),
+            },
+            DtlsRole::Server => SrtpKeys {
+                local_key: server_key.to_vec(),
+                local_salt: server_salt.to_vec(),
+                remote_key: client_key.to_vec(),
+                remote_salt: client_salt.to_vec(),
+            },
+        })
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        let result = match self.role {
+            DtlsRole::Client => self.stream.connect(),
+            DtlsRole::Server => self.stream.accept(),
+        };
+
+        match result {
+            Ok(()) => {
+                self.connected = true;
+                Ok(())
+            }
+            Err(e) if e.code() == ErrorCode::WANT_READ || e.code() == ErrorCode::WANT_WRITE => Ok(()),
+            Err(e) => Err(WebRtcError::Dtls(e.to_string())),
+        }
+    }
+
+    fn take_outgoing(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.stream.get_mut().outgoing)
+    }
+}
+
+/// 按 RFC 7983 判断复用端口上的数据报是否为 DTLS 记录
+pub fn is_dtls(data: &[u8]) -> bool {
+    data.first().is_some_and(|byte| (20..=63).contains(byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dtls_handshake_exports_matching_keys() {
+        let server_cert = DtlsCertificate::generate().unwrap();
+        let client_cert = DtlsCertificate::generate().unwrap();
+        assert_eq!(server_cert.fingerprint().len(), 32 * 3 - 1);
+
+        let mut server = DtlsTransport::new(&server_cert, DtlsRole::Server).unwrap();
+        let mut client = DtlsTransport::new(&client_cert, DtlsRole::Client).unwrap();
+
+        let mut to_server = client.start().unwrap();
+        assert!(to_server.iter().all(|datagram| is_dtls(datagram)));
+
+        for _ in 0..10 {
+            let mut to_client = Vec::new();
+            for datagram in to_server.drain(..) {
+                to_client.extend(server.handle_datagram(&datagram).unwrap());
+            }
+            for datagram in to_client {
+                to_server.extend(client.handle_datagram(&datagram).unwrap());
+            }
+            if client.is_connected() && server.is_connected() {
+                break;
+            }
+        }
+
+        assert!(client.is_connected() && server.is_connected());
+        assert_eq!(server.remote_fingerprint().as_deref(), Some(client_cert.fingerprint()));
+        assert_eq!(client.remote_fingerprint().as_deref(), Some(server_cert.fingerprint()));
+
+        let server_keys = server.srtp_keys().unwrap();
+        let client_keys = client.srtp_keys().unwrap();
+        assert_eq!(server_keys.local_key, client_keys.remote_key);
+        assert_eq!(server_keys.remote_salt, client_keys.local_salt);
+        assert_ne!(server_keys.local_key, server_keys.remote_key);
+    }
+}
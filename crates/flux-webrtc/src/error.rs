@@ -0,0 +1,42 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum WebRtcError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Stream not found: {0}")]
+    StreamNotFound(String),
+
+    #[error("Stream already exists: {0}")]
+    StreamExists(String),
+
+    #[error("Session not found: {0}")]
+    SessionNotFound(String),
+
+    #[error("Unsupported codec: {0}")]
+    UnsupportedCodec(String),
+
+    #[error("Invalid SDP: {0}")]
+    InvalidSdp(String),
+
+    #[error("Invalid packet: {0}")]
+    InvalidPacket(String),
+
+    #[error("DTLS error: {0}")]
+    Dtls(String),
+
+    #[error("SRTP authentication failed")]
+    SrtpAuth,
+
+    #[error("Session limit reached: {0}")]
+    SessionLimit(usize),
+
+    #[error("Crypto error: {0}")]
+    Crypto(#[from] openssl::error::ErrorStack),
+
+    #[error("Other error: {0}")]
+    Other(#[from] anyhow::Error),
+}
+
+pub type Result<T> = std::result::Result<T, WebRtcError>;
@@ -0,0 +1,187 @@
+//! ICE-lite（RFC 8445 2.5）：只响应对端的连通性检查，由对端（控制方）提名候选对
+
+use crate::config::WebRtcConfig;
+use crate::stun::{self, StunMessage, ATTR_USE_CANDIDATE, BINDING_REQUEST};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// host 候选的最高优先级（type preference 126，local preference 65535，component 1）
+const HOST_CANDIDATE_PRIORITY: u32 = 2_130_706_431;
+
+/// 本端 ICE 凭证
+#[derive(Debug, Clone)]
+pub struct IceCredentials {
+    pub ufrag: String,
+    pub pwd: String,
+}
+
+impl IceCredentials {
+    /// 随机生成（ufrag ≥ 4 字符，pwd ≥ 22 字符）
+    pub fn generate() -> Self {
+        Self {
+            ufrag: random_string(8),
+            pwd: random_string(24),
+        }
+    }
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// 候选地址：配置的公网 IP 优先，否则探测默认路由网卡地址，探测失败退回回环地址
+///
+/// 会话 socket 绑定在 IPv4 通配地址上，因此只公布 IPv4 候选。
+pub fn candidate_addresses(config: &WebRtcConfig) -> Vec<IpAddr> {
+    let configured: Vec<IpAddr> = config
+        .public_ips
+        .iter()
+        .filter_map(|ip| ip.parse().ok())
+        .filter(IpAddr::is_ipv4)
+        .collect();
+    if !configured.is_empty() {
+        return configured;
+    }
+
+    vec![default_route_address().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))]
+}
+
+/// UDP connect 不发送数据，只借助路由表选出出口地址
+fn default_route_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified()).then_some(ip)
+}
+
+/// 生成 SDP 中 `a=candidate:` 之后的 host 候选描述
+pub fn host_candidates(addresses: &[IpAddr], port: u16) -> Vec<String> {
+    addresses
+        .iter()
+        .enumerate()
+        .map(|(index, ip)| {
+            format!(
+                "{} 1 udp {} {} {} typ host",
+                index + 1,
+                HOST_CANDIDATE_PRIORITY - index as u32,
+                ip,
+                port
+            )
+        })
+        .collect()
+}
+
+/// ICE-lite 代理（单组件，rtcp-mux + BUNDLE）
+pub struct IceLiteAgent {
+    local: IceCredentials,
+    remote_ufrag: String,
+    selected: Option<SocketAddr>,
+    last_check: Option<Instant>,
+}
+
+impl IceLiteAgent {
+    pub fn new(local: IceCredentials, remote_ufrag: String) -> Self {
+        Self {
+            local,
+            remote_ufrag,
+            selected: None,
+            last_check: None,
+        }
+    }
+
+    pub fn local_credentials(&self) -> &IceCredentials {
+        &self.local
+    }
+
+    /// 对端提名的地址（媒体发送目标）
+    pub fn selected_address(&self) -> Option<SocketAddr> {
+        self.selected
+    }
+
+    /// 对端是否在 `timeout` 内仍有连通性检查（RFC 7675 consent freshness）
+    pub fn is_alive(&self, timeout: Duration) -> bool {
+        self.last_check
+            .is_some_and(|last_check| last_check.elapsed() < timeout)
+    }
+
+    /// 处理 STUN 数据报，返回需要回送的响应
+    pub fn handle_stun(&mut self, data: &[u8], from: SocketAddr) -> Option<Vec<u8>> {
+        let message = StunMessage::decode(data).ok()?;
+        if message.message_type != BINDING_REQUEST {
+            return None;
+        }
+
+        // 对端发来的检查：USERNAME = 本端ufrag:对端ufrag，以本端 pwd 签名
+        let expected_username = format!("{}:{}", self.local.ufrag, self.remote_ufrag);
+        if message.username() != Some(expected_username.as_str())
+            || !message.verify_integrity(data, &self.local.pwd)
+        {
+            debug!(target: "webrtc", %from, "Rejecting unauthenticated STUN binding request");
+            return stun::binding_error(&message.transaction_id, 401, "Unauthorized").ok();
+        }
+
+        self.last_check = Some(Instant::now());
+        if message.has_attribute(ATTR_USE_CANDIDATE) && self.selected != Some(from) {
+            debug!(target: "webrtc", %from, "ICE candidate pair nominated");
+            self.selected = Some(from);
+        }
+
+        stun::binding_success(&message.transaction_id, from, &self.local.pwd).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ice_lite_nomination() {
+        let local = IceCredentials::generate();
+        let mut agent = IceLiteAgent::new(local.clone(), "peer".to_string());
+        let from: SocketAddr = "192.168.1.20:50000".parse().unwrap();
+        let tid = [7u8; 12];
+
+        // 错误的密码 → 401
+        let request =
+            stun::binding_request(&tid, &format!("{}:peer", local.ufrag), "wrong", 1, 1, true).unwrap();
+        let response = agent.handle_stun(&request, from).unwrap();
+        assert_eq!(StunMessage::decode(&response).unwrap().message_type, stun::BINDING_ERROR);
+        assert!(agent.selected_address().is_none());
+
+        // 未提名的检查只刷新 consent
+        let request =
+            stun::binding_request(&tid, &format!("{}:peer", local.ufrag), &local.pwd, 1, 1, false).unwrap();
+        let response = agent.handle_stun(&request, from).unwrap();
+        let response = StunMessage::decode(&response).unwrap();
+        assert_eq!(response.message_type, stun::BINDING_SUCCESS);
+        assert_eq!(response.xor_mapped_address(), Some(from));
+        assert!(agent.selected_address().is_none());
+        assert!(agent.is_alive(Duration::from_secs(30)));
+
+        let request =
+            stun::binding_request(&tid, &format!("{}:peer", local.ufrag), &local.pwd, 1, 1, true).unwrap();
+        agent.handle_stun(&request, from).unwrap();
+        assert_eq!(agent.selected_address(), Some(from));
+    }
+
+    #[test]
+    fn test_host_candidates() {
+        let config = WebRtcConfig {
+            public_ips: vec!["203.0.113.7".to_string(), "::1".to_string()],
+            ..Default::default()
+        };
+        let addresses = candidate_addresses(&config);
+        assert_eq!(addresses, vec!["203.0.113.7".parse::<IpAddr>().unwrap()]);
+        assert_eq!(
+            host_candidates(&addresses, 40000),
+            vec!["1 1 udp 2130706431 203.0.113.7 40000 typ host".to_string()]
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! 接收侧解包：H.264 RTP → Annex B 访问单元（复用 flux-rtspd 的 H264Depacketizer），
+//! RTP 时间戳 → 毫秒
+
+use crate::rtp::RtpPacket;
+use bytes::Bytes;
+use flux_media_core::codec::ANNEXB_START_CODE;
+use flux_rtspd::h264_depacketizer::H264Depacketizer;
+use flux_rtspd::rtp_receiver::RtpPacket as RtspRtpPacket;
+use tracing::debug;
+
+const NAL_TYPE_IDR: u8 = 5;
+const NAL_TYPE_SPS: u8 = 7;
+const NAL_TYPE_PPS: u8 = 8;
+const NAL_TYPE_AUD: u8 = 9;
+
+/// 组装完成的 H.264 访问单元
+#[derive(Debug, Clone)]
+pub struct AccessUnit {
+    /// Annex B 格式，关键帧前带 SPS/PPS
+    pub data: Bytes,
+    pub rtp_timestamp: u32,
+    pub is_keyframe: bool,
+}
+
+/// H.264 访问单元组装器：按 RTP 时间戳聚合 NALU，marker 位或时间戳变化时输出
+pub struct H264AccessUnitAssembler {
+    depacketizer: H264Depacketizer,
+    nalus: Vec<Bytes>,
+    timestamp: Option<u32>,
+    last_sequence: Option<u16>,
+    /// 当前访问单元是否丢包（丢包后整帧丢弃，等待下一个关键帧）
+    corrupted: bool,
+    waiting_keyframe: bool,
+    sps: Option<Bytes>,
+    pps: Option<Bytes>,
+}
+
+impl H264AccessUnitAssembler {
+    pub fn new() -> Self {
+        Self {
+            depacketizer: H264Depacketizer::new(),
+            nalus: Vec::new(),
+            timestamp: None,
+            last_sequence: None,
+            corrupted: false,
+            waiting_keyframe: true,
+            sps: None,
+            pps: None,
+        }
+    }
+
+    /// 是否在等待关键帧（启动或丢包后），可据此发送 PLI
+    pub fn is_waiting_keyframe(&self) -> bool {
+        self.waiting_keyframe
+    }
+
+    /// 处理一个 RTP 包，返回完成的访问单元（上一帧缺少 marker 位时可能一次输出两个）
+    pub fn push(&mut self, packet: &RtpPacket) -> Vec<AccessUnit> {
+        let mut completed = Vec::new();
+
+        if self.timestamp.is_some_and(|timestamp| timestamp != packet.timestamp) {
+            // 上一帧缺少 marker 位（丢包或发送端不置位）
+            completed.extend(self.flush());
+        }
+
+        if let Some(last) = self.last_sequence {
+            if packet.sequence_number != last.wrapping_add(1) {
+                debug!(target: "webrtc", expected = last.wrapping_add(1), got = packet.sequence_number, "RTP sequence gap");
+                self.corrupted = true;
+            }
+        }
+        self.last_sequence = Some(packet.sequence_number);
+        self.timestamp = Some(packet.timestamp);
+
+        let rtsp_packet = RtspRtpPacket {
+            version: 2,
+            padding: false,
+            extension: false,
+            csrc_count: 0,
+            marker: packet.marker,
+            payload_type: packet.payload_type,
+            sequence_number: packet.sequence_number,
+            timestamp: packet.timestamp,
+            ssrc: packet.ssrc,
+            payload: packet.payload.clone(),
+        };
+        match self.depacketizer.process_rtp(rtsp_packet) {
+            Ok(nalus) => self.nalus.extend(nalus.into_iter().map(|nalu| nalu.data)),
+            Err(_) => self.corrupted = true,
+        }
+
+        if packet.marker {
+            completed.extend(self.flush());
+        }
+        completed
+    }
+
+    fn flush(&mut self) -> Option<AccessUnit> {
+        let timestamp = self.timestamp.take()?;
+        let nalus = std::mem::take(&mut self.nalus);
+        if std::mem::take(&mut self.corrupted) {
+            self.waiting_keyframe = true;
+            return None;
+        }
+
+        let mut is_keyframe = false;
+        let mut has_parameter_sets = false;
+        for nalu in &nalus {
+            match nalu.first().map(|header| header & 0x1F) {
+                Some(NAL_TYPE_SPS) => {
+                    self.sps = Some(nalu.clone());
+                    has_parameter_sets = true;
+                }
+                Some(NAL_TYPE_PPS) => self.pps = Some(nalu.clone()),
+                Some(NAL_TYPE_IDR) => is_keyframe = true,
+                _ => {}
+            }
+        }
+
+        if self.waiting_keyframe {
+            if !is_keyframe || self.sps.is_none() || self.pps.is_none() {
+                return None;
+            }
+            self.waiting_keyframe = false;
+        }
+
+        let mut data = Vec::with_capacity(nalus.iter().map(|nalu| nalu.len() + 4).sum::<usize>() + 64);
+        if is_keyframe && !has_parameter_sets {
+            for nalu in [&self.sps, &self.pps].into_iter().flatten() {
+                data.extend_from_slice(&ANNEXB_START_CODE);
+                data.extend_from_slice(nalu);
+            }
+        }
+        for nalu in nalus.iter().filter(|nalu| !nalu.is_empty() && nalu[0] & 0x1F != NAL_TYPE_AUD) {
+            data.extend_from_slice(&ANNEXB_START_CODE);
+            data.extend_from_slice(nalu);
+        }
+        if data.is_empty() {
+            return None;
+        }
+
+        Some(AccessUnit {
+            data: Bytes::from(data),
+            rtp_timestamp: timestamp,
+            is_keyframe,
+        })
+    }
+}
+
+impl Default for H264AccessUnitAssembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// RTP 时间戳 → 毫秒：展开 32 位回绕，起点对齐到首包到达时刻（相对会话开始）
+///
+/// 各轨道的 RTP 时间戳起点随机且互不相关，按到达时刻对齐足以保证音视频大致同步。
+pub struct TimestampMapper {
+    clock_rate: u32,
+    offset_ms: u64,
+    base: Option<u32>,
+    last: u32,
+    extended: u64,
+}
+
+impl TimestampMapper {
+    pub fn new(clock_rate: u32) -> Self {
+        Self {
+            clock_rate: clock_rate.max(1),
+            offset_ms: 0,
+            base: None,
+            last: 0,
+            extended: 0,
+        }
+    }
+
+    /// 转换时间戳；`arrival_ms` 仅在首包时用作起点
+    pub fn map(&mut self, rtp_timestamp: u32, arrival_ms: u64) -> u32 {
+        match self.base {
+            None => {
+                self.base = Some(rtp_timestamp);
+                self.offset_ms = arrival_ms;
+                self.extended = 0;
+            }
+            Some(_) => {
+                // 有符号差值：允许少量乱序
+                let delta = rtp_timestamp.wrapping_sub(self.last) as i32 as i64;
+                self.extended = (self.extended as i64 + delta).max(0) as u64;
+            }
+        }
+        self.last = rtp_timestamp;
+        (self.offset_ms + self.extended * 1000 / self.clock_rate as u64) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(sequence_number: u16, timestamp: u32, marker: bool, payload: &[u8]) -> RtpPacket {
+        RtpPacket {
+            marker,
+            payload_type: 102,
+            sequence_number,
+            timestamp,
+            ssrc: 1,
+            payload: Bytes::copy_from_slice(payload),
+        }
+    }
+
+    #[test]
+    fn test_assemble_keyframe_and_gap() {
+        let mut assembler = H264AccessUnitAssembler::new();
+
+        // P 帧在关键帧之前被丢弃
+        assert!(assembler.push(&packet(1, 0, true, &[0x41, 0x01])).is_empty());
+        assert!(assembler.is_waiting_keyframe());
+
+        // STAP-A(SPS, PPS) + FU-A(IDR)
+        let stap_a = [0x18, 0x00, 0x04, 0x67, 0x42, 0xE0, 0x1F, 0x00, 0x02, 0x68, 0xCE];
+        assert!(assembler.push(&packet(2, 3000, false, &stap_a)).is_empty());
+        assert!(assembler.push(&packet(3, 3000, false, &[0x7C, 0x85, 0xAA])).is_empty());
+        let unit = assembler.push(&packet(4, 3000, true, &[0x7C, 0x45, 0xBB])).remove(0);
+        assert!(unit.is_keyframe);
+        assert_eq!(unit.rtp_timestamp, 3000);
+        assert_eq!(
+            unit.data.as_ref(),
+            &[0, 0, 0, 1, 0x67, 0x42, 0xE0, 0x1F, 0, 0, 0, 1, 0x68, 0xCE, 0, 0, 0, 1, 0x65, 0xAA, 0xBB]
+        );
+        assert!(!assembler.is_waiting_keyframe());
+
+        // 丢包的帧被丢弃，之后的关键帧补上参数集
+        assert!(assembler.push(&packet(6, 6000, true, &[0x41, 0x02])).is_empty());
+        assert!(assembler.push(&packet(7, 9000, true, &[0x41, 0x03])).is_empty());
+        assert!(assembler.is_waiting_keyframe());
+        let unit = assembler.push(&packet(8, 12000, true, &[0x65, 0x04])).remove(0);
+        assert!(unit.is_keyframe);
+        assert_eq!(&unit.data[..8], &[0, 0, 0, 1, 0x67, 0x42, 0xE0, 0x1F]);
+
+        // 无 marker 位时由时间戳变化触发输出
+        assert!(assembler.push(&packet(9, 15000, false, &[0x41, 0x05])).is_empty());
+        let units = assembler.push(&packet(10, 18000, true, &[0x41, 0x06]));
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].rtp_timestamp, 15000);
+        assert_eq!(units[1].rtp_timestamp, 18000);
+        assert!(!units[0].is_keyframe);
+    }
+
+    #[test]
+    fn test_timestamp_mapper_wraparound() {
+        let mut mapper = TimestampMapper::new(90000);
+        assert_eq!(mapper.map(u32::MAX - 8999, 500), 500);
+        assert_eq!(mapper.map(0, 600), 600);
+        // 回绕后继续递增
+        assert_eq!(mapper.map(9000, 700), 700);
+        // 轻微乱序不会倒退到负值
+        assert_eq!(mapper.map(8100, 710), 690);
+    }
+}
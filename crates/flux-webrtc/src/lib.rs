@@ -0,0 +1,23 @@
+//! WebRTC 输入输出：ICE-lite + DTLS-SRTP，H.264 / Opus / G.711 直通，
+//! 通过 WHEP 播放、WHIP 推流接入 flux-stream
+pub mod config;
+pub mod dtls;
+pub mod error;
+pub mod ice;
+pub mod ingest;
+pub mod packetizer;
+pub mod rtp;
+pub mod sdp;
+pub mod session;
+pub mod srtp;
+pub mod stun;
+mod transport;
+pub mod whep;
+pub mod whip;
+
+pub use config::WebRtcConfig;
+pub use error::{Result, WebRtcError};
+pub use packetizer::RtpPacketizer;
+pub use session::{EgressAudioCodec, EgressCodecs, WhepSession};
+pub use whep::WhepServer;
+pub use whip::{WhipServer, WhipSession};
@@ -0,0 +1,160 @@
+//! RTP 打包：H.264（RFC 6184 单 NALU / FU-A）与单帧音频（Opus、G.711）
+
+use crate::rtp::RtpPacket;
+use bytes::{BufMut, Bytes, BytesMut};
+use flux_media_core::codec::split_annexb;
+
+/// 默认 RTP 负载上限（为 SRTP/UDP/IP 头与隧道预留空间）
+pub const DEFAULT_MTU: usize = 1200;
+
+/// H.264 FU-A NALU 类型
+const NAL_TYPE_FU_A: u8 = 28;
+
+/// RTP 打包器（每条轨道一个，维护序号与时钟换算）
+pub struct RtpPacketizer {
+    payload_type: u8,
+    ssrc: u32,
+    clock_rate: u32,
+    sequence_number: u16,
+    mtu: usize,
+}
+
+impl RtpPacketizer {
+    pub fn new(payload_type: u8, ssrc: u32, clock_rate: u32) -> Self {
+        Self {
+            payload_type,
+            ssrc,
+            clock_rate,
+            sequence_number: rand::random(),
+            mtu: DEFAULT_MTU,
+        }
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    pub fn with_mtu(mut self, mtu: usize) -> Self {
+        self.mtu = mtu.max(16);
+        self
+    }
+
+    /// 毫秒时间戳 → RTP 时间戳
+    pub fn rtp_timestamp(&self, timestamp_ms: u32) -> u32 {
+        (timestamp_ms as u64 * self.clock_rate as u64 / 1000) as u32
+    }
+
+    /// 打包一个 Annex B 访问单元，最后一个包置 marker 位
+    pub fn packetize_h264(&mut self, access_unit: &[u8], timestamp_ms: u32) -> Vec<RtpPacket> {
+        let timestamp = self.rtp_timestamp(timestamp_ms);
+        let mut payloads = Vec::new();
+
+        for nalu in split_annexb(access_unit) {
+            if nalu.len() <= self.mtu {
+                payloads.push(Bytes::copy_from_slice(nalu));
+                continue;
+            }
+
+            // FU-A 分片：FU indicator 保留 F/NRI，FU header 携带 S/E 位与原类型
+            let indicator = (nalu[0] & 0xE0) | NAL_TYPE_FU_A;
+            let nal_type = nalu[0] & 0x1F;
+            let chunks: Vec<&[u8]> = nalu[1..].chunks(self.mtu - 2).collect();
+            let last = chunks.len() - 1;
+
+            for (index, chunk) in chunks.into_iter().enumerate() {
+                let mut header = nal_type;
+                if index == 0 {
+                    header |= 0x80;
+                }
+                if index == last {
+                    header |= 0x40;
+                }
+
+                let mut payload = BytesMut::with_capacity(chunk.len() + 2);
+                payload.put_u8(indicator);
+                payload.put_u8(header);
+                payload.put_slice(chunk);
+                payloads.push(payload.freeze());
+            }
+        }
+
+        let count = payloads.len();
+        payloads
+            .into_iter()
+            .enumerate()
+            .map(|(index, payload)| self.next_packet(payload, timestamp, index + 1 == count))
+            .collect()
+    }
+
+    /// 打包单个音频帧（Opus / G.711 每帧一个 RTP 包）
+    pub fn packetize_frame(&mut self, frame: &[u8], timestamp_ms: u32) -> RtpPacket {
+        let timestamp = self.rtp_timestamp(timestamp_ms);
+        self.next_packet(Bytes::copy_from_slice(frame), timestamp, false)
+    }
+
+    fn next_packet(&mut self, payload: Bytes, timestamp: u32, marker: bool) -> RtpPacket {
+        let sequence_number = self.sequence_number;
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+
+        RtpPacket {
+            marker,
+            payload_type: self.payload_type,
+            sequence_number,
+            timestamp,
+            ssrc: self.ssrc,
+            payload,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packetize_single_nalus() {
+        let mut packetizer = RtpPacketizer::new(96, 1, 90000);
+        let access_unit = [0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x68, 0xCE, 0, 0, 0, 1, 0x65, 0x88];
+        let packets = packetizer.packetize_h264(&access_unit, 1000);
+
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[0].payload.as_ref(), &[0x67, 0x42]);
+        assert_eq!(packets[2].payload.as_ref(), &[0x65, 0x88]);
+        assert!(!packets[0].marker);
+        assert!(packets[2].marker);
+        assert!(packets.iter().all(|packet| packet.timestamp == 90000));
+        assert_eq!(
+            packets[1].sequence_number,
+            packets[0].sequence_number.wrapping_add(1)
+        );
+    }
+
+    #[test]
+    fn test_packetize_fu_a() {
+        let mut packetizer = RtpPacketizer::new(96, 1, 90000).with_mtu(100);
+        let mut access_unit = vec![0, 0, 0, 1, 0x65];
+        access_unit.extend((0..250).map(|i| (i % 200) as u8 + 1));
+        let packets = packetizer.packetize_h264(&access_unit, 0);
+
+        // 250 字节负载按 98 字节切分为 3 片
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[0].payload[0], 0x60 | NAL_TYPE_FU_A);
+        assert_eq!(packets[0].payload[1], 0x80 | 0x05);
+        assert_eq!(packets[1].payload[1], 0x05);
+        assert_eq!(packets[2].payload[1], 0x40 | 0x05);
+        assert!(packets[2].marker);
+
+        let reassembled: Vec<u8> = packets.iter().flat_map(|packet| packet.payload[2..].to_vec()).collect();
+        assert_eq!(reassembled, access_unit[5..]);
+    }
+
+    #[test]
+    fn test_packetize_audio_frame() {
+        let mut packetizer = RtpPacketizer::new(8, 2, 8000);
+        let packet = packetizer.packetize_frame(&[0xD5; 160], 20);
+        assert_eq!(packet.timestamp, 160);
+        assert_eq!(packet.payload.len(), 160);
+        assert_eq!((packet.payload_type, packet.ssrc), (8, 2));
+        assert!(!packet.marker);
+    }
+}
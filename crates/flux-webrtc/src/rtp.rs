@@ -0,0 +1,110 @@
+//! RTP 包（RFC 3550）编解码
+
+use crate::error::{Result, WebRtcError};
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// RTP 固定头长度
+pub const RTP_HEADER_LEN: usize = 12;
+
+/// RTP 包（不含 CSRC 与头扩展的发送侧表示）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpPacket {
+    pub marker: bool,
+    pub payload_type: u8,
+    pub sequence_number: u16,
+    pub timestamp: u32,
+    pub ssrc: u32,
+    pub payload: Bytes,
+}
+
+impl RtpPacket {
+    /// 序列化为网络字节
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(RTP_HEADER_LEN + self.payload.len());
+        buf.put_u8(0x80);
+        buf.put_u8(((self.marker as u8) << 7) | (self.payload_type & 0x7F));
+        buf.put_u16(self.sequence_number);
+        buf.put_u32(self.timestamp);
+        buf.put_u32(self.ssrc);
+        buf.put_slice(&self.payload);
+        buf.freeze()
+    }
+
+    /// 解析 RTP 包（跳过 CSRC 与头扩展，去除填充）
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let header_len = header_len(data)?;
+        let mut payload_end = data.len();
+        if data[0] & 0x20 != 0 {
+            let padding = *data.last().unwrap_or(&0) as usize;
+            if padding == 0 || header_len + padding > data.len() {
+                return Err(WebRtcError::InvalidPacket("Invalid RTP padding".to_string()));
+            }
+            payload_end -= padding;
+        }
+
+        Ok(Self {
+            marker: data[1] & 0x80 != 0,
+            payload_type: data[1] & 0x7F,
+            sequence_number: u16::from_be_bytes([data[2], data[3]]),
+            timestamp: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            ssrc: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+            payload: Bytes::copy_from_slice(&data[header_len..payload_end]),
+        })
+    }
+}
+
+/// RTP 头总长度（含 CSRC 与头扩展）
+pub fn header_len(data: &[u8]) -> Result<usize> {
+    if data.len() < RTP_HEADER_LEN || data[0] >> 6 != 2 {
+        return Err(WebRtcError::InvalidPacket("Invalid RTP header".to_string()));
+    }
+
+    let mut len = RTP_HEADER_LEN + (data[0] & 0x0F) as usize * 4;
+    if data[0] & 0x10 != 0 {
+        if data.len() < len + 4 {
+            return Err(WebRtcError::InvalidPacket("Truncated RTP extension".to_string()));
+        }
+        len += 4 + u16::from_be_bytes([data[len + 2], data[len + 3]]) as usize * 4;
+    }
+
+    if len > data.len() {
+        return Err(WebRtcError::InvalidPacket("Truncated RTP header".to_string()));
+    }
+    Ok(len)
+}
+
+/// 按 RFC 5761 区分复用在同一端口上的 RTCP（PT 192~223）与 RTP
+pub fn is_rtcp(data: &[u8]) -> bool {
+    data.len() >= 2 && (192..=223).contains(&data[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtp_roundtrip() {
+        let packet = RtpPacket {
+            marker: true,
+            payload_type: 96,
+            sequence_number: 65535,
+            timestamp: 90000,
+            ssrc: 0x1234_5678,
+            payload: Bytes::from_static(&[0x65, 0x88]),
+        };
+        let bytes = packet.to_bytes();
+        assert_eq!(&bytes[..2], &[0x80, 0xE0]);
+        assert_eq!(RtpPacket::parse(&bytes).unwrap(), packet);
+        assert!(!is_rtcp(&bytes));
+
+        // 带头扩展（1 个 32 位字）与 2 字节填充
+        let mut data = vec![0xB0, 0x08, 0, 1, 0, 0, 0, 160, 0, 0, 0, 7];
+        data.extend_from_slice(&[0xBE, 0xDE, 0, 1, 0x10, 0xAA, 0, 0]);
+        data.extend_from_slice(&[0xD5, 0xD5, 0, 2]);
+        let parsed = RtpPacket::parse(&data).unwrap();
+        assert_eq!(parsed.payload_type, 8);
+        assert_eq!(parsed.payload.as_ref(), &[0xD5, 0xD5]);
+
+        assert!(is_rtcp(&[0x81, 200, 0, 6]));
+    }
+}
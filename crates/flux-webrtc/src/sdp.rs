@@ -0,0 +1,462 @@
+//! WebRTC SDP（JSEP）解析与 answer 生成，只覆盖 WHEP/WHIP 需要的属性子集
+
+use crate::error::{Result, WebRtcError};
+use std::fmt::Write;
+
+/// 媒体格式（rtpmap + fmtp）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpFormat {
+    pub payload_type: u8,
+    /// 编码名（如 H264、opus、PCMA）
+    pub encoding: String,
+    pub clock_rate: u32,
+    pub channels: Option<u16>,
+    pub fmtp: Option<String>,
+    pub rtcp_fb: Vec<String>,
+}
+
+impl RtpFormat {
+    /// fmtp 参数值（如 `packetization-mode`）
+    pub fn fmtp_param(&self, name: &str) -> Option<&str> {
+        self.fmtp.as_deref()?.split(';').find_map(|param| {
+            let (key, value) = param.trim().split_once('=')?;
+            (key == name).then_some(value)
+        })
+    }
+}
+
+/// 媒体方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    SendRecv,
+    SendOnly,
+    RecvOnly,
+    Inactive,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::SendRecv => "sendrecv",
+            Direction::SendOnly => "sendonly",
+            Direction::RecvOnly => "recvonly",
+            Direction::Inactive => "inactive",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "sendrecv" => Some(Direction::SendRecv),
+            "sendonly" => Some(Direction::SendOnly),
+            "recvonly" => Some(Direction::RecvOnly),
+            "inactive" => Some(Direction::Inactive),
+            _ => None,
+        }
+    }
+}
+
+/// m= 段
+#[derive(Debug, Clone)]
+pub struct MediaDescription {
+    /// video / audio / application
+    pub kind: String,
+    pub port: u16,
+    pub protocol: String,
+    /// m= 行中的格式（原样保留，拒绝时回填）
+    pub format_ids: Vec<String>,
+    pub mid: Option<String>,
+    pub direction: Direction,
+    pub formats: Vec<RtpFormat>,
+    pub ssrcs: Vec<u32>,
+    pub ice_ufrag: Option<String>,
+    pub ice_pwd: Option<String>,
+    pub fingerprint: Option<String>,
+    pub setup: Option<String>,
+}
+
+/// 会话描述
+#[derive(Debug, Clone, Default)]
+pub struct SessionDescription {
+    pub ice_ufrag: Option<String>,
+    pub ice_pwd: Option<String>,
+    /// `sha-256 AB:CD:...`
+    pub fingerprint: Option<String>,
+    pub setup: Option<String>,
+    pub media: Vec<MediaDescription>,
+}
+
+impl SessionDescription {
+    pub fn parse(sdp: &str) -> Result<Self> {
+        let mut session = SessionDescription::default();
+
+        for line in sdp.lines() {
+            let line = line.trim_end_matches('\r');
+            let Some((kind, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            match kind {
+                "m" => session.media.push(parse_media_line(value)?),
+                "a" => {
+                    let (name, attr_value) = value.split_once(':').unwrap_or((value, ""));
+                    match session.media.last_mut() {
+                        Some(media) => apply_media_attribute(media, name, attr_value),
+                        None => match name {
+                            "ice-ufrag" => session.ice_ufrag = Some(attr_value.to_string()),
+                            "ice-pwd" => session.ice_pwd = Some(attr_value.to_string()),
+                            "fingerprint" => session.fingerprint = Some(attr_value.to_string()),
+                            "setup" => session.setup = Some(attr_value.to_string()),
+                            _ => {}
+                        },
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if session.media.is_empty() {
+            return Err(WebRtcError::InvalidSdp("No media section".to_string()));
+        }
+        Ok(session)
+    }
+
+    /// ICE 凭证（媒体级优先，BUNDLE 下各段相同）
+    pub fn ice_credentials(&self) -> Option<(String, String)> {
+        let media = self.media.first();
+        let ufrag = media
+            .and_then(|m| m.ice_ufrag.clone())
+            .or_else(|| self.ice_ufrag.clone())?;
+        let pwd = media
+            .and_then(|m| m.ice_pwd.clone())
+            .or_else(|| self.ice_pwd.clone())?;
+        Some((ufrag, pwd))
+    }
+
+    /// DTLS 指纹：(算法, 值)
+    pub fn fingerprint(&self) -> Option<(String, String)> {
+        let value = self
+            .media
+            .iter()
+            .find_map(|m| m.fingerprint.clone())
+            .or_else(|| self.fingerprint.clone())?;
+        let (algorithm, fingerprint) = value.split_once(' ')?;
+        Some((algorithm.to_ascii_lowercase(), fingerprint.trim().to_string()))
+    }
+
+    /// DTLS setup 角色（actpass / active / passive）
+    pub fn setup(&self) -> Option<&str> {
+        self.media
+            .iter()
+            .find_map(|m| m.setup.as_deref())
+            .or(self.setup.as_deref())
+    }
+}
+
+fn parse_media_line(value: &str) -> Result<MediaDescription> {
+    let mut parts = value.split_whitespace();
+    let kind = parts.next().unwrap_or_default().to_string();
+    let port = parts
+        .next()
+        .and_then(|port| port.split('/').next())
+        .and_then(|port| port.parse().ok())
+        .ok_or_else(|| WebRtcError::InvalidSdp(format!("Invalid media line: {}", value)))?;
+    let protocol = parts.next().unwrap_or_default().to_string();
+    let format_ids = parts.map(str::to_string).collect();
+
+    Ok(MediaDescription {
+        kind,
+        port,
+        protocol,
+        format_ids,
+        mid: None,
+        direction: Direction::SendRecv,
+        formats: Vec::new(),
+        ssrcs: Vec::new(),
+        ice_ufrag: None,
+        ice_pwd: None,
+        fingerprint: None,
+        setup: None,
+    })
+}
+
+fn apply_media_attribute(media: &mut MediaDescription, name: &str, value: &str) {
+    if let Some(direction) = Direction::parse(name) {
+        media.direction = direction;
+        return;
+    }
+
+    match name {
+        "mid" => media.mid = Some(value.to_string()),
+        "ice-ufrag" => media.ice_ufrag = Some(value.to_string()),
+        "ice-pwd" => media.ice_pwd = Some(value.to_string()),
+        "fingerprint" => media.fingerprint = Some(value.to_string()),
+        "setup" => media.setup = Some(value.to_string()),
+        "rtpmap" => {
+            // rtpmap:<pt> <encoding>/<clock>[/<channels>]
+            let Some((pt, codec)) = value.split_once(' ') else {
+                return;
+            };
+            let Ok(payload_type) = pt.parse() else {
+                return;
+            };
+            let mut codec_parts = codec.split('/');
+            let encoding = codec_parts.next().unwrap_or_default().to_string();
+            let clock_rate = codec_parts.next().and_then(|c| c.parse().ok()).unwrap_or(0);
+            let channels = codec_parts.next().and_then(|c| c.parse().ok());
+            media.formats.push(RtpFormat {
+                payload_type,
+                encoding,
+                clock_rate,
+                channels,
+                fmtp: None,
+                rtcp_fb: Vec::new(),
+            });
+        }
+        "fmtp" => {
+            if let Some((pt, params)) = value.split_once(' ') {
+                if let Some(format) = find_format(media, pt) {
+                    format.fmtp = Some(params.to_string());
+                }
+            }
+        }
+        "rtcp-fb" => {
+            if let Some((pt, feedback)) = value.split_once(' ') {
+                if let Some(format) = find_format(media, pt) {
+                    format.rtcp_fb.push(feedback.to_string());
+                }
+            }
+        }
+        "ssrc" => {
+            if let Some(ssrc) = value.split_whitespace().next().and_then(|s| s.parse().ok()) {
+                if !media.ssrcs.contains(&ssrc) {
+                    media.ssrcs.push(ssrc);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn find_format<'a>(media: &'a mut MediaDescription, pt: &str) -> Option<&'a mut RtpFormat> {
+    let payload_type: u8 = pt.parse().ok()?;
+    media
+        .formats
+        .iter_mut()
+        .find(|format| format.payload_type == payload_type)
+}
+
+/// answer 中一个 m= 段的协商结果
+#[derive(Debug, Clone)]
+pub enum AnswerMedia {
+    /// 接受：使用选定的格式，本端发送/接收的 SSRC（接收方向为 None）
+    Accepted {
+        format: RtpFormat,
+        direction: Direction,
+        ssrc: Option<u32>,
+    },
+    /// 拒绝（端口置 0）
+    Rejected,
+}
+
+/// 本端传输参数
+#[derive(Debug, Clone)]
+pub struct AnswerTransport {
+    pub ice_ufrag: String,
+    pub ice_pwd: String,
+    pub fingerprint: String,
+    /// active / passive
+    pub setup: &'static str,
+    /// `a=candidate:` 之后的候选描述
+    pub candidates: Vec<String>,
+    /// 媒体流标识（msid / cname）
+    pub stream_label: String,
+}
+
+/// 按 offer 的 m= 段顺序生成 ICE-lite answer（全部 BUNDLE 到首个接受的段）
+pub fn build_answer(offer: &SessionDescription, media: &[AnswerMedia], transport: &AnswerTransport) -> String {
+    let mut sdp = String::new();
+    let session_id: u64 = rand::random::<u64>() >> 1;
+
+    let _ = writeln!(sdp, "v=0\r");
+    let _ = writeln!(sdp, "o=- {} 2 IN IP4 127.0.0.1\r", session_id);
+    let _ = writeln!(sdp, "s=-\r");
+    let _ = writeln!(sdp, "t=0 0\r");
+    let _ = writeln!(sdp, "a=ice-lite\r");
+
+    let bundle: Vec<&str> = offer
+        .media
+        .iter()
+        .zip(media)
+        .filter(|(_, answer)| matches!(answer, AnswerMedia::Accepted { .. }))
+        .filter_map(|(offered, _)| offered.mid.as_deref())
+        .collect();
+    if !bundle.is_empty() {
+        let _ = writeln!(sdp, "a=group:BUNDLE {}\r", bundle.join(" "));
+    }
+    let _ = writeln!(sdp, "a=msid-semantic: WMS {}\r", transport.stream_label);
+
+    for (offered, answer) in offer.media.iter().zip(media) {
+        match answer {
+            AnswerMedia::Rejected => {
+                let _ = writeln!(
+                    sdp,
+                    "m={} 0 {} {}\r",
+                    offered.kind,
+                    offered.protocol,
+                    offered.format_ids.join(" ")
+                );
+                let _ = writeln!(sdp, "c=IN IP4 0.0.0.0\r");
+                if let Some(mid) = &offered.mid {
+                    let _ = writeln!(sdp, "a=mid:{}\r", mid);
+                }
+                let _ = writeln!(sdp, "a=inactive\r");
+            }
+            AnswerMedia::Accepted {
+                format,
+                direction,
+                ssrc,
+            } => {
+                let _ = writeln!(sdp, "m={} 9 {} {}\r", offered.kind, offered.protocol, format.payload_type);
+                let _ = writeln!(sdp, "c=IN IP4 0.0.0.0\r");
+                if let Some(mid) = &offered.mid {
+                    let _ = writeln!(sdp, "a=mid:{}\r", mid);
+                }
+                let _ = writeln!(sdp, "a=ice-ufrag:{}\r", transport.ice_ufrag);
+                let _ = writeln!(sdp, "a=ice-pwd:{}\r", transport.ice_pwd);
+                let _ = writeln!(sdp, "a=fingerprint:sha-256 {}\r", transport.fingerprint);
+                let _ = writeln!(sdp, "a=setup:{}\r", transport.setup);
+                let _ = writeln!(sdp, "a={}\r", direction.as_str());
+                let _ = writeln!(sdp, "a=rtcp-mux\r");
+
+                let mut rtpmap = format!("a=rtpmap:{} {}/{}", format.payload_type, format.encoding, format.clock_rate);
+                if let Some(channels) = format.channels {
+                    let _ = write!(rtpmap, "/{}", channels);
+                }
+                let _ = writeln!(sdp, "{}\r", rtpmap);
+                if let Some(fmtp) = &format.fmtp {
+                    let _ = writeln!(sdp, "a=fmtp:{} {}\r", format.payload_type, fmtp);
+                }
+                for feedback in &format.rtcp_fb {
+                    let _ = writeln!(sdp, "a=rtcp-fb:{} {}\r", format.payload_type, feedback);
+                }
+
+                if let Some(ssrc) = ssrc {
+                    let _ = writeln!(sdp, "a=msid:{} {}\r", transport.stream_label, offered.kind);
+                    let _ = writeln!(sdp, "a=ssrc:{} cname:{}\r", ssrc, transport.stream_label);
+                    let _ = writeln!(sdp, "a=ssrc:{} msid:{} {}\r", ssrc, transport.stream_label, offered.kind);
+                }
+
+                for candidate in &transport.candidates {
+                    let _ = writeln!(sdp, "a=candidate:{}\r", candidate);
+                }
+                let _ = writeln!(sdp, "a=end-of-candidates\r");
+            }
+        }
+    }
+
+    sdp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BROWSER_OFFER: &str = "v=0\r\n\
+o=- 4611731400430051336 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0 1\r\n\
+a=msid-semantic: WMS\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96 102 103\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=ice-ufrag:EsAw\r\n\
+a=ice-pwd:bP+XJMM09aR8AiX1jdukzR6Y\r\n\
+a=fingerprint:sha-256 D2:FA:0E:C3:22:59:5E:14:95:69:92:3D:13:B4:84:24:2C:C2:A2:C0:3E:FD:34:8E:5E:EA:6F:AF:52:CE:E6:0F\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=recvonly\r\n\
+a=rtcp-mux\r\n\
+a=rtpmap:96 VP8/90000\r\n\
+a=rtpmap:102 H264/90000\r\n\
+a=rtcp-fb:102 nack pli\r\n\
+a=fmtp:102 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42001f\r\n\
+a=rtpmap:103 H264/90000\r\n\
+a=fmtp:103 level-asymmetry-allowed=1;packetization-mode=0;profile-level-id=42001f\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111 0 8\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:1\r\n\
+a=recvonly\r\n\
+a=rtpmap:111 opus/48000/2\r\n\
+a=rtpmap:0 PCMU/8000\r\n\
+a=rtpmap:8 PCMA/8000\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+a=mid:2\r\n";
+
+    #[test]
+    fn test_parse_browser_offer() {
+        let offer = SessionDescription::parse(BROWSER_OFFER).unwrap();
+        assert_eq!(offer.media.len(), 3);
+        assert_eq!(
+            offer.ice_credentials(),
+            Some(("EsAw".to_string(), "bP+XJMM09aR8AiX1jdukzR6Y".to_string()))
+        );
+        let (algorithm, fingerprint) = offer.fingerprint().unwrap();
+        assert_eq!(algorithm, "sha-256");
+        assert!(fingerprint.starts_with("D2:FA"));
+        assert_eq!(offer.setup(), Some("actpass"));
+
+        let video = &offer.media[0];
+        assert_eq!(video.direction, Direction::RecvOnly);
+        assert_eq!(video.formats.len(), 3);
+        let h264 = &video.formats[1];
+        assert_eq!(h264.fmtp_param("packetization-mode"), Some("1"));
+        assert_eq!(h264.rtcp_fb, vec!["nack pli".to_string()]);
+
+        let audio = &offer.media[1];
+        assert_eq!(audio.formats[0].channels, Some(2));
+        assert_eq!(audio.formats[2].encoding, "PCMA");
+
+        assert!(SessionDescription::parse("v=0\r\n").is_err());
+    }
+
+    #[test]
+    fn test_build_answer() {
+        let offer = SessionDescription::parse(BROWSER_OFFER).unwrap();
+        let media = vec![
+            AnswerMedia::Accepted {
+                format: offer.media[0].formats[1].clone(),
+                direction: Direction::SendOnly,
+                ssrc: Some(1111),
+            },
+            AnswerMedia::Accepted {
+                format: offer.media[1].formats[2].clone(),
+                direction: Direction::SendOnly,
+                ssrc: Some(2222),
+            },
+            AnswerMedia::Rejected,
+        ];
+        let transport = AnswerTransport {
+            ice_ufrag: "srvu".to_string(),
+            ice_pwd: "server-password-0123456789".to_string(),
+            fingerprint: "AA:BB".to_string(),
+            setup: "passive",
+            candidates: vec!["1 1 udp 2130706431 127.0.0.1 40000 typ host".to_string()],
+            stream_label: "flux".to_string(),
+        };
+
+        let answer = build_answer(&offer, &media, &transport);
+        assert!(answer.contains("a=ice-lite\r\n"));
+        assert!(answer.contains("a=group:BUNDLE 0 1\r\n"));
+        assert!(answer.contains("m=video 9 UDP/TLS/RTP/SAVPF 102\r\n"));
+        assert!(answer.contains("a=rtpmap:8 PCMA/8000\r\n"));
+        assert!(answer.contains("a=ssrc:1111 cname:flux\r\n"));
+        assert!(answer.contains("m=application 0 UDP/DTLS/SCTP webrtc-datachannel\r\n"));
+        assert!(answer.contains("a=candidate:1 1 udp 2130706431 127.0.0.1 40000 typ host\r\n"));
+
+        // answer 可被自身解析
+        let parsed = SessionDescription::parse(&answer).unwrap();
+        assert_eq!(parsed.setup(), Some("passive"));
+        assert_eq!(parsed.media[0].ssrcs, vec![1111]);
+        assert_eq!(parsed.media[2].port, 0);
+    }
+}
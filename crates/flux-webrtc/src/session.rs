@@ -0,0 +1,388 @@
+//! WHEP 播放会话：单 UDP 端口上复用 STUN / DTLS / SRTP，向浏览器推送一路流
+
+use crate::config::WebRtcConfig;
+use crate::dtls::DtlsCertificate;
+use crate::error::{Result, WebRtcError};
+use crate::packetizer::RtpPacketizer;
+use crate::sdp::{build_answer, AnswerMedia, Direction, RtpFormat, SessionDescription};
+use crate::transport::{PeerTransport, TransportEvent};
+use flux_media_core::types::StreamId;
+use flux_stream::{MediaPacket, PacketType, StreamMetadata};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{Notify, RwLock};
+use tracing::{debug, info, warn};
+
+/// 超时检查间隔
+pub(crate) const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 会话表（会话退出时自行移除）
+pub(crate) type SessionMap<T = WhepSession> = Arc<RwLock<HashMap<String, Arc<T>>>>;
+
+/// WebRTC 可直通的音频编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EgressAudioCodec {
+    Opus,
+    Pcma,
+    Pcmu,
+}
+
+impl EgressAudioCodec {
+    /// 由 `StreamMetadata.audio_codec` 映射
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "opus" => Some(Self::Opus),
+            "pcma" | "g711a" => Some(Self::Pcma),
+            "pcmu" | "g711u" => Some(Self::Pcmu),
+            _ => None,
+        }
+    }
+
+    pub fn clock_rate(&self) -> u32 {
+        match self {
+            Self::Opus => 48000,
+            Self::Pcma | Self::Pcmu => 8000,
+        }
+    }
+
+    /// SDP rtpmap 中的编码名
+    pub fn encoding_name(&self) -> &'static str {
+        match self {
+            Self::Opus => "opus",
+            Self::Pcma => "PCMA",
+            Self::Pcmu => "PCMU",
+        }
+    }
+}
+
+/// 可输出的编码组合
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EgressCodecs {
+    pub video: bool,
+    pub audio: Option<EgressAudioCodec>,
+}
+
+/// 根据流元数据选择可直通的编码（不转码；AAC / H.265 等浏览器不支持的轨道被丢弃）
+pub fn select_codecs(metadata: &StreamMetadata) -> Result<EgressCodecs> {
+    // 尚未收到序列头时按 H.264 协商，这是各接入协议最常见的编码
+    let video = match metadata.video_codec.as_deref() {
+        None => true,
+        Some(codec) => codec.eq_ignore_ascii_case("h264"),
+    };
+    let audio = metadata
+        .audio_codec
+        .as_deref()
+        .and_then(EgressAudioCodec::from_name);
+
+    if !video && audio.is_none() {
+        return Err(WebRtcError::UnsupportedCodec(format!(
+            "video={:?}, audio={:?}",
+            metadata.video_codec, metadata.audio_codec
+        )));
+    }
+    Ok(EgressCodecs { video, audio })
+}
+
+/// 从 offer 的格式中选择 H.264：要求 packetization-mode=1，优先 Constrained Baseline
+pub(crate) fn choose_h264(formats: &[RtpFormat]) -> Option<&RtpFormat> {
+    let candidates: Vec<&RtpFormat> = formats
+        .iter()
+        .filter(|format| format.encoding.eq_ignore_ascii_case("H264"))
+        .filter(|format| format.fmtp_param("packetization-mode") == Some("1"))
+        .collect();
+
+    candidates
+        .iter()
+        .find(|format| {
+            format
+                .fmtp_param("profile-level-id")
+                .is_some_and(|profile| profile.eq_ignore_ascii_case("42e01f"))
+        })
+        .or_else(|| candidates.first())
+        .copied()
+}
+
+pub(crate) fn choose_audio(formats: &[RtpFormat], codec: EgressAudioCodec) -> Option<&RtpFormat> {
+    formats.iter().find(|format| {
+        format.encoding.eq_ignore_ascii_case(codec.encoding_name()) && format.clock_rate == codec.clock_rate()
+    })
+}
+
+/// 单条输出轨道
+struct Track {
+    packetizer: RtpPacketizer,
+}
+
+/// 与 offer 协商的结果
+struct Negotiated {
+    media: Vec<AnswerMedia>,
+    video: Option<Track>,
+    audio: Option<Track>,
+}
+
+fn negotiate(offer: &SessionDescription, codecs: EgressCodecs) -> Result<Negotiated> {
+    let mut negotiated = Negotiated {
+        media: Vec::with_capacity(offer.media.len()),
+        video: None,
+        audio: None,
+    };
+
+    for media in &offer.media {
+        let receives = matches!(media.direction, Direction::RecvOnly | Direction::SendRecv);
+        let chosen = match media.kind.as_str() {
+            "video" if codecs.video && negotiated.video.is_none() && receives => choose_h264(&media.formats),
+            "audio" if negotiated.audio.is_none() && receives => codecs
+                .audio
+                .and_then(|codec| choose_audio(&media.formats, codec)),
+            _ => None,
+        };
+
+        let Some(format) = chosen else {
+            negotiated.media.push(AnswerMedia::Rejected);
+            continue;
+        };
+
+        let ssrc: u32 = rand::random();
+        let track = Track {
+            packetizer: RtpPacketizer::new(format.payload_type, ssrc, format.clock_rate),
+        };
+        if media.kind == "video" {
+            negotiated.video = Some(track);
+        } else {
+            negotiated.audio = Some(track);
+        }
+        negotiated.media.push(AnswerMedia::Accepted {
+            format: format.clone(),
+            direction: Direction::SendOnly,
+            ssrc: Some(ssrc),
+        });
+    }
+
+    if negotiated.video.is_none() && negotiated.audio.is_none() {
+        return Err(WebRtcError::UnsupportedCodec(
+            "No common codec with offer".to_string(),
+        ));
+    }
+    Ok(negotiated)
+}
+
+/// WHEP 会话句柄
+pub struct WhepSession {
+    pub id: String,
+    pub stream_id: StreamId,
+    pub codecs: EgressCodecs,
+    /// 本端 UDP 地址
+    pub local_addr: SocketAddr,
+    shutdown: Arc<Notify>,
+}
+
+impl WhepSession {
+    /// 创建会话：协商 offer、绑定 UDP 端口并启动会话任务，返回 (会话, SDP answer)
+    pub(crate) async fn start(
+        stream_id: StreamId,
+        codecs: EgressCodecs,
+        offer: &SessionDescription,
+        receiver: broadcast::Receiver<MediaPacket>,
+        certificate: &DtlsCertificate,
+        config: &WebRtcConfig,
+        sessions: SessionMap,
+    ) -> Result<(Arc<Self>, String)> {
+        let negotiated = negotiate(offer, codecs)?;
+        let transport = PeerTransport::new(offer, certificate, config).await?;
+        let local_addr = transport.local_addr()?;
+
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let answer_transport = transport.answer_transport(certificate, format!("flux-{}", &id[..8]));
+        let answer = build_answer(offer, &negotiated.media, &answer_transport);
+
+        let session = Arc::new(Self {
+            id: id.clone(),
+            stream_id: stream_id.clone(),
+            codecs,
+            local_addr,
+            shutdown: Arc::new(Notify::new()),
+        });
+
+        let runtime = SessionRuntime {
+            id: id.clone(),
+            stream_id,
+            transport,
+            video: negotiated.video,
+            audio: negotiated.audio,
+            waiting_keyframe: true,
+        };
+
+        sessions.write().await.insert(id.clone(), session.clone());
+        let shutdown = session.shutdown.clone();
+        tokio::spawn(async move {
+            runtime.run(receiver, shutdown).await;
+            sessions.write().await.remove(&id);
+        });
+
+        Ok((session, answer))
+    }
+
+    /// 关闭会话（会话任务退出并从会话表移除）
+    pub fn close(&self) {
+        self.shutdown.notify_one();
+    }
+}
+
+/// 会话任务持有的状态
+struct SessionRuntime {
+    id: String,
+    stream_id: StreamId,
+    transport: PeerTransport,
+    video: Option<Track>,
+    audio: Option<Track>,
+    waiting_keyframe: bool,
+}
+
+impl SessionRuntime {
+    async fn run(mut self, mut receiver: broadcast::Receiver<MediaPacket>, shutdown: Arc<Notify>) {
+        let mut buf = vec![0u8; 1500];
+        let mut tick = tokio::time::interval(TICK_INTERVAL);
+
+        info!(target: "webrtc", session_id = %self.id, stream_id = %self.stream_id, "WHEP session started");
+
+        loop {
+            tokio::select! {
+                _ = shutdown.notified() => break,
+                result = self.transport.recv_from(&mut buf) => {
+                    let event = match result {
+                        Ok((len, from)) => self.transport.handle_datagram(&buf[..len], from).await,
+                        Err(e) => Err(e),
+                    };
+                    match event {
+                        Ok(TransportEvent::Connected) => {
+                            info!(target: "webrtc", session_id = %self.id, "DTLS-SRTP established");
+                            // 丢弃握手期间积压的数据，从最新关键帧开始发送
+                            receiver = receiver.resubscribe();
+                        }
+                        // 浏览器的 RTCP（RR / PLI）暂不处理：直通模式无法向源端请求关键帧
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!(target: "webrtc", session_id = %self.id, "Session failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+                packet = receiver.recv(), if self.transport.is_connected() => match packet {
+                    Ok(packet) => {
+                        if let Err(e) = self.send_media(&packet).await {
+                            debug!(target: "webrtc", session_id = %self.id, "Failed to send media: {}", e);
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!(target: "webrtc", session_id = %self.id, skipped, "Receiver lagged, waiting for keyframe");
+                        self.waiting_keyframe = true;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = tick.tick() => {
+                    if !self.transport.is_alive() {
+                        info!(target: "webrtc", session_id = %self.id, "WHEP session timed out");
+                        break;
+                    }
+                }
+            }
+        }
+
+        info!(target: "webrtc", session_id = %self.id, stream_id = %self.stream_id, "WHEP session closed");
+    }
+
+    async fn send_media(&mut self, packet: &MediaPacket) -> Result<()> {
+        let rtp_packets = match packet.packet_type {
+            PacketType::Video => {
+                let Some(track) = self.video.as_mut() else {
+                    return Ok(());
+                };
+                if self.waiting_keyframe {
+                    if !packet.is_keyframe {
+                        return Ok(());
+                    }
+                    self.waiting_keyframe = false;
+                }
+                track.packetizer.packetize_h264(&packet.data, packet.timestamp)
+            }
+            PacketType::Audio => {
+                let Some(track) = self.audio.as_mut() else {
+                    return Ok(());
+                };
+                vec![track.packetizer.packetize_frame(&packet.data, packet.timestamp)]
+            }
+        };
+
+        for rtp in rtp_packets {
+            self.transport.send_rtp(&rtp.to_bytes()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(video: Option<&str>, audio: Option<&str>) -> StreamMetadata {
+        StreamMetadata {
+            video_codec: video.map(str::to_string),
+            audio_codec: audio.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_select_codecs() {
+        let codecs = select_codecs(&metadata(Some("h264"), Some("pcma"))).unwrap();
+        assert!(codecs.video);
+        assert_eq!(codecs.audio, Some(EgressAudioCodec::Pcma));
+
+        // AAC 无法直通，只输出视频
+        let codecs = select_codecs(&metadata(Some("h264"), Some("aac"))).unwrap();
+        assert_eq!(codecs.audio, None);
+
+        let codecs = select_codecs(&metadata(Some("h265"), Some("opus"))).unwrap();
+        assert!(!codecs.video);
+        assert_eq!(codecs.audio, Some(EgressAudioCodec::Opus));
+
+        assert!(select_codecs(&metadata(Some("h265"), Some("aac"))).is_err());
+    }
+
+    #[test]
+    fn test_negotiate_prefers_constrained_baseline() {
+        let offer = SessionDescription::parse(
+            "v=0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 100 102 104\r\n\
+a=mid:0\r\n\
+a=recvonly\r\n\
+a=rtpmap:100 H264/90000\r\n\
+a=fmtp:100 packetization-mode=0;profile-level-id=42e01f\r\n\
+a=rtpmap:102 H264/90000\r\n\
+a=fmtp:102 packetization-mode=1;profile-level-id=640032\r\n\
+a=rtpmap:104 H264/90000\r\n\
+a=fmtp:104 packetization-mode=1;profile-level-id=42e01f\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+a=mid:1\r\n\
+a=recvonly\r\n\
+a=rtpmap:111 opus/48000/2\r\n",
+        )
+        .unwrap();
+
+        let codecs = EgressCodecs {
+            video: true,
+            audio: Some(EgressAudioCodec::Pcmu),
+        };
+        let negotiated = negotiate(&offer, codecs).unwrap();
+        assert!(matches!(
+            &negotiated.media[0],
+            AnswerMedia::Accepted { format, .. } if format.payload_type == 104
+        ));
+        // offer 中没有 PCMU，音频段被拒绝
+        assert!(matches!(negotiated.media[1], AnswerMedia::Rejected));
+        assert!(negotiated.audio.is_none());
+    }
+}
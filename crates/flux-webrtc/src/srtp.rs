@@ -0,0 +1,310 @@
+//! SRTP/SRTCP（RFC 3711），保护套件 SRTP_AES128_CM_HMAC_SHA1_80
+//!
+//! 主密钥由 DTLS-SRTP（RFC 5764）导出，会话密钥按 4.3 节派生（密钥派生率为 0）。
+
+use crate::error::{Result, WebRtcError};
+use crate::rtp;
+use crate::stun::hmac_sha1;
+use openssl::symm::{encrypt, Cipher};
+use std::collections::HashMap;
+
+/// DTLS use_srtp 扩展中协商的保护套件名
+pub const SRTP_PROFILE: &str = "SRTP_AES128_CM_SHA1_80";
+
+pub const MASTER_KEY_LEN: usize = 16;
+pub const MASTER_SALT_LEN: usize = 14;
+const AUTH_KEY_LEN: usize = 20;
+const AUTH_TAG_LEN: usize = 10;
+const SRTCP_INDEX_LEN: usize = 4;
+const RTCP_HEADER_LEN: usize = 8;
+
+/// 密钥派生标签（RFC 3711 4.3.2）
+const LABEL_RTP_ENCRYPTION: u8 = 0x00;
+const LABEL_RTP_AUTH: u8 = 0x01;
+const LABEL_RTP_SALT: u8 = 0x02;
+const LABEL_RTCP_ENCRYPTION: u8 = 0x03;
+const LABEL_RTCP_AUTH: u8 = 0x04;
+const LABEL_RTCP_SALT: u8 = 0x05;
+
+/// 单方向的会话密钥
+#[derive(Clone)]
+struct SessionKeys {
+    encryption: [u8; MASTER_KEY_LEN],
+    salt: [u8; MASTER_SALT_LEN],
+    auth: [u8; AUTH_KEY_LEN],
+}
+
+/// 每个 SSRC 的回绕计数状态
+#[derive(Debug, Clone, Copy, Default)]
+struct SsrcState {
+    roc: u32,
+    last_sequence: Option<u16>,
+}
+
+/// 单方向 SRTP 上下文（发送端用于加密，接收端用于解密）
+pub struct SrtpContext {
+    rtp: SessionKeys,
+    rtcp: SessionKeys,
+    ssrc_states: HashMap<u32, SsrcState>,
+    srtcp_index: u32,
+}
+
+impl SrtpContext {
+    pub fn new(master_key: &[u8], master_salt: &[u8]) -> Result<Self> {
+        if master_key.len() != MASTER_KEY_LEN || master_salt.len() != MASTER_SALT_LEN {
+            return Err(WebRtcError::Dtls("Invalid SRTP master key length".to_string()));
+        }
+
+        Ok(Self {
+            rtp: SessionKeys {
+                encryption: derive(master_key, master_salt, LABEL_RTP_ENCRYPTION)?,
+                salt: derive(master_key, master_salt, LABEL_RTP_SALT)?,
+                auth: derive(master_key, master_salt, LABEL_RTP_AUTH)?,
+            },
+            rtcp: SessionKeys {
+                encryption: derive(master_key, master_salt, LABEL_RTCP_ENCRYPTION)?,
+                salt: derive(master_key, master_salt, LABEL_RTCP_SALT)?,
+                auth: derive(master_key, master_salt, LABEL_RTCP_AUTH)?,
+            },
+            ssrc_states: HashMap::new(),
+            srtcp_index: 0,
+        })
+    }
+
+    /// 加密 RTP 包并追加认证标签
+    pub fn protect_rtp(&mut self, packet: &[u8]) -> Result<Vec<u8>> {
+        let header_len = rtp::header_len(packet)?;
+        let ssrc = u32::from_be_bytes([packet[8], packet[9], packet[10], packet[11]]);
+        let sequence = u16::from_be_bytes([packet[2], packet[3]]);
+
+        // 发送端：序号回绕时 ROC 加一
+        let state = self.ssrc_states.entry(ssrc).or_default();
+        if let Some(last) = state.last_sequence {
+            if sequence < last && last - sequence > 0x8000 {
+                state.roc = state.roc.wrapping_add(1);
+            }
+        }
+        state.last_sequence = Some(sequence);
+        let roc = state.roc;
+        let index = ((roc as u64) << 16) | sequence as u64;
+
+        let mut out = packet[..header_len].to_vec();
+        out.extend(aes_cm(&self.rtp, ssrc, index, &packet[header_len..])?);
+        let tag = rtp_auth_tag(&self.rtp.auth, &out, roc)?;
+        out.extend_from_slice(&tag);
+        Ok(out)
+    }
+
+    /// 校验并解密 SRTP 包
+    pub fn unprotect_rtp(&mut self, packet: &[u8]) -> Result<Vec<u8>> {
+        if packet.len() < rtp::RTP_HEADER_LEN + AUTH_TAG_LEN {
+            return Err(WebRtcError::InvalidPacket("SRTP packet too short".to_string()));
+        }
+        let (body, tag) = packet.split_at(packet.len() - AUTH_TAG_LEN);
+        let header_len = rtp::header_len(body)?;
+        let ssrc = u32::from_be_bytes([body[8], body[9], body[10], body[11]]);
+        let sequence = u16::from_be_bytes([body[2], body[3]]);
+
+        let state = self.ssrc_states.get(&ssrc).copied().unwrap_or_default();
+        let roc = estimate_roc(state, sequence);
+        if rtp_auth_tag(&self.rtp.auth, body, roc)? != tag {
+            return Err(WebRtcError::SrtpAuth);
+        }
+
+        let index = ((roc as u64) << 16) | sequence as u64;
+        let mut out = body[..header_len].to_vec();
+        out.extend(aes_cm(&self.rtp, ssrc, index, &body[header_len..])?);
+
+        // 认证通过后才推进接收状态
+        let newer = match state.last_sequence {
+            None => true,
+            Some(last) => roc > state.roc || (roc == state.roc && sequence > last),
+        };
+        if newer {
+            self.ssrc_states.insert(
+                ssrc,
+                SsrcState {
+                    roc,
+                    last_sequence: Some(sequence),
+                },
+            );
+        }
+        Ok(out)
+    }
+
+    /// 加密 RTCP 包（E 位置 1），追加 SRTCP 索引与认证标签
+    pub fn protect_rtcp(&mut self, packet: &[u8]) -> Result<Vec<u8>> {
+        if packet.len() < RTCP_HEADER_LEN {
+            return Err(WebRtcError::InvalidPacket("RTCP packet too short".to_string()));
+        }
+        let ssrc = u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]);
+        let index = self.srtcp_index;
+        self.srtcp_index = (self.srtcp_index + 1) & 0x7FFF_FFFF;
+
+        let mut out = packet[..RTCP_HEADER_LEN].to_vec();
+        out.extend(aes_cm(&self.rtcp, ssrc, index as u64, &packet[RTCP_HEADER_LEN..])?);
+        out.extend_from_slice(&(index | 0x8000_0000).to_be_bytes());
+        let tag = hmac_sha1(&self.rtcp.auth, &out)?;
+        out.extend_from_slice(&tag[..AUTH_TAG_LEN]);
+        Ok(out)
+    }
+
+    /// 校验并解密 SRTCP 包
+    pub fn unprotect_rtcp(&mut self, packet: &[u8]) -> Result<Vec<u8>> {
+        if packet.len() < RTCP_HEADER_LEN + SRTCP_INDEX_LEN + AUTH_TAG_LEN {
+            return Err(WebRtcError::InvalidPacket("SRTCP packet too short".to_string()));
+        }
+        let (body, tag) = packet.split_at(packet.len() - AUTH_TAG_LEN);
+        if hmac_sha1(&self.rtcp.auth, body)?[..AUTH_TAG_LEN] != *tag {
+            return Err(WebRtcError::SrtpAuth);
+        }
+
+        let (payload, index_bytes) = body.split_at(body.len() - SRTCP_INDEX_LEN);
+        let index_field = u32::from_be_bytes([index_bytes[0], index_bytes[1], index_bytes[2], index_bytes[3]]);
+        let ssrc = u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]);
+
+        let mut out = payload[..RTCP_HEADER_LEN].to_vec();
+        if index_field & 0x8000_0000 != 0 {
+            out.extend(aes_cm(
+                &self.rtcp,
+                ssrc,
+                (index_field & 0x7FFF_FFFF) as u64,
+                &payload[RTCP_HEADER_LEN..],
+            )?);
+        } else {
+            out.extend_from_slice(&payload[RTCP_HEADER_LEN..]);
+        }
+        Ok(out)
+    }
+}
+
+/// 会话密钥派生（RFC 3711 4.3.1，kdr = 0）：x = label || 0^48 异或 master_salt，
+/// 以 x * 2^16 为 IV 对主密钥做 AES-CM 生成所需长度的密钥流
+fn derive<const N: usize>(master_key: &[u8], master_salt: &[u8], label: u8) -> Result<[u8; N]> {
+    let mut iv = [0u8; 16];
+    iv[..MASTER_SALT_LEN].copy_from_slice(master_salt);
+    iv[7] ^= label;
+
+    let keystream = encrypt(Cipher::aes_128_ctr(), master_key, Some(&iv), &[0u8; N])?;
+    let mut out = [0u8; N];
+    out.copy_from_slice(&keystream[..N]);
+    Ok(out)
+}
+
+/// AES-CM 加解密（RFC 3711 4.1.1）：IV = (salt * 2^16) ^ (SSRC * 2^64) ^ (index * 2^16)
+fn aes_cm(keys: &SessionKeys, ssrc: u32, index: u64, data: &[u8]) -> Result<Vec<u8>> {
+    let mut iv = [0u8; 16];
+    iv[..MASTER_SALT_LEN].copy_from_slice(&keys.salt);
+    for (i, byte) in ssrc.to_be_bytes().iter().enumerate() {
+        iv[4 + i] ^= byte;
+    }
+    for (i, byte) in index.to_be_bytes()[2..].iter().enumerate() {
+        iv[8 + i] ^= byte;
+    }
+    Ok(encrypt(Cipher::aes_128_ctr(), &keys.encryption, Some(&iv), data)?)
+}
+
+/// RTP 认证标签：HMAC-SHA1(auth_key, 包 || ROC) 截断为 80 位
+fn rtp_auth_tag(auth_key: &[u8], packet: &[u8], roc: u32) -> Result<Vec<u8>> {
+    let mut covered = Vec::with_capacity(packet.len() + 4);
+    covered.extend_from_slice(packet);
+    covered.extend_from_slice(&roc.to_be_bytes());
+    let mut tag = hmac_sha1(auth_key, &covered)?;
+    tag.truncate(AUTH_TAG_LEN);
+    Ok(tag)
+}
+
+/// 接收端 ROC 估计（RFC 3711 附录 A）
+fn estimate_roc(state: SsrcState, sequence: u16) -> u32 {
+    let Some(last) = state.last_sequence else {
+        return state.roc;
+    };
+
+    if last < 0x8000 {
+        if sequence > last && sequence - last > 0x8000 {
+            state.roc.wrapping_sub(1)
+        } else {
+            state.roc
+        }
+    } else if last - 0x8000 > sequence {
+        state.roc.wrapping_add(1)
+    } else {
+        state.roc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_key_derivation_rfc3711() {
+        // RFC 3711 附录 B.3
+        let master_key = hex("E1F97A0D3E018BE0D64FA32C06DE4139");
+        let master_salt = hex("0EC675AD498AFEEBB6960B3AABE6");
+
+        let encryption: [u8; 16] = derive(&master_key, &master_salt, LABEL_RTP_ENCRYPTION).unwrap();
+        let salt: [u8; 14] = derive(&master_key, &master_salt, LABEL_RTP_SALT).unwrap();
+        let auth: [u8; 20] = derive(&master_key, &master_salt, LABEL_RTP_AUTH).unwrap();
+
+        assert_eq!(encryption.to_vec(), hex("C61E7A93744F39EE10734AFE3FF7A087"));
+        assert_eq!(salt.to_vec(), hex("30CBBC08863D8C85D49DB34A9AE1"));
+        assert_eq!(auth.to_vec(), hex("CEBE321F6FF7716B6FD4AB49AF256A156D38BAA4"));
+    }
+
+    #[test]
+    fn test_rtp_protect_roundtrip() {
+        let key = [0x11u8; MASTER_KEY_LEN];
+        let salt = [0x22u8; MASTER_SALT_LEN];
+        let mut sender = SrtpContext::new(&key, &salt).unwrap();
+        let mut receiver = SrtpContext::new(&key, &salt).unwrap();
+
+        // 跨越序号回绕
+        for sequence in [65534u16, 65535, 0, 1] {
+            let mut packet = vec![0x80, 0x60];
+            packet.extend_from_slice(&sequence.to_be_bytes());
+            packet.extend_from_slice(&[0, 0, 0x10, 0, 0xCA, 0xFE, 0xBA, 0xBE]);
+            packet.extend_from_slice(b"payload");
+
+            let protected = sender.protect_rtp(&packet).unwrap();
+            assert_eq!(protected.len(), packet.len() + AUTH_TAG_LEN);
+            assert_eq!(&protected[..12], &packet[..12]);
+            assert_ne!(&protected[12..19], b"payload");
+
+            assert_eq!(receiver.unprotect_rtp(&protected).unwrap(), packet);
+        }
+        assert_eq!(receiver.ssrc_states[&0xCAFE_BABE].roc, 1);
+
+        // 篡改负载导致认证失败
+        let mut packet = vec![0x80, 0x60, 0, 2, 0, 0, 0x10, 0, 0xCA, 0xFE, 0xBA, 0xBE, 1, 2, 3];
+        let mut protected = sender.protect_rtp(&packet).unwrap();
+        protected[12] ^= 0xFF;
+        assert!(matches!(receiver.unprotect_rtp(&protected), Err(WebRtcError::SrtpAuth)));
+        packet[12] = 9;
+        assert!(receiver.unprotect_rtp(&sender.protect_rtp(&packet).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_rtcp_protect_roundtrip() {
+        let key = [0x33u8; MASTER_KEY_LEN];
+        let salt = [0x44u8; MASTER_SALT_LEN];
+        let mut sender = SrtpContext::new(&key, &salt).unwrap();
+        let mut receiver = SrtpContext::new(&key, &salt).unwrap();
+
+        // PLI：V=2 FMT=1 PT=206
+        let pli = [0x81, 206, 0, 2, 0, 0, 0, 1, 0xCA, 0xFE, 0xBA, 0xBE];
+        let protected = sender.protect_rtcp(&pli).unwrap();
+        assert_eq!(protected.len(), pli.len() + SRTCP_INDEX_LEN + AUTH_TAG_LEN);
+        assert_eq!(receiver.unprotect_rtcp(&protected).unwrap(), pli);
+
+        let second = sender.protect_rtcp(&pli).unwrap();
+        assert_eq!(&second[12..16], &0x8000_0001u32.to_be_bytes());
+    }
+}
@@ -0,0 +1,313 @@
+//! STUN 消息（RFC 5389）：ICE 连通性检查所需的 Binding 请求/响应、
+//! MESSAGE-INTEGRITY（HMAC-SHA1，短期凭证）与 FINGERPRINT
+
+use crate::error::{Result, WebRtcError};
+use bytes::{BufMut, BytesMut};
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use std::net::{IpAddr, SocketAddr};
+
+pub const MAGIC_COOKIE: u32 = 0x2112_A442;
+pub const HEADER_LEN: usize = 20;
+
+/// 消息类型
+pub const BINDING_REQUEST: u16 = 0x0001;
+pub const BINDING_SUCCESS: u16 = 0x0101;
+pub const BINDING_ERROR: u16 = 0x0111;
+
+/// 属性类型
+pub const ATTR_USERNAME: u16 = 0x0006;
+pub const ATTR_MESSAGE_INTEGRITY: u16 = 0x0008;
+pub const ATTR_ERROR_CODE: u16 = 0x0009;
+pub const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+pub const ATTR_PRIORITY: u16 = 0x0024;
+pub const ATTR_USE_CANDIDATE: u16 = 0x0025;
+pub const ATTR_FINGERPRINT: u16 = 0x8028;
+pub const ATTR_ICE_CONTROLLED: u16 = 0x8029;
+pub const ATTR_ICE_CONTROLLING: u16 = 0x802A;
+
+const FINGERPRINT_XOR: u32 = 0x5354_554E;
+const INTEGRITY_LEN: usize = 20;
+
+/// 按 RFC 7983 判断复用端口上的数据报是否为 STUN
+pub fn is_stun(data: &[u8]) -> bool {
+    data.len() >= HEADER_LEN
+        && data[0] < 4
+        && u32::from_be_bytes([data[4], data[5], data[6], data[7]]) == MAGIC_COOKIE
+}
+
+/// 解析后的 STUN 消息
+#[derive(Debug, Clone)]
+pub struct StunMessage {
+    pub message_type: u16,
+    pub transaction_id: [u8; 12],
+    pub attributes: Vec<(u16, Vec<u8>)>,
+    /// MESSAGE-INTEGRITY 属性在原始消息中的偏移
+    integrity_offset: Option<usize>,
+}
+
+impl StunMessage {
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        if !is_stun(data) {
+            return Err(WebRtcError::InvalidPacket("Not a STUN message".to_string()));
+        }
+
+        let message_type = u16::from_be_bytes([data[0], data[1]]);
+        let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+        if HEADER_LEN + length > data.len() || !length.is_multiple_of(4) {
+            return Err(WebRtcError::InvalidPacket("Invalid STUN length".to_string()));
+        }
+
+        let mut transaction_id = [0u8; 12];
+        transaction_id.copy_from_slice(&data[8..20]);
+
+        let mut attributes = Vec::new();
+        let mut integrity_offset = None;
+        let mut offset = HEADER_LEN;
+        let end = HEADER_LEN + length;
+        while offset + 4 <= end {
+            let attr_type = u16::from_be_bytes([data[offset], data[offset + 1]]);
+            let attr_len = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+            let value_start = offset + 4;
+            if value_start + attr_len > end {
+                return Err(WebRtcError::InvalidPacket("Truncated STUN attribute".to_string()));
+            }
+
+            if attr_type == ATTR_MESSAGE_INTEGRITY {
+                integrity_offset = Some(offset);
+            }
+            attributes.push((attr_type, data[value_start..value_start + attr_len].to_vec()));
+            offset = value_start + attr_len.div_ceil(4) * 4;
+        }
+
+        Ok(Self {
+            message_type,
+            transaction_id,
+            attributes,
+            integrity_offset,
+        })
+    }
+
+    pub fn attribute(&self, attr_type: u16) -> Option<&[u8]> {
+        self.attributes
+            .iter()
+            .find(|(t, _)| *t == attr_type)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    pub fn has_attribute(&self, attr_type: u16) -> bool {
+        self.attribute(attr_type).is_some()
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        self.attribute(ATTR_USERNAME)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    /// XOR-MAPPED-ADDRESS（仅 IPv4/IPv6 两种族）
+    pub fn xor_mapped_address(&self) -> Option<SocketAddr> {
+        let value = self.attribute(ATTR_XOR_MAPPED_ADDRESS)?;
+        if value.len() < 8 {
+            return None;
+        }
+        let port = u16::from_be_bytes([value[2], value[3]]) ^ (MAGIC_COOKIE >> 16) as u16;
+        let mask = xor_mask(&self.transaction_id);
+        let ip = match value[1] {
+            0x01 => {
+                let mut octets = [0u8; 4];
+                for (i, octet) in octets.iter_mut().enumerate() {
+                    *octet = value[4 + i] ^ mask[i];
+                }
+                IpAddr::from(octets)
+            }
+            0x02 if value.len() >= 20 => {
+                let mut octets = [0u8; 16];
+                for (i, octet) in octets.iter_mut().enumerate() {
+                    *octet = value[4 + i] ^ mask[i];
+                }
+                IpAddr::from(octets)
+            }
+            _ => return None,
+        };
+        Some(SocketAddr::new(ip, port))
+    }
+
+    /// 使用短期凭证校验 MESSAGE-INTEGRITY（`raw` 为收到的原始字节）
+    pub fn verify_integrity(&self, raw: &[u8], password: &str) -> bool {
+        let (Some(offset), Some(expected)) = (self.integrity_offset, self.attribute(ATTR_MESSAGE_INTEGRITY)) else {
+            return false;
+        };
+
+        // 长度字段按“消息截止到 MESSAGE-INTEGRITY 属性末尾”重写后参与计算
+        let mut covered = raw[..offset].to_vec();
+        let length = (offset + 4 + INTEGRITY_LEN - HEADER_LEN) as u16;
+        covered[2..4].copy_from_slice(&length.to_be_bytes());
+
+        hmac_sha1(password.as_bytes(), &covered).is_ok_and(|digest| digest.as_slice() == expected)
+    }
+}
+
+/// STUN 消息构造器
+pub struct StunWriter {
+    buf: BytesMut,
+}
+
+impl StunWriter {
+    pub fn new(message_type: u16, transaction_id: &[u8; 12]) -> Self {
+        let mut buf = BytesMut::with_capacity(128);
+        buf.put_u16(message_type);
+        buf.put_u16(0);
+        buf.put_u32(MAGIC_COOKIE);
+        buf.put_slice(transaction_id);
+        Self { buf }
+    }
+
+    pub fn attribute(mut self, attr_type: u16, value: &[u8]) -> Self {
+        self.buf.put_u16(attr_type);
+        self.buf.put_u16(value.len() as u16);
+        self.buf.put_slice(value);
+        let padding = (4 - value.len() % 4) % 4;
+        self.buf.put_bytes(0, padding);
+        self.set_length();
+        self
+    }
+
+    pub fn xor_mapped_address(self, addr: SocketAddr) -> Self {
+        let mut transaction_id = [0u8; 12];
+        transaction_id.copy_from_slice(&self.buf[8..20]);
+        let mask = xor_mask(&transaction_id);
+
+        let mut value = vec![0u8];
+        let port = addr.port() ^ (MAGIC_COOKIE >> 16) as u16;
+        match addr.ip() {
+            IpAddr::V4(ip) => {
+                value.push(0x01);
+                value.extend_from_slice(&port.to_be_bytes());
+                value.extend(ip.octets().iter().zip(mask.iter()).map(|(a, b)| a ^ b));
+            }
+            IpAddr::V6(ip) => {
+                value.push(0x02);
+                value.extend_from_slice(&port.to_be_bytes());
+                value.extend(ip.octets().iter().zip(mask.iter()).map(|(a, b)| a ^ b));
+            }
+        }
+        self.attribute(ATTR_XOR_MAPPED_ADDRESS, &value)
+    }
+
+    /// 追加 MESSAGE-INTEGRITY（可选）与 FINGERPRINT，输出最终字节
+    pub fn finish(mut self, integrity_key: Option<&str>) -> Result<Vec<u8>> {
+        if let Some(key) = integrity_key {
+            // 长度需先包含 MESSAGE-INTEGRITY 属性本身
+            let length = (self.buf.len() - HEADER_LEN + 4 + INTEGRITY_LEN) as u16;
+            self.buf[2..4].copy_from_slice(&length.to_be_bytes());
+            let digest = hmac_sha1(key.as_bytes(), &self.buf)?;
+            self = self.attribute(ATTR_MESSAGE_INTEGRITY, &digest);
+        }
+
+        let length = (self.buf.len() - HEADER_LEN + 8) as u16;
+        self.buf[2..4].copy_from_slice(&length.to_be_bytes());
+        let crc = crc32fast::hash(&self.buf) ^ FINGERPRINT_XOR;
+        self = self.attribute(ATTR_FINGERPRINT, &crc.to_be_bytes());
+
+        Ok(self.buf.to_vec())
+    }
+
+    fn set_length(&mut self) {
+        let length = (self.buf.len() - HEADER_LEN) as u16;
+        self.buf[2..4].copy_from_slice(&length.to_be_bytes());
+    }
+}
+
+/// 构造 ICE 连通性检查请求（控制方）；`username` 为 `对端ufrag:本端ufrag`
+pub fn binding_request(
+    transaction_id: &[u8; 12],
+    username: &str,
+    password: &str,
+    priority: u32,
+    tie_breaker: u64,
+    use_candidate: bool,
+) -> Result<Vec<u8>> {
+    let mut writer = StunWriter::new(BINDING_REQUEST, transaction_id)
+        .attribute(ATTR_USERNAME, username.as_bytes())
+        .attribute(ATTR_PRIORITY, &priority.to_be_bytes())
+        .attribute(ATTR_ICE_CONTROLLING, &tie_breaker.to_be_bytes());
+    if use_candidate {
+        writer = writer.attribute(ATTR_USE_CANDIDATE, &[]);
+    }
+    writer.finish(Some(password))
+}
+
+/// 构造 Binding 成功响应
+pub fn binding_success(transaction_id: &[u8; 12], mapped: SocketAddr, password: &str) -> Result<Vec<u8>> {
+    StunWriter::new(BINDING_SUCCESS, transaction_id)
+        .xor_mapped_address(mapped)
+        .finish(Some(password))
+}
+
+/// 构造 Binding 错误响应（如 401 Unauthorized、487 Role Conflict）
+pub fn binding_error(transaction_id: &[u8; 12], code: u16, reason: &str) -> Result<Vec<u8>> {
+    let mut value = vec![0, 0, (code / 100) as u8, (code % 100) as u8];
+    value.extend_from_slice(reason.as_bytes());
+    StunWriter::new(BINDING_ERROR, transaction_id)
+        .attribute(ATTR_ERROR_CODE, &value)
+        .finish(None)
+}
+
+pub(crate) fn hmac_sha1(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let pkey = PKey::hmac(key)?;
+    let mut signer = Signer::new(MessageDigest::sha1(), &pkey)?;
+    signer.update(data)?;
+    Ok(signer.sign_to_vec()?)
+}
+
+/// XOR-MAPPED-ADDRESS 掩码：magic cookie + transaction ID
+fn xor_mask(transaction_id: &[u8; 12]) -> [u8; 16] {
+    let mut mask = [0u8; 16];
+    mask[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    mask[4..].copy_from_slice(transaction_id);
+    mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binding_request_integrity() {
+        let transaction_id = [7u8; 12];
+        let request = binding_request(&transaction_id, "srv:cli", "server-password", 1_000, 42, true).unwrap();
+
+        assert!(is_stun(&request));
+        let message = StunMessage::decode(&request).unwrap();
+        assert_eq!(message.message_type, BINDING_REQUEST);
+        assert_eq!(message.username(), Some("srv:cli"));
+        assert!(message.has_attribute(ATTR_USE_CANDIDATE));
+        assert!(message.verify_integrity(&request, "server-password"));
+        assert!(!message.verify_integrity(&request, "wrong"));
+
+        // FINGERPRINT 为最后一个属性，覆盖其之前的全部字节
+        let fingerprint = message.attribute(ATTR_FINGERPRINT).unwrap();
+        let crc = crc32fast::hash(&request[..request.len() - 8]) ^ FINGERPRINT_XOR;
+        assert_eq!(fingerprint, crc.to_be_bytes());
+    }
+
+    #[test]
+    fn test_binding_success_mapped_address() {
+        let transaction_id = [1u8; 12];
+        let addr: SocketAddr = "192.0.2.1:32853".parse().unwrap();
+        let response = binding_success(&transaction_id, addr, "pwd").unwrap();
+
+        let message = StunMessage::decode(&response).unwrap();
+        assert_eq!(message.message_type, BINDING_SUCCESS);
+        assert_eq!(message.transaction_id, transaction_id);
+        assert_eq!(message.xor_mapped_address(), Some(addr));
+        assert!(message.verify_integrity(&response, "pwd"));
+
+        let v6: SocketAddr = "[2001:db8::1]:5000".parse().unwrap();
+        let response = binding_success(&transaction_id, v6, "pwd").unwrap();
+        assert_eq!(StunMessage::decode(&response).unwrap().xor_mapped_address(), Some(v6));
+
+        assert!(!is_stun(&[0x80, 0x60, 0, 1]));
+    }
+}
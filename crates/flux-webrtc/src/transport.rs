@@ -0,0 +1,232 @@
+//! 单端口传输：按 RFC 7983 在一个 UDP socket 上复用 STUN / DTLS / SRTP / SRTCP
+//!
+//! WHEP 与 WHIP 会话共用：负责 ICE-lite 应答、DTLS 握手与指纹校验、SRTP 加解密，
+//! 会话任务只处理解密后的 RTP / RTCP。
+
+use crate::config::WebRtcConfig;
+use crate::dtls::{is_dtls, DtlsCertificate, DtlsRole, DtlsTransport};
+use crate::error::{Result, WebRtcError};
+use crate::ice::{self, IceCredentials, IceLiteAgent};
+use crate::rtp::is_rtcp;
+use crate::sdp::{AnswerTransport, SessionDescription};
+use crate::srtp::SrtpContext;
+use crate::stun::is_stun;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+/// 握手超时与 consent 超时
+pub(crate) const SESSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 一个数据报的处理结果
+pub(crate) enum TransportEvent {
+    /// 无需上层处理（STUN、DTLS 握手中间消息、RTCP 报告、非法数据）
+    None,
+    /// DTLS-SRTP 刚建立完成
+    Connected,
+    /// 解密后的 RTP 包
+    Rtp(Vec<u8>),
+}
+
+/// SRTP 上下文：发送与接收各一个
+struct SrtpSession {
+    outbound: SrtpContext,
+    inbound: SrtpContext,
+}
+
+pub(crate) struct PeerTransport {
+    socket: UdpSocket,
+    ice: IceLiteAgent,
+    dtls: DtlsTransport,
+    dtls_started: bool,
+    remote_fingerprint: String,
+    setup: &'static str,
+    candidates: Vec<String>,
+    srtp: Option<SrtpSession>,
+    created_at: Instant,
+}
+
+impl PeerTransport {
+    /// 按 offer 的 ICE / DTLS 参数创建传输并绑定 UDP 端口
+    pub(crate) async fn new(
+        offer: &SessionDescription,
+        certificate: &DtlsCertificate,
+        config: &WebRtcConfig,
+    ) -> Result<Self> {
+        let (remote_ufrag, _) = offer
+            .ice_credentials()
+            .ok_or_else(|| WebRtcError::InvalidSdp("Missing ICE credentials".to_string()))?;
+        let (algorithm, remote_fingerprint) = offer
+            .fingerprint()
+            .ok_or_else(|| WebRtcError::InvalidSdp("Missing DTLS fingerprint".to_string()))?;
+        if algorithm != "sha-256" {
+            return Err(WebRtcError::InvalidSdp(format!(
+                "Unsupported fingerprint algorithm: {}",
+                algorithm
+            )));
+        }
+
+        // 对端 actpass / active 时本端作为 DTLS 服务端（passive）
+        let (role, setup) = match offer.setup() {
+            Some("passive") => (DtlsRole::Client, "active"),
+            _ => (DtlsRole::Server, "passive"),
+        };
+
+        let socket = bind_socket(config).await?;
+        let port = socket.local_addr()?.port();
+
+        Ok(Self {
+            socket,
+            ice: IceLiteAgent::new(IceCredentials::generate(), remote_ufrag),
+            dtls: DtlsTransport::new(certificate, role)?,
+            dtls_started: false,
+            remote_fingerprint,
+            setup,
+            candidates: ice::host_candidates(&ice::candidate_addresses(config), port),
+            srtp: None,
+            created_at: Instant::now(),
+        })
+    }
+
+    pub(crate) fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// answer 中本端的传输参数
+    pub(crate) fn answer_transport(&self, certificate: &DtlsCertificate, stream_label: String) -> AnswerTransport {
+        let credentials = self.ice.local_credentials();
+        AnswerTransport {
+            ice_ufrag: credentials.ufrag.clone(),
+            ice_pwd: credentials.pwd.clone(),
+            fingerprint: certificate.fingerprint().to_string(),
+            setup: self.setup,
+            candidates: self.candidates.clone(),
+            stream_label,
+        }
+    }
+
+    pub(crate) fn is_connected(&self) -> bool {
+        self.srtp.is_some()
+    }
+
+    /// 握手未完成时按创建时间，完成后按 consent 判断是否存活
+    pub(crate) fn is_alive(&self) -> bool {
+        if self.is_connected() {
+            self.ice.is_alive(SESSION_TIMEOUT)
+        } else {
+            self.created_at.elapsed() < SESSION_TIMEOUT
+        }
+    }
+
+    pub(crate) async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        Ok(self.socket.recv_from(buf).await?)
+    }
+
+    /// 处理收到的数据报
+    pub(crate) async fn handle_datagram(&mut self, data: &[u8], from: SocketAddr) -> Result<TransportEvent> {
+        if is_stun(data) {
+            if let Some(response) = self.ice.handle_stun(data, from) {
+                self.socket.send_to(&response, from).await?;
+            }
+            // 本端为 DTLS 客户端时，候选对提名后发起握手
+            if self.dtls.role() == DtlsRole::Client && !self.dtls_started && self.ice.selected_address().is_some() {
+                self.dtls_started = true;
+                let datagrams = self.dtls.start()?;
+                self.send_all(&datagrams).await?;
+            }
+            return Ok(TransportEvent::None);
+        }
+
+        // 仅接受已提名地址上的 DTLS / SRTP
+        if self.ice.selected_address() != Some(from) {
+            return Ok(TransportEvent::None);
+        }
+
+        if is_dtls(data) {
+            let datagrams = self.dtls.handle_datagram(data)?;
+            self.send_all(&datagrams).await?;
+            if self.dtls.is_connected() && self.srtp.is_none() {
+                self.on_dtls_connected()?;
+                return Ok(TransportEvent::Connected);
+            }
+            return Ok(TransportEvent::None);
+        }
+
+        let Some(srtp) = self.srtp.as_mut() else {
+            return Ok(TransportEvent::None);
+        };
+        // 对端的 SR/RR 暂不使用；认证失败的包直接丢弃，不影响会话
+        if is_rtcp(data) {
+            return Ok(TransportEvent::None);
+        }
+        Ok(srtp
+            .inbound
+            .unprotect_rtp(data)
+            .map(TransportEvent::Rtp)
+            .unwrap_or(TransportEvent::None))
+    }
+
+    fn on_dtls_connected(&mut self) -> Result<()> {
+        let fingerprint = self
+            .dtls
+            .remote_fingerprint()
+            .ok_or_else(|| WebRtcError::Dtls("Missing peer certificate".to_string()))?;
+        if !fingerprint.eq_ignore_ascii_case(&self.remote_fingerprint) {
+            return Err(WebRtcError::Dtls("Peer certificate fingerprint mismatch".to_string()));
+        }
+
+        let keys = self.dtls.srtp_keys()?;
+        self.srtp = Some(SrtpSession {
+            outbound: SrtpContext::new(&keys.local_key, &keys.local_salt)?,
+            inbound: SrtpContext::new(&keys.remote_key, &keys.remote_salt)?,
+        });
+        Ok(())
+    }
+
+    /// 加密并发送 RTP（未连通时丢弃）
+    pub(crate) async fn send_rtp(&mut self, packet: &[u8]) -> Result<()> {
+        let (Some(srtp), Some(target)) = (self.srtp.as_mut(), self.ice.selected_address()) else {
+            return Ok(());
+        };
+        let protected = srtp.outbound.protect_rtp(packet)?;
+        self.socket.send_to(&protected, target).await?;
+        Ok(())
+    }
+
+    /// 加密并发送 RTCP（未连通时丢弃）
+    pub(crate) async fn send_rtcp(&mut self, packet: &[u8]) -> Result<()> {
+        let (Some(srtp), Some(target)) = (self.srtp.as_mut(), self.ice.selected_address()) else {
+            return Ok(());
+        };
+        let protected = srtp.outbound.protect_rtcp(packet)?;
+        self.socket.send_to(&protected, target).await?;
+        Ok(())
+    }
+
+    async fn send_all(&self, datagrams: &[Vec<u8>]) -> Result<()> {
+        let Some(target) = self.ice.selected_address() else {
+            return Ok(());
+        };
+        for datagram in datagrams {
+            self.socket.send_to(datagram, target).await?;
+        }
+        Ok(())
+    }
+}
+
+/// 在配置的端口范围内绑定，未配置时使用系统分配端口
+async fn bind_socket(config: &WebRtcConfig) -> Result<UdpSocket> {
+    if config.udp_port_min > 0 && config.udp_port_max >= config.udp_port_min {
+        for port in config.udp_port_min..=config.udp_port_max {
+            if let Ok(socket) = UdpSocket::bind(("0.0.0.0", port)).await {
+                return Ok(socket);
+            }
+        }
+        return Err(WebRtcError::Other(anyhow::anyhow!(
+            "No free UDP port in {}-{}",
+            config.udp_port_min,
+            config.udp_port_max
+        )));
+    }
+    Ok(UdpSocket::bind("0.0.0.0:0").await?)
+}
@@ -0,0 +1,215 @@
+//! WHEP（WebRTC-HTTP Egress Protocol）：POST SDP offer 创建播放会话，DELETE 资源关闭会话
+
+use crate::config::WebRtcConfig;
+use crate::dtls::DtlsCertificate;
+use crate::error::{Result, WebRtcError};
+use crate::sdp::SessionDescription;
+use crate::session::{self, SessionMap, WhepSession};
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, post},
+    Router,
+};
+use flux_media_core::types::StreamId;
+use flux_stream::StreamManager;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+pub(crate) const SDP_CONTENT_TYPE: &str = "application/sdp";
+
+/// WHEP 服务：为 flux-stream 中注册的流创建 WebRTC 播放会话
+pub struct WhepServer {
+    stream_manager: Arc<StreamManager>,
+    config: WebRtcConfig,
+    certificate: DtlsCertificate,
+    sessions: SessionMap,
+}
+
+impl WhepServer {
+    pub fn new(stream_manager: Arc<StreamManager>, config: WebRtcConfig) -> Result<Self> {
+        Ok(Self {
+            stream_manager,
+            config,
+            certificate: DtlsCertificate::generate()?,
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+        })
+    }
+
+    /// 创建会话，返回 (会话, SDP answer)
+    pub async fn create_session(&self, stream_id: &StreamId, offer: &str) -> Result<(Arc<WhepSession>, String)> {
+        if self.session_count().await >= self.config.max_sessions {
+            return Err(WebRtcError::SessionLimit(self.config.max_sessions));
+        }
+
+        let metadata = self
+            .stream_manager
+            .get_metadata(stream_id)
+            .await
+            .ok_or_else(|| WebRtcError::StreamNotFound(stream_id.to_string()))?;
+        let codecs = session::select_codecs(&metadata)?;
+        let offer = SessionDescription::parse(offer)?;
+        let receiver = self
+            .stream_manager
+            .subscribe(stream_id)
+            .await
+            .map_err(|_| WebRtcError::StreamNotFound(stream_id.to_string()))?;
+
+        let (session, answer) = WhepSession::start(
+            stream_id.clone(),
+            codecs,
+            &offer,
+            receiver,
+            &self.certificate,
+            &self.config,
+            self.sessions.clone(),
+        )
+        .await?;
+
+        info!(
+            target: "webrtc",
+            session_id = %session.id,
+            stream_id = %stream_id,
+            local_addr = %session.local_addr,
+            "WHEP session created"
+        );
+        Ok((session, answer))
+    }
+
+    /// 关闭会话
+    pub async fn delete_session(&self, session_id: &str) -> Result<()> {
+        let session = self
+            .sessions
+            .write()
+            .await
+            .remove(session_id)
+            .ok_or_else(|| WebRtcError::SessionNotFound(session_id.to_string()))?;
+        session.close();
+        Ok(())
+    }
+
+    pub async fn get_session(&self, session_id: &str) -> Option<Arc<WhepSession>> {
+        self.sessions.read().await.get(session_id).cloned()
+    }
+
+    pub async fn session_count(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+}
+
+/// WHEP 路由：`POST /whep/:protocol/*identifier`，`DELETE /whep/sessions/:session_id`
+pub fn router<S>(server: Arc<WhepServer>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/whep/sessions/:session_id", delete(delete_session))
+        .route("/whep/:protocol/*identifier", post(create_session))
+        .with_state(server)
+}
+
+async fn create_session(
+    State(server): State<Arc<WhepServer>>,
+    Path((protocol, identifier)): Path<(String, String)>,
+    headers: HeaderMap,
+    offer: String,
+) -> Response {
+    let is_sdp = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(SDP_CONTENT_TYPE));
+    if !is_sdp {
+        return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+    }
+
+    let stream_id = StreamId::new(&protocol, &identifier);
+    match server.create_session(&stream_id, &offer).await {
+        Ok((session, answer)) => (
+            StatusCode::CREATED,
+            [
+                (header::CONTENT_TYPE, SDP_CONTENT_TYPE.to_string()),
+                (header::LOCATION, format!("/whep/sessions/{}", session.id)),
+            ],
+            answer,
+        )
+            .into_response(),
+        Err(e) => {
+            warn!(target: "webrtc", stream_id = %stream_id, "Failed to create WHEP session: {}", e);
+            (error_status(&e), e.to_string()).into_response()
+        }
+    }
+}
+
+async fn delete_session(State(server): State<Arc<WhepServer>>, Path(session_id): Path<String>) -> Response {
+    match server.delete_session(&session_id).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(e) => (error_status(&e), e.to_string()).into_response(),
+    }
+}
+
+pub(crate) fn error_status(error: &WebRtcError) -> StatusCode {
+    match error {
+        WebRtcError::StreamNotFound(_) | WebRtcError::SessionNotFound(_) => StatusCode::NOT_FOUND,
+        WebRtcError::StreamExists(_) => StatusCode::CONFLICT,
+        WebRtcError::InvalidSdp(_) => StatusCode::BAD_REQUEST,
+        WebRtcError::UnsupportedCodec(_) => StatusCode::NOT_ACCEPTABLE,
+        WebRtcError::SessionLimit(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use flux_config::StreamingConfig;
+    use tower::ServiceExt;
+
+    fn server() -> Arc<WhepServer> {
+        let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+        Arc::new(WhepServer::new(stream_manager, WebRtcConfig::default()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_whep_router_errors() {
+        let app: Router = router(server());
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/whep/rtmp/live/cam1")
+                    .header(header::CONTENT_TYPE, "text/plain")
+                    .body(Body::from("v=0"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/whep/rtmp/live/cam1")
+                    .header(header::CONTENT_TYPE, SDP_CONTENT_TYPE)
+                    .body(Body::from("v=0\r\n"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app
+            .oneshot(
+                Request::delete("/whep/sessions/missing")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
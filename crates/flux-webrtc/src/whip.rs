@@ -0,0 +1,542 @@
+//! WHIP（WebRTC-HTTP Ingestion Protocol）：浏览器 / OBS 通过 WebRTC 推流
+//!
+//! 接收 SRTP，H.264 解包为 Annex B 访问单元、Opus / G.711 按帧透传，
+//! 以 `webrtc/{app}/{stream}` 注册到 flux-stream，由各输出协议订阅。
+
+use crate::config::WebRtcConfig;
+use crate::dtls::DtlsCertificate;
+use crate::error::{Result, WebRtcError};
+use crate::ingest::{H264AccessUnitAssembler, TimestampMapper};
+use crate::rtp::RtpPacket;
+use crate::sdp::{build_answer, AnswerMedia, Direction, SessionDescription};
+use crate::session::{choose_audio, choose_h264, EgressAudioCodec, SessionMap, TICK_INTERVAL};
+use crate::transport::{PeerTransport, TransportEvent};
+use crate::whep::{error_status, SDP_CONTENT_TYPE};
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, post},
+    Router,
+};
+use flux_config::StreamMode;
+use flux_media_core::codec::{parse_avc_sps_dimensions, split_annexb};
+use flux_media_core::types::StreamId;
+use flux_stream::{MediaPacket, PacketType, Protocol, Stream, StreamManager, StreamMetadata, StreamStatus};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Notify, RwLock};
+use tracing::{debug, info, warn};
+
+/// 周期性请求关键帧：浏览器编码器默认只在收到 PLI 时输出关键帧，
+/// HLS 切片与新加入的播放者都依赖稳定的 GOP
+const KEYFRAME_REQUEST_INTERVAL: Duration = Duration::from_secs(2);
+
+/// RTCP PSFB / PLI（RFC 4585 6.3.1）
+const RTCP_PT_PSFB: u8 = 206;
+const RTCP_FMT_PLI: u8 = 1;
+
+/// 推流事件通道容量
+const PUBLISH_EVENT_CAPACITY: usize = 64;
+
+/// WHIP 推流注册到 flux-stream 的流
+pub struct WhipStream {
+    stream_id: StreamId,
+    metadata: Arc<RwLock<StreamMetadata>>,
+}
+
+#[async_trait]
+impl Stream for WhipStream {
+    fn stream_id(&self) -> &StreamId {
+        &self.stream_id
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::WebRTC
+    }
+
+    async fn metadata(&self) -> StreamMetadata {
+        self.metadata.read().await.clone()
+    }
+
+    async fn status(&self) -> StreamStatus {
+        StreamStatus::Running
+    }
+
+    async fn start(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// 接收轨道
+struct VideoTrack {
+    payload_type: u8,
+    assembler: H264AccessUnitAssembler,
+    timestamps: TimestampMapper,
+    /// 对端媒体 SSRC（首个 RTP 包确定，用于 PLI）
+    ssrc: Option<u32>,
+}
+
+struct AudioTrack {
+    payload_type: u8,
+    timestamps: TimestampMapper,
+}
+
+/// 与 offer 协商的结果
+struct Negotiated {
+    media: Vec<AnswerMedia>,
+    video: Option<VideoTrack>,
+    audio: Option<(AudioTrack, EgressAudioCodec)>,
+}
+
+/// 推流端：H.264（packetization-mode=1）+ Opus，音频缺少 Opus 时退回 G.711
+fn negotiate(offer: &SessionDescription) -> Result<Negotiated> {
+    let mut negotiated = Negotiated {
+        media: Vec::with_capacity(offer.media.len()),
+        video: None,
+        audio: None,
+    };
+
+    for media in &offer.media {
+        let sends = matches!(media.direction, Direction::SendOnly | Direction::SendRecv);
+        let accepted = match media.kind.as_str() {
+            "video" if sends && negotiated.video.is_none() => choose_h264(&media.formats).inspect(|format| {
+                negotiated.video = Some(VideoTrack {
+                    payload_type: format.payload_type,
+                    assembler: H264AccessUnitAssembler::new(),
+                    timestamps: TimestampMapper::new(format.clock_rate),
+                    ssrc: None,
+                });
+            }),
+            "audio" if sends && negotiated.audio.is_none() => [EgressAudioCodec::Opus, EgressAudioCodec::Pcma, EgressAudioCodec::Pcmu]
+                .into_iter()
+                .find_map(|codec| choose_audio(&media.formats, codec).map(|format| (codec, format)))
+                .map(|(codec, format)| {
+                    negotiated.audio = Some((
+                        AudioTrack {
+                            payload_type: format.payload_type,
+                            timestamps: TimestampMapper::new(format.clock_rate),
+                        },
+                        codec,
+                    ));
+                    format
+                }),
+            _ => None,
+        };
+
+        negotiated.media.push(match accepted {
+            Some(format) => AnswerMedia::Accepted {
+                format: format.clone(),
+                direction: Direction::RecvOnly,
+                ssrc: None,
+            },
+            None => AnswerMedia::Rejected,
+        });
+    }
+
+    if negotiated.video.is_none() && negotiated.audio.is_none() {
+        return Err(WebRtcError::UnsupportedCodec(
+            "Offer has no H.264 or Opus/G.711 track".to_string(),
+        ));
+    }
+    Ok(negotiated)
+}
+
+/// WHIP 推流会话句柄
+pub struct WhipSession {
+    pub id: String,
+    pub stream_id: StreamId,
+    /// 本端 UDP 地址
+    pub local_addr: SocketAddr,
+    shutdown: Arc<Notify>,
+}
+
+impl WhipSession {
+    /// 关闭会话（流从 flux-stream 注销）
+    pub fn close(&self) {
+        self.shutdown.notify_one();
+    }
+}
+
+/// WHIP 服务：把推流注册为 flux-stream 中的 WebRTC 流
+pub struct WhipServer {
+    stream_manager: Arc<StreamManager>,
+    config: WebRtcConfig,
+    certificate: DtlsCertificate,
+    sessions: SessionMap<WhipSession>,
+    published: broadcast::Sender<StreamId>,
+}
+
+impl WhipServer {
+    pub fn new(stream_manager: Arc<StreamManager>, config: WebRtcConfig) -> Result<Self> {
+        let (published, _) = broadcast::channel(PUBLISH_EVENT_CAPACITY);
+        Ok(Self {
+            stream_manager,
+            config,
+            certificate: DtlsCertificate::generate()?,
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            published,
+        })
+    }
+
+    /// 订阅新推流事件（流注册到 flux-stream 后发出）
+    pub fn subscribe_published(&self) -> broadcast::Receiver<StreamId> {
+        self.published.subscribe()
+    }
+
+    /// 创建推流会话，返回 (会话, SDP answer)
+    pub async fn create_session(&self, stream_id: &StreamId, offer: &str) -> Result<(Arc<WhipSession>, String)> {
+        if self.session_count().await >= self.config.max_sessions {
+            return Err(WebRtcError::SessionLimit(self.config.max_sessions));
+        }
+        if self.stream_manager.get_metadata(stream_id).await.is_some() {
+            return Err(WebRtcError::StreamExists(stream_id.to_string()));
+        }
+
+        let offer = SessionDescription::parse(offer)?;
+        let negotiated = negotiate(&offer)?;
+        let transport = PeerTransport::new(&offer, &self.certificate, &self.config).await?;
+        let local_addr = transport.local_addr()?;
+
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let answer_transport = transport.answer_transport(&self.certificate, format!("flux-{}", &id[..8]));
+        let answer = build_answer(&offer, &negotiated.media, &answer_transport);
+
+        let metadata = Arc::new(RwLock::new(StreamMetadata {
+            video_codec: negotiated.video.as_ref().map(|_| "h264".to_string()),
+            audio_codec: negotiated
+                .audio
+                .as_ref()
+                .map(|(_, codec)| codec.encoding_name().to_ascii_lowercase()),
+            audio_sample_rate: negotiated.audio.as_ref().map(|(_, codec)| codec.clock_rate()),
+            audio_channels: negotiated
+                .audio
+                .as_ref()
+                .map(|(_, codec)| if *codec == EgressAudioCodec::Opus { 2 } else { 1 }),
+            ..Default::default()
+        }));
+        self.stream_manager
+            .register_stream(
+                Box::new(WhipStream {
+                    stream_id: stream_id.clone(),
+                    metadata: metadata.clone(),
+                }),
+                StreamMode::Passthrough { remux: true },
+            )
+            .await?;
+
+        let session = Arc::new(WhipSession {
+            id: id.clone(),
+            stream_id: stream_id.clone(),
+            local_addr,
+            shutdown: Arc::new(Notify::new()),
+        });
+
+        let runtime = IngestRuntime {
+            id: id.clone(),
+            stream_id: stream_id.clone(),
+            transport,
+            video: negotiated.video,
+            audio: negotiated.audio.map(|(track, _)| track),
+            metadata,
+            stream_manager: self.stream_manager.clone(),
+            started_at: Instant::now(),
+            last_keyframe_request: None,
+            rtcp_ssrc: rand::random(),
+        };
+
+        self.sessions.write().await.insert(id.clone(), session.clone());
+        let sessions = self.sessions.clone();
+        let stream_manager = self.stream_manager.clone();
+        let shutdown = session.shutdown.clone();
+        let task_stream_id = stream_id.clone();
+        tokio::spawn(async move {
+            runtime.run(shutdown).await;
+            sessions.write().await.remove(&id);
+            if let Err(e) = stream_manager.unregister_stream(&task_stream_id).await {
+                warn!(target: "webrtc", stream_id = %task_stream_id, "Failed to unregister WHIP stream: {}", e);
+            }
+        });
+
+        let _ = self.published.send(stream_id.clone());
+        info!(
+            target: "webrtc",
+            session_id = %session.id,
+            stream_id = %stream_id,
+            local_addr = %local_addr,
+            "WHIP session created"
+        );
+        Ok((session, answer))
+    }
+
+    /// 结束推流
+    pub async fn delete_session(&self, session_id: &str) -> Result<()> {
+        let session = self
+            .sessions
+            .write()
+            .await
+            .remove(session_id)
+            .ok_or_else(|| WebRtcError::SessionNotFound(session_id.to_string()))?;
+        session.close();
+        Ok(())
+    }
+
+    pub async fn get_session(&self, session_id: &str) -> Option<Arc<WhipSession>> {
+        self.sessions.read().await.get(session_id).cloned()
+    }
+
+    pub async fn session_count(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+}
+
+/// 推流会话任务
+struct IngestRuntime {
+    id: String,
+    stream_id: StreamId,
+    transport: PeerTransport,
+    video: Option<VideoTrack>,
+    audio: Option<AudioTrack>,
+    metadata: Arc<RwLock<StreamMetadata>>,
+    stream_manager: Arc<StreamManager>,
+    started_at: Instant,
+    last_keyframe_request: Option<Instant>,
+    /// 本端 RTCP 发送者 SSRC
+    rtcp_ssrc: u32,
+}
+
+impl IngestRuntime {
+    async fn run(mut self, shutdown: Arc<Notify>) {
+        let mut buf = vec![0u8; 1500];
+        let mut tick = tokio::time::interval(TICK_INTERVAL);
+
+        info!(target: "webrtc", session_id = %self.id, stream_id = %self.stream_id, "WHIP session started");
+
+        loop {
+            tokio::select! {
+                _ = shutdown.notified() => break,
+                result = self.transport.recv_from(&mut buf) => {
+                    let event = match result {
+                        Ok((len, from)) => self.transport.handle_datagram(&buf[..len], from).await,
+                        Err(e) => Err(e),
+                    };
+                    match event {
+                        Ok(TransportEvent::Connected) => {
+                            info!(target: "webrtc", session_id = %self.id, "DTLS-SRTP established");
+                        }
+                        Ok(TransportEvent::Rtp(packet)) => self.handle_rtp(&packet).await,
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!(target: "webrtc", session_id = %self.id, "Session failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+                _ = tick.tick() => {
+                    if !self.transport.is_alive() {
+                        info!(target: "webrtc", session_id = %self.id, "WHIP session timed out");
+                        break;
+                    }
+                    self.request_keyframe().await;
+                }
+            }
+        }
+
+        info!(target: "webrtc", session_id = %self.id, stream_id = %self.stream_id, "WHIP session closed");
+    }
+
+    async fn handle_rtp(&mut self, data: &[u8]) {
+        let Ok(packet) = RtpPacket::parse(data) else {
+            return;
+        };
+        let arrival_ms = self.started_at.elapsed().as_millis() as u64;
+
+        if let Some(video) = self.video.as_mut().filter(|video| video.payload_type == packet.payload_type) {
+            if video.ssrc.is_none() {
+                video.ssrc = Some(packet.ssrc);
+            }
+
+            let mut packets = Vec::new();
+            for unit in video.assembler.push(&packet) {
+                packets.push(MediaPacket {
+                    data: unit.data,
+                    timestamp: video.timestamps.map(unit.rtp_timestamp, arrival_ms),
+                    is_keyframe: unit.is_keyframe,
+                    packet_type: PacketType::Video,
+                });
+            }
+            for packet in packets {
+                if packet.is_keyframe {
+                    self.update_dimensions(&packet.data).await;
+                }
+                self.publish(packet).await;
+            }
+            return;
+        }
+
+        if let Some(audio) = self.audio.as_mut().filter(|audio| audio.payload_type == packet.payload_type) {
+            if packet.payload.is_empty() {
+                return;
+            }
+            let timestamp = audio.timestamps.map(packet.timestamp, arrival_ms);
+            self.publish(MediaPacket {
+                data: packet.payload,
+                timestamp,
+                is_keyframe: false,
+                packet_type: PacketType::Audio,
+            })
+            .await;
+        }
+    }
+
+    async fn update_dimensions(&self, access_unit: &[u8]) {
+        let Some(sps) = split_annexb(access_unit)
+            .into_iter()
+            .find(|nalu| nalu.first().is_some_and(|header| header & 0x1F == 7))
+        else {
+            return;
+        };
+        if let Ok((width, height)) = parse_avc_sps_dimensions(sps) {
+            let mut metadata = self.metadata.write().await;
+            metadata.width = Some(width);
+            metadata.height = Some(height);
+        }
+    }
+
+    async fn publish(&self, packet: MediaPacket) {
+        if let Err(e) = self.stream_manager.publish_packet(&self.stream_id, packet).await {
+            debug!(target: "webrtc", stream_id = %self.stream_id, "Failed to publish packet: {}", e);
+        }
+    }
+
+    /// 组装器等待关键帧（启动或丢包后）时发送 PLI，按固定间隔限速
+    async fn request_keyframe(&mut self) {
+        let Some(media_ssrc) = self
+            .video
+            .as_ref()
+            .filter(|video| video.assembler.is_waiting_keyframe())
+            .and_then(|video| video.ssrc)
+        else {
+            return;
+        };
+        if !self.transport.is_connected()
+            || self
+                .last_keyframe_request
+                .is_some_and(|last| last.elapsed() < KEYFRAME_REQUEST_INTERVAL)
+        {
+            return;
+        }
+        self.last_keyframe_request = Some(Instant::now());
+
+        let mut pli = Vec::with_capacity(12);
+        pli.push(0x80 | RTCP_FMT_PLI);
+        pli.push(RTCP_PT_PSFB);
+        pli.extend_from_slice(&2u16.to_be_bytes());
+        pli.extend_from_slice(&self.rtcp_ssrc.to_be_bytes());
+        pli.extend_from_slice(&media_ssrc.to_be_bytes());
+        if let Err(e) = self.transport.send_rtcp(&pli).await {
+            debug!(target: "webrtc", session_id = %self.id, "Failed to send PLI: {}", e);
+        }
+    }
+}
+
+/// WHIP 路由：`POST /whip/:app/:stream`，`DELETE /whip/sessions/:session_id`
+pub fn router<S>(server: Arc<WhipServer>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/whip/sessions/:session_id", delete(delete_session))
+        .route("/whip/:app/:stream", post(create_session))
+        .with_state(server)
+}
+
+async fn create_session(
+    State(server): State<Arc<WhipServer>>,
+    Path((app, stream)): Path<(String, String)>,
+    headers: HeaderMap,
+    offer: String,
+) -> Response {
+    let is_sdp = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(SDP_CONTENT_TYPE));
+    if !is_sdp {
+        return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+    }
+
+    let stream_id = StreamId::new("webrtc", &format!("{}/{}", app, stream));
+    match server.create_session(&stream_id, &offer).await {
+        Ok((session, answer)) => (
+            StatusCode::CREATED,
+            [
+                (header::CONTENT_TYPE, SDP_CONTENT_TYPE.to_string()),
+                (header::LOCATION, format!("/whip/sessions/{}", session.id)),
+            ],
+            answer,
+        )
+            .into_response(),
+        Err(e) => {
+            warn!(target: "webrtc", stream_id = %stream_id, "Failed to create WHIP session: {}", e);
+            (error_status(&e), e.to_string()).into_response()
+        }
+    }
+}
+
+async fn delete_session(State(server): State<Arc<WhipServer>>, Path(session_id): Path<String>) -> Response {
+    match server.delete_session(&session_id).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(e) => (error_status(&e), e.to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_obs_offer() {
+        // OBS / 浏览器推流 offer：VP8 优先，H.264 与 Opus 可用
+        let offer = SessionDescription::parse(
+            "v=0\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111 0\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtpmap:111 opus/48000/2\r\n\
+a=rtpmap:0 PCMU/8000\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96 102\r\n\
+a=mid:1\r\n\
+a=sendonly\r\n\
+a=rtpmap:96 VP8/90000\r\n\
+a=rtpmap:102 H264/90000\r\n\
+a=fmtp:102 packetization-mode=1;profile-level-id=42e01f\r\n",
+        )
+        .unwrap();
+
+        let negotiated = negotiate(&offer).unwrap();
+        assert!(matches!(
+            &negotiated.media[0],
+            AnswerMedia::Accepted { format, direction: Direction::RecvOnly, ssrc: None } if format.payload_type == 111
+        ));
+        assert_eq!(negotiated.video.as_ref().unwrap().payload_type, 102);
+        assert_eq!(negotiated.audio.as_ref().unwrap().1, EgressAudioCodec::Opus);
+
+        // 只接收的 offer 不能用于推流
+        let offer = SessionDescription::parse(
+            "v=0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 102\r\n\
+a=recvonly\r\n\
+a=rtpmap:102 H264/90000\r\n\
+a=fmtp:102 packetization-mode=1\r\n",
+        )
+        .unwrap();
+        assert!(matches!(negotiate(&offer), Err(WebRtcError::UnsupportedCodec(_))));
+    }
+}
@@ -0,0 +1,228 @@
+//! 回环 WebRTC 客户端：完成 ICE / DTLS-SRTP 握手，解密并校验服务端推送的 RTP
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use flux_config::{StreamMode, StreamingConfig};
+use flux_media_core::types::StreamId;
+use flux_stream::{MediaPacket, PacketType, Protocol, Stream, StreamManager, StreamMetadata, StreamStatus};
+use flux_webrtc::dtls::{is_dtls, DtlsCertificate, DtlsRole, DtlsTransport};
+use flux_webrtc::rtp::RtpPacket;
+use flux_webrtc::sdp::SessionDescription;
+use flux_webrtc::srtp::SrtpContext;
+use flux_webrtc::stun::{self, is_stun, StunMessage};
+use flux_webrtc::{WebRtcConfig, WhepServer};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+struct MockStream {
+    stream_id: StreamId,
+}
+
+#[async_trait]
+impl Stream for MockStream {
+    fn stream_id(&self) -> &StreamId {
+        &self.stream_id
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::RTMP
+    }
+
+    async fn metadata(&self) -> StreamMetadata {
+        StreamMetadata {
+            video_codec: Some("h264".to_string()),
+            audio_codec: Some("pcmu".to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn status(&self) -> StreamStatus {
+        StreamStatus::Running
+    }
+
+    async fn start(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn client_offer(fingerprint: &str) -> String {
+    format!(
+        "v=0\r\n\
+o=- 1 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0 1\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 102\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=ice-ufrag:clnt\r\n\
+a=ice-pwd:client-password-0123456789\r\n\
+a=fingerprint:sha-256 {fingerprint}\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=recvonly\r\n\
+a=rtcp-mux\r\n\
+a=rtpmap:102 H264/90000\r\n\
+a=fmtp:102 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111 0\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:1\r\n\
+a=recvonly\r\n\
+a=rtcp-mux\r\n\
+a=rtpmap:111 opus/48000/2\r\n\
+a=rtpmap:0 PCMU/8000\r\n"
+    )
+}
+
+fn candidate_address(answer: &str) -> SocketAddr {
+    let line = answer
+        .lines()
+        .find_map(|line| line.strip_prefix("a=candidate:"))
+        .expect("answer has candidate");
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    format!("{}:{}", fields[4], fields[5]).parse().unwrap()
+}
+
+async fn recv(socket: &UdpSocket) -> Vec<u8> {
+    let mut buf = vec![0u8; 2048];
+    let (len, _) = timeout(Duration::from_secs(5), socket.recv_from(&mut buf))
+        .await
+        .expect("datagram before timeout")
+        .unwrap();
+    buf.truncate(len);
+    buf
+}
+
+#[tokio::test]
+async fn test_whep_loopback_session() {
+    let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+    let stream_id = StreamId::new("rtmp", "live/cam1");
+    stream_manager
+        .register_stream(
+            Box::new(MockStream {
+                stream_id: stream_id.clone(),
+            }),
+            StreamMode::Passthrough { remux: true },
+        )
+        .await
+        .unwrap();
+
+    let config = WebRtcConfig {
+        public_ips: vec!["127.0.0.1".to_string()],
+        ..Default::default()
+    };
+    let server = WhepServer::new(stream_manager.clone(), config).unwrap();
+
+    // 1. SDP offer / answer
+    let client_cert = DtlsCertificate::generate().unwrap();
+    let (session, raw_answer) = server
+        .create_session(&stream_id, &client_offer(client_cert.fingerprint()))
+        .await
+        .unwrap();
+    let answer = SessionDescription::parse(&raw_answer).unwrap();
+    assert_eq!(answer.setup(), Some("passive"));
+    let (server_ufrag, server_pwd) = answer.ice_credentials().unwrap();
+    let (_, server_fingerprint) = answer.fingerprint().unwrap();
+    let video_pt = answer.media[0].formats[0].payload_type;
+    let video_ssrc = answer.media[0].ssrcs[0];
+    assert_eq!(video_pt, 102);
+    assert_eq!(answer.media[1].formats[0].encoding, "PCMU");
+    let target = candidate_address(&raw_answer);
+    assert_eq!(server.session_count().await, 1);
+
+    // 2. ICE：控制方提名
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let request = stun::binding_request(
+        &[1u8; 12],
+        &format!("{}:clnt", server_ufrag),
+        &server_pwd,
+        1_845_501_695,
+        42,
+        true,
+    )
+    .unwrap();
+    socket.send_to(&request, target).await.unwrap();
+    let raw_response = recv(&socket).await;
+    assert!(is_stun(&raw_response));
+    let response = StunMessage::decode(&raw_response).unwrap();
+    assert_eq!(response.message_type, stun::BINDING_SUCCESS);
+    assert!(response.verify_integrity(&raw_response, &server_pwd));
+    assert_eq!(response.xor_mapped_address(), Some(socket.local_addr().unwrap()));
+
+    // 3. DTLS：客户端发起握手
+    let mut dtls = DtlsTransport::new(&client_cert, DtlsRole::Client).unwrap();
+    for datagram in dtls.start().unwrap() {
+        socket.send_to(&datagram, target).await.unwrap();
+    }
+    while !dtls.is_connected() {
+        let datagram = recv(&socket).await;
+        assert!(is_dtls(&datagram));
+        for reply in dtls.handle_datagram(&datagram).unwrap() {
+            socket.send_to(&reply, target).await.unwrap();
+        }
+    }
+    assert_eq!(dtls.remote_fingerprint().as_deref(), Some(server_fingerprint.as_str()));
+    let keys = dtls.srtp_keys().unwrap();
+    let mut srtp = SrtpContext::new(&keys.remote_key, &keys.remote_salt).unwrap();
+
+    // 服务端在最后一个握手记录发出后建立 SRTP，给会话任务一点时间
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // 4. 发布媒体：非关键帧被丢弃，关键帧分片为 FU-A
+    let mut idr = vec![0, 0, 0, 1, 0x67, 0x42, 0xE0, 0x1F, 0, 0, 0, 1, 0x68, 0xCE, 0x3C, 0x80, 0, 0, 0, 1, 0x65];
+    idr.extend(std::iter::repeat_n(0xAB, 3000));
+    let publish = |data: Vec<u8>, timestamp: u32, is_keyframe: bool, packet_type: PacketType| {
+        let stream_manager = stream_manager.clone();
+        let stream_id = stream_id.clone();
+        async move {
+            stream_manager
+                .publish_packet(
+                    &stream_id,
+                    MediaPacket {
+                        data: Bytes::from(data),
+                        timestamp,
+                        is_keyframe,
+                        packet_type,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+    };
+    publish(vec![0, 0, 0, 1, 0x41, 0x9A], 0, false, PacketType::Video).await;
+    publish(idr, 40, true, PacketType::Video).await;
+    publish(vec![0xFF; 160], 40, false, PacketType::Audio).await;
+
+    let mut video_packets = Vec::new();
+    let mut audio_packets = Vec::new();
+    while audio_packets.is_empty() {
+        let datagram = recv(&socket).await;
+        let packet = RtpPacket::parse(&srtp.unprotect_rtp(&datagram).unwrap()).unwrap();
+        if packet.ssrc == video_ssrc {
+            video_packets.push(packet);
+        } else {
+            audio_packets.push(packet);
+        }
+    }
+
+    // SPS、PPS 单 NALU，IDR 至少三个 FU-A 分片
+    assert!(video_packets.len() >= 5);
+    assert_eq!(video_packets[0].payload[0] & 0x1F, 7);
+    assert_eq!(video_packets[2].payload[0] & 0x1F, 28);
+    assert!(video_packets.iter().all(|p| p.payload_type == video_pt && p.timestamp == 3600));
+    assert!(video_packets.last().unwrap().marker);
+    assert_eq!(audio_packets[0].payload_type, 0);
+    assert_eq!(audio_packets[0].timestamp, 320);
+    assert_eq!(audio_packets[0].payload.len(), 160);
+
+    // 5. 关闭会话
+    server.delete_session(&session.id).await.unwrap();
+    assert!(server.get_session(&session.id).await.is_none());
+    assert!(server.delete_session(&session.id).await.is_err());
+}
@@ -0,0 +1,181 @@
+//! 回环 WHIP 推流：客户端完成 ICE / DTLS-SRTP 握手后发送 H.264 + Opus，
+//! 校验 flux-stream 订阅者收到的访问单元与服务端发出的 PLI
+
+use flux_config::StreamingConfig;
+use flux_media_core::types::StreamId;
+use flux_stream::{PacketType, StreamManager};
+use flux_webrtc::dtls::{DtlsCertificate, DtlsRole, DtlsTransport};
+use flux_webrtc::sdp::SessionDescription;
+use flux_webrtc::srtp::SrtpContext;
+use flux_webrtc::stun;
+use flux_webrtc::{RtpPacketizer, WebRtcConfig, WebRtcError, WhipServer};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+fn client_offer(fingerprint: &str) -> String {
+    format!(
+        "v=0\r\n\
+o=- 1 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0 1\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 102\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=ice-ufrag:obsc\r\n\
+a=ice-pwd:client-password-0123456789\r\n\
+a=fingerprint:sha-256 {fingerprint}\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtcp-mux\r\n\
+a=rtpmap:102 H264/90000\r\n\
+a=fmtp:102 packetization-mode=1;profile-level-id=42e01f\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:1\r\n\
+a=sendonly\r\n\
+a=rtcp-mux\r\n\
+a=rtpmap:111 opus/48000/2\r\n"
+    )
+}
+
+fn candidate_address(answer: &str) -> SocketAddr {
+    let line = answer
+        .lines()
+        .find_map(|line| line.strip_prefix("a=candidate:"))
+        .expect("answer has candidate");
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    format!("{}:{}", fields[4], fields[5]).parse().unwrap()
+}
+
+async fn recv(socket: &UdpSocket) -> Vec<u8> {
+    let mut buf = vec![0u8; 2048];
+    let (len, _) = timeout(Duration::from_secs(5), socket.recv_from(&mut buf))
+        .await
+        .expect("datagram before timeout")
+        .unwrap();
+    buf.truncate(len);
+    buf
+}
+
+#[tokio::test]
+async fn test_whip_loopback_publish() {
+    let stream_manager = Arc::new(StreamManager::new(StreamingConfig::default()));
+    let config = WebRtcConfig {
+        public_ips: vec!["127.0.0.1".to_string()],
+        ..Default::default()
+    };
+    let server = WhipServer::new(stream_manager.clone(), config).unwrap();
+    let mut published = server.subscribe_published();
+    let stream_id = StreamId::new("webrtc", "live/field1");
+
+    // 1. SDP：流立即注册到 flux-stream
+    let client_cert = DtlsCertificate::generate().unwrap();
+    let offer = client_offer(client_cert.fingerprint());
+    let (session, raw_answer) = server.create_session(&stream_id, &offer).await.unwrap();
+    assert_eq!(published.recv().await.unwrap(), stream_id);
+    let metadata = stream_manager.get_metadata(&stream_id).await.unwrap();
+    assert_eq!(metadata.video_codec.as_deref(), Some("h264"));
+    assert_eq!(metadata.audio_codec.as_deref(), Some("opus"));
+    assert!(raw_answer.contains("a=recvonly\r\n"));
+
+    // 同名流不能重复推
+    assert!(matches!(
+        server.create_session(&stream_id, &offer).await,
+        Err(WebRtcError::StreamExists(_))
+    ));
+
+    let answer = SessionDescription::parse(&raw_answer).unwrap();
+    let (server_ufrag, server_pwd) = answer.ice_credentials().unwrap();
+    let target = candidate_address(&raw_answer);
+    let mut subscriber = stream_manager.subscribe(&stream_id).await.unwrap();
+
+    // 2. ICE + DTLS
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let request = stun::binding_request(
+        &[2u8; 12],
+        &format!("{}:obsc", server_ufrag),
+        &server_pwd,
+        1_845_501_695,
+        7,
+        true,
+    )
+    .unwrap();
+    socket.send_to(&request, target).await.unwrap();
+    assert!(stun::is_stun(&recv(&socket).await));
+
+    let mut dtls = DtlsTransport::new(&client_cert, DtlsRole::Client).unwrap();
+    for datagram in dtls.start().unwrap() {
+        socket.send_to(&datagram, target).await.unwrap();
+    }
+    while !dtls.is_connected() {
+        let datagram = recv(&socket).await;
+        for reply in dtls.handle_datagram(&datagram).unwrap() {
+            socket.send_to(&reply, target).await.unwrap();
+        }
+    }
+    let keys = dtls.srtp_keys().unwrap();
+    let mut outbound = SrtpContext::new(&keys.local_key, &keys.local_salt).unwrap();
+    let mut inbound = SrtpContext::new(&keys.remote_key, &keys.remote_salt).unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // 3. 发送关键帧（FU-A 分片）、P 帧与 Opus 帧
+    let mut video = RtpPacketizer::new(102, 0x1111_0000, 90000);
+    let mut audio = RtpPacketizer::new(111, 0x2222_0000, 48000);
+    let mut idr = vec![0, 0, 0, 1, 0x67, 0x64, 0x00, 0x1F, 0xAC, 0xD9, 0x40, 0x50, 0x05, 0xB9];
+    idr.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xCE, 0x3C, 0x80, 0, 0, 0, 1, 0x65]);
+    idr.extend(std::iter::repeat_n(0x5A, 4000));
+
+    let mut rtp_packets = video.packetize_h264(&idr, 0);
+    rtp_packets.push(audio.packetize_frame(&[0xFC, 0x01, 0x02], 0));
+    rtp_packets.extend(video.packetize_h264(&[0, 0, 0, 1, 0x41, 0x9A, 0x01], 40));
+    for packet in rtp_packets {
+        let protected = outbound.protect_rtp(&packet.to_bytes()).unwrap();
+        socket.send_to(&protected, target).await.unwrap();
+    }
+
+    let mut received = Vec::new();
+    while received.len() < 3 {
+        let packet = timeout(Duration::from_secs(5), subscriber.recv())
+            .await
+            .expect("media packet before timeout")
+            .unwrap();
+        received.push(packet);
+    }
+
+    let keyframe = &received[0];
+    assert_eq!(keyframe.packet_type, PacketType::Video);
+    assert!(keyframe.is_keyframe);
+    assert_eq!(keyframe.data.as_ref(), idr.as_slice());
+    assert_eq!(received[1].packet_type, PacketType::Audio);
+    assert_eq!(received[1].data.as_ref(), &[0xFC, 0x01, 0x02]);
+    assert!(!received[2].is_keyframe);
+    assert_eq!(received[2].timestamp - keyframe.timestamp, 40);
+
+    let metadata = stream_manager.get_metadata(&stream_id).await.unwrap();
+    assert_eq!((metadata.width, metadata.height), (Some(1280), Some(720)));
+
+    // 4. 丢包后服务端发送 PLI 请求关键帧
+    let _lost = video.packetize_h264(&[0, 0, 0, 1, 0x41, 0x9A, 0x02], 80);
+    for packet in video.packetize_h264(&[0, 0, 0, 1, 0x41, 0x9A, 0x03], 120) {
+        let protected = outbound.protect_rtp(&packet.to_bytes()).unwrap();
+        socket.send_to(&protected, target).await.unwrap();
+    }
+    let rtcp = inbound.unprotect_rtcp(&recv(&socket).await).unwrap();
+    assert_eq!(rtcp[0] & 0x1F, 1);
+    assert_eq!(rtcp[1], 206);
+    assert_eq!(&rtcp[8..12], &0x1111_0000u32.to_be_bytes());
+
+    // 5. 结束推流后流被注销
+    server.delete_session(&session.id).await.unwrap();
+    timeout(Duration::from_secs(2), async {
+        while stream_manager.get_metadata(&stream_id).await.is_some() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("stream unregistered");
+}
@@ -1,4 +1,4 @@
-use flux_plugin_sdk::{export_plugin_alloc, read_string_from_host, trace, debug, info, warn, error};
+use flux_plugin_sdk::{export_plugin_alloc, on_message, read_string_from_host, trace, debug, info, warn, error, Message, PluginResult};
 
 export_plugin_alloc!();
 
@@ -47,3 +47,37 @@ pub extern "C" fn on_msg(ptr: i32, len: i32) -> i32 {
     
     input.len() as i32
 }
+
+/// ABI v1 示例：为消息补充处理标记后返回（在处理链中配置 `function = "enrich"`）
+#[on_message]
+fn enrich(msg: Message) -> PluginResult<Message> {
+    if !msg.payload.is_object() {
+        return Err("payload must be a JSON object".into());
+    }
+    let mut payload = msg.payload.clone();
+    payload["processed_by"] = serde_json::json!("dummy_plugin");
+    debug!("Enriched message on topic {}", msg.topic);
+    Ok(msg.derive(msg.topic.clone(), payload))
+}
+
+/// Host 能力示例：按主题计数，每满 100 条发布一次汇总（清单需声明 `kv`、`publish`、`time`）
+#[on_message]
+fn count(msg: Message) -> PluginResult<Option<Message>> {
+    let key = format!("count/{}", msg.topic);
+    let count = flux_plugin_sdk::host::kv_get(&key)?
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|text| text.parse::<u64>().ok())
+        .unwrap_or(0)
+        + 1;
+    flux_plugin_sdk::host::kv_set(&key, count.to_string().as_bytes())?;
+
+    if count % 100 == 0 {
+        let summary = serde_json::json!({
+            "topic": msg.topic,
+            "count": count,
+            "at": flux_plugin_sdk::host::now_ms()?,
+        });
+        flux_plugin_sdk::host::publish("stats/dummy_plugin", &summary)?;
+    }
+    Ok(None)
+}
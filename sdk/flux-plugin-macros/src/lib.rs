@@ -0,0 +1,46 @@
+//! Wasm 插件入口函数属性宏
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, ItemFn};
+
+/// 将 `fn(T) -> PluginResult<R>` 导出为 ABI v1 入口函数
+///
+/// 输入 `T` 由 Host 传入的 JSON 解码，返回值 `R` 编码为 JSON 交回 Host，导出名与函数名相同。
+///
+/// ```ignore
+/// #[flux_plugin_sdk::on_message]
+/// fn on_msg(msg: Message) -> PluginResult<Vec<Message>> {
+///     Ok(vec![msg])
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn on_message(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(proc_macro2::Span::call_site(), "on_message takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let func = parse_macro_input!(item as ItemFn);
+    if func.sig.inputs.len() != 1 {
+        return syn::Error::new_spanned(&func.sig, "on_message handler must take exactly one argument")
+            .to_compile_error()
+            .into();
+    }
+
+    let name = func.sig.ident.clone();
+    let mut handler = func;
+    handler.sig.ident = format_ident!("__flux_handler_{}", name);
+    handler.vis = syn::Visibility::Inherited;
+    let handler_name = &handler.sig.ident;
+
+    quote! {
+        #[no_mangle]
+        pub extern "C" fn #name(ptr: i32, len: i32) -> i64 {
+            #handler
+            unsafe { ::flux_plugin_sdk::abi::handle(ptr, len, #handler_name) }
+        }
+    }
+    .into()
+}
@@ -0,0 +1,132 @@
+//! 插件 ABI v1：入口函数的输入解码与结果编码
+//!
+//! 通常通过 [`on_message`](crate::on_message) 属性宏使用，无需直接调用。
+
+use flux_types::plugin::{pack_ptr_len, PluginResponse};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+
+pub use flux_types::plugin::PLUGIN_ABI_VERSION;
+
+/// 插件处理错误，作为错误响应返回给 Host
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginError(pub String);
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for PluginError {
+    fn from(message: String) -> Self {
+        Self(message)
+    }
+}
+
+impl From<&str> for PluginError {
+    fn from(message: &str) -> Self {
+        Self(message.to_string())
+    }
+}
+
+impl From<serde_json::Error> for PluginError {
+    fn from(e: serde_json::Error) -> Self {
+        Self(e.to_string())
+    }
+}
+
+pub type PluginResult<T> = Result<T, PluginError>;
+
+/// 解码输入、执行处理函数并编码为 JSON 响应
+pub fn handle_bytes<I, O, F>(input: &[u8], handler: F) -> Vec<u8>
+where
+    I: DeserializeOwned,
+    O: Serialize,
+    F: FnOnce(I) -> PluginResult<O>,
+{
+    let response = match serde_json::from_slice::<I>(input) {
+        Ok(input) => match handler(input).and_then(|output| Ok(serde_json::to_value(output)?)) {
+            Ok(data) => PluginResponse::Ok { data: Some(data) },
+            Err(e) => PluginResponse::Error { message: e.0 },
+        },
+        Err(e) => PluginResponse::Error {
+            message: format!("Invalid input: {}", e),
+        },
+    };
+    serde_json::to_vec(&response).unwrap_or_default()
+}
+
+/// 入口函数包装：读取 Host 写入的输入，返回打包的结果缓冲区指针与长度
+///
+/// # Safety
+///
+/// `ptr` 与 `len` 必须是 Host 通过 `alloc` 分配并写入的输入区域。
+pub unsafe fn handle<I, O, F>(ptr: i32, len: i32, handler: F) -> i64
+where
+    I: DeserializeOwned,
+    O: Serialize,
+    F: FnOnce(I) -> PluginResult<O>,
+{
+    let input = std::slice::from_raw_parts(ptr as *const u8, len as usize);
+    into_host_buffer(handle_bytes(input, handler))
+}
+
+/// 将结果交给 Host，Host 读取后通过 `dealloc(ptr, len)` 释放
+pub fn into_host_buffer(bytes: Vec<u8>) -> i64 {
+    // 转为 Box<[u8]> 保证容量等于长度，与 `dealloc` 的释放方式一致
+    let mut bytes = bytes.into_boxed_slice();
+    let (ptr, len) = (bytes.as_mut_ptr(), bytes.len());
+    std::mem::forget(bytes);
+    pack_ptr_len(ptr as usize as u32, len as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flux_types::message::Message;
+
+    fn split(msg: Message) -> PluginResult<Vec<Message>> {
+        let values = msg.payload["values"].as_array().ok_or("values must be an array")?;
+        Ok(values
+            .iter()
+            .map(|value| msg.derive(format!("{}/item", msg.topic), value.clone()))
+            .collect())
+    }
+
+    // 导出函数的指针参数只在 wasm32 上有效，这里只验证宏展开可以编译
+    #[crate::on_message]
+    fn on_split(msg: Message) -> PluginResult<Vec<Message>> {
+        split(msg)
+    }
+
+    fn response(bytes: Vec<u8>) -> PluginResponse {
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_handle_bytes() {
+        let input = Message::new("batch".to_string(), serde_json::json!({ "values": [1, 2] }));
+        let bytes = serde_json::to_vec(&input).unwrap();
+        let PluginResponse::Ok { data: Some(data) } = response(handle_bytes(&bytes, split)) else {
+            panic!("expected data");
+        };
+        assert_eq!(data.as_array().unwrap().len(), 2);
+        assert_eq!(data[1]["topic"], "batch/item");
+        assert_eq!(data[1]["payload"], 2);
+
+        let invalid = Message::new("batch".to_string(), serde_json::json!({}));
+        let bytes = serde_json::to_vec(&invalid).unwrap();
+        assert_eq!(
+            response(handle_bytes(&bytes, split)),
+            PluginResponse::Error {
+                message: "values must be an array".to_string()
+            }
+        );
+        assert!(matches!(
+            response(handle_bytes(b"not json", split)),
+            PluginResponse::Error { .. }
+        ));
+    }
+}
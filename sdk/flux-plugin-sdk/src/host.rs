@@ -0,0 +1,155 @@
+//! Host 函数封装（KV 状态、发布消息、设备元数据、时间、HTTP）
+//!
+//! 每个能力都需在插件清单中声明，未声明时返回 [`HostError::Denied`]。
+
+use crate::abi::PluginError;
+use flux_types::plugin::{
+    unpack_ptr_len, HOST_ERR_DENIED, HOST_ERR_FAILED, HOST_ERR_INVALID, HOST_ERR_LIMIT,
+};
+use serde::Serialize;
+use std::fmt;
+
+pub use flux_types::plugin::{HttpRequest, HttpResponse};
+
+// Host 提供的能力函数声明
+extern "C" {
+    fn flux_time_now_ms() -> i64;
+    fn flux_kv_get(key_ptr: *const u8, key_len: usize) -> i64;
+    fn flux_kv_set(key_ptr: *const u8, key_len: usize, value_ptr: *const u8, value_len: usize) -> i32;
+    fn flux_kv_delete(key_ptr: *const u8, key_len: usize) -> i32;
+    fn flux_publish(topic_ptr: *const u8, topic_len: usize, payload_ptr: *const u8, payload_len: usize) -> i32;
+    fn flux_device_get(id_ptr: *const u8, id_len: usize) -> i64;
+    fn flux_http_request(req_ptr: *const u8, req_len: usize) -> i64;
+}
+
+/// Host 函数错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostError {
+    /// 插件未声明对应能力（或 URL 不在允许列表）
+    Denied,
+    /// 参数无效
+    Invalid,
+    /// Host 执行失败
+    Failed,
+    /// 超出大小或数量限制
+    Limit,
+    /// 未知错误码
+    Unknown(i32),
+}
+
+impl HostError {
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            HOST_ERR_DENIED => Self::Denied,
+            HOST_ERR_INVALID => Self::Invalid,
+            HOST_ERR_FAILED => Self::Failed,
+            HOST_ERR_LIMIT => Self::Limit,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for HostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Denied => f.write_str("host capability denied"),
+            Self::Invalid => f.write_str("invalid host call arguments"),
+            Self::Failed => f.write_str("host call failed"),
+            Self::Limit => f.write_str("host call limit exceeded"),
+            Self::Unknown(code) => write!(f, "host call returned error code {}", code),
+        }
+    }
+}
+
+impl From<HostError> for PluginError {
+    fn from(e: HostError) -> Self {
+        Self(e.to_string())
+    }
+}
+
+pub type HostResult<T> = Result<T, HostError>;
+
+fn status(code: i32) -> HostResult<()> {
+    if code < 0 {
+        Err(HostError::from_code(code))
+    } else {
+        Ok(())
+    }
+}
+
+/// 取回 Host 通过 `alloc` 分配的结果缓冲区
+fn take_buffer(packed: i64) -> HostResult<Option<Vec<u8>>> {
+    if packed < 0 {
+        return Err(HostError::from_code(packed as i32));
+    }
+    let (ptr, len) = unpack_ptr_len(packed);
+    if len == 0 {
+        return Ok(None);
+    }
+    // SAFETY: 缓冲区由本插件的 `alloc(len)` 分配（容量等于长度），所有权交给插件
+    let bytes = unsafe { Vec::from_raw_parts(ptr as usize as *mut u8, len as usize, len as usize) };
+    Ok(Some(bytes))
+}
+
+/// 当前时间（Unix 毫秒，需 `time` 能力）
+pub fn now_ms() -> HostResult<i64> {
+    let now = unsafe { flux_time_now_ms() };
+    if now < 0 {
+        return Err(HostError::from_code(now as i32));
+    }
+    Ok(now)
+}
+
+/// 读取插件状态（需 `kv` 能力）
+pub fn kv_get(key: &str) -> HostResult<Option<Vec<u8>>> {
+    take_buffer(unsafe { flux_kv_get(key.as_ptr(), key.len()) })
+}
+
+/// 写入插件状态（需 `kv` 能力）
+pub fn kv_set(key: &str, value: &[u8]) -> HostResult<()> {
+    status(unsafe { flux_kv_set(key.as_ptr(), key.len(), value.as_ptr(), value.len()) })
+}
+
+/// 删除插件状态（需 `kv` 能力）
+pub fn kv_delete(key: &str) -> HostResult<()> {
+    status(unsafe { flux_kv_delete(key.as_ptr(), key.len()) })
+}
+
+/// 向事件总线发布消息（需 `publish` 能力）
+pub fn publish<T: Serialize>(topic: &str, payload: &T) -> HostResult<()> {
+    let payload = serde_json::to_vec(payload).map_err(|_| HostError::Invalid)?;
+    status(unsafe { flux_publish(topic.as_ptr(), topic.len(), payload.as_ptr(), payload.len()) })
+}
+
+/// 读取设备元数据（需 `device_read` 能力），设备不存在时返回 `None`
+pub fn device(device_id: &str) -> HostResult<Option<serde_json::Value>> {
+    match take_buffer(unsafe { flux_device_get(device_id.as_ptr(), device_id.len()) })? {
+        Some(bytes) => serde_json::from_slice(&bytes).map(Some).map_err(|_| HostError::Failed),
+        None => Ok(None),
+    }
+}
+
+/// 发起 HTTP 请求（需 `http` 能力，且 URL 在清单的允许列表内）
+pub fn http_request(request: &HttpRequest) -> HostResult<HttpResponse> {
+    let request = serde_json::to_vec(request).map_err(|_| HostError::Invalid)?;
+    let bytes = take_buffer(unsafe { flux_http_request(request.as_ptr(), request.len()) })?
+        .ok_or(HostError::Failed)?;
+    serde_json::from_slice(&bytes).map_err(|_| HostError::Failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_error_codes() {
+        assert_eq!(status(0), Ok(()));
+        assert_eq!(status(HOST_ERR_DENIED), Err(HostError::Denied));
+        assert_eq!(take_buffer(HOST_ERR_LIMIT as i64), Err(HostError::Limit));
+        assert_eq!(take_buffer(0), Ok(None));
+        assert_eq!(HostError::from_code(-42), HostError::Unknown(-42));
+
+        let err: PluginError = HostError::Denied.into();
+        assert_eq!(err.to_string(), "host capability denied");
+    }
+}
@@ -1,7 +1,17 @@
+// 属性宏展开后通过 `::flux_plugin_sdk` 引用本 crate
+extern crate self as flux_plugin_sdk;
+
 pub use flux_types;
 
+pub mod abi;
+pub mod host;
 pub mod logging;
 pub mod macros;
 
 // 重新导出 macros 中的内容
 pub use macros::*;
+
+pub use abi::{PluginError, PluginResult};
+pub use host::{HostError, HostResult};
+pub use flux_plugin_macros::on_message;
+pub use flux_types::message::Message;
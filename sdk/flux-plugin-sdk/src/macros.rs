@@ -1,8 +1,14 @@
 /// Macro to export the `alloc` and `dealloc` functions.
 /// This is REQUIRED for the Host to write data into the Guest's linear memory.
+/// Also exports `flux_abi_version` so the Host can check ABI compatibility.
 #[macro_export]
 macro_rules! export_plugin_alloc {
     () => {
+        #[no_mangle]
+        pub extern "C" fn flux_abi_version() -> i32 {
+            $crate::abi::PLUGIN_ABI_VERSION
+        }
+
         #[no_mangle]
         pub extern "C" fn alloc(len: usize) -> *mut u8 {
             let mut buf = Vec::with_capacity(len);